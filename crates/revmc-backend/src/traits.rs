@@ -128,6 +128,10 @@ pub enum Attribute {
     AlwaysInline,
     NoInline,
     Speculatable,
+    /// Optimize for size, keeping optimizations that don't increase code size, like `-Os`.
+    OptimizeForSize,
+    /// Optimize for size aggressively, even at the cost of performance, like `-Oz`.
+    MinSize,
 
     // Parameter attributes.
     NoAlias,
@@ -210,6 +214,18 @@ pub trait Backend: BackendTypes + TypeMethods {
     fn dump_disasm(&mut self, path: &Path) -> Result<()>;
 
     fn is_aot(&self) -> bool;
+    /// Returns the target machine.
+    ///
+    /// Defaults to [`Target::Native`].
+    fn target(&self) -> &Target {
+        &Target::Native
+    }
+    /// Returns `true` if the target is little-endian.
+    ///
+    /// Defaults to the endianness of the host.
+    fn is_little_endian(&self) -> bool {
+        cfg!(target_endian = "little")
+    }
 
     fn function_name_is_unique(&self, name: &str) -> bool;
 
@@ -221,10 +237,24 @@ pub trait Backend: BackendTypes + TypeMethods {
         param_names: &[&str],
         linkage: Linkage,
     ) -> Result<(Self::Builder<'_>, Self::FuncId)>;
+    /// Declares a function without building its body.
+    ///
+    /// Defaults to building a function and dropping its builder.
+    fn declare_function(
+        &mut self,
+        name: &str,
+        ret: Option<Self::Type>,
+        params: &[Self::Type],
+        linkage: Linkage,
+    ) -> Result<Self::FuncId> {
+        self.build_function(name, ret, params, &[], linkage).map(|(_, id)| id)
+    }
     fn verify_module(&mut self) -> Result<()>;
     fn optimize_module(&mut self) -> Result<()>;
     fn write_object<W: std::io::Write>(&mut self, w: W) -> Result<()>;
     fn jit_function(&mut self, id: Self::FuncId) -> Result<usize>;
+    /// Returns the size of the machine code of a JIT-compiled function, in bytes, if known.
+    fn function_size(&self, id: Self::FuncId) -> Option<usize>;
     unsafe fn free_function(&mut self, id: Self::FuncId) -> Result<()>;
     unsafe fn free_all_functions(&mut self) -> Result<()>;
 }
@@ -305,6 +335,17 @@ pub trait Builder: BackendTypes + TypeMethods {
         let _ = then_is_cold;
         self.brif(cond, then_block, else_block)
     }
+    /// Branches with the given relative weights of `[then_block, else_block]`.
+    fn brif_weighted(
+        &mut self,
+        cond: Self::Value,
+        then_block: Self::BasicBlock,
+        else_block: Self::BasicBlock,
+        weights: [u32; 2],
+    ) {
+        let _ = weights;
+        self.brif(cond, then_block, else_block)
+    }
     fn switch(
         &mut self,
         index: Self::Value,
@@ -398,6 +439,10 @@ pub trait Builder: BackendTypes + TypeMethods {
     }
 
     fn unreachable(&mut self);
+    /// Tells the optimizer that `cond` is always true. It is undefined behavior if it is not.
+    ///
+    /// Backends that don't support this may ignore it.
+    fn assume(&mut self, cond: Self::Value);
 
     fn get_or_build_function(
         &mut self,
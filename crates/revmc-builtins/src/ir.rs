@@ -67,9 +67,17 @@ impl<B: Backend> Builtins<B> {
         for attr in default_attrs.iter().chain(builtin.attrs()).copied() {
             bcx.add_function_attribute(Some(f), attr, FunctionAttributeLocation::Function);
         }
+        // The sizes and alignments are those of the host types, which are not used on targets
+        // with another pointer width, like `wasm32`.
+        let host_layout = bcx.type_bit_width(bcx.type_ptr_sized_int()) == usize::BITS;
         let param_attrs = builtin.param_attrs();
         for (i, param_attrs) in param_attrs.iter().enumerate() {
             for attr in param_attrs {
+                if !host_layout
+                    && matches!(attr, Attribute::Dereferenceable(_) | Attribute::Align(_))
+                {
+                    continue;
+                }
                 bcx.add_function_attribute(
                     Some(f),
                     *attr,
@@ -157,6 +165,9 @@ macro_rules! builtins {
                 const FUNCSTACKPUSH: u8 = 0;
                 const FUNCSTACKPOP: u8 = 0;
                 const FUNCSTACKGROW: u8 = 0;
+                const RESULTSINK: u8 = 0;
+                const PROFILEBLOCK: u8 = 0;
+                const MAPSLOT: u8 = 0;
 
                 match self {
                     $(Self::$ident => [<$ident:upper>]),*
@@ -172,6 +183,7 @@ builtins! {
         let usize = bcx.type_ptr_sized_int();
         let bool = bcx.type_int(1);
         let u8 = bcx.type_int(8);
+        let u32 = bcx.type_int(32);
     }
 
     @param_attrs |op| {
@@ -221,25 +233,26 @@ builtins! {
     MulMod         = __revmc_builtin_mulmod(@[sp] ptr) None,
     Exp            = __revmc_builtin_exp(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     Keccak256      = __revmc_builtin_keccak256(@[ecx] ptr, @[sp] ptr) Some(u8),
+    MapSlot        = __revmc_builtin_map_slot(ptr, ptr, ptr) None,
     Balance        = __revmc_builtin_balance(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     CallDataCopy   = __revmc_builtin_calldatacopy(@[ecx] ptr, @[sp] ptr) Some(u8),
     CodeSize       = __revmc_builtin_codesize(@[ecx] ptr) Some(usize),
     CodeCopy       = __revmc_builtin_codecopy(@[ecx] ptr, @[sp] ptr) Some(u8),
-    GasPrice       = __revmc_builtin_gas_price(@[ecx] ptr, @[sp] ptr) None,
+    GasPrice       = __revmc_builtin_gas_price(@[ecx] ptr, @[sp] ptr) Some(u8),
     ExtCodeSize    = __revmc_builtin_extcodesize(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     ExtCodeCopy    = __revmc_builtin_extcodecopy(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     ReturnDataCopy = __revmc_builtin_returndatacopy(@[ecx] ptr, @[sp] ptr) Some(u8),
     ExtCodeHash    = __revmc_builtin_extcodehash(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     BlockHash      = __revmc_builtin_blockhash(@[ecx] ptr, @[sp] ptr) Some(u8),
-    Difficulty     = __revmc_builtin_difficulty(@[ecx] ptr, @[sp] ptr, u8) None,
+    Difficulty     = __revmc_builtin_difficulty(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     SelfBalance    = __revmc_builtin_self_balance(@[ecx] ptr, @[sp] ptr) Some(u8),
-    BlobHash       = __revmc_builtin_blob_hash(@[ecx] ptr, @[sp] ptr) None,
-    BlobBaseFee    = __revmc_builtin_blob_base_fee(@[ecx] ptr, @[sp] ptr) None,
+    BlobHash       = __revmc_builtin_blob_hash(@[ecx] ptr, @[sp] ptr) Some(u8),
+    BlobBaseFee    = __revmc_builtin_blob_base_fee(@[ecx] ptr, @[sp] ptr) Some(u8),
     Sload          = __revmc_builtin_sload(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     Sstore         = __revmc_builtin_sstore(@[ecx] ptr, @[sp] ptr, u8) Some(u8),
     Msize          = __revmc_builtin_msize(@[ecx] ptr) Some(usize),
     Tstore         = __revmc_builtin_tstore(@[ecx] ptr, @[sp] ptr) Some(u8),
-    Tload          = __revmc_builtin_tload(@[ecx] ptr, @[sp] ptr) None,
+    Tload          = __revmc_builtin_tload(@[ecx] ptr, @[sp] ptr) Some(u8),
     Mcopy          = __revmc_builtin_mcopy(@[ecx] ptr, @[sp] ptr) Some(u8),
     Log            = __revmc_builtin_log(@[ecx] ptr, @[sp_dyn] ptr, u8) Some(u8),
     DataLoad       = __revmc_builtin_data_load(@[ecx] ptr, @[sp] ptr) None,
@@ -259,4 +272,6 @@ builtins! {
     FuncStackGrow  = __revmc_builtin_func_stack_grow(@[ecx] ptr) None,
 
     ResizeMemory   = __revmc_builtin_resize_memory(@[ecx] ptr, usize) Some(u8),
+    ResultSink     = __revmc_builtin_result_sink(@[ecx] ptr, u32, u8) None,
+    ProfileBlock   = __revmc_builtin_profile_block(@[ecx] ptr, u32) None,
 }
@@ -17,10 +17,10 @@ use revm_interpreter::{
     InterpreterResult,
 };
 use revm_primitives::{
-    eof::EofHeader, Address, Bytes, CreateScheme, Eof, Log, LogData, SpecId, KECCAK_EMPTY,
-    MAX_INITCODE_SIZE, U256,
+    eof::EofHeader, Address, Bytes, CreateScheme, Eof, Log, LogData, SpecId, B256,
+    BLOCK_HASH_HISTORY, KECCAK_EMPTY, MAX_INITCODE_SIZE, U256,
 };
-use revmc_context::{EvmContext, EvmWord};
+use revmc_context::{EvmContext, EvmWord, GasMode, HostCallKind};
 
 pub mod gas;
 
@@ -98,6 +98,9 @@ pub enum CreateKind {
 
 // NOTE: All functions MUST be `extern "C"` and their parameters must match `Builtin` enum.
 //
+// NOTE: The static context checks of state changing instructions are done by the compiled code
+// before calling the builtins, except for `EXTCALL`, which checks after expanding memory.
+//
 // The `sp` parameter always points to the last popped stack element.
 // If results are expected to be pushed back onto the stack, they must be written to the read
 // pointers in **reverse order**, meaning the last pointer is the first return value.
@@ -124,10 +127,12 @@ pub unsafe extern "C" fn __revmc_builtin_exp(
     rev![base, exponent_ptr]: &mut [EvmWord; 2],
     spec_id: SpecId,
 ) -> InstructionResult {
-    let exponent = exponent_ptr.to_u256();
-    gas_opt!(ecx, gas::dyn_exp_cost(spec_id, exponent));
-    *exponent_ptr = base.to_u256().pow(exponent).into();
-    InstructionResult::Continue
+    catch_panic!(ecx, "exp", {
+        let exponent = exponent_ptr.to_u256();
+        gas_opt!(ecx, gas::dyn_exp_cost(spec_id, exponent));
+        *exponent_ptr = base.to_u256().pow(exponent).into();
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -135,17 +140,31 @@ pub unsafe extern "C" fn __revmc_builtin_keccak256(
     ecx: &mut EvmContext<'_>,
     rev![offset, len_ptr]: &mut [EvmWord; 2],
 ) -> InstructionResult {
-    let len = try_into_usize!(len_ptr);
-    *len_ptr = EvmWord::from_be_bytes(if len == 0 {
-        KECCAK_EMPTY.0
-    } else {
-        gas_opt!(ecx, gas::dyn_keccak256_cost(len as u64));
-        let offset = try_into_usize!(offset);
-        ensure_memory!(ecx, offset, len);
-        let data = ecx.memory.slice(offset, len);
-        revm_primitives::keccak256(data).0
-    });
-    InstructionResult::Continue
+    catch_panic!(ecx, "keccak256", {
+        let len = try_into_usize!(len_ptr);
+        *len_ptr = EvmWord::from_be_bytes(if len == 0 {
+            KECCAK_EMPTY.0
+        } else {
+            gas_opt!(ecx, gas_params(ecx).keccak256_cost(len as u64));
+            let offset = try_into_usize!(offset);
+            ensure_memory!(ecx, offset, len);
+            let data = ecx.memory.slice(offset, len);
+            revm_primitives::keccak256(data).0
+        });
+        InstructionResult::Continue
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __revmc_builtin_map_slot(
+    key: *const EvmWord,
+    slot: &EvmWord,
+    out: *mut EvmWord,
+) {
+    let mut buf = [0u8; 64];
+    (*key).copy_be_into(&mut buf[..32]);
+    slot.copy_be_into(&mut buf[32..]);
+    *out = EvmWord::from_be_bytes(revm_primitives::keccak256(buf).0);
 }
 
 #[no_mangle]
@@ -154,20 +173,22 @@ pub unsafe extern "C" fn __revmc_builtin_balance(
     address: &mut EvmWord,
     spec_id: SpecId,
 ) -> InstructionResult {
-    let state = try_host!(ecx.host.balance(address.to_address()));
-    *address = state.data.into();
-    let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
-        gas::warm_cold_cost(state.is_cold)
-    } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
-        // EIP-1884: Repricing for trie-size-dependent opcodes
-        700
-    } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
-        400
-    } else {
-        20
-    };
-    gas!(ecx, gas);
-    InstructionResult::Continue
+    catch_panic!(ecx, "balance", {
+        let state = try_host!(ecx.host.balance(address.to_address()));
+        *address = state.data.into();
+        let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
+            gas_params(ecx).warm_cold_cost(state.is_cold)
+        } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+            // EIP-1884: Repricing for trie-size-dependent opcodes
+            700
+        } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
+            400
+        } else {
+            20
+        };
+        host_gas!(ecx, Balance, gas);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -175,13 +196,16 @@ pub unsafe extern "C" fn __revmc_builtin_calldatacopy(
     ecx: &mut EvmContext<'_>,
     sp: &mut [EvmWord; 3],
 ) -> InstructionResult {
-    let input = decouple_lt(&ecx.contract.input[..]);
-    copy_operation(ecx, sp, input)
+    catch_panic!(ecx, "calldatacopy", {
+        let input = decouple_lt(&ecx.contract.input[..]);
+        copy_operation(ecx, sp, input)
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn __revmc_builtin_codesize(ecx: &mut EvmContext<'_>) -> usize {
     assume!(!ecx.contract.bytecode.is_eof());
+    // The length of the original bytecode, without the padding added by the analysis.
     ecx.contract.bytecode.len()
 }
 
@@ -190,14 +214,22 @@ pub unsafe extern "C" fn __revmc_builtin_codecopy(
     ecx: &mut EvmContext<'_>,
     sp: &mut [EvmWord; 3],
 ) -> InstructionResult {
-    assume!(!ecx.contract.bytecode.is_eof());
-    let code = decouple_lt(ecx.contract.bytecode.original_byte_slice());
-    copy_operation(ecx, sp, code)
+    catch_panic!(ecx, "codecopy", {
+        assume!(!ecx.contract.bytecode.is_eof());
+        let code = decouple_lt(ecx.contract.bytecode.original_byte_slice());
+        copy_operation(ecx, sp, code)
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn __revmc_builtin_gas_price(ecx: &mut EvmContext<'_>, slot: &mut EvmWord) {
-    *slot = ecx.host.env().effective_gas_price().into();
+pub unsafe extern "C" fn __revmc_builtin_gas_price(
+    ecx: &mut EvmContext<'_>,
+    slot: &mut EvmWord,
+) -> InstructionResult {
+    catch_panic!(ecx, "gas_price", {
+        *slot = host!(ecx.host.env()).effective_gas_price().into();
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -206,19 +238,21 @@ pub unsafe extern "C" fn __revmc_builtin_extcodesize(
     address: &mut EvmWord,
     spec_id: SpecId,
 ) -> InstructionResult {
-    let (code, state) =
-        Eip7702CodeLoad::new_state_load(try_host!(ecx.host.code(address.to_address())))
-            .into_components();
-    *address = code.len().into();
-    let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
-        gas::warm_cold_cost_with_delegation(state)
-    } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
-        700
-    } else {
-        20
-    };
-    gas!(ecx, gas);
-    InstructionResult::Continue
+    catch_panic!(ecx, "extcodesize", {
+        let (code, state) =
+            Eip7702CodeLoad::new_state_load(try_host!(ecx.host.code(address.to_address())))
+                .into_components();
+        *address = code.len().into();
+        let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
+            gas_params(ecx).warm_cold_cost_with_delegation(state)
+        } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
+            700
+        } else {
+            20
+        };
+        host_gas!(ecx, Code, gas);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -227,18 +261,24 @@ pub unsafe extern "C" fn __revmc_builtin_extcodecopy(
     rev![address, memory_offset, code_offset, len]: &mut [EvmWord; 4],
     spec_id: SpecId,
 ) -> InstructionResult {
-    let state_load = try_host!(ecx.host.code(address.to_address()));
-
-    let len = try_into_usize!(len);
-    gas_opt!(ecx, gas::extcodecopy_cost(spec_id, len as u64, state_load.is_cold));
-    if len != 0 {
-        let memory_offset = try_into_usize!(memory_offset);
-        let code_offset = code_offset.to_u256();
-        let code_offset = as_usize_saturated!(code_offset).min(state_load.data.len());
-        ensure_memory!(ecx, memory_offset, len);
-        ecx.memory.set_data(memory_offset, code_offset, len, &state_load.data);
-    }
-    InstructionResult::Continue
+    catch_panic!(ecx, "extcodecopy", {
+        let state_load = try_host!(ecx.host.code(address.to_address()));
+
+        let len = try_into_usize!(len);
+        let Some(gas) = gas_params(ecx).extcodecopy_cost(spec_id, len as u64, state_load.is_cold)
+        else {
+            return InstructionResult::OutOfGas;
+        };
+        host_gas!(ecx, Code, gas);
+        if len != 0 {
+            let memory_offset = try_into_usize!(memory_offset);
+            let code_offset = code_offset.to_u256();
+            let code_offset = as_usize_saturated!(code_offset).min(state_load.data.len());
+            ensure_memory!(ecx, memory_offset, len);
+            ecx.memory.set_data(memory_offset, code_offset, len, &state_load.data);
+        }
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -246,20 +286,22 @@ pub unsafe extern "C" fn __revmc_builtin_returndatacopy(
     ecx: &mut EvmContext<'_>,
     rev![memory_offset, offset, len]: &mut [EvmWord; 3],
 ) -> InstructionResult {
-    let len = try_into_usize!(len);
-    gas_opt!(ecx, gas::dyn_verylowcopy_cost(len as u64));
-    let data_offset = offset.to_u256();
-    let data_offset = as_usize_saturated!(data_offset);
-    let (data_end, overflow) = data_offset.overflowing_add(len);
-    if overflow || data_end > ecx.return_data.len() {
-        return InstructionResult::OutOfOffset;
-    }
-    if len != 0 {
-        let memory_offset = try_into_usize!(memory_offset);
-        ensure_memory!(ecx, memory_offset, len);
-        ecx.memory.set(memory_offset, &ecx.return_data[data_offset..data_end]);
-    }
-    InstructionResult::Continue
+    catch_panic!(ecx, "returndatacopy", {
+        let len = try_into_usize!(len);
+        gas_opt!(ecx, gas_params(ecx).copy_cost(len as u64));
+        let data_offset = offset.to_u256();
+        let data_offset = as_usize_saturated!(data_offset);
+        let (data_end, overflow) = data_offset.overflowing_add(len);
+        if overflow || data_end > ecx.return_data.len() {
+            return InstructionResult::OutOfOffset;
+        }
+        if len != 0 {
+            let memory_offset = try_into_usize!(memory_offset);
+            ensure_memory!(ecx, memory_offset, len);
+            ecx.memory.set(memory_offset, &ecx.return_data[data_offset..data_end]);
+        }
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -268,19 +310,21 @@ pub unsafe extern "C" fn __revmc_builtin_extcodehash(
     address: &mut EvmWord,
     spec_id: SpecId,
 ) -> InstructionResult {
-    let (hash, state) =
-        Eip7702CodeLoad::new_state_load(try_host!(ecx.host.code_hash(address.to_address())))
-            .into_components();
-    *address = EvmWord::from_be_bytes(hash.0);
-    let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
-        gas::warm_cold_cost_with_delegation(state)
-    } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
-        700
-    } else {
-        400
-    };
-    gas!(ecx, gas);
-    InstructionResult::Continue
+    catch_panic!(ecx, "extcodehash", {
+        let (hash, state) =
+            Eip7702CodeLoad::new_state_load(try_host!(ecx.host.code_hash(address.to_address())))
+                .into_components();
+        *address = EvmWord::from_be_slice(hash.as_slice());
+        let gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
+            gas_params(ecx).warm_cold_cost_with_delegation(state)
+        } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+            700
+        } else {
+            400
+        };
+        host_gas!(ecx, CodeHash, gas);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -288,9 +332,24 @@ pub unsafe extern "C" fn __revmc_builtin_blockhash(
     ecx: &mut EvmContext<'_>,
     number_ptr: &mut EvmWord,
 ) -> InstructionResult {
-    let hash = try_host!(ecx.host.block_hash(as_u64_saturated!(number_ptr.to_u256())));
-    *number_ptr = EvmWord::from_be_bytes(hash.0);
-    InstructionResult::Continue
+    catch_panic!(ecx, "blockhash", {
+        let number = number_ptr.to_u256();
+        // Only the `BLOCK_HASH_HISTORY` blocks before the current one are available.
+        let current = host!(ecx.host.env()).block.number;
+        let in_range = matches!(
+            current.checked_sub(number),
+            Some(age) if age != U256::ZERO && age <= U256::from(BLOCK_HASH_HISTORY)
+        );
+        *number_ptr = match u64::try_from(number) {
+            Ok(number) if in_range => {
+                let hash = try_host!(ecx.host.block_hash(number));
+                ecx.log_host_call(HostCallKind::BlockHash, 0);
+                EvmWord::from_be_slice(hash.as_slice())
+            }
+            _ => EvmWord::ZERO,
+        };
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -298,12 +357,16 @@ pub unsafe extern "C" fn __revmc_builtin_difficulty(
     ecx: &mut EvmContext<'_>,
     slot: &mut EvmWord,
     spec_id: SpecId,
-) {
-    *slot = if spec_id.is_enabled_in(SpecId::MERGE) {
-        EvmWord::from_be_bytes(ecx.host.env().block.prevrandao.unwrap().0)
-    } else {
-        ecx.host.env().block.difficulty.into()
-    };
+) -> InstructionResult {
+    catch_panic!(ecx, "difficulty", {
+        let block = &host!(ecx.host.env()).block;
+        *slot = if spec_id.is_enabled_in(SpecId::MERGE) {
+            EvmWord::from_be_bytes(block.prevrandao.unwrap().0)
+        } else {
+            block.difficulty.into()
+        };
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -311,35 +374,38 @@ pub unsafe extern "C" fn __revmc_builtin_self_balance(
     ecx: &mut EvmContext<'_>,
     slot: &mut EvmWord,
 ) -> InstructionResult {
-    let state = try_host!(ecx.host.balance(ecx.contract.target_address));
-    *slot = state.data.into();
-    InstructionResult::Continue
+    catch_panic!(ecx, "self_balance", {
+        let state = try_host!(ecx.host.balance(ecx.contract.target_address));
+        ecx.log_host_call(HostCallKind::Balance, 0);
+        *slot = state.data.into();
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn __revmc_builtin_blob_hash(
     ecx: &mut EvmContext<'_>,
     index_ptr: &mut EvmWord,
-) {
-    let index = index_ptr.to_u256();
-    *index_ptr = EvmWord::from_be_bytes(
-        ecx.host
-            .env()
-            .tx
-            .blob_hashes
-            .get(as_usize_saturated!(index))
-            .copied()
-            .unwrap_or_default()
-            .0,
-    );
+) -> InstructionResult {
+    catch_panic!(ecx, "blob_hash", {
+        let index = index_ptr.to_u256();
+        let blob_hashes = &host!(ecx.host.env()).tx.blob_hashes;
+        *index_ptr = EvmWord::from_be_bytes(
+            blob_hashes.get(as_usize_saturated!(index)).copied().unwrap_or_default().0,
+        );
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn __revmc_builtin_blob_base_fee(
     ecx: &mut EvmContext<'_>,
     slot: &mut EvmWord,
-) {
-    *slot = ecx.host.env().block.get_blob_gasprice().unwrap_or_default().into();
+) -> InstructionResult {
+    catch_panic!(ecx, "blob_base_fee", {
+        *slot = host!(ecx.host.env()).block.get_blob_gasprice().unwrap_or_default().into();
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -348,11 +414,13 @@ pub unsafe extern "C" fn __revmc_builtin_sload(
     index: &mut EvmWord,
     spec_id: SpecId,
 ) -> InstructionResult {
-    let address = ecx.contract.target_address;
-    let state = try_opt!(ecx.host.sload(address, index.to_u256()));
-    gas!(ecx, gas::sload_cost(spec_id, state.is_cold));
-    *index = state.data.into();
-    InstructionResult::Continue
+    catch_panic!(ecx, "sload", {
+        let address = ecx.contract.target_address;
+        let state = try_host!(ecx.host.sload(address, index.to_u256()));
+        host_gas!(ecx, Sload, gas_params(ecx).sload_cost(spec_id, state.is_cold));
+        *index = state.data.into();
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -361,14 +429,26 @@ pub unsafe extern "C" fn __revmc_builtin_sstore(
     rev![index, value]: &mut [EvmWord; 2],
     spec_id: SpecId,
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-
-    let state =
-        try_opt!(ecx.host.sstore(ecx.contract.target_address, index.to_u256(), value.to_u256()));
-
-    gas_opt!(ecx, gas::sstore_cost(spec_id, &state.data, ecx.gas.remaining(), state.is_cold));
-    ecx.gas.record_refund(gas::sstore_refund(spec_id, &state.data));
-    InstructionResult::Continue
+    catch_panic!(ecx, "sstore", {
+        let state = try_host!(ecx.host.sstore(
+            ecx.contract.target_address,
+            index.to_u256(),
+            value.to_u256()
+        ));
+
+        let params = gas_params(ecx);
+        // The EIP-2200 stipend check halts with an out-of-gas error, which `ObserveOnly` never does.
+        let remaining =
+            if ecx.gas_mode == GasMode::ObserveOnly { u64::MAX } else { ecx.gas.remaining() };
+        let Some(gas) = params.sstore_cost(spec_id, &state.data, remaining, state.is_cold) else {
+            let result = InstructionResult::OutOfGas;
+            ecx.log_host_call_result(HostCallKind::Sstore, 0, result);
+            return result;
+        };
+        host_gas!(ecx, Sstore, gas);
+        ecx.gas.record_refund(params.sstore_refund(spec_id, &state.data));
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -381,14 +461,23 @@ pub unsafe extern "C" fn __revmc_builtin_tstore(
     ecx: &mut EvmContext<'_>,
     rev![key, value]: &mut [EvmWord; 2],
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-    ecx.host.tstore(ecx.contract.target_address, key.to_u256(), value.to_u256());
-    InstructionResult::Continue
+    catch_panic!(ecx, "tstore", {
+        host!(ecx.host.tstore(ecx.contract.target_address, key.to_u256(), value.to_u256()));
+        ecx.log_host_call(HostCallKind::Tstore, 0);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn __revmc_builtin_tload(ecx: &mut EvmContext<'_>, key: &mut EvmWord) {
-    *key = ecx.host.tload(ecx.contract.target_address, key.to_u256()).into();
+pub unsafe extern "C" fn __revmc_builtin_tload(
+    ecx: &mut EvmContext<'_>,
+    key: &mut EvmWord,
+) -> InstructionResult {
+    catch_panic!(ecx, "tload", {
+        *key = host!(ecx.host.tload(ecx.contract.target_address, key.to_u256())).into();
+        ecx.log_host_call(HostCallKind::Tload, 0);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -396,15 +485,17 @@ pub unsafe extern "C" fn __revmc_builtin_mcopy(
     ecx: &mut EvmContext<'_>,
     rev![dst, src, len]: &mut [EvmWord; 3],
 ) -> InstructionResult {
-    let len = try_into_usize!(len);
-    gas_opt!(ecx, gas::dyn_verylowcopy_cost(len as u64));
-    if len != 0 {
-        let dst = try_into_usize!(dst);
-        let src = try_into_usize!(src);
-        ensure_memory!(ecx, dst.max(src), len);
-        ecx.memory.copy(dst, src, len);
-    }
-    InstructionResult::Continue
+    catch_panic!(ecx, "mcopy", {
+        let len = try_into_usize!(len);
+        gas_opt!(ecx, gas_params(ecx).copy_cost(len as u64));
+        if len != 0 {
+            let dst = try_into_usize!(dst);
+            let src = try_into_usize!(src);
+            ensure_memory!(ecx, dst.max(src), len);
+            ecx.memory.copy(dst, src, len);
+        }
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -413,30 +504,36 @@ pub unsafe extern "C" fn __revmc_builtin_log(
     sp: *mut EvmWord,
     n: u8,
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-    assume!(n <= 4, "invalid log topic count: {n}");
-    let sp = sp.add(n as usize);
-    read_words!(sp, offset, len);
-    let len = try_into_usize!(len);
-    gas_opt!(ecx, gas::dyn_log_cost(len as u64));
-    let data = if len != 0 {
-        let offset = try_into_usize!(offset);
-        ensure_memory!(ecx, offset, len);
-        Bytes::copy_from_slice(ecx.memory.slice(offset, len))
-    } else {
-        Bytes::new()
-    };
-
-    let mut topics = Vec::with_capacity(n as usize);
-    for i in 1..=n {
-        topics.push(sp.sub(i as usize).read().to_be_bytes().into());
-    }
+    catch_panic!(ecx, "log", {
+        assume!(n <= 4, "invalid log topic count: {n}");
+        let sp = sp.add(n as usize);
+        read_words!(sp, offset, len);
+        let len = try_into_usize!(len);
+        let Some(log_cost) = gas::dyn_log_cost(len as u64) else {
+            return InstructionResult::OutOfGas;
+        };
+        gas!(ecx, log_cost);
+        let data = if len != 0 {
+            let offset = try_into_usize!(offset);
+            ensure_memory!(ecx, offset, len);
+            Bytes::copy_from_slice(ecx.memory.slice(offset, len))
+        } else {
+            Bytes::new()
+        };
+
+        let mut topics = Vec::with_capacity(n as usize);
+        for i in 1..=n {
+            topics.push(sp.sub(i as usize).read().to_be_bytes().into());
+        }
 
-    ecx.host.log(Log {
-        address: ecx.contract.target_address,
-        data: LogData::new(topics, data).expect("too many topics"),
-    });
-    InstructionResult::Continue
+        let log = Log {
+            address: ecx.contract.target_address,
+            data: LogData::new(topics, data).expect("too many topics"),
+        };
+        host!(ecx.host.log(log));
+        ecx.log_host_call(HostCallKind::Log, log_cost);
+        InstructionResult::Continue
+    })
 }
 
 pub unsafe extern "C" fn __revmc_builtin_data_load(ecx: &mut EvmContext<'_>, slot: &mut EvmWord) {
@@ -451,8 +548,10 @@ pub unsafe extern "C" fn __revmc_builtin_data_copy(
     ecx: &mut EvmContext<'_>,
     sp: &mut [EvmWord; 3],
 ) -> InstructionResult {
-    let data = decouple_lt(ecx.contract.bytecode.eof().unwrap().data());
-    copy_operation(ecx, sp, data)
+    catch_panic!(ecx, "data_copy", {
+        let data = decouple_lt(ecx.contract.bytecode.eof().unwrap().data());
+        copy_operation(ecx, sp, data)
+    })
 }
 
 pub unsafe extern "C" fn __revmc_builtin_returndataload(
@@ -478,52 +577,53 @@ pub unsafe extern "C" fn __revmc_builtin_eof_create(
     initcontainer_index: usize,
     _spec_id: SpecId,
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-    gas!(ecx, gas::EOF_CREATE_GAS);
-    let sub_container = ecx
-        .contract
-        .bytecode
-        .eof()
-        .expect("EOF is set")
-        .body
-        .container_section
-        .get(initcontainer_index)
-        .cloned()
-        .expect("EOF is checked");
-
-    let in_len = try_into_usize!(in_len);
-    let input = if in_len != 0 {
-        let in_offset = try_into_usize!(in_offset);
-        ensure_memory!(ecx, in_offset, in_len);
-        Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
-    } else {
-        Bytes::new()
-    };
-
-    let eof = Eof::decode(sub_container.clone()).expect("Subcontainer is verified");
-    assert!(eof.body.is_data_filled);
-
-    gas_opt!(ecx, gas::cost_per_word(sub_container.len() as u64, gas::KECCAK256WORD));
-
-    let created_address = ecx
-        .contract
-        .target_address
-        .create2(salt.to_be_bytes(), revm_primitives::keccak256(sub_container));
-
-    let gas_limit = ecx.gas.remaining_63_of_64_parts();
-    gas!(ecx, gas_limit);
-    *ecx.next_action = InterpreterAction::EOFCreate {
-        inputs: Box::new(EOFCreateInputs::new_opcode(
-            ecx.contract.target_address,
-            created_address,
-            value.to_u256(),
-            eof,
-            gas_limit,
-            input,
-        )),
-    };
-
-    InstructionResult::Continue
+    catch_panic!(ecx, "eof_create", {
+        gas!(ecx, gas::EOF_CREATE_GAS);
+        let sub_container = ecx
+            .contract
+            .bytecode
+            .eof()
+            .expect("EOF is set")
+            .body
+            .container_section
+            .get(initcontainer_index)
+            .cloned()
+            .expect("EOF is checked");
+
+        let in_len = try_into_usize!(in_len);
+        let input = if in_len != 0 {
+            let in_offset = try_into_usize!(in_offset);
+            ensure_memory!(ecx, in_offset, in_len);
+            Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
+        } else {
+            Bytes::new()
+        };
+
+        let eof = Eof::decode(sub_container.clone()).expect("Subcontainer is verified");
+        assert!(eof.body.is_data_filled);
+
+        gas_opt!(ecx, gas_params(ecx).keccak256_cost(sub_container.len() as u64));
+
+        let created_address = ecx
+            .contract
+            .target_address
+            .create2(salt.to_be_bytes(), revm_primitives::keccak256(sub_container));
+
+        let gas_limit = ecx.gas.remaining_63_of_64_parts();
+        gas!(ecx, gas_limit);
+        *ecx.next_action = InterpreterAction::EOFCreate {
+            inputs: Box::new(EOFCreateInputs::new_opcode(
+                ecx.contract.target_address,
+                created_address,
+                value.to_u256(),
+                eof,
+                gas_limit,
+                input,
+            )),
+        };
+
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -533,48 +633,58 @@ pub unsafe extern "C" fn __revmc_builtin_return_contract(
     deploy_container_index: usize,
     _spec_id: SpecId,
 ) -> InstructionResult {
-    if !ecx.is_eof_init {
-        return InstructionResult::ReturnContractInNotInitEOF;
-    }
-    let aux_data_len = try_into_usize!(aux_data_len);
-    let container = ecx
-        .contract
-        .bytecode
-        .eof()
-        .expect("EOF is set")
-        .body
-        .container_section
-        .get(deploy_container_index)
-        .expect("EOF is checked");
-    let (eof_header, _) = EofHeader::decode(container).expect("valid EOF header");
-
-    let aux_slice = if aux_data_len != 0 {
-        let aux_data_offset = try_into_usize!(aux_data_offset);
-        try_ir!(ensure_memory_inner(ecx.memory, ecx.gas, aux_data_offset, aux_data_len));
-        ecx.memory.slice(aux_data_offset, aux_data_len)
-    } else {
-        &[]
-    };
-
-    let static_aux_size = eof_header.eof_size() - container.len();
-
-    let new_data_size = eof_header.data_size as usize - static_aux_size + aux_slice.len();
-    if new_data_size > 0xFFFF {
-        return InstructionResult::EofAuxDataOverflow;
-    }
-    if new_data_size < eof_header.data_size as usize {
-        return InstructionResult::EofAuxDataTooSmall;
-    }
-    let new_data_size = (new_data_size as u16).to_be_bytes();
+    catch_panic!(ecx, "return_contract", {
+        if !ecx.is_eof_init {
+            return InstructionResult::ReturnContractInNotInitEOF;
+        }
+        let aux_data_len = try_into_usize!(aux_data_len);
+        let container = ecx
+            .contract
+            .bytecode
+            .eof()
+            .expect("EOF is set")
+            .body
+            .container_section
+            .get(deploy_container_index)
+            .expect("EOF is checked");
+        let (eof_header, _) = EofHeader::decode(container).expect("valid EOF header");
+
+        let aux_slice = if aux_data_len != 0 {
+            let aux_data_offset = try_into_usize!(aux_data_offset);
+            let params = gas_params(ecx);
+            try_ir!(ensure_memory_inner(
+                ecx.memory,
+                ecx.gas,
+                ecx.gas_mode,
+                params,
+                aux_data_offset,
+                aux_data_len
+            ));
+            ecx.memory.slice(aux_data_offset, aux_data_len)
+        } else {
+            &[]
+        };
+
+        let static_aux_size = eof_header.eof_size() - container.len();
+
+        let new_data_size = eof_header.data_size as usize - static_aux_size + aux_slice.len();
+        if new_data_size > 0xFFFF {
+            return InstructionResult::EofAuxDataOverflow;
+        }
+        if new_data_size < eof_header.data_size as usize {
+            return InstructionResult::EofAuxDataTooSmall;
+        }
+        let new_data_size = (new_data_size as u16).to_be_bytes();
 
-    let mut output = [container, aux_slice].concat();
-    output[eof_header.data_size_raw_i()..][..2].copy_from_slice(&new_data_size);
+        let mut output = [container, aux_slice].concat();
+        output[eof_header.data_size_raw_i()..][..2].copy_from_slice(&new_data_size);
 
-    let result = InstructionResult::ReturnContract;
-    *ecx.next_action = crate::InterpreterAction::Return {
-        result: InterpreterResult { output: output.into(), gas: *ecx.gas, result },
-    };
-    result
+        let result = InstructionResult::ReturnContract;
+        *ecx.next_action = crate::InterpreterAction::Return {
+            result: InterpreterResult { output: output.into(), gas: *ecx.gas, result },
+        };
+        result
+    })
 }
 
 #[no_mangle]
@@ -584,66 +694,80 @@ pub unsafe extern "C" fn __revmc_builtin_create(
     spec_id: SpecId,
     create_kind: CreateKind,
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-
-    let len = match create_kind {
-        CreateKind::Create => 3,
-        CreateKind::Create2 => 4,
-    };
-    let mut sp = sp.add(len);
-    pop!(sp; value, code_offset, len);
-
-    let len = try_into_usize!(len);
-    let code = if len != 0 {
-        if spec_id.is_enabled_in(SpecId::SHANGHAI) {
-            // Limit is set as double of max contract bytecode size
-            let max_initcode_size = ecx
-                .host
-                .env()
-                .cfg
-                .limit_contract_code_size
-                .map(|limit| limit.saturating_mul(2))
-                .unwrap_or(MAX_INITCODE_SIZE);
-            if len > max_initcode_size {
-                return InstructionResult::CreateInitCodeSizeLimit;
+    catch_panic!(ecx, "create", {
+        let len = match create_kind {
+            CreateKind::Create => 3,
+            CreateKind::Create2 => 4,
+        };
+        let mut sp = sp.add(len);
+        pop!(sp; value, code_offset, len);
+
+        let len = try_into_usize!(len);
+        let code = if len != 0 {
+            if spec_id.is_enabled_in(SpecId::SHANGHAI) {
+                // Limit is set as double of max contract bytecode size
+                let max_initcode_size = ecx
+                    .host
+                    .env()
+                    .cfg
+                    .limit_contract_code_size
+                    .map(|limit| limit.saturating_mul(2))
+                    .unwrap_or(MAX_INITCODE_SIZE);
+                if len > max_initcode_size {
+                    return InstructionResult::CreateInitCodeSizeLimit;
+                }
+                gas!(ecx, gas::initcode_cost(len as u64));
             }
-            gas!(ecx, gas::initcode_cost(len as u64));
-        }
-
-        let code_offset = try_into_usize!(code_offset);
-        ensure_memory!(ecx, code_offset, len);
-        Bytes::copy_from_slice(ecx.memory.slice(code_offset, len))
-    } else {
-        Bytes::new()
-    };
-
-    let is_create2 = create_kind == CreateKind::Create2;
-    gas_opt!(ecx, if is_create2 { gas::create2_cost(len as u64) } else { Some(gas::CREATE) });
-
-    let scheme = if is_create2 {
-        pop!(sp; salt);
-        CreateScheme::Create2 { salt: salt.to_u256() }
-    } else {
-        CreateScheme::Create
-    };
-
-    let mut gas_limit = ecx.gas.remaining();
-    if spec_id.is_enabled_in(SpecId::TANGERINE) {
-        gas_limit -= gas_limit / 64;
-    }
-    gas!(ecx, gas_limit);
 
-    *ecx.next_action = InterpreterAction::Create {
-        inputs: Box::new(CreateInputs {
-            caller: ecx.contract.target_address,
-            scheme,
-            value: value.to_u256(),
-            init_code: code,
-            gas_limit,
-        }),
-    };
+            let code_offset = try_into_usize!(code_offset);
+            ensure_memory!(ecx, code_offset, len);
+            Bytes::copy_from_slice(ecx.memory.slice(code_offset, len))
+        } else {
+            Bytes::new()
+        };
+
+        let is_create2 = create_kind == CreateKind::Create2;
+        let create_cost = if is_create2 {
+            gas_params(ecx)
+                .keccak256_cost(len as u64)
+                .and_then(|cost| cost.checked_add(gas::CREATE))
+        } else {
+            Some(gas::CREATE)
+        };
+        gas_opt!(ecx, create_cost);
+
+        let scheme = if is_create2 {
+            pop!(sp; salt);
+            CreateScheme::Create2 { salt: salt.to_u256() }
+        } else {
+            CreateScheme::Create
+        };
+
+        let mut gas_limit = ecx.gas.remaining();
+        if spec_id.is_enabled_in(SpecId::TANGERINE) {
+            gas_limit -= gas_limit / 64;
+        }
+        gas!(ecx, gas_limit);
+
+        *ecx.next_action = InterpreterAction::Create {
+            inputs: Box::new(CreateInputs {
+                caller: ecx.contract.target_address,
+                scheme,
+                value: value.to_u256(),
+                init_code: code,
+                gas_limit,
+            }),
+        };
+
+        InstructionResult::Continue
+    })
+}
 
-    InstructionResult::Continue
+/// Computes the address of a contract created with `CREATE2` by `deployer`:
+/// `keccak256(0xff ++ deployer ++ salt ++ init_code_hash)[12..]`.
+#[inline]
+pub fn create2_address(deployer: Address, salt: U256, init_code_hash: B256) -> Address {
+    deployer.create2(salt.to_be_bytes::<32>(), init_code_hash)
 }
 
 #[no_mangle]
@@ -653,107 +777,110 @@ pub unsafe extern "C" fn __revmc_builtin_call(
     spec_id: SpecId,
     call_kind: CallKind,
 ) -> InstructionResult {
-    let len = match call_kind {
-        CallKind::Call | CallKind::CallCode => 7,
-        CallKind::DelegateCall | CallKind::StaticCall => 6,
-    };
-    let mut sp = sp.add(len);
-
-    pop!(sp; local_gas_limit, to);
-    let local_gas_limit = local_gas_limit.to_u256();
-    let to = to.to_address();
-
-    // max gas limit is not possible in real ethereum situation.
-    // But for tests we would not like to fail on this.
-    // Gas limit for subcall is taken as min of this value and current gas limit.
-    let local_gas_limit = as_u64_saturated!(local_gas_limit);
-
-    let value = match call_kind {
-        CallKind::Call | CallKind::CallCode => {
-            pop!(sp; value);
-            let value = value.to_u256();
-            if call_kind == CallKind::Call && ecx.is_static && value != U256::ZERO {
-                return InstructionResult::CallNotAllowedInsideStatic;
+    catch_panic!(ecx, "call", {
+        let len = match call_kind {
+            CallKind::Call | CallKind::CallCode => 7,
+            CallKind::DelegateCall | CallKind::StaticCall => 6,
+        };
+        let mut sp = sp.add(len);
+
+        pop!(sp; local_gas_limit, to);
+        let local_gas_limit = local_gas_limit.to_u256();
+        let to = to.to_address();
+
+        // max gas limit is not possible in real ethereum situation.
+        // But for tests we would not like to fail on this.
+        // Gas limit for subcall is taken as min of this value and current gas limit.
+        let local_gas_limit = as_u64_saturated!(local_gas_limit);
+
+        let value = match call_kind {
+            CallKind::Call | CallKind::CallCode => {
+                pop!(sp; value);
+                value.to_u256()
             }
-            value
+            CallKind::DelegateCall | CallKind::StaticCall => U256::ZERO,
+        };
+        let transfers_value = value != U256::ZERO;
+
+        pop!(sp; in_offset, in_len, out_offset, out_len);
+
+        let in_len = try_into_usize!(in_len);
+        let input = if in_len != 0 {
+            let in_offset = try_into_usize!(in_offset);
+            ensure_memory!(ecx, in_offset, in_len);
+            Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
+        } else {
+            Bytes::new()
+        };
+
+        let out_len = try_into_usize!(out_len);
+        let out_offset = if out_len != 0 {
+            let out_offset = try_into_usize!(out_offset);
+            ensure_memory!(ecx, out_offset, out_len);
+            out_offset
+        } else {
+            usize::MAX // unrealistic value so we are sure it is not used
+        };
+
+        // Load account and calculate gas cost.
+        let mut account_load = try_host!(ecx.host.load_account_delegated(to));
+
+        if call_kind != CallKind::Call {
+            account_load.is_empty = false;
         }
-        CallKind::DelegateCall | CallKind::StaticCall => U256::ZERO,
-    };
-    let transfers_value = value != U256::ZERO;
-
-    pop!(sp; in_offset, in_len, out_offset, out_len);
-
-    let in_len = try_into_usize!(in_len);
-    let input = if in_len != 0 {
-        let in_offset = try_into_usize!(in_offset);
-        ensure_memory!(ecx, in_offset, in_len);
-        Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
-    } else {
-        Bytes::new()
-    };
-
-    let out_len = try_into_usize!(out_len);
-    let out_offset = if out_len != 0 {
-        let out_offset = try_into_usize!(out_offset);
-        ensure_memory!(ecx, out_offset, out_len);
-        out_offset
-    } else {
-        usize::MAX // unrealistic value so we are sure it is not used
-    };
-
-    // Load account and calculate gas cost.
-    let mut account_load = try_host!(ecx.host.load_account_delegated(to));
-
-    if call_kind != CallKind::Call {
-        account_load.is_empty = false;
-    }
-
-    gas!(ecx, gas::call_cost(spec_id, transfers_value, account_load));
-
-    // EIP-150: Gas cost changes for IO-heavy operations
-    let mut gas_limit = if spec_id.is_enabled_in(SpecId::TANGERINE) {
-        let gas = ecx.gas.remaining();
-        // take l64 part of gas_limit
-        (gas - gas / 64).min(local_gas_limit)
-    } else {
-        local_gas_limit
-    };
 
-    gas!(ecx, gas_limit);
-
-    // Add call stipend if there is value to be transferred.
-    if matches!(call_kind, CallKind::Call | CallKind::CallCode) && transfers_value {
-        gas_limit = gas_limit.saturating_add(gas::CALL_STIPEND);
-    }
+        host_gas!(
+            ecx,
+            LoadAccountDelegated,
+            gas_params(ecx).call_cost(spec_id, transfers_value, account_load)
+        );
+
+        // EIP-150: Gas cost changes for IO-heavy operations
+        let mut gas_limit = if spec_id.is_enabled_in(SpecId::TANGERINE) {
+            let gas = ecx.gas.remaining();
+            // take l64 part of gas_limit
+            (gas - gas / 64).min(local_gas_limit)
+        } else {
+            local_gas_limit
+        };
+
+        gas!(ecx, gas_limit);
+
+        // Add call stipend if there is value to be transferred.
+        if matches!(call_kind, CallKind::Call | CallKind::CallCode) && transfers_value {
+            gas_limit = gas_limit.saturating_add(gas::CALL_STIPEND);
+        }
 
-    *ecx.next_action = InterpreterAction::Call {
-        inputs: Box::new(CallInputs {
-            input,
-            return_memory_offset: out_offset..out_offset + out_len,
-            gas_limit,
-            bytecode_address: to,
-            target_address: if matches!(call_kind, CallKind::DelegateCall | CallKind::CallCode) {
-                ecx.contract.target_address
-            } else {
-                to
-            },
-            caller: if call_kind == CallKind::DelegateCall {
-                ecx.contract.caller
-            } else {
-                ecx.contract.target_address
-            },
-            value: if call_kind == CallKind::DelegateCall {
-                CallValue::Apparent(ecx.contract.call_value)
-            } else {
-                CallValue::Transfer(value)
-            },
-            scheme: call_kind.into(),
-            is_static: ecx.is_static || call_kind == CallKind::StaticCall,
-            is_eof: false,
-        }),
-    };
-
-    InstructionResult::Continue
+        *ecx.next_action = InterpreterAction::Call {
+            inputs: Box::new(CallInputs {
+                input,
+                return_memory_offset: out_offset..out_offset + out_len,
+                gas_limit,
+                bytecode_address: to,
+                target_address: if matches!(call_kind, CallKind::DelegateCall | CallKind::CallCode)
+                {
+                    ecx.contract.target_address
+                } else {
+                    to
+                },
+                caller: if call_kind == CallKind::DelegateCall {
+                    ecx.contract.caller
+                } else {
+                    ecx.contract.target_address
+                },
+                value: if call_kind == CallKind::DelegateCall {
+                    CallValue::Apparent(ecx.contract.call_value)
+                } else {
+                    CallValue::Transfer(value)
+                },
+                scheme: call_kind.into(),
+                is_static: ecx.is_static || call_kind == CallKind::StaticCall,
+                is_eof: false,
+            }),
+        };
+
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -763,77 +890,77 @@ pub unsafe extern "C" fn __revmc_builtin_ext_call(
     call_kind: ExtCallKind,
     spec_id: SpecId,
 ) -> InstructionResult {
-    let (target_address, in_offset, in_len, value) = if call_kind == ExtCallKind::Call {
-        let rev![target_address, in_offset, in_len, value] = &mut *sp.cast::<[EvmWord; 4]>();
-        (target_address, in_offset, in_len, value.to_u256())
-    } else {
-        let rev![target_address, in_offset, in_len] = &mut *sp.cast::<[EvmWord; 3]>();
-        (target_address, in_offset, in_len, U256::ZERO)
-    };
-
-    let target_address_bytes = target_address.to_be_bytes();
-    let (pad, target_address) = target_address_bytes.split_last_chunk::<20>().unwrap();
-    if !pad.iter().all(|i| *i == 0) {
-        return InstructionResult::InvalidEXTCALLTarget;
-    }
-    let target_address = Address::new(*target_address);
-
-    let in_len = try_into_usize!(in_len);
-    let input = if in_len != 0 {
-        let in_offset = try_into_usize!(in_offset);
-        ensure_memory!(ecx, in_offset, in_len);
-        Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
-    } else {
-        Bytes::new()
-    };
-
-    let transfers_value = value != U256::ZERO;
-    if ecx.is_static && transfers_value {
-        return InstructionResult::CallNotAllowedInsideStatic;
-    }
+    catch_panic!(ecx, "ext_call", {
+        let (target_address, in_offset, in_len, value) = if call_kind == ExtCallKind::Call {
+            let rev![target_address, in_offset, in_len, value] = &mut *sp.cast::<[EvmWord; 4]>();
+            (target_address, in_offset, in_len, value.to_u256())
+        } else {
+            let rev![target_address, in_offset, in_len] = &mut *sp.cast::<[EvmWord; 3]>();
+            (target_address, in_offset, in_len, U256::ZERO)
+        };
+
+        let target_address_bytes = target_address.to_be_bytes();
+        let (pad, target_address) = target_address_bytes.split_last_chunk::<20>().unwrap();
+        if !pad.iter().all(|i| *i == 0) {
+            return InstructionResult::InvalidEXTCALLTarget;
+        }
+        let target_address = Address::new(*target_address);
+
+        let in_len = try_into_usize!(in_len);
+        let input = if in_len != 0 {
+            let in_offset = try_into_usize!(in_offset);
+            ensure_memory!(ecx, in_offset, in_len);
+            Bytes::copy_from_slice(ecx.memory.slice(in_offset, in_len))
+        } else {
+            Bytes::new()
+        };
+
+        let transfers_value = value != U256::ZERO;
+        if ecx.is_static && transfers_value {
+            return InstructionResult::CallNotAllowedInsideStatic;
+        }
 
-    let Some(account_load) = ecx.host.load_account_delegated(target_address) else {
-        return InstructionResult::FatalExternalError;
-    };
-    let call_cost = gas::call_cost(spec_id, transfers_value, account_load);
-    gas!(ecx, call_cost);
-
-    let gas_reduce = core::cmp::max(ecx.gas.remaining() / 64, 5000);
-    let gas_limit = ecx.gas.remaining().saturating_sub(gas_reduce);
-    if gas_limit < gas::MIN_CALLEE_GAS {
-        ecx.return_data = &[];
-        return EXTCALL_LIGHT_FAILURE;
-    }
-    gas!(ecx, gas_limit);
-
-    // Call host to interact with target contract
-    *ecx.next_action = InterpreterAction::Call {
-        inputs: Box::new(CallInputs {
-            input,
-            gas_limit,
-            target_address: if call_kind == ExtCallKind::DelegateCall {
-                ecx.contract.target_address
-            } else {
-                target_address
-            },
-            caller: if call_kind == ExtCallKind::DelegateCall {
-                ecx.contract.caller
-            } else {
-                ecx.contract.target_address
-            },
-            bytecode_address: target_address,
-            value: if call_kind == ExtCallKind::DelegateCall {
-                CallValue::Apparent(ecx.contract.call_value)
-            } else {
-                CallValue::Transfer(value)
-            },
-            scheme: call_kind.into(),
-            is_static: ecx.is_static || call_kind == ExtCallKind::StaticCall,
-            is_eof: true,
-            return_memory_offset: 0..0,
-        }),
-    };
-    InstructionResult::Continue
+        let account_load = try_host!(ecx.host.load_account_delegated(target_address));
+        let call_cost = gas_params(ecx).call_cost(spec_id, transfers_value, account_load);
+        host_gas!(ecx, LoadAccountDelegated, call_cost);
+
+        let gas_reduce = core::cmp::max(ecx.gas.remaining() / 64, 5000);
+        let gas_limit = ecx.gas.remaining().saturating_sub(gas_reduce);
+        if gas_limit < gas::MIN_CALLEE_GAS {
+            ecx.return_data = &[];
+            return EXTCALL_LIGHT_FAILURE;
+        }
+        gas!(ecx, gas_limit);
+
+        // Call host to interact with target contract
+        *ecx.next_action = InterpreterAction::Call {
+            inputs: Box::new(CallInputs {
+                input,
+                gas_limit,
+                target_address: if call_kind == ExtCallKind::DelegateCall {
+                    ecx.contract.target_address
+                } else {
+                    target_address
+                },
+                caller: if call_kind == ExtCallKind::DelegateCall {
+                    ecx.contract.caller
+                } else {
+                    ecx.contract.target_address
+                },
+                bytecode_address: target_address,
+                value: if call_kind == ExtCallKind::DelegateCall {
+                    CallValue::Apparent(ecx.contract.call_value)
+                } else {
+                    CallValue::Transfer(value)
+                },
+                scheme: call_kind.into(),
+                is_static: ecx.is_static || call_kind == ExtCallKind::StaticCall,
+                is_eof: true,
+                return_memory_offset: 0..0,
+            }),
+        };
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -842,17 +969,20 @@ pub unsafe extern "C" fn __revmc_builtin_do_return(
     rev![offset, len]: &mut [EvmWord; 2],
     result: InstructionResult,
 ) -> InstructionResult {
-    let len = try_into_usize!(len);
-    let output = if len != 0 {
-        let offset = try_into_usize!(offset);
-        ensure_memory!(ecx, offset, len);
-        ecx.memory.slice(offset, len).to_vec().into()
-    } else {
-        Bytes::new()
-    };
-    *ecx.next_action =
-        InterpreterAction::Return { result: InterpreterResult { output, gas: *ecx.gas, result } };
-    InstructionResult::Continue
+    catch_panic!(ecx, "do_return", {
+        let len = try_into_usize!(len);
+        let output = if len != 0 {
+            let offset = try_into_usize!(offset);
+            ensure_memory!(ecx, offset, len);
+            ecx.memory.slice(offset, len).to_vec().into()
+        } else {
+            Bytes::new()
+        };
+        *ecx.next_action = InterpreterAction::Return {
+            result: InterpreterResult { output, gas: *ecx.gas, result },
+        };
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -861,17 +991,18 @@ pub unsafe extern "C" fn __revmc_builtin_selfdestruct(
     target: &mut EvmWord,
     spec_id: SpecId,
 ) -> InstructionResult {
-    ensure_non_staticcall!(ecx);
-
-    let res = try_host!(ecx.host.selfdestruct(ecx.contract.target_address, target.to_address()));
+    catch_panic!(ecx, "selfdestruct", {
+        let res =
+            try_host!(ecx.host.selfdestruct(ecx.contract.target_address, target.to_address()));
 
-    // EIP-3529: Reduction in refunds
-    if !spec_id.is_enabled_in(SpecId::LONDON) && !res.previously_destroyed {
-        ecx.gas.record_refund(gas::SELFDESTRUCT);
-    }
-    gas!(ecx, gas::selfdestruct_cost(spec_id, res));
+        // EIP-3529: Reduction in refunds
+        if !spec_id.is_enabled_in(SpecId::LONDON) && !res.previously_destroyed {
+            ecx.gas.record_refund(gas::SELFDESTRUCT);
+        }
+        host_gas!(ecx, Selfdestruct, gas_params(ecx).selfdestruct_cost(spec_id, res));
 
-    InstructionResult::Continue
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -880,11 +1011,13 @@ pub unsafe extern "C" fn __revmc_builtin_func_stack_push(
     pc: usize,
     new_idx: usize,
 ) -> InstructionResult {
-    if ecx.func_stack.return_stack_len() >= 1024 {
-        return InstructionResult::EOFFunctionStackOverflow;
-    }
-    ecx.func_stack.push(pc, new_idx);
-    InstructionResult::Continue
+    catch_panic!(ecx, "func_stack_push", {
+        if ecx.func_stack.return_stack_len() >= 1024 {
+            return InstructionResult::EOFFunctionStackOverflow;
+        }
+        ecx.func_stack.push(pc, new_idx);
+        InstructionResult::Continue
+    })
 }
 
 #[no_mangle]
@@ -902,5 +1035,26 @@ pub unsafe extern "C" fn __revmc_builtin_resize_memory(
     ecx: &mut EvmContext<'_>,
     new_size: usize,
 ) -> InstructionResult {
-    resize_memory(ecx, new_size)
+    catch_panic!(ecx, "resize_memory", { resize_memory(ecx, new_size) })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __revmc_builtin_result_sink(
+    ecx: &mut EvmContext<'_>,
+    pc: u32,
+    result: InstructionResult,
+) {
+    if let Some(sink) = &mut ecx.result_sink {
+        // The function is already failing, so a panic is only recorded.
+        if let Err(payload) = catch_unwind(|| sink(pc, result)) {
+            ecx.set_host_panic("result_sink", payload);
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __revmc_builtin_profile_block(ecx: &mut EvmContext<'_>, pc: u32) {
+    if let Some(profile) = &mut ecx.block_profile {
+        profile.record(pc);
+    }
 }
@@ -8,20 +8,46 @@ macro_rules! tri {
     };
 }
 
-macro_rules! try_opt {
-    ($e:expr) => {
-        match $e {
-            Some(x) => x,
-            None => return InstructionResult::InvalidOperandOOG,
+/// Calls a host method, returning `FatalExternalError` if it panics.
+macro_rules! host {
+    ($ecx:ident.host.$f:ident($($args:tt)*)) => {
+        match catch_unwind(|| $ecx.host.$f($($args)*)) {
+            Ok(x) => x,
+            Err(payload) => {
+                log_host_failure!($ecx, $f);
+                $ecx.set_host_panic(stringify!($f), payload);
+                return InstructionResult::FatalExternalError;
+            }
+        }
+    };
+}
+
+/// Runs the body of a fallible builtin, returning `FatalExternalError` if it panics.
+///
+/// Panics in host methods are caught by `host!` first, so that they are attributed to the method
+/// rather than to the builtin.
+macro_rules! catch_panic {
+    ($ecx:ident, $name:literal, $body:block) => {
+        match catch_unwind(|| -> InstructionResult { $body }) {
+            Ok(result) => result,
+            Err(payload) => {
+                $ecx.set_host_panic($name, payload);
+                InstructionResult::FatalExternalError
+            }
         }
     };
 }
 
+/// Calls a fallible host method, returning `FatalExternalError` if it fails or panics.
 macro_rules! try_host {
-    ($e:expr) => {
-        match $e {
+    ($ecx:ident.host.$f:ident($($args:tt)*)) => {
+        match host!($ecx.host.$f($($args)*)) {
             Some(x) => x,
-            None => return InstructionResult::FatalExternalError,
+            None => {
+                log_host_failure!($ecx, $f);
+                $ecx.set_host_error(stringify!($f));
+                return InstructionResult::FatalExternalError;
+            }
         }
     };
 }
@@ -37,7 +63,7 @@ macro_rules! try_ir {
 
 macro_rules! gas {
     ($ecx:expr, $gas:expr) => {
-        if !$ecx.gas.record_cost($gas) {
+        if !$ecx.spend_gas($gas) {
             return InstructionResult::OutOfGas;
         }
     };
@@ -52,10 +78,29 @@ macro_rules! gas_opt {
     };
 }
 
-macro_rules! ensure_non_staticcall {
-    ($ecx:expr) => {
-        if $ecx.is_static {
-            return InstructionResult::StateChangeDuringStaticCall;
+/// Charges the dynamic gas of a host call, and logs the call.
+macro_rules! host_gas {
+    ($ecx:expr, $kind:ident, $gas:expr) => {{
+        let gas = $gas;
+        if !$ecx.spend_gas(gas) {
+            let result = InstructionResult::OutOfGas;
+            $ecx.log_host_call_result(HostCallKind::$kind, gas, result);
+            return result;
+        }
+        $ecx.log_host_call(HostCallKind::$kind, gas);
+    }};
+}
+
+/// Logs a failed host call. `env` is not logged.
+macro_rules! log_host_failure {
+    ($ecx:ident, env) => {};
+    ($ecx:ident, $f:ident) => {
+        paste::paste! {
+            $ecx.log_host_call_result(
+                HostCallKind::[<$f:camel>],
+                0,
+                InstructionResult::FatalExternalError,
+            )
         }
     };
 }
@@ -1,6 +1,5 @@
-use crate::gas;
-use revm_interpreter::{as_usize_saturated, Gas, InstructionResult, SharedMemory};
-use revmc_context::{EvmContext, EvmWord};
+use revm_interpreter::{as_usize_saturated, num_words, Gas, InstructionResult, SharedMemory};
+use revmc_context::{EvmContext, EvmWord, GasMode, GasParams};
 
 /// Splits the stack pointer into `N` elements by casting it to an array.
 ///
@@ -16,43 +15,92 @@ pub(crate) unsafe fn read_words_rev<'a, const N: usize>(sp: *mut EvmWord) -> &'a
     &mut *sp.cast::<[EvmWord; N]>()
 }
 
+/// The payload of a caught panic.
+#[cfg(feature = "std")]
+pub(crate) type PanicPayload = alloc::boxed::Box<dyn core::any::Any + Send>;
+/// Panics cannot be caught without `std`.
+#[cfg(not(feature = "std"))]
+pub(crate) type PanicPayload = core::convert::Infallible;
+
+/// Calls `f`, catching any panic.
+///
+/// Builtins are called from the compiled code, so they must never unwind. Panics can only be
+/// caught with `std`, otherwise this calls `f` directly, and a panic aborts the process.
+#[inline(always)]
+pub(crate) fn catch_unwind<R>(f: impl FnOnce() -> R) -> Result<R, PanicPayload> {
+    #[cfg(feature = "std")]
+    return std::panic::catch_unwind(core::panic::AssertUnwindSafe(f));
+    #[cfg(not(feature = "std"))]
+    return Ok(f());
+}
+
+/// Returns the dynamic gas cost parameters of the context.
+#[inline(always)]
+pub(crate) fn gas_params<'a>(ecx: &EvmContext<'a>) -> &'a GasParams {
+    ecx.gas_params.unwrap_or(&GasParams::DEFAULT)
+}
+
+/// The maximum size in bytes that memory can be expanded to.
+///
+/// Expanding to `2^32` bytes costs more than `2^45` gas with the default parameters, so any
+/// expansion past it is out of gas in practice. This also keeps the size computations from
+/// overflowing, and bounds the allocation in [`GasMode::ObserveOnly`] mode.
+const MAX_MEMORY_SIZE: usize = u32::MAX as usize;
+
 #[inline]
 pub(crate) fn ensure_memory(
     ecx: &mut EvmContext<'_>,
     offset: usize,
     len: usize,
 ) -> InstructionResult {
-    ensure_memory_inner(ecx.memory, ecx.gas, offset, len)
+    let params = gas_params(ecx);
+    ensure_memory_inner(ecx.memory, ecx.gas, ecx.gas_mode, params, offset, len)
 }
 
 #[inline]
 pub(crate) fn ensure_memory_inner(
     memory: &mut SharedMemory,
     gas: &mut Gas,
+    gas_mode: GasMode,
+    params: &GasParams,
     offset: usize,
     len: usize,
 ) -> InstructionResult {
     let new_size = offset.saturating_add(len);
     if new_size > memory.len() {
-        return resize_memory_inner(memory, gas, new_size);
+        return resize_memory_inner(memory, gas, gas_mode, params, new_size);
     }
     InstructionResult::Continue
 }
 
 #[inline]
 pub(crate) fn resize_memory(ecx: &mut EvmContext<'_>, new_size: usize) -> InstructionResult {
-    resize_memory_inner(ecx.memory, ecx.gas, new_size)
+    let params = gas_params(ecx);
+    resize_memory_inner(ecx.memory, ecx.gas, ecx.gas_mode, params, new_size)
 }
 
 fn resize_memory_inner(
     memory: &mut SharedMemory,
     gas: &mut Gas,
+    gas_mode: GasMode,
+    params: &GasParams,
     new_size: usize,
 ) -> InstructionResult {
     // TODO: Memory limit
-    if !revm_interpreter::interpreter::resize_memory(memory, gas, new_size) {
+    // Same as `revm_interpreter::interpreter::resize_memory`, but with custom gas parameters.
+    if new_size > MAX_MEMORY_SIZE {
         return InstructionResult::MemoryOOG;
     }
+    let new_words = num_words(new_size as u64);
+    let Some(new_len) = (new_words as usize).checked_mul(32) else {
+        return InstructionResult::MemoryOOG;
+    };
+    let new_cost = params.memory_gas(new_words);
+    let current_cost = params.memory_gas(num_words(memory.len() as u64));
+    if !gas_mode.record_cost(gas, new_cost.saturating_sub(current_cost)) {
+        return InstructionResult::MemoryOOG;
+    }
+    memory.resize(new_len);
     InstructionResult::Continue
 }
 
@@ -63,7 +111,7 @@ pub(crate) unsafe fn copy_operation(
 ) -> InstructionResult {
     let len = try_into_usize!(len);
     if len != 0 {
-        gas_opt!(ecx, gas::dyn_verylowcopy_cost(len as u64));
+        gas_opt!(ecx, gas_params(ecx).copy_cost(len as u64));
         let memory_offset = try_into_usize!(memory_offset);
         ensure_memory!(ecx, memory_offset, len);
         let data_offset = data_offset.to_u256();
@@ -77,3 +125,36 @@ pub(crate) unsafe fn copy_operation(
 pub(crate) const unsafe fn decouple_lt<'b, T: ?Sized>(x: &T) -> &'b T {
     core::mem::transmute(x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_memory_limit() {
+        let params = &GasParams::DEFAULT;
+        for gas_mode in [GasMode::Metered, GasMode::ObserveOnly] {
+            for (offset, len) in
+                [(usize::MAX, 32), (u32::MAX as usize, u32::MAX as usize), (0, MAX_MEMORY_SIZE + 1)]
+            {
+                let mut memory = SharedMemory::new();
+                let mut gas = Gas::new(u64::MAX);
+                let r = ensure_memory_inner(&mut memory, &mut gas, gas_mode, params, offset, len);
+                assert_eq!(r, InstructionResult::MemoryOOG, "{gas_mode:?} {offset} {len}");
+                assert!(memory.is_empty());
+                assert_eq!(gas.spent(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn resize_memory_rounds_up() {
+        let params = &GasParams::DEFAULT;
+        let mut memory = SharedMemory::new();
+        let mut gas = Gas::new(u64::MAX);
+        let r = ensure_memory_inner(&mut memory, &mut gas, GasMode::Metered, params, 31, 2);
+        assert_eq!(r, InstructionResult::Continue);
+        assert_eq!(memory.len(), 64);
+        assert_eq!(gas.spent(), params.memory_gas(2));
+    }
+}
@@ -3,6 +3,7 @@
 use tester as test;
 
 use std::{
+    ffi::OsStr,
     fs,
     path::{Path, PathBuf},
     process::Command,
@@ -72,6 +73,19 @@ fn make_tests(cmd: &'static Path, tests: &mut Vec<TestDescAndFn>) {
             testfn: TestFn::DynTestFn(Box::new(move || run_test(&config, &path))),
         });
     }
+
+    if cfg!(target_os = "linux") {
+        tests.push(TestDescAndFn {
+            desc: TestDesc {
+                name: TestName::StaticTestName("aot/fibonacci-calldata"),
+                allow_fail: false,
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                test_type: TestType::Unknown,
+            },
+            testfn: TestFn::DynTestFn(Box::new(move || run_aot_test(&config))),
+        });
+    }
 }
 
 fn collect_tests(root: &Path) -> impl Iterator<Item = DirEntry> {
@@ -124,6 +138,43 @@ fn run_test(config: &Config, path: &Path) {
     );
 }
 
+/// Builds a shared library with `aot build` and runs it with `aot run`, checking that the result
+/// matches the JIT-compiled run of the same bytecode.
+fn run_aot_test(config: &Config) {
+    const BENCH: &str = "fibonacci-calldata";
+    // fib(11) = 89 = 0x59.
+    const CALLDATA: &str = "0x000000000000000000000000000000000000000000000000000000000000000a";
+    const OUTPUT: &str = "0x0000000000000000000000000000000000000000000000000000000000000059";
+
+    let run = |args: &[&OsStr]| {
+        let output = Command::new(config.cmd).args(args).output().expect("failed to run test");
+        assert!(
+            output.status.success(),
+            "{args:?} failed with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let lib = config.build_base.join("aot").join(format!("{BENCH}.so"));
+    let _ = fs::remove_file(&lib);
+    run(&["aot".as_ref(), "build".as_ref(), BENCH.as_ref(), "-o".as_ref(), lib.as_ref()]);
+    assert!(lib.exists(), "no library produced");
+
+    let aot = run(&[
+        "aot".as_ref(),
+        "run".as_ref(),
+        lib.as_ref(),
+        "--calldata".as_ref(),
+        CALLDATA.as_ref(),
+    ]);
+    let jit = run(&[BENCH.as_ref(), "--calldata".as_ref(), CALLDATA.as_ref()]);
+    assert!(aot.starts_with(&jit), "AOT and JIT results differ:\n{aot}\n{jit}");
+    assert!(aot.contains("InstructionResult::Return"), "{aot}");
+    assert!(aot.contains(&format!("Output: {OUTPUT}")), "{aot}");
+}
+
 struct Config {
     cmd: &'static Path,
     root: &'static Path,
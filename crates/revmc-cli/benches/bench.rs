@@ -4,7 +4,7 @@ use criterion::{
     criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion,
 };
 use revm_interpreter::SharedMemory;
-use revm_primitives::{Env, SpecId};
+use revm_primitives::{Env, SpecId, EOF_MAGIC_BYTES};
 use revmc::{llvm, EvmCompiler, EvmCompilerFn, EvmContext, EvmLlvmBackend, EvmStack};
 use revmc_cli::Bench;
 use std::time::Duration;
@@ -77,16 +77,54 @@ fn run_bench(c: &mut Criterion, bench: &Bench) {
         // ("no_stack", (true, false)),
         // ("no_gas_no_stack", (false, false)),
     ];
-    let jit_ids = jit_matrix.map(|(name, (gas, stack))| {
-        compiler.gas_metering(gas);
-        unsafe { compiler.stack_bound_checks(stack) };
-        (name, compiler.translate(name, bytecode, SPEC_ID).expect(name))
-    });
+    let mut jit_ids = jit_matrix
+        .map(|(name, (gas, stack))| {
+            compiler.gas_metering(gas);
+            unsafe { compiler.stack_bound_checks(stack) };
+            (name, compiler.translate(name, bytecode, SPEC_ID).expect(name))
+        })
+        .to_vec();
+    // Specialize the default configuration for the benchmarked selector, if any.
+    if let Some(&selector) = calldata.first_chunk::<4>() {
+        if !bytecode.starts_with(&EOF_MAGIC_BYTES) {
+            let name = "specialized";
+            compiler.gas_metering(true);
+            unsafe { compiler.stack_bound_checks(true) };
+            let id = compiler
+                .specialize_for_selector(name, "default", bytecode, SPEC_ID, selector)
+                .expect(name);
+            jit_ids.push((name, id));
+        }
+    }
+    // Same as "no_gas", but without the gas parameter.
+    compiler.gas_metering(false);
+    unsafe { compiler.stack_bound_checks(true) };
+    compiler.no_gas_signature(Some(gas_limit));
+    let no_gas_param_id = compiler.translate("no_gas_param", bytecode, SPEC_ID).unwrap();
+    compiler.no_gas_signature(None);
+
     for &(name, fn_id) in &jit_ids {
         let jit = unsafe { compiler.jit_function(fn_id) }.expect(name);
         g.bench_function(format!("revmc/{name}"), |b| b.iter(|| call_jit(jit)));
     }
 
+    let jit = unsafe { compiler.jit_function_no_gas(no_gas_param_id) }.unwrap();
+    g.bench_function("revmc/no_gas_param", |b| {
+        b.iter(|| {
+            for (i, input) in stack_input.iter().enumerate() {
+                stack.as_mut_slice()[i] = input.into();
+            }
+            let mut stack_len = stack_input.len();
+
+            let mut interpreter =
+                revm_interpreter::Interpreter::new(contract.clone(), gas_limit, false);
+            host.clear();
+            let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+
+            unsafe { jit.call(Some(&mut stack), Some(&mut stack_len), &mut ecx) }
+        })
+    });
+
     g.bench_function("revm-interpreter", |b| {
         b.iter(|| {
             let mut int = revm_interpreter::Interpreter::new(contract.clone(), gas_limit, false);
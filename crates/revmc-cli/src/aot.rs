@@ -0,0 +1,143 @@
+use revm_primitives::{hex, SpecId, U256};
+use revmc::eyre::{bail, eyre, OptionExt, Result, WrapErr};
+use serde_json::{json, Value};
+use std::{
+    ffi::{c_char, CStr},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+/// The name of the symbol that holds the [`AotMetadata`] of a library built with `aot build`.
+pub const AOT_METADATA_SYMBOL: &str = "__revmc_aot_metadata";
+
+/// The prefix of the builtin symbols that compiled code calls into.
+///
+/// Must be kept in sync with `revmc-builtins`.
+const BUILTIN_PREFIX: &str = "__revmc_builtin_";
+
+/// What `aot run` needs to know to call a function compiled with `aot build`.
+///
+/// Embedded in the shared library as a NUL-terminated JSON string in [`AOT_METADATA_SYMBOL`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AotMetadata {
+    /// The symbol name of the compiled function.
+    pub name: String,
+    /// The spec that the function was compiled for.
+    pub spec_id: SpecId,
+    /// The compiled bytecode, which is also the code of the contract that it is called with.
+    pub bytecode: Vec<u8>,
+    /// The initial stack, from the bottom.
+    pub stack_input: Vec<U256>,
+}
+
+impl AotMetadata {
+    /// Serializes the metadata to JSON.
+    pub fn to_json(&self) -> String {
+        json!({
+            "name": self.name,
+            "spec_id": self.spec_id as u8,
+            "bytecode": hex::encode_prefixed(&self.bytecode),
+            "stack_input": self.stack_input,
+        })
+        .to_string()
+    }
+
+    /// Deserializes the metadata from JSON created with [`to_json`](Self::to_json).
+    pub fn from_json(s: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(s)?;
+        let field = |name: &str| value.get(name).ok_or_else(|| eyre!("missing field `{name}`"));
+        let name = field("name")?.as_str().ok_or_eyre("`name` is not a string")?.to_string();
+        let spec_id = field("spec_id")?
+            .as_u64()
+            .and_then(|id| SpecId::try_from_u8(id.try_into().ok()?))
+            .ok_or_eyre("`spec_id` is not a valid spec")?;
+        let bytecode = hex::decode(field("bytecode")?.as_str().unwrap_or_default())
+            .wrap_err("`bytecode` is not valid hex")?;
+        let stack_input = serde_json::from_value(field("stack_input")?.clone())?;
+        Ok(Self { name, spec_id, bytecode, stack_input })
+    }
+
+    /// Returns the C source that defines [`AOT_METADATA_SYMBOL`], to be compiled and linked
+    /// together with the object file of the function.
+    pub fn to_c_source(&self) -> String {
+        let mut s = String::from(
+            "#ifdef _WIN32\n__declspec(dllexport)\n#else\n__attribute__((used, visibility(\"default\")))\n#endif\n",
+        );
+        let _ = write!(s, "const char {AOT_METADATA_SYMBOL}[] = {{");
+        for b in self.to_json().bytes().chain([0]) {
+            let _ = write!(s, "{b},");
+        }
+        s.push_str("};\n");
+        s
+    }
+
+    /// Reads the metadata embedded in the given library.
+    ///
+    /// # Safety
+    ///
+    /// If the library defines [`AOT_METADATA_SYMBOL`], it must be a NUL-terminated string.
+    pub unsafe fn from_library(lib: &libloading::Library) -> Result<Self> {
+        let ptr = unsafe { lib.get::<*const c_char>(AOT_METADATA_SYMBOL.as_bytes()) }
+            .wrap_err("the library has no AOT metadata; was it built with `aot build`?")?;
+        let s = unsafe { CStr::from_ptr(*ptr) }.to_str()?;
+        Self::from_json(s).wrap_err("invalid AOT metadata")
+    }
+}
+
+/// Returns `path` with the platform's shared library extension if it has none.
+pub fn shared_library_path(path: &Path) -> PathBuf {
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(std::env::consts::DLL_EXTENSION)
+    }
+}
+
+/// Loads a shared library built with `aot build`.
+///
+/// All of its symbols are resolved immediately where supported, so that builtins that are neither
+/// linked into the library nor exported by the current executable are reported here instead of
+/// crashing the process on their first call.
+pub fn load_library(path: &Path) -> Result<libloading::Library> {
+    #[cfg(unix)]
+    let lib = {
+        use libloading::os::unix::{Library, RTLD_LOCAL, RTLD_NOW};
+        unsafe { Library::open(Some(path), RTLD_NOW | RTLD_LOCAL) }.map(Into::into)
+    };
+    #[cfg(not(unix))]
+    let lib = unsafe { libloading::Library::new(path) };
+    match lib {
+        Ok(lib) => Ok(lib),
+        Err(e) if e.to_string().contains(BUILTIN_PREFIX) => bail!(
+            "{e}\n\nthe library calls revmc builtins that are not available in this process; \
+             link them into the library with `aot build --builtins <PATH>`, or load it from an \
+             executable built with `revmc_build::emit()`"
+        ),
+        Err(e) => Err(e).wrap_err_with(|| format!("failed to load {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_json() {
+        let metadata = AotMetadata {
+            name: "fibonacci".into(),
+            spec_id: SpecId::CANCUN,
+            bytecode: vec![0x5f, 0x00],
+            stack_input: vec![U256::from(69)],
+        };
+        assert_eq!(AotMetadata::from_json(&metadata.to_json()).unwrap(), metadata);
+        assert!(AotMetadata::from_json("{}").is_err());
+        assert!(metadata.to_c_source().contains(AOT_METADATA_SYMBOL));
+    }
+
+    #[test]
+    fn library_path() {
+        let ext = std::env::consts::DLL_EXTENSION;
+        assert_eq!(shared_library_path(Path::new("a")), Path::new("a").with_extension(ext));
+        assert_eq!(shared_library_path(Path::new("a.so")), Path::new("a.so"));
+    }
+}
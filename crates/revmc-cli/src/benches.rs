@@ -37,6 +37,12 @@ pub fn get_benches() -> Vec<Bench> {
         // https://github.com/lambdaclass/evm_mlir/blob/b766d0bbc2093bbfa4feb3aa25baf82b512aee74/bench/revm_comparison/src/lib.rs#L12-L15
         // https://blog.lambdaclass.com/evm-performance-boosts-with-mlir/
         // > We chose 1000 as N
+        Bench {
+            name: "stack_shuffle",
+            bytecode: STACK_SHUFFLE.to_vec(),
+            stack_input: vec![U256::from(1000)],
+            ..Default::default()
+        },
         Bench {
             name: "fibonacci-calldata",
             bytecode: hex!(
@@ -164,6 +170,40 @@ pub fn get_benches() -> Vec<Bench> {
     ]
 }
 
+#[rustfmt::skip]
+const STACK_SHUFFLE: &[u8] = &[
+    // input to the program (number of iterations)
+    op::PUSH1, 1,
+    op::PUSH1, 2,
+    op::PUSH1, 3,
+
+    // LOOP:
+    op::JUMPDEST,
+    op::DUP4,
+    op::ISZERO,
+    op::PUSH1, 45, // end
+    op::JUMPI,
+
+    // shuffle, stack height is unchanged
+    op::DUP1, op::SWAP2, op::DUP3, op::SWAP3, op::POP, op::POP,
+    op::DUP1, op::SWAP2, op::DUP3, op::SWAP3, op::POP, op::POP,
+    op::DUP1, op::SWAP2, op::DUP3, op::SWAP3, op::POP, op::POP,
+    op::DUP1, op::SWAP2, op::DUP3, op::SWAP3, op::POP, op::POP,
+
+    // decrement counter
+    op::SWAP3,
+    op::PUSH1, 1,
+    op::SWAP1,
+    op::SUB,
+    op::SWAP3,
+    op::PUSH1, 6, // goto LOOP
+    op::JUMP,
+
+    // end:
+    op::JUMPDEST,
+    op::STOP,
+];
+
 #[rustfmt::skip]
 const FIBONACCI: &[u8] = &[
     // input to the program (which fib number we want)
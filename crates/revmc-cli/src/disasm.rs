@@ -0,0 +1,96 @@
+use revmc::{
+    eyre::{eyre, Result},
+    primitives::hex,
+    Bytecode, InstInfo, Opcode,
+};
+use std::fmt::Write;
+
+/// Formats the compiler's view of the given analyzed bytecode.
+///
+/// If `unoptimized` is the same bytecode analyzed without optimizations, instructions that were
+/// rewritten by the optimization passes are marked. If `pc` is set, only the section containing
+/// the instruction at that program counter is printed.
+pub fn disasm(
+    bytecode: &Bytecode<'_>,
+    unoptimized: Option<&Bytecode<'_>>,
+    pc: Option<usize>,
+    json: bool,
+) -> Result<String> {
+    let mut insts = bytecode.inst_infos().collect::<Vec<_>>();
+    if let Some(pc) = pc {
+        let inst = bytecode
+            .inst_infos()
+            .filter(|inst| !inst.is_synthetic() && inst.pc() <= pc)
+            .last()
+            .ok_or_else(|| eyre!("no instruction at pc {pc}"))?
+            .index();
+        let start = insts[..=inst].iter().rposition(|inst| inst.section().is_some()).unwrap_or(0);
+        let end = insts[inst + 1..]
+            .iter()
+            .position(|inst| inst.section().is_some())
+            .map_or(insts.len(), |len| inst + 1 + len);
+        insts.truncate(end);
+        insts.drain(..start);
+    }
+
+    let rewritten = |inst: &InstInfo<'_, '_>| {
+        unoptimized.is_some_and(|unoptimized| {
+            unoptimized.inst_infos().nth(inst.index()).map_or(true, |u| {
+                !u.flag_names().eq(inst.flag_names()) || u.jump_target() != inst.jump_target()
+            })
+        })
+    };
+
+    if json {
+        let insts = insts.iter().map(|inst| {
+            let opcode = inst.opcode();
+            let (inputs, outputs) = inst.stack_io();
+            serde_json::json!({
+                "ic": inst.index(),
+                "pc": inst.pc(),
+                "opcode": Opcode { immediate: None, ..opcode }.to_string(),
+                "immediate": opcode.immediate.map(hex::encode_prefixed),
+                "base_gas": inst.base_gas(),
+                "inputs": inputs,
+                "outputs": outputs,
+                "flags": inst.flag_names().collect::<Vec<_>>(),
+                "jump_target": inst.jump_target(),
+                "section": inst.section().map(|section| serde_json::json!({
+                    "gas_cost": section.gas_cost,
+                    "inputs": section.inputs,
+                    "max_growth": section.max_growth,
+                })),
+                "synthetic": inst.is_synthetic(),
+                "rewritten": rewritten(inst),
+            })
+        });
+        return Ok(serde_json::to_string_pretty(&insts.collect::<Vec<_>>())?);
+    }
+
+    let mut s = String::new();
+    for inst in &insts {
+        if let Some(section) = inst.section() {
+            writeln!(
+                s,
+                "; section: gas {}, inputs {}, max growth {}",
+                section.gas_cost, section.inputs, section.max_growth,
+            )?;
+        }
+        let pc =
+            if inst.is_synthetic() { format!("({})", inst.pc()) } else { inst.pc().to_string() };
+        let marker = if rewritten(inst) { '*' } else { ' ' };
+        let (inputs, outputs) = inst.stack_io();
+        let flags = inst.flag_names().collect::<Vec<_>>().join(" | ");
+        write!(
+            s,
+            "{marker}{:>6} {pc:>8}  {:<40} {inputs}->{outputs}  {flags}",
+            inst.index(),
+            inst.opcode().to_string(),
+        )?;
+        if let Some(target) = inst.jump_target() {
+            write!(s, " -> {target}")?;
+        }
+        writeln!(s)?;
+    }
+    Ok(s)
+}
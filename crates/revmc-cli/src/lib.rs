@@ -8,9 +8,18 @@ use revmc::{
 };
 use std::{cmp::Ordering, path::Path, str::FromStr};
 
+mod aot;
+pub use aot::*;
+
 mod benches;
 pub use benches::*;
 
+mod disasm;
+pub use disasm::disasm;
+
+mod memory_host;
+pub use memory_host::{MemoryAccount, MemoryHost};
+
 pub fn read_code(code: Option<&str>, code_path: Option<&Path>) -> Result<Vec<u8>> {
     if let Some(code) = code {
         return read_code_string(code.trim().as_bytes(), None);
@@ -1,20 +1,39 @@
 #![allow(missing_docs)]
 
-use clap::{Parser, ValueEnum};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use color_eyre::{eyre::eyre, Result};
-use revm_interpreter::{opcode::make_instruction_table, SharedMemory};
-use revm_primitives::{address, spec_to_generic, Env, SpecId, TransactTo};
-use revmc::{eyre::ensure, EvmCompiler, EvmContext, EvmLlvmBackend, OptimizationLevel};
-use revmc_cli::{get_benches, read_code, Bench};
+use revm_interpreter::{
+    opcode::make_instruction_table, Contract, InstructionResult, Interpreter, InterpreterAction,
+    SharedMemory,
+};
+use revm_primitives::{address, hex, spec_to_generic, Bytes, Env, SpecId, TransactTo, U256};
+use revmc::{
+    eyre::{ensure, WrapErr},
+    CompileError, EvmCompiler, EvmCompilerFn, EvmContext, EvmLlvmBackend, ExecutionProfile,
+    HostExt, OptimizationLevel, RawEvmCompilerFn, RecordingHost, ReplayHost,
+};
+use revmc_cli::{disasm, get_benches, read_code, AotMetadata, Bench, MemoryHost};
 use std::{
     hint::black_box,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Increase logging verbosity: `-v` for debug, `-vv` for trace.
+    ///
+    /// Ignored if `RUST_LOG` is set.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
     /// Benchmark name, "custom", path to a file, or a symbol to load from a shared object.
-    bench_name: String,
+    #[arg(required = true)]
+    bench_name: Option<String>,
     #[arg(default_value = "1")]
     n_iters: u64,
 
@@ -43,6 +62,29 @@ struct Cli {
     #[arg(long, conflicts_with = "aot")]
     interpret: bool,
 
+    /// Run once, recording the host calls to the given JSON file.
+    #[arg(long, conflicts_with_all = ["interpret", "replay"])]
+    record: Option<PathBuf>,
+    /// Run once, replaying the host calls from the given JSON file created with `--record`.
+    #[arg(long, conflicts_with = "interpret")]
+    replay: Option<PathBuf>,
+
+    /// Load the initial accounts from the given JSON file, an object of accounts keyed by
+    /// address, each with optional `balance`, `nonce`, `code` and `storage` fields.
+    #[arg(long, conflicts_with = "replay")]
+    state: Option<PathBuf>,
+    /// Write the accounts after the last run to the given JSON file.
+    #[arg(long, conflicts_with = "replay")]
+    dump_state: Option<PathBuf>,
+
+    /// Count the executed basic blocks over all runs, writing the profile to the given JSON file.
+    #[arg(long, conflicts_with_all = ["interpret", "load", "record", "replay", "profile_in"])]
+    profile_out: Option<PathBuf>,
+    /// Optimize the code layout for the profile in the given JSON file created with
+    /// `--profile-out`.
+    #[arg(long)]
+    profile_in: Option<PathBuf>,
+
     /// Target triple.
     #[arg(long, default_value = "native")]
     target: String,
@@ -79,14 +121,156 @@ struct Cli {
     gas_limit: u64,
 }
 
-fn main() -> Result<()> {
+#[derive(Subcommand)]
+enum Command {
+    /// Print the bytecode as analyzed by the compiler.
+    Disasm(DisasmArgs),
+    /// Run Ethereum state test fixtures, comparing the compiled code with the interpreter.
+    Statetest(StatetestArgs),
+    /// Compile bytecode to a shared library ahead of time, or run such a library.
+    Aot(AotArgs),
+}
+
+#[derive(Args)]
+struct DisasmArgs {
+    #[arg(long)]
+    code: Option<String>,
+    #[arg(long, conflicts_with = "code")]
+    code_path: Option<PathBuf>,
+    #[arg(long, value_enum, default_value = "osaka")]
+    spec_id: SpecIdValueEnum,
+    /// Run the optimization passes, and mark the instructions they rewrote with `*`.
+    #[arg(long)]
+    optimize: bool,
+    /// Print JSON instead of text.
+    #[arg(long)]
+    json: bool,
+    /// Print only the section containing the instruction at this program counter.
+    #[arg(long)]
+    pc: Option<usize>,
+    /// Print the control flow graph in the Graphviz DOT format.
+    #[arg(long, conflicts_with_all = ["optimize", "json", "pc"])]
+    dot: bool,
+}
+
+#[derive(Args)]
+struct StatetestArgs {
+    /// Fixture files or directories to run.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    #[arg(short = 'O', long, default_value = "3")]
+    opt_level: OptimizationLevel,
+}
+
+#[derive(Args)]
+struct AotArgs {
+    #[command(subcommand)]
+    command: AotCommand,
+}
+
+#[derive(Subcommand)]
+enum AotCommand {
+    /// Compile and link bytecode to a shared library that can be run with `aot run`.
+    Build(AotBuildArgs),
+    /// Run a shared library built with `aot build`.
+    Run(AotRunArgs),
+}
+
+#[derive(Args)]
+struct AotBuildArgs {
+    /// Benchmark name or path to a file containing the bytecode.
+    bytecode: String,
+    /// Output path. The platform's shared library extension is added if it has none.
+    #[arg(short = 'o', long)]
+    out: PathBuf,
+    /// Symbol name of the compiled function. Defaults to the benchmark name or file stem.
+    #[arg(long)]
+    name: Option<String>,
+    #[arg(short = 'O', long, default_value = "3")]
+    opt_level: OptimizationLevel,
+    #[arg(long, value_enum, default_value = "osaka")]
+    spec_id: SpecIdValueEnum,
+    #[arg(long)]
+    no_gas: bool,
+    /// Target triple.
+    #[arg(long, default_value = "native")]
+    target: String,
+    /// Target CPU.
+    #[arg(long)]
+    target_cpu: Option<String>,
+    /// Target features.
+    #[arg(long)]
+    target_features: Option<String>,
+    /// C compiler to link with. Defaults to `CC`, or the first of `cc`, `clang` and `gcc` found.
+    #[arg(long)]
+    cc: Option<PathBuf>,
+    /// Linker for the C compiler to use. Defaults to `lld` if found.
+    #[arg(long)]
+    linker: Option<PathBuf>,
+    /// Static libraries or object files defining the revmc builtins, to link into the library.
+    ///
+    /// Without them, the builtins are resolved when the library is loaded, from an executable
+    /// built with `revmc_build::emit()` such as this one.
+    #[arg(long)]
+    builtins: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct AotRunArgs {
+    /// Path to the shared library.
+    library: PathBuf,
+    #[arg(long)]
+    calldata: Option<String>,
+    #[arg(long, default_value = "1000000000")]
+    gas_limit: u64,
+    /// Load the initial accounts from the given JSON file, like the `--state` of a JIT run.
+    #[arg(long)]
+    state: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
     if std::env::var_os("RUST_BACKTRACE").is_none() {
         std::env::set_var("RUST_BACKTRACE", "1");
     }
     let _ = color_eyre::install();
-    let _ = init_tracing_subscriber();
 
-    let cli = Cli::parse();
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+/// Returns the exit code for the given error.
+///
+/// Compilation errors have a distinct code for each kind, so that scripts can tell them apart.
+fn exit_code(err: &color_eyre::Report) -> u8 {
+    match err.downcast_ref::<CompileError>() {
+        Some(CompileError::UnsupportedOpcode { .. }) => 2,
+        Some(CompileError::InvalidBytecode(_)) => 3,
+        Some(CompileError::LimitExceeded(_)) => 4,
+        Some(CompileError::Backend(_)) => 5,
+        Some(CompileError::Io(_)) => 6,
+        _ => 1,
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let _ = init_tracing_subscriber(cli.verbose);
+    match cli.command {
+        Some(Command::Disasm(args)) => return disassemble(args),
+        Some(Command::Statetest(args)) => return statetest(args),
+        Some(Command::Aot(args)) => {
+            return match args.command {
+                AotCommand::Build(args) => aot_build(args),
+                AotCommand::Run(args) => aot_run(args),
+            }
+        }
+        None => {}
+    }
+    let bench_name = cli.bench_name.unwrap();
 
     // Build the compiler.
     let context = revmc::llvm::inkwell::context::Context::create();
@@ -100,14 +284,14 @@ fn main() -> Result<()> {
     compiler.debug_assertions(cli.debug_assertions);
     compiler.validate_eof(!cli.no_validate);
 
-    let Bench { name, bytecode, calldata, stack_input, native: _ } = if cli.bench_name == "custom" {
+    let Bench { name, bytecode, calldata, stack_input, native: _ } = if bench_name == "custom" {
         Bench {
             name: "custom",
             bytecode: read_code(cli.code.as_deref(), cli.code_path.as_deref())?,
             ..Default::default()
         }
-    } else if Path::new(&cli.bench_name).exists() {
-        let path = Path::new(&cli.bench_name);
+    } else if Path::new(&bench_name).exists() {
+        let path = Path::new(&bench_name);
         ensure!(path.is_file(), "argument must be a file");
         ensure!(cli.code.is_none(), "--code is not allowed with a file argument");
         ensure!(cli.code_path.is_none(), "--code-path is not allowed with a file argument");
@@ -117,17 +301,17 @@ fn main() -> Result<()> {
             ..Default::default()
         }
     } else {
-        match get_benches().into_iter().find(|b| b.name == cli.bench_name) {
+        match get_benches().into_iter().find(|b| b.name == bench_name) {
             Some(b) => b,
             None => {
                 if cli.load.is_some() {
                     Bench {
-                        name: cli.bench_name.clone().leak(),
+                        name: bench_name.clone().leak(),
                         bytecode: Vec::new(),
                         ..Default::default()
                     }
                 } else {
-                    return Err(eyre!("unknown benchmark: {}", cli.bench_name));
+                    return Err(eyre!("unknown benchmark: {}", bench_name));
                 }
             }
         }
@@ -135,27 +319,23 @@ fn main() -> Result<()> {
     compiler.set_module_name(name);
 
     let calldata = if let Some(calldata) = cli.calldata {
-        revmc::primitives::hex::decode(calldata)?.into()
+        hex::decode(calldata)?.into()
     } else {
         calldata.into()
     };
     let gas_limit = cli.gas_limit;
 
-    let mut env = Env::default();
-    env.tx.caller = address!("0000000000000000000000000000000000000001");
-    env.tx.transact_to = TransactTo::Call(address!("0000000000000000000000000000000000000002"));
-    env.tx.data = calldata;
-    env.tx.gas_limit = gas_limit;
-
-    let bytecode = revm_interpreter::analysis::to_analysed(revm_primitives::Bytecode::new_raw(
-        revm_primitives::Bytes::copy_from_slice(&bytecode),
-    ));
-    let contract = revm_interpreter::Contract::new_env(&env, bytecode, None);
-    let mut host = revm_interpreter::DummyHost::new(env);
+    let env = new_env(calldata, gas_limit);
+    let contract = new_contract(&env, &bytecode);
+    let accounts = match &cli.state {
+        Some(path) => serde_json::from_slice(&std::fs::read(path)?)?,
+        None => Default::default(),
+    };
 
     let bytecode = contract.bytecode.original_byte_slice();
 
     let spec_id = if cli.eof { SpecId::OSAKA } else { cli.spec_id.into() };
+    let mut host = MemoryHost::with_accounts(env, spec_id, accounts);
     if !stack_input.is_empty() {
         compiler.inspect_stack_length(true);
     }
@@ -165,14 +345,20 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let f_id = compiler.translate(name, bytecode, spec_id)?;
+    compiler.block_profile(cli.profile_out.is_some());
+    let f_id = if let Some(path) = &cli.profile_in {
+        let profile: ExecutionProfile = serde_json::from_slice(&std::fs::read(path)?)?;
+        compiler.translate_with_profile(name, bytecode, spec_id, &profile)?
+    } else {
+        compiler.translate(name, bytecode, spec_id)?
+    };
 
     let mut load = cli.load;
     if cli.aot {
         let out_dir = if let Some(out_dir) = compiler.out_dir() {
-            out_dir.join(cli.bench_name)
+            out_dir.join(bench_name)
         } else {
-            let dir = std::env::temp_dir().join("revmc-cli").join(cli.bench_name);
+            let dir = std::env::temp_dir().join("revmc-cli").join(bench_name);
             std::fs::create_dir_all(&dir)?;
             dir
         };
@@ -204,9 +390,8 @@ fn main() -> Result<()> {
     let f = if let Some(load) = load {
         if let Some(load) = load {
             lib = unsafe { libloading::Library::new(load) }?;
-            let f: libloading::Symbol<'_, revmc::EvmCompilerFn> =
-                unsafe { lib.get(name.as_bytes())? };
-            *f
+            let f: libloading::Symbol<'_, RawEvmCompilerFn> = unsafe { lib.get(name.as_bytes())? };
+            EvmCompilerFn::new(*f)
         } else {
             return Err(eyre!("--load with no argument requires --aot"));
         }
@@ -214,26 +399,41 @@ fn main() -> Result<()> {
         unsafe { compiler.jit_function(f_id)? }
     };
 
+    if cli.record.is_some() || cli.replay.is_some() {
+        let mut interpreter = Interpreter::new(contract.clone(), gas_limit, false);
+        let ret = if let Some(path) = &cli.record {
+            let mut host = RecordingHost::new(host);
+            let ret = call_compiled(f, &mut interpreter, &mut host, &stack_input, None);
+            std::fs::write(path, serde_json::to_vec_pretty(host.tape())?)?;
+            eprintln!("Recorded {} host calls to {}", host.tape().calls.len(), path.display());
+            if let Some(path) = &cli.dump_state {
+                dump_state(host.inner(), path)?;
+            }
+            ret
+        } else {
+            let path = cli.replay.as_ref().unwrap();
+            let mut host = ReplayHost::new(serde_json::from_slice(&std::fs::read(path)?)?);
+            let ret = call_compiled(f, &mut interpreter, &mut host, &stack_input, None);
+            ensure!(host.is_finished(), "{} recorded host calls were not made", host.remaining());
+            ret
+        };
+        print_result(ret, &interpreter.next_action);
+        return Ok(());
+    }
+
+    let mut profile = cli.profile_out.as_ref().map(|_| ExecutionProfile::new(bytecode));
+
     #[allow(unused_parens)]
     let table = spec_to_generic!(spec_id, (const { &make_instruction_table::<_, SPEC>() }));
-    let mut run = |f: revmc::EvmCompilerFn| {
-        let mut interpreter =
-            revm_interpreter::Interpreter::new(contract.clone(), gas_limit, false);
-        host.clear();
+    let mut run = |f: EvmCompilerFn| {
+        let mut interpreter = Interpreter::new(contract.clone(), gas_limit, false);
+        host.revert_transaction();
 
         if cli.interpret {
             let action = interpreter.run(SharedMemory::new(), table, &mut host);
             (interpreter.instruction_result, action)
         } else {
-            let (mut ecx, stack, stack_len) =
-                EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
-
-            for (i, input) in stack_input.iter().enumerate() {
-                stack.as_mut_slice()[i] = input.into();
-            }
-            *stack_len = stack_input.len();
-
-            let r = unsafe { f.call_noinline(Some(stack), Some(stack_len), &mut ecx) };
+            let r = call_compiled(f, &mut interpreter, &mut host, &stack_input, profile.as_mut());
             (r, interpreter.next_action)
         }
     };
@@ -243,14 +443,194 @@ fn main() -> Result<()> {
     }
 
     let (ret, action) = run(f);
-    println!("InstructionResult::{ret:?}");
-    println!("InterpreterAction::{action:#?}");
+    print_result(ret, &action);
 
     if cli.n_iters > 1 {
         bench(cli.n_iters, name, || run(f));
+    }
+
+    if let Some(path) = &cli.dump_state {
+        dump_state(&host, path)?;
+    }
+
+    if let (Some(path), Some(profile)) = (&cli.profile_out, &profile) {
+        std::fs::write(path, serde_json::to_vec_pretty(profile)?)?;
+        eprintln!("Wrote the counts of {} blocks to {}", profile.blocks.len(), path.display());
+    }
+
+    Ok(())
+}
+
+fn aot_build(args: AotBuildArgs) -> Result<()> {
+    let bench = if Path::new(&args.bytecode).is_file() {
+        let path = Path::new(&args.bytecode);
+        Bench {
+            name: path.file_stem().unwrap().to_str().unwrap().to_string().leak(),
+            bytecode: read_code(None, Some(path))?,
+            ..Default::default()
+        }
+    } else {
+        revmc_cli::get_bench(&args.bytecode)
+            .ok_or_else(|| eyre!("unknown benchmark or file: {}", args.bytecode))?
+    };
+    let metadata = AotMetadata {
+        name: args.name.unwrap_or_else(|| bench.name.to_string()),
+        spec_id: args.spec_id.into(),
+        bytecode: bench.bytecode,
+        stack_input: bench.stack_input,
+    };
+
+    let context = revmc::llvm::inkwell::context::Context::create();
+    let target = revmc::Target::new(args.target, args.target_cpu, args.target_features);
+    let backend = EvmLlvmBackend::new_for_target(&context, true, args.opt_level, &target)?;
+    let mut compiler = EvmCompiler::new(backend);
+    compiler.set_module_name(&metadata.name);
+    compiler.gas_metering(!args.no_gas);
+    compiler.frame_pointers(true);
+    compiler.inspect_stack_length(!metadata.stack_input.is_empty());
+    compiler.translate(&metadata.name, &metadata.bytecode[..], metadata.spec_id)?;
+
+    let out = revmc_cli::shared_library_path(&args.out);
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let obj = out.with_extension("o");
+    compiler.write_object_to_file(&obj)?;
+    let metadata_src = out.with_extension("metadata.c");
+    std::fs::write(&metadata_src, metadata.to_c_source())?;
+
+    let mut linker = revmc::Linker::new();
+    linker.cc(args.cc);
+    linker.linker(args.linker);
+    if !cfg!(windows) {
+        linker.cflags(["-fPIC"]);
+    }
+    let inputs = [obj.clone(), metadata_src.clone()].into_iter().chain(args.builtins);
+    let linked = linker.link(&out, inputs).wrap_err("failed to link the shared library");
+    let _ = std::fs::remove_file(&obj);
+    let _ = std::fs::remove_file(&metadata_src);
+    linked?;
+    ensure!(out.exists(), "Failed to link object file");
+    eprintln!("Linked {} to {}", metadata.name, out.display());
+    Ok(())
+}
+
+fn aot_run(args: AotRunArgs) -> Result<()> {
+    let lib = revmc_cli::load_library(&args.library)?;
+    let metadata = unsafe { AotMetadata::from_library(&lib) }?;
+    let f: libloading::Symbol<'_, RawEvmCompilerFn> = unsafe { lib.get(metadata.name.as_bytes()) }
+        .wrap_err_with(|| format!("missing function symbol {:?}", metadata.name))?;
+    let f = EvmCompilerFn::new(*f);
+
+    let calldata = match &args.calldata {
+        Some(calldata) => hex::decode(calldata)?.into(),
+        None => Bytes::new(),
+    };
+    let env = new_env(calldata, args.gas_limit);
+    let contract = new_contract(&env, &metadata.bytecode);
+    let accounts = match &args.state {
+        Some(path) => serde_json::from_slice(&std::fs::read(path)?)?,
+        None => Default::default(),
+    };
+    let mut host = MemoryHost::with_accounts(env, metadata.spec_id, accounts);
+
+    let mut interpreter = Interpreter::new(contract, args.gas_limit, false);
+    let ret = call_compiled(f, &mut interpreter, &mut host, &metadata.stack_input, None);
+    print_result(ret, &interpreter.next_action);
+    let output = match &interpreter.next_action {
+        InterpreterAction::Return { result } => result.output.clone(),
+        _ => Bytes::new(),
+    };
+    println!("Gas used: {}", interpreter.gas.spent());
+    println!("Output: {output}");
+    Ok(())
+}
+
+fn new_env(calldata: Bytes, gas_limit: u64) -> Env {
+    let mut env = Env::default();
+    env.tx.caller = address!("0000000000000000000000000000000000000001");
+    env.tx.transact_to = TransactTo::Call(address!("0000000000000000000000000000000000000002"));
+    env.tx.data = calldata;
+    env.tx.gas_limit = gas_limit;
+    env
+}
+
+fn new_contract(env: &Env, bytecode: &[u8]) -> Contract {
+    let bytecode = revm_interpreter::analysis::to_analysed(revm_primitives::Bytecode::new_raw(
+        Bytes::copy_from_slice(bytecode),
+    ));
+    Contract::new_env(env, bytecode, None)
+}
+
+fn print_result(ret: InstructionResult, action: &InterpreterAction) {
+    println!("InstructionResult::{ret:?}");
+    println!("InterpreterAction::{action:#?}");
+}
+
+fn dump_state(host: &MemoryHost, path: &Path) -> Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(&host.accounts)?)?;
+    eprintln!("Wrote {} accounts to {}", host.accounts.len(), path.display());
+    Ok(())
+}
+
+fn call_compiled(
+    f: EvmCompilerFn,
+    interpreter: &mut Interpreter,
+    host: &mut dyn HostExt,
+    stack_input: &[U256],
+    profile: Option<&mut ExecutionProfile>,
+) -> InstructionResult {
+    let (mut ecx, stack, stack_len) = EvmContext::from_interpreter_with_stack(interpreter, host);
+    ecx.block_profile = profile;
+
+    for (i, input) in stack_input.iter().enumerate() {
+        stack.as_mut_slice()[i] = input.into();
+    }
+    *stack_len = stack_input.len();
+
+    unsafe { f.call_noinline(Some(stack), Some(stack_len), &mut ecx) }
+}
+
+fn disassemble(args: DisasmArgs) -> Result<()> {
+    let code = read_code(args.code.as_deref(), args.code_path.as_deref())?;
+    let spec_id = args.spec_id.into();
+
+    if args.dot {
+        let config = revmc::analysis::AnalysisConfig::default();
+        print!("{}", revmc::analysis::analyze(&code, spec_id, &config)?.to_dot());
         return Ok(());
     }
 
+    let context = revmc::llvm::inkwell::context::Context::create();
+    let backend = EvmLlvmBackend::new(&context, false, OptimizationLevel::None)?;
+    let mut compiler = EvmCompiler::new(backend);
+    let optimized = compiler.parse(code[..].into(), spec_id)?;
+    compiler.inline_threshold(0);
+    compiler.opt_dead_code(false);
+    compiler.opt_map_slot(false);
+    let unoptimized = compiler.parse(code[..].into(), spec_id)?;
+
+    let s = if args.optimize {
+        disasm(&optimized, Some(&unoptimized), args.pc, args.json)?
+    } else {
+        disasm(&unoptimized, None, args.pc, args.json)?
+    };
+    print!("{s}");
+    Ok(())
+}
+
+fn statetest(args: StatetestArgs) -> Result<()> {
+    let context = revmc::llvm::inkwell::context::Context::create();
+    let backend = EvmLlvmBackend::new(&context, false, args.opt_level)?;
+    let mut compiler = EvmCompiler::new(backend);
+    let mut report = revmc::statetest::Report::default();
+    for path in &args.paths {
+        for path in revmc::statetest::find_all_json_tests(path) {
+            revmc::statetest::run_file(&mut compiler, &path, &mut report)?;
+        }
+    }
+    println!("{report}");
+    ensure!(report.is_success(), "{} state tests failed", report.failures.len());
     Ok(())
 }
 
@@ -268,10 +648,16 @@ fn bench<T>(n_iters: u64, name: &str, mut f: impl FnMut() -> T) {
     eprintln!("{name}: {:>9?} ({d:>12?} / {n_iters})", d / n_iters as u32);
 }
 
-fn init_tracing_subscriber() -> Result<(), tracing_subscriber::util::TryInitError> {
-    use tracing_subscriber::prelude::*;
-    let registry = tracing_subscriber::Registry::default()
-        .with(tracing_subscriber::EnvFilter::from_default_env());
+fn init_tracing_subscriber(verbose: u8) -> Result<(), tracing_subscriber::util::TryInitError> {
+    use tracing_subscriber::{prelude::*, EnvFilter};
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(match verbose {
+            0 => "error",
+            1 => "debug",
+            _ => "trace",
+        })
+    });
+    let registry = tracing_subscriber::Registry::default().with(filter);
     #[cfg(feature = "tracy")]
     let registry = registry.with(tracing_tracy::TracyLayer::default());
     registry.with(tracing_subscriber::fmt::layer()).try_init()
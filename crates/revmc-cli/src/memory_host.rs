@@ -0,0 +1,461 @@
+//! An in-memory host with persistent state.
+
+use revm_interpreter::{
+    AccountLoad, Eip7702CodeLoad, Host, SStoreResult, SelfDestructResult, StateLoad,
+};
+use revm_primitives::{
+    keccak256, Address, Bytes, Env, HashMap, HashSet, Log, SpecId, B256, KECCAK_EMPTY, U256,
+};
+use serde::{Deserialize, Serialize};
+
+/// The number of precompiles, which are warm at the start of a transaction.
+const PRECOMPILES: u8 = 10;
+
+/// An account of a [`MemoryHost`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryAccount {
+    /// The balance.
+    pub balance: U256,
+    /// The nonce.
+    pub nonce: u64,
+    /// The code.
+    pub code: Bytes,
+    /// The storage.
+    pub storage: HashMap<U256, U256>,
+}
+
+impl MemoryAccount {
+    /// Returns `true` if the account is empty, as defined by EIP-161.
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_zero() && self.nonce == 0 && self.code.is_empty()
+    }
+}
+
+/// A [`Host`] with in-memory accounts.
+///
+/// Unlike [`DummyHost`](revm_interpreter::DummyHost), which has a single storage and no accounts,
+/// this keeps the storage, balance and code of every account, and tracks warm accounts and storage
+/// slots and original storage values within a transaction, so that the EIP-2929 and EIP-2200 gas
+/// costs are the same as with a real database.
+///
+/// A transaction starts when the host is created, with the accounts of the environment warm: the
+/// caller, the called address, the precompiles, the access list, and since Shanghai (EIP-3651) the
+/// coinbase. Use [`start_transaction`](Self::start_transaction) to start a new one, or
+/// [`revert_transaction`](Self::revert_transaction) to run the same one again. EIP-7702
+/// delegations are not resolved.
+///
+/// [`accounts`](Self::accounts) can be loaded from and dumped to a JSON object keyed by address.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryHost {
+    /// The environment.
+    pub env: Env,
+    /// The spec, which determines the accounts that are warm at the start of a transaction.
+    pub spec_id: SpecId,
+    /// The accounts. Missing accounts are empty.
+    pub accounts: HashMap<Address, MemoryAccount>,
+    /// The block hashes, by number. Missing block hashes are zero.
+    pub block_hashes: HashMap<u64, B256>,
+    /// The transient storage of the current transaction.
+    pub transient_storage: HashMap<(Address, U256), U256>,
+    /// The logs emitted in the current transaction.
+    pub logs: Vec<Log>,
+    /// The accounts that selfdestructed in the current transaction.
+    pub selfdestructs: HashSet<Address>,
+    warm_accounts: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    original_values: HashMap<(Address, U256), U256>,
+    journal: Vec<JournalEntry>,
+}
+
+/// A change to the accounts, to undo in [`MemoryHost::revert_transaction`].
+#[derive(Clone, Debug)]
+enum JournalEntry {
+    /// The account was created.
+    AccountCreated(Address),
+    /// The storage slot was changed from the given value, or was not set.
+    StorageChanged(Address, U256, Option<U256>),
+    /// The balance was changed from the given value.
+    BalanceChanged(Address, U256),
+}
+
+impl MemoryHost {
+    /// Creates a new host with no accounts, and starts a transaction.
+    pub fn new(env: Env, spec_id: SpecId) -> Self {
+        Self::with_accounts(env, spec_id, HashMap::default())
+    }
+
+    /// Creates a new host with the given accounts, and starts a transaction.
+    pub fn with_accounts(
+        env: Env,
+        spec_id: SpecId,
+        accounts: HashMap<Address, MemoryAccount>,
+    ) -> Self {
+        let mut this = Self { env, spec_id, accounts, ..Default::default() };
+        this.start_transaction();
+        this
+    }
+
+    /// Starts a new transaction with the current environment.
+    ///
+    /// This clears the transient storage, the logs, the selfdestructs, and the warm accounts and
+    /// storage slots, and then warms the accounts of the environment. The accounts are kept.
+    pub fn start_transaction(&mut self) {
+        self.journal.clear();
+        self.transient_storage.clear();
+        self.logs.clear();
+        self.selfdestructs.clear();
+        self.warm_accounts.clear();
+        self.warm_slots.clear();
+        self.original_values.clear();
+
+        let env = &self.env;
+        self.warm_accounts.insert(env.tx.caller);
+        self.warm_accounts.extend(env.tx.transact_to.to().copied());
+        if self.spec_id.is_enabled_in(SpecId::SHANGHAI) {
+            self.warm_accounts.insert(env.block.coinbase);
+        }
+        self.warm_accounts.extend((1..=PRECOMPILES).map(Address::with_last_byte));
+        for item in &env.tx.access_list {
+            self.warm_accounts.insert(item.address);
+            self.warm_slots.extend(
+                item.storage_keys.iter().map(|key| (item.address, U256::from_be_bytes(key.0))),
+            );
+        }
+    }
+
+    /// Undoes the changes to the accounts made in the current transaction, and starts a new one.
+    ///
+    /// This is cheaper than cloning the host to run the same transaction again, since only the
+    /// changed accounts and storage slots are restored.
+    pub fn revert_transaction(&mut self) {
+        while let Some(entry) = self.journal.pop() {
+            match entry {
+                JournalEntry::AccountCreated(address) => {
+                    self.accounts.remove(&address);
+                }
+                JournalEntry::StorageChanged(address, index, value) => {
+                    let storage = &mut self.accounts.get_mut(&address).unwrap().storage;
+                    match value {
+                        Some(value) => storage.insert(index, value),
+                        None => storage.remove(&index),
+                    };
+                }
+                JournalEntry::BalanceChanged(address, balance) => {
+                    self.accounts.get_mut(&address).unwrap().balance = balance;
+                }
+            }
+        }
+        self.start_transaction();
+    }
+
+    /// Returns the account at `address`, if it exists.
+    pub fn account(&self, address: Address) -> Option<&MemoryAccount> {
+        self.accounts.get(&address)
+    }
+
+    /// Returns the storage value of `address` at `index`, without warming it.
+    pub fn storage(&self, address: Address, index: U256) -> U256 {
+        self.account(address)
+            .and_then(|account| account.storage.get(&index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Warms `address`, returning `true` if it was cold.
+    fn warm_account(&mut self, address: Address) -> bool {
+        self.warm_accounts.insert(address)
+    }
+
+    /// Returns the account at `address` for writing, creating it if it does not exist.
+    fn account_mut(&mut self, address: Address) -> &mut MemoryAccount {
+        if !self.accounts.contains_key(&address) {
+            self.journal.push(JournalEntry::AccountCreated(address));
+        }
+        self.accounts.entry(address).or_default()
+    }
+
+    /// Loads `address`, returning it and whether it was cold.
+    fn load(&mut self, address: Address) -> StateLoad<Option<&MemoryAccount>> {
+        let is_cold = self.warm_account(address);
+        StateLoad::new(self.accounts.get(&address), is_cold)
+    }
+}
+
+impl Host for MemoryHost {
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+        let account = self.load(address);
+        let is_empty = account.data.map_or(true, MemoryAccount::is_empty);
+        let load =
+            Eip7702CodeLoad { state_load: account.map(drop), is_delegate_account_cold: None };
+        Some(AccountLoad { load, is_empty })
+    }
+
+    fn block_hash(&mut self, number: u64) -> Option<B256> {
+        Some(self.block_hashes.get(&number).copied().unwrap_or_default())
+    }
+
+    fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+        Some(
+            self.load(address)
+                .map(|account| account.map(|account| account.balance).unwrap_or_default()),
+        )
+    }
+
+    fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+        Some(
+            self.load(address)
+                .map(|account| account.map(|account| account.code.clone()).unwrap_or_default()),
+        )
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+        // EIP-1052: empty accounts have a zero hash.
+        Some(self.load(address).map(|account| match account {
+            Some(account) if !account.is_empty() => {
+                if account.code.is_empty() {
+                    KECCAK_EMPTY
+                } else {
+                    keccak256(&account.code)
+                }
+            }
+            _ => B256::ZERO,
+        }))
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+        let is_cold = self.warm_slots.insert((address, index));
+        Some(StateLoad::new(self.storage(address, index), is_cold))
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Option<StateLoad<SStoreResult>> {
+        let is_cold = self.warm_slots.insert((address, index));
+        let previous = self.account_mut(address).storage.insert(index, value);
+        self.journal.push(JournalEntry::StorageChanged(address, index, previous));
+        let present_value = previous.unwrap_or_default();
+        let original_value = *self.original_values.entry((address, index)).or_insert(present_value);
+        Some(StateLoad::new(
+            SStoreResult { original_value, present_value, new_value: value },
+            is_cold,
+        ))
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        self.transient_storage.get(&(address, index)).copied().unwrap_or_default()
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        self.transient_storage.insert((address, index), value);
+    }
+
+    fn log(&mut self, log: Log) {
+        self.logs.push(log);
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<StateLoad<SelfDestructResult>> {
+        let is_cold = self.warm_account(target);
+        let target_exists = self.account(target).is_some_and(|account| !account.is_empty());
+        let balance = match self.accounts.get_mut(&address) {
+            Some(account) => std::mem::take(&mut account.balance),
+            None => U256::ZERO,
+        };
+        if !balance.is_zero() {
+            self.journal.push(JournalEntry::BalanceChanged(address, balance));
+            let target_account = self.account_mut(target);
+            let target_balance = target_account.balance;
+            target_account.balance += balance;
+            self.journal.push(JournalEntry::BalanceChanged(target, target_balance));
+        }
+        let previously_destroyed = !self.selfdestructs.insert(address);
+        let result = SelfDestructResult {
+            had_value: !balance.is_zero(),
+            target_exists,
+            previously_destroyed,
+        };
+        Some(StateLoad::new(result, is_cold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_interpreter::{
+        analysis::to_analysed, opcode as op, Contract, Interpreter, InterpreterAction, SharedMemory,
+    };
+    use revm_primitives::{AccessListItem, Bytecode, CancunSpec, TxKind};
+
+    const CALLER: Address = Address::with_last_byte(0xca);
+    const CONTRACT: Address = Address::with_last_byte(0xc0);
+
+    fn env() -> Env {
+        let mut env = Env::default();
+        env.tx.caller = CALLER;
+        env.tx.transact_to = TxKind::Call(CONTRACT);
+        env.tx.access_list = vec![AccessListItem {
+            address: Address::with_last_byte(0xaa),
+            storage_keys: vec![B256::with_last_byte(1)],
+        }];
+        env
+    }
+
+    #[test]
+    fn warm_accounts() {
+        let mut host = MemoryHost::new(env(), SpecId::CANCUN);
+        for address in [CALLER, CONTRACT, Address::ZERO, Address::with_last_byte(1)] {
+            assert!(!host.balance(address).unwrap().is_cold, "{address}");
+        }
+        let other = Address::with_last_byte(0x99);
+        assert!(host.balance(other).unwrap().is_cold);
+        assert!(!host.code(other).unwrap().is_cold);
+        assert!(!host.sload(Address::with_last_byte(0xaa), U256::from(1)).unwrap().is_cold);
+        assert!(host.sload(Address::with_last_byte(0xaa), U256::from(2)).unwrap().is_cold);
+
+        host.start_transaction();
+        assert!(host.balance(other).unwrap().is_cold);
+
+        // The coinbase is only warm since Shanghai.
+        let mut host = MemoryHost::new(env(), SpecId::MERGE);
+        assert!(host.balance(Address::ZERO).unwrap().is_cold);
+        assert!(!host.balance(CALLER).unwrap().is_cold);
+    }
+
+    #[test]
+    fn accounts() {
+        let mut accounts = HashMap::default();
+        let code = Bytes::from_static(&[op::STOP]);
+        accounts.insert(CONTRACT, MemoryAccount { code: code.clone(), ..Default::default() });
+        accounts.insert(CALLER, MemoryAccount { balance: U256::from(10), ..Default::default() });
+        let mut host = MemoryHost::with_accounts(env(), SpecId::CANCUN, accounts);
+
+        assert_eq!(host.code(CONTRACT).unwrap().data, code);
+        assert_eq!(host.code_hash(CONTRACT).unwrap().data, keccak256(&code));
+        assert_eq!(host.code_hash(CALLER).unwrap().data, KECCAK_EMPTY);
+        assert_eq!(host.code_hash(Address::ZERO).unwrap().data, B256::ZERO);
+        assert!(!host.load_account_delegated(CALLER).unwrap().is_empty);
+        assert!(host.load_account_delegated(Address::ZERO).unwrap().is_empty);
+
+        let result = host.selfdestruct(CALLER, CONTRACT).unwrap().data;
+        assert_eq!(
+            result,
+            SelfDestructResult {
+                had_value: true,
+                target_exists: true,
+                previously_destroyed: false
+            }
+        );
+        assert_eq!(host.balance(CALLER).unwrap().data, U256::ZERO);
+        assert_eq!(host.balance(CONTRACT).unwrap().data, U256::from(10));
+        assert!(host.selfdestruct(CALLER, CONTRACT).unwrap().previously_destroyed);
+    }
+
+    #[test]
+    fn storage() {
+        let mut host = MemoryHost::new(env(), SpecId::CANCUN);
+        let index = U256::from(1);
+        let result = host.sstore(CONTRACT, index, U256::from(1)).unwrap();
+        assert!(result.is_cold);
+        assert_eq!(result.data.original_value, U256::ZERO);
+        let result = host.sstore(CONTRACT, index, U256::from(2)).unwrap();
+        assert!(!result.is_cold);
+        assert_eq!(result.data.original_value, U256::ZERO);
+        assert_eq!(result.data.present_value, U256::from(1));
+        assert_eq!(host.storage(CONTRACT, index), U256::from(2));
+        assert_eq!(host.storage(CALLER, index), U256::ZERO);
+
+        host.start_transaction();
+        let result = host.sstore(CONTRACT, index, U256::from(3)).unwrap();
+        assert!(result.is_cold);
+        assert_eq!(result.data.original_value, U256::from(2));
+
+        host.tstore(CONTRACT, index, U256::from(4));
+        assert_eq!(host.tload(CONTRACT, index), U256::from(4));
+        assert_eq!(host.tload(CALLER, index), U256::ZERO);
+    }
+
+    #[test]
+    fn revert_transaction() {
+        let mut accounts = HashMap::default();
+        let mut storage = HashMap::default();
+        storage.insert(U256::from(1), U256::from(1));
+        accounts.insert(CONTRACT, MemoryAccount { storage, ..Default::default() });
+        accounts.insert(CALLER, MemoryAccount { balance: U256::from(10), ..Default::default() });
+        let mut host = MemoryHost::with_accounts(env(), SpecId::CANCUN, accounts.clone());
+
+        let other = Address::with_last_byte(0x99);
+        host.sstore(CONTRACT, U256::from(1), U256::from(2));
+        host.sstore(CONTRACT, U256::from(2), U256::from(3));
+        host.sstore(other, U256::from(1), U256::from(4));
+        host.selfdestruct(CALLER, other);
+        assert_eq!(host.balance(other).unwrap().data, U256::from(10));
+        assert_ne!(host.accounts, accounts);
+
+        host.revert_transaction();
+        assert_eq!(host.accounts, accounts);
+        let result = host.sstore(CONTRACT, U256::from(1), U256::from(2)).unwrap();
+        assert!(result.is_cold);
+        assert_eq!(result.data.original_value, U256::from(1));
+    }
+
+    #[test]
+    fn serde() {
+        let json = r#"{
+            "0x00000000000000000000000000000000000000c0": {
+                "balance": "0x1",
+                "code": "0x00",
+                "storage": { "0x1": "0x2" }
+            }
+        }"#;
+        let accounts: HashMap<Address, MemoryAccount> = serde_json::from_str(json).unwrap();
+        let host = MemoryHost::with_accounts(env(), SpecId::CANCUN, accounts);
+        let account = host.account(CONTRACT).unwrap();
+        assert_eq!(account.balance, U256::from(1));
+        assert_eq!(account.nonce, 0);
+        assert_eq!(account.code[..], [op::STOP]);
+        assert_eq!(host.storage(CONTRACT, U256::from(1)), U256::from(2));
+
+        let json = serde_json::to_string(&host.accounts).unwrap();
+        let accounts: HashMap<Address, MemoryAccount> = serde_json::from_str(&json).unwrap();
+        assert_eq!(accounts, host.accounts);
+    }
+
+    #[test]
+    fn interpreter_gas() {
+        #[rustfmt::skip]
+        let code = [
+            // Cold and warm `SLOAD`.
+            op::PUSH1, 1, op::SLOAD, op::POP,
+            op::PUSH1, 1, op::SLOAD, op::POP,
+            // Cold and warm `BALANCE`.
+            op::PUSH1, 0x99, op::BALANCE, op::POP,
+            op::PUSH1, 0x99, op::BALANCE, op::POP,
+            op::STOP,
+        ];
+        let bytecode = to_analysed(Bytecode::new_raw(Bytes::copy_from_slice(&code)));
+        let contract = Contract { target_address: CONTRACT, bytecode, ..Default::default() };
+        let mut host = MemoryHost::new(env(), SpecId::CANCUN);
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let table = op::make_instruction_table::<MemoryHost, CancunSpec>();
+        let action = interpreter.run(SharedMemory::new(), &table, &mut host);
+        let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+        assert!(result.is_ok(), "{result:?}");
+        let verylow = 3 + 2;
+        assert_eq!(result.gas.spent(), 4 * verylow + 2100 + 100 + 2600 + 100);
+    }
+}
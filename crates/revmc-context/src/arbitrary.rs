@@ -0,0 +1,110 @@
+//! [`proptest`] support.
+
+use crate::EvmWord;
+use alloc::vec;
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    prop_oneof,
+    sample::select,
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+use revm_primitives::U256;
+
+impl Arbitrary for EvmWord {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates words that are biased towards the edge cases of 256-bit arithmetic.
+    ///
+    /// About half of the words are uniformly random. The rest are, in order of decreasing
+    /// likelihood:
+    /// - values next to `0`, `U256::MAX`, and the signed extremes `i256::MIN` and `i256::MAX`;
+    /// - powers of two, or one off from them, which cover every bit and every limb boundary;
+    /// - small positive and negative numbers, which fit in 64 bits in their signed form.
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        let edge = select(vec![
+            U256::ZERO,
+            U256::from(1),
+            U256::from(2),
+            U256::MAX,
+            U256::MAX - U256::from(1),
+            U256::from(1) << 255,
+            (U256::from(1) << 255) + U256::from(1),
+            (U256::from(1) << 255) - U256::from(1),
+        ]);
+        let pow2 =
+            (0..256usize, prop_oneof![Just(0i8), Just(-1), Just(1)]).prop_map(|(shift, offset)| {
+                let x = U256::from(1) << shift;
+                match offset {
+                    -1 => x.wrapping_sub(U256::from(1)),
+                    1 => x.wrapping_add(U256::from(1)),
+                    _ => x,
+                }
+            });
+        let small = prop_oneof![
+            any::<u64>().prop_map(U256::from),
+            any::<i64>().prop_map(|x| U256::from(x.unsigned_abs()).wrapping_neg()),
+        ];
+        let uniform = any::<[u64; 4]>().prop_map(U256::from_limbs);
+        prop_oneof![
+            3 => edge,
+            2 => pow2,
+            1 => small,
+            6 => uniform,
+        ]
+        .prop_map(Self::from_u256)
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{
+        strategy::ValueTree,
+        test_runner::{Config, TestRunner},
+    };
+
+    #[test]
+    fn covers_edge_values() {
+        let mut runner = TestRunner::new(Config::default());
+        let strategy = any::<EvmWord>();
+        let mut seen = [false; 4];
+        let mut small = 0;
+        for _ in 0..1000 {
+            let word = strategy.new_tree(&mut runner).unwrap().current().to_u256();
+            for (seen, value) in
+                seen.iter_mut().zip([U256::ZERO, U256::from(1), U256::MAX, U256::from(1) << 255])
+            {
+                *seen |= word == value;
+            }
+            small += (word < U256::from(1) << 64) as usize;
+        }
+        assert_eq!(seen, [true; 4]);
+        // Uniformly random words are small with a probability of 2^-192.
+        assert!(small > 100, "{small}");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn roundtrip(word: EvmWord) {
+            proptest::prop_assert_eq!(EvmWord::from_u256(word.to_u256()), word);
+        }
+
+        #[test]
+        fn from_be_slice(slice in proptest::collection::vec(any::<u8>(), 0..=32)) {
+            let mut padded = [0u8; 32];
+            padded[32 - slice.len()..].copy_from_slice(&slice);
+            proptest::prop_assert_eq!(EvmWord::from_be_slice(&slice), EvmWord::from_be_bytes(padded));
+        }
+
+        #[test]
+        fn copy_be_into(word: EvmWord, len in 0..=32usize) {
+            let mut out = [0u8; 32];
+            word.copy_be_into(&mut out[..len]);
+            proptest::prop_assert_eq!(&out[..len], &word.to_be_bytes()[32 - len..]);
+            let low = EvmWord::from_be_slice(&out[..len]);
+            proptest::prop_assert_eq!(low.to_u256(), word.to_u256() & (U256::MAX >> (256 - 8 * len)));
+        }
+    }
+}
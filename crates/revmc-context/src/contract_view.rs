@@ -0,0 +1,182 @@
+//! A stable view of the [`Contract`] fields read by compiled code.
+
+use core::{
+    mem::{align_of, offset_of, size_of},
+    slice,
+};
+use revm_interpreter::Contract;
+use revm_primitives::{Address, U256};
+
+/// The [`Contract`] fields that compiled code reads, with a fixed `#[repr(C)]` layout.
+///
+/// Compiled functions receive a pointer to this view instead of the [`Contract`] itself, which
+/// makes their ABI independent of the layout of revm's types: an object compiled against one
+/// version of revm can be linked against and called from another. The view is created from the
+/// contract right before each call by [`EvmCompilerFn::call`](crate::EvmCompilerFn::call).
+///
+/// The layout must never change without also changing the symbol names or versioning the
+/// compiled artifacts, since it is baked into them.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ContractView {
+    /// Pointer to the call data.
+    pub input_ptr: *const u8,
+    /// Length of the call data.
+    pub input_len: usize,
+    /// Pointer to the original bytecode, without the padding added by analysis.
+    pub code_ptr: *const u8,
+    /// Length of the original bytecode.
+    pub code_len: usize,
+    /// Value sent to the contract, as little-endian 64-bit limbs in native endianness.
+    pub call_value: U256,
+    /// Address of the executing contract.
+    pub target_address: Address,
+    /// Address of the caller.
+    pub caller: Address,
+}
+
+const _: () = {
+    let word = size_of::<usize>();
+    assert!(offset_of!(ContractView, input_ptr) == 0);
+    assert!(offset_of!(ContractView, input_len) == word);
+    assert!(offset_of!(ContractView, code_ptr) == 2 * word);
+    assert!(offset_of!(ContractView, code_len) == 3 * word);
+    assert!(offset_of!(ContractView, call_value) == 4 * word);
+    assert!(offset_of!(ContractView, target_address) == 4 * word + 32);
+    assert!(offset_of!(ContractView, caller) == 4 * word + 52);
+    assert!(size_of::<ContractView>() == 4 * word + 72);
+    assert!(align_of::<ContractView>() == align_of::<u64>());
+};
+
+impl ContractView {
+    /// Creates a view of the given contract.
+    ///
+    /// The view borrows the call data and bytecode of the contract without a lifetime, so it must
+    /// not outlive it.
+    #[inline]
+    pub fn new(contract: &Contract) -> Self {
+        let code = contract.bytecode.original_byte_slice();
+        Self {
+            input_ptr: contract.input.as_ptr(),
+            input_len: contract.input.len(),
+            code_ptr: code.as_ptr(),
+            code_len: code.len(),
+            call_value: contract.call_value,
+            target_address: contract.target_address,
+            caller: contract.caller,
+        }
+    }
+
+    /// Returns the call data.
+    ///
+    /// # Safety
+    ///
+    /// The contract that the view was created from must still be alive.
+    #[inline]
+    pub unsafe fn input(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.input_ptr, self.input_len) }
+    }
+
+    /// Returns the original bytecode.
+    ///
+    /// # Safety
+    ///
+    /// The contract that the view was created from must still be alive.
+    #[inline]
+    pub unsafe fn code(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.code_ptr, self.code_len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_contract, EvmCompilerFn, EvmContext, EvmStack, EvmWord};
+    use revm_interpreter::{Gas, InstructionResult, Interpreter};
+    use revm_primitives::{address, Bytes, Env};
+
+    fn contract() -> Contract {
+        Contract {
+            input: Bytes::from_static(&[1, 2, 3]),
+            target_address: address!("1000000000000000000000000000000000000001"),
+            caller: address!("2000000000000000000000000000000000000002"),
+            call_value: U256::from(69),
+            ..test_contract(Bytes::from_static(&[0x5f, 0x00]))
+        }
+    }
+
+    #[test]
+    fn new() {
+        let contract = contract();
+        let view = ContractView::new(&contract);
+        unsafe {
+            assert_eq!(view.input(), [1, 2, 3]);
+            assert_eq!(view.code(), [0x5f, 0x00]);
+        }
+        assert_eq!(view.call_value, contract.call_value);
+        assert_eq!(view.target_address, contract.target_address);
+        assert_eq!(view.caller, contract.caller);
+    }
+
+    /// The offsets that compiled code was built with. These must never change.
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn layout() {
+        assert_eq!(offset_of!(ContractView, input_ptr), 0);
+        assert_eq!(offset_of!(ContractView, input_len), 8);
+        assert_eq!(offset_of!(ContractView, code_ptr), 16);
+        assert_eq!(offset_of!(ContractView, code_len), 24);
+        assert_eq!(offset_of!(ContractView, call_value), 32);
+        assert_eq!(offset_of!(ContractView, target_address), 64);
+        assert_eq!(offset_of!(ContractView, caller), 84);
+        assert_eq!(size_of::<ContractView>(), 104);
+        assert_eq!(align_of::<ContractView>(), 8);
+    }
+
+    /// Stands in for a function compiled ahead of time: reads the view at fixed offsets, without
+    /// knowing anything about the layout of `Contract`, and pushes `CALLER`, `CALLVALUE` and
+    /// `CALLDATASIZE`.
+    #[cfg(target_pointer_width = "64")]
+    unsafe extern "C" fn frozen_fn(
+        _gas: *mut Gas,
+        stack: *mut EvmStack,
+        stack_len: *mut usize,
+        _env: *const Env,
+        contract: *const ContractView,
+        _ecx: *mut EvmContext<'_>,
+    ) -> InstructionResult {
+        unsafe {
+            let view = contract.cast::<u8>();
+            let caller = Address::from_slice(slice::from_raw_parts(view.add(84), 20));
+            let call_value = view.add(32).cast::<U256>().read_unaligned();
+            let input_len = view.add(8).cast::<usize>().read_unaligned();
+            let stack = (*stack).as_mut_slice();
+            stack[0] = EvmWord::from_be_bytes(caller.into_word().0);
+            stack[1] = call_value.into();
+            stack[2] = U256::from(input_len).into();
+            *stack_len = 3;
+        }
+        InstructionResult::Stop
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn frozen_abi() {
+        let contract = contract();
+        let mut interpreter = Interpreter::new(contract.clone(), u64::MAX, false);
+        let mut host = revm_interpreter::DummyHost::default();
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        let f = EvmCompilerFn::new(frozen_fn);
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        assert_eq!(
+            stack.as_slice()[..*stack_len],
+            [
+                EvmWord::from_be_bytes(contract.caller.into_word().0),
+                EvmWord::from(U256::from(69)),
+                EvmWord::from(3u64),
+            ]
+        );
+    }
+}
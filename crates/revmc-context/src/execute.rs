@@ -0,0 +1,123 @@
+//! Executing bytecode generically over compiled functions and the interpreter.
+
+use crate::{EvmCompilerFn, HostExt};
+use revm_interpreter::{
+    opcode::make_instruction_table, Interpreter, InterpreterAction, SharedMemory,
+    EMPTY_SHARED_MEMORY,
+};
+use revm_primitives::{spec_to_generic, SpecId};
+
+/// A way to execute the bytecode of an [`Interpreter`], either compiled or interpreted.
+///
+/// This is object safe, so that a pipeline can be generic over how bytecode is executed by storing
+/// `Arc<dyn EvmExecute + Send + Sync>` instead of checking for a compiled function everywhere.
+pub trait EvmExecute {
+    /// Executes the interpreter's contract, re-using its resources.
+    ///
+    /// This behaves the same as [`Interpreter::run`], returning an
+    /// [`InstructionResult`](revm_interpreter::InstructionResult) in the interpreter's
+    /// [`instruction_result`](Interpreter::instruction_result) field and the next action in the
+    /// [`next_action`](Interpreter::next_action) field.
+    ///
+    /// # Safety
+    ///
+    /// For compiled functions, the caller must ensure that the function is safe to call, see
+    /// [`EvmCompilerFn::call_with_interpreter`].
+    unsafe fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction;
+
+    /// Same as [`execute`](Self::execute), but with the given memory instead of the
+    /// interpreter's.
+    ///
+    /// # Safety
+    ///
+    /// See [`execute`](Self::execute).
+    unsafe fn execute_with_memory(
+        &self,
+        interpreter: &mut Interpreter,
+        memory: &mut SharedMemory,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        interpreter.shared_memory = core::mem::replace(memory, EMPTY_SHARED_MEMORY);
+        let action = unsafe { self.execute(interpreter, host) };
+        *memory = interpreter.take_memory();
+        action
+    }
+}
+
+impl EvmExecute for EvmCompilerFn {
+    #[inline]
+    unsafe fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        unsafe { self.call_with_interpreter(interpreter, host) }
+    }
+}
+
+/// Executes bytecode with the revm interpreter, using the instruction table of a spec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InterpreterExec {
+    spec_id: SpecId,
+}
+
+impl InterpreterExec {
+    /// Creates a new interpreter executor for the given spec.
+    pub const fn new(spec_id: SpecId) -> Self {
+        Self { spec_id }
+    }
+
+    /// Returns the spec.
+    pub const fn spec_id(&self) -> SpecId {
+        self.spec_id
+    }
+}
+
+impl EvmExecute for InterpreterExec {
+    unsafe fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        let memory = interpreter.take_memory();
+        spec_to_generic!(
+            self.spec_id,
+            interpreter.run(memory, &make_instruction_table::<dyn HostExt + '_, SPEC>(), host)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_contract;
+    use alloc::sync::Arc;
+    use revm_interpreter::{opcode as op, DummyHost, InstructionResult};
+    use revm_primitives::Bytes;
+
+    #[test]
+    fn interpreter_exec() {
+        #[rustfmt::skip]
+        const CODE: &[u8] = &[
+            op::PUSH1, 0x69, op::PUSH0, op::MSTORE,
+            op::PUSH1, 0x20, op::PUSH0, op::RETURN,
+        ];
+        let contract = test_contract(Bytes::from_static(CODE));
+        let exec: Arc<dyn EvmExecute + Send + Sync> =
+            Arc::new(InterpreterExec::new(SpecId::CANCUN));
+        let mut host = DummyHost::default();
+        let mut interpreter = Interpreter::new(contract, 100, false);
+        let mut memory = SharedMemory::new();
+        let action = unsafe { exec.execute_with_memory(&mut interpreter, &mut memory, &mut host) };
+        let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(result.output[31], 0x69);
+        assert_eq!(result.gas.spent(), 3 + 2 + 3 + 3 + 3 + 2);
+        assert_eq!(memory.context_memory()[31], 0x69);
+        assert!(interpreter.shared_memory.is_empty());
+    }
+}
@@ -0,0 +1,886 @@
+//! Calls between compiled functions that bypass the host's call loop.
+
+#[cfg(feature = "std")]
+use crate::HostError;
+use crate::{ContextConfig, EvmCompilerFn, EvmContext, EvmStack, EvmWord, FrameOutcome, HostExt};
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::Range;
+use revm_interpreter::{
+    CallInputs, CallOutcome, Contract, FunctionStack, Gas, InstructionResult, Interpreter,
+    InterpreterAction, InterpreterResult, SharedMemory, EMPTY_SHARED_MEMORY,
+};
+use revm_primitives::{Bytes, U256};
+
+/// A host that can enter and exit call frames itself, allowing calls to compiled code to be
+/// executed directly, see [`EvmCompilerFn::call_with_fast_calls`].
+///
+/// This is implemented by the handler integration, which owns the journal and the mapping from
+/// code hashes to compiled functions. Entering and exiting a frame must make the same journal
+/// calls as the host makes for the call frames that it executes itself, such as revm's
+/// `make_call_frame` and `call_return`.
+pub trait FastCallHost: HostExt {
+    /// A journal checkpoint, created when entering a call frame.
+    type Checkpoint;
+
+    /// Enters the call frame of `inputs`: checks the call depth, creates a journal checkpoint,
+    /// and transfers the call value.
+    ///
+    /// Returns `None` to execute the call on the slow path instead, with any journal changes
+    /// already reverted. This must be the case when the code of the callee is not compiled, or
+    /// when the call does not execute any code, such as calls to precompiles, to accounts
+    /// without code, or calls that fail before execution because of the depth limit or an
+    /// insufficient balance.
+    fn enter_call(&mut self, inputs: &CallInputs) -> Option<FastCall<Self::Checkpoint>>;
+
+    /// Exits a call frame entered with [`enter_call`](Self::enter_call), committing the
+    /// checkpoint if `result` is a success, and reverting it otherwise.
+    fn exit_call(&mut self, checkpoint: Self::Checkpoint, result: InstructionResult);
+}
+
+/// A call frame entered with [`FastCallHost::enter_call`].
+#[derive(Debug)]
+pub struct FastCall<C> {
+    /// The compiled function of the callee.
+    pub function: EvmCompilerFn,
+    /// The callee contract.
+    pub contract: Contract,
+    /// The checkpoint to exit the call frame with.
+    pub checkpoint: C,
+}
+
+/// A pool of stacks for the call frames executed by [`EvmCompilerFn::call_with_fast_calls`].
+#[derive(Debug, Default)]
+pub struct StackPool {
+    stacks: Vec<Vec<EvmWord>>,
+}
+
+impl StackPool {
+    /// Creates a new empty pool.
+    pub const fn new() -> Self {
+        Self { stacks: Vec::new() }
+    }
+
+    /// Returns the number of stacks available for reuse.
+    pub fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    /// Returns `true` if no stacks are available for reuse.
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    fn take(&mut self) -> Vec<EvmWord> {
+        self.stacks.pop().unwrap_or_else(EvmStack::new_heap)
+    }
+
+    fn give(&mut self, stack: Vec<EvmWord>) {
+        self.stacks.push(stack);
+    }
+}
+
+impl EvmCompilerFn {
+    /// Same as [`call_with_interpreter_and_memory`](Self::call_with_interpreter_and_memory), but
+    /// executes calls to compiled code directly instead of returning them to the host.
+    ///
+    /// When the function suspends on a call, the call frame is entered with
+    /// [`FastCallHost::enter_call`], and the callee is executed on a new context of `memory` with
+    /// a stack from `stacks`, without creating an [`Interpreter`]. Its outcome is then inserted
+    /// into `interpreter` and the function is resumed, like after the host executed the call.
+    /// Calls made by the callee are executed the same way. The frames are executed in a loop, not
+    /// recursively, so the native stack does not grow with the call depth.
+    ///
+    /// The contexts of this function and of all the callees are configured with `config`, so that,
+    /// for example, opcode counts and block profiles include the callees.
+    ///
+    /// Returns the first action that cannot be executed directly. If a nested call cannot be
+    /// executed directly, or requests a create, the frames entered since the call made by this
+    /// function are reverted, and that call is returned so that the host executes it from the
+    /// start on the slow path. The opcode counts and the step budget of `config` are restored to
+    /// their values before the call, but the host call log, the block profile, the result sink
+    /// and the user data have already seen the reverted frames, and see them again on the slow
+    /// path.
+    ///
+    /// If a callee fails with [`InstructionResult::FatalExternalError`], such as when a host call
+    /// fails, the frames are reverted and this function returns that result without resuming, as
+    /// if it had failed itself. Use [`try_call_with_fast_calls`](Self::try_call_with_fast_calls)
+    /// to get the error of the host call.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that this function and the functions returned by
+    /// [`FastCallHost::enter_call`] are safe to call.
+    pub unsafe fn call_with_fast_calls<H: FastCallHost>(
+        self,
+        interpreter: &mut Interpreter,
+        memory: &mut SharedMemory,
+        host: &mut H,
+        stacks: &mut StackPool,
+        config: &mut ContextConfig<'_>,
+    ) -> InterpreterAction {
+        FastCalls::new(host, stacks, config).run(self, interpreter, memory)
+    }
+
+    /// Same as [`call_with_fast_calls`](Self::call_with_fast_calls), but returns the error of a
+    /// failed host call, if any, including one made by a callee.
+    ///
+    /// See [`report_host_error`](crate::report_host_error) for more information.
+    ///
+    /// # Safety
+    ///
+    /// See [`call_with_fast_calls`](Self::call_with_fast_calls).
+    #[cfg(feature = "std")]
+    pub unsafe fn try_call_with_fast_calls<H: FastCallHost>(
+        self,
+        interpreter: &mut Interpreter,
+        memory: &mut SharedMemory,
+        host: &mut H,
+        stacks: &mut StackPool,
+        config: &mut ContextConfig<'_>,
+    ) -> Result<InterpreterAction, HostError> {
+        let mut calls = FastCalls::new(host, stacks, config);
+        let action = calls.run(self, interpreter, memory);
+        match calls.host_error {
+            Some(error) => Err(error),
+            None => Ok(action),
+        }
+    }
+}
+
+/// Why a call could not be executed directly.
+enum Fallback {
+    /// The call must be executed on the slow path.
+    SlowPath,
+    /// A frame failed with [`InstructionResult::FatalExternalError`], which aborts execution.
+    Fatal,
+}
+
+impl Fallback {
+    /// Returns the result to exit the reverted frames with.
+    fn result(&self) -> InstructionResult {
+        match self {
+            Self::SlowPath => InstructionResult::Revert,
+            Self::Fatal => InstructionResult::FatalExternalError,
+        }
+    }
+}
+
+/// What a call frame does when it stops executing.
+enum Step {
+    /// The frame suspended on a call.
+    Call(Box<CallInputs>),
+    /// The frame returned.
+    Return(CallOutcome),
+}
+
+/// The state shared by the call frames executed directly.
+struct FastCalls<'a, 'c, H> {
+    host: &'a mut H,
+    stacks: &'a mut StackPool,
+    config: &'a mut ContextConfig<'c>,
+    /// The error of the host call that failed, if any.
+    #[cfg(feature = "std")]
+    host_error: Option<HostError>,
+}
+
+impl<'a, 'c, H: FastCallHost> FastCalls<'a, 'c, H> {
+    fn new(host: &'a mut H, stacks: &'a mut StackPool, config: &'a mut ContextConfig<'c>) -> Self {
+        Self {
+            host,
+            stacks,
+            config,
+            #[cfg(feature = "std")]
+            host_error: None,
+        }
+    }
+
+    /// Runs `f` with `interpreter`, executing the calls that it makes directly.
+    unsafe fn run(
+        &mut self,
+        f: EvmCompilerFn,
+        interpreter: &mut Interpreter,
+        memory: &mut SharedMemory,
+    ) -> InterpreterAction {
+        interpreter.shared_memory = core::mem::replace(memory, EMPTY_SHARED_MEMORY);
+        let action = loop {
+            let action =
+                f.call_with_interpreter_inner(interpreter, &mut *self.host, self.config, |_ecx| {
+                    #[cfg(feature = "std")]
+                    if let Some(error) = _ecx.take_host_error() {
+                        self.host_error = Some(error);
+                    }
+                });
+            let InterpreterAction::Call { inputs } = &action else { break action };
+            let sinks = Sinks::save(self.config);
+            match self.call(inputs, &mut interpreter.shared_memory) {
+                Ok(outcome) => FrameOutcome::Call(outcome).insert_into(interpreter),
+                Err(Fallback::SlowPath) => {
+                    sinks.restore(self.config);
+                    break action;
+                }
+                Err(Fallback::Fatal) => {
+                    let result = InstructionResult::FatalExternalError;
+                    interpreter.instruction_result = result;
+                    let output = Bytes::new();
+                    break InterpreterAction::Return {
+                        result: InterpreterResult { result, output, gas: interpreter.gas },
+                    };
+                }
+            }
+        };
+        *memory = interpreter.take_memory();
+        action
+    }
+
+    /// Executes the call of `inputs`, and the calls that it makes, directly.
+    unsafe fn call(
+        &mut self,
+        inputs: &CallInputs,
+        memory: &mut SharedMemory,
+    ) -> Result<CallOutcome, Fallback> {
+        // The callers of the current frame, innermost last.
+        let mut callers = Vec::new();
+        let mut frame = self.enter(inputs, memory)?;
+        loop {
+            let step = match frame.run(memory, self) {
+                Ok(Step::Call(inputs)) => match self.enter(&inputs, memory) {
+                    Ok(callee) => {
+                        callers.push(core::mem::replace(&mut frame, callee));
+                        continue;
+                    }
+                    Err(fallback) => Err(fallback),
+                },
+                Ok(Step::Return(outcome)) => Ok(outcome),
+                Err(fallback) => Err(fallback),
+            };
+            match step {
+                Ok(outcome) => {
+                    self.exit(frame, memory, outcome.result.result);
+                    match callers.pop() {
+                        Some(caller) => frame = caller,
+                        None => return Ok(outcome),
+                    }
+                    frame.insert_call_outcome(memory, outcome);
+                }
+                Err(fallback) => {
+                    self.exit(frame, memory, fallback.result());
+                    while let Some(caller) = callers.pop() {
+                        self.exit(caller, memory, fallback.result());
+                    }
+                    return Err(fallback);
+                }
+            }
+        }
+    }
+
+    /// Enters the call frame of `inputs`.
+    fn enter(
+        &mut self,
+        inputs: &CallInputs,
+        memory: &mut SharedMemory,
+    ) -> Result<CallFrame<H::Checkpoint>, Fallback> {
+        // `EXTCALL` outcomes are inserted differently.
+        if inputs.is_eof {
+            return Err(Fallback::SlowPath);
+        }
+        let FastCall { function, contract, checkpoint } =
+            self.host.enter_call(inputs).ok_or(Fallback::SlowPath)?;
+        memory.new_context();
+        Ok(CallFrame {
+            function,
+            checkpoint,
+            memory_offset: inputs.return_memory_offset.clone(),
+            contract,
+            gas: Gas::new(inputs.gas_limit),
+            return_data: Bytes::new(),
+            func_stack: FunctionStack::default(),
+            is_static: inputs.is_static,
+            resume_at: 0,
+            stack: self.stacks.take(),
+            stack_len: 0,
+        })
+    }
+
+    /// Exits a call frame entered with [`enter`](Self::enter).
+    fn exit(
+        &mut self,
+        frame: CallFrame<H::Checkpoint>,
+        memory: &mut SharedMemory,
+        result: InstructionResult,
+    ) {
+        memory.free_context();
+        self.stacks.give(frame.stack);
+        self.host.exit_call(frame.checkpoint, result);
+    }
+}
+
+/// The values of the sinks of a [`ContextConfig`] that can be restored.
+struct Sinks {
+    opcode_counts: Option<[u64; 256]>,
+    step_budget: Option<u64>,
+}
+
+impl Sinks {
+    fn save(config: &ContextConfig<'_>) -> Self {
+        Self {
+            opcode_counts: config.opcode_counts.as_deref().copied(),
+            step_budget: config.step_budget.as_deref().copied(),
+        }
+    }
+
+    fn restore(self, config: &mut ContextConfig<'_>) {
+        if let (Some(counts), Some(saved)) =
+            (config.opcode_counts.as_deref_mut(), self.opcode_counts)
+        {
+            *counts = saved;
+        }
+        if let (Some(budget), Some(saved)) = (config.step_budget.as_deref_mut(), self.step_budget) {
+            *budget = saved;
+        }
+    }
+}
+
+/// The state of a call frame executed directly, which would otherwise be in an [`Interpreter`].
+struct CallFrame<C> {
+    function: EvmCompilerFn,
+    checkpoint: C,
+    /// The range of the caller's memory that the output is copied to.
+    memory_offset: Range<usize>,
+    contract: Contract,
+    gas: Gas,
+    return_data: Bytes,
+    func_stack: FunctionStack,
+    is_static: bool,
+    resume_at: usize,
+    stack: Vec<EvmWord>,
+    stack_len: usize,
+}
+
+impl<C> CallFrame<C> {
+    /// Runs the frame until it returns or suspends on a call.
+    unsafe fn run<H: FastCallHost>(
+        &mut self,
+        memory: &mut SharedMemory,
+        calls: &mut FastCalls<'_, '_, H>,
+    ) -> Result<Step, Fallback> {
+        let mut next_action = InterpreterAction::None;
+        let ContextConfig {
+            gas_params,
+            gas_mode,
+            host_call_log,
+            opcode_counts,
+            block_profile,
+            step_budget,
+            result_sink,
+            user_data,
+        } = calls.config.reborrow();
+        let mut ecx = EvmContext {
+            memory: &mut *memory,
+            contract: &mut self.contract,
+            gas: &mut self.gas,
+            host: &mut *calls.host,
+            next_action: &mut next_action,
+            return_data: &self.return_data,
+            func_stack: &mut self.func_stack,
+            is_static: self.is_static,
+            is_eof_init: false,
+            gas_params,
+            gas_mode,
+            host_call_log,
+            opcode_counts,
+            block_profile,
+            step_budget,
+            result_sink,
+            user_data,
+            resume_at: self.resume_at,
+            #[cfg(feature = "std")]
+            host_error: None,
+        };
+        let stack = EvmStack::from_mut_vec(&mut self.stack);
+        let result = self.function.call(Some(stack), Some(&mut self.stack_len), &mut ecx);
+        if !ecx.is_suspended() {
+            if result == InstructionResult::FatalExternalError {
+                #[cfg(feature = "std")]
+                {
+                    calls.host_error = ecx.take_host_error();
+                }
+                return Err(Fallback::Fatal);
+            }
+            return Ok(Step::Return(ecx.into_call_outcome(result, self.memory_offset.clone())));
+        }
+        self.resume_at = ecx.resume_at;
+        match next_action {
+            InterpreterAction::Call { inputs } => Ok(Step::Call(inputs)),
+            _ => Err(Fallback::SlowPath),
+        }
+    }
+
+    /// Same as [`Interpreter::insert_call_outcome`] for legacy bytecode.
+    fn insert_call_outcome(&mut self, memory: &mut SharedMemory, outcome: CallOutcome) {
+        let offset = outcome.memory_start();
+        let len = outcome.memory_length();
+        let result = outcome.result;
+        let success = result.result.is_ok();
+        if success || result.result.is_revert() {
+            self.gas.erase_cost(result.gas.remaining());
+            if success {
+                self.gas.record_refund(result.gas.refunded());
+            }
+            memory.set(offset, &result.output[..len.min(result.output.len())]);
+        }
+        self.return_data = result.output;
+        self.stack[self.stack_len] = U256::from(success).into();
+        self.stack_len += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GasMode;
+    use alloc::{boxed::Box, vec};
+    use revm_interpreter::{
+        analysis::to_analysed, gas, opcode as op, AccountLoad, CallScheme, CallValue, DummyHost,
+        Host, InterpreterResult, SStoreResult, SelfDestructResult, StateLoad,
+    };
+    use revm_primitives::{Address, Bytecode, Env, Log, B256};
+
+    const NOT_COMPILED: u8 = 9;
+
+    /// Calls `input[0]` with the rest of the input and half of its gas as address `0` and `4`, and
+    /// also transfers value as `6`, calls it with all of its gas as `7`, succeeds as `1`, reverts
+    /// as `2`, runs out of gas as `3`, selfdestructs as `5`, and fails a host call as `8`.
+    ///
+    /// Pushes its address and gas mode to the user data, if it is a `Vec`, and uses one step of
+    /// the step budget.
+    unsafe extern "C" fn test_contract(
+        _gas: *mut Gas,
+        stack: *mut EvmStack,
+        stack_len: *mut usize,
+        _env: *const Env,
+        _contract: *const crate::ContractView,
+        ecx: *mut EvmContext<'_>,
+    ) -> InstructionResult {
+        let (stack, stack_len, ecx) = (&mut *stack, &mut *stack_len, &mut *ecx);
+        let frame = (ecx.contract.target_address[19], ecx.gas_mode);
+        if let Some(frames) = ecx.user_data_mut::<Vec<(u8, GasMode)>>() {
+            frames.push(frame);
+        }
+        if let Some(budget) = ecx.step_budget.as_deref_mut() {
+            *budget -= 1;
+        }
+        let (result, output) = match ecx.contract.target_address[19] {
+            0 | 4 | 6 | 7 if ecx.resume_at == 0 => {
+                let target = Address::with_last_byte(ecx.contract.input[0]);
+                let transfers_value = ecx.contract.target_address[19] == 6;
+                let cost = if transfers_value { 100 + gas::CALLVALUE } else { 100 };
+                let mut gas_limit = ecx.gas.remaining() - cost;
+                if ecx.contract.target_address[19] != 7 {
+                    gas_limit /= 2;
+                }
+                ecx.memory.resize(32);
+                assert!(ecx.gas.record_cost(cost + gas_limit));
+                // The stipend is not charged to the caller.
+                if transfers_value {
+                    gas_limit += gas::CALL_STIPEND;
+                }
+                *ecx.next_action = InterpreterAction::Call {
+                    inputs: Box::new(CallInputs {
+                        input: ecx.contract.input.slice(1..),
+                        return_memory_offset: 0..32,
+                        gas_limit,
+                        bytecode_address: target,
+                        target_address: target,
+                        caller: ecx.contract.target_address,
+                        value: CallValue::Transfer(U256::from(transfers_value)),
+                        scheme: CallScheme::Call,
+                        is_static: false,
+                        is_eof: false,
+                    }),
+                };
+                ecx.resume_at = 1;
+                return InstructionResult::CallOrCreate;
+            }
+            0 | 4 | 6 | 7 => {
+                *stack_len -= 1;
+                let mut output = stack.as_slice()[*stack_len].to_be_bytes().to_vec();
+                output.extend_from_slice(ecx.memory.slice(0, 32));
+                output.extend_from_slice(ecx.return_data);
+                (InstructionResult::Return, output)
+            }
+            1 => {
+                assert!(ecx.gas.record_cost(300));
+                ecx.gas.record_refund(10);
+                ecx.memory.resize(64);
+                ecx.memory.set(32, &[0x11; 32]);
+                (InstructionResult::Return, ecx.memory.slice(32, 32).to_vec())
+            }
+            2 => {
+                assert!(ecx.gas.record_cost(200));
+                (InstructionResult::Revert, vec![0x82, 0xb4, 0x29, 0x00])
+            }
+            3 => {
+                assert!(ecx.gas.record_cost(50));
+                return InstructionResult::OutOfGas;
+            }
+            5 => {
+                assert!(ecx.gas.record_cost(5000));
+                ecx.gas.record_refund(24000);
+                (InstructionResult::SelfDestruct, vec![])
+            }
+            8 => {
+                #[cfg(feature = "std")]
+                ecx.set_host_error("sload");
+                return InstructionResult::FatalExternalError;
+            }
+            _ => unreachable!(),
+        };
+        *ecx.next_action = InterpreterAction::Return {
+            result: InterpreterResult { result, output: output.into(), gas: *ecx.gas },
+        };
+        result
+    }
+
+    /// Records entered and exited call frames, with whether they were committed.
+    #[derive(Default)]
+    struct TestHost {
+        inner: DummyHost,
+        journal: Vec<(u8, Option<bool>)>,
+    }
+
+    impl TestHost {
+        fn contract(&self, inputs: &CallInputs) -> Option<Contract> {
+            let callee = inputs.target_address[19];
+            if callee == NOT_COMPILED {
+                return None;
+            }
+            let bytecode = to_analysed(Bytecode::new_raw(Bytes::from_static(&[op::STOP])));
+            let hash = B256::with_last_byte(callee);
+            Some(Contract::new_with_context(inputs.input.clone(), bytecode, Some(hash), inputs))
+        }
+    }
+
+    impl FastCallHost for TestHost {
+        type Checkpoint = u8;
+
+        fn enter_call(&mut self, inputs: &CallInputs) -> Option<FastCall<u8>> {
+            let contract = self.contract(inputs)?;
+            let callee = inputs.target_address[19];
+            self.journal.push((callee, None));
+            let function = EvmCompilerFn::new(test_contract);
+            Some(FastCall { function, contract, checkpoint: callee })
+        }
+
+        fn exit_call(&mut self, checkpoint: u8, result: InstructionResult) {
+            self.journal.push((checkpoint, Some(result.is_ok())));
+        }
+    }
+
+    impl Host for TestHost {
+        fn env(&self) -> &Env {
+            self.inner.env()
+        }
+
+        fn env_mut(&mut self) -> &mut Env {
+            self.inner.env_mut()
+        }
+
+        fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+            self.inner.load_account_delegated(address)
+        }
+
+        fn block_hash(&mut self, number: u64) -> Option<B256> {
+            self.inner.block_hash(number)
+        }
+
+        fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+            self.inner.balance(address)
+        }
+
+        fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+            self.inner.code(address)
+        }
+
+        fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+            self.inner.code_hash(address)
+        }
+
+        fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+            self.inner.sload(address, index)
+        }
+
+        fn sstore(
+            &mut self,
+            address: Address,
+            index: U256,
+            value: U256,
+        ) -> Option<StateLoad<SStoreResult>> {
+            self.inner.sstore(address, index, value)
+        }
+
+        fn tload(&mut self, address: Address, index: U256) -> U256 {
+            self.inner.tload(address, index)
+        }
+
+        fn tstore(&mut self, address: Address, index: U256, value: U256) {
+            self.inner.tstore(address, index, value)
+        }
+
+        fn log(&mut self, log: Log) {
+            self.inner.log(log)
+        }
+
+        fn selfdestruct(
+            &mut self,
+            address: Address,
+            target: Address,
+        ) -> Option<StateLoad<SelfDestructResult>> {
+            self.inner.selfdestruct(address, target)
+        }
+    }
+
+    /// Runs the interpreter's contract to completion, executing calls directly if `stacks` is
+    /// set, and on the slow path otherwise.
+    unsafe fn run(
+        host: &mut TestHost,
+        interpreter: &mut Interpreter,
+        memory: &mut SharedMemory,
+        mut stacks: Option<&mut StackPool>,
+    ) -> InterpreterResult {
+        let f = EvmCompilerFn::new(test_contract);
+        loop {
+            let action = match stacks.as_deref_mut() {
+                Some(stacks) => {
+                    let config = &mut ContextConfig::default();
+                    f.call_with_fast_calls(interpreter, memory, host, stacks, config)
+                }
+                None => f.call_with_interpreter_and_memory(interpreter, memory, host),
+            };
+            let inputs = match action {
+                InterpreterAction::Return { result } => return result,
+                InterpreterAction::Call { inputs } => inputs,
+                action => panic!("{action:?}"),
+            };
+            let result = match host.contract(&inputs) {
+                Some(contract) => {
+                    let callee = inputs.target_address[19];
+                    host.journal.push((callee, None));
+                    let mut child = Interpreter::new(contract, inputs.gas_limit, inputs.is_static);
+                    memory.new_context();
+                    let result = run(host, &mut child, memory, stacks.as_deref_mut());
+                    memory.free_context();
+                    host.journal.push((callee, Some(result.is_ok())));
+                    result
+                }
+                None => InterpreterResult {
+                    result: InstructionResult::Stop,
+                    output: Bytes::new(),
+                    gas: Gas::new(inputs.gas_limit),
+                },
+            };
+            let outcome = CallOutcome::new(result, inputs.return_memory_offset.clone());
+            interpreter.shared_memory = core::mem::replace(memory, EMPTY_SHARED_MEMORY);
+            FrameOutcome::Call(outcome).insert_into(interpreter);
+            *memory = interpreter.take_memory();
+        }
+    }
+
+    fn execute(input: &[u8], stacks: Option<&mut StackPool>) -> (InterpreterResult, TestHost) {
+        let contract = Contract {
+            input: Bytes::copy_from_slice(input),
+            ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+        };
+        let mut host = TestHost::default();
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let mut memory = SharedMemory::new();
+        let result = unsafe { run(&mut host, &mut interpreter, &mut memory, stacks) };
+        (result, host)
+    }
+
+    #[test]
+    fn fast_calls() {
+        let mut stacks = StackPool::new();
+        for input in [
+            &[1][..],
+            &[2],
+            &[3],
+            &[5],
+            &[4, 1],
+            &[4, 2],
+            &[4, 3],
+            &[4, 5],
+            &[6, 1],
+            &[6, 2],
+            &[6, 3],
+            &[6, 5],
+            &[NOT_COMPILED],
+        ] {
+            let (expected, slow) = execute(input, None);
+            let (result, fast) = execute(input, Some(&mut stacks));
+            assert_eq!(result, expected, "{input:?}");
+            assert_eq!(fast.journal, slow.journal, "{input:?}");
+        }
+        // One stack for each nested frame.
+        assert_eq!(stacks.len(), 2);
+
+        let (result, _) = execute(&[1], None);
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(result.output[31], 1);
+        assert_eq!(result.output[32..64], [0x11; 32]);
+        assert_eq!(result.output[64..], [0x11; 32]);
+        assert_eq!(result.gas.spent(), 100 + 300);
+        assert_eq!(result.gas.refunded(), 10);
+
+        let (result, _) = execute(&[2], None);
+        assert_eq!(result.output[31], 0);
+        assert_eq!(result.output[32..36], [0x82, 0xb4, 0x29, 0x00]);
+        assert_eq!(result.output[64..], [0x82, 0xb4, 0x29, 0x00]);
+        assert_eq!(result.gas.spent(), 100 + 200);
+        assert_eq!(result.gas.refunded(), 0);
+
+        let (result, _) = execute(&[3], None);
+        assert_eq!(result.output[31], 0);
+        assert_eq!(result.gas.spent(), 100 + (100_000 - 100) / 2);
+    }
+
+    #[test]
+    fn fast_calls_fallback() {
+        let input = [4, NOT_COMPILED];
+        let (expected, slow) = execute(&input, None);
+        assert_eq!(slow.journal, [(4, None), (4, Some(true))]);
+
+        let mut stacks = StackPool::new();
+        let (result, fast) = execute(&input, Some(&mut stacks));
+        assert_eq!(result, expected);
+        assert_eq!(fast.journal, [(4, None), (4, Some(false)), (4, None), (4, Some(true))]);
+    }
+
+    #[test]
+    fn fast_calls_fallback_restores_sinks() {
+        let contract = Contract {
+            input: Bytes::from_static(&[4, NOT_COMPILED]),
+            ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+        };
+        let mut host = TestHost::default();
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let mut memory = SharedMemory::new();
+        let mut stacks = StackPool::new();
+        let mut budget = 10;
+        let mut config = ContextConfig { step_budget: Some(&mut budget), ..Default::default() };
+        let f = EvmCompilerFn::new(test_contract);
+        let action = unsafe {
+            f.call_with_fast_calls(
+                &mut interpreter,
+                &mut memory,
+                &mut host,
+                &mut stacks,
+                &mut config,
+            )
+        };
+        let InterpreterAction::Call { inputs } = action else { panic!("{action:?}") };
+        assert_eq!(inputs.target_address[19], 4);
+        // Only the step of the outermost frame, since the slow path executes `4` again.
+        assert_eq!(budget, 9);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fast_calls_host_error() {
+        let contract = Contract {
+            input: Bytes::from_static(&[4, 8]),
+            ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+        };
+        let mut host = TestHost::default();
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let mut memory = SharedMemory::new();
+        let mut stacks = StackPool::new();
+        let f = EvmCompilerFn::new(test_contract);
+        let error = unsafe {
+            f.try_call_with_fast_calls(
+                &mut interpreter,
+                &mut memory,
+                &mut host,
+                &mut stacks,
+                &mut ContextConfig::default(),
+            )
+        }
+        .unwrap_err();
+        assert_eq!(error.to_string(), "host call `sload` failed");
+        assert_eq!(interpreter.instruction_result, InstructionResult::FatalExternalError);
+        // The failed host call is not made again on the slow path.
+        assert_eq!(host.journal, [(4, None), (8, None), (8, Some(false)), (4, Some(false))]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fast_calls_deep() {
+        const DEPTH: usize = 1024;
+
+        // Too small for the frames to be executed recursively.
+        let thread = std::thread::Builder::new().stack_size(64 * 1024).spawn(|| {
+            let mut input = vec![7; DEPTH - 1];
+            input.push(1);
+            let contract = Contract {
+                input: input.into(),
+                ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+            };
+            let mut host = TestHost::default();
+            let mut interpreter = Interpreter::new(contract, 10_000_000, false);
+            let mut memory = SharedMemory::new();
+            let mut stacks = StackPool::new();
+            let f = EvmCompilerFn::new(test_contract);
+            let action = unsafe {
+                f.call_with_fast_calls(
+                    &mut interpreter,
+                    &mut memory,
+                    &mut host,
+                    &mut stacks,
+                    &mut ContextConfig::default(),
+                )
+            };
+            (action, host.journal, stacks.len())
+        });
+        let (action, journal, stacks) = thread.unwrap().join().unwrap();
+        let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(result.gas.spent(), 100 * DEPTH as u64 + 300);
+        assert_eq!(journal.len(), 2 * DEPTH);
+        assert!(journal[DEPTH..].iter().all(|&(_, committed)| committed == Some(true)));
+        assert_eq!(stacks, DEPTH);
+    }
+
+    #[test]
+    fn fast_calls_inherit_config() {
+        let contract = Contract {
+            input: Bytes::from_static(&[4, 0, 1]),
+            ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+        };
+        let mut host = TestHost::default();
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let mut memory = SharedMemory::new();
+        let mut stacks = StackPool::new();
+        let mut frames = Vec::<(u8, GasMode)>::new();
+        let mut config = ContextConfig {
+            gas_mode: GasMode::Disabled,
+            user_data: Some(&mut frames),
+            ..Default::default()
+        };
+        let f = EvmCompilerFn::new(test_contract);
+        let action = unsafe {
+            f.call_with_fast_calls(
+                &mut interpreter,
+                &mut memory,
+                &mut host,
+                &mut stacks,
+                &mut config,
+            )
+        };
+        let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(host.journal.len(), 6);
+
+        // The outermost frame has address 0.
+        let expected = [0, 4, 0, 1, 0, 4, 0].map(|address| (address, GasMode::Disabled));
+        assert_eq!(frames, expected);
+    }
+}
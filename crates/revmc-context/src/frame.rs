@@ -0,0 +1,271 @@
+//! Owned snapshots of suspended frames.
+
+use crate::{EvmCompilerFn, EvmContext, EvmStack, ResumeAt, RevertReason};
+use alloc::vec::Vec;
+use core::fmt;
+use revm_interpreter::{
+    CallOutcome, CreateOutcome, Gas, InstructionResult, Interpreter, InterpreterAction,
+    SharedMemory,
+};
+use revm_primitives::{Bytes, B256, U256};
+
+/// An owned snapshot of a compiled function that is suspended on a call or create.
+///
+/// This contains everything that the function needs to be resumed, so it can be serialized on
+/// one machine and resumed with [`EvmCompilerFn::resume_from`] on another that has the same
+/// compiled code.
+///
+/// The snapshot is tied to the compiled code by [`artifact`](Self::artifact), a caller-chosen
+/// identifier of the code and how it was compiled, such as a hash of the bytecode, spec, and
+/// compiler configuration. The resume point is stored as an index into the function's resume
+/// points rather than as a code address, so only functions whose [`FnMeta::resume_indexes`] is
+/// set can be captured, which is guaranteed by compiling with `EvmCompiler::validate_resume_at`.
+///
+/// [`FnMeta::resume_indexes`]: crate::FnMeta::resume_indexes
+///
+/// [`EvmCompilerFn::resume_from`]: crate::EvmCompilerFn::resume_from
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SuspendedFrame {
+    /// The identifier of the compiled code.
+    pub artifact: B256,
+    /// The index of the resume point.
+    pub resume_index: usize,
+    /// The live part of the stack, from bottom to top.
+    pub stack: Vec<U256>,
+    /// The memory of the current call.
+    pub memory: Bytes,
+    /// The gas.
+    pub gas: Gas,
+    /// The return data of the last call.
+    pub return_data: Bytes,
+    /// Whether the context is static.
+    pub is_static: bool,
+    /// Whether the context is EOF init.
+    pub is_eof_init: bool,
+}
+
+/// The result of the call or create that a [`SuspendedFrame`] is suspended on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameOutcome {
+    /// The result of a call.
+    Call(CallOutcome),
+    /// The result of a legacy create.
+    Create(CreateOutcome),
+    /// The result of an EOF create.
+    EOFCreate(CreateOutcome),
+}
+
+/// The error returned when restoring a [`SuspendedFrame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuspendedFrameError {
+    /// The function's resume points are code addresses, or its [`FnMeta`](crate::FnMeta) is not
+    /// known, so they cannot be used in another process.
+    NotPortable,
+    /// The frame was captured from different compiled code.
+    ArtifactMismatch {
+        /// The expected artifact.
+        expected: B256,
+        /// The artifact of the frame.
+        found: B256,
+    },
+}
+
+impl fmt::Display for SuspendedFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotPortable => f.write_str("resume points of the function are not indexes"),
+            Self::ArtifactMismatch { expected, found } => {
+                write!(f, "frame was captured from artifact {found}, expected {expected}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SuspendedFrameError {}
+
+impl SuspendedFrame {
+    /// Captures a suspended frame of `f`.
+    ///
+    /// `stack` and `stack_len` are the stack and stack length that the function was called with.
+    ///
+    /// Returns an error if the resume points of `f` are not indexes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function is not suspended, see [`EvmContext::is_suspended`].
+    pub fn capture(
+        f: &EvmCompilerFn,
+        ecx: &EvmContext<'_>,
+        stack: &EvmStack,
+        stack_len: usize,
+        artifact: B256,
+    ) -> Result<Self, SuspendedFrameError> {
+        assert!(ecx.is_suspended(), "function is not suspended");
+        if !f.has_resume_indexes() {
+            return Err(SuspendedFrameError::NotPortable);
+        }
+        Ok(Self {
+            artifact,
+            resume_index: ecx.resume_at,
+            stack: stack.as_slice()[..stack_len].iter().map(|word| word.to_u256()).collect(),
+            memory: ecx.memory.context_memory().to_vec().into(),
+            gas: *ecx.gas,
+            return_data: ecx.return_data.to_vec().into(),
+            is_static: ecx.is_static,
+            is_eof_init: ecx.is_eof_init,
+        })
+    }
+
+    /// Restores the frame into an interpreter, so that calling the compiled function with it
+    /// resumes execution.
+    ///
+    /// The interpreter must have been created with the same contract as the one the frame was
+    /// captured from. Its stack, memory, gas, and return data are replaced.
+    ///
+    /// Returns an error if the frame was not captured from the `expected` artifact.
+    pub fn restore_into(
+        self,
+        interpreter: &mut Interpreter,
+        expected: B256,
+    ) -> Result<(), SuspendedFrameError> {
+        if self.artifact != expected {
+            return Err(SuspendedFrameError::ArtifactMismatch { expected, found: self.artifact });
+        }
+        interpreter.stack.data_mut().clear();
+        interpreter.stack.data_mut().extend_from_slice(&self.stack);
+        let mut memory = SharedMemory::new();
+        memory.resize(self.memory.len());
+        memory.set(0, &self.memory);
+        interpreter.shared_memory = memory;
+        interpreter.gas = self.gas;
+        interpreter.return_data_buffer = self.return_data;
+        interpreter.is_static = self.is_static;
+        interpreter.is_eof_init = self.is_eof_init;
+        interpreter.instruction_result = InstructionResult::Continue;
+        interpreter.next_action = InterpreterAction::None;
+        ResumeAt::store(&mut interpreter.instruction_pointer, self.resume_index);
+        Ok(())
+    }
+}
+
+impl FrameOutcome {
+    /// Decodes the output of the outcome if it is a revert.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        let result = match self {
+            Self::Call(outcome) => &outcome.result,
+            Self::Create(outcome) | Self::EOFCreate(outcome) => &outcome.result,
+        };
+        result.result.is_revert().then(|| RevertReason::decode(&result.output))
+    }
+
+    /// Inserts the outcome into the interpreter, like revm does before resuming the frame.
+    ///
+    /// The callee's remaining gas, including any unused call stipend, is credited back to the
+    /// caller if the call returned or reverted, and its refund is only merged if it returned.
+    pub fn insert_into(self, interpreter: &mut Interpreter) {
+        match self {
+            Self::Call(outcome) => {
+                let mut memory = interpreter.take_memory();
+                interpreter.insert_call_outcome(&mut memory, outcome);
+                interpreter.shared_memory = memory;
+            }
+            Self::Create(outcome) => interpreter.insert_create_outcome(outcome),
+            Self::EOFCreate(outcome) => interpreter.insert_eofcreate_outcome(outcome),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FnMeta;
+    use revm_interpreter::{Contract, DummyHost, InterpreterResult};
+
+    unsafe extern "C" fn test_fn(
+        _gas: *mut Gas,
+        _stack: *mut EvmStack,
+        _stack_len: *mut usize,
+        _env: *const revm_primitives::Env,
+        _contract: *const crate::ContractView,
+        _ecx: *mut EvmContext<'_>,
+    ) -> InstructionResult {
+        InstructionResult::Stop
+    }
+
+    fn suspended(interpreter: &mut Interpreter, host: &mut DummyHost) -> SuspendedFrame {
+        let mut stack = EvmStack::new();
+        stack.as_mut_slice()[0] = U256::from(0x42).into();
+        let mut ecx = EvmContext::from_interpreter(interpreter, host);
+        ecx.memory.resize(32);
+        ecx.memory.set(0, &[1, 2, 3]);
+        ecx.resume_at = 2;
+        *ecx.next_action = InterpreterAction::Return {
+            result: InterpreterResult {
+                result: InstructionResult::Stop,
+                output: Bytes::new(),
+                gas: Gas::new(0),
+            },
+        };
+        assert!(!ecx.is_suspended());
+        *ecx.next_action = InterpreterAction::Create {
+            inputs: alloc::boxed::Box::new(revm_interpreter::CreateInputs {
+                caller: Default::default(),
+                scheme: revm_primitives::CreateScheme::Create,
+                value: U256::ZERO,
+                init_code: Bytes::new(),
+                gas_limit: 0,
+            }),
+        };
+        let f = EvmCompilerFn::new(test_fn);
+        let r = SuspendedFrame::capture(&f, &ecx, &stack, 1, B256::repeat_byte(1));
+        assert_eq!(r, Err(SuspendedFrameError::NotPortable));
+        let f = EvmCompilerFn::new_with_meta(test_fn, FnMeta::default());
+        let r = SuspendedFrame::capture(&f, &ecx, &stack, 1, B256::repeat_byte(1));
+        assert_eq!(r, Err(SuspendedFrameError::NotPortable));
+        let meta = FnMeta { resume_indexes: true, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        SuspendedFrame::capture(&f, &ecx, &stack, 1, B256::repeat_byte(1)).unwrap()
+    }
+
+    #[test]
+    fn capture_restore() {
+        let mut host = DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 1000, true);
+        interpreter.shared_memory = SharedMemory::new();
+        let frame = suspended(&mut interpreter, &mut host);
+        assert_eq!(frame.resume_index, 2);
+        assert_eq!(frame.stack, [U256::from(0x42)]);
+        assert_eq!(frame.memory.len(), 32);
+        assert_eq!(frame.memory[..3], [1, 2, 3]);
+        assert!(frame.is_static);
+
+        let mut fresh = Interpreter::new(Contract::default(), 0, false);
+        let r = frame.clone().restore_into(&mut fresh, B256::ZERO);
+        let expected = SuspendedFrameError::ArtifactMismatch {
+            expected: B256::ZERO,
+            found: B256::repeat_byte(1),
+        };
+        assert_eq!(r, Err(expected));
+
+        frame.clone().restore_into(&mut fresh, frame.artifact).unwrap();
+        assert_eq!(fresh.stack.data(), &frame.stack);
+        assert_eq!(fresh.shared_memory.context_memory(), &frame.memory[..]);
+        assert_eq!(fresh.gas, frame.gas);
+        assert!(fresh.is_static);
+        let ecx = EvmContext::from_interpreter(&mut fresh, &mut host);
+        assert_eq!(ecx.resume_at, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let mut host = DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 1000, false);
+        interpreter.shared_memory = SharedMemory::new();
+        let frame = suspended(&mut interpreter, &mut host);
+        let json = serde_json::to_vec(&frame).unwrap();
+        assert_eq!(serde_json::from_slice::<SuspendedFrame>(&json).unwrap(), frame);
+    }
+}
@@ -0,0 +1,519 @@
+use revm_interpreter::{
+    gas::{self, CALLVALUE, CALL_STIPEND, INSTANBUL_SLOAD_GAS, NEWACCOUNT, REFUND_SSTORE_CLEARS},
+    num_words, AccountLoad, Eip7702CodeLoad, Gas, SStoreResult, SelfDestructResult, StateLoad,
+};
+use revm_primitives::SpecId;
+
+/// The parameters of the dynamic gas costs that are paid at runtime.
+///
+/// The default values are the Ethereum mainnet costs. Costs that only apply before the Berlin
+/// hardfork are not configurable.
+///
+/// All the arithmetic on the parameters saturates, so a cost that does not fit in a `u64` makes the
+/// execution run out of gas instead of overflowing.
+///
+/// Set [`EvmContext::gas_params`](crate::EvmContext::gas_params) to use a custom set of
+/// parameters, for example to experiment with repricing memory and storage opcodes. The base cost
+/// of each opcode is compiled into the function instead, and is overridden together with these
+/// parameters with `EvmCompiler::gas_table`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GasParams {
+    /// The linear cost of a memory word.
+    pub memory_word: u64,
+    /// The divisor of the quadratic memory expansion cost.
+    pub memory_quotient: u64,
+    /// The cost of copying a word in `*COPY` instructions.
+    pub copy_word: u64,
+    /// The cost of hashing a word in `KECCAK256` and `CREATE2`.
+    pub keccak256_word: u64,
+    /// The cost of accessing a warm account or storage slot.
+    pub warm_access: u64,
+    /// The cost of accessing a cold account.
+    pub cold_account_access: u64,
+    /// The cost of accessing a cold storage slot.
+    pub cold_sload: u64,
+    /// The cost of setting a storage slot from zero to non-zero.
+    pub sstore_set: u64,
+    /// The cost of changing a non-zero storage slot, including the cold access cost since Berlin.
+    pub sstore_reset: u64,
+    /// The refund for clearing a storage slot since London.
+    pub sstore_clears_refund: u64,
+}
+
+impl Default for GasParams {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl GasParams {
+    /// The Ethereum mainnet gas parameters.
+    pub const DEFAULT: Self = Self {
+        memory_word: gas::MEMORY,
+        memory_quotient: 512,
+        copy_word: gas::COPY,
+        keccak256_word: gas::KECCAK256WORD,
+        warm_access: gas::WARM_STORAGE_READ_COST,
+        cold_account_access: gas::COLD_ACCOUNT_ACCESS_COST,
+        cold_sload: gas::COLD_SLOAD_COST,
+        sstore_set: gas::SSTORE_SET,
+        sstore_reset: gas::SSTORE_RESET,
+        sstore_clears_refund: gas::SSTORE_RESET - gas::COLD_SLOAD_COST
+            + gas::ACCESS_LIST_STORAGE_KEY,
+    };
+
+    /// Memory expansion cost for a given number of words.
+    #[inline]
+    pub const fn memory_gas(&self, num_words: u64) -> u64 {
+        let squared = num_words.saturating_mul(num_words);
+        let quadratic = match squared.checked_div(self.memory_quotient) {
+            Some(quadratic) => quadratic,
+            None if squared == 0 => 0,
+            None => u64::MAX,
+        };
+        self.memory_word.saturating_mul(num_words).saturating_add(quadratic)
+    }
+
+    /// Dynamic `*COPY` cost for the given length.
+    #[inline]
+    pub const fn copy_cost(&self, len: u64) -> Option<u64> {
+        self.copy_word.checked_mul(num_words(len))
+    }
+
+    /// Dynamic `KECCAK256` cost for the given length.
+    #[inline]
+    pub const fn keccak256_cost(&self, len: u64) -> Option<u64> {
+        self.keccak256_word.checked_mul(num_words(len))
+    }
+
+    /// Berlin warm and cold account access cost.
+    #[inline]
+    pub const fn warm_cold_cost(&self, is_cold: bool) -> u64 {
+        if is_cold {
+            self.cold_account_access
+        } else {
+            self.warm_access
+        }
+    }
+
+    /// Berlin warm and cold account access cost, including the cost of loading the delegated
+    /// account, if any.
+    #[inline]
+    pub const fn warm_cold_cost_with_delegation(&self, load: Eip7702CodeLoad<()>) -> u64 {
+        let mut gas = self.warm_cold_cost(load.state_load.is_cold);
+        if let Some(is_cold) = load.is_delegate_account_cold {
+            gas = gas.saturating_add(self.warm_cold_cost(is_cold));
+        }
+        gas
+    }
+
+    /// `EXTCODECOPY` cost, without the memory expansion cost.
+    #[inline]
+    pub const fn extcodecopy_cost(&self, spec_id: SpecId, len: u64, is_cold: bool) -> Option<u64> {
+        let base_gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
+            self.warm_cold_cost(is_cold)
+        } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
+            700
+        } else {
+            20
+        };
+        match self.copy_cost(len) {
+            Some(copy_cost) => base_gas.checked_add(copy_cost),
+            None => None,
+        }
+    }
+
+    /// `SLOAD` cost.
+    #[inline]
+    pub const fn sload_cost(&self, spec_id: SpecId, is_cold: bool) -> u64 {
+        if spec_id.is_enabled_in(SpecId::BERLIN) {
+            if is_cold {
+                self.cold_sload
+            } else {
+                self.warm_access
+            }
+        } else {
+            gas::sload_cost(spec_id, is_cold)
+        }
+    }
+
+    /// `SSTORE` cost. Returns `None` if the remaining gas is not above the call stipend.
+    #[inline]
+    pub fn sstore_cost(
+        &self,
+        spec_id: SpecId,
+        vals: &SStoreResult,
+        remaining: u64,
+        is_cold: bool,
+    ) -> Option<u64> {
+        // EIP-1706 Disable SSTORE with gasleft lower than call stipend
+        if spec_id.is_enabled_in(SpecId::ISTANBUL) && remaining <= CALL_STIPEND {
+            return None;
+        }
+
+        if spec_id.is_enabled_in(SpecId::BERLIN) {
+            let warm_sstore_reset = self.sstore_reset.saturating_sub(self.cold_sload);
+            let mut gas = self.istanbul_sstore_cost(vals, self.warm_access, warm_sstore_reset);
+            if is_cold {
+                gas = gas.saturating_add(self.cold_sload);
+            }
+            Some(gas)
+        } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+            Some(self.istanbul_sstore_cost(vals, INSTANBUL_SLOAD_GAS, self.sstore_reset))
+        } else if vals.is_present_zero() && !vals.is_new_zero() {
+            Some(self.sstore_set)
+        } else {
+            Some(self.sstore_reset)
+        }
+    }
+
+    /// EIP-2200: Structured Definitions for Net Gas Metering
+    #[inline]
+    fn istanbul_sstore_cost(&self, vals: &SStoreResult, sload: u64, sstore_reset: u64) -> u64 {
+        if vals.is_new_eq_present() {
+            sload
+        } else if vals.is_original_eq_present() && vals.is_original_zero() {
+            self.sstore_set
+        } else if vals.is_original_eq_present() {
+            sstore_reset
+        } else {
+            sload
+        }
+    }
+
+    /// `SSTORE` refund.
+    pub fn sstore_refund(&self, spec_id: SpecId, vals: &SStoreResult) -> i64 {
+        if !spec_id.is_enabled_in(SpecId::ISTANBUL) {
+            return if !vals.is_present_zero() && vals.is_new_zero() {
+                REFUND_SSTORE_CLEARS
+            } else {
+                0
+            };
+        }
+
+        // EIP-3529: Reduction in refunds
+        let sstore_clears_schedule = if spec_id.is_enabled_in(SpecId::LONDON) {
+            saturating_i64(self.sstore_clears_refund)
+        } else {
+            REFUND_SSTORE_CLEARS
+        };
+        if vals.is_new_eq_present() {
+            0
+        } else if vals.is_original_eq_present() && vals.is_new_zero() {
+            sstore_clears_schedule
+        } else {
+            let mut refund = 0;
+
+            if !vals.is_original_zero() {
+                if vals.is_present_zero() {
+                    refund -= sstore_clears_schedule;
+                } else if vals.is_new_zero() {
+                    refund += sstore_clears_schedule;
+                }
+            }
+
+            if vals.is_original_eq_new() {
+                let (sstore_reset, sload) = if spec_id.is_enabled_in(SpecId::BERLIN) {
+                    (self.sstore_reset.saturating_sub(self.cold_sload), self.warm_access)
+                } else {
+                    (self.sstore_reset, self.sload_cost(spec_id, false))
+                };
+                let restored = if vals.is_original_zero() { self.sstore_set } else { sstore_reset };
+                refund = refund.saturating_add(saturating_i64(restored.saturating_sub(sload)));
+            }
+
+            refund
+        }
+    }
+
+    /// `*CALL*` cost, without the memory expansion cost and the gas passed to the callee.
+    #[inline]
+    pub const fn call_cost(
+        &self,
+        spec_id: SpecId,
+        transfers_value: bool,
+        account_load: AccountLoad,
+    ) -> u64 {
+        let mut gas = if spec_id.is_enabled_in(SpecId::BERLIN) {
+            self.warm_cold_cost_with_delegation(account_load.load)
+        } else {
+            gas::call_cost(spec_id, false, AccountLoad { is_empty: false, ..account_load })
+        };
+        if transfers_value {
+            gas = gas.saturating_add(CALLVALUE);
+        }
+        // EIP-161: State trie clearing (invariant-preserving alternative)
+        if account_load.is_empty
+            && (transfers_value || !spec_id.is_enabled_in(SpecId::SPURIOUS_DRAGON))
+        {
+            gas = gas.saturating_add(NEWACCOUNT);
+        }
+        gas
+    }
+
+    /// `SELFDESTRUCT` cost.
+    #[inline]
+    pub const fn selfdestruct_cost(
+        &self,
+        spec_id: SpecId,
+        res: StateLoad<SelfDestructResult>,
+    ) -> u64 {
+        let is_cold = res.is_cold;
+        let mut gas = gas::selfdestruct_cost(spec_id, StateLoad { is_cold: false, ..res });
+        if spec_id.is_enabled_in(SpecId::BERLIN) && is_cold {
+            gas = gas.saturating_add(self.cold_account_access);
+        }
+        gas
+    }
+}
+
+/// Converts a gas cost to a refund, saturating at `i64::MAX`.
+#[inline]
+const fn saturating_i64(x: u64) -> i64 {
+    if x > i64::MAX as u64 {
+        i64::MAX
+    } else {
+        x as i64
+    }
+}
+
+/// How gas costs are metered.
+///
+/// Set with `EvmCompiler::gas_metering_mode` for the compiled code, and with
+/// [`EvmContext::gas_mode`](crate::EvmContext::gas_mode) for the costs paid by builtins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GasMode {
+    /// Gas costs are paid, and execution halts when the gas limit is exceeded.
+    #[default]
+    Metered,
+    /// Gas costs are paid, but execution never halts with an out-of-gas error.
+    ///
+    /// When a cost exceeds the remaining gas, the gas limit is raised by the cost instead, so that
+    /// [`Gas::spent`] is the total cost of the execution, and the remaining gas seen by `GAS`,
+    /// `SSTORE` and calls is unchanged. This is useful for estimating the gas of calls that are
+    /// executed without a meaningful gas limit, such as `eth_call`.
+    ObserveOnly,
+    /// Gas costs are not tracked, neither by the compiled code nor by the builtins.
+    ///
+    /// Execution never halts with an out-of-gas error, and [`Gas::spent`] is not updated. This is
+    /// useful for testing the semantics of the compiled code, where gas differences would mask
+    /// value differences. See also [`EvmContext::disable_gas`](crate::EvmContext::disable_gas).
+    Disabled,
+}
+
+impl GasMode {
+    /// Records a gas cost in this mode.
+    ///
+    /// Returns `false` if there is not enough gas remaining, which is never the case in
+    /// [`ObserveOnly`](Self::ObserveOnly) mode. In [`Disabled`](Self::Disabled) mode, nothing is
+    /// recorded and this always returns `true`.
+    #[inline]
+    pub fn record_cost(self, gas: &mut Gas, cost: u64) -> bool {
+        if self == Self::Disabled {
+            return true;
+        }
+        if gas.record_cost(cost) {
+            return true;
+        }
+        if self != Self::ObserveOnly {
+            return false;
+        }
+        let (remaining, refunded) = (gas.remaining(), gas.refunded());
+        let limit = gas.limit().saturating_add(cost);
+        *gas = Gas::new(limit);
+        let recorded = gas.record_cost(limit - remaining);
+        debug_assert!(recorded);
+        gas.set_refund(refunded);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_interpreter::{Eip7702CodeLoad, SStoreResult};
+    use revm_primitives::U256;
+
+    const SPECS: &[SpecId] = &[
+        SpecId::FRONTIER,
+        SpecId::TANGERINE,
+        SpecId::SPURIOUS_DRAGON,
+        SpecId::ISTANBUL,
+        SpecId::BERLIN,
+        SpecId::LONDON,
+        SpecId::CANCUN,
+        SpecId::PRAGUE,
+    ];
+
+    #[test]
+    fn default_matches_revm() {
+        let p = GasParams::default();
+        for words in [0, 1, 2, 100, 1 << 20, u64::MAX] {
+            assert_eq!(p.memory_gas(words), gas::memory_gas(words));
+        }
+        for len in [0, 1, 32, 33, 1000] {
+            assert_eq!(
+                p.copy_cost(len).unwrap() + gas::VERYLOW,
+                gas::verylowcopy_cost(len).unwrap()
+            );
+            assert_eq!(
+                p.keccak256_cost(len).unwrap() + gas::KECCAK256,
+                gas::keccak256_cost(len).unwrap()
+            );
+        }
+
+        let values = [U256::ZERO, U256::from(1), U256::from(2)];
+        for &spec_id in SPECS {
+            for is_cold in [false, true] {
+                assert_eq!(p.sload_cost(spec_id, is_cold), gas::sload_cost(spec_id, is_cold));
+                assert_eq!(
+                    p.extcodecopy_cost(spec_id, 69, is_cold),
+                    gas::extcodecopy_cost(spec_id, 69, is_cold)
+                );
+
+                for delegate in [None, Some(false), Some(true)] {
+                    let load = Eip7702CodeLoad {
+                        state_load: StateLoad::new((), is_cold),
+                        is_delegate_account_cold: delegate,
+                    };
+                    if spec_id.is_enabled_in(SpecId::BERLIN) {
+                        assert_eq!(
+                            p.warm_cold_cost_with_delegation(load.clone()),
+                            gas::warm_cold_cost_with_delegation(load.clone())
+                        );
+                    }
+                    for is_empty in [false, true] {
+                        for transfers_value in [false, true] {
+                            let account_load = AccountLoad { load: load.clone(), is_empty };
+                            assert_eq!(
+                                p.call_cost(spec_id, transfers_value, account_load.clone()),
+                                gas::call_cost(spec_id, transfers_value, account_load)
+                            );
+                        }
+                    }
+                }
+
+                for had_value in [false, true] {
+                    for target_exists in [false, true] {
+                        let res = StateLoad::new(
+                            SelfDestructResult {
+                                had_value,
+                                target_exists,
+                                previously_destroyed: false,
+                            },
+                            is_cold,
+                        );
+                        assert_eq!(
+                            p.selfdestruct_cost(spec_id, res.clone()),
+                            gas::selfdestruct_cost(spec_id, res)
+                        );
+                    }
+                }
+
+                for original_value in values {
+                    for present_value in values {
+                        for new_value in values {
+                            let vals = SStoreResult { original_value, present_value, new_value };
+                            for remaining in [CALL_STIPEND, 100_000] {
+                                assert_eq!(
+                                    p.sstore_cost(spec_id, &vals, remaining, is_cold),
+                                    gas::sstore_cost(spec_id, &vals, remaining, is_cold)
+                                );
+                            }
+                            assert_eq!(
+                                p.sstore_refund(spec_id, &vals),
+                                gas::sstore_refund(spec_id, &vals)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn saturating() {
+        let max = GasParams {
+            memory_word: u64::MAX,
+            memory_quotient: 0,
+            copy_word: u64::MAX,
+            keccak256_word: u64::MAX,
+            warm_access: u64::MAX,
+            cold_account_access: u64::MAX,
+            cold_sload: u64::MAX,
+            sstore_set: u64::MAX,
+            sstore_reset: u64::MAX,
+            sstore_clears_refund: u64::MAX,
+        };
+        // Costs lower than the access costs that they include.
+        let underflow = GasParams { sstore_set: 0, sstore_reset: 0, ..max };
+        assert_eq!(max.memory_gas(0), 0);
+        assert_eq!(max.memory_gas(1), u64::MAX);
+        assert_eq!(max.copy_cost(33), None);
+        assert_eq!(max.keccak256_cost(33), None);
+
+        let values = [U256::ZERO, U256::from(1), U256::from(2)];
+        for p in [max, underflow] {
+            for &spec_id in SPECS {
+                for is_cold in [false, true] {
+                    let load = Eip7702CodeLoad {
+                        state_load: StateLoad::new((), is_cold),
+                        is_delegate_account_cold: Some(is_cold),
+                    };
+                    let _ = p.warm_cold_cost_with_delegation(load.clone());
+                    for is_empty in [false, true] {
+                        let account_load = AccountLoad { load: load.clone(), is_empty };
+                        let _ = p.call_cost(spec_id, true, account_load);
+                    }
+                    let res = StateLoad::new(
+                        SelfDestructResult {
+                            had_value: true,
+                            target_exists: false,
+                            previously_destroyed: false,
+                        },
+                        is_cold,
+                    );
+                    let _ = p.selfdestruct_cost(spec_id, res);
+
+                    for original_value in values {
+                        for present_value in values {
+                            for new_value in values {
+                                let vals =
+                                    SStoreResult { original_value, present_value, new_value };
+                                let _ = p.sstore_cost(spec_id, &vals, u64::MAX, is_cold);
+                                let _ = p.sstore_refund(spec_id, &vals);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gas_mode() {
+        let mut gas = Gas::new(100);
+        assert!(GasMode::Metered.record_cost(&mut gas, 60));
+        assert!(!GasMode::Metered.record_cost(&mut gas, 60));
+        assert_eq!(gas.spent(), 60);
+
+        gas.set_refund(5);
+        assert!(GasMode::ObserveOnly.record_cost(&mut gas, 60));
+        assert_eq!(gas.spent(), 120);
+        assert_eq!(gas.limit(), 160);
+        assert_eq!(gas.remaining(), 40);
+        assert_eq!(gas.refunded(), 5);
+        assert!(GasMode::ObserveOnly.record_cost(&mut gas, 30));
+        assert_eq!(gas.spent(), 150);
+        assert_eq!(gas.remaining(), 10);
+
+        let mut gas = Gas::new(100);
+        assert!(GasMode::Disabled.record_cost(&mut gas, 60));
+        assert!(GasMode::Disabled.record_cost(&mut gas, u64::MAX));
+        assert_eq!(gas.spent(), 0);
+        assert_eq!(gas.limit(), 100);
+    }
+}
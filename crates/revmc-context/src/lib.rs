@@ -2,19 +2,77 @@
 #![cfg_attr(not(test), warn(unused_extern_crates))]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 extern crate alloc;
 
-use alloc::vec::Vec;
-use core::{fmt, mem::MaybeUninit, ptr};
+use alloc::{borrow::Cow, vec::Vec};
+use core::{any::Any, cmp::Ordering, fmt, mem::MaybeUninit, ops::Range, ptr};
 use revm_interpreter::{
-    Contract, FunctionStack, Gas, Host, InstructionResult, Interpreter, InterpreterAction,
-    InterpreterResult, SharedMemory, EMPTY_SHARED_MEMORY,
+    CallOutcome, Contract, FunctionStack, Gas, Host, InstructionResult, Interpreter,
+    InterpreterAction, InterpreterResult, SharedMemory, EMPTY_SHARED_MEMORY,
 };
-use revm_primitives::{Address, Bytes, Env, U256};
+use revm_primitives::{keccak256, Address, Bytes, CreateScheme, Env, B256, U256};
 
-#[cfg(feature = "host-ext-any")]
-use core::any::Any;
+#[cfg(feature = "std")]
+pub use revm_primitives::ruint::ParseError;
+
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+
+#[cfg(feature = "proptest")]
+mod arbitrary;
+
+mod contract_view;
+pub use contract_view::ContractView;
+
+mod gas;
+pub use gas::{GasMode, GasParams};
+
+mod execute;
+pub use execute::{EvmExecute, InterpreterExec};
+
+mod fast_call;
+pub use fast_call::{FastCall, FastCallHost, StackPool};
+
+mod frame;
+pub use frame::{FrameOutcome, SuspendedFrame, SuspendedFrameError};
+
+mod state;
+pub use state::DecodeStateError;
+
+mod override_host;
+pub use override_host::{HostRequest, HostResponse, OverrideHost};
+
+mod profile;
+pub use profile::ExecutionProfile;
+
+mod revert;
+pub use revert::RevertReason;
+
+mod ring;
+pub use ring::{HostCallKind, HostCallLog, HostCallRecord, HostCallRing};
+
+mod run;
+pub use run::SubcallHost;
+
+#[cfg(feature = "std")]
+mod shadow;
+#[cfg(feature = "std")]
+pub use shadow::{ShadowDivergence, ShadowExecutor, ShadowMismatch, ShadowOutcome, ShadowPolicy};
+
+mod stack_reader;
+pub use stack_reader::StackReader;
+
+#[cfg(feature = "std")]
+mod tape;
+#[cfg(feature = "std")]
+pub use tape::{HostCall, HostTape, RecordingHost, ReplayHost};
+
+pub mod wasm32;
+
+#[cfg(test)]
+mod test_utils;
 
 /// The EVM bytecode compiler runtime context.
 ///
@@ -39,10 +97,57 @@ pub struct EvmContext<'a> {
     pub is_static: bool,
     /// Whether the context is EOF init.
     pub is_eof_init: bool,
+    /// The parameters of the dynamic gas costs. `None` uses [`GasParams::DEFAULT`].
+    pub gas_params: Option<&'a GasParams>,
+    /// How the gas costs paid by builtins, such as memory expansion and copy costs, are metered.
+    ///
+    /// Must match the mode the function was compiled with, which is checked by
+    /// [`EvmCompilerFn::call_checked`]. Defaults to [`GasMode::Metered`].
+    pub gas_mode: GasMode,
+    /// Where builtins log the host calls they make, usually a [`HostCallRing`].
+    ///
+    /// Nothing is logged if this is `None`.
+    pub host_call_log: Option<&'a mut dyn HostCallLog>,
+    /// Per-opcode execution counts, indexed by opcode byte.
+    ///
+    /// Only incremented by functions compiled with opcode counting enabled, and only if this is
+    /// `Some`.
+    pub opcode_counts: Option<&'a mut [u64; 256]>,
+    /// Per-block execution counts.
+    ///
+    /// Only recorded by functions compiled with block profiling enabled, and only if this is
+    /// `Some`.
+    pub block_profile: Option<&'a mut ExecutionProfile>,
+    /// The number of instructions that may still be executed.
+    ///
+    /// Only decremented by functions compiled with a step budget, and only if this is `Some`.
+    /// Executing an instruction when the budget is zero fails with
+    /// [`InstructionResult::FatalExternalError`]. This bounds execution independently of gas.
+    pub step_budget: Option<&'a mut u64>,
+    /// Called with the program counter of the instruction and the result, whenever the function
+    /// returns, including when it suspends with [`InstructionResult::CallOrCreate`].
+    ///
+    /// Only called by functions compiled with a result sink, and only if this is `Some`. The pc
+    /// is `u32::MAX` if the result was not produced by an instruction, such as when
+    /// [`resume_at`](Self::resume_at) is invalid. The sink must not panic.
+    pub result_sink: Option<&'a mut dyn FnMut(u32, InstructionResult)>,
+    /// Arbitrary state for custom builtins, owned by the caller and borrowed for the lifetime of
+    /// the context.
+    ///
+    /// Unlike the host, which may serve many executions, this is local to a single execution and
+    /// is never touched by the compiled code. Use [`user_data_mut`](Self::user_data_mut) to
+    /// retrieve it.
+    pub user_data: Option<&'a mut dyn Any>,
     /// An index that is used internally to keep track of where execution should resume.
     /// `0` is the initial state.
     #[doc(hidden)]
     pub resume_at: usize,
+    /// The error of the last failed host call.
+    ///
+    /// This is set when a builtin returns [`InstructionResult::FatalExternalError`] because a
+    /// [`Host`] method returned `None`, see [`report_host_error`], or panicked, see [`HostPanic`].
+    #[cfg(feature = "std")]
+    pub host_error: Option<HostError>,
 }
 
 impl fmt::Debug for EvmContext<'_> {
@@ -51,20 +156,122 @@ impl fmt::Debug for EvmContext<'_> {
     }
 }
 
+/// The configuration of an [`EvmContext`] that is not part of the interpreter's state.
+///
+/// Contexts that are created for nested call frames, such as by
+/// [`EvmCompilerFn::call_with_fast_calls`], inherit this from the context of their caller. See
+/// the fields of [`EvmContext`] with the same names for more information.
+#[derive(Default)]
+pub struct ContextConfig<'a> {
+    /// See [`EvmContext::gas_params`].
+    pub gas_params: Option<&'a GasParams>,
+    /// See [`EvmContext::gas_mode`].
+    pub gas_mode: GasMode,
+    /// See [`EvmContext::host_call_log`].
+    pub host_call_log: Option<&'a mut dyn HostCallLog>,
+    /// See [`EvmContext::opcode_counts`].
+    pub opcode_counts: Option<&'a mut [u64; 256]>,
+    /// See [`EvmContext::block_profile`].
+    pub block_profile: Option<&'a mut ExecutionProfile>,
+    /// See [`EvmContext::step_budget`].
+    pub step_budget: Option<&'a mut u64>,
+    /// See [`EvmContext::result_sink`].
+    pub result_sink: Option<&'a mut dyn FnMut(u32, InstructionResult)>,
+    /// See [`EvmContext::user_data`].
+    pub user_data: Option<&'a mut dyn Any>,
+}
+
+impl fmt::Debug for ContextConfig<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextConfig")
+            .field("gas_params", &self.gas_params)
+            .field("gas_mode", &self.gas_mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ContextConfig<'_> {
+    /// Reborrows the configuration, to configure a context that does not outlive it.
+    pub fn reborrow(&mut self) -> ContextConfig<'_> {
+        ContextConfig {
+            gas_params: self.gas_params,
+            gas_mode: self.gas_mode,
+            host_call_log: match &mut self.host_call_log {
+                Some(log) => Some(&mut **log),
+                None => None,
+            },
+            opcode_counts: self.opcode_counts.as_deref_mut(),
+            block_profile: self.block_profile.as_deref_mut(),
+            step_budget: self.step_budget.as_deref_mut(),
+            result_sink: match &mut self.result_sink {
+                Some(sink) => Some(&mut **sink),
+                None => None,
+            },
+            user_data: self.user_data.as_deref_mut(),
+        }
+    }
+}
+
 impl<'a> EvmContext<'a> {
     /// Creates a new context from an interpreter.
     #[inline]
     pub fn from_interpreter(interpreter: &'a mut Interpreter, host: &'a mut dyn HostExt) -> Self {
-        Self::from_interpreter_with_stack(interpreter, host).0
+        // SAFETY: The stack is discarded.
+        unsafe { Self::from_interpreter_with_stack_uninit(interpreter, host).0 }
     }
 
     /// Creates a new context from an interpreter.
+    ///
+    /// The unused words of the interpreter's stack are zeroed, see
+    /// [`EvmStack::from_interpreter_stack`]. This writes up to the whole stack, 32 KiB, on every
+    /// call, so prefer [`EvmCompilerFn::call_with_interpreter`], which does not need to initialize
+    /// the unused words, to only call a function.
     #[inline]
     pub fn from_interpreter_with_stack<'b: 'a>(
         interpreter: &'a mut Interpreter,
         host: &'b mut dyn HostExt,
     ) -> (Self, &'a mut EvmStack, &'a mut usize) {
-        let (stack, stack_len) = EvmStack::from_interpreter_stack(&mut interpreter.stack);
+        EvmStack::zero_interpreter_stack(&mut interpreter.stack);
+        // SAFETY: The stack was just fully initialized.
+        unsafe { Self::from_interpreter_with_stack_uninit(interpreter, host) }
+    }
+
+    /// Creates a new context from an interpreter to re-enter a suspended function at
+    /// `resume_at`.
+    ///
+    /// This is the sequence to follow when the context was dropped while the host handled the
+    /// call or create that the function suspended on: the stack is re-derived from the
+    /// interpreter like in [`from_interpreter_with_stack`](Self::from_interpreter_with_stack),
+    /// the pending [`next_action`](Self::next_action) is cleared, and
+    /// [`resume_at`](Self::resume_at) is set to the saved resume point.
+    ///
+    /// The result of the call or create must already be in the interpreter, see
+    /// [`FrameOutcome::insert_into`].
+    #[inline]
+    pub fn resume_from<'b: 'a>(
+        interpreter: &'a mut Interpreter,
+        host: &'b mut dyn HostExt,
+        resume_at: usize,
+    ) -> (Self, &'a mut EvmStack, &'a mut usize) {
+        interpreter.next_action = InterpreterAction::None;
+        let (mut this, stack, stack_len) = Self::from_interpreter_with_stack(interpreter, host);
+        this.resume_at = resume_at;
+        (this, stack, stack_len)
+    }
+
+    /// Same as [`from_interpreter_with_stack`](Self::from_interpreter_with_stack), but doesn't
+    /// initialize the unused words of the interpreter's stack.
+    ///
+    /// # Safety
+    ///
+    /// Only the first `stack_len` words of the returned stack may be read, see
+    /// [`EvmStack::new_uninit`].
+    #[inline]
+    unsafe fn from_interpreter_with_stack_uninit<'b: 'a>(
+        interpreter: &'a mut Interpreter,
+        host: &'b mut dyn HostExt,
+    ) -> (Self, &'a mut EvmStack, &'a mut usize) {
+        let (stack, stack_len) = EvmStack::from_interpreter_stack_uninit(&mut interpreter.stack);
         let resume_at = ResumeAt::load(
             interpreter.instruction_pointer,
             interpreter.contract.bytecode.original_byte_slice(),
@@ -79,11 +286,79 @@ impl<'a> EvmContext<'a> {
             func_stack: &mut interpreter.function_stack,
             is_static: interpreter.is_static,
             is_eof_init: interpreter.is_eof_init,
+            gas_params: None,
+            gas_mode: GasMode::Metered,
+            host_call_log: None,
+            opcode_counts: None,
+            block_profile: None,
+            step_budget: None,
+            result_sink: None,
+            user_data: None,
             resume_at,
+            #[cfg(feature = "std")]
+            host_error: None,
         };
         (this, stack, stack_len)
     }
 
+    /// Replaces the configuration of the context.
+    #[inline]
+    pub fn set_config(&mut self, config: ContextConfig<'a>) {
+        let ContextConfig {
+            gas_params,
+            gas_mode,
+            host_call_log,
+            opcode_counts,
+            block_profile,
+            step_budget,
+            result_sink,
+            user_data,
+        } = config;
+        self.gas_params = gas_params;
+        self.gas_mode = gas_mode;
+        self.host_call_log = host_call_log;
+        self.opcode_counts = opcode_counts;
+        self.block_profile = block_profile;
+        self.step_budget = step_budget;
+        self.result_sink = result_sink;
+        self.user_data = user_data;
+    }
+
+    /// Calls `f` with a context created from the interpreter, but bound to a new empty
+    /// [`SharedMemory`] instead of the interpreter's memory.
+    ///
+    /// Returns the result of `f` and the memory, which is only borrowed by the context for the
+    /// duration of the call. The interpreter's own memory is left untouched, even if `f` panics.
+    #[cfg(feature = "std")]
+    pub fn with_fresh_memory<R>(
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+        f: impl FnOnce(&mut EvmContext<'_>, &mut EvmStack, &mut usize) -> R,
+    ) -> (R, SharedMemory) {
+        /// Puts the interpreter's memory back when dropped.
+        struct RestoreMemory<'a> {
+            interpreter: &'a mut Interpreter,
+            memory: Option<SharedMemory>,
+        }
+
+        impl Drop for RestoreMemory<'_> {
+            fn drop(&mut self) {
+                if let Some(memory) = self.memory.take() {
+                    self.interpreter.shared_memory = memory;
+                }
+            }
+        }
+
+        let memory = core::mem::take(&mut interpreter.shared_memory);
+        let mut guard = RestoreMemory { interpreter, memory: Some(memory) };
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(guard.interpreter, host);
+        let r = f(&mut ecx, stack, stack_len);
+        let memory = guard.memory.take().unwrap();
+        let fresh = core::mem::replace(&mut guard.interpreter.shared_memory, memory);
+        (r, fresh)
+    }
+
     /// Creates a new interpreter by cloning the context.
     pub fn to_interpreter(&self, stack: revm_interpreter::Stack) -> Interpreter {
         let bytecode = self.contract.bytecode.bytecode().clone();
@@ -106,6 +381,514 @@ impl<'a> EvmContext<'a> {
             next_action: self.next_action.clone(),
         }
     }
+
+    /// Returns the environment the compiled code reads the block and transaction fields from.
+    ///
+    /// Same as `self.host.env()`.
+    #[inline]
+    pub fn env(&self) -> &Env {
+        self.host.env()
+    }
+
+    /// Returns the hash of the contract's code.
+    ///
+    /// This is [`Contract::hash`] if it is set, and is otherwise computed from the bytecode, with
+    /// the hash of empty code being [`KECCAK_EMPTY`](revm_primitives::KECCAK_EMPTY).
+    #[inline]
+    pub fn code_hash(&self) -> B256 {
+        self.contract.hash.unwrap_or_else(|| self.contract.bytecode.hash_slow())
+    }
+
+    /// Sets the contract's transient storage (EIP-1153) at `key` to `value`, like `TSTORE`.
+    ///
+    /// This writes to the host's transient storage with [`Host::tstore`], so it requires a host
+    /// that supports it, such as [`DummyHost`](revm_interpreter::DummyHost). This is useful for
+    /// seeding transient storage before calling a compiled function.
+    #[inline]
+    pub fn set_transient(&mut self, key: U256, value: U256) {
+        self.host.tstore(self.contract.target_address, key, value);
+    }
+
+    /// Returns the contract's transient storage (EIP-1153) at `key`, like `TLOAD`.
+    ///
+    /// This reads from the host's transient storage with [`Host::tload`], which takes the host by
+    /// mutable reference. See [`set_transient`](Self::set_transient).
+    #[inline]
+    pub fn get_transient(&mut self, key: U256) -> U256 {
+        self.host.tload(self.contract.target_address, key)
+    }
+
+    /// Returns the remaining gas.
+    #[inline]
+    pub fn gas_remaining(&self) -> u64 {
+        self.gas.remaining()
+    }
+
+    /// Returns the gas limit.
+    #[inline]
+    pub fn gas_limit(&self) -> u64 {
+        self.gas.limit()
+    }
+
+    /// Records a gas cost, like the compiled code does for every instruction.
+    ///
+    /// Returns `false` if there is not enough gas remaining, in which case no gas is spent. See
+    /// [`GasMode::record_cost`].
+    #[inline]
+    pub fn spend_gas(&mut self, amount: u64) -> bool {
+        self.gas_mode.record_cost(self.gas, amount)
+    }
+
+    /// Disables gas metering for the builtins, and replaces the gas with an effectively infinite
+    /// limit.
+    ///
+    /// Instructions that read the remaining gas, such as `GAS`, `CALL` and `SSTORE`, see
+    /// `u64::MAX`. Combined with a function compiled with [`GasMode::Disabled`], out-of-gas errors
+    /// can never occur, which is useful for testing the semantics of the compiled code.
+    #[inline]
+    pub fn disable_gas(&mut self) {
+        self.gas_mode = GasMode::Disabled;
+        *self.gas = Gas::new(u64::MAX);
+    }
+
+    /// Returns the accumulated gas refund, before the end-of-transaction cap is applied.
+    ///
+    /// Same as [`Gas::refunded`]: refunds are added and un-refunds are subtracted, so this can be
+    /// negative in the middle of a transaction.
+    #[inline]
+    pub fn refunded(&self) -> i64 {
+        self.gas.refunded()
+    }
+
+    /// Overrides the accumulated gas refund.
+    #[inline]
+    pub fn set_refunded(&mut self, refunded: i64) {
+        self.gas.set_refund(refunded);
+    }
+
+    /// Returns whether the pending or terminal result is a revert.
+    ///
+    /// Returns `Some(true)` for a revert, `Some(false)` for any other result, or `None` if there
+    /// is no result yet, e.g. when execution is still running or suspended on a call or create.
+    #[inline]
+    pub fn reverted(&self) -> Option<bool> {
+        match &*self.next_action {
+            InterpreterAction::Return { result } => Some(result.result.is_revert()),
+            _ => None,
+        }
+    }
+
+    /// Decodes the output of the pending or terminal result if it is a revert.
+    ///
+    /// Returns `None` if there is no result yet, or if it is not a revert.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        match &*self.next_action {
+            InterpreterAction::Return { result } if result.result.is_revert() => {
+                Some(RevertReason::decode(&result.output))
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds the [`CallOutcome`] to insert into the parent frame once the compiled function of
+    /// this call frame returned `result`.
+    ///
+    /// The output is the memory range returned by `RETURN` or `REVERT`, and is empty for any other
+    /// result. The gas is the current gas, fully spent on `OutOfGas` like in
+    /// [`EvmCompilerFn::call_with_interpreter`]. `memory_offset` is the range of the parent's
+    /// memory that the output is copied to, from the `CALL` inputs.
+    pub fn into_call_outcome(
+        self,
+        result: InstructionResult,
+        memory_offset: Range<usize>,
+    ) -> CallOutcome {
+        let output = match &*self.next_action {
+            InterpreterAction::Return { result } => result.output.clone(),
+            _ => Bytes::new(),
+        };
+        let mut gas = *self.gas;
+        if result == InstructionResult::OutOfGas {
+            gas.spend_all();
+        }
+        CallOutcome::new(InterpreterResult { result, output, gas }, memory_offset)
+    }
+
+    /// Returns `true` if the compiled function suspended to request a call or create, and expects
+    /// to be re-entered at [`resume_at`](Self::resume_at) once the action's result is available.
+    ///
+    /// Returns `false` if execution completed, including on a revert or an error.
+    #[inline]
+    pub fn is_suspended(&self) -> bool {
+        self.resume_at != 0 && self.next_action.is_some() && !self.next_action.is_return()
+    }
+
+    /// Returns the address of the contract that the pending `CREATE2` action will create, or
+    /// `None` if the pending action is not a `CREATE2`.
+    ///
+    /// This allows the host to warm the created address (EIP-2929) before executing the action.
+    /// The address is computed from the action itself, so it is never stale.
+    pub fn create2_address(&self) -> Option<Address> {
+        let InterpreterAction::Create { inputs } = &*self.next_action else { return None };
+        let CreateScheme::Create2 { salt } = inputs.scheme else { return None };
+        Some(inputs.caller.create2(salt.to_be_bytes::<32>(), keccak256(&inputs.init_code)))
+    }
+
+    /// Reads a word from memory, zero-padding the bytes past the end of memory like `MLOAD`.
+    ///
+    /// Memory is not expanded.
+    #[inline]
+    pub fn memory_word(&self, offset: usize) -> EvmWord {
+        let mut word = [0u8; 32];
+        let slice = self.memory_slice(offset, 32);
+        word.copy_from_slice(&slice);
+        EvmWord::from_be_bytes(word)
+    }
+
+    /// Reads `len` bytes from memory, zero-padding the bytes past the end of memory.
+    ///
+    /// Memory is not expanded. Returns a borrowed slice if the range is fully in memory.
+    pub fn memory_slice(&self, offset: usize, len: usize) -> Cow<'_, [u8]> {
+        let memory = self.memory.context_memory();
+        let start = offset.min(memory.len());
+        let end = offset.saturating_add(len).min(memory.len());
+        let available = &memory[start..end];
+        if available.len() == len {
+            return Cow::Borrowed(available);
+        }
+        let mut data = alloc::vec![0u8; len];
+        data[..available.len()].copy_from_slice(available);
+        Cow::Owned(data)
+    }
+
+    /// Returns `true` if the memory at `offset..offset + expected.len()` equals `expected`,
+    /// treating the bytes past the end of memory as zero like [`memory_slice`](Self::memory_slice).
+    ///
+    /// Memory is not expanded, and nothing is copied.
+    pub fn memory_eq(&self, offset: usize, expected: &[u8]) -> bool {
+        let memory = self.memory.context_memory();
+        let start = offset.min(memory.len());
+        let end = offset.saturating_add(expected.len()).min(memory.len());
+        let (in_memory, past_end) = expected.split_at(end - start);
+        memory[start..end] == *in_memory && past_end.iter().all(|&b| b == 0)
+    }
+
+    /// Returns an iterator over the memory in `chunk`-sized slices, without copying it.
+    ///
+    /// The last slice is shorter if the memory length is not a multiple of `chunk`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is zero.
+    pub fn memory_chunks(&self, chunk: usize) -> impl Iterator<Item = &[u8]> + '_ {
+        self.memory.context_memory().chunks(chunk)
+    }
+
+    /// Captures the observable state into an owned snapshot.
+    ///
+    /// `stack` and `stack_len` are the stack and stack length that the function is called with.
+    pub fn to_owned_state(&self, stack: &EvmStack, stack_len: usize) -> OwnedEvmState {
+        OwnedEvmState {
+            gas: *self.gas,
+            stack: stack.as_slice()[..stack_len].to_vec(),
+            memory: self.memory.context_memory().to_vec(),
+            is_static: self.is_static,
+            resume_at: self.resume_at,
+            return_data: self.return_data.to_vec(),
+        }
+    }
+
+    /// Returns a human-readable dump of the context, for diagnostics.
+    ///
+    /// `stack` and `stack_len` are the stack and stack length that the function is called with.
+    /// At most `mem_preview` bytes of memory are included, as a hexdump.
+    ///
+    /// The output format is not stable.
+    #[cfg(feature = "std")]
+    pub fn dump(&self, stack: &EvmStack, stack_len: usize, mem_preview: usize) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let gas = &*self.gas;
+        let _ = writeln!(
+            out,
+            "gas: remaining={} limit={} refunded={}",
+            gas.remaining(),
+            gas.limit(),
+            gas.refunded()
+        );
+        let _ = writeln!(out, "is_static: {}", self.is_static);
+        let _ = writeln!(out, "is_eof_init: {}", self.is_eof_init);
+        let _ = writeln!(out, "resume_at: {}", self.resume_at);
+        let _ = writeln!(out, "func_stack: depth={}", self.func_stack.return_stack.len());
+
+        let stack_len = stack_len.min(EvmStack::CAPACITY);
+        let _ = writeln!(out, "stack: len={stack_len}");
+        for (i, word) in stack.as_slice()[..stack_len].iter().enumerate().rev() {
+            let _ = writeln!(out, "  {i:4}: {word:#066x}");
+        }
+
+        let memory = self.memory.context_memory();
+        let _ = writeln!(out, "memory: len={}", memory.len());
+        for (i, chunk) in memory[..mem_preview.min(memory.len())].chunks(32).enumerate() {
+            let _ = write!(out, "  {:08x}:", i * 32);
+            for byte in chunk {
+                let _ = write!(out, " {byte:02x}");
+            }
+            out.push('\n');
+        }
+
+        let _ = writeln!(out, "next_action: {:?}", self.next_action);
+        out
+    }
+
+    /// Takes the error of the last failed host call, if any.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn take_host_error(&mut self) -> Option<HostError> {
+        self.host_error.take()
+    }
+
+    /// Returns the logs emitted so far, as recorded by the attached host.
+    ///
+    /// This only reflects what the host recorded: it is empty unless the host is a
+    /// [`RecordingHost<H>`], whose [`emitted_logs`](RecordingHost::emitted_logs) are returned.
+    #[cfg(all(feature = "std", feature = "host-ext-any"))]
+    #[inline]
+    pub fn emitted_logs<H: Host + 'static>(
+        &self,
+    ) -> impl Iterator<Item = &revm_primitives::Log> + '_ {
+        self.host
+            .downcast_ref::<RecordingHost<H>>()
+            .into_iter()
+            .flat_map(RecordingHost::emitted_logs)
+    }
+
+    /// Returns the beneficiary of the last `SELFDESTRUCT`, as recorded by the attached host.
+    ///
+    /// This requires the cooperation of the host: it is `None` unless the host is a
+    /// [`RecordingHost<H>`], whose [`selfdestruct_target`](RecordingHost::selfdestruct_target) is
+    /// returned.
+    #[cfg(all(feature = "std", feature = "host-ext-any"))]
+    #[inline]
+    pub fn selfdestruct_target<H: Host + 'static>(&self) -> Option<Address> {
+        self.host.downcast_ref::<RecordingHost<H>>()?.selfdestruct_target()
+    }
+
+    /// Returns the [`user_data`](Self::user_data) if it is set and is of type `T`.
+    #[inline]
+    pub fn user_data_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.user_data.as_deref_mut()?.downcast_mut()
+    }
+
+    /// Logs a host call to [`host_call_log`](Self::host_call_log), if set. Called by builtins.
+    #[doc(hidden)]
+    #[inline]
+    pub fn log_host_call(&mut self, kind: HostCallKind, gas: u64) {
+        self.log_host_call_result(kind, gas, InstructionResult::Continue);
+    }
+
+    /// Logs a host call that ended with the given result to
+    /// [`host_call_log`](Self::host_call_log), if set. Called by builtins.
+    #[doc(hidden)]
+    #[inline]
+    pub fn log_host_call_result(
+        &mut self,
+        kind: HostCallKind,
+        gas: u64,
+        result: InstructionResult,
+    ) {
+        if let Some(log) = &mut self.host_call_log {
+            log.record(HostCallRecord { kind, gas, result });
+        }
+    }
+
+    /// Records that the given host call returned `None`. Called by builtins.
+    #[doc(hidden)]
+    #[cold]
+    pub fn set_host_error(&mut self, call: &'static str) {
+        #[cfg(feature = "std")]
+        {
+            let error = REPORTED_HOST_ERROR.with(|e| e.take());
+            self.host_error =
+                Some(error.unwrap_or_else(|| format!("host call `{call}` failed").into()));
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = call;
+    }
+
+    /// Records that the given host call or builtin panicked with `payload`. Called by builtins.
+    ///
+    /// Panics can only be caught with `std`.
+    #[doc(hidden)]
+    #[cold]
+    #[cfg(feature = "std")]
+    pub fn set_host_panic(&mut self, call: &'static str, payload: Box<dyn Any + Send>) {
+        self.host_error = Some(Box::new(HostPanic::new(call, payload)));
+    }
+
+    /// Panics cannot be caught without `std`, so this is never called.
+    #[doc(hidden)]
+    #[cfg(not(feature = "std"))]
+    pub fn set_host_panic(&mut self, _call: &'static str, payload: core::convert::Infallible) {
+        match payload {}
+    }
+}
+
+/// An owned snapshot of the observable state of an [`EvmContext`].
+///
+/// Created with [`EvmContext::to_owned_state`]. It can be serialized to bytes with
+/// [`encode`](Self::encode), for example to reproduce a failing execution from a bug report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedEvmState {
+    /// The gas.
+    pub gas: Gas,
+    /// The live part of the stack, from bottom to top.
+    pub stack: Vec<EvmWord>,
+    /// The memory of the current call.
+    pub memory: Vec<u8>,
+    /// Whether the context is static.
+    pub is_static: bool,
+    /// The resume point.
+    pub resume_at: usize,
+    /// The return data of the last call.
+    pub return_data: Vec<u8>,
+}
+
+/// Creates a [`Contract`] for tests from just its bytecode.
+///
+/// The bytecode is analyzed like revm does before executing it, which is what compiled functions
+/// expect. Every other field has its default value: zero addresses, empty call data, zero value
+/// and no code hash. Use struct update syntax to set them:
+///
+/// ```
+/// use revm_interpreter::{opcode as op, Contract};
+/// use revm_primitives::{Address, Bytes};
+/// use revmc_context::test_contract;
+///
+/// let contract = Contract {
+///     caller: Address::with_last_byte(1),
+///     ..test_contract(Bytes::from_static(&[op::PUSH0, op::STOP]))
+/// };
+/// assert_eq!(contract.bytecode.original_byte_slice(), [0x5f, 0x00]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the bytecode starts with the EOF magic but is not valid EOF.
+#[cfg(feature = "std")]
+pub fn test_contract(bytecode: impl Into<Bytes>) -> Contract {
+    Contract {
+        bytecode: revm_interpreter::analysis::to_analysed(revm_primitives::Bytecode::new_raw(
+            bytecode.into(),
+        )),
+        ..Default::default()
+    }
+}
+
+/// An error reported by a [`Host`] implementation.
+#[cfg(feature = "std")]
+pub type HostError = Box<dyn std::error::Error + Send + Sync>;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static REPORTED_HOST_ERROR: core::cell::Cell<Option<HostError>> =
+        const { core::cell::Cell::new(None) };
+}
+
+/// Reports the cause of a failed host call.
+///
+/// [`Host`] methods signal failure by returning `None`, which the compiled function turns into
+/// [`InstructionResult::FatalExternalError`]. A host can call this right before returning `None`
+/// to attach the underlying error, e.g. a database error, which is then stored in
+/// [`EvmContext::host_error`].
+///
+/// The error is passed through a thread-local, so it must be reported on the thread that runs the
+/// compiled function. It is taken by the next failed host call on that thread, and discarded when
+/// a function is entered with [`EvmCompilerFn::call`] or [`EvmCompilerFnNoGas::call`]. An error
+/// that is reported by a host call that then succeeds stays pending until then, so code that
+/// calls builtins without going through these entry points may attribute it to a later failed
+/// call.
+#[cfg(feature = "std")]
+pub fn report_host_error(error: impl Into<HostError>) {
+    REPORTED_HOST_ERROR.with(|e| e.set(Some(error.into())));
+}
+
+/// Discards an error reported with [`report_host_error`] that no failed host call picked up, so
+/// that it is not attributed to a later call.
+#[inline]
+fn clear_reported_host_error() {
+    #[cfg(feature = "std")]
+    REPORTED_HOST_ERROR.with(|e| drop(e.take()));
+}
+
+/// A panic in a [`Host`] method or a builtin.
+///
+/// Builtins catch panics instead of unwinding through the compiled code, and store them in
+/// [`EvmContext::host_error`] as this error, which can be downcast to rethrow the panic with
+/// [`resume`](Self::resume).
+///
+/// Without `std`, panics cannot be caught and abort the process instead.
+#[cfg(feature = "std")]
+pub struct HostPanic {
+    call: &'static str,
+    message: String,
+    // `HostError` must be `Sync`.
+    payload: std::sync::Mutex<Box<dyn Any + Send>>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for HostPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostPanic")
+            .field("call", &self.call)
+            .field("message", &self.message)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for HostPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "host call `{}` panicked: {}", self.call, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HostPanic {}
+
+#[cfg(feature = "std")]
+impl HostPanic {
+    fn new(call: &'static str, payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(&message) = payload.downcast_ref::<&str>() {
+            message.into()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".into()
+        };
+        Self { call, message, payload: std::sync::Mutex::new(payload) }
+    }
+
+    /// Returns the name of the [`Host`] method that panicked, or of the builtin if the panic did
+    /// not happen in a host method.
+    pub fn call(&self) -> &'static str {
+        self.call
+    }
+
+    /// Returns the panic message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the panic payload.
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload.into_inner().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Rethrows the panic with [`std::panic::resume_unwind`].
+    pub fn resume(self) -> ! {
+        std::panic::resume_unwind(self.into_payload())
+    }
 }
 
 /// Extension trait for [`Host`].
@@ -149,6 +932,102 @@ impl dyn HostExt {
     }
 }
 
+/// Implements [`Host`] for a type by forwarding every method to the host that it dereferences to.
+#[cfg(any(test, feature = "std"))]
+macro_rules! forward_host {
+    (impl[$($generics:tt)*] $ty:ty) => {
+        impl<$($generics)*> revm_interpreter::Host for $ty {
+            fn env(&self) -> &revm_primitives::Env {
+                (**self).env()
+            }
+
+            fn env_mut(&mut self) -> &mut revm_primitives::Env {
+                (**self).env_mut()
+            }
+
+            fn load_account_delegated(
+                &mut self,
+                address: revm_primitives::Address,
+            ) -> Option<revm_interpreter::AccountLoad> {
+                (**self).load_account_delegated(address)
+            }
+
+            fn block_hash(&mut self, number: u64) -> Option<revm_primitives::B256> {
+                (**self).block_hash(number)
+            }
+
+            fn balance(
+                &mut self,
+                address: revm_primitives::Address,
+            ) -> Option<revm_interpreter::StateLoad<revm_primitives::U256>> {
+                (**self).balance(address)
+            }
+
+            fn code(
+                &mut self,
+                address: revm_primitives::Address,
+            ) -> Option<revm_interpreter::StateLoad<revm_primitives::Bytes>> {
+                (**self).code(address)
+            }
+
+            fn code_hash(
+                &mut self,
+                address: revm_primitives::Address,
+            ) -> Option<revm_interpreter::StateLoad<revm_primitives::B256>> {
+                (**self).code_hash(address)
+            }
+
+            fn sload(
+                &mut self,
+                address: revm_primitives::Address,
+                index: revm_primitives::U256,
+            ) -> Option<revm_interpreter::StateLoad<revm_primitives::U256>> {
+                (**self).sload(address, index)
+            }
+
+            fn sstore(
+                &mut self,
+                address: revm_primitives::Address,
+                index: revm_primitives::U256,
+                value: revm_primitives::U256,
+            ) -> Option<revm_interpreter::StateLoad<revm_interpreter::SStoreResult>> {
+                (**self).sstore(address, index, value)
+            }
+
+            fn tload(
+                &mut self,
+                address: revm_primitives::Address,
+                index: revm_primitives::U256,
+            ) -> revm_primitives::U256 {
+                (**self).tload(address, index)
+            }
+
+            fn tstore(
+                &mut self,
+                address: revm_primitives::Address,
+                index: revm_primitives::U256,
+                value: revm_primitives::U256,
+            ) {
+                (**self).tstore(address, index, value)
+            }
+
+            fn log(&mut self, log: revm_primitives::Log) {
+                (**self).log(log)
+            }
+
+            fn selfdestruct(
+                &mut self,
+                address: revm_primitives::Address,
+                target: revm_primitives::Address,
+            ) -> Option<revm_interpreter::StateLoad<revm_interpreter::SelfDestructResult>> {
+                (**self).selfdestruct(address, target)
+            }
+        }
+    };
+}
+#[cfg(any(test, feature = "std"))]
+pub(crate) use forward_host;
+
 /// Declare [`RawEvmCompilerFn`] functions in an `extern "C"` block.
 ///
 /// # Examples
@@ -175,7 +1054,7 @@ macro_rules! extern_revmc {
                     stack: *mut $crate::EvmStack,
                     stack_len: *mut usize,
                     env: *const $crate::private::revm_primitives::Env,
-                    contract: *const $crate::private::revm_interpreter::Contract,
+                    contract: *const $crate::ContractView,
                     ecx: *mut $crate::EvmContext<'_>,
                 ) -> $crate::private::revm_interpreter::InstructionResult;
             )+
@@ -187,19 +1066,46 @@ macro_rules! extern_revmc {
 ///
 /// Prefer using [`EvmCompilerFn`] instead of this type. See [`EvmCompilerFn::call`] for more
 /// information.
+///
+/// The environment is passed by `*const` pointer, since execution never modifies it. Changes to
+/// the environment must go through the [`Host`] instead:
+///
+/// ```compile_fail
+/// # use revmc_context::{ContractView, EvmContext, EvmStack};
+/// # use revm_interpreter::{Gas, InstructionResult};
+/// # use revm_primitives::Env;
+/// unsafe extern "C" fn f(
+///     _gas: *mut Gas,
+///     _stack: *mut EvmStack,
+///     _stack_len: *mut usize,
+///     env: *const Env,
+///     _contract: *const ContractView,
+///     _ecx: *mut EvmContext<'_>,
+/// ) -> InstructionResult {
+///     (*env).cfg.chain_id = 1;
+///     InstructionResult::Stop
+/// }
+/// let _: revmc_context::RawEvmCompilerFn = f;
+/// ```
 // When changing the signature, also update the corresponding declarations in `fn translate`.
 pub type RawEvmCompilerFn = unsafe extern "C" fn(
     gas: *mut Gas,
     stack: *mut EvmStack,
     stack_len: *mut usize,
     env: *const Env,
-    contract: *const Contract,
+    contract: *const ContractView,
     ecx: *mut EvmContext<'_>,
 ) -> InstructionResult;
 
 /// An EVM bytecode function.
+///
+/// Optionally carries the [`FnMeta`] that it was compiled with, which is checked by
+/// [`call`](Self::call).
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct EvmCompilerFn(RawEvmCompilerFn);
+pub struct EvmCompilerFn {
+    f: RawEvmCompilerFn,
+    meta: Option<FnMeta>,
+}
 
 impl From<RawEvmCompilerFn> for EvmCompilerFn {
     #[inline]
@@ -219,13 +1125,57 @@ impl EvmCompilerFn {
     /// Wraps the function.
     #[inline]
     pub const fn new(f: RawEvmCompilerFn) -> Self {
-        Self(f)
+        Self { f, meta: None }
+    }
+
+    /// Wraps the function, attaching the calling convention that it was compiled with.
+    ///
+    /// `meta` must match how the function was compiled for [`call`](Self::call) to reject invalid
+    /// arguments.
+    #[inline]
+    pub const fn new_with_meta(f: RawEvmCompilerFn, meta: FnMeta) -> Self {
+        Self { f, meta: Some(meta) }
     }
 
     /// Unwraps the function.
     #[inline]
     pub const fn into_inner(self) -> RawEvmCompilerFn {
-        self.0
+        self.f
+    }
+
+    /// Returns the calling convention that the function was compiled with, if known.
+    #[inline]
+    pub const fn meta(&self) -> Option<FnMeta> {
+        self.meta
+    }
+
+    /// Returns `true` if the function is known to use indexes as its resume points, see
+    /// [`FnMeta::resume_indexes`].
+    #[inline]
+    pub fn has_resume_indexes(&self) -> bool {
+        self.meta.is_some_and(|meta| meta.resume_indexes)
+    }
+
+    /// Reads one byte from every page of the first `code_size` bytes of the function's machine
+    /// code, so that the first call does not page fault on them.
+    ///
+    /// The size of the machine code is reported by the compiler in
+    /// `CompilationStats::machine_code_bytes`, if the backend supports it. The LLVM backend does
+    /// not, so its functions cannot be prefaulted.
+    ///
+    /// # Safety
+    ///
+    /// `code_size` must not be larger than the size of the function's machine code.
+    pub unsafe fn prefault(&self, code_size: usize) {
+        // The smallest page size of the supported targets. Touching every 4 KiB also touches
+        // every page of a larger size.
+        const PAGE_SIZE: usize = 4096;
+        let start = self.f as *const u8;
+        let mut offset = 0;
+        while offset < code_size {
+            unsafe { ptr::read_volatile(start.add(offset)) };
+            offset = (start as usize + offset + PAGE_SIZE) / PAGE_SIZE * PAGE_SIZE - start as usize;
+        }
     }
 
     /// Calls the function by re-using the interpreter's resources and memory.
@@ -255,6 +1205,11 @@ impl EvmCompilerFn {
     /// interpreter's [`instruction_result`](Interpreter::instruction_result) field and the next
     /// action in the [`next_action`](Interpreter::next_action) field.
     ///
+    /// If the function carries its [`FnMeta`] and doesn't
+    /// [write the stack length](FnMeta::writes_stack_length), the interpreter's stack is emptied
+    /// after the call, since the function always starts with an empty stack and its final height
+    /// is not known. Otherwise the interpreter's stack length is the function's final stack length.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that the function is safe to call.
@@ -263,12 +1218,87 @@ impl EvmCompilerFn {
         self,
         interpreter: &mut Interpreter,
         host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        self.call_with_interpreter_inner(interpreter, host, &mut ContextConfig::default(), |_| {})
+    }
+
+    /// Resumes a [`SuspendedFrame`] with the result of the call or create that it is suspended on.
+    ///
+    /// The frame is restored into `interpreter` with [`SuspendedFrame::restore_into`], which must
+    /// have been created with the same contract as the one the frame was captured from, and the
+    /// outcome is inserted like revm does before resuming a frame. Execution then continues like
+    /// [`call_with_interpreter`](Self::call_with_interpreter).
+    ///
+    /// Returns an error if the frame was not captured from the `artifact` of this function, or if
+    /// the function's resume points are not indexes.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the function is safe to call, and that it is the function
+    /// identified by `artifact`.
+    pub unsafe fn resume_from(
+        self,
+        frame: SuspendedFrame,
+        artifact: revm_primitives::B256,
+        outcome: FrameOutcome,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> Result<InterpreterAction, SuspendedFrameError> {
+        if !self.has_resume_indexes() {
+            return Err(SuspendedFrameError::NotPortable);
+        }
+        frame.restore_into(interpreter, artifact)?;
+        outcome.insert_into(interpreter);
+        Ok(self.call_with_interpreter(interpreter, host))
+    }
+
+    /// Same as [`call_with_interpreter`](Self::call_with_interpreter), but returns the error of a
+    /// failed host call, if any.
+    ///
+    /// See [`report_host_error`] for more information.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the function is safe to call.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub unsafe fn try_call_with_interpreter(
+        self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> Result<InterpreterAction, HostError> {
+        let mut host_error = None;
+        let config = &mut ContextConfig::default();
+        let action = self.call_with_interpreter_inner(interpreter, host, config, |ecx| {
+            host_error = ecx.take_host_error();
+        });
+        match host_error {
+            Some(error) => Err(error),
+            None => Ok(action),
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn call_with_interpreter_inner(
+        self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+        config: &mut ContextConfig<'_>,
+        after_call: impl FnOnce(&mut EvmContext<'_>),
     ) -> InterpreterAction {
         interpreter.next_action = InterpreterAction::None;
 
+        // The stack is only accessed by the function, which doesn't read uninitialized words.
         let (mut ecx, stack, stack_len) =
-            EvmContext::from_interpreter_with_stack(interpreter, host);
+            EvmContext::from_interpreter_with_stack_uninit(interpreter, host);
+        ecx.set_config(config.reborrow());
         let result = self.call(Some(stack), Some(stack_len), &mut ecx);
+        after_call(&mut ecx);
+
+        // Don't leave the stale length of the stack before the call.
+        if self.meta.is_some_and(|meta| !meta.writes_stack_length) {
+            *stack_len = 0;
+        }
 
         // Set the remaining gas to 0 if the result is `OutOfGas`,
         // as it might have overflown inside of the function.
@@ -303,7 +1333,13 @@ impl EvmCompilerFn {
     ///   to `true`.
     /// - `ecx`: The context object.
     ///
-    /// These conditions are enforced at runtime if `debug_assertions` is set to `true`.
+    /// These conditions are enforced here if the function carries its [`FnMeta`], and inside of
+    /// the function if `debug_assertions` is set to `true`.
+    ///
+    /// If the function carries its [`FnMeta`] and inspects the stack length, starting it with a
+    /// stack shorter than [`FnMeta::min_stack_len`] returns
+    /// [`InstructionResult::StackUnderflow`] without calling it. This makes such calls safe even
+    /// if the function was compiled without stack bound checks.
     ///
     /// Use of this method is discouraged, as setup and cleanup need to be done manually.
     ///
@@ -317,16 +1353,69 @@ impl EvmCompilerFn {
         stack_len: Option<&mut usize>,
         ecx: &mut EvmContext<'_>,
     ) -> InstructionResult {
-        (self.0)(
+        self.call_ptr(option_as_mut_ptr(stack), stack_len, ecx)
+    }
+
+    /// Same as [`call`](Self::call), but with a stack created with [`EvmStack::new_uninit`].
+    ///
+    /// After the call, read the first `stack_len` words with [`EvmStack::assume_init_slice`].
+    ///
+    /// # Safety
+    ///
+    /// See [`call`](Self::call).
+    #[inline]
+    pub unsafe fn call_uninit(
+        self,
+        stack: &mut MaybeUninit<EvmStack>,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> InstructionResult {
+        self.call_ptr(stack.as_mut_ptr(), stack_len, ecx)
+    }
+
+    #[inline]
+    unsafe fn call_ptr(
+        self,
+        stack: *mut EvmStack,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> InstructionResult {
+        if let Some(meta) = self.meta {
+            meta.check_args(!stack.is_null(), stack_len.is_some());
+            if meta.underflows(stack_len.as_deref(), ecx.resume_at) {
+                return InstructionResult::StackUnderflow;
+            }
+        }
+        clear_reported_host_error();
+        (self.f)(
             ecx.gas,
-            option_as_mut_ptr(stack),
+            stack,
             option_as_mut_ptr(stack_len),
             ecx.host.env(),
-            ecx.contract,
+            &ContractView::new(ecx.contract),
             ecx,
         )
     }
 
+    /// Same as [`call`](Self::call), but first checks the context against the information
+    /// recorded when compiling the function, returning an error instead of calling it on mismatch.
+    ///
+    /// # Safety
+    ///
+    /// See [`call`](Self::call).
+    #[inline]
+    pub unsafe fn call_checked(
+        self,
+        info: &FnInfo,
+        stack: Option<&mut EvmStack>,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> Result<InstructionResult, CallCheckError> {
+        info.check_signature(FnSignature::Default)?;
+        info.check(ecx)?;
+        Ok(self.call(stack, stack_len, ecx))
+    }
+
     /// Same as [`call`](Self::call) but with `#[inline(never)]`.
     ///
     /// Use of this method is discouraged, as setup and cleanup need to be done manually.
@@ -343,126 +1432,595 @@ impl EvmCompilerFn {
     ) -> InstructionResult {
         self.call(stack, stack_len, ecx)
     }
-}
 
-/// EVM context stack.
-#[repr(C)]
-#[allow(missing_debug_implementations)]
-pub struct EvmStack([MaybeUninit<EvmWord>; 1024]);
+    /// Calls each function in order with the same context and stack, collecting the results.
+    ///
+    /// The stack length and resume point are reset before each call. Everything else, such as gas
+    /// and memory, carries over from one call to the next unless reset by the caller.
+    ///
+    /// # Safety
+    ///
+    /// See [`call`](Self::call).
+    pub unsafe fn call_batch(
+        funcs: &[Self],
+        ecx: &mut EvmContext<'_>,
+        stack: &mut EvmStack,
+        stack_len: &mut usize,
+    ) -> Vec<InstructionResult> {
+        funcs
+            .iter()
+            .map(|f| {
+                *stack_len = 0;
+                ecx.resume_at = 0;
+                f.call(Some(stack), Some(stack_len), ecx)
+            })
+            .collect()
+    }
+}
 
-#[allow(clippy::new_without_default)]
-impl EvmStack {
-    /// The size of the stack in bytes.
-    pub const SIZE: usize = 32 * Self::CAPACITY;
+/// The raw function signature of a bytecode function compiled without the gas parameter.
+///
+/// Prefer using [`EvmCompilerFnNoGas`] instead of this type. See [`FnSignature::NoGas`] for more
+/// information.
+// When changing the signature, also update the corresponding declarations in `fn translate`.
+pub type RawEvmCompilerFnNoGas = unsafe extern "C" fn(
+    stack: *mut EvmStack,
+    stack_len: *mut usize,
+    env: *const Env,
+    contract: *const ContractView,
+    ecx: *mut EvmContext<'_>,
+) -> InstructionResult;
 
-    /// The size of the stack in U256 elements.
-    pub const CAPACITY: usize = 1024;
+/// An EVM bytecode function compiled without the gas parameter.
+///
+/// See [`FnSignature::NoGas`].
+#[derive(Clone, Copy, Debug)]
+pub struct EvmCompilerFnNoGas(RawEvmCompilerFnNoGas);
 
-    /// Creates a new EVM stack, allocated on the stack.
-    ///
-    /// Use [`EvmStack::new_heap`] to create a stack on the heap.
+impl From<RawEvmCompilerFnNoGas> for EvmCompilerFnNoGas {
     #[inline]
-    pub fn new() -> Self {
-        Self(unsafe { MaybeUninit::uninit().assume_init() })
+    fn from(f: RawEvmCompilerFnNoGas) -> Self {
+        Self::new(f)
     }
+}
 
-    /// Creates a vector that can be used as a stack.
+impl From<EvmCompilerFnNoGas> for RawEvmCompilerFnNoGas {
     #[inline]
-    pub fn new_heap() -> Vec<EvmWord> {
-        Vec::with_capacity(1024)
+    fn from(f: EvmCompilerFnNoGas) -> Self {
+        f.into_inner()
     }
+}
 
-    /// Creates a stack from the interpreter's stack. Assumes that the stack is large enough.
+impl EvmCompilerFnNoGas {
+    /// Wraps the function.
     #[inline]
-    pub fn from_interpreter_stack(stack: &mut revm_interpreter::Stack) -> (&mut Self, &mut usize) {
-        debug_assert!(stack.data().capacity() >= Self::CAPACITY);
-        unsafe {
-            let data = Self::from_mut_ptr(stack.data_mut().as_mut_ptr().cast());
-            // Vec { data: ptr, cap: usize, len: usize }
-            let len = &mut *(stack.data_mut() as *mut Vec<_>).cast::<usize>().add(2);
-            debug_assert_eq!(stack.len(), *len);
-            (data, len)
-        }
+    pub const fn new(f: RawEvmCompilerFnNoGas) -> Self {
+        Self(f)
     }
 
-    /// Creates a stack from a vector's buffer.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the vector's capacity is less than the required stack capacity.
+    /// Unwraps the function.
     #[inline]
-    pub fn from_vec(vec: &Vec<EvmWord>) -> &Self {
-        assert!(vec.capacity() >= Self::CAPACITY);
-        unsafe { Self::from_ptr(vec.as_ptr()) }
+    pub const fn into_inner(self) -> RawEvmCompilerFnNoGas {
+        self.0
     }
 
-    /// Creates a stack from a mutable vector's buffer.
-    ///
-    /// The bytecode function will overwrite the internal contents of the vector, and will not
-    /// set the length. This is simply to have the stack allocated on the heap.
-    ///
-    /// # Panics
+    /// Calls the function.
     ///
-    /// Panics if the vector's capacity is less than the required stack capacity.
+    /// Same as [`EvmCompilerFn::call`], but [`EvmContext::gas`] is only used by builtins.
     ///
-    /// # Examples
+    /// # Safety
     ///
-    /// ```rust
-    /// use revmc_context::EvmStack;
-    /// let mut stack_buf = EvmStack::new_heap();
-    /// let stack = EvmStack::from_mut_vec(&mut stack_buf);
-    /// assert_eq!(stack.as_slice().len(), EvmStack::CAPACITY);
-    /// ```
+    /// See [`EvmCompilerFn::call`].
     #[inline]
-    pub fn from_mut_vec(vec: &mut Vec<EvmWord>) -> &mut Self {
-        assert!(vec.capacity() >= Self::CAPACITY);
-        unsafe { Self::from_mut_ptr(vec.as_mut_ptr()) }
+    pub unsafe fn call(
+        self,
+        stack: Option<&mut EvmStack>,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> InstructionResult {
+        self.call_ptr(option_as_mut_ptr(stack), stack_len, ecx)
     }
 
-    /// Creates a stack from a slice.
+    /// Same as [`EvmCompilerFn::call_uninit`], but [`EvmContext::gas`] is only used by builtins.
     ///
-    /// # Panics
+    /// # Safety
     ///
-    /// Panics if the slice's length is less than the required stack capacity.
+    /// See [`EvmCompilerFn::call`].
     #[inline]
-    pub const fn from_slice(slice: &[EvmWord]) -> &Self {
-        assert!(slice.len() >= Self::CAPACITY);
-        unsafe { Self::from_ptr(slice.as_ptr()) }
+    pub unsafe fn call_uninit(
+        self,
+        stack: &mut MaybeUninit<EvmStack>,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> InstructionResult {
+        self.call_ptr(stack.as_mut_ptr(), stack_len, ecx)
     }
 
-    /// Creates a stack from a mutable slice.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the slice's length is less than the required stack capacity.
     #[inline]
-    pub fn from_mut_slice(slice: &mut [EvmWord]) -> &mut Self {
-        assert!(slice.len() >= Self::CAPACITY);
-        unsafe { Self::from_mut_ptr(slice.as_mut_ptr()) }
+    unsafe fn call_ptr(
+        self,
+        stack: *mut EvmStack,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> InstructionResult {
+        clear_reported_host_error();
+        (self.0)(
+            stack,
+            option_as_mut_ptr(stack_len),
+            ecx.host.env(),
+            &ContractView::new(ecx.contract),
+            ecx,
+        )
     }
 
-    /// Creates a stack from a pointer.
+    /// Same as [`call`](Self::call), but first checks the context against the information
+    /// recorded when compiling the function, returning an error instead of calling it on mismatch.
     ///
     /// # Safety
     ///
-    /// The caller must ensure that the pointer is valid and points to at least [`EvmStack::SIZE`]
-    /// bytes.
+    /// See [`call`](Self::call).
     #[inline]
-    pub const unsafe fn from_ptr<'a>(ptr: *const EvmWord) -> &'a Self {
-        &*ptr.cast()
+    pub unsafe fn call_checked(
+        self,
+        info: &FnInfo,
+        stack: Option<&mut EvmStack>,
+        stack_len: Option<&mut usize>,
+        ecx: &mut EvmContext<'_>,
+    ) -> Result<InstructionResult, CallCheckError> {
+        info.check_signature(FnSignature::NoGas)?;
+        info.check(ecx)?;
+        Ok(self.call(stack, stack_len, ecx))
     }
+}
 
-    /// Creates a stack from a mutable pointer.
-    ///
+/// Information about how an [`EvmCompilerFn`] was compiled.
+///
+/// Returned by the compiler alongside the function, and checked by
+/// [`EvmCompilerFn::call_checked`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FnInfo {
+    /// The number of resume points, not counting the function entry.
+    pub resume_points: u32,
+    /// The [`EvmContext::is_static`] value that the function was specialized for, if any.
+    pub static_context: Option<bool>,
+    /// The dynamic gas cost parameters that the function was compiled with.
+    pub gas_params: GasParams,
+    /// How the function handles the preconditions on its arguments.
+    pub assertions: Assertions,
+    /// How the function meters gas.
+    pub gas_mode: GasMode,
+    /// The signature of the function, which determines the wrapper to call it through.
+    pub signature: FnSignature,
+    /// The calling convention of the function, which is also attached to the JIT-compiled
+    /// [`EvmCompilerFn`].
+    pub meta: FnMeta,
+}
+
+impl FnInfo {
+    /// Checks that a function with this information can be called with the given context.
+    pub fn check(&self, ecx: &EvmContext<'_>) -> Result<(), CallCheckError> {
+        if let Some(expected) = self.static_context {
+            if ecx.is_static != expected {
+                return Err(CallCheckError::StaticContext { expected });
+            }
+        }
+        if ecx.gas_params.unwrap_or(&GasParams::DEFAULT) != &self.gas_params {
+            return Err(CallCheckError::GasParams);
+        }
+        if (ecx.gas_mode == GasMode::ObserveOnly) != (self.gas_mode == GasMode::ObserveOnly) {
+            return Err(CallCheckError::GasMode { expected: self.gas_mode });
+        }
+        Ok(())
+    }
+
+    /// Checks that the function is called through the wrapper for its signature.
+    fn check_signature(&self, wrapper: FnSignature) -> Result<(), CallCheckError> {
+        if self.signature != wrapper {
+            return Err(CallCheckError::Signature { expected: self.signature });
+        }
+        Ok(())
+    }
+}
+
+/// The calling convention that an [`EvmCompilerFn`] was compiled with.
+///
+/// Attached to the function with [`EvmCompilerFn::new_with_meta`], and checked by
+/// [`EvmCompilerFn::call`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FnMeta {
+    /// Whether the stack is allocated in the function, in which case the stack argument is
+    /// ignored.
+    pub local_stack: bool,
+    /// Whether the stack length is observable outside the function, in which case the stack
+    /// length argument is required.
+    pub inspect_stack_length: bool,
+    /// Whether the function stores the final stack length through the stack length argument.
+    ///
+    /// This is the case if [`inspect_stack_length`](Self::inspect_stack_length) is set, or if the
+    /// bytecode may suspend execution. Otherwise the length is never written, and
+    /// [`EvmCompilerFn::call_with_interpreter`] empties the interpreter's stack after the call.
+    pub writes_stack_length: bool,
+    /// Whether gas costs are tracked.
+    pub gas_metered: bool,
+    /// Whether the stack length is checked against the stack bounds.
+    pub stack_bound_checks: bool,
+    /// The stack length that the function requires on entry, which is the number of stack items
+    /// that its first section reads before pushing them.
+    ///
+    /// Only relevant if [`inspect_stack_length`](Self::inspect_stack_length) is set, since the
+    /// function otherwise starts with an empty stack. [`EvmCompilerFn::call`] returns
+    /// [`InstructionResult::StackUnderflow`] without calling the function if the stack length
+    /// argument is smaller.
+    pub min_stack_len: u16,
+    /// Whether the [`EvmContext::resume_at`] values of the function are indexes of its resume
+    /// points rather than code addresses. Only indexes can be used in another process, see
+    /// [`SuspendedFrame`].
+    pub resume_indexes: bool,
+}
+
+impl Default for FnMeta {
+    /// Returns the compiler defaults.
+    fn default() -> Self {
+        Self {
+            local_stack: false,
+            inspect_stack_length: false,
+            writes_stack_length: false,
+            gas_metered: true,
+            stack_bound_checks: true,
+            min_stack_len: 0,
+            resume_indexes: false,
+        }
+    }
+}
+
+impl FnMeta {
+    /// Asserts that the stack and stack length arguments are passed if the function needs them.
+    #[inline]
+    #[track_caller]
+    fn check_args(&self, has_stack: bool, has_stack_len: bool) {
+        assert!(self.local_stack || has_stack, "stack is required by the function");
+        assert!(
+            !(self.inspect_stack_length || self.writes_stack_length) || has_stack_len,
+            "stack length is required by the function"
+        );
+    }
+
+    /// Returns `true` if a function starting with the given stack length would underflow the
+    /// stack on entry.
+    #[inline]
+    fn underflows(&self, stack_len: Option<&usize>, resume_at: usize) -> bool {
+        self.inspect_stack_length
+            && resume_at == 0
+            && stack_len.is_some_and(|&len| len < self.min_stack_len as usize)
+    }
+}
+
+/// The signature of a compiled function.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FnSignature {
+    /// [`RawEvmCompilerFn`], called through [`EvmCompilerFn`].
+    #[default]
+    Default,
+    /// [`RawEvmCompilerFnNoGas`], called through [`EvmCompilerFnNoGas`].
+    ///
+    /// The gas parameter is dropped, which is only possible with gas metering disabled, and `GAS`
+    /// pushes a constant. This is meant for measuring raw throughput, not for execution.
+    NoGas,
+}
+
+/// How a compiled function handles the preconditions on its arguments, such as pointers that
+/// must not be null.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Assertions {
+    /// Check the preconditions at runtime, panicking if they are violated.
+    Check,
+    /// Assume that the preconditions hold, allowing the optimizer to exploit them.
+    ///
+    /// Violating them is undefined behavior.
+    Assume,
+    /// Neither check nor assume the preconditions.
+    #[default]
+    None,
+}
+
+/// The error returned by [`EvmCompilerFn::call_checked`] when the arguments do not match how the
+/// function was compiled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CallCheckError {
+    /// The function was specialized for a different [`EvmContext::is_static`] value.
+    StaticContext {
+        /// The value that the function was specialized for.
+        expected: bool,
+    },
+    /// The function was compiled with different [`EvmContext::gas_params`].
+    GasParams,
+    /// The function was compiled with a different [`EvmContext::gas_mode`].
+    GasMode {
+        /// The mode that the function was compiled with.
+        expected: GasMode,
+    },
+    /// The function is called through the wrapper of a different signature.
+    Signature {
+        /// The signature that the function was compiled with.
+        expected: FnSignature,
+    },
+}
+
+impl fmt::Display for CallCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaticContext { expected } => {
+                write!(f, "function was compiled for `is_static = {expected}`")
+            }
+            Self::GasParams => f.write_str("function was compiled with different gas parameters"),
+            Self::GasMode { expected } => {
+                write!(f, "function was compiled with gas mode `{expected:?}`")
+            }
+            Self::Signature { expected } => {
+                write!(f, "function was compiled with signature `{expected:?}`")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CallCheckError {}
+
+/// EVM context stack.
+///
+/// The safe accessors, like [`as_slice`](Self::as_slice), assume that all the words are
+/// initialized, which all the safe constructors guarantee. Compiled functions never read words
+/// that they haven't written, so a stack from [`new_uninit`](Self::new_uninit) can be passed to
+/// them directly, without ever creating an `EvmStack` reference to it.
+#[repr(C)]
+#[allow(missing_debug_implementations)]
+pub struct EvmStack([MaybeUninit<EvmWord>; 1024]);
+
+#[allow(clippy::new_without_default)]
+impl EvmStack {
+    /// The size of the stack in bytes.
+    pub const SIZE: usize = 32 * Self::CAPACITY;
+
+    /// The size of the stack in U256 elements.
+    pub const CAPACITY: usize = 1024;
+
+    /// Creates a new zero-initialized EVM stack, allocated on the stack.
+    ///
+    /// Use [`EvmStack::new_heap`] to create a stack on the heap.
+    #[inline]
+    pub fn new() -> Self {
+        Self([MaybeUninit::new(EvmWord::ZERO); Self::CAPACITY])
+    }
+
+    /// Creates a new uninitialized EVM stack, allocated on the stack.
+    ///
+    /// This skips zeroing [`SIZE`](Self::SIZE) bytes, for stacks that are only passed to compiled
+    /// functions with [`EvmCompilerFn::call_uninit`]. After a call, only the first `stack_len`
+    /// words are initialized, and they must be read with
+    /// [`assume_init_slice`](Self::assume_init_slice).
+    ///
+    /// The stack must never be assumed to be initialized, for example with
+    /// [`MaybeUninit::assume_init_mut`], since the safe accessors would read the uninitialized
+    /// words.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use revmc_context::EvmStack;
+    /// let mut stack = EvmStack::new_uninit();
+    /// let mut stack_len = 0;
+    /// // ... call a compiled function with `f.call_uninit(&mut stack, Some(&mut stack_len), ecx)` ...
+    /// // SAFETY: Compiled functions initialize the first `stack_len` words.
+    /// let words = unsafe { EvmStack::assume_init_slice(&stack, stack_len) };
+    /// assert!(words.is_empty());
+    /// ```
+    #[inline]
+    pub const fn new_uninit() -> MaybeUninit<Self> {
+        MaybeUninit::uninit()
+    }
+
+    /// Creates a zero-initialized vector that can be used as a stack.
+    #[inline]
+    pub fn new_heap() -> Vec<EvmWord> {
+        alloc::vec![EvmWord::ZERO; Self::CAPACITY]
+    }
+
+    /// Creates a zero-initialized vector that can be used as a stack, allocated with the given
+    /// allocator.
+    ///
+    /// This allows reusing stack buffers from a pool or an arena instead of the global allocator.
+    #[cfg(feature = "allocator_api")]
+    #[inline]
+    pub fn new_heap_in<A: Allocator>(alloc: A) -> Vec<EvmWord, A> {
+        let mut vec = Vec::with_capacity_in(Self::CAPACITY, alloc);
+        vec.resize(Self::CAPACITY, EvmWord::ZERO);
+        vec
+    }
+
+    /// Creates a stack from the interpreter's stack.
+    ///
+    /// The words past the interpreter's stack length are zeroed, which writes up to
+    /// [`CAPACITY`](Self::CAPACITY) words. The returned length is the length of the interpreter's
+    /// stack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack's capacity is less than the required stack capacity.
+    #[inline]
+    pub fn from_interpreter_stack(stack: &mut revm_interpreter::Stack) -> (&mut Self, &mut usize) {
+        Self::zero_interpreter_stack(stack);
+        // SAFETY: The stack was just fully initialized.
+        unsafe { Self::from_interpreter_stack_uninit(stack) }
+    }
+
+    /// Zeroes the words past the interpreter's stack length.
+    fn zero_interpreter_stack(stack: &mut revm_interpreter::Stack) {
+        let data = stack.data_mut();
+        assert!(data.capacity() >= Self::CAPACITY);
+        let unused = Self::CAPACITY.saturating_sub(data.len());
+        for word in &mut data.spare_capacity_mut()[..unused] {
+            word.write(U256::ZERO);
+        }
+    }
+
+    /// Same as [`from_interpreter_stack`](Self::from_interpreter_stack), but doesn't initialize
+    /// the words past the interpreter's stack length.
+    ///
+    /// # Safety
+    ///
+    /// The stack's capacity must be at least [`EvmStack::CAPACITY`], and only the first
+    /// `stack_len` words of the returned stack may be read.
+    #[inline]
+    unsafe fn from_interpreter_stack_uninit(
+        stack: &mut revm_interpreter::Stack,
+    ) -> (&mut Self, &mut usize) {
+        debug_assert!(stack.data().capacity() >= Self::CAPACITY);
+        unsafe {
+            // Not `from_mut_ptr`, since the words past the length may be uninitialized.
+            let data = &mut *stack.data_mut().as_mut_ptr().cast::<Self>();
+            // Vec { data: ptr, cap: usize, len: usize }
+            let len = &mut *(stack.data_mut() as *mut Vec<_>).cast::<usize>().add(2);
+            debug_assert_eq!(stack.len(), *len);
+            (data, len)
+        }
+    }
+
+    /// Creates a stack from a vector's buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector's length is less than the required stack capacity.
+    #[cfg(not(feature = "allocator_api"))]
+    #[inline]
+    #[allow(clippy::ptr_arg)]
+    pub fn from_vec(vec: &Vec<EvmWord>) -> &Self {
+        Self::from_slice(vec)
+    }
+
+    /// Creates a stack from a vector's buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector's length is less than the required stack capacity.
+    #[cfg(feature = "allocator_api")]
+    #[inline]
+    #[allow(clippy::ptr_arg)]
+    pub fn from_vec<A: Allocator>(vec: &Vec<EvmWord, A>) -> &Self {
+        Self::from_slice(vec)
+    }
+
+    /// Creates a stack from a mutable vector's buffer.
+    ///
+    /// The bytecode function will overwrite the contents of the vector, and will not change its
+    /// length. This is simply to have the stack allocated on the heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector's length is less than the required stack capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use revmc_context::EvmStack;
+    /// let mut stack_buf = EvmStack::new_heap();
+    /// let stack = EvmStack::from_mut_vec(&mut stack_buf);
+    /// assert_eq!(stack.as_slice().len(), EvmStack::CAPACITY);
+    /// ```
+    #[cfg(not(feature = "allocator_api"))]
+    #[inline]
+    #[allow(clippy::ptr_arg)]
+    pub fn from_mut_vec(vec: &mut Vec<EvmWord>) -> &mut Self {
+        Self::from_mut_slice(vec)
+    }
+
+    /// Creates a stack from a mutable vector's buffer.
+    ///
+    /// The bytecode function will overwrite the contents of the vector, and will not change its
+    /// length. This is simply to have the stack allocated on the heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector's length is less than the required stack capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(allocator_api)]
+    /// use revmc_context::EvmStack;
+    /// use std::alloc::Global;
+    ///
+    /// let mut stack_buf = EvmStack::new_heap_in(Global);
+    /// let stack = EvmStack::from_mut_vec(&mut stack_buf);
+    /// assert_eq!(stack.as_slice().len(), EvmStack::CAPACITY);
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    #[inline]
+    #[allow(clippy::ptr_arg)]
+    pub fn from_mut_vec<A: Allocator>(vec: &mut Vec<EvmWord, A>) -> &mut Self {
+        Self::from_mut_slice(vec)
+    }
+
+    /// Creates a stack from a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice's length is less than the required stack capacity.
+    #[inline]
+    pub const fn from_slice(slice: &[EvmWord]) -> &Self {
+        assert!(slice.len() >= Self::CAPACITY);
+        unsafe { Self::from_ptr(slice.as_ptr()) }
+    }
+
+    /// Creates a stack from a mutable slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice's length is less than the required stack capacity.
+    #[inline]
+    pub fn from_mut_slice(slice: &mut [EvmWord]) -> &mut Self {
+        assert!(slice.len() >= Self::CAPACITY);
+        unsafe { Self::from_mut_ptr(slice.as_mut_ptr()) }
+    }
+
+    /// Creates a stack from a pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the pointer is valid and points to at least [`EvmStack::SIZE`]
+    /// initialized bytes.
+    #[inline]
+    pub const unsafe fn from_ptr<'a>(ptr: *const EvmWord) -> &'a Self {
+        &*ptr.cast()
+    }
+
+    /// Creates a stack from a mutable pointer.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that the pointer is valid and points to at least [`EvmStack::SIZE`]
-    /// bytes.
+    /// initialized bytes.
     #[inline]
     pub unsafe fn from_mut_ptr<'a>(ptr: *mut EvmWord) -> &'a mut Self {
         &mut *ptr.cast()
     }
 
+    /// Returns the size of the stack in bytes, [`SIZE`](Self::SIZE).
+    ///
+    /// This is the size of the buffer to allocate when passing a stack over FFI.
+    #[inline]
+    pub const fn capacity_bytes(&self) -> usize {
+        Self::SIZE
+    }
+
+    /// Returns the size of the stack in words, [`CAPACITY`](Self::CAPACITY).
+    #[inline]
+    pub const fn capacity_words(&self) -> usize {
+        Self::CAPACITY
+    }
+
     /// Returns the stack as a byte array.
     #[inline]
     pub const fn as_bytes(&self) -> &[u8; Self::SIZE] {
@@ -486,15 +2044,97 @@ impl EvmStack {
     pub fn as_mut_slice(&mut self) -> &mut [EvmWord; Self::CAPACITY] {
         unsafe { &mut *self.0.as_mut_ptr().cast() }
     }
+
+    /// Overwrites the bottom of the stack with `words`, given in top-to-bottom order, and sets
+    /// `len` to the number of words.
+    ///
+    /// The first word becomes the top of the stack, which is how stacks are usually written in
+    /// EIPs and tests: after `set_from_top(&mut len, &[a, b])`, `SUB` computes `a - b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `words` is longer than [`EvmStack::CAPACITY`].
+    #[inline]
+    pub fn set_from_top(&mut self, len: &mut usize, words: &[EvmWord]) {
+        assert!(words.len() <= Self::CAPACITY, "stack overflow");
+        for (slot, word) in self.0.iter_mut().zip(words.iter().rev()) {
+            slot.write(*word);
+        }
+        *len = words.len();
+    }
+
+    /// Returns the word at `depth` from the top of a stack of `len` words, without popping it.
+    ///
+    /// Depth `0` is the top of the stack. Returns `None` if `depth` is not less than `len`, or if
+    /// `len` is greater than [`EvmStack::CAPACITY`].
+    #[inline]
+    pub fn peek(&self, len: usize, depth: usize) -> Option<&EvmWord> {
+        if depth >= len {
+            return None;
+        }
+        self.as_slice().get(len - 1 - depth)
+    }
+
+    /// Returns a [`StackReader`] that pops typed values from the top of a stack of `len` words.
+    #[inline]
+    pub fn reader(&self, len: usize) -> StackReader<'_> {
+        StackReader::new(self, len)
+    }
+
+    /// Returns the first `len` words of a stack created with [`new_uninit`](Self::new_uninit).
+    ///
+    /// # Safety
+    ///
+    /// The first `len` words must be initialized, such as after calling a compiled function that
+    /// set the stack length to `len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than [`EvmStack::CAPACITY`].
+    #[inline]
+    pub unsafe fn assume_init_slice(stack: &MaybeUninit<Self>, len: usize) -> &[EvmWord] {
+        // SAFETY: `EvmStack` only holds `MaybeUninit` words, which may be uninitialized.
+        let words = unsafe { &stack.assume_init_ref().0[..len] };
+        unsafe { &*(words as *const [MaybeUninit<EvmWord>] as *const [EvmWord]) }
+    }
 }
 
 /// A native-endian 256-bit unsigned integer, aligned to 8 bytes.
 ///
-/// This is a transparent wrapper around [`U256`] on little-endian targets.
+/// This is a transparent wrapper around [`U256`] on little-endian targets. On big-endian targets
+/// the layouts differ, since [`U256`] always stores its limbs in little-endian order, so the
+/// reference casts ([`as_u256`](Self::as_u256) and friends) are only available on little-endian
+/// targets. Use [`to_u256`](Self::to_u256) and [`with_u256_mut`](Self::with_u256_mut) instead to
+/// support both.
 #[repr(C, align(8))]
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct EvmWord([u8; 32]);
 
+// The casts between `EvmWord` and `U256` rely on `U256` being 4 little-endian `u64` limbs, stored
+// in order, and not aligned more than `EvmWord`. Fail to compile if `ruint` ever changes this.
+const _: () = {
+    assert!(core::mem::size_of::<EvmWord>() == core::mem::size_of::<U256>());
+    assert!(core::mem::align_of::<EvmWord>() >= core::mem::align_of::<U256>());
+
+    #[cfg(target_endian = "little")]
+    {
+        const LIMBS: [u64; 4] =
+            [0x0706050403020100, 0x0f0e0d0c0b0a0908, 0x1716151413121110, 0x1f1e1d1c1b1a1918];
+        let bytes = EvmWord::from_u256(U256::from_limbs(LIMBS)).to_ne_bytes();
+        let mut i = 0;
+        while i < 32 {
+            assert!(bytes[i] == i as u8, "unexpected `U256` layout");
+            i += 1;
+        }
+        let limbs = EvmWord::from_ne_bytes(bytes).into_u256().into_limbs();
+        let mut i = 0;
+        while i < 4 {
+            assert!(limbs[i] == LIMBS[i], "unexpected `U256` layout");
+            i += 1;
+        }
+    }
+};
+
 macro_rules! impl_fmt {
     ($($trait:ident),* $(,)?) => {
         $(
@@ -566,6 +2206,38 @@ macro_rules! impl_conversions_through_u256 {
 
 impl_conversions_through_u256!(bool, u8, u16, u32, u64, usize, u128);
 
+impl TryFrom<&[u8]> for EvmWord {
+    type Error = ();
+
+    /// See [`EvmWord::from_be_slice`]. Fails if the slice is longer than 32 bytes.
+    #[inline]
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        if slice.len() > 32 {
+            return Err(());
+        }
+        Ok(Self::from_be_slice(slice))
+    }
+}
+
+impl TryFrom<EvmWord> for Address {
+    type Error = ();
+
+    /// See [`EvmWord::to_address_checked`].
+    #[inline]
+    fn try_from(value: EvmWord) -> Result<Self, Self::Error> {
+        value.to_address_checked().ok_or(())
+    }
+}
+
+impl TryFrom<&EvmWord> for Address {
+    type Error = ();
+
+    #[inline]
+    fn try_from(value: &EvmWord) -> Result<Self, Self::Error> {
+        (*value).try_into()
+    }
+}
+
 impl From<U256> for EvmWord {
     #[inline]
     fn from(value: U256) -> Self {
@@ -587,14 +2259,89 @@ impl From<&mut U256> for EvmWord {
     }
 }
 
-impl EvmWord {
-    /// The zero value.
-    pub const ZERO: Self = Self([0; 32]);
+/// Parses a `0x`-prefixed hexadecimal or a decimal string, like [`U256`].
+#[cfg(feature = "std")]
+impl core::str::FromStr for EvmWord {
+    type Err = ParseError;
 
-    /// Creates a new value from native-endian bytes.
     #[inline]
-    pub const fn from_ne_bytes(x: [u8; 32]) -> Self {
-        Self(x)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        U256::from_str(s).map(Self::from_u256)
+    }
+}
+
+impl core::hash::Hash for EvmWord {
+    /// Hashes the value like the [`U256`] that it converts to, on all targets.
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.to_u256().hash(state);
+    }
+}
+
+impl PartialOrd for EvmWord {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EvmWord {
+    /// Compares the values as unsigned integers, regardless of the target's endianness.
+    ///
+    /// See [`signed_cmp`](Self::signed_cmp) for the signed comparison.
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_u256().cmp(&other.to_u256())
+    }
+}
+
+impl EvmWord {
+    /// The zero value.
+    pub const ZERO: Self = Self([0; 32]);
+
+    /// The maximum value, `2**256 - 1`.
+    pub const MAX: Self = Self([0xff; 32]);
+
+    /// Creates a new value from a `u64`.
+    #[inline]
+    pub const fn from_u64(x: u64) -> Self {
+        Self::from_limbs([x, 0, 0, 0])
+    }
+
+    /// Parses a big-endian hexadecimal string of at most 64 digits, with an optional `0x` prefix.
+    ///
+    /// This is meant for constants, for example `const X: EvmWord = EvmWord::from_be_hex("0x69")`.
+    /// Use [`from_str_radix`](Self::from_str_radix) to parse untrusted input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string is empty, longer than 64 digits, or contains a non-hexadecimal digit.
+    pub const fn from_be_hex(s: &str) -> Self {
+        let s = s.as_bytes();
+        let mut i =
+            if s.len() >= 2 && s[0] == b'0' && (s[1] == b'x' || s[1] == b'X') { 2 } else { 0 };
+        assert!(i < s.len() && s.len() - i <= 64, "expected 1 to 64 hexadecimal digits");
+        let mut limbs = [0u64; 4];
+        while i < s.len() {
+            let digit = match s[i] {
+                b'0'..=b'9' => s[i] - b'0',
+                b'a'..=b'f' => s[i] - b'a' + 10,
+                b'A'..=b'F' => s[i] - b'A' + 10,
+                _ => panic!("invalid hexadecimal digit"),
+            };
+            limbs[3] = limbs[3] << 4 | limbs[2] >> 60;
+            limbs[2] = limbs[2] << 4 | limbs[1] >> 60;
+            limbs[1] = limbs[1] << 4 | limbs[0] >> 60;
+            limbs[0] = limbs[0] << 4 | digit as u64;
+            i += 1;
+        }
+        Self::from_limbs(limbs)
+    }
+
+    /// Creates a new value from native-endian bytes.
+    #[inline]
+    pub const fn from_ne_bytes(x: [u8; 32]) -> Self {
+        Self(x)
     }
 
     /// Creates a new value from big-endian bytes.
@@ -609,6 +2356,30 @@ impl EvmWord {
         Self::from_le(Self(x))
     }
 
+    /// Creates a new value from at most 32 big-endian bytes.
+    ///
+    /// A shorter slice is the low bytes of the value, like the immediate of a `PUSH` shorter than
+    /// 32 bytes, and an empty slice is zero. Unlike padding the slice into a `[u8; 32]` and calling
+    /// [`from_be_bytes`](Self::from_be_bytes), this writes the bytes directly in native order.
+    ///
+    /// `TryFrom<&[u8]>` is the same, but fails instead of panicking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is longer than 32 bytes.
+    #[inline]
+    pub fn from_be_slice(slice: &[u8]) -> Self {
+        assert!(slice.len() <= 32, "slice of {} bytes does not fit in a word", slice.len());
+        let mut word = Self::ZERO;
+        #[cfg(target_endian = "little")]
+        for (dst, &src) in word.0.iter_mut().zip(slice.iter().rev()) {
+            *dst = src;
+        }
+        #[cfg(target_endian = "big")]
+        word.0[32 - slice.len()..].copy_from_slice(slice);
+        word
+    }
+
     /// Converts an integer from big endian to the target's endianness.
     #[inline]
     pub fn from_be(x: Self) -> Self {
@@ -636,6 +2407,27 @@ impl EvmWord {
         return Self(value.to_be_bytes());
     }
 
+    /// Creates a new value from 4 little-endian `u64` limbs, like [`U256::from_limbs`].
+    ///
+    /// Unlike [`from_u256`](Self::from_u256), this is usable in constants on all targets.
+    #[inline]
+    pub const fn from_limbs(limbs: [u64; 4]) -> Self {
+        let mut bytes = [0u8; 32];
+        let mut i = 0;
+        while i < 4 {
+            let limb = limbs[i].to_ne_bytes();
+            // The most significant limb comes first on big-endian targets.
+            let offset = if cfg!(target_endian = "little") { i * 8 } else { (3 - i) * 8 };
+            let mut j = 0;
+            while j < 8 {
+                bytes[offset + j] = limb[j];
+                j += 1;
+            }
+            i += 1;
+        }
+        Self(bytes)
+    }
+
     /// Converts a [`U256`] reference to a [`U256`].
     #[inline]
     #[cfg(target_endian = "little")]
@@ -670,6 +2462,26 @@ impl EvmWord {
         self.0
     }
 
+    /// Writes the low `out.len()` bytes of the value into `out`, in big-endian byte order.
+    ///
+    /// This is the inverse of [`from_be_slice`](Self::from_be_slice) for values that fit in
+    /// `out`, and writes the same bytes as the end of [`to_be_bytes`](Self::to_be_bytes).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is longer than 32 bytes.
+    #[inline]
+    pub fn copy_be_into(&self, out: &mut [u8]) {
+        let len = out.len();
+        assert!(len <= 32, "slice of {len} bytes is longer than a word");
+        #[cfg(target_endian = "little")]
+        for (dst, &src) in out.iter_mut().zip(self.0[..len].iter().rev()) {
+            *dst = src;
+        }
+        #[cfg(target_endian = "big")]
+        out.copy_from_slice(&self.0[32 - len..]);
+    }
+
     /// Converts `self` to big endian from the target's endianness.
     #[inline]
     pub fn to_be(self) -> Self {
@@ -709,6 +2521,23 @@ impl EvmWord {
         unsafe { &mut *(self as *mut Self as *mut U256) }
     }
 
+    /// Calls `f` with this value as a mutable [`U256`], writing back any changes.
+    ///
+    /// This is a no-op cast on little-endian systems, and a conversion in both directions on
+    /// big-endian systems.
+    #[inline]
+    pub fn with_u256_mut<R>(&mut self, f: impl FnOnce(&mut U256) -> R) -> R {
+        #[cfg(target_endian = "little")]
+        return f(self.as_u256_mut());
+        #[cfg(target_endian = "big")]
+        {
+            let mut value = self.to_u256();
+            let r = f(&mut value);
+            *self = Self::from_u256(value);
+            r
+        }
+    }
+
     /// Converts this value to a [`U256`]. This is a simple copy on little-endian systems.
     #[inline]
     pub const fn to_u256(&self) -> U256 {
@@ -728,10 +2557,185 @@ impl EvmWord {
     }
 
     /// Converts this value to an [`Address`].
+    ///
+    /// The high 12 bytes are ignored. See [`to_address_checked`](Self::to_address_checked) for a
+    /// strict version.
     #[inline]
     pub fn to_address(self) -> Address {
         Address::from_word(self.to_be_bytes().into())
     }
+
+    /// Converts this value to an [`Address`], returning `None` if any of the high 12 bytes are
+    /// non-zero.
+    #[inline]
+    pub fn to_address_checked(self) -> Option<Address> {
+        let bytes = self.to_be_bytes();
+        if bytes[..12].iter().any(|&b| b != 0) {
+            return None;
+        }
+        Some(Address::from_slice(&bytes[12..]))
+    }
+
+    /// Creates a new value with a function selector in the most significant 4 bytes, and the
+    /// rest zero.
+    ///
+    /// This is the word that `CALLDATALOAD` reads at offset 0 from calldata that only contains the
+    /// selector.
+    #[inline]
+    pub fn from_selector(selector: [u8; 4]) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[..4].copy_from_slice(&selector);
+        Self::from_be_bytes(bytes)
+    }
+
+    /// Returns the most significant 4 bytes of this value, where `CALLDATALOAD` at offset 0 reads
+    /// the function selector.
+    #[inline]
+    pub fn selector(&self) -> [u8; 4] {
+        let bytes = self.to_be_bytes();
+        [bytes[0], bytes[1], bytes[2], bytes[3]]
+    }
+
+    /// Computes the Keccak-256 hash of the concatenated big-endian representations of `inputs`.
+    ///
+    /// This is the `KECCAK256` of the words stored consecutively in memory. For example, the
+    /// storage slot of `key` in a Solidity mapping at `slot` is `keccak(&[key, slot])`.
+    pub fn keccak(inputs: &[Self]) -> Self {
+        let mut hasher = revm_primitives::alloy_primitives::Keccak256::new();
+        for input in inputs {
+            hasher.update(input.to_be_bytes());
+        }
+        Self::from_be_bytes(hasher.finalize().0)
+    }
+
+    /// Converts this value to a `usize`, saturating at `usize::MAX`.
+    ///
+    /// This matches how revm converts memory offsets and lengths: oversized values are clamped,
+    /// and then fail the gas or memory limit checks.
+    #[inline]
+    pub fn as_usize_saturated(&self) -> usize {
+        self.to_u256().saturating_to()
+    }
+
+    /// Parses a string in the given radix, without a prefix.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseError> {
+        U256::from_str_radix(s, radix.into()).map(Self::from_u256)
+    }
+
+    /// Computes `(a + b) % m` with the semantics of the `ADDMOD` opcode.
+    ///
+    /// The intermediate sum is not truncated to 256 bits. Returns zero if `m` is zero.
+    #[inline]
+    pub fn addmod(a: Self, b: Self, m: Self) -> Self {
+        a.into_u256().add_mod(b.into_u256(), m.into_u256()).into()
+    }
+
+    /// Computes `(a * b) % m` with the semantics of the `MULMOD` opcode.
+    ///
+    /// The intermediate product is not truncated to 256 bits. Returns zero if `m` is zero.
+    #[inline]
+    pub fn mulmod(a: Self, b: Self, m: Self) -> Self {
+        a.into_u256().mul_mod(b.into_u256(), m.into_u256()).into()
+    }
+
+    /// Computes `a / b` with the semantics of the `DIV` opcode.
+    ///
+    /// Returns zero if `b` is zero.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn div(a: Self, b: Self) -> Self {
+        a.into_u256().checked_div(b.into_u256()).unwrap_or_default().into()
+    }
+
+    /// Computes `a % b` with the semantics of the `MOD` opcode.
+    ///
+    /// Returns zero if `b` is zero.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn rem(a: Self, b: Self) -> Self {
+        a.into_u256().checked_rem(b.into_u256()).unwrap_or_default().into()
+    }
+
+    /// Computes `a / b` as two's complement signed integers, with the semantics of the `SDIV`
+    /// opcode.
+    ///
+    /// The quotient is rounded towards zero. Returns zero if `b` is zero, and `i256::MIN` for
+    /// `i256::MIN / -1`, which overflows.
+    #[inline]
+    pub fn sdiv(a: Self, b: Self) -> Self {
+        let (a, b) = (a.into_u256(), b.into_u256());
+        if b.is_zero() {
+            return Self::ZERO;
+        }
+        // `abs(i256::MIN)` is `2**255` as an unsigned value, so the overflow wraps back to `MIN`.
+        let q = abs(a) / abs(b);
+        if a.bit(255) != b.bit(255) { q.wrapping_neg() } else { q }.into()
+    }
+
+    /// Computes `a % b` as two's complement signed integers, with the semantics of the `SMOD`
+    /// opcode.
+    ///
+    /// The result has the sign of `a`. Returns zero if `b` is zero.
+    #[inline]
+    pub fn smod(a: Self, b: Self) -> Self {
+        let (a, b) = (a.into_u256(), b.into_u256());
+        if b.is_zero() {
+            return Self::ZERO;
+        }
+        let r = abs(a) % abs(b);
+        if a.bit(255) { r.wrapping_neg() } else { r }.into()
+    }
+
+    /// Computes `self ** exp` modulo `2**256`, with the semantics of the `EXP` opcode.
+    ///
+    /// `0 ** 0` is `1`.
+    #[inline]
+    pub fn pow(self, exp: Self) -> Self {
+        self.into_u256().wrapping_pow(exp.into_u256()).into()
+    }
+
+    /// Sign-extends this value from the byte at `byte_index`, with the semantics of the
+    /// `SIGNEXTEND` opcode.
+    ///
+    /// Byte `0` is the least significant byte. If `byte_index` is 31 or more, the value is
+    /// returned unchanged, otherwise all the bits above bit `8 * byte_index + 7` are set to that
+    /// bit.
+    #[inline]
+    pub fn signextend(self, byte_index: Self) -> Self {
+        let byte_index = byte_index.into_u256();
+        if byte_index >= U256::from(31) {
+            return self;
+        }
+        let bit = byte_index.as_limbs()[0] as usize * 8 + 7;
+        let mask = (U256::from(1) << bit) - U256::from(1);
+        let x = self.into_u256();
+        if x.bit(bit) { x | !mask } else { x & mask }.into()
+    }
+
+    /// Compares this value to `other` as two's complement signed integers, with the semantics of
+    /// the `SLT` and `SGT` opcodes.
+    #[inline]
+    pub fn signed_cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.to_u256(), other.to_u256());
+        match (a.bit(255), b.bit(255)) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            // Values with the same sign compare like their unsigned representations.
+            _ => a.cmp(&b),
+        }
+    }
+}
+
+/// Returns the absolute value of a two's complement signed integer, as an unsigned value.
+#[inline]
+fn abs(x: U256) -> U256 {
+    if x.bit(255) {
+        x.wrapping_neg()
+    } else {
+        x
+    }
 }
 
 /// Logic for handling the `resume_at` field.
@@ -773,6 +2777,68 @@ pub mod private {
 mod tests {
     use super::*;
 
+    #[test]
+    fn stack() {
+        let stack = EvmStack::new();
+        assert!(stack.as_slice().iter().all(|word| *word == EvmWord::ZERO));
+        assert!(stack.as_bytes().iter().all(|&byte| byte == 0));
+        assert_eq!(stack.capacity_bytes(), stack.as_bytes().len());
+        assert_eq!(stack.capacity_words(), stack.as_slice().len());
+
+        let mut buf = EvmStack::new_heap();
+        let stack = EvmStack::from_mut_vec(&mut buf);
+        stack.as_mut_slice()[1] = EvmWord::from(1u64);
+        assert_eq!(buf[..2], [EvmWord::ZERO, EvmWord::from(1u64)]);
+
+        let mut uninit = EvmStack::new_uninit();
+        unsafe { (*uninit.as_mut_ptr()).0[0].write(EvmWord::from(2u64)) };
+        assert_eq!(unsafe { EvmStack::assume_init_slice(&uninit, 1) }, [EvmWord::from(2u64)]);
+
+        let mut interpreter_stack = revm_interpreter::Stack::new();
+        interpreter_stack.push(U256::from(3)).unwrap();
+        let (stack, stack_len) = EvmStack::from_interpreter_stack(&mut interpreter_stack);
+        assert_eq!(*stack_len, 1);
+        assert_eq!(stack.as_slice()[0], EvmWord::from(3u64));
+        assert!(stack.as_slice()[1..].iter().all(|word| *word == EvmWord::ZERO));
+    }
+
+    #[test]
+    fn peek() {
+        let mut stack = EvmStack::new();
+        let mut len = 0;
+        assert_eq!(stack.peek(len, 0), None);
+
+        stack.set_from_top(&mut len, &[1u64, 2, 3].map(EvmWord::from));
+        assert_eq!(stack.peek(len, 0), Some(&EvmWord::from(1u64)));
+        assert_eq!(stack.peek(len, 1), Some(&EvmWord::from(2u64)));
+        assert_eq!(stack.peek(len, len - 1), Some(&EvmWord::from(3u64)));
+        assert_eq!(stack.peek(len, len), None);
+        assert_eq!(stack.peek(len, usize::MAX), None);
+        assert_eq!(stack.peek(EvmStack::CAPACITY + 1, 0), None);
+    }
+
+    #[test]
+    fn set_from_top() {
+        let words = [1u64, 2, 3].map(EvmWord::from);
+        let mut stack = EvmStack::new();
+        let mut len = 69;
+        stack.set_from_top(&mut len, &words);
+        assert_eq!(len, 3);
+
+        // Same layout as pushing from the bottom up.
+        let mut interpreter_stack = revm_interpreter::Stack::new();
+        for word in words.iter().rev() {
+            interpreter_stack.push(word.to_u256()).unwrap();
+        }
+        let (expected, expected_len) = EvmStack::from_interpreter_stack(&mut interpreter_stack);
+        assert_eq!(*expected_len, len);
+        assert_eq!(stack.as_slice()[..len], expected.as_slice()[..len]);
+        assert_eq!(interpreter_stack.peek(0), Ok(U256::from(1)));
+
+        stack.set_from_top(&mut len, &[]);
+        assert_eq!(len, 0);
+    }
+
     #[test]
     fn conversions() {
         let mut word = EvmWord::ZERO;
@@ -781,6 +2847,846 @@ mod tests {
         assert_eq!(usize::try_from(&mut word), Ok(0));
     }
 
+    #[test]
+    #[cfg(feature = "allocator_api")]
+    fn stack_heap_in() {
+        let mut buf = EvmStack::new_heap_in(std::alloc::System);
+        assert_eq!(buf.len(), EvmStack::CAPACITY);
+        let stack = EvmStack::from_mut_vec(&mut buf);
+        stack.as_mut_slice()[0] = EvmWord::from(1u64);
+        assert_eq!(EvmStack::from_vec(&buf).as_slice()[..2], [EvmWord::from(1u64), EvmWord::ZERO]);
+    }
+
+    #[test]
+    fn selector() {
+        let selector = [0xa9, 0x05, 0x9c, 0xbb];
+        let word = EvmWord::from_selector(selector);
+        assert_eq!(word.selector(), selector);
+        assert_eq!(word.to_u256(), U256::from(0xa9059cbbu32) << 224);
+
+        let mut calldata = [0u8; 32];
+        calldata[..4].copy_from_slice(&selector);
+        calldata[4] = 1;
+        assert_eq!(EvmWord::from_be_bytes(calldata).selector(), selector);
+    }
+
+    #[test]
+    fn endianness() {
+        let mut be = [0u8; 32];
+        for (i, byte) in be.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut le = be;
+        le.reverse();
+        let ne = if cfg!(target_endian = "little") { le } else { be };
+        let value = U256::from_be_bytes(be);
+
+        let word = EvmWord::from_be_bytes(be);
+        assert_eq!(word, EvmWord::from_le_bytes(le));
+        assert_eq!(word, EvmWord::from_ne_bytes(ne));
+        assert_eq!(word, EvmWord::from_u256(value));
+        assert_eq!(word, EvmWord::from(value));
+        assert_eq!(word.to_be_bytes(), be);
+        assert_eq!(word.to_le_bytes(), le);
+        assert_eq!(word.to_ne_bytes(), ne);
+        assert_eq!(word.to_be().to_ne_bytes(), be);
+        assert_eq!(word.to_le().to_ne_bytes(), le);
+        assert_eq!(EvmWord::from_be(EvmWord::from_ne_bytes(be)), word);
+        assert_eq!(EvmWord::from_le(EvmWord::from_ne_bytes(le)), word);
+        assert_eq!(word.swap_bytes().swap_bytes(), word);
+        assert_eq!(word.to_u256(), value);
+        assert_eq!(word.into_u256(), value);
+        assert_eq!(word.to_address(), Address::from_slice(&be[12..]));
+
+        let mut word = word;
+        word.with_u256_mut(|value| *value += U256::from(1));
+        assert_eq!(word.to_u256(), value + U256::from(1));
+
+        #[cfg(target_endian = "little")]
+        {
+            assert_eq!(*word.as_u256(), value + U256::from(1));
+            assert_eq!(*EvmWord::from_u256_ref(&value), EvmWord::from_be_bytes(be));
+        }
+    }
+
+    #[test]
+    fn address_conversions() {
+        let address = Address::repeat_byte(0x69);
+        let clean = EvmWord::from_be_bytes(address.into_word().0);
+        assert_eq!(clean.to_address(), address);
+        assert_eq!(clean.to_address_checked(), Some(address));
+        assert_eq!(Address::try_from(clean), Ok(address));
+        assert_eq!(Address::try_from(&clean), Ok(address));
+
+        let mut bytes = address.into_word().0;
+        bytes[0] = 1;
+        let dirty = EvmWord::from_be_bytes(bytes);
+        assert_eq!(dirty.to_address(), address);
+        assert_eq!(dirty.to_address_checked(), None);
+        assert_eq!(Address::try_from(dirty), Err(()));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_str() {
+        let parse = |s: &str| s.parse::<EvmWord>().map(EvmWord::into_u256);
+        assert_eq!(parse("0"), Ok(U256::ZERO));
+        assert_eq!(parse("0xdeadbeef"), Ok(U256::from(0xdeadbeef_u64)));
+        assert_eq!(parse("0XDEADBEEF"), Ok(U256::from(0xdeadbeef_u64)));
+        assert_eq!(parse("1234567890"), Ok(U256::from(1234567890)));
+        assert_eq!(parse(&U256::MAX.to_string()), Ok(U256::MAX));
+        assert_eq!(parse(&format!("{:#x}", U256::MAX)), Ok(U256::MAX));
+
+        // Overflow.
+        assert!(parse(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        )
+        .is_err());
+        assert!(parse(&format!("0x1{}", "0".repeat(64))).is_err());
+
+        // Malformed.
+        assert!(parse("0xdeadbeeg").is_err());
+        assert!(parse("12a").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse(" 1").is_err());
+
+        let parse_radix =
+            |s: &str, radix| EvmWord::from_str_radix(s, radix).map(EvmWord::into_u256);
+        assert_eq!(parse_radix("ff", 16), Ok(U256::from(0xff)));
+        assert_eq!(parse_radix("777", 8), Ok(U256::from(0o777)));
+        assert!(parse_radix("0xff", 16).is_err());
+        assert!(parse_radix("2", 2).is_err());
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        let w = |x: U256| EvmWord::from(x);
+        let max = U256::MAX;
+
+        // `max + max` overflows 256 bits.
+        assert_eq!(EvmWord::addmod(w(max), w(max), w(max)), EvmWord::ZERO);
+        assert_eq!(EvmWord::addmod(w(max), w(max), w(max - U256::from(1))), w(U256::from(2)));
+        assert_eq!(EvmWord::addmod(w(max), w(U256::from(1)), w(U256::from(10))), w(U256::from(6)));
+        assert_eq!(EvmWord::addmod(w(max), w(max), EvmWord::ZERO), EvmWord::ZERO);
+
+        // `max * max` overflows 256 bits.
+        assert_eq!(EvmWord::mulmod(w(max), w(max), w(max)), EvmWord::ZERO);
+        assert_eq!(EvmWord::mulmod(w(max), w(max), w(max - U256::from(1))), w(U256::from(1)));
+        assert_eq!(EvmWord::mulmod(w(max), w(max), w(U256::from(12))), w(U256::from(9)));
+        assert_eq!(EvmWord::mulmod(w(max), w(max), EvmWord::ZERO), EvmWord::ZERO);
+    }
+
+    #[test]
+    fn division() {
+        use revm_interpreter::instructions::i256::{i256_div, i256_mod};
+
+        let w = |x: U256| EvmWord::from(x);
+        let n = |x: u64| EvmWord::from(x);
+        let min = U256::from(1) << 255;
+        let max = min - U256::from(1);
+        let neg = |x: u64| w(U256::from(x).wrapping_neg());
+
+        // Division by zero is zero.
+        for x in [n(0), n(1), w(U256::MAX), w(min)] {
+            assert_eq!(EvmWord::div(x, n(0)), n(0));
+            assert_eq!(EvmWord::rem(x, n(0)), n(0));
+            assert_eq!(EvmWord::sdiv(x, n(0)), n(0));
+            assert_eq!(EvmWord::smod(x, n(0)), n(0));
+        }
+        // Zero divided by anything is zero.
+        for x in [n(1), w(U256::MAX), w(min)] {
+            assert_eq!(EvmWord::div(n(0), x), n(0));
+            assert_eq!(EvmWord::rem(n(0), x), n(0));
+            assert_eq!(EvmWord::sdiv(n(0), x), n(0));
+            assert_eq!(EvmWord::smod(n(0), x), n(0));
+        }
+
+        assert_eq!(EvmWord::div(n(7), n(2)), n(3));
+        assert_eq!(EvmWord::rem(n(7), n(2)), n(1));
+        assert_eq!(EvmWord::div(w(U256::MAX), n(1)), w(U256::MAX));
+        assert_eq!(EvmWord::div(neg(7), n(2)), w(U256::MAX / U256::from(2) - U256::from(3)));
+
+        // `i256::MIN / -1` overflows to `i256::MIN`, and the remainder is zero.
+        assert_eq!(EvmWord::sdiv(w(min), neg(1)), w(min));
+        assert_eq!(EvmWord::smod(w(min), neg(1)), n(0));
+        assert_eq!(EvmWord::sdiv(w(min), n(1)), w(min));
+        assert_eq!(EvmWord::sdiv(w(min), w(min)), n(1));
+        assert_eq!(EvmWord::sdiv(w(max), w(min)), n(0));
+
+        // Signed division rounds towards zero, and the remainder has the sign of the dividend.
+        assert_eq!(EvmWord::sdiv(neg(7), n(2)), neg(3));
+        assert_eq!(EvmWord::sdiv(n(7), neg(2)), neg(3));
+        assert_eq!(EvmWord::sdiv(neg(7), neg(2)), n(3));
+        assert_eq!(EvmWord::smod(neg(7), n(2)), neg(1));
+        assert_eq!(EvmWord::smod(n(7), neg(2)), n(1));
+        assert_eq!(EvmWord::smod(neg(7), neg(2)), neg(1));
+
+        let (one, seven) = (U256::from(1), U256::from(7));
+        let values = [U256::ZERO, one, seven, U256::MAX, U256::MAX - seven, min, min + one, max];
+        for a in values {
+            for b in values {
+                let (wa, wb) = (w(a), w(b));
+                assert_eq!(EvmWord::div(wa, wb), w(a.checked_div(b).unwrap_or_default()));
+                assert_eq!(EvmWord::rem(wa, wb), w(a.checked_rem(b).unwrap_or_default()));
+                assert_eq!(EvmWord::sdiv(wa, wb), w(i256_div(a, b)), "{a:#x} {b:#x}");
+                assert_eq!(EvmWord::smod(wa, wb), w(i256_mod(a, b)), "{a:#x} {b:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn pow() {
+        let w = |x: U256| EvmWord::from(x);
+        let n = |x: u64| EvmWord::from(x);
+
+        assert_eq!(n(0).pow(n(0)), n(1));
+        assert_eq!(n(0).pow(n(1)), n(0));
+        assert_eq!(n(0).pow(w(U256::MAX)), n(0));
+        assert_eq!(n(1).pow(w(U256::MAX)), n(1));
+        assert_eq!(n(3).pow(n(5)), n(243));
+        assert_eq!(n(2).pow(n(255)), w(U256::from(1) << 255));
+        assert_eq!(n(2).pow(n(256)), n(0));
+        assert_eq!(n(2).pow(w(U256::MAX)), n(0));
+
+        // Odd bases never wrap to zero: `(-1) ** e` is `1` for even `e` and `-1` for odd `e`.
+        assert_eq!(w(U256::MAX).pow(w(U256::MAX - U256::from(1))), n(1));
+        assert_eq!(w(U256::MAX).pow(w(U256::MAX)), w(U256::MAX));
+        // The multiplicative order of `3` modulo `2**256` is `2**254`.
+        assert_eq!(n(3).pow(w(U256::from(1) << 255)), n(1));
+        assert_eq!(n(3).pow(w(U256::from(1) << 254)), n(1));
+        assert_ne!(n(3).pow(w(U256::from(1) << 253)), n(1));
+    }
+
+    #[test]
+    fn from_limbs() {
+        const LIMBS: [u64; 4] = [1, 0x0203_0405_0607_0809, 0, u64::MAX];
+        const WORD: EvmWord = EvmWord::from_limbs(LIMBS);
+        assert_eq!(WORD.to_u256(), U256::from_limbs(LIMBS));
+        assert_eq!(WORD.to_be_bytes()[..8], [0xff; 8]);
+        assert_eq!(WORD.to_be_bytes()[31], 1);
+
+        for limbs in [[0; 4], [u64::MAX; 4], [0, 0, 0, 1 << 63], [0xdead, 0xbeef, 0xcafe, 0xf00d]] {
+            assert_eq!(EvmWord::from_limbs(limbs).to_u256(), U256::from_limbs(limbs));
+        }
+    }
+
+    #[test]
+    fn keccak() {
+        let w = |x: U256| EvmWord::from(x);
+        assert_eq!(EvmWord::keccak(&[]).to_be_bytes(), revm_primitives::KECCAK_EMPTY.0);
+        // The first element of a dynamic array at slot 0.
+        assert_eq!(
+            EvmWord::keccak(&[EvmWord::ZERO]),
+            EvmWord::from_be_hex(
+                "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+            )
+        );
+        // `mapping(address => uint256) balances` at slot 3, like in `ERC20`.
+        let holder = Address::with_last_byte(0x69);
+        let key = w(U256::from_be_slice(holder.as_slice()));
+        let slot = EvmWord::keccak(&[key, EvmWord::from_u64(3)]);
+        let mut buf = [0u8; 64];
+        buf[12..32].copy_from_slice(holder.as_slice());
+        buf[63] = 3;
+        assert_eq!(slot.to_be_bytes(), revm_primitives::keccak256(buf).0);
+        assert_eq!(
+            EvmWord::keccak(&[EvmWord::ZERO, EvmWord::ZERO]),
+            EvmWord::from_be_hex(
+                "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+            )
+        );
+    }
+
+    #[test]
+    fn consts() {
+        const X: EvmWord =
+            EvmWord::from_be_hex("0x0102030405060708090a0b0c0d0e0f10111213141516171819");
+        assert_eq!(X, "0x0102030405060708090a0b0c0d0e0f10111213141516171819".parse().unwrap());
+        assert_eq!(EvmWord::from_be_hex("0"), EvmWord::ZERO);
+        assert_eq!(EvmWord::from_be_hex("0XaBcD"), EvmWord::from_u64(0xabcd));
+        assert_eq!(EvmWord::from_be_hex(&"f".repeat(64)), EvmWord::MAX);
+        let top = alloc::format!("0x1{}", "0".repeat(63));
+        assert_eq!(EvmWord::from_be_hex(&top), EvmWord::from(U256::from(1) << 252));
+        assert_eq!(EvmWord::MAX.to_u256(), U256::MAX);
+        assert_eq!(EvmWord::from_u64(u64::MAX).to_u256(), U256::from(u64::MAX));
+    }
+
+    #[test]
+    #[should_panic = "expected 1 to 64 hexadecimal digits"]
+    fn from_be_hex_too_long() {
+        EvmWord::from_be_hex(&"0".repeat(65));
+    }
+
+    #[test]
+    #[should_panic = "invalid hexadecimal digit"]
+    fn from_be_hex_invalid() {
+        EvmWord::from_be_hex("0x0g");
+    }
+
+    #[test]
+    fn ord_and_hash() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // Sorted numerically. The byte order of the native representation is not.
+        let values = [
+            U256::ZERO,
+            U256::from(1),
+            U256::from(0x100),
+            U256::from(u64::MAX),
+            U256::from(1) << 64,
+            U256::from(1) << 255,
+            U256::MAX,
+        ];
+        let map: alloc::collections::BTreeMap<EvmWord, usize> =
+            values.iter().rev().enumerate().map(|(i, &x)| (EvmWord::from(x), i)).collect();
+        let keys: Vec<U256> = map.keys().map(EvmWord::to_u256).collect();
+        assert_eq!(keys, values);
+        for a in values {
+            for b in values {
+                assert_eq!(EvmWord::from(a).cmp(&EvmWord::from(b)), a.cmp(&b));
+            }
+        }
+
+        let state = RandomState::new();
+        for x in values {
+            assert_eq!(state.hash_one(EvmWord::from(x)), state.hash_one(x));
+        }
+    }
+
+    #[test]
+    fn signextend() {
+        let w = |x: U256| EvmWord::from(x);
+        let x = w(U256::from(0x80ff_u64));
+
+        // Byte 0 is `0xff`, byte 1 is `0x80`.
+        assert_eq!(x.signextend(EvmWord::ZERO), w(U256::MAX));
+        assert_eq!(x.signextend(w(U256::from(1))), w(U256::MAX << 16 | U256::from(0x80ff)));
+        assert_eq!(x.signextend(w(U256::from(2))), x);
+        assert_eq!(w(U256::from(0x7f)).signextend(EvmWord::ZERO), w(U256::from(0x7f)));
+        assert_eq!(w(U256::MAX << 8).signextend(EvmWord::ZERO), EvmWord::ZERO);
+
+        // The sign bit is the most significant bit of byte 30.
+        let x = w(U256::from(1) << 247);
+        assert_eq!(x.signextend(w(U256::from(30))), w(U256::MAX << 247));
+        assert_eq!(x.signextend(w(U256::from(31))), x);
+        assert_eq!(x.signextend(w(U256::from(32))), x);
+        assert_eq!(x.signextend(w(U256::from(u64::MAX) + U256::from(30))), x);
+        assert_eq!(x.signextend(w(U256::MAX)), x);
+    }
+
+    #[test]
+    fn signed_cmp() {
+        use revm_interpreter::instructions::i256::i256_cmp;
+
+        let w = |x: U256| EvmWord::from(x);
+        let min = U256::from(1) << 255;
+        let max = min - U256::from(1);
+        let (zero, one, two) = (U256::ZERO, U256::from(1), U256::from(2));
+        let neg1 = U256::MAX;
+
+        assert_eq!(w(neg1).signed_cmp(&w(zero)), Ordering::Less);
+        assert_eq!(w(zero).signed_cmp(&w(neg1)), Ordering::Greater);
+        assert_eq!(w(min).signed_cmp(&w(max)), Ordering::Less);
+        assert_eq!(w(max).signed_cmp(&w(min)), Ordering::Greater);
+        assert_eq!(w(one).signed_cmp(&w(two)), Ordering::Less);
+        assert_eq!(w(two).signed_cmp(&w(one)), Ordering::Greater);
+        assert_eq!(w(min).signed_cmp(&w(min)), Ordering::Equal);
+
+        // `SLT` and `SGT` are `i256_cmp(a, b) == Less` and `Greater`.
+        let values = [zero, one, two, neg1, neg1 - one, min, min + one, max, max - one];
+        for a in values {
+            for b in values {
+                assert_eq!(w(a).signed_cmp(&w(b)), i256_cmp(&a, &b), "{a:#x} {b:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn gas() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.gas_limit(), 100);
+        assert_eq!(ecx.gas_remaining(), 100);
+
+        assert!(ecx.spend_gas(60));
+        assert_eq!(ecx.gas_remaining(), 40);
+        assert!(!ecx.spend_gas(41));
+        assert_eq!(ecx.gas_remaining(), 40);
+        assert!(ecx.spend_gas(40));
+        assert_eq!(ecx.gas_remaining(), 0);
+        assert_eq!(ecx.gas_limit(), 100);
+
+        assert_eq!(ecx.refunded(), 0);
+        ecx.gas.record_refund(4800);
+        ecx.gas.record_refund(-2800);
+        assert_eq!(ecx.refunded(), 2000);
+        ecx.set_refunded(-100);
+        assert_eq!(ecx.refunded(), -100);
+        assert_eq!(ecx.gas.refunded(), -100);
+
+        ecx.disable_gas();
+        assert_eq!(ecx.gas_mode, GasMode::Disabled);
+        assert_eq!(ecx.gas_limit(), u64::MAX);
+        assert!(ecx.spend_gas(u64::MAX));
+        assert!(ecx.spend_gas(1));
+        assert_eq!(ecx.gas_remaining(), u64::MAX);
+    }
+
+    #[test]
+    fn to_owned_state() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.stack.push(U256::from(1)).unwrap();
+        interpreter.stack.push(U256::from(2)).unwrap();
+        interpreter.shared_memory.resize(32);
+        interpreter.shared_memory.set(31, &[0x69]);
+        interpreter.return_data_buffer = Bytes::from_static(&[1, 2, 3]);
+        let state = {
+            let (mut ecx, stack, stack_len) =
+                EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+            ecx.spend_gas(10);
+            ecx.resume_at = 2;
+            ecx.to_owned_state(stack, *stack_len)
+        };
+        assert_eq!(state.gas.remaining(), 90);
+        assert_eq!(state.stack, [EvmWord::from(1u64), EvmWord::from(2u64)]);
+        assert_eq!(state.memory.len(), 32);
+        assert_eq!(state.memory[31], 0x69);
+        assert!(!state.is_static);
+        assert_eq!(state.resume_at, 2);
+        assert_eq!(state.return_data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn be_slice() {
+        assert_eq!(EvmWord::from_be_slice(&[]), EvmWord::ZERO);
+        assert_eq!(EvmWord::from_be_slice(&[0x12, 0x34]), EvmWord::from(0x1234u64));
+        assert_eq!(EvmWord::from_be_slice(&[0xff; 32]), EvmWord::MAX);
+        assert_eq!(EvmWord::try_from(&[0x69][..]), Ok(EvmWord::from(0x69u64)));
+        assert_eq!(EvmWord::try_from(&[0; 33][..]), Err(()));
+
+        let word = EvmWord::from(0x1234u64);
+        let mut out = [0xaa; 3];
+        word.copy_be_into(&mut out);
+        assert_eq!(out, [0x00, 0x12, 0x34]);
+        word.copy_be_into(&mut out[..1]);
+        assert_eq!(out, [0x34, 0x12, 0x34]);
+        word.copy_be_into(&mut []);
+        let mut out = [0; 32];
+        EvmWord::MAX.copy_be_into(&mut out);
+        assert_eq!(out, [0xff; 32]);
+    }
+
+    #[test]
+    #[should_panic = "slice of 33 bytes does not fit in a word"]
+    fn from_be_slice_too_long() {
+        EvmWord::from_be_slice(&[0; 33]);
+    }
+
+    #[test]
+    #[should_panic = "slice of 33 bytes is longer than a word"]
+    fn copy_be_into_too_long() {
+        EvmWord::ZERO.copy_be_into(&mut [0; 33]);
+    }
+
+    #[test]
+    fn as_usize_saturated() {
+        assert_eq!(EvmWord::from(69u64).as_usize_saturated(), 69);
+        assert_eq!(EvmWord::from(usize::MAX).as_usize_saturated(), usize::MAX);
+        let above = U256::from(usize::MAX) + U256::from(1);
+        assert_eq!(EvmWord::from(above).as_usize_saturated(), usize::MAX);
+        assert_eq!(EvmWord::from(U256::MAX).as_usize_saturated(), usize::MAX);
+        assert_eq!(usize::try_from(EvmWord::from(above)), Err(()));
+    }
+
+    #[test]
+    fn dump() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.stack.push(U256::from(1)).unwrap();
+        interpreter.stack.push(U256::from(0x69)).unwrap();
+        interpreter.shared_memory.resize(64);
+        interpreter.shared_memory.set(1, &[0xab]);
+        let (ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        let dump = ecx.dump(stack, *stack_len, 2);
+        assert!(dump.contains("gas: remaining=100 limit=100 refunded=0\n"), "{dump}");
+        assert!(dump.contains(&format!("     1: {:#066x}\n", 0x69)), "{dump}");
+        assert!(dump.contains(&format!("     0: {:#066x}\n", 1)), "{dump}");
+        assert!(dump.contains("memory: len=64\n  00000000: 00 ab\n"), "{dump}");
+        assert!(dump.contains("next_action: None\n"), "{dump}");
+    }
+
+    #[test]
+    fn with_fresh_memory() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.shared_memory.resize(32);
+        interpreter.shared_memory.set(0, &[0x69]);
+        let (len, memory) =
+            EvmContext::with_fresh_memory(&mut interpreter, &mut host, |ecx, _, _| {
+                let len = ecx.memory.len();
+                ecx.memory.resize(64);
+                ecx.memory.set(63, &[0x42]);
+                len
+            });
+        assert_eq!(len, 0);
+        assert_eq!(memory.len(), 64);
+        assert_eq!(memory.context_memory()[63], 0x42);
+        assert_eq!(interpreter.shared_memory.len(), 32);
+        assert_eq!(interpreter.shared_memory.context_memory()[0], 0x69);
+
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            EvmContext::with_fresh_memory(&mut interpreter, &mut host, |ecx, _, _| {
+                ecx.memory.resize(64);
+                panic!("unwind");
+            })
+        }));
+        assert!(r.is_err());
+        assert_eq!(interpreter.shared_memory.len(), 32);
+        assert_eq!(interpreter.shared_memory.context_memory()[0], 0x69);
+    }
+
+    #[test]
+    fn memory_read() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.shared_memory.resize(64);
+        interpreter.shared_memory.set(32, &[0xAA; 32]);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+
+        assert_eq!(ecx.memory_word(0), EvmWord::ZERO);
+        assert_eq!(ecx.memory_word(32), EvmWord::from_be_bytes([0xAA; 32]));
+        assert!(matches!(ecx.memory_slice(16, 32), Cow::Borrowed(_)));
+
+        // Across the end of memory.
+        let mut expected = [0u8; 32];
+        expected[..16].fill(0xAA);
+        assert_eq!(ecx.memory_word(48), EvmWord::from_be_bytes(expected));
+        assert_eq!(ecx.memory_slice(60, 8)[..], [0xAA, 0xAA, 0xAA, 0xAA, 0, 0, 0, 0]);
+
+        // Past the end of memory.
+        assert_eq!(ecx.memory_word(1000), EvmWord::ZERO);
+        assert_eq!(ecx.memory_slice(usize::MAX, 4)[..], [0; 4]);
+        assert_eq!(ecx.memory.len(), 64);
+    }
+
+    #[test]
+    fn transient_storage() {
+        let mut host = RecordingHost::new(revm_interpreter::DummyHost::new(Env::default()));
+        let contract =
+            Contract { target_address: Address::with_last_byte(0x69), ..Default::default() };
+        let mut interpreter = Interpreter::new(contract, u64::MAX, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        let (key, value) = (U256::from(1), U256::from(0x42));
+        assert_eq!(ecx.get_transient(key), U256::ZERO);
+        ecx.set_transient(key, value);
+        assert_eq!(ecx.get_transient(key), value);
+        assert_eq!(ecx.get_transient(U256::from(2)), U256::ZERO);
+
+        // Stored in the host, under the contract's address.
+        assert_eq!(host.inner().transient_storage.get(&key), Some(&value));
+        let address = Address::with_last_byte(0x69);
+        assert!(host.tape().calls.iter().all(|call| matches!(
+            call,
+            HostCall::Tload { address: a, .. } | HostCall::Tstore { address: a, .. } if *a == address
+        )));
+    }
+
+    #[test]
+    fn code_hash() {
+        let code = Bytes::from_static(&[0x60, 0x01, 0x00]);
+        let bytecode = revm_interpreter::analysis::to_analysed(revm_primitives::Bytecode::new_raw(
+            code.clone(),
+        ));
+        let mut host = revm_interpreter::DummyHost::default();
+
+        let contract = Contract { bytecode: bytecode.clone(), ..Default::default() };
+        let mut interpreter = Interpreter::new(contract, 100, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.code_hash(), revm_primitives::keccak256(&code));
+
+        let hash = B256::repeat_byte(1);
+        let contract = Contract { bytecode, hash: Some(hash), ..Default::default() };
+        let mut interpreter = Interpreter::new(contract, 100, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.code_hash(), hash);
+
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.code_hash(), revm_primitives::KECCAK_EMPTY);
+    }
+
+    #[test]
+    fn create2_address() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.create2_address(), None);
+
+        let caller = Address::with_last_byte(0x69);
+        let init_code = Bytes::from_static(&[0x00]);
+        let mut inputs = revm_interpreter::CreateInputs {
+            caller,
+            scheme: CreateScheme::Create2 { salt: U256::from(1) },
+            value: U256::ZERO,
+            init_code: init_code.clone(),
+            gas_limit: 100,
+        };
+        *ecx.next_action = InterpreterAction::Create { inputs: Box::new(inputs.clone()) };
+        let expected = caller.create2(U256::from(1).to_be_bytes::<32>(), keccak256(&init_code));
+        assert_eq!(ecx.create2_address(), Some(expected));
+
+        // Follows the pending action.
+        inputs.scheme = CreateScheme::Create;
+        *ecx.next_action = InterpreterAction::Create { inputs: Box::new(inputs) };
+        assert_eq!(ecx.create2_address(), None);
+        *ecx.next_action = InterpreterAction::None;
+        assert_eq!(ecx.create2_address(), None);
+    }
+
+    #[test]
+    fn memory_eq() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.shared_memory.resize(64);
+        interpreter.shared_memory.set(32, &[0xAA; 32]);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+
+        assert!(ecx.memory_eq(0, &[]));
+        assert!(ecx.memory_eq(0, &[0; 32]));
+        assert!(ecx.memory_eq(32, &[0xAA; 32]));
+        assert!(!ecx.memory_eq(31, &[0xAA; 32]));
+
+        // Across the end of memory, which reads as zero.
+        let mut expected = [0u8; 48];
+        expected[..16].fill(0xAA);
+        assert!(ecx.memory_eq(48, &expected));
+        expected[47] = 1;
+        assert!(!ecx.memory_eq(48, &expected));
+
+        // Past the end of memory.
+        assert!(ecx.memory_eq(1000, &[0; 32]));
+        assert!(!ecx.memory_eq(1000, &[1]));
+        assert!(ecx.memory_eq(usize::MAX, &[0; 4]));
+        assert_eq!(ecx.memory.len(), 64);
+    }
+
+    #[test]
+    fn memory_chunks() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.shared_memory.resize(70);
+        interpreter.shared_memory.set(64, &[0xAA; 6]);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+
+        let chunks = ecx.memory_chunks(32).collect::<Vec<_>>();
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), [32, 32, 6]);
+        assert_eq!(chunks[2], [0xAA; 6]);
+        assert_eq!(chunks.concat(), ecx.memory.context_memory());
+        assert_eq!(ecx.memory_chunks(100).count(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "host-ext-any")]
+    fn emitted_logs() {
+        use revm_interpreter::DummyHost;
+        use revm_primitives::Log;
+
+        let log = Log::new_unchecked(Address::with_last_byte(1), vec![], Bytes::from_static(b"a"));
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+
+        let mut host = RecordingHost::new(DummyHost::default());
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.emitted_logs::<DummyHost>().count(), 0);
+        ecx.host.log(log.clone());
+        assert!(ecx.emitted_logs::<DummyHost>().eq([&log]));
+
+        let mut host = DummyHost::default();
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        ecx.host.log(log);
+        assert_eq!(ecx.emitted_logs::<DummyHost>().count(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "host-ext-any")]
+    fn selfdestruct_target() {
+        use revm_interpreter::DummyHost;
+
+        let target = Address::with_last_byte(0x69);
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+
+        let mut host = RecordingHost::new(DummyHost::default());
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.selfdestruct_target::<DummyHost>(), None);
+        ecx.host.selfdestruct(Address::ZERO, target);
+        assert_eq!(ecx.selfdestruct_target::<DummyHost>(), Some(target));
+
+        let mut host = DummyHost::default();
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        ecx.host.selfdestruct(Address::ZERO, target);
+        assert_eq!(ecx.selfdestruct_target::<DummyHost>(), None);
+    }
+
+    #[test]
+    fn fn_info_check() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, true);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+
+        let mut info = FnInfo::default();
+        assert_eq!(info.check(&ecx), Ok(()));
+        info.static_context = Some(true);
+        assert_eq!(info.check(&ecx), Ok(()));
+        info.static_context = Some(false);
+        assert_eq!(info.check(&ecx), Err(CallCheckError::StaticContext { expected: false }));
+        info.static_context = None;
+
+        info.gas_mode = GasMode::Disabled;
+        assert_eq!(info.check(&ecx), Ok(()));
+        info.gas_mode = GasMode::ObserveOnly;
+        let expected = GasMode::ObserveOnly;
+        assert_eq!(info.check(&ecx), Err(CallCheckError::GasMode { expected }));
+        ecx.gas_mode = GasMode::ObserveOnly;
+        assert_eq!(info.check(&ecx), Ok(()));
+
+        assert_eq!(info.check_signature(FnSignature::Default), Ok(()));
+        let expected = FnSignature::Default;
+        let r = info.check_signature(FnSignature::NoGas);
+        assert_eq!(r, Err(CallCheckError::Signature { expected }));
+        info.signature = FnSignature::NoGas;
+        assert_eq!(info.check_signature(FnSignature::NoGas), Ok(()));
+    }
+
+    #[test]
+    fn reverted() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.reverted(), None);
+
+        for (result, expected) in [
+            (InstructionResult::Return, false),
+            (InstructionResult::Stop, false),
+            (InstructionResult::Revert, true),
+            (InstructionResult::OutOfGas, false),
+        ] {
+            *ecx.next_action = InterpreterAction::Return {
+                result: InterpreterResult { result, output: Bytes::new(), gas: Gas::new(0) },
+            };
+            assert_eq!(ecx.reverted(), Some(expected), "{result:?}");
+        }
+    }
+
+    #[test]
+    fn revert_reason() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.revert_reason(), None);
+
+        let output = Bytes::from_static(&[0x82, 0xb4, 0x29, 0x00]);
+        for (result, expected) in [
+            (InstructionResult::Return, None),
+            (
+                InstructionResult::Revert,
+                Some(RevertReason::Custom {
+                    selector: [0x82, 0xb4, 0x29, 0x00],
+                    data: Bytes::new(),
+                }),
+            ),
+        ] {
+            *ecx.next_action = InterpreterAction::Return {
+                result: InterpreterResult { result, output: output.clone(), gas: Gas::new(0) },
+            };
+            assert_eq!(ecx.revert_reason(), expected, "{result:?}");
+        }
+    }
+
+    #[test]
+    fn into_call_outcome() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 1000, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert!(ecx.gas.record_cost(100));
+        let output = Bytes::from_static(&[1, 2, 3]);
+        *ecx.next_action = InterpreterAction::Return {
+            result: InterpreterResult {
+                result: InstructionResult::Revert,
+                output: output.clone(),
+                gas: Gas::new(0),
+            },
+        };
+        let outcome = ecx.into_call_outcome(InstructionResult::Revert, 32..64);
+        assert_eq!(outcome.instruction_result(), &InstructionResult::Revert);
+        assert_eq!(outcome.output(), &output);
+        assert_eq!(outcome.gas().spent(), 100);
+        assert_eq!(outcome.memory_offset, 32..64);
+
+        let mut interpreter = Interpreter::new(Contract::default(), 1000, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        let outcome = ecx.into_call_outcome(InstructionResult::OutOfGas, 0..0);
+        assert!(outcome.output().is_empty());
+        assert_eq!(outcome.gas().remaining(), 0);
+    }
+
+    #[test]
+    fn is_suspended() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert!(!ecx.is_suspended());
+
+        // Completed or reverted.
+        for result in [InstructionResult::Stop, InstructionResult::Revert] {
+            *ecx.next_action = InterpreterAction::Return {
+                result: InterpreterResult { result, output: Bytes::new(), gas: Gas::new(0) },
+            };
+            ecx.resume_at = 1;
+            assert!(!ecx.is_suspended(), "{result:?}");
+        }
+
+        // Suspended on a create.
+        *ecx.next_action = InterpreterAction::Create {
+            inputs: Box::new(revm_interpreter::CreateInputs {
+                caller: Address::ZERO,
+                scheme: revm_primitives::CreateScheme::Create,
+                value: U256::ZERO,
+                init_code: Bytes::new(),
+                gas_limit: 0,
+            }),
+        };
+        ecx.resume_at = 0;
+        assert!(!ecx.is_suspended());
+        ecx.resume_at = 1;
+        assert!(ecx.is_suspended());
+    }
+
+    #[test]
+    fn resume_from() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), 100, false);
+        interpreter.stack.push(U256::from(1)).unwrap();
+        interpreter.stack.push(U256::from(2)).unwrap();
+        interpreter.next_action = InterpreterAction::Create {
+            inputs: Box::new(revm_interpreter::CreateInputs {
+                caller: Address::ZERO,
+                scheme: revm_primitives::CreateScheme::Create,
+                value: U256::ZERO,
+                init_code: Bytes::new(),
+                gas_limit: 0,
+            }),
+        };
+
+        let (ecx, stack, stack_len) = EvmContext::resume_from(&mut interpreter, &mut host, 2);
+        assert_eq!(ecx.resume_at, 2);
+        assert!(ecx.next_action.is_none());
+        assert!(!ecx.is_suspended());
+        assert_eq!(*stack_len, 2);
+        assert_eq!(stack.as_slice()[..2], [EvmWord::from(1u64), EvmWord::from(2u64)]);
+    }
+
     extern_revmc! {
         #[link_name = "__test_fn"]
         fn test_fn;
@@ -792,7 +3698,7 @@ mod tests {
         _stack: *mut EvmStack,
         _stack_len: *mut usize,
         _env: *const Env,
-        _contract: *const Contract,
+        _contract: *const ContractView,
         _ecx: *mut EvmContext<'_>,
     ) -> InstructionResult {
         InstructionResult::Continue
@@ -805,6 +3711,190 @@ mod tests {
         assert_eq!(test_fn as usize, __test_fn as usize);
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn host_error() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert!(ecx.take_host_error().is_none());
+
+        ecx.set_host_error("sload");
+        assert_eq!(ecx.take_host_error().unwrap().to_string(), "host call `sload` failed");
+        assert!(ecx.take_host_error().is_none());
+
+        report_host_error("missing trie node");
+        ecx.set_host_error("sload");
+        assert_eq!(ecx.take_host_error().unwrap().to_string(), "missing trie node");
+
+        // Not picked up by a failed host call, so it is discarded when the next call starts.
+        report_host_error("stale");
+        let r = unsafe { EvmCompilerFn::new(test_fn).call(None, None, &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+        ecx.set_host_error("sload");
+        assert_eq!(ecx.take_host_error().unwrap().to_string(), "host call `sload` failed");
+    }
+
+    #[test]
+    fn user_data() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.user_data_mut::<u64>(), None);
+
+        let mut data = 1u64;
+        ecx.user_data = Some(&mut data);
+        assert_eq!(ecx.user_data_mut::<u32>(), None);
+        *ecx.user_data_mut::<u64>().unwrap() += 1;
+        drop(ecx);
+        assert_eq!(data, 2);
+    }
+
+    #[test]
+    fn call_batch() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        *stack_len = 3;
+        let f = EvmCompilerFn::new(test_fn);
+        let results = unsafe { EvmCompilerFn::call_batch(&[f, f], &mut ecx, stack, stack_len) };
+        assert_eq!(results, [InstructionResult::Continue; 2]);
+        assert_eq!(*stack_len, 0);
+    }
+
+    #[test]
+    fn call_uninit() {
+        unsafe extern "C" fn push(
+            _gas: *mut Gas,
+            stack: *mut EvmStack,
+            stack_len: *mut usize,
+            _env: *const Env,
+            _contract: *const ContractView,
+            _ecx: *mut EvmContext<'_>,
+        ) -> InstructionResult {
+            unsafe {
+                (*stack).0[*stack_len].write(EvmWord::from(7u64));
+                *stack_len += 1;
+            }
+            InstructionResult::Stop
+        }
+
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        let mut stack = EvmStack::new_uninit();
+        let mut stack_len = 0;
+        let meta = FnMeta { inspect_stack_length: true, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(push, meta);
+        let r = unsafe { f.call_uninit(&mut stack, Some(&mut stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        assert_eq!(
+            unsafe { EvmStack::assume_init_slice(&stack, stack_len) },
+            [EvmWord::from(7u64)]
+        );
+    }
+
+    #[test]
+    fn fn_meta() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        assert_eq!(EvmCompilerFn::new(test_fn).meta(), None);
+
+        let mut meta = FnMeta { inspect_stack_length: true, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        assert_eq!(f.meta(), Some(meta));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+
+        meta.inspect_stack_length = false;
+        meta.local_stack = true;
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        let r = unsafe { f.call(None, None, &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+    }
+
+    #[test]
+    fn stale_stack_len() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        interpreter.stack.push(U256::from(1)).unwrap();
+
+        // `test_fn` doesn't write the stack length.
+        let f = EvmCompilerFn::new(test_fn);
+        let _ = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+        assert_eq!(interpreter.stack.len(), 1);
+
+        let meta = FnMeta { writes_stack_length: true, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        let _ = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+        assert_eq!(interpreter.stack.len(), 1);
+
+        let f = EvmCompilerFn::new_with_meta(test_fn, FnMeta::default());
+        let _ = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+        assert_eq!(interpreter.stack.len(), 0);
+    }
+
+    #[test]
+    fn fn_meta_min_stack_len() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        let mut meta =
+            FnMeta { inspect_stack_length: true, min_stack_len: 2, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        // The empty default bytecode doesn't contain the instruction pointer.
+        ecx.resume_at = 0;
+
+        *stack_len = 1;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::StackUnderflow);
+        *stack_len = 2;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+
+        // Resumed functions continue with the stack they suspended with.
+        *stack_len = 0;
+        ecx.resume_at = 1;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+
+        // The stack length is ignored.
+        ecx.resume_at = 0;
+        meta.inspect_stack_length = false;
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+    }
+
+    #[test]
+    #[should_panic = "stack length is required by the function"]
+    fn fn_meta_missing_stack_len() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let (mut ecx, stack, _) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        let meta = FnMeta { inspect_stack_length: true, ..Default::default() };
+        let f = EvmCompilerFn::new_with_meta(test_fn, meta);
+        let _ = unsafe { f.call(Some(stack), None, &mut ecx) };
+    }
+
+    #[test]
+    fn prefault() {
+        let mut host = revm_interpreter::DummyHost::default();
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let (mut ecx, stack, stack_len) =
+            EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+        let f = EvmCompilerFn::new(test_fn);
+        unsafe { f.prefault(0) };
+        unsafe { f.prefault(1) };
+        let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+        assert_eq!(r, InstructionResult::Continue);
+    }
+
     #[test]
     fn borrowing_host() {
         #[allow(unused)]
@@ -0,0 +1,246 @@
+//! Overriding of individual host calls.
+
+use alloc::boxed::Box;
+use core::fmt;
+use revm_interpreter::{AccountLoad, Host, SStoreResult, SelfDestructResult, StateLoad};
+use revm_primitives::{Address, Bytes, Env, Log, B256, U256};
+
+/// A host call, with its arguments. Passed to the closure of an [`OverrideHost`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum HostRequest {
+    LoadAccountDelegated { address: Address },
+    BlockHash { number: u64 },
+    Balance { address: Address },
+    Code { address: Address },
+    CodeHash { address: Address },
+    Sload { address: Address, index: U256 },
+    Sstore { address: Address, index: U256, value: U256 },
+    Tload { address: Address, index: U256 },
+    Tstore { address: Address, index: U256, value: U256 },
+    Log { log: Log },
+    Selfdestruct { address: Address, target: Address },
+}
+
+/// The return value of a host call. Returned by the closure of an [`OverrideHost`].
+///
+/// The variant must match the one of the [`HostRequest`]. `Tstore` and `Log` responses drop the
+/// call instead of forwarding it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum HostResponse {
+    LoadAccountDelegated(Option<AccountLoad>),
+    BlockHash(Option<B256>),
+    Balance(Option<StateLoad<U256>>),
+    Code(Option<StateLoad<Bytes>>),
+    CodeHash(Option<StateLoad<B256>>),
+    Sload(Option<StateLoad<U256>>),
+    Sstore(Option<StateLoad<SStoreResult>>),
+    Tload(U256),
+    Tstore,
+    Log,
+    Selfdestruct(Option<StateLoad<SelfDestructResult>>),
+}
+
+/// The closure of an [`OverrideHost`].
+type OverrideFn = Box<dyn FnMut(HostRequest) -> Option<HostResponse>>;
+
+/// A [`Host`] that forwards to an inner host, unless a closure overrides the call.
+///
+/// The closure is called with every host call before it is forwarded, and returns `Some` to
+/// respond to the call instead of the inner host. This allows scripting specific host behaviors,
+/// such as making an `SLOAD` return a chosen value, or making a host call fail by returning
+/// `None` in the response, which fails the instruction with
+/// [`InstructionResult::FatalExternalError`](revm_interpreter::InstructionResult::FatalExternalError).
+///
+/// Calls and creates are not host calls, see [`EvmContext::next_action`](crate::EvmContext).
+///
+/// # Panics
+///
+/// Host calls panic if the response does not match the request.
+///
+/// # Examples
+///
+/// ```
+/// use revm_interpreter::{DummyHost, Host, StateLoad};
+/// use revm_primitives::{Address, U256};
+/// use revmc_context::{HostRequest, HostResponse, OverrideHost};
+///
+/// let mut host = OverrideHost::new(DummyHost::default(), |request| match request {
+///     HostRequest::Sload { index, .. } if index == U256::from(1) => {
+///         Some(HostResponse::Sload(Some(StateLoad::new(U256::from(69), true))))
+///     }
+///     _ => None,
+/// });
+/// assert_eq!(host.sload(Address::ZERO, U256::from(1)).unwrap().data, U256::from(69));
+/// assert_eq!(host.sload(Address::ZERO, U256::from(2)).unwrap().data, U256::ZERO);
+/// ```
+pub struct OverrideHost<H> {
+    host: H,
+    f: OverrideFn,
+}
+
+impl<H: fmt::Debug> fmt::Debug for OverrideHost<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideHost").field("host", &self.host).finish_non_exhaustive()
+    }
+}
+
+impl<H: Host> OverrideHost<H> {
+    /// Creates a new overriding host.
+    pub fn new(host: H, f: impl FnMut(HostRequest) -> Option<HostResponse> + 'static) -> Self {
+        Self { host, f: Box::new(f) }
+    }
+
+    /// Returns a reference to the inner host.
+    #[inline]
+    pub fn inner(&self) -> &H {
+        &self.host
+    }
+
+    /// Returns a mutable reference to the inner host.
+    ///
+    /// Calls made directly on the inner host are not overridden.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
+
+    /// Consumes the overriding host, returning the inner host.
+    #[inline]
+    pub fn into_inner(self) -> H {
+        self.host
+    }
+}
+
+#[cold]
+#[track_caller]
+fn mismatched(request: &str, response: &HostResponse) -> ! {
+    panic!("mismatched response to a `{request}` host call: {response:?}")
+}
+
+macro_rules! forward {
+    ($self:ident, $variant:ident { $($arg:ident),* $(,)? }, $method:ident) => {
+        match ($self.f)(HostRequest::$variant { $($arg,)* }) {
+            Some(HostResponse::$variant(result)) => result,
+            Some(response) => mismatched(stringify!($variant), &response),
+            None => $self.host.$method($($arg),*),
+        }
+    };
+}
+
+impl<H: Host> Host for OverrideHost<H> {
+    fn env(&self) -> &Env {
+        self.host.env()
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        self.host.env_mut()
+    }
+
+    fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+        forward!(self, LoadAccountDelegated { address }, load_account_delegated)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Option<B256> {
+        forward!(self, BlockHash { number }, block_hash)
+    }
+
+    fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+        forward!(self, Balance { address }, balance)
+    }
+
+    fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+        forward!(self, Code { address }, code)
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+        forward!(self, CodeHash { address }, code_hash)
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+        forward!(self, Sload { address, index }, sload)
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Option<StateLoad<SStoreResult>> {
+        forward!(self, Sstore { address, index, value }, sstore)
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        forward!(self, Tload { address, index }, tload)
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        match (self.f)(HostRequest::Tstore { address, index, value }) {
+            Some(HostResponse::Tstore) => {}
+            Some(response) => mismatched("Tstore", &response),
+            None => self.host.tstore(address, index, value),
+        }
+    }
+
+    fn log(&mut self, log: Log) {
+        match (self.f)(HostRequest::Log { log: log.clone() }) {
+            Some(HostResponse::Log) => {}
+            Some(response) => mismatched("Log", &response),
+            None => self.host.log(log),
+        }
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<StateLoad<SelfDestructResult>> {
+        forward!(self, Selfdestruct { address, target }, selfdestruct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EvmContext;
+    use alloc::vec;
+    use revm_interpreter::{Contract, DummyHost, Interpreter};
+
+    #[test]
+    fn override_host() {
+        let address = Address::with_last_byte(1);
+        let mut host = OverrideHost::new(DummyHost::default(), move |request| match request {
+            HostRequest::Sload { index, .. } if index == U256::from(1) => {
+                Some(HostResponse::Sload(Some(StateLoad::new(U256::from(2), true))))
+            }
+            HostRequest::Balance { address: a } if a == address => {
+                Some(HostResponse::Balance(None))
+            }
+            HostRequest::Log { .. } => Some(HostResponse::Log),
+            _ => None,
+        });
+        host.inner_mut().storage.insert(U256::from(3), U256::from(4));
+
+        let mut interpreter = Interpreter::new(Contract::default(), u64::MAX, false);
+        let ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+        assert_eq!(ecx.host.sload(address, U256::from(1)).unwrap().data, U256::from(2));
+        assert_eq!(ecx.host.sload(address, U256::from(3)).unwrap().data, U256::from(4));
+        assert_eq!(ecx.host.balance(address), None);
+        assert!(ecx.host.balance(Address::ZERO).is_some());
+        ecx.host.log(Log::new_unchecked(address, vec![], Bytes::new()));
+        ecx.host.tstore(address, U256::from(5), U256::from(6));
+        assert_eq!(ecx.host.tload(address, U256::from(5)), U256::from(6));
+
+        let host = host.into_inner();
+        assert!(host.log.is_empty());
+    }
+
+    #[test]
+    #[should_panic = "mismatched response to a `Sload` host call: Tload(0)"]
+    fn override_host_mismatched() {
+        let mut host =
+            OverrideHost::new(DummyHost::default(), |_| Some(HostResponse::Tload(U256::ZERO)));
+        host.sload(Address::ZERO, U256::ZERO);
+    }
+}
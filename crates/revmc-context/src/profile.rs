@@ -0,0 +1,109 @@
+//! Per-block execution profiles.
+
+use alloc::collections::BTreeMap;
+use revm_primitives::{keccak256, B256};
+
+/// The number of times each basic block of a contract was executed, keyed by the program counter
+/// of the first instruction of the block.
+///
+/// Recorded in [`EvmContext::block_profile`](crate::EvmContext::block_profile) by functions
+/// compiled with block profiling enabled, and fed back into the compiler to lay out the code of the
+/// same contract according to the observed frequencies.
+///
+/// The profile is tied to the code it was recorded for by its hash, so that a profile of a
+/// different contract, or of an older version of the same contract, can be detected and rejected.
+/// With the `serde` feature, it can be saved to and loaded from a JSON file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionProfile {
+    /// The Keccak-256 hash of the profiled code.
+    pub code_hash: B256,
+    /// The execution count of each block that was executed at least once.
+    pub blocks: BTreeMap<u32, u64>,
+}
+
+impl ExecutionProfile {
+    /// Creates a new empty profile for the given code.
+    pub fn new(code: &[u8]) -> Self {
+        Self { code_hash: keccak256(code), blocks: BTreeMap::new() }
+    }
+
+    /// Returns `true` if the profile was recorded for the given code.
+    pub fn is_for(&self, code: &[u8]) -> bool {
+        self.code_hash == keccak256(code)
+    }
+
+    /// Records an execution of the block starting at `pc`.
+    #[inline]
+    pub fn record(&mut self, pc: u32) {
+        let count = self.blocks.entry(pc).or_default();
+        *count = count.saturating_add(1);
+    }
+
+    /// Returns the number of times the block starting at `pc` was executed.
+    #[inline]
+    pub fn count(&self, pc: u32) -> u64 {
+        self.blocks.get(&pc).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if no block was executed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Adds the counts of `other` to this profile.
+    ///
+    /// Returns `false` and leaves this profile unchanged if `other` was recorded for different
+    /// code.
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if self.code_hash != other.code_hash {
+            return false;
+        }
+        for (&pc, &n) in &other.blocks {
+            let count = self.blocks.entry(pc).or_default();
+            *count = count.saturating_add(n);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let code = [0x5b, 0x00];
+        let mut profile = ExecutionProfile::new(&code);
+        assert!(profile.is_empty());
+        assert!(profile.is_for(&code));
+        assert!(!profile.is_for(&[0x00]));
+
+        profile.record(0);
+        profile.record(0);
+        profile.record(1);
+        assert_eq!(profile.count(0), 2);
+        assert_eq!(profile.count(1), 1);
+        assert_eq!(profile.count(2), 0);
+
+        let mut merged = profile.clone();
+        assert!(merged.merge(&profile));
+        assert_eq!(merged.count(0), 4);
+        assert_eq!(merged.count(1), 2);
+
+        let other = ExecutionProfile::new(&[0x00]);
+        assert!(!merged.merge(&other));
+        assert_eq!(merged.count(0), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let mut profile = ExecutionProfile::new(&[0x00]);
+        profile.record(0);
+        profile.record(10);
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(serde_json::from_str::<ExecutionProfile>(&json).unwrap(), profile);
+    }
+}
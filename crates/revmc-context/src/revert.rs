@@ -0,0 +1,212 @@
+//! Decoding of revert reasons.
+
+use alloc::string::String;
+use core::fmt;
+use revm_primitives::{hex, Bytes, U256};
+
+/// The selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// The selector of `Panic(uint256)`.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// The decoded output of a `REVERT`.
+///
+/// See [`decode`](Self::decode).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RevertReason {
+    /// No output, as in `revert()` or `require(condition)`.
+    Empty,
+    /// `Error(string)`, as in `revert("message")` or `require(condition, "message")`.
+    Error(String),
+    /// `Panic(uint256)`, emitted by Solidity for failed assertions and checked arithmetic. See
+    /// [`panic_description`](Self::panic_description).
+    Panic(U256),
+    /// A custom error, identified by its selector.
+    Custom {
+        /// The error selector.
+        selector: [u8; 4],
+        /// The ABI-encoded error arguments.
+        data: Bytes,
+    },
+    /// Output that is not ABI-encoded, such as an `Error(string)` or a `Panic(uint256)` with
+    /// malformed arguments, or less than 4 bytes.
+    Raw(Bytes),
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("revert"),
+            Self::Error(message) => write!(f, "revert: {message}"),
+            Self::Panic(code) => match self.panic_description() {
+                Some(description) => write!(f, "panic: {description} ({code:#x})"),
+                None => write!(f, "panic: unknown code {code:#x}"),
+            },
+            Self::Custom { selector, data } => {
+                write!(f, "custom error {}", hex::encode_prefixed(selector))?;
+                if !data.is_empty() {
+                    write!(f, ": {data}")?;
+                }
+                Ok(())
+            }
+            Self::Raw(data) => write!(f, "revert: {data}"),
+        }
+    }
+}
+
+impl RevertReason {
+    /// Decodes the output of a `REVERT`.
+    ///
+    /// This never fails: output that is not a well-formed `Error(string)` or `Panic(uint256)` is
+    /// returned as [`Custom`](Self::Custom) if it starts with any other selector, or as
+    /// [`Raw`](Self::Raw) otherwise.
+    pub fn decode(output: &[u8]) -> Self {
+        if output.is_empty() {
+            return Self::Empty;
+        }
+        let Some((&selector, data)) = output.split_first_chunk::<4>() else {
+            return Self::Raw(Bytes::copy_from_slice(output));
+        };
+        let decoded = match selector {
+            ERROR_SELECTOR => decode_string(data).map(Self::Error),
+            PANIC_SELECTOR => (data.len() == 32).then(|| Self::Panic(U256::from_be_slice(data))),
+            _ => Some(Self::Custom { selector, data: Bytes::copy_from_slice(data) }),
+        };
+        decoded.unwrap_or_else(|| Self::Raw(Bytes::copy_from_slice(output)))
+    }
+
+    /// Returns the description of a [`Panic`](Self::Panic) code, as documented by Solidity.
+    ///
+    /// Returns `None` for other reasons and unknown codes.
+    pub fn panic_description(&self) -> Option<&'static str> {
+        let Self::Panic(code) = self else { return None };
+        let code = u8::try_from(*code).ok()?;
+        Some(match code {
+            0x00 => "generic compiler panic",
+            0x01 => "assertion failed",
+            0x11 => "arithmetic underflow or overflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array encoding",
+            0x31 => "pop on empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to zero-initialized internal function",
+            _ => return None,
+        })
+    }
+}
+
+/// Decodes an ABI-encoded `string` argument.
+fn decode_string(data: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        let word = data.get(offset..offset.checked_add(32)?)?;
+        usize::try_from(U256::from_be_slice(word)).ok()
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn decode() {
+        // `require(false, "ERC20: transfer amount exceeds balance")`, from OpenZeppelin 4.
+        let output = hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "0000000000000000000000000000000000000000000000000000000000000026"
+            "45524332303a207472616e7366657220616d6f756e7420657863656564732062"
+            "616c616e63650000000000000000000000000000000000000000000000000000"
+        );
+        let reason = RevertReason::decode(&output);
+        let message = "ERC20: transfer amount exceeds balance";
+        assert_eq!(reason, RevertReason::Error(message.into()));
+        assert_eq!(reason.to_string(), "revert: ERC20: transfer amount exceeds balance");
+
+        // `uint8(255) + 1` with checked arithmetic.
+        let output = hex!(
+            "4e487b71"
+            "0000000000000000000000000000000000000000000000000000000000000011"
+        );
+        let reason = RevertReason::decode(&output);
+        assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(reason.panic_description(), Some("arithmetic underflow or overflow"));
+        assert_eq!(reason.to_string(), "panic: arithmetic underflow or overflow (0x11)");
+
+        // `error Unauthorized(); revert Unauthorized();`
+        let output = hex!("82b42900");
+        let reason = RevertReason::decode(&output);
+        let expected = RevertReason::Custom { selector: hex!("82b42900"), data: Bytes::new() };
+        assert_eq!(reason, expected);
+        assert_eq!(reason.to_string(), "custom error 0x82b42900");
+
+        // `error InsufficientBalance(uint256 available); revert InsufficientBalance(1);`
+        let output = hex!(
+            "cf479181"
+            "0000000000000000000000000000000000000000000000000000000000000001"
+        );
+        let RevertReason::Custom { selector, data } = RevertReason::decode(&output) else {
+            panic!()
+        };
+        assert_eq!(selector, hex!("cf479181"));
+        assert_eq!(data[..], output[4..]);
+
+        assert_eq!(RevertReason::decode(&[]), RevertReason::Empty);
+        assert_eq!(RevertReason::decode(&[]).to_string(), "revert");
+    }
+
+    #[test]
+    fn decode_malformed() {
+        let raw = |output: &[u8]| RevertReason::Raw(Bytes::copy_from_slice(output));
+
+        // Less than a selector.
+        let output = hex!("08c379");
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+        assert_eq!(RevertReason::decode(&output).to_string(), "revert: 0x08c379");
+
+        // Truncated string.
+        let output = hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "0000000000000000000000000000000000000000000000000000000000000026"
+            "45524332303a"
+        );
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+
+        // Out of bounds and overflowing offsets and lengths.
+        let output = hex!(
+            "08c379a0"
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+        );
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+        let output = hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "000000000000000000000000000000000000000000000000ffffffffffffffff"
+        );
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+
+        // Invalid UTF-8.
+        let output = hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "0000000000000000000000000000000000000000000000000000000000000001"
+            "ff00000000000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+
+        // Truncated panic code, and unknown code.
+        let output = hex!("4e487b710011");
+        assert_eq!(RevertReason::decode(&output), raw(&output));
+        let reason = RevertReason::Panic(U256::from(0x99));
+        assert_eq!(reason.panic_description(), None);
+        assert_eq!(reason.to_string(), "panic: unknown code 0x99");
+    }
+}
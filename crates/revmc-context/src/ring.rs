@@ -0,0 +1,184 @@
+//! Bounded logging of host calls.
+
+use revm_interpreter::InstructionResult;
+
+/// The [`Host`](revm_interpreter::Host) method of a host call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum HostCallKind {
+    LoadAccountDelegated,
+    BlockHash,
+    Balance,
+    Code,
+    CodeHash,
+    Sload,
+    Sstore,
+    Tload,
+    Tstore,
+    Log,
+    Selfdestruct,
+}
+
+/// A host call made by a builtin, with the dynamic gas that the builtin charged for it.
+///
+/// The gas does not include the static cost of the instruction, which is paid by the compiled
+/// code. Calls that fail are logged too, with the result that the builtin returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostCallRecord {
+    /// The host method that was called.
+    pub kind: HostCallKind,
+    /// The dynamic gas charged, or that could not be paid if the call ran out of gas.
+    pub gas: u64,
+    /// How the call ended: [`Continue`](InstructionResult::Continue) if it succeeded,
+    /// [`OutOfGas`](InstructionResult::OutOfGas) if its gas could not be paid, and
+    /// [`FatalExternalError`](InstructionResult::FatalExternalError) if the host failed or
+    /// panicked.
+    pub result: InstructionResult,
+}
+
+/// A sink for the host calls made by builtins. See [`EvmContext::host_call_log`].
+///
+/// [`EvmContext::host_call_log`]: crate::EvmContext::host_call_log
+pub trait HostCallLog {
+    /// Records a host call.
+    fn record(&mut self, record: HostCallRecord);
+}
+
+/// A fixed-size ring buffer of the last `N` host calls.
+///
+/// Recording never allocates, so unlike [`RecordingHost`](crate::RecordingHost) this can be left
+/// on in long-running processes, and dumped when something goes wrong.
+///
+/// # Examples
+///
+/// ```
+/// use revm_interpreter::InstructionResult;
+/// use revmc_context::{HostCallKind, HostCallLog, HostCallRecord, HostCallRing};
+///
+/// let mut ring = HostCallRing::<2>::new();
+/// for gas in [1, 2, 3] {
+///     let result = InstructionResult::Continue;
+///     ring.record(HostCallRecord { kind: HostCallKind::Sload, gas, result });
+/// }
+/// assert_eq!(ring.total(), 3);
+/// assert_eq!(ring.iter().map(|r| r.gas).collect::<Vec<_>>(), [2, 3]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HostCallRing<const N: usize> {
+    records: [HostCallRecord; N],
+    total: u64,
+}
+
+impl<const N: usize> Default for HostCallRing<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> HostCallLog for HostCallRing<N> {
+    #[inline]
+    fn record(&mut self, record: HostCallRecord) {
+        self.push(record);
+    }
+}
+
+impl<const N: usize> HostCallRing<N> {
+    /// Creates a new empty ring buffer.
+    #[inline]
+    pub const fn new() -> Self {
+        const EMPTY: HostCallRecord = HostCallRecord {
+            kind: HostCallKind::Sload,
+            gas: 0,
+            result: InstructionResult::Continue,
+        };
+        Self { records: [EMPTY; N], total: 0 }
+    }
+
+    /// Records a host call, overwriting the oldest one if the buffer is full.
+    #[inline]
+    pub fn push(&mut self, record: HostCallRecord) {
+        if N == 0 {
+            return;
+        }
+        self.records[(self.total % N as u64) as usize] = record;
+        self.total += 1;
+    }
+
+    /// Returns the number of host calls in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.total.min(N as u64) as usize
+    }
+
+    /// Returns `true` if no host calls have been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Returns the number of host calls recorded since creation or the last [`clear`](Self::clear),
+    /// including the ones that have been overwritten.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the most recent host call.
+    #[inline]
+    pub fn last(&self) -> Option<&HostCallRecord> {
+        self.iter().next_back()
+    }
+
+    /// Returns an iterator over the host calls in the buffer, from oldest to newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HostCallRecord> + ExactSizeIterator {
+        let len = self.len();
+        let start = if len < N { 0 } else { (self.total % N as u64) as usize };
+        (0..len).map(move |i| &self.records[(start + i) % N])
+    }
+
+    /// Removes all host calls.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.total = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(gas: u64) -> HostCallRecord {
+        HostCallRecord { kind: HostCallKind::Balance, gas, result: InstructionResult::Continue }
+    }
+
+    #[test]
+    fn ring() {
+        let mut ring = HostCallRing::<3>::new();
+        assert!(ring.is_empty());
+        assert_eq!(ring.last(), None);
+
+        ring.push(record(1));
+        ring.push(record(2));
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.iter().map(|r| r.gas).collect::<Vec<_>>(), [1, 2]);
+
+        for gas in 3..=7 {
+            ring.push(record(gas));
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.total(), 7);
+        assert_eq!(ring.iter().map(|r| r.gas).collect::<Vec<_>>(), [5, 6, 7]);
+        assert_eq!(ring.last(), Some(&record(7)));
+
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.iter().count(), 0);
+
+        let mut empty = HostCallRing::<0>::new();
+        empty.push(record(1));
+        assert_eq!(empty.len(), 0);
+    }
+}
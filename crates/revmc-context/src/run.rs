@@ -0,0 +1,220 @@
+//! Running compiled functions to completion.
+
+use crate::{EvmCompilerFn, FrameOutcome, HostExt};
+use alloc::boxed::Box;
+use revm_interpreter::{
+    CallInputs, CallOutcome, CreateInputs, CreateOutcome, EOFCreateInputs, Interpreter,
+    InterpreterAction, InterpreterResult, SharedMemory,
+};
+
+/// A host that executes the calls and creates requested by a function run with
+/// [`EvmCompilerFn::run_to_completion`].
+///
+/// Each method receives the shared memory of the requesting frame. A nested frame should be
+/// executed in a new context of it, created with [`SharedMemory::new_context`] and freed with
+/// [`SharedMemory::free_context`] before returning.
+///
+/// The host is responsible for the journal and the call depth. A nested frame that is executed
+/// with [`EvmCompilerFn::run_to_completion`] again recurses on the native stack, so the host must
+/// enforce the call depth limit of 1024 frames, returning a failed outcome instead of recursing
+/// past it, like revm does.
+pub trait SubcallHost: HostExt {
+    /// Executes a call and returns its outcome.
+    fn execute_call(&mut self, inputs: Box<CallInputs>, memory: &mut SharedMemory) -> CallOutcome;
+
+    /// Executes a legacy create and returns its outcome.
+    fn execute_create(
+        &mut self,
+        inputs: Box<CreateInputs>,
+        memory: &mut SharedMemory,
+    ) -> CreateOutcome;
+
+    /// Executes an EOF create and returns its outcome.
+    fn execute_eofcreate(
+        &mut self,
+        inputs: Box<EOFCreateInputs>,
+        memory: &mut SharedMemory,
+    ) -> CreateOutcome;
+}
+
+impl EvmCompilerFn {
+    /// Runs the function until it returns, executing the calls and creates that it suspends on
+    /// with `host`.
+    ///
+    /// Execution starts like [`call_with_interpreter`](Self::call_with_interpreter), on the
+    /// interpreter's memory. Whenever the function suspends, the request is passed to the matching
+    /// [`SubcallHost`] method, and its outcome is inserted into `interpreter` like revm does
+    /// before resuming the function, which is then called again. Returns the result of the first
+    /// call that does not suspend.
+    ///
+    /// This does not recurse by itself: nested frames are executed by the host, which may run
+    /// them with this method, and must limit the call depth, see [`SubcallHost`].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the function is safe to call.
+    pub unsafe fn run_to_completion<H: SubcallHost>(
+        self,
+        interpreter: &mut Interpreter,
+        host: &mut H,
+    ) -> InterpreterResult {
+        loop {
+            let outcome = match self.call_with_interpreter(interpreter, host) {
+                InterpreterAction::Return { result } => return result,
+                InterpreterAction::Call { inputs } => {
+                    FrameOutcome::Call(host.execute_call(inputs, &mut interpreter.shared_memory))
+                }
+                InterpreterAction::Create { inputs } => FrameOutcome::Create(
+                    host.execute_create(inputs, &mut interpreter.shared_memory),
+                ),
+                InterpreterAction::EOFCreate { inputs } => FrameOutcome::EOFCreate(
+                    host.execute_eofcreate(inputs, &mut interpreter.shared_memory),
+                ),
+                InterpreterAction::None => {
+                    unreachable!("compiled functions always return an action")
+                }
+            };
+            outcome.insert_into(interpreter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ret, test_fn, TestHost};
+    use alloc::vec::Vec;
+    use revm_interpreter::{
+        analysis::to_analysed, opcode as op, CallScheme, CallValue, Contract, Gas,
+        InstructionResult, EMPTY_SHARED_MEMORY,
+    };
+    use revm_primitives::{Address, Bytecode, Bytes, B256, U256};
+
+    test_fn! {
+        /// Calls `input[0]` with the rest of the input, if any, and returns the return data of the
+        /// call followed by the last byte of its own address.
+        fn test_contract(ecx) {
+            if !ecx.contract.input.is_empty() && ecx.resume_at == 0 {
+                let target = Address::with_last_byte(ecx.contract.input[0]);
+                let gas_limit = ecx.gas.remaining() / 2;
+                assert!(ecx.gas.record_cost(gas_limit));
+                *ecx.next_action = InterpreterAction::Call {
+                    inputs: Box::new(CallInputs {
+                        input: ecx.contract.input.slice(1..),
+                        return_memory_offset: 0..0,
+                        gas_limit,
+                        bytecode_address: target,
+                        target_address: target,
+                        caller: ecx.contract.target_address,
+                        value: CallValue::Transfer(U256::ZERO),
+                        scheme: CallScheme::Call,
+                        is_static: false,
+                        is_eof: false,
+                    }),
+                };
+                ecx.resume_at = 1;
+                return InstructionResult::CallOrCreate;
+            }
+            let mut output = ecx.return_data.to_vec();
+            output.push(ecx.contract.target_address[19]);
+            ret(ecx, InstructionResult::Return, output)
+        }
+    }
+
+    /// Executes calls with [`EvmCompilerFn::run_to_completion`], up to `max_depth` frames.
+    struct Frames {
+        depth: usize,
+        max_depth: usize,
+        calls: Vec<u8>,
+    }
+
+    impl SubcallHost for TestHost<Frames> {
+        fn execute_call(
+            &mut self,
+            inputs: Box<CallInputs>,
+            memory: &mut SharedMemory,
+        ) -> CallOutcome {
+            let memory_offset = inputs.return_memory_offset.clone();
+            if self.state.depth >= self.state.max_depth {
+                let result = InterpreterResult {
+                    result: InstructionResult::CallTooDeep,
+                    output: Bytes::new(),
+                    gas: Gas::new(inputs.gas_limit),
+                };
+                return CallOutcome::new(result, memory_offset);
+            }
+            self.state.calls.push(inputs.target_address[19]);
+            let bytecode = to_analysed(Bytecode::new_raw(Bytes::from_static(&[op::STOP])));
+            let contract = Contract::new_with_context(
+                inputs.input.clone(),
+                bytecode,
+                Some(B256::ZERO),
+                &inputs,
+            );
+            let mut child = Interpreter::new(contract, inputs.gas_limit, inputs.is_static);
+            child.shared_memory = core::mem::replace(memory, EMPTY_SHARED_MEMORY);
+            child.shared_memory.new_context();
+            self.state.depth += 1;
+            let f = EvmCompilerFn::new(test_contract);
+            let result = unsafe { f.run_to_completion(&mut child, self) };
+            self.state.depth -= 1;
+            *memory = child.take_memory();
+            memory.free_context();
+            CallOutcome::new(result, memory_offset)
+        }
+
+        fn execute_create(
+            &mut self,
+            _inputs: Box<CreateInputs>,
+            _memory: &mut SharedMemory,
+        ) -> CreateOutcome {
+            unreachable!()
+        }
+
+        fn execute_eofcreate(
+            &mut self,
+            _inputs: Box<EOFCreateInputs>,
+            _memory: &mut SharedMemory,
+        ) -> CreateOutcome {
+            unreachable!()
+        }
+    }
+
+    fn run(input: &[u8], max_depth: usize) -> (InterpreterResult, Frames) {
+        let contract = Contract {
+            input: Bytes::copy_from_slice(input),
+            ..crate::test_contract(Bytes::from_static(&[op::STOP]))
+        };
+        let mut host = TestHost::new(Frames { depth: 1, max_depth, calls: Vec::new() });
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        interpreter.shared_memory = SharedMemory::new();
+        let f = EvmCompilerFn::new(test_contract);
+        let result = unsafe { f.run_to_completion(&mut interpreter, &mut host) };
+        (result, host.state)
+    }
+
+    #[test]
+    fn run_to_completion() {
+        let (result, host) = run(&[], 1024);
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(result.output[..], [0]);
+        assert!(host.calls.is_empty());
+
+        let (result, host) = run(&[1, 2, 3], 1024);
+        assert_eq!(result.result, InstructionResult::Return);
+        assert_eq!(result.output[..], [3, 2, 1, 0]);
+        assert_eq!(host.calls, [1, 2, 3]);
+        assert_eq!(host.depth, 1);
+        // Every frame forwards half of its gas, and each callee returns it unspent.
+        assert_eq!(result.gas.remaining(), 100_000);
+    }
+
+    #[test]
+    fn run_to_completion_depth_limit() {
+        let (result, host) = run(&[1, 2, 3], 3);
+        assert_eq!(result.result, InstructionResult::Return);
+        // The call from the third frame fails, leaving it with empty return data.
+        assert_eq!(result.output[..], [2, 1, 0]);
+        assert_eq!(host.calls, [1, 2]);
+    }
+}
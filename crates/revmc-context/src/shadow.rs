@@ -0,0 +1,540 @@
+//! Differential execution of compiled functions against the interpreter.
+
+use crate::{
+    EvmExecute, HostCall, HostExt, HostTape, InterpreterExec, OwnedEvmState, RecordingHost,
+    ReplayHost,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+use revm_interpreter::{Contract, Gas, InstructionResult, Interpreter, InterpreterAction};
+use revm_primitives::{keccak256, SpecId, B256};
+
+/// Which run of a [`ShadowExecutor`] is returned, and applied to the host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ShadowPolicy {
+    /// Return the interpreter's result. The compiled function runs in the shadow.
+    #[default]
+    Interpreter,
+    /// Return the compiled function's result. The interpreter runs in the shadow.
+    Compiled,
+}
+
+/// The result of one of the runs of a shadowed frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowOutcome {
+    /// The instruction result.
+    pub result: InstructionResult,
+    /// The next action: the output of the frame, or the call or create that it suspended on.
+    pub action: InterpreterAction,
+    /// The gas at the end of the run.
+    pub gas: Gas,
+}
+
+/// A difference between the runs of a shadowed frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShadowMismatch {
+    /// The instruction results differ.
+    Result,
+    /// The outputs differ, or the calls or creates that the frame suspended on.
+    Output,
+    /// The remaining or refunded gas differs. Not compared if either run halted with an error,
+    /// since all of the gas is spent then.
+    Gas,
+    /// The host calls differ, which includes the logs and the storage writes.
+    HostCall {
+        /// The index of the first differing host call.
+        index: usize,
+        /// The host call made by the returned run, if any.
+        expected: Option<Box<HostCall>>,
+        /// The host call made by the shadow run instead, if any.
+        actual: Option<Box<HostCall>>,
+    },
+}
+
+/// A report of a frame whose compiled function and interpreter runs diverged, created by a
+/// [`ShadowExecutor`].
+///
+/// The report contains everything needed to reproduce the frame in isolation: the contract, the
+/// [`OwnedEvmState`] that the frame started with, which can be saved with
+/// [`OwnedEvmState::encode`], and the [`HostTape`] of the returned run, which can be replayed with
+/// a [`ReplayHost`].
+#[derive(Clone, Debug)]
+pub struct ShadowDivergence {
+    /// The hash of the contract's code.
+    pub code_hash: B256,
+    /// The spec that the frame was interpreted with.
+    pub spec_id: SpecId,
+    /// The policy that the executor returned the result of.
+    pub policy: ShadowPolicy,
+    /// The contract of the frame.
+    pub contract: Contract,
+    /// The state that the frame started with.
+    pub state: OwnedEvmState,
+    /// The host calls of the returned run.
+    pub tape: HostTape,
+    /// The result of the interpreter.
+    pub interpreter: ShadowOutcome,
+    /// The result of the compiled function.
+    pub compiled: ShadowOutcome,
+    /// The differences between the two runs, in the order of the fields of [`ShadowOutcome`],
+    /// followed by the host calls.
+    pub mismatches: Vec<ShadowMismatch>,
+}
+
+impl fmt::Display for ShadowDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { interpreter: i, compiled: c, .. } = self;
+        write!(f, "compiled code {} diverged from the interpreter", self.code_hash)?;
+        for mismatch in &self.mismatches {
+            match mismatch {
+                ShadowMismatch::Result => {
+                    write!(f, "\n  result: interpreter={:?} compiled={:?}", i.result, c.result)
+                }
+                ShadowMismatch::Output => {
+                    write!(f, "\n  output: interpreter={:?} compiled={:?}", i.action, c.action)
+                }
+                ShadowMismatch::Gas => write!(
+                    f,
+                    "\n  gas: interpreter={}/{} compiled={}/{} (remaining/refunded)",
+                    i.gas.remaining(),
+                    i.gas.refunded(),
+                    c.gas.remaining(),
+                    c.gas.refunded(),
+                ),
+                ShadowMismatch::HostCall { index, expected, actual } => {
+                    write!(f, "\n  host call #{index}: expected={expected:?} actual={actual:?}")
+                }
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// The callback of a [`ShadowExecutor`].
+type DivergenceFn = Box<dyn Fn(&ShadowDivergence) + Send + Sync>;
+
+/// An [`EvmExecute`] that runs both a compiled function and the interpreter, compares their
+/// results, and reports divergences.
+///
+/// This is meant for rolling out compiled code with confidence: both runs are executed online for
+/// every outermost frame, and the result of the run chosen by the [`ShadowPolicy`] is returned,
+/// while every divergence is passed to a callback with the full context needed to reproduce it.
+///
+/// The returned run is executed first, on the real host, and its host calls are recorded. The
+/// shadow run is then executed on a copy of the frame, with its host calls answered from the
+/// recording instead of the real host: its writes are dropped, and it observes the same state as
+/// the returned run did. Once the shadow run deviates from the recorded host calls, its host calls
+/// fail.
+///
+/// Frames are only shadowed until their first call or create: the rest of a frame after a
+/// sub-call is executed with the returned run only. To also keep the cost bounded across calls,
+/// use [`execute_at_depth`](Self::execute_at_depth), which only shadows the outermost frames.
+///
+/// With the `host-ext-any` feature, the host passed to the returned run is a wrapper around the
+/// real host, so the executors must not downcast it.
+pub struct ShadowExecutor<C> {
+    compiled: C,
+    interpreter: InterpreterExec,
+    policy: ShadowPolicy,
+    on_divergence: DivergenceFn,
+}
+
+impl<C: fmt::Debug> fmt::Debug for ShadowExecutor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShadowExecutor")
+            .field("compiled", &self.compiled)
+            .field("interpreter", &self.interpreter)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: EvmExecute> ShadowExecutor<C> {
+    /// Creates a new shadow executor that runs `compiled` against the interpreter of `spec_id`,
+    /// calling `on_divergence` with every divergence.
+    ///
+    /// The policy defaults to [`ShadowPolicy::Interpreter`].
+    pub fn new(
+        compiled: C,
+        spec_id: SpecId,
+        on_divergence: impl Fn(&ShadowDivergence) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            compiled,
+            interpreter: InterpreterExec::new(spec_id),
+            policy: ShadowPolicy::default(),
+            on_divergence: Box::new(on_divergence),
+        }
+    }
+
+    /// Sets the policy.
+    pub fn with_policy(mut self, policy: ShadowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the policy.
+    #[inline]
+    pub fn policy(&self) -> ShadowPolicy {
+        self.policy
+    }
+
+    /// Returns the compiled executor.
+    #[inline]
+    pub fn compiled(&self) -> &C {
+        &self.compiled
+    }
+
+    /// Same as [`execute`](EvmExecute::execute), but only shadows the frame if `depth` is `0`,
+    /// that is, if it is called by the transaction.
+    ///
+    /// # Safety
+    ///
+    /// See [`EvmExecute::execute`].
+    pub unsafe fn execute_at_depth(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+        depth: usize,
+    ) -> InterpreterAction {
+        if depth == 0 {
+            unsafe { self.execute_shadowed(interpreter, host) }
+        } else {
+            unsafe { self.returned().execute(interpreter, host) }
+        }
+    }
+
+    /// Shadows the frame if it starts execution.
+    unsafe fn execute_shadowed(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        if !starts_execution(interpreter) {
+            return unsafe { self.returned().execute(interpreter, host) };
+        }
+
+        let mut shadow = Interpreter::new(
+            interpreter.contract.clone(),
+            interpreter.gas.limit(),
+            interpreter.is_static,
+        );
+        shadow.gas = interpreter.gas;
+        shadow.is_eof_init = interpreter.is_eof_init;
+        shadow.shared_memory = interpreter.shared_memory.clone();
+        shadow.return_data_buffer = interpreter.return_data_buffer.clone();
+        let state = OwnedEvmState {
+            gas: interpreter.gas,
+            stack: Vec::new(),
+            memory: interpreter.shared_memory.context_memory().to_vec(),
+            is_static: interpreter.is_static,
+            resume_at: 0,
+            return_data: interpreter.return_data_buffer.to_vec(),
+        };
+
+        #[allow(unused_unsafe)]
+        let mut recording = RecordingHost::new(unsafe { HostRef::new(host) });
+        let action = unsafe { self.returned().execute(interpreter, &mut recording) };
+        let tape = recording.into_tape();
+
+        let mut replay = ReplayHost::lenient(tape.clone());
+        let shadow_action = unsafe { self.shadowed().execute(&mut shadow, &mut replay) };
+
+        let returned = ShadowOutcome {
+            result: interpreter.instruction_result,
+            action: action.clone(),
+            gas: interpreter.gas,
+        };
+        let shadowed = ShadowOutcome {
+            result: shadow.instruction_result,
+            action: shadow_action,
+            gas: shadow.gas,
+        };
+        let mut mismatches = compare(&returned, &shadowed);
+        let deviation = match replay.deviation() {
+            Some((index, call)) => Some((index, Some(Box::new(call.clone())))),
+            None if !replay.is_finished() => Some((tape.calls.len() - replay.remaining(), None)),
+            None => None,
+        };
+        if let Some((index, actual)) = deviation {
+            let expected = tape.calls.get(index).cloned().map(Box::new);
+            mismatches.push(ShadowMismatch::HostCall { index, expected, actual });
+        }
+        if mismatches.is_empty() {
+            return action;
+        }
+
+        let contract = &interpreter.contract;
+        let code_hash =
+            contract.hash.unwrap_or_else(|| keccak256(contract.bytecode.original_byte_slice()));
+        let (interpreter_outcome, compiled) = match self.policy {
+            ShadowPolicy::Interpreter => (returned, shadowed),
+            ShadowPolicy::Compiled => (shadowed, returned),
+        };
+        (self.on_divergence)(&ShadowDivergence {
+            code_hash,
+            spec_id: self.interpreter.spec_id(),
+            policy: self.policy,
+            contract: contract.clone(),
+            state,
+            tape,
+            interpreter: interpreter_outcome,
+            compiled,
+            mismatches,
+        });
+        action
+    }
+
+    fn returned(&self) -> &dyn EvmExecute {
+        match self.policy {
+            ShadowPolicy::Interpreter => &self.interpreter,
+            ShadowPolicy::Compiled => &self.compiled,
+        }
+    }
+
+    fn shadowed(&self) -> &dyn EvmExecute {
+        match self.policy {
+            ShadowPolicy::Interpreter => &self.compiled,
+            ShadowPolicy::Compiled => &self.interpreter,
+        }
+    }
+}
+
+impl<C: EvmExecute> EvmExecute for ShadowExecutor<C> {
+    unsafe fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        host: &mut dyn HostExt,
+    ) -> InterpreterAction {
+        unsafe { self.execute_shadowed(interpreter, host) }
+    }
+}
+
+/// Returns `true` if the interpreter is at the start of its frame, rather than resuming it.
+fn starts_execution(interpreter: &Interpreter) -> bool {
+    interpreter.instruction_pointer == interpreter.bytecode.as_ptr()
+}
+
+/// Compares the outcomes of two runs, without the host calls.
+fn compare(a: &ShadowOutcome, b: &ShadowOutcome) -> Vec<ShadowMismatch> {
+    let mut mismatches = Vec::new();
+    if a.result != b.result {
+        mismatches.push(ShadowMismatch::Result);
+    }
+    let same_output = match (&a.action, &b.action) {
+        (InterpreterAction::Return { result: a }, InterpreterAction::Return { result: b }) => {
+            a.output == b.output
+        }
+        (a, b) => a == b,
+    };
+    if !same_output {
+        mismatches.push(ShadowMismatch::Output);
+    }
+    if !a.result.is_error() && !b.result.is_error() && a.gas != b.gas {
+        mismatches.push(ShadowMismatch::Gas);
+    }
+    mismatches
+}
+
+/// A [`Host`] that forwards to the host of a frame, so that the host can be wrapped in another
+/// host that is itself passed as a `dyn HostExt`.
+///
+/// With `host-ext-any`, hosts are `'static` to be downcast, so the wrapper holds a pointer to the
+/// host instead of borrowing it, see [`HostRef::new`].
+struct HostRef<'a> {
+    #[cfg(not(feature = "host-ext-any"))]
+    host: &'a mut dyn HostExt,
+    #[cfg(feature = "host-ext-any")]
+    host: core::ptr::NonNull<dyn HostExt>,
+    #[cfg(feature = "host-ext-any")]
+    _borrow: core::marker::PhantomData<&'a mut dyn HostExt>,
+}
+
+impl HostRef<'_> {
+    #[cfg(not(feature = "host-ext-any"))]
+    fn new(host: &mut dyn HostExt) -> HostRef<'_> {
+        HostRef { host }
+    }
+
+    /// # Safety
+    ///
+    /// The returned value must not outlive `host`.
+    #[cfg(feature = "host-ext-any")]
+    unsafe fn new(host: &mut dyn HostExt) -> HostRef<'static> {
+        HostRef { host: host.into(), _borrow: core::marker::PhantomData }
+    }
+}
+
+/// The host that a [`HostRef`] forwards to, which is always `'static` with `host-ext-any`.
+#[cfg(not(feature = "host-ext-any"))]
+type DynHost<'a> = dyn HostExt + 'a;
+#[cfg(feature = "host-ext-any")]
+type DynHost<'a> = dyn HostExt;
+
+impl<'a> core::ops::Deref for HostRef<'a> {
+    type Target = DynHost<'a>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        #[cfg(not(feature = "host-ext-any"))]
+        return self.host;
+        #[cfg(feature = "host-ext-any")]
+        return unsafe { self.host.as_ref() };
+    }
+}
+
+impl core::ops::DerefMut for HostRef<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        #[cfg(not(feature = "host-ext-any"))]
+        return self.host;
+        #[cfg(feature = "host-ext-any")]
+        return unsafe { self.host.as_mut() };
+    }
+}
+
+crate::forward_host!(impl[] HostRef<'_>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_contract,
+        test_utils::{ret, test_fn},
+        EvmCompilerFn,
+    };
+    use revm_interpreter::{opcode as op, DummyHost, SharedMemory};
+    use revm_primitives::{Address, Bytes, Log};
+    use std::sync::{Arc, Mutex};
+
+    #[rustfmt::skip]
+    const CODE: &[u8] = &[
+        op::PUSH1, 0x69, op::PUSH0, op::MSTORE,
+        op::PUSH1, 0x20, op::PUSH0, op::LOG0,
+        op::PUSH1, 0x20, op::PUSH0, op::RETURN,
+    ];
+    const GAS: u64 = (3 + 2 + 3 + 3) + (3 + 2 + 375 + 8 * 32) + (3 + 2);
+
+    test_fn! {
+        /// Stands in for the compiled `CODE`, storing `VALUE` instead of `0x69` and spending `GAS`.
+        fn compiled<const VALUE: u8, const GAS: u64>(ecx) {
+            if !ecx.gas.record_cost(GAS) {
+                return InstructionResult::OutOfGas;
+            }
+            ecx.memory.resize(32);
+            ecx.memory.set(31, &[VALUE]);
+            let output = Bytes::copy_from_slice(ecx.memory.slice(0, 32));
+            let log = Log::new_unchecked(ecx.contract.target_address, vec![], output.clone());
+            ecx.host.log(log);
+            ret(ecx, InstructionResult::Return, output)
+        }
+    }
+
+    fn executor(
+        f: EvmCompilerFn,
+        policy: ShadowPolicy,
+    ) -> (ShadowExecutor<EvmCompilerFn>, Arc<Mutex<Vec<ShadowDivergence>>>) {
+        let divergences = Arc::new(Mutex::new(Vec::new()));
+        let divergences2 = divergences.clone();
+        let executor = ShadowExecutor::new(f, SpecId::CANCUN, move |divergence| {
+            divergences2.lock().unwrap().push(divergence.clone())
+        })
+        .with_policy(policy);
+        (executor, divergences)
+    }
+
+    fn run(executor: &dyn EvmExecute) -> (InterpreterAction, DummyHost) {
+        let mut host = DummyHost::default();
+        let mut interpreter = interpreter();
+        let mut memory = SharedMemory::new();
+        let action =
+            unsafe { executor.execute_with_memory(&mut interpreter, &mut memory, &mut host) };
+        (action, host)
+    }
+
+    fn interpreter() -> Interpreter {
+        let contract = Contract {
+            target_address: Address::with_last_byte(1),
+            ..test_contract(Bytes::from_static(CODE))
+        };
+        Interpreter::new(contract, 100_000, false)
+    }
+
+    fn output(action: &InterpreterAction) -> &[u8] {
+        let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+        &result.output
+    }
+
+    #[test]
+    fn agree() {
+        for policy in [ShadowPolicy::Interpreter, ShadowPolicy::Compiled] {
+            let (executor, divergences) =
+                executor(EvmCompilerFn::new(compiled::<0x69, GAS>), policy);
+            let (action, host) = run(&executor);
+            assert_eq!(output(&action)[31], 0x69);
+            // The shadow run's log is not applied.
+            assert_eq!(host.log.len(), 1);
+            assert!(divergences.lock().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn miscompiled() {
+        let (executor, divergences) =
+            executor(EvmCompilerFn::new(compiled::<0x42, GAS>), ShadowPolicy::Interpreter);
+        let (action, host) = run(&executor);
+        assert_eq!(output(&action)[31], 0x69);
+        assert_eq!(host.log.len(), 1);
+        assert_eq!(host.log[0].data.data[31], 0x69);
+
+        let divergences = divergences.lock().unwrap();
+        let [divergence] = &divergences[..] else { panic!("{divergences:?}") };
+        assert_eq!(divergence.code_hash, keccak256(CODE));
+        assert_eq!(divergence.policy, ShadowPolicy::Interpreter);
+        assert_eq!(divergence.state.gas.remaining(), 100_000);
+        assert_eq!(output(&divergence.interpreter.action)[31], 0x69);
+        assert_eq!(output(&divergence.compiled.action)[31], 0x42);
+        let [ShadowMismatch::Output, ShadowMismatch::HostCall { index: 0, expected, actual }] =
+            &divergence.mismatches[..]
+        else {
+            panic!("{:?}", divergence.mismatches)
+        };
+        assert_eq!(expected.as_deref(), divergence.tape.calls.first());
+        let Some(HostCall::Log { log }) = actual.as_deref() else { panic!("{actual:?}") };
+        assert_eq!(log.data.data[31], 0x42);
+
+        let report = divergence.to_string();
+        assert!(report.starts_with(&format!("compiled code {}", keccak256(CODE))), "{report}");
+        assert!(report.contains("\n  output: "), "{report}");
+        assert!(report.contains("\n  host call #0: "), "{report}");
+    }
+
+    #[test]
+    fn miscompiled_gas() {
+        let (executor, divergences) =
+            executor(EvmCompilerFn::new(compiled::<0x69, { GAS + 1 }>), ShadowPolicy::Compiled);
+        let (action, _) = run(&executor);
+        let InterpreterAction::Return { result } = action else { panic!() };
+        assert_eq!(result.gas.spent(), GAS + 1);
+
+        let divergences = divergences.lock().unwrap();
+        let [divergence] = &divergences[..] else { panic!("{divergences:?}") };
+        assert_eq!(divergence.mismatches, [ShadowMismatch::Gas]);
+        assert_eq!(divergence.interpreter.gas.spent(), GAS);
+        assert_eq!(divergence.compiled.gas.spent(), GAS + 1);
+    }
+
+    #[test]
+    fn depth() {
+        let (executor, divergences) =
+            executor(EvmCompilerFn::new(compiled::<0x42, GAS>), ShadowPolicy::Compiled);
+        for (depth, diverged) in [(1, false), (0, true)] {
+            let mut host = DummyHost::default();
+            let mut interpreter = interpreter();
+            let action = unsafe { executor.execute_at_depth(&mut interpreter, &mut host, depth) };
+            assert_eq!(output(&action)[31], 0x42);
+            assert_eq!(divergences.lock().unwrap().len(), diverged as usize, "{depth}");
+        }
+    }
+}
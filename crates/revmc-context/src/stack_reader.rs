@@ -0,0 +1,164 @@
+//! Typed reads from the top of an [`EvmStack`].
+
+use crate::{EvmStack, EvmWord};
+use core::fmt;
+use revm_primitives::{Address, U256};
+
+/// Pops typed values from the top of an [`EvmStack`], without modifying it.
+///
+/// Created with [`EvmStack::reader`]. Each `pop_*` method reads the word at the cursor, starting
+/// at the top of the stack, and moves the cursor one word down on success. If the stack is
+/// exhausted or the word does not fit the requested type, `None` is returned and the cursor is
+/// left in place, so result extraction can be written with `?`:
+///
+/// ```
+/// # use revmc_context::{EvmStack, EvmWord};
+/// # use revm_primitives::{Address, U256};
+/// # fn f() -> Option<()> {
+/// let mut stack = EvmStack::new();
+/// let mut len = 0;
+/// let to = Address::with_last_byte(1);
+/// stack.set_from_top(&mut len, &[EvmWord::from(69u64), EvmWord::from_be_bytes(to.into_word().0)]);
+///
+/// let mut reader = stack.reader(len);
+/// let amount = reader.pop_u256()?;
+/// let recipient = reader.pop_address()?;
+/// assert_eq!((amount, recipient), (U256::from(69), to));
+/// assert!(reader.pop_u256().is_none());
+/// # Some(())
+/// # }
+/// # f().unwrap();
+/// ```
+///
+/// It is also an iterator over the remaining words, from top to bottom.
+#[derive(Clone)]
+pub struct StackReader<'a> {
+    stack: &'a EvmStack,
+    len: usize,
+}
+
+impl fmt::Debug for StackReader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a> StackReader<'a> {
+    /// Creates a new reader over the first `len` words of `stack`.
+    ///
+    /// `len` is clamped to [`EvmStack::CAPACITY`].
+    #[inline]
+    pub fn new(stack: &'a EvmStack, len: usize) -> Self {
+        Self { stack, len: len.min(EvmStack::CAPACITY) }
+    }
+
+    /// Returns the number of words left below the cursor.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the word at the cursor without moving it.
+    #[inline]
+    pub fn peek(&self) -> Option<&'a EvmWord> {
+        self.stack.peek(self.len, 0)
+    }
+
+    /// Pops the word at the cursor.
+    #[inline]
+    pub fn pop_word(&mut self) -> Option<EvmWord> {
+        self.pop_map(Some)
+    }
+
+    /// Pops the word at the cursor as a [`U256`].
+    #[inline]
+    pub fn pop_u256(&mut self) -> Option<U256> {
+        self.pop_map(|word| Some(word.to_u256()))
+    }
+
+    /// Pops the word at the cursor as a `usize`, if it fits.
+    #[inline]
+    pub fn pop_usize(&mut self) -> Option<usize> {
+        self.pop_map(|word| word.try_into().ok())
+    }
+
+    /// Pops the word at the cursor as an [`Address`], if its high 12 bytes are zero.
+    ///
+    /// See [`EvmWord::to_address_checked`].
+    #[inline]
+    pub fn pop_address(&mut self) -> Option<Address> {
+        self.pop_map(EvmWord::to_address_checked)
+    }
+
+    /// Pops the word at the cursor as a `bool`, if it is `0` or `1`.
+    #[inline]
+    pub fn pop_bool(&mut self) -> Option<bool> {
+        self.pop_map(|word| word.try_into().ok())
+    }
+
+    #[inline]
+    fn pop_map<T>(&mut self, f: impl FnOnce(EvmWord) -> Option<T>) -> Option<T> {
+        let value = f(*self.peek()?)?;
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl Iterator for StackReader<'_> {
+    type Item = EvmWord;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop_word()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl ExactSizeIterator for StackReader<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop() {
+        let mut stack = EvmStack::new();
+        let mut len = 0;
+        let address = Address::with_last_byte(0x69);
+        stack.set_from_top(
+            &mut len,
+            &[
+                EvmWord::from(1u64),
+                EvmWord::from(2u64),
+                EvmWord::from_be_bytes(address.into_word().0),
+                EvmWord::MAX,
+                EvmWord::from(3u64),
+            ],
+        );
+
+        let mut reader = stack.reader(len);
+        assert_eq!(reader.remaining(), 5);
+        assert_eq!(reader.pop_bool(), Some(true));
+        // Not a bool, so the cursor stays.
+        assert_eq!(reader.pop_bool(), None);
+        assert_eq!(reader.pop_usize(), Some(2));
+        assert_eq!(reader.pop_address(), Some(address));
+        assert_eq!(reader.pop_address(), None);
+        assert_eq!(reader.pop_usize(), None);
+        assert_eq!(reader.peek(), Some(&EvmWord::MAX));
+        assert_eq!(reader.pop_u256(), Some(U256::MAX));
+        assert_eq!(reader.remaining(), 1);
+        assert_eq!(reader.collect::<Vec<_>>(), [EvmWord::from(3u64)]);
+
+        let mut reader = stack.reader(len);
+        assert_eq!(reader.len(), 5);
+        assert_eq!(reader.nth(4), Some(EvmWord::from(3u64)));
+        assert_eq!(reader.pop_word(), None);
+        assert_eq!(reader.pop_u256(), None);
+        assert_eq!(stack.reader(0).peek(), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Binary encoding of [`OwnedEvmState`].
+
+use crate::{EvmStack, EvmWord, OwnedEvmState};
+use alloc::vec::Vec;
+use core::fmt;
+use revm_interpreter::Gas;
+
+/// The magic bytes at the start of an encoded state.
+const MAGIC: [u8; 4] = *b"RVMS";
+
+/// The current version of the encoding.
+///
+/// Bump this when changing the encoding, and keep decoding the previous versions.
+const VERSION: u16 = 1;
+
+/// The `is_static` bit of the flags byte.
+const FLAG_STATIC: u8 = 1 << 0;
+
+/// The error returned by [`OwnedEvmState::decode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeStateError {
+    /// The input does not start with the magic bytes.
+    InvalidMagic,
+    /// The input was encoded with an unknown version of the encoding.
+    UnsupportedVersion(u16),
+    /// The input ended before the end of the state.
+    UnexpectedEof,
+    /// The input continues after the end of the state.
+    TrailingBytes,
+    /// A field has an invalid value.
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for DecodeStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMagic => f.write_str("invalid magic bytes"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version {version}"),
+            Self::UnexpectedEof => f.write_str("unexpected end of input"),
+            Self::TrailingBytes => f.write_str("trailing bytes after the end of the state"),
+            Self::InvalidField(field) => write!(f, "invalid {field}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeStateError {}
+
+impl OwnedEvmState {
+    /// Encodes the state into a compact binary form, which can be decoded with
+    /// [`decode`](Self::decode).
+    ///
+    /// The encoding starts with a versioned header, so that states encoded by older versions of
+    /// this crate remain decodable. Integers are little-endian, and stack words are big-endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            48 + self.stack.len() * 32 + self.memory.len() + self.return_data.len(),
+        );
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.gas.limit().to_le_bytes());
+        out.extend_from_slice(&self.gas.remaining().to_le_bytes());
+        out.extend_from_slice(&self.gas.refunded().to_le_bytes());
+        out.push(if self.is_static { FLAG_STATIC } else { 0 });
+        out.extend_from_slice(&(self.resume_at as u64).to_le_bytes());
+        out.extend_from_slice(&(self.stack.len() as u64).to_le_bytes());
+        for word in &self.stack {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+        for bytes in [&self.memory, &self.return_data] {
+            out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            out.extend_from_slice(bytes);
+        }
+        out
+    }
+
+    /// Decodes a state encoded with [`encode`](Self::encode).
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeStateError> {
+        let mut r = Reader(bytes);
+        if r.array::<4>()? != MAGIC {
+            return Err(DecodeStateError::InvalidMagic);
+        }
+        let version = u16::from_le_bytes(r.array()?);
+        if version != VERSION {
+            return Err(DecodeStateError::UnsupportedVersion(version));
+        }
+
+        let limit = r.u64()?;
+        let remaining = r.u64()?;
+        let refunded = i64::from_le_bytes(r.array()?);
+        if remaining > limit {
+            return Err(DecodeStateError::InvalidField("gas"));
+        }
+        let mut gas = Gas::new(limit);
+        let recorded = gas.record_cost(limit - remaining);
+        debug_assert!(recorded);
+        gas.set_refund(refunded);
+
+        let [flags] = r.array()?;
+        if flags & !FLAG_STATIC != 0 {
+            return Err(DecodeStateError::InvalidField("flags"));
+        }
+        let resume_at = r.len()?;
+
+        let stack_len = r.len()?;
+        if stack_len > EvmStack::CAPACITY {
+            return Err(DecodeStateError::InvalidField("stack length"));
+        }
+        let stack = (0..stack_len)
+            .map(|_| r.array().map(EvmWord::from_be_bytes))
+            .collect::<Result<_, _>>()?;
+        let len = r.len()?;
+        let memory = r.bytes(len)?.to_vec();
+        let len = r.len()?;
+        let return_data = r.bytes(len)?.to_vec();
+
+        if !r.0.is_empty() {
+            return Err(DecodeStateError::TrailingBytes);
+        }
+        Ok(Self { gas, stack, memory, is_static: flags & FLAG_STATIC != 0, resume_at, return_data })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeStateError> {
+        if self.0.len() < len {
+            return Err(DecodeStateError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeStateError> {
+        self.bytes(N).map(|bytes| bytes.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeStateError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn len(&mut self) -> Result<usize, DecodeStateError> {
+        usize::try_from(self.u64()?).map_err(|_| DecodeStateError::UnexpectedEof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_primitives::U256;
+
+    fn state() -> OwnedEvmState {
+        let mut gas = Gas::new(1000);
+        assert!(gas.record_cost(300));
+        gas.set_refund(-20);
+        OwnedEvmState {
+            gas,
+            stack: vec![EvmWord::from(U256::from(1)), EvmWord::from(U256::MAX)],
+            memory: vec![0x69; 64],
+            is_static: true,
+            resume_at: 3,
+            return_data: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let state = state();
+        let encoded = state.encode();
+        assert_eq!(encoded[..6], [b'R', b'V', b'M', b'S', 1, 0]);
+        assert_eq!(OwnedEvmState::decode(&encoded), Ok(state));
+
+        let empty = OwnedEvmState {
+            gas: Gas::new(0),
+            stack: vec![],
+            memory: vec![],
+            is_static: false,
+            resume_at: 0,
+            return_data: vec![],
+        };
+        assert_eq!(OwnedEvmState::decode(&empty.encode()), Ok(empty));
+    }
+
+    #[test]
+    fn errors() {
+        let encoded = state().encode();
+        let decode = |f: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = encoded.clone();
+            f(&mut bytes);
+            OwnedEvmState::decode(&bytes)
+        };
+        assert_eq!(decode(&|b| b[0] = 0), Err(DecodeStateError::InvalidMagic));
+        assert_eq!(decode(&|b| b[4] = 2), Err(DecodeStateError::UnsupportedVersion(2)));
+        assert_eq!(decode(&|b| b.truncate(6)), Err(DecodeStateError::UnexpectedEof));
+        assert_eq!(decode(&|b| b.truncate(b.len() - 1)), Err(DecodeStateError::UnexpectedEof));
+        assert_eq!(decode(&|b| b.push(0)), Err(DecodeStateError::TrailingBytes));
+        // `remaining` is larger than `limit`.
+        assert_eq!(decode(&|b| b[14..22].fill(0xff)), Err(DecodeStateError::InvalidField("gas")));
+        assert_eq!(decode(&|b| b[30] = 2), Err(DecodeStateError::InvalidField("flags")));
+        assert_eq!(
+            decode(&|b| b[39..47].fill(0xff)),
+            Err(DecodeStateError::InvalidField("stack length"))
+        );
+    }
+}
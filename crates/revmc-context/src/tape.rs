@@ -0,0 +1,488 @@
+//! Recording and replaying of host interactions.
+
+use revm_interpreter::{AccountLoad, Host, SStoreResult, SelfDestructResult, StateLoad};
+use revm_primitives::{Address, Bytes, Env, Log, B256, U256};
+
+/// A recorded sequence of host calls. Created by [`RecordingHost`], replayed by [`ReplayHost`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostTape {
+    /// The environment at the start of the recording.
+    ///
+    /// Changes made through [`Host::env_mut`] are not recorded, as the same execution makes the
+    /// same changes when replayed.
+    pub env: Env,
+    /// The host calls, in order.
+    pub calls: Vec<HostCall>,
+}
+
+/// A single host call, with its arguments and return value.
+///
+/// Calls and creates are not host calls; they are returned to the caller through
+/// [`EvmContext::next_action`](crate::EvmContext::next_action), together with the return data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "method", rename_all = "snake_case"))]
+#[allow(missing_docs)]
+pub enum HostCall {
+    LoadAccountDelegated {
+        address: Address,
+        result: Option<AccountLoad>,
+    },
+    BlockHash {
+        number: u64,
+        result: Option<B256>,
+    },
+    Balance {
+        address: Address,
+        result: Option<StateLoad<U256>>,
+    },
+    Code {
+        address: Address,
+        result: Option<StateLoad<Bytes>>,
+    },
+    CodeHash {
+        address: Address,
+        result: Option<StateLoad<B256>>,
+    },
+    Sload {
+        address: Address,
+        index: U256,
+        result: Option<StateLoad<U256>>,
+    },
+    Sstore {
+        address: Address,
+        index: U256,
+        value: U256,
+        result: Option<StateLoad<SStoreResult>>,
+    },
+    Tload {
+        address: Address,
+        index: U256,
+        result: U256,
+    },
+    Tstore {
+        address: Address,
+        index: U256,
+        value: U256,
+    },
+    Log {
+        log: Log,
+    },
+    Selfdestruct {
+        address: Address,
+        target: Address,
+        result: Option<StateLoad<SelfDestructResult>>,
+    },
+}
+
+impl HostCall {
+    /// Returns `true` if both calls are to the same method with the same arguments.
+    fn same_request(&self, other: &Self) -> bool {
+        self.clone().without_result() == other.clone().without_result()
+    }
+
+    fn without_result(mut self) -> Self {
+        match &mut self {
+            Self::LoadAccountDelegated { result, .. } => *result = None,
+            Self::BlockHash { result, .. } => *result = None,
+            Self::Balance { result, .. } => *result = None,
+            Self::Code { result, .. } => *result = None,
+            Self::CodeHash { result, .. } => *result = None,
+            Self::Sload { result, .. } => *result = None,
+            Self::Sstore { result, .. } => *result = None,
+            Self::Tload { result, .. } => *result = U256::ZERO,
+            Self::Tstore { .. } | Self::Log { .. } => {}
+            Self::Selfdestruct { result, .. } => *result = None,
+        }
+        self
+    }
+}
+
+/// A [`Host`] that forwards to an inner host, and records every call into a [`HostTape`].
+#[derive(Debug)]
+pub struct RecordingHost<H> {
+    host: H,
+    tape: HostTape,
+    selfdestruct_target: Option<Address>,
+}
+
+impl<H: Host> RecordingHost<H> {
+    /// Creates a new recording host, capturing the inner host's current environment.
+    pub fn new(host: H) -> Self {
+        let env = host.env().clone();
+        Self { host, tape: HostTape { env, calls: Vec::new() }, selfdestruct_target: None }
+    }
+
+    /// Returns a reference to the inner host.
+    #[inline]
+    pub fn inner(&self) -> &H {
+        &self.host
+    }
+
+    /// Returns a mutable reference to the inner host.
+    ///
+    /// Calls made directly on the inner host are not recorded.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
+
+    /// Returns the tape recorded so far.
+    #[inline]
+    pub fn tape(&self) -> &HostTape {
+        &self.tape
+    }
+
+    /// Returns the logs emitted so far, in order.
+    ///
+    /// These are the [`HostCall::Log`] entries of the tape.
+    pub fn emitted_logs(&self) -> impl Iterator<Item = &Log> + '_ {
+        self.tape.calls.iter().filter_map(|call| match call {
+            HostCall::Log { log } => Some(log),
+            _ => None,
+        })
+    }
+
+    /// Returns the beneficiary of the last `SELFDESTRUCT` that the inner host performed, if any.
+    ///
+    /// Calls that failed, i.e. that returned `None`, are not counted.
+    #[inline]
+    pub fn selfdestruct_target(&self) -> Option<Address> {
+        self.selfdestruct_target
+    }
+
+    /// Consumes the recording host, returning the inner host and the recorded tape.
+    #[inline]
+    pub fn into_parts(self) -> (H, HostTape) {
+        (self.host, self.tape)
+    }
+
+    /// Consumes the recording host, returning the recorded tape.
+    #[inline]
+    pub fn into_tape(self) -> HostTape {
+        self.tape
+    }
+}
+
+impl<H: Host> Host for RecordingHost<H> {
+    fn env(&self) -> &Env {
+        self.host.env()
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        self.host.env_mut()
+    }
+
+    fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+        let result = self.host.load_account_delegated(address);
+        self.tape.calls.push(HostCall::LoadAccountDelegated { address, result: result.clone() });
+        result
+    }
+
+    fn block_hash(&mut self, number: u64) -> Option<B256> {
+        let result = self.host.block_hash(number);
+        self.tape.calls.push(HostCall::BlockHash { number, result });
+        result
+    }
+
+    fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+        let result = self.host.balance(address);
+        self.tape.calls.push(HostCall::Balance { address, result: result.clone() });
+        result
+    }
+
+    fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+        let result = self.host.code(address);
+        self.tape.calls.push(HostCall::Code { address, result: result.clone() });
+        result
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+        let result = self.host.code_hash(address);
+        self.tape.calls.push(HostCall::CodeHash { address, result: result.clone() });
+        result
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+        let result = self.host.sload(address, index);
+        self.tape.calls.push(HostCall::Sload { address, index, result: result.clone() });
+        result
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Option<StateLoad<SStoreResult>> {
+        let result = self.host.sstore(address, index, value);
+        self.tape.calls.push(HostCall::Sstore { address, index, value, result: result.clone() });
+        result
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        let result = self.host.tload(address, index);
+        self.tape.calls.push(HostCall::Tload { address, index, result });
+        result
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        self.host.tstore(address, index, value);
+        self.tape.calls.push(HostCall::Tstore { address, index, value });
+    }
+
+    fn log(&mut self, log: Log) {
+        self.tape.calls.push(HostCall::Log { log: log.clone() });
+        self.host.log(log);
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<StateLoad<SelfDestructResult>> {
+        let result = self.host.selfdestruct(address, target);
+        if result.is_some() {
+            self.selfdestruct_target = Some(target);
+        }
+        self.tape.calls.push(HostCall::Selfdestruct { address, target, result: result.clone() });
+        result
+    }
+}
+
+/// A [`Host`] that answers calls from a [`HostTape`].
+///
+/// # Panics
+///
+/// Host calls panic if they deviate from the recorded sequence, showing the expected and the
+/// actual call.
+#[derive(Debug)]
+pub struct ReplayHost {
+    env: Env,
+    calls: Vec<HostCall>,
+    next: usize,
+    lenient: bool,
+    deviation: Option<(usize, HostCall)>,
+}
+
+impl ReplayHost {
+    /// Creates a new replay host from the given tape.
+    pub fn new(tape: HostTape) -> Self {
+        Self { env: tape.env, calls: tape.calls, next: 0, lenient: false, deviation: None }
+    }
+
+    /// Creates a new replay host that fails host calls instead of panicking once they deviate from
+    /// the tape. The first deviating call is returned by [`deviation`](Self::deviation).
+    pub(crate) fn lenient(tape: HostTape) -> Self {
+        Self { lenient: true, ..Self::new(tape) }
+    }
+
+    /// Returns the index of the first call that deviated from the tape, and the call itself.
+    pub(crate) fn deviation(&self) -> Option<(usize, &HostCall)> {
+        self.deviation.as_ref().map(|(index, call)| (*index, call))
+    }
+
+    /// Returns the number of calls that have not been replayed yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.calls.len() - self.next
+    }
+
+    /// Returns `true` if all the calls have been replayed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the recorded call matching `call`, ignoring its result.
+    ///
+    /// Returns `None` if the host is lenient and the call deviates from the tape, or an earlier
+    /// call did.
+    #[track_caller]
+    fn replay(&mut self, call: HostCall) -> Option<HostCall> {
+        if self.deviation.is_some() {
+            return None;
+        }
+        let index = self.next;
+        let expected = self.calls.get(index);
+        if expected.is_some_and(|expected| expected.same_request(&call)) {
+            self.next += 1;
+            return expected.cloned();
+        }
+        if self.lenient {
+            self.deviation = Some((index, call));
+            return None;
+        }
+        match expected {
+            Some(expected) => panic!(
+                "host call #{index} deviates from the tape\nexpected: {:?}\n  actual: {:?}",
+                expected.clone().without_result(),
+                call,
+            ),
+            None => panic!("host call #{index} is not in the tape\n  actual: {call:?}"),
+        }
+    }
+}
+
+macro_rules! replay {
+    ($self:ident, $variant:ident { $($arg:ident),* $(,)? }) => {
+        match $self.replay(HostCall::$variant { $($arg,)* result: Default::default() }) {
+            Some(HostCall::$variant { result, .. }) => result,
+            Some(_) => unreachable!(),
+            None => Default::default(),
+        }
+    };
+}
+
+impl Host for ReplayHost {
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+        replay!(self, LoadAccountDelegated { address })
+    }
+
+    fn block_hash(&mut self, number: u64) -> Option<B256> {
+        replay!(self, BlockHash { number })
+    }
+
+    fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+        replay!(self, Balance { address })
+    }
+
+    fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+        replay!(self, Code { address })
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+        replay!(self, CodeHash { address })
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+        replay!(self, Sload { address, index })
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Option<StateLoad<SStoreResult>> {
+        replay!(self, Sstore { address, index, value })
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        replay!(self, Tload { address, index })
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        self.replay(HostCall::Tstore { address, index, value });
+    }
+
+    fn log(&mut self, log: Log) {
+        self.replay(HostCall::Log { log });
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<StateLoad<SelfDestructResult>> {
+        replay!(self, Selfdestruct { address, target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_interpreter::DummyHost;
+
+    fn record() -> HostTape {
+        let mut host = RecordingHost::new(DummyHost::default());
+        let address = Address::with_last_byte(1);
+        host.sstore(address, U256::from(1), U256::from(2));
+        assert_eq!(host.sload(address, U256::from(1)).unwrap().data, U256::from(2));
+        host.tstore(address, U256::from(3), U256::from(4));
+        assert_eq!(host.tload(address, U256::from(3)), U256::from(4));
+        host.log(Log::new_unchecked(address, vec![], Bytes::from_static(b"log")));
+        assert_eq!(host.inner().log.len(), 1);
+        assert!(host.emitted_logs().eq(&host.inner().log));
+        host.into_tape()
+    }
+
+    #[test]
+    fn selfdestruct_target() {
+        let mut host = RecordingHost::new(DummyHost::default());
+        assert_eq!(host.selfdestruct_target(), None);
+        let address = Address::with_last_byte(1);
+        host.selfdestruct(address, Address::with_last_byte(2));
+        host.selfdestruct(address, Address::with_last_byte(3));
+        assert_eq!(host.selfdestruct_target(), Some(Address::with_last_byte(3)));
+    }
+
+    #[test]
+    fn record_replay() {
+        let tape = record();
+        assert_eq!(tape.calls.len(), 5);
+        let HostCall::Sstore { result: recorded_sstore, .. } = &tape.calls[0] else { panic!() };
+        let recorded_sstore = recorded_sstore.clone();
+
+        let mut host = ReplayHost::new(tape);
+        let address = Address::with_last_byte(1);
+        assert_eq!(host.sstore(address, U256::from(1), U256::from(2)), recorded_sstore);
+        assert_eq!(host.sload(address, U256::from(1)).unwrap().data, U256::from(2));
+        host.tstore(address, U256::from(3), U256::from(4));
+        assert_eq!(host.tload(address, U256::from(3)), U256::from(4));
+        assert_eq!(host.remaining(), 1);
+        host.log(Log::new_unchecked(address, vec![], Bytes::from_static(b"log")));
+        assert!(host.is_finished());
+    }
+
+    #[test]
+    #[should_panic = "host call #1 deviates from the tape"]
+    fn replay_deviation() {
+        let mut host = ReplayHost::new(record());
+        let address = Address::with_last_byte(1);
+        host.sstore(address, U256::from(1), U256::from(2));
+        host.sload(address, U256::from(2));
+    }
+
+    #[test]
+    fn replay_lenient() {
+        let mut host = ReplayHost::lenient(record());
+        let address = Address::with_last_byte(1);
+        assert!(host.sstore(address, U256::from(1), U256::from(2)).is_some());
+        assert_eq!(host.sload(address, U256::from(2)), None);
+        let (index, call) = host.deviation().unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(*call, HostCall::Sload { address, index: U256::from(2), result: None });
+        // Calls fail after the first deviation, even if they match the tape.
+        assert_eq!(host.sload(address, U256::from(1)), None);
+        assert_eq!(host.deviation().unwrap().0, 1);
+        assert_eq!(host.remaining(), 4);
+    }
+
+    #[test]
+    #[should_panic = "host call #5 is not in the tape"]
+    fn replay_past_end() {
+        let mut host = ReplayHost::new(record());
+        for call in host.calls.clone() {
+            host.replay(call);
+        }
+        host.block_hash(0);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let tape = record();
+        let json = serde_json::to_string(&tape).unwrap();
+        assert_eq!(serde_json::from_str::<HostTape>(&json).unwrap(), tape);
+    }
+}
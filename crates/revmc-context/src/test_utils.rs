@@ -0,0 +1,84 @@
+//! Helpers shared by the tests of this crate.
+
+use crate::EvmContext;
+use core::ops::{Deref, DerefMut};
+use revm_interpreter::{DummyHost, InstructionResult, InterpreterAction, InterpreterResult};
+use revm_primitives::Bytes;
+
+/// A host that forwards to a [`DummyHost`], with the state of a test.
+#[derive(Debug, Default)]
+pub(crate) struct TestHost<T = ()> {
+    pub(crate) inner: DummyHost,
+    pub(crate) state: T,
+}
+
+impl<T> TestHost<T> {
+    pub(crate) fn new(state: T) -> Self {
+        Self { inner: DummyHost::default(), state }
+    }
+}
+
+impl<T> Deref for TestHost<T> {
+    type Target = DummyHost;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for TestHost<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+crate::forward_host!(impl[T] TestHost<T>);
+
+/// Defines an `extern "C"` function with the signature of
+/// [`RawEvmCompilerFn`](crate::RawEvmCompilerFn), to stand in for a compiled function.
+///
+/// The body is called with the context, and optionally the stack and its length, as references.
+macro_rules! test_fn {
+    ($(#[$attr:meta])* fn $name:ident $(<$(const $c:ident: $ct:ty),*>)? ($ecx:ident) $body:block) => {
+        $(#[$attr])*
+        unsafe extern "C" fn $name $(<$(const $c: $ct),*>)? (
+            _gas: *mut revm_interpreter::Gas,
+            _stack: *mut $crate::EvmStack,
+            _stack_len: *mut usize,
+            _env: *const revm_primitives::Env,
+            _contract: *const $crate::ContractView,
+            ecx: *mut $crate::EvmContext<'_>,
+        ) -> revm_interpreter::InstructionResult {
+            let $ecx = unsafe { &mut *ecx };
+            $body
+        }
+    };
+    ($(#[$attr:meta])* fn $name:ident ($ecx:ident, $stack:ident, $stack_len:ident) $body:block) => {
+        $(#[$attr])*
+        unsafe extern "C" fn $name(
+            _gas: *mut revm_interpreter::Gas,
+            stack: *mut $crate::EvmStack,
+            stack_len: *mut usize,
+            _env: *const revm_primitives::Env,
+            _contract: *const $crate::ContractView,
+            ecx: *mut $crate::EvmContext<'_>,
+        ) -> revm_interpreter::InstructionResult {
+            let ($ecx, $stack, $stack_len) = unsafe { (&mut *ecx, &mut *stack, &mut *stack_len) };
+            $body
+        }
+    };
+}
+pub(crate) use test_fn;
+
+/// Sets the next action of a function defined with [`test_fn!`] to return `output` with its
+/// remaining gas, and returns `result`.
+pub(crate) fn ret(
+    ecx: &mut EvmContext<'_>,
+    result: InstructionResult,
+    output: impl Into<Bytes>,
+) -> InstructionResult {
+    *ecx.next_action = InterpreterAction::Return {
+        result: InterpreterResult { result, output: output.into(), gas: *ecx.gas },
+    };
+    result
+}
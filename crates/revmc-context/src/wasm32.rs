@@ -0,0 +1,204 @@
+//! Fixed layouts of the arguments of functions compiled for `wasm32`.
+//!
+//! Functions compiled for `wasm32` take the same arguments as [`RawEvmCompilerFn`], as `i32`
+//! addresses in the linear memory of the module. The layouts of the host types depend on the host,
+//! so the compiled code instead reads and writes the `#[repr(C)]` types of this module, whose
+//! offsets are fixed: pointers and `usize`s are [`Ptr`]s, 64-bit integers are 8-byte aligned, and
+//! all values are little-endian, like the linear memory. The stack is an [`EvmStack`] and the stack
+//! length a `u32`.
+//!
+//! The types only contain the fields that compiled code accesses directly. Everything else is
+//! accessed by builtins, which the embedder implements, so it can keep that state anywhere.
+//!
+//! [`RawEvmCompilerFn`]: crate::RawEvmCompilerFn
+//! [`EvmStack`]: crate::EvmStack
+
+use core::mem::{offset_of, size_of};
+use revm_primitives::{Address, U256};
+
+/// An address in linear memory. `0` is the null pointer.
+pub type Ptr = u32;
+
+/// The layout of [`Gas`](revm_interpreter::Gas).
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gas {
+    /// The gas limit.
+    pub limit: u64,
+    /// The remaining gas.
+    pub remaining: u64,
+    /// The refunded gas.
+    pub refunded: i64,
+}
+
+/// The layout of a slice.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Slice {
+    /// Pointer to the first element.
+    pub ptr: Ptr,
+    /// Number of elements.
+    pub len: u32,
+}
+
+/// The layout of a `Vec`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vec {
+    /// Number of elements that fit in the allocation.
+    pub cap: u32,
+    /// Pointer to the allocation.
+    pub ptr: Ptr,
+    /// Number of elements.
+    pub len: u32,
+}
+
+/// The layout of [`SharedMemory`](revm_interpreter::SharedMemory).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SharedMemory {
+    /// The memory of all the contexts, as bytes.
+    pub buffer: Vec,
+    /// Offset of the memory of the current context in `buffer`.
+    pub last_checkpoint: u32,
+}
+
+/// The layout of [`FunctionReturnFrame`](revm_interpreter::FunctionReturnFrame).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FunctionReturnFrame {
+    /// Index of the code section to return to.
+    pub idx: u32,
+    /// The return address.
+    pub pc: u32,
+}
+
+/// The layout of [`FunctionStack`](revm_interpreter::FunctionStack).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FunctionStack {
+    /// The [`FunctionReturnFrame`]s.
+    pub return_stack: Vec,
+    /// Index of the current code section.
+    pub current_code_idx: u32,
+}
+
+/// The layout of [`EvmContext`](crate::EvmContext).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EvmContext {
+    /// Pointer to the [`SharedMemory`].
+    pub memory: Ptr,
+    /// The return data of the last call.
+    pub return_data: Slice,
+    /// Pointer to the [`FunctionStack`]. Only used by EOF bytecode.
+    pub func_stack: Ptr,
+    /// Pointer to the `[u64; 256]` opcode counts, or null.
+    pub opcode_counts: Ptr,
+    /// Pointer to the `u64` step budget, or null.
+    pub step_budget: Ptr,
+    /// The resume point.
+    pub resume_at: u32,
+    /// Whether the context is static.
+    pub is_static: bool,
+}
+
+/// The layout of [`ContractView`](crate::ContractView).
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ContractView {
+    /// Pointer to the call data.
+    pub input_ptr: Ptr,
+    /// Length of the call data.
+    pub input_len: u32,
+    /// Pointer to the original bytecode.
+    pub code_ptr: Ptr,
+    /// Length of the original bytecode.
+    pub code_len: u32,
+    /// Value sent to the contract.
+    pub call_value: U256,
+    /// Address of the executing contract.
+    pub target_address: Address,
+    /// Address of the caller.
+    pub caller: Address,
+}
+
+/// The layout of the [`Env`](revm_primitives::Env) fields read by compiled code.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Env {
+    /// The configuration.
+    pub cfg: CfgEnv,
+    /// The block.
+    pub block: BlockEnv,
+    /// The transaction.
+    pub tx: TxEnv,
+}
+
+/// The layout of the [`CfgEnv`](revm_primitives::CfgEnv) fields read by compiled code.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CfgEnv {
+    /// The chain ID.
+    pub chain_id: u64,
+}
+
+/// The layout of the [`BlockEnv`](revm_primitives::BlockEnv) fields read by compiled code.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockEnv {
+    /// The block number.
+    pub number: U256,
+    /// The block timestamp.
+    pub timestamp: U256,
+    /// The block gas limit.
+    pub gas_limit: U256,
+    /// The block base fee.
+    pub basefee: U256,
+    /// The beneficiary of the block.
+    pub coinbase: Address,
+}
+
+/// The layout of the [`TxEnv`](revm_primitives::TxEnv) fields read by compiled code.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TxEnv {
+    /// The caller of the transaction.
+    pub caller: Address,
+}
+
+// The layouts are baked into the compiled modules, so they must never change.
+const _: () = {
+    assert!(size_of::<Gas>() == 24);
+    assert!(offset_of!(Gas, limit) == 0);
+    assert!(offset_of!(Gas, remaining) == 8);
+
+    assert!(size_of::<SharedMemory>() == 16);
+    assert!(offset_of!(SharedMemory, buffer) + offset_of!(Vec, ptr) == 4);
+    assert!(offset_of!(SharedMemory, buffer) + offset_of!(Vec, len) == 8);
+    assert!(offset_of!(SharedMemory, last_checkpoint) == 12);
+
+    assert!(size_of::<FunctionReturnFrame>() == 8);
+    assert!(size_of::<FunctionStack>() == 16);
+    assert!(offset_of!(FunctionStack, current_code_idx) == 12);
+
+    assert!(size_of::<EvmContext>() == 32);
+    assert!(offset_of!(EvmContext, memory) == 0);
+    assert!(offset_of!(EvmContext, return_data) + offset_of!(Slice, len) == 8);
+    assert!(offset_of!(EvmContext, func_stack) == 12);
+    assert!(offset_of!(EvmContext, opcode_counts) == 16);
+    assert!(offset_of!(EvmContext, step_budget) == 20);
+    assert!(offset_of!(EvmContext, resume_at) == 24);
+    assert!(offset_of!(EvmContext, is_static) == 28);
+
+    assert!(size_of::<ContractView>() == 88);
+    assert!(offset_of!(ContractView, input_len) == 4);
+    assert!(offset_of!(ContractView, call_value) == 16);
+    assert!(offset_of!(ContractView, target_address) == 48);
+    assert!(offset_of!(ContractView, caller) == 68);
+
+    assert!(size_of::<Env>() == 184);
+    assert!(offset_of!(Env, block) + offset_of!(BlockEnv, number) == 8);
+    assert!(offset_of!(Env, block) + offset_of!(BlockEnv, coinbase) == 136);
+    assert!(offset_of!(Env, tx) + offset_of!(TxEnv, caller) == 160);
+};
@@ -2,6 +2,10 @@
 #![cfg_attr(not(test), warn(unused_extern_crates))]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
+
 use codegen::ir::Function;
 use cranelift::{
     codegen::ir::{FuncRef, StackSlot},
@@ -12,8 +16,9 @@ use cranelift_module::{DataDescription, FuncId, FuncOrDataId, Linkage, Module, M
 use cranelift_object::{ObjectBuilder, ObjectModule};
 use pretty_clif::CommentWriter;
 use revmc_backend::{
-    eyre::eyre, Backend, BackendTypes, Builder, OptimizationLevel, Result, TailCallKind,
-    TypeMethods, U256,
+    eyre::{ensure, eyre},
+    Backend, BackendTypes, Builder, OptimizationLevel, Result, TailCallKind, Target, TypeMethods,
+    U256,
 };
 use std::{
     collections::HashMap,
@@ -21,6 +26,7 @@ use std::{
     path::Path,
     sync::{Arc, RwLock},
 };
+use target_lexicon::Triple;
 
 mod pretty_clif;
 
@@ -47,8 +53,11 @@ pub struct EvmCraneliftBackend {
     symbols: Symbols,
 
     opt_level: OptimizationLevel,
+    target: Target,
     comments: CommentWriter,
     functions: Vec<FuncId>,
+    /// The size of the machine code of each defined function.
+    code_sizes: HashMap<FuncId, usize>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -67,17 +76,37 @@ impl EvmCraneliftBackend {
     /// [`is_supported`](Self::is_supported).
     #[track_caller]
     pub fn new(aot: bool, opt_level: OptimizationLevel) -> Self {
+        Self::new_for_target(aot, opt_level, &Target::Native).unwrap()
+    }
+
+    /// Creates a new instance of the compiler for the given target.
+    ///
+    /// The target CPU is one of the Cranelift ISA presets, which follow the LLVM CPU names, and
+    /// the features are LLVM-style, such as `+sse4.2,-avx`.
+    ///
+    /// Only AOT compilation supports targets other than [`Target::Native`].
+    pub fn new_for_target(
+        aot: bool,
+        opt_level: OptimizationLevel,
+        target: &Target,
+    ) -> Result<Self> {
+        ensure!(
+            aot || matches!(target, Target::Native),
+            "JIT compilation is only supported for the host machine"
+        );
         let symbols = Symbols::new();
-        let module = ModuleWrapper::new(aot, opt_level, &symbols).unwrap();
-        Self {
+        let module = ModuleWrapper::new(aot, opt_level, target, &symbols)?;
+        Ok(Self {
             builder_context: FunctionBuilderContext::new(),
             ctx: module.get().make_context(),
             module,
             symbols,
             opt_level,
+            target: target.clone(),
             comments: CommentWriter::new(),
             functions: Vec::new(),
-        }
+            code_sizes: HashMap::new(),
+        })
     }
 
     fn finish_module(&mut self) -> Result<Option<ObjectModule>> {
@@ -92,7 +121,7 @@ impl EvmCraneliftBackend {
                 None
             }
             ModuleWrapper::Aot(_) => {
-                let new = ModuleWrapper::new_aot(self.opt_level)?;
+                let new = ModuleWrapper::new_aot(self.opt_level, &self.target)?;
                 let ModuleWrapper::Aot(old) = std::mem::replace(&mut self.module, new) else {
                     unreachable!()
                 };
@@ -168,6 +197,10 @@ impl Backend for EvmCraneliftBackend {
         self.module.is_aot()
     }
 
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
     fn function_name_is_unique(&self, name: &str) -> bool {
         self.module.get().get_name(name).is_none()
     }
@@ -225,10 +258,26 @@ impl Backend for EvmCraneliftBackend {
         Ok((builder, id))
     }
 
+    fn declare_function(
+        &mut self,
+        name: &str,
+        ret: Option<Self::Type>,
+        params: &[Self::Type],
+        linkage: revmc_backend::Linkage,
+    ) -> Result<FuncId> {
+        let mut signature = self.module.get().make_signature();
+        signature.returns.extend(ret.map(AbiParam::new));
+        signature.params.extend(params.iter().copied().map(AbiParam::new));
+        let id =
+            self.module.get_mut().declare_function(name, convert_linkage(linkage), &signature)?;
+        Ok(id)
+    }
+
     fn verify_module(&mut self) -> Result<()> {
         Ok(())
     }
 
+    #[cfg_attr(feature = "tracing", instrument(name = "codegen", level = "debug", skip_all))]
     fn optimize_module(&mut self) -> Result<()> {
         // Define the function to jit. This finishes compilation, although
         // there may be outstanding relocations to perform. Currently, jit
@@ -237,6 +286,9 @@ impl Backend for EvmCraneliftBackend {
         // function below.
         for &id in &self.functions {
             self.module.get_mut().define_function(id, &mut self.ctx)?;
+            if let Some(code) = self.ctx.compiled_code() {
+                self.code_sizes.insert(id, code.code_info().total_size as usize);
+            }
         }
         self.functions.clear();
 
@@ -252,6 +304,7 @@ impl Backend for EvmCraneliftBackend {
         Ok(())
     }
 
+    #[cfg_attr(feature = "tracing", instrument(name = "codegen", level = "debug", skip_all))]
     fn write_object<W: std::io::Write>(&mut self, w: W) -> Result<()> {
         let module =
             self.finish_module()?.ok_or_else(|| eyre!("cannot write object in JIT mode"))?;
@@ -264,6 +317,10 @@ impl Backend for EvmCraneliftBackend {
         self.module.get_finalized_function(id).map(|ptr| ptr as usize)
     }
 
+    fn function_size(&self, id: Self::FuncId) -> Option<usize> {
+        self.code_sizes.get(&id).copied()
+    }
+
     unsafe fn free_function(&mut self, id: Self::FuncId) -> Result<()> {
         // This doesn't exist yet.
         let _ = id;
@@ -271,6 +328,7 @@ impl Backend for EvmCraneliftBackend {
     }
 
     unsafe fn free_all_functions(&mut self) -> Result<()> {
+        self.code_sizes.clear();
         self.finish_module().map(drop)
     }
 }
@@ -744,6 +802,11 @@ impl<'a> Builder for EvmCraneliftBuilder<'a> {
         self.bcx.call_memcpy(config, dst, src, len)
     }
 
+    fn assume(&mut self, cond: Self::Value) {
+        // Cranelift has no equivalent.
+        let _ = cond;
+    }
+
     fn unreachable(&mut self) {
         self.bcx.ins().trap(TrapCode::user(0).unwrap());
     }
@@ -869,9 +932,14 @@ enum ModuleWrapper {
 }
 
 impl ModuleWrapper {
-    fn new(aot: bool, opt_level: OptimizationLevel, symbols: &Symbols) -> Result<Self> {
+    fn new(
+        aot: bool,
+        opt_level: OptimizationLevel,
+        target: &Target,
+        symbols: &Symbols,
+    ) -> Result<Self> {
         if aot {
-            Self::new_aot(opt_level)
+            Self::new_aot(opt_level, target)
         } else {
             Self::new_jit(opt_level, symbols.clone())
         }
@@ -886,10 +954,10 @@ impl ModuleWrapper {
         Ok(Self::Jit(JITModule::new(builder)))
     }
 
-    fn new_aot(opt_level: OptimizationLevel) -> Result<Self> {
+    fn new_aot(opt_level: OptimizationLevel, target: &Target) -> Result<Self> {
         let mut flag_builder = settings::builder();
         flag_builder.set("opt_level", opt_level_flag(opt_level))?;
-        let isa_builder = cranelift_native::builder().map_err(|s| eyre!(s))?;
+        let isa_builder = isa_builder(target)?;
         let isa = isa_builder.finish(settings::Flags::new(flag_builder))?;
 
         let builder =
@@ -955,6 +1023,28 @@ fn convert_linkage(linkage: revmc_backend::Linkage) -> Linkage {
     }
 }
 
+/// Returns the ISA builder for the given target.
+fn isa_builder(target: &Target) -> Result<isa::Builder> {
+    let Target::Triple { triple, cpu, features } = target else {
+        return cranelift_native::builder().map_err(|s| eyre!(s));
+    };
+    let triple = triple.parse::<Triple>().map_err(|e| eyre!("invalid target triple: {e}"))?;
+    let mut builder = isa::lookup(triple)?;
+    if let Some(cpu) = cpu {
+        builder.enable(cpu).map_err(|e| eyre!("unsupported target CPU `{cpu}`: {e}"))?;
+    }
+    for feature in features.iter().flat_map(|f| f.split(',')).filter(|f| !f.is_empty()) {
+        let (name, value) = match feature.strip_prefix('-') {
+            Some(name) => (name, "false"),
+            None => (feature.strip_prefix('+').unwrap_or(feature), "true"),
+        };
+        builder
+            .set(&format!("has_{}", name.replace('.', "")), value)
+            .map_err(|e| eyre!("unsupported target feature `{feature}`: {e}"))?;
+    }
+    Ok(builder)
+}
+
 fn opt_level_flag(opt_level: OptimizationLevel) -> &'static str {
     match opt_level {
         OptimizationLevel::None => "none",
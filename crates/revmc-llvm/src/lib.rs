@@ -1,10 +1,32 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(not(test), warn(unused_extern_crates))]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+// Some values are only computed to be logged.
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables, unused_assignments))]
 
+#[cfg(feature = "tracing")]
 #[macro_use]
 extern crate tracing;
 
+/// No-op stand-ins for the `tracing` macros, used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[macro_use]
+#[allow(unused_macros)]
+mod no_tracing {
+    macro_rules! trace {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! debug {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! warn {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! error {
+        ($($t:tt)*) => {};
+    }
+}
+
 use inkwell::{
     attributes::{Attribute, AttributeLoc},
     basic_block::BasicBlock,
@@ -13,7 +35,8 @@ use inkwell::{
     passes::PassBuilderOptions,
     support::error_handling::install_fatal_error_handler,
     targets::{
-        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+        ByteOrdering, CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
+        TargetTriple,
     },
     types::{
         AnyType, AnyTypeEnum, BasicType, BasicTypeEnum, FunctionType, IntType, PointerType,
@@ -65,6 +88,10 @@ pub struct EvmLlvmBackend<'ctx> {
     module: Module<'ctx>,
     exec_engine: Option<ExecutionEngine<'ctx>>,
     machine: TargetMachine,
+    target: revmc_backend::Target,
+    target_info: TargetInfo,
+    reloc_mode: RelocMode,
+    code_model: CodeModel,
 
     ty_void: VoidType<'ctx>,
     ty_ptr: PointerType<'ctx>,
@@ -96,7 +123,10 @@ impl<'ctx> EvmLlvmBackend<'ctx> {
     }
 
     /// Creates a new LLVM backend for the given target.
-    #[instrument(name = "new_llvm_backend", level = "debug", skip_all)]
+    #[cfg_attr(
+        feature = "tracing",
+        instrument(name = "new_llvm_backend", level = "debug", skip_all)
+    )]
     pub fn new_for_target(
         cx: &'ctx Context,
         aot: bool,
@@ -107,20 +137,14 @@ impl<'ctx> EvmLlvmBackend<'ctx> {
 
         let opt_level = convert_opt_level(opt_level);
 
-        let target_info = TargetInfo::new(target)?;
+        let target_info = TargetInfo::new(target)?.into_owned();
+        let target_config = target.clone();
         let target = &target_info.target;
-        let machine = target
-            .create_target_machine(
-                &target_info.triple,
-                &target_info.cpu,
-                &target_info.features,
-                opt_level,
-                RelocMode::PIC,
-                if aot { CodeModel::Default } else { CodeModel::JITDefault },
-            )
-            .ok_or_else(|| eyre::eyre!("failed to create target machine"))?;
+        let reloc_mode = RelocMode::PIC;
+        let code_model = if aot { CodeModel::Default } else { CodeModel::JITDefault };
+        let machine = create_target_machine(&target_info, opt_level, reloc_mode, code_model)?;
 
-        let module = create_module(cx, &machine)?;
+        let module = create_module(cx, &machine, reloc_mode)?;
 
         let exec_engine = if aot {
             None
@@ -154,6 +178,10 @@ impl<'ctx> EvmLlvmBackend<'ctx> {
             module,
             exec_engine,
             machine,
+            target: target_config,
+            target_info,
+            reloc_mode,
+            code_model,
             ty_void,
             ty_i1,
             ty_i8,
@@ -176,6 +204,70 @@ impl<'ctx> EvmLlvmBackend<'ctx> {
         self.cx
     }
 
+    /// Sets the relocation mode of the emitted code.
+    ///
+    /// Must be called before any functions are translated.
+    ///
+    /// Defaults to [`RelocMode::PIC`], which is required to link AOT objects into shared
+    /// libraries on most targets.
+    pub fn set_reloc_mode(&mut self, reloc_mode: RelocMode) -> Result<()> {
+        self.reloc_mode = reloc_mode;
+        self.reset_target_machine()
+    }
+
+    /// Sets the code model of the emitted code.
+    ///
+    /// Must be called before any functions are translated.
+    ///
+    /// Defaults to [`CodeModel::Default`] in AOT mode and [`CodeModel::JITDefault`] in JIT mode.
+    pub fn set_code_model(&mut self, code_model: CodeModel) -> Result<()> {
+        self.code_model = code_model;
+        self.reset_target_machine()
+    }
+
+    /// Sets the target triple, CPU and features to emit code for.
+    ///
+    /// This can be used to cross-compile AOT objects for a machine other than the host. Missing
+    /// `cpu` and `features` default to the target's generic ones.
+    ///
+    /// Must be called before any functions are translated. Only supported in AOT mode.
+    pub fn set_target(
+        &mut self,
+        triple: &str,
+        cpu: Option<String>,
+        features: Option<String>,
+    ) -> Result<()> {
+        if !self.aot {
+            return Err(eyre::eyre!("cannot change the target of a JIT backend"));
+        }
+        let target = revmc_backend::Target::new(triple, cpu, features);
+        self.target_info = TargetInfo::new(&target)?.into_owned();
+        self.target = target;
+        self.reset_target_machine()
+    }
+
+    /// Re-creates the target machine and the module after a change in the target configuration.
+    fn reset_target_machine(&mut self) -> Result<()> {
+        if !self.functions.is_empty() {
+            return Err(eyre::eyre!(
+                "cannot change the target configuration after translating functions"
+            ));
+        }
+        self.machine = create_target_machine(
+            &self.target_info,
+            self.opt_level,
+            self.reloc_mode,
+            self.code_model,
+        )?;
+        self.ty_isize = self.cx.ptr_sized_int_type(&self.machine.get_target_data(), None);
+        // The JIT module is owned by the execution engine, and its target cannot change.
+        if self.aot {
+            self.clear_module();
+            self.module = create_module(self.cx, &self.machine, self.reloc_mode)?;
+        }
+        Ok(())
+    }
+
     fn exec_engine(&self) -> &ExecutionEngine<'ctx> {
         assert!(!self.aot, "requested JIT execution engine on AOT");
         self.exec_engine.as_ref().expect("missing JIT execution engine")
@@ -285,6 +377,14 @@ impl<'ctx> Backend for EvmLlvmBackend<'ctx> {
         self.aot
     }
 
+    fn target(&self) -> &revmc_backend::Target {
+        &self.target
+    }
+
+    fn is_little_endian(&self) -> bool {
+        self.machine.get_target_data().get_byte_ordering() == ByteOrdering::LittleEndian
+    }
+
     fn function_name_is_unique(&self, name: &str) -> bool {
         self.module.get_function(name).is_none()
     }
@@ -333,6 +433,7 @@ impl<'ctx> Backend for EvmLlvmBackend<'ctx> {
         self.module.verify().map_err(error_msg)
     }
 
+    #[cfg_attr(feature = "tracing", instrument(name = "llvm_opt", level = "debug", skip_all))]
     fn optimize_module(&mut self) -> Result<()> {
         // From `opt --help`, `-passes`.
         let passes = match self.opt_level {
@@ -345,6 +446,7 @@ impl<'ctx> Backend for EvmLlvmBackend<'ctx> {
         self.module.run_passes(passes, &self.machine, opts).map_err(error_msg)
     }
 
+    #[cfg_attr(feature = "tracing", instrument(name = "codegen", level = "debug", skip_all))]
     fn write_object<W: std::io::Write>(&mut self, mut w: W) -> Result<()> {
         let buffer = self
             .machine
@@ -354,12 +456,19 @@ impl<'ctx> Backend for EvmLlvmBackend<'ctx> {
         Ok(())
     }
 
+    #[cfg_attr(feature = "tracing", instrument(name = "codegen", level = "debug", skip_all))]
     fn jit_function(&mut self, id: Self::FuncId) -> Result<usize> {
         let name = self.id_to_name(id);
         let addr = self.exec_engine().get_function_address(name)?;
         Ok(addr)
     }
 
+    fn function_size(&self, id: Self::FuncId) -> Option<usize> {
+        // MCJIT does not report the size of the emitted machine code.
+        let _ = id;
+        None
+    }
+
     unsafe fn free_function(&mut self, id: Self::FuncId) -> Result<()> {
         let name = self.id_to_name(id);
         let function = self.exec_engine().get_function_value(name)?;
@@ -373,7 +482,7 @@ impl<'ctx> Backend for EvmLlvmBackend<'ctx> {
         if let Some(exec_engine) = &self.exec_engine {
             exec_engine.remove_module(&self.module).map_err(|e| Error::msg(e.to_string()))?;
         }
-        self.module = create_module(self.cx, &self.machine)?;
+        self.module = create_module(self.cx, &self.machine, self.reloc_mode)?;
         if self.exec_engine.is_some() {
             self.exec_engine =
                 Some(self.module.create_jit_execution_engine(self.opt_level).map_err(error_msg)?);
@@ -388,7 +497,9 @@ impl Drop for EvmLlvmBackend<'_> {
     }
 }
 
-/// Cached target information for the host machine.
+/// Target information used to create the target machine.
+///
+/// The host's information is cached.
 #[derive(Debug)]
 struct TargetInfo {
     triple: TargetTriple,
@@ -779,6 +890,20 @@ impl Builder for EvmLlvmBuilder<'_, '_> {
         self.set_branch_weights(inst, weights);
     }
 
+    fn brif_weighted(
+        &mut self,
+        cond: Self::Value,
+        then_block: Self::BasicBlock,
+        else_block: Self::BasicBlock,
+        weights: [u32; 2],
+    ) {
+        let inst = self
+            .bcx
+            .build_conditional_branch(cond.into_int_value(), then_block, else_block)
+            .unwrap();
+        self.set_branch_weights(inst, weights);
+    }
+
     fn switch(
         &mut self,
         index: Self::Value,
@@ -1051,6 +1176,13 @@ impl Builder for EvmLlvmBuilder<'_, '_> {
         self.memcpy_inner(dst, src, len, true);
     }
 
+    fn assume(&mut self, cond: Self::Value) {
+        let assume = self.get_or_add_function("llvm.assume", |this| {
+            this.ty_void.fn_type(&[this.ty_i1.into()], false)
+        });
+        self.bcx.build_call(assume, &[cond.into()], "").unwrap();
+    }
+
     fn unreachable(&mut self) {
         self.bcx.build_unreachable().unwrap();
     }
@@ -1172,22 +1304,47 @@ fn init_() -> Result<()> {
     Ok(())
 }
 
-fn create_module<'ctx>(cx: &'ctx Context, machine: &TargetMachine) -> Result<Module<'ctx>> {
+fn create_target_machine(
+    target_info: &TargetInfo,
+    opt_level: OptimizationLevel,
+    reloc_mode: RelocMode,
+    code_model: CodeModel,
+) -> Result<TargetMachine> {
+    target_info
+        .target
+        .create_target_machine(
+            &target_info.triple,
+            &target_info.cpu,
+            &target_info.features,
+            opt_level,
+            reloc_mode,
+            code_model,
+        )
+        .ok_or_else(|| eyre::eyre!("failed to create target machine"))
+}
+
+fn create_module<'ctx>(
+    cx: &'ctx Context,
+    machine: &TargetMachine,
+    reloc_mode: RelocMode,
+) -> Result<Module<'ctx>> {
     let module_name = "evm";
     let module = cx.create_module(module_name);
     module.set_source_file_name(module_name);
     module.set_data_layout(&machine.get_target_data().get_data_layout());
     module.set_triple(&machine.get_triple());
-    module.add_basic_value_flag(
-        "PIC Level",
-        FlagBehavior::Error, // TODO: Min
-        cx.i32_type().const_int(2, false),
-    );
-    module.add_basic_value_flag(
-        "RtLibUseGOT",
-        FlagBehavior::Warning,
-        cx.i32_type().const_int(1, false),
-    );
+    if reloc_mode == RelocMode::PIC {
+        module.add_basic_value_flag(
+            "PIC Level",
+            FlagBehavior::Error, // TODO: Min
+            cx.i32_type().const_int(2, false),
+        );
+        module.add_basic_value_flag(
+            "RtLibUseGOT",
+            FlagBehavior::Warning,
+            cx.i32_type().const_int(1, false),
+        );
+    }
     Ok(module)
 }
 
@@ -1255,6 +1412,8 @@ fn convert_attribute(bcx: &EvmLlvmBuilder<'_, '_>, attr: revmc_backend::Attribut
         OurAttr::AlwaysInline => ("alwaysinline", AttrValue::Enum(1)),
         OurAttr::NoInline => ("noinline", AttrValue::Enum(1)),
         OurAttr::Speculatable => ("speculatable", AttrValue::Enum(1)),
+        OurAttr::OptimizeForSize => ("optsize", AttrValue::Enum(1)),
+        OurAttr::MinSize => ("minsize", AttrValue::Enum(1)),
 
         OurAttr::NoAlias => ("noalias", AttrValue::Enum(1)),
         OurAttr::NoCapture => ("nocapture", AttrValue::Enum(1)),
@@ -0,0 +1,415 @@
+//! Standalone bytecode analysis.
+//!
+//! [`analyze`] runs the same analysis passes as [`EvmCompiler`](crate::EvmCompiler) and returns
+//! the results as plain owned data, for static analysis tooling that doesn't need to compile
+//! anything.
+//!
+//! # Stability
+//!
+//! The types and functions in this module follow semver. All the structs are `#[non_exhaustive]`,
+//! so new fields and configuration options may be added in minor releases.
+//!
+//! The following are **not** covered by semver, as they depend on the analysis passes, which may
+//! become more precise over time:
+//! - the instructions added by the analysis, like inlined copies of internal functions;
+//! - the exact instructions marked as dead code, and where basic blocks are split;
+//! - whether [`Analysis::max_stack_height`] can be proven;
+//! - the output of [`Analysis::to_dot`], which is meant for humans.
+
+use super::{stack_io, validate_eof, AnalysisConfig as PassConfig, Bytecode, InstFlags, Opcode};
+use revm_interpreter::opcode as op;
+use revm_primitives::{Bytes, Eof, SpecId, EOF_MAGIC_BYTES};
+use revmc_backend::Result;
+use rustc_hash::FxHashSet;
+use std::{borrow::Cow, fmt::Write, ops::Range};
+
+/// The maximum EVM stack height.
+const STACK_CAP: usize = 1024;
+
+/// [`analyze`] configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AnalysisConfig {
+    /// Overrides the base gas cost of each opcode.
+    ///
+    /// Defaults to `None`, the costs of the given [`SpecId`].
+    pub base_gas: Option<[u16; 256]>,
+    /// The maximum number of instructions of an internal function to inline. `0` disables
+    /// inlining.
+    ///
+    /// Defaults to `0`.
+    pub inline_threshold: usize,
+}
+
+/// Analyzes the given bytecode.
+///
+/// EOF bytecode is decoded and validated if `spec_id` is Osaka or later.
+pub fn analyze(code: &[u8], spec_id: SpecId, config: &AnalysisConfig) -> Result<Analysis> {
+    let mut eof = None;
+    if spec_id.is_enabled_in(SpecId::OSAKA) && code.starts_with(&EOF_MAGIC_BYTES) {
+        let decoded = Eof::decode(Bytes::copy_from_slice(code))?;
+        validate_eof(&decoded)?;
+        eof = Some(Cow::Owned(decoded));
+    }
+    let mut bytecode = Bytecode::new(code, eof, spec_id);
+    bytecode.analyze(PassConfig {
+        base_gas: config.base_gas.as_ref(),
+        inline_threshold: config.inline_threshold,
+        dead_code: false,
+        stack_observable: true,
+        map_slot: false,
+        custom_opcodes: None,
+        selector: None,
+    })?;
+    Ok(Analysis::new(&bytecode))
+}
+
+/// The result of [`analyze`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Analysis {
+    /// The [`SpecId`] the bytecode was analyzed with.
+    pub spec_id: SpecId,
+    /// Whether the bytecode is EOF.
+    ///
+    /// In EOF, all program counters are relative to the start of the first code section.
+    pub is_eof: bool,
+    /// All the instructions, including dead code and the instructions added by the analysis.
+    pub instructions: Vec<Instruction>,
+    /// The basic blocks of the live instructions, in instruction order. The entry block is the
+    /// first one.
+    pub blocks: Vec<BasicBlock>,
+    /// Valid `JUMP`/`JUMPI` destinations: bit `pc % 8` of byte `pc / 8` is set if `pc` is one.
+    /// Empty in EOF.
+    pub jumpdests: Vec<u8>,
+    /// The program counter ranges that can never be executed.
+    pub dead_code: Vec<Range<usize>>,
+    /// The maximum stack height reachable from the entry point, if it can be proven.
+    ///
+    /// This is `None` if the bytecode has dynamic jumps. In EOF, this is the maximum of the
+    /// validated stack heights of all code sections.
+    pub max_stack_height: Option<usize>,
+}
+
+/// A single instruction of an [`Analysis`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Instruction {
+    /// The program counter.
+    ///
+    /// Copies of inlined instructions have the program counter of the original instruction, and
+    /// the padding `STOP` has the length of the code.
+    pub pc: usize,
+    /// The opcode byte.
+    pub opcode: u8,
+    /// The immediate data. Empty if none, or if it is truncated by the end of the code.
+    pub immediate: Vec<u8>,
+    /// The base gas cost.
+    pub base_gas: u16,
+    /// The index of the basic block containing this instruction. `None` for dead code.
+    pub block: Option<usize>,
+    /// Whether the instruction can never be executed.
+    pub is_dead_code: bool,
+    /// Whether this is a `JUMP`/`JUMPI` whose target is known statically.
+    pub is_static_jump: bool,
+    /// Whether this is a `JUMP`/`JUMPI` whose target is known to be invalid.
+    pub is_invalid_jump: bool,
+    /// Whether the instruction is part of an inlined copy of an internal function.
+    pub is_inlined: bool,
+    /// Whether the instruction is not in the original bytecode, like the copies of inlined
+    /// functions and the padding `STOP`.
+    pub is_synthetic: bool,
+}
+
+impl Instruction {
+    /// Returns the opcode and its immediate data.
+    #[inline]
+    pub fn opcode(&self) -> Opcode<'_> {
+        Opcode {
+            opcode: self.opcode,
+            immediate: Some(&self.immediate[..]).filter(|i| !i.is_empty()),
+        }
+    }
+}
+
+/// A basic block of an [`Analysis`]: a sequence of instructions that is only entered at the
+/// start and only exited at the end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BasicBlock {
+    /// The range of [`Analysis::instructions`] in this block.
+    pub instructions: Range<usize>,
+    /// The sum of the base gas costs of the instructions. This doesn't include dynamic gas.
+    pub gas: u64,
+    /// The indices of the blocks that can jump or fall through to this block, sorted.
+    pub predecessors: Vec<usize>,
+    /// The indices of the blocks that this block can jump or fall through to, sorted.
+    ///
+    /// Dynamic jumps can reach all the `JUMPDEST` blocks. EOF `CALLF`s fall through and don't
+    /// have an edge to the called section.
+    pub successors: Vec<usize>,
+}
+
+impl Analysis {
+    fn new(bytecode: &Bytecode<'_>) -> Self {
+        let is_eof = bytecode.is_eof();
+        let n_original = bytecode.pc_to_inst.len();
+
+        let mut instructions = bytecode
+            .iter_all_insts()
+            .map(|(inst, data)| Instruction {
+                pc: if inst < n_original || data.flags.contains(InstFlags::INLINED) {
+                    data.pc as usize
+                } else {
+                    bytecode.code.len()
+                },
+                opcode: data.opcode,
+                immediate: bytecode.get_imm(data).unwrap_or_default().to_vec(),
+                base_gas: data.base_gas,
+                block: None,
+                is_dead_code: data.is_dead_code(),
+                is_static_jump: data.is_legacy_static_jump(),
+                is_invalid_jump: data.flags.contains(InstFlags::INVALID_JUMP),
+                is_inlined: data.flags.contains(InstFlags::INLINED),
+                is_synthetic: inst >= n_original,
+            })
+            .collect::<Vec<_>>();
+
+        // Split the live instructions into basic blocks.
+        let mut is_leader = vec![false; instructions.len()];
+        is_leader[0] = true;
+        if let Some(eof) = &bytecode.eof {
+            for section in 0..eof.body.code_section.len() {
+                is_leader[bytecode.eof_section_inst(section)] = true;
+            }
+        }
+        for (inst, data) in bytecode.iter_insts() {
+            if data.is_reachable_jumpdest(is_eof, bytecode.has_dynamic_jumps()) {
+                is_leader[inst] = true;
+            }
+            if data.is_branching(is_eof) && inst + 1 < is_leader.len() {
+                is_leader[inst + 1] = true;
+            }
+        }
+        let mut blocks = Vec::<BasicBlock>::new();
+        let mut current = None;
+        for (inst, data) in bytecode.iter_all_insts() {
+            if data.is_dead_code() {
+                current = None;
+                continue;
+            }
+            if is_leader[inst] || current.is_none() {
+                current = Some(blocks.len());
+                blocks.push(BasicBlock {
+                    instructions: inst..inst,
+                    gas: 0,
+                    predecessors: Vec::new(),
+                    successors: Vec::new(),
+                });
+            }
+            let block = current.unwrap();
+            blocks[block].instructions.end = inst + 1;
+            blocks[block].gas += data.base_gas as u64;
+            instructions[inst].block = Some(block);
+            if data.is_branching(is_eof) {
+                current = None;
+            }
+        }
+
+        // Connect the blocks.
+        let dynamic_targets = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| {
+                let first = &instructions[block.instructions.start];
+                first.opcode == op::JUMPDEST && !first.is_inlined
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        for block in 0..blocks.len() {
+            let last = blocks[block].instructions.end - 1;
+            let data = bytecode.inst(last);
+            let mut targets = Vec::new();
+            if is_eof && data.is_eof_jump() {
+                targets.extend(bytecode.iter_rjump_target_insts(data).map(|(_, inst)| inst));
+            } else if data.is_legacy_jump() && !data.flags.contains(InstFlags::INVALID_JUMP) {
+                if data.is_legacy_static_jump() {
+                    targets.push(data.data as usize);
+                } else {
+                    targets.extend(dynamic_targets.iter().map(|&b| blocks[b].instructions.start));
+                }
+            }
+            let is_unconditional = matches!(data.opcode, op::JUMP | op::RJUMP);
+            if !data.is_diverging(is_eof) && !is_unconditional {
+                targets.push(last + 1);
+            }
+            let mut successors =
+                targets.into_iter().filter_map(|inst| instructions[inst].block).collect::<Vec<_>>();
+            successors.sort_unstable();
+            successors.dedup();
+            blocks[block].successors = successors;
+        }
+        for block in 0..blocks.len() {
+            for successor in blocks[block].successors.clone() {
+                blocks[successor].predecessors.push(block);
+            }
+        }
+
+        let mut jumpdests = Vec::new();
+        if !is_eof {
+            jumpdests = vec![0u8; bytecode.code.len().div_ceil(8)];
+            for inst in &instructions[..n_original] {
+                if inst.opcode == op::JUMPDEST {
+                    jumpdests[inst.pc / 8] |= 1 << (inst.pc % 8);
+                }
+            }
+        }
+
+        let mut dead_code = Vec::<Range<usize>>::new();
+        for (i, inst) in instructions[..n_original].iter().enumerate() {
+            if !inst.is_dead_code {
+                continue;
+            }
+            let end = instructions[..n_original].get(i + 1).map_or(bytecode.code.len(), |i| i.pc);
+            match dead_code.last_mut() {
+                Some(last) if last.end == inst.pc => last.end = end,
+                _ => dead_code.push(inst.pc..end),
+            }
+        }
+
+        let max_stack_height = match &bytecode.eof {
+            Some(eof) => eof.body.types_section.iter().map(|t| t.max_stack_size as usize).max(),
+            None if bytecode.has_dynamic_jumps() => None,
+            None => Some(max_stack_height(&instructions, &blocks)),
+        };
+
+        Self {
+            spec_id: bytecode.spec_id,
+            is_eof,
+            instructions,
+            blocks,
+            jumpdests,
+            dead_code,
+            max_stack_height,
+        }
+    }
+
+    /// Returns `true` if `pc` is a valid `JUMP`/`JUMPI` destination.
+    #[inline]
+    pub fn is_jumpdest(&self, pc: usize) -> bool {
+        self.jumpdests.get(pc / 8).is_some_and(|byte| byte & (1 << (pc % 8)) != 0)
+    }
+
+    /// Returns the control flow graph in the Graphviz DOT format.
+    ///
+    /// Each basic block is a node listing its instructions and base gas cost.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::new();
+        s.push_str("digraph cfg {\n");
+        s.push_str("    node [shape=box, fontname=monospace];\n");
+        for (i, block) in self.blocks.iter().enumerate() {
+            write!(s, "    b{i} [label=\"").unwrap();
+            for inst in &self.instructions[block.instructions.clone()] {
+                write!(s, "{:04x}: {}\\l", inst.pc, inst.opcode()).unwrap();
+            }
+            writeln!(s, "gas: {}\\l\"];", block.gas).unwrap();
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            for successor in &block.successors {
+                writeln!(s, "    b{i} -> b{successor};").unwrap();
+            }
+        }
+        s.push_str("}\n");
+        s
+    }
+}
+
+/// Walks all the paths from the entry block, tracking the stack height at each block entry.
+///
+/// Paths that underflow or overflow the stack stop there, as execution would too.
+fn max_stack_height(instructions: &[Instruction], blocks: &[BasicBlock]) -> usize {
+    let mut max = 0;
+    let mut seen = FxHashSet::default();
+    let mut worklist = vec![(0, 0)];
+    'outer: while let Some((block, mut height)) = worklist.pop() {
+        if !seen.insert((block, height)) {
+            continue;
+        }
+        for inst in &instructions[blocks[block].instructions.clone()] {
+            let (inputs, outputs) = stack_io(inst.opcode);
+            let (inputs, outputs) = (inputs as usize, outputs as usize);
+            if height < inputs || height - inputs + outputs > STACK_CAP {
+                continue 'outer;
+            }
+            height = height - inputs + outputs;
+            max = max.max(height);
+        }
+        worklist.extend(blocks[block].successors.iter().map(|&successor| (successor, height)));
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Compares `actual` to the snapshot file, or overwrites it if `REVMC_BLESS` is set.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/bytecode/snapshots")
+            .join(format!("{name}.dot"));
+        if std::env::var_os("REVMC_BLESS").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert!(expected == actual, "snapshot {name} differs; rerun with REVMC_BLESS=1 to update");
+    }
+
+    fn decode(s: &str) -> Vec<u8> {
+        revm_primitives::hex::decode(s.trim()).unwrap()
+    }
+
+    #[test]
+    fn small() {
+        #[rustfmt::skip]
+        let code = [
+            op::PUSH1, 1, op::PUSH1, 7, op::JUMPI,
+            op::INVALID, op::ADD,
+            op::JUMPDEST, op::STOP,
+        ];
+        let analysis = analyze(&code, SpecId::CANCUN, &AnalysisConfig::default()).unwrap();
+        assert_eq!(analysis.instructions.len(), 7);
+        assert_eq!(analysis.blocks.len(), 3);
+        assert_eq!(analysis.blocks[0].instructions, 0..3);
+        assert_eq!(analysis.blocks[0].gas, 3 + 3 + 10);
+        assert_eq!(analysis.blocks[0].successors, [1, 2]);
+        assert_eq!(analysis.blocks[2].predecessors, [0]);
+        assert_eq!(analysis.dead_code, vec![6..7]);
+        assert!(analysis.is_jumpdest(7));
+        assert!(!analysis.is_jumpdest(6));
+        assert_eq!(analysis.max_stack_height, Some(2));
+    }
+
+    #[test]
+    fn hash_10k() {
+        let code = decode(include_str!("../../../../data/hash_10k.rt.hex"));
+        let analysis = analyze(&code, SpecId::CANCUN, &AnalysisConfig::default()).unwrap();
+        assert!(!analysis.is_eof);
+        assert_eq!(analysis.max_stack_height, None);
+        assert_eq!(analysis.blocks[0].predecessors, [] as [usize; 0]);
+        assert_snapshot("hash_10k", &analysis.to_dot());
+    }
+
+    #[test]
+    fn hash_10k_eof() {
+        let code = decode(include_str!("../../../../data/hash_10k-eof.rt.hex"));
+        let analysis = analyze(&code, SpecId::OSAKA, &AnalysisConfig::default()).unwrap();
+        assert!(analysis.is_eof);
+        assert!(analysis.jumpdests.is_empty());
+        assert!(analysis.dead_code.is_empty());
+        assert!(analysis.max_stack_height.is_some());
+        assert_snapshot("hash_10k-eof", &analysis.to_dot());
+    }
+}
@@ -0,0 +1,159 @@
+use super::{Bytecode, Inst, InstData, InstFlags};
+use revm_interpreter::opcode as op;
+
+impl Bytecode<'_> {
+    /// Skips the logic of pure instructions whose results are never used.
+    ///
+    /// A value is dead if it is immediately popped by `POP`, or, if `stack_observable` is
+    /// `false`, if it is still on the stack at `STOP`, or below the operands of `RETURN`. The
+    /// instructions that produce a dead value are marked as `SKIP_LOGIC`, together with the
+    /// instructions that produce their inputs, as long as all of them are pure and contiguous.
+    ///
+    /// Skipped instructions still belong to their sections, so the gas and the stack bound checks
+    /// are unaffected.
+    #[cfg_attr(feature = "tracing", instrument(name = "dead_values", level = "debug", skip_all))]
+    pub(super) fn eliminate_dead_values(&mut self, stack_observable: bool) {
+        debug_assert!(!self.is_eof());
+
+        let mut killed = Vec::new();
+        for inst in 0..self.insts.len() {
+            let data = &self.insts[inst];
+            if data.is_dead_code() {
+                continue;
+            }
+            match data.opcode {
+                op::POP => {
+                    killed.clear();
+                    if self.kill_value(inst, &mut killed).is_some() {
+                        killed.push(inst);
+                        self.skip_logic(&killed);
+                    }
+                }
+                op::STOP if !stack_observable => self.kill_all_values(inst),
+                op::RETURN if !stack_observable => {
+                    if let Some(cursor) = self.skip_live_values(inst, 2) {
+                        self.kill_all_values(cursor);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Kills the values on the stack right before `cursor`, from the top, until one cannot be
+    /// killed.
+    fn kill_all_values(&mut self, mut cursor: Inst) {
+        let mut killed = Vec::new();
+        loop {
+            killed.clear();
+            let Some(next) = self.kill_value(cursor, &mut killed) else { break };
+            self.skip_logic(&killed);
+            cursor = next;
+        }
+    }
+
+    /// Returns the first instruction that produces the top `n` values on the stack right before
+    /// `cursor`, which are used by the instruction at `cursor`.
+    ///
+    /// Returns `None` if they cannot all be traced back, or if one of them is a copy of a value
+    /// below them, which must then stay alive.
+    fn skip_live_values(&self, mut cursor: Inst, n: usize) -> Option<Inst> {
+        let mut producers = Vec::new();
+        for _ in 0..n {
+            cursor = self.kill_value(cursor, &mut producers)?;
+        }
+        let copies =
+            producers.iter().any(|&inst| matches!(self.insts[inst].opcode, op::DUP1..=op::DUP16));
+        (!copies).then_some(cursor)
+    }
+
+    /// Kills the value on top of the stack right before `cursor` by adding the instructions that
+    /// produce it to `killed`.
+    ///
+    /// Returns the first of these instructions, or `None` if the value cannot be killed.
+    fn kill_value(&self, cursor: Inst, killed: &mut Vec<Inst>) -> Option<Inst> {
+        // Skip over already killed values, which have no effect on the stack.
+        let mut inst = cursor;
+        loop {
+            inst = inst.checked_sub(1)?;
+            if !self.insts[inst].flags.contains(InstFlags::SKIP_LOGIC) {
+                break;
+            }
+        }
+
+        let data = &self.insts[inst];
+        if data.is_dead_code() || data.is_jumpdest() || !data.is_pure() {
+            return None;
+        }
+        killed.push(inst);
+
+        // The duplicated value is a copy, the input is still on the stack.
+        if matches!(data.opcode, op::DUP1..=op::DUP16) {
+            return Some(inst);
+        }
+
+        let (inp, out) = data.stack_io();
+        if out != 1 {
+            return None;
+        }
+        let mut first = inst;
+        for _ in 0..inp {
+            first = self.kill_value(first, killed)?;
+        }
+        Some(first)
+    }
+
+    fn skip_logic(&mut self, insts: &[Inst]) {
+        trace!(?insts, "skipping dead values");
+        for &inst in insts {
+            self.insts[inst].flags |= InstFlags::SKIP_LOGIC;
+        }
+    }
+}
+
+impl InstData {
+    /// Returns `true` if this instruction has no side effects other than on the stack, and its
+    /// gas cost is static.
+    pub(super) fn is_pure(&self) -> bool {
+        let excluded =
+            InstFlags::DISABLED | InstFlags::UNKNOWN | InstFlags::EOF_ONLY | InstFlags::CUSTOM;
+        if self.flags.intersects(excluded) {
+            return false;
+        }
+        matches!(
+            self.opcode,
+            op::ADD
+                | op::MUL
+                | op::SUB
+                | op::DIV
+                | op::SDIV
+                | op::MOD
+                | op::SMOD
+                | op::ADDMOD
+                | op::MULMOD
+                | op::SIGNEXTEND
+                | op::LT
+                | op::GT
+                | op::SLT
+                | op::SGT
+                | op::EQ
+                | op::ISZERO
+                | op::AND
+                | op::OR
+                | op::XOR
+                | op::NOT
+                | op::BYTE
+                | op::SHL
+                | op::SHR
+                | op::SAR
+                | op::ADDRESS
+                | op::CALLER
+                | op::CALLVALUE
+                | op::CALLDATASIZE
+                | op::CODESIZE
+                | op::PC
+                | op::PUSH0..=op::PUSH32
+                | op::DUP1..=op::DUP16
+        )
+    }
+}
@@ -0,0 +1,122 @@
+//! Read-only access to the analysis results, for debugging tools.
+
+use super::{Bytecode, Inst, InstData, InstFlags, Opcode, Section};
+use std::fmt;
+
+impl<'a> Bytecode<'a> {
+    /// Returns the analysis results of all the instructions, including dead code and the
+    /// instructions added by the analysis passes.
+    pub fn inst_infos(&self) -> impl ExactSizeIterator<Item = InstInfo<'_, 'a>> + Clone + '_ {
+        (0..self.insts.len()).map(|inst| InstInfo { bytecode: self, inst })
+    }
+
+    /// Returns the analysis results of the instruction at the given program counter, if any.
+    pub fn inst_info_at_pc(&self, pc: usize) -> Option<InstInfo<'_, 'a>> {
+        let &inst = self.pc_to_inst.get(&u32::try_from(pc).ok()?)?;
+        Some(InstInfo { bytecode: self, inst: inst as Inst })
+    }
+}
+
+/// The analysis results of a single instruction. Returned by [`Bytecode::inst_infos`].
+#[derive(Clone, Copy)]
+pub struct InstInfo<'b, 'a> {
+    bytecode: &'b Bytecode<'a>,
+    inst: Inst,
+}
+
+impl fmt::Debug for InstInfo<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.data().fmt(f)
+    }
+}
+
+impl<'a> InstInfo<'_, 'a> {
+    /// Returns the index of the instruction.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.inst
+    }
+
+    /// Returns the program counter of the instruction.
+    ///
+    /// Copies of inlined instructions have the program counter of the original instruction.
+    #[inline]
+    pub fn pc(&self) -> usize {
+        self.data().pc as usize
+    }
+
+    /// Returns `true` if the instruction is not in the original bytecode, like the copies of
+    /// inlined functions and the padding `STOP`.
+    #[inline]
+    pub fn is_synthetic(&self) -> bool {
+        self.inst >= self.bytecode.pc_to_inst.len()
+    }
+
+    /// Returns the opcode and its immediate data.
+    #[inline]
+    pub fn opcode(&self) -> Opcode<'a> {
+        self.data().to_op_in(self.bytecode)
+    }
+
+    /// Returns the names of the flags set by the analysis.
+    ///
+    /// The flags are an implementation detail of the analysis and may change at any time.
+    pub fn flag_names(&self) -> impl Iterator<Item = &'static str> {
+        self.data().flags.iter_names().map(|(name, _)| name)
+    }
+
+    /// Returns the base gas cost of the instruction.
+    #[inline]
+    pub fn base_gas(&self) -> u16 {
+        self.data().base_gas
+    }
+
+    /// Returns the number of input and output stack elements of the instruction.
+    ///
+    /// This accounts for the jump targets that are resolved at compile time.
+    #[inline]
+    pub fn stack_io(&self) -> (u8, u8) {
+        self.data().stack_io()
+    }
+
+    /// Returns the section that starts at this instruction, if any.
+    #[inline]
+    pub fn section(&self) -> Option<SectionInfo> {
+        let section = self.data().section;
+        (!section.is_empty()).then(|| SectionInfo::from(section))
+    }
+
+    /// Returns the index of the target instruction if this is a `JUMP` or `JUMPI` with a valid
+    /// static target.
+    #[inline]
+    pub fn jump_target(&self) -> Option<usize> {
+        let data = self.data();
+        (data.is_legacy_static_jump() && !data.flags.contains(InstFlags::INVALID_JUMP))
+            .then_some(data.data as usize)
+    }
+
+    #[inline]
+    fn data(&self) -> &'_ InstData {
+        self.bytecode.inst(self.inst)
+    }
+}
+
+/// A section of instructions that are executed sequentially. Returned by [`InstInfo::section`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SectionInfo {
+    /// The total base gas cost of all instructions in the section.
+    pub gas_cost: u32,
+    /// The stack height required to execute the section.
+    pub inputs: u16,
+    /// The maximum stack height growth relative to the stack height at section start.
+    pub max_growth: i16,
+}
+
+impl From<Section> for SectionInfo {
+    #[inline]
+    fn from(section: Section) -> Self {
+        let Section { gas_cost, inputs, max_growth } = section;
+        Self { gas_cost, inputs, max_growth }
+    }
+}
@@ -1,5 +1,6 @@
 use revm_interpreter::{gas, opcode as op};
 use revm_primitives::{spec_to_generic, SpecId};
+use revmc_context::GasParams;
 
 /// Opcode information.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -100,6 +101,26 @@ pub const fn op_info_map(spec_id: SpecId) -> &'static [OpcodeInfo; 256] {
     spec_to_generic!(spec_id, (const { &make_map(<SPEC as revm_primitives::Spec>::SPEC_ID) }))
 }
 
+/// The gas costs to compile bytecode with.
+///
+/// See [`EvmCompiler::gas_table`](crate::EvmCompiler::gas_table).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasTable {
+    /// The base cost of each opcode, which is paid by the compiled code.
+    ///
+    /// Opcodes with a dynamic cost pay the rest at runtime, according to [`params`](Self::params).
+    pub base: [u16; 256],
+    /// The parameters of the dynamic costs.
+    pub params: GasParams,
+}
+
+impl GasTable {
+    /// Returns the Ethereum mainnet gas table for the given `SpecId`.
+    pub fn for_spec(spec_id: SpecId) -> Self {
+        Self { base: op_info_map(spec_id).map(OpcodeInfo::base_gas), params: GasParams::DEFAULT }
+    }
+}
+
 #[allow(unused_mut)]
 const fn make_map(spec_id: SpecId) -> [OpcodeInfo; 256] {
     const DYNAMIC: u16 = OpcodeInfo::DYNAMIC;
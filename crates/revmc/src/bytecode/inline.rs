@@ -0,0 +1,168 @@
+use super::{Bytecode, Inst, InstFlags};
+use revm_interpreter::opcode as op;
+
+/// A call to an internal function that can be inlined.
+#[derive(Clone, Copy, Debug)]
+struct InternalCall {
+    /// The static `JUMP` into the function.
+    call: Inst,
+    /// The function's entry `JUMPDEST`.
+    entry: Inst,
+    /// The dynamic `JUMP` that returns from the function.
+    ret: Inst,
+    /// The `JUMPDEST` that the function returns to.
+    ret_target: Inst,
+}
+
+impl Bytecode<'_> {
+    /// Inlines small internal functions reached by static jumps.
+    ///
+    /// Solidity internal functions are called by pushing the return address, jumping to the
+    /// function entry, and returning with a dynamic `JUMP` to the address left on the stack:
+    ///
+    /// ```text
+    /// PUSH ret ... PUSH fn JUMP   ; call site
+    /// fn: JUMPDEST ... JUMP       ; callee, returns to `ret`
+    /// ret: JUMPDEST ...
+    /// ```
+    ///
+    /// When the return address is provably the value consumed by the callee's return `JUMP`, the
+    /// callee's instructions are copied to the end of the bytecode and the call site jumps to the
+    /// copy instead, whose return `JUMP` then becomes static.
+    ///
+    /// The copies keep the original instructions' base gas and program counters, so gas and `PC`
+    /// are unaffected.
+    #[cfg_attr(feature = "tracing", instrument(name = "inline", level = "debug", skip_all))]
+    pub(super) fn inline_internal_functions(&mut self, threshold: usize) {
+        debug_assert!(!self.is_eof());
+
+        if threshold == 0 {
+            return;
+        }
+
+        let mut calls = Vec::new();
+        for inst in 0..self.insts.len() {
+            if let Some(call) = self.find_internal_call(inst, threshold) {
+                trace!(?call, "found internal call");
+                calls.push(call);
+            }
+        }
+
+        for call in calls {
+            self.inline_internal_call(call);
+        }
+    }
+
+    /// Returns the internal call whose return address is pushed by `push_inst`, if it can be
+    /// inlined.
+    fn find_internal_call(&self, push_inst: Inst, threshold: usize) -> Option<InternalCall> {
+        let push = self.inst(push_inst);
+        if !push.is_push() || push.opcode == op::PUSH0 || push.flags.contains(InstFlags::SKIP_LOGIC)
+        {
+            return None;
+        }
+        let ret_pc = imm_to_usize(self.get_imm(push)?)?;
+        if !self.is_valid_jump(ret_pc) {
+            return None;
+        }
+
+        // Track the position of the return address from the top of the stack, first through the
+        // rest of the call site and then through the callee.
+        let mut pos = 0;
+        let mut call = None;
+        let mut inst = push_inst + 1;
+        loop {
+            let data = self.insts.get(inst)?;
+            if let Some((_, entry)) = call {
+                if inst - entry > threshold {
+                    return None;
+                }
+            }
+
+            if data.opcode == op::JUMP && data.flags.contains(InstFlags::STATIC_JUMP) {
+                if call.is_some() || data.flags.contains(InstFlags::INVALID_JUMP) {
+                    return None;
+                }
+                let entry = data.data as usize;
+                call = Some((inst, entry));
+                inst = entry + 1;
+                continue;
+            }
+            if data.opcode == op::JUMP {
+                let (call, entry) = call?;
+                if pos != 0 {
+                    return None;
+                }
+                let ret_target = self.pc_to_inst(ret_pc);
+                return Some(InternalCall { call, entry, ret: inst, ret_target });
+            }
+            if data.is_jumpdest() || data.is_branching(false) || data.may_suspend(false) {
+                return None;
+            }
+
+            if !data.flags.contains(InstFlags::SKIP_LOGIC) {
+                pos = match data.opcode {
+                    op::DUP1..=op::DUP16 => {
+                        let n = (data.opcode - op::DUP1) as usize;
+                        // Bail if the return address is duplicated.
+                        if pos == n {
+                            return None;
+                        }
+                        pos + 1
+                    }
+                    op::SWAP1..=op::SWAP16 => {
+                        let n = (data.opcode - op::SWAP1 + 1) as usize;
+                        if pos == 0 {
+                            n
+                        } else if pos == n {
+                            0
+                        } else {
+                            pos
+                        }
+                    }
+                    _ => {
+                        let (inp, out) = data.stack_io();
+                        // Bail if the return address is consumed.
+                        if pos < inp as usize {
+                            return None;
+                        }
+                        pos - inp as usize + out as usize
+                    }
+                };
+            }
+            inst += 1;
+        }
+    }
+
+    /// Appends a copy of the callee and redirects the call site to it.
+    fn inline_internal_call(&mut self, call: InternalCall) {
+        let InternalCall { call, entry, ret, ret_target } = call;
+        let start = self.insts.len();
+        for inst in entry..=ret {
+            let mut data = self.insts[inst].clone();
+            data.flags |= InstFlags::INLINED;
+            self.insts.push(data);
+        }
+        debug!(call, entry, len = ret - entry + 1, start, "inlined internal call");
+
+        // Mark the copied entry `JUMPDEST` as reachable, and jump to it.
+        self.insts[start].data = 1;
+        self.insts[call].data = start as u32;
+
+        // Return statically.
+        let copied_ret = self.insts.last_mut().unwrap();
+        copied_ret.flags |= InstFlags::STATIC_JUMP | InstFlags::RETURN_JUMP;
+        copied_ret.data = ret_target as u32;
+        self.insts[ret_target].data = 1;
+    }
+}
+
+fn imm_to_usize(imm: &[u8]) -> Option<usize> {
+    const USIZE_SIZE: usize = std::mem::size_of::<usize>();
+    if imm.len() > USIZE_SIZE {
+        return None;
+    }
+    let mut padded = [0; USIZE_SIZE];
+    padded[USIZE_SIZE - imm.len()..].copy_from_slice(imm);
+    Some(usize::from_be_bytes(padded))
+}
@@ -0,0 +1,318 @@
+use super::{Bytecode, Inst, InstData, InstFlags};
+use revm_interpreter::opcode as op;
+use revm_primitives::U256;
+use rustc_hash::FxHashSet;
+
+/// The maximum number of instructions to simulate before an instruction, see
+/// [`Bytecode::run_before`].
+const MAX_RUN_LEN: usize = 32;
+
+/// The maximum number of instructions to visit when looking for reads of the scratch space.
+const MAX_VISITED: usize = 256;
+
+/// Both words of the scratch space, `0x00..0x20` and `0x20..0x40`, as a bit mask.
+const SCRATCH: u8 = 0b11;
+
+/// The flags of the instructions that are never part of a mapping slot computation.
+const EXCLUDED: InstFlags = InstFlags::SKIP_LOGIC
+    .union(InstFlags::DEAD_CODE)
+    .union(InstFlags::DISABLED)
+    .union(InstFlags::UNKNOWN)
+    .union(InstFlags::EOF_ONLY)
+    .union(InstFlags::CUSTOM);
+
+/// A stack value in a run of instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Const(U256),
+    Unknown,
+}
+
+/// The simulated state right before an instruction, see [`Bytecode::run_before`].
+#[derive(Debug, Default)]
+struct Run {
+    /// The stack, from bottom to top. The values below it are unknown.
+    stack: Vec<Value>,
+    /// The `MSTORE`s of the run, with their offsets.
+    stores: Vec<(Inst, Value)>,
+}
+
+impl Run {
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Unknown)
+    }
+
+    /// Returns the `n`th value from the top of the stack, starting at 0.
+    fn peek(&self, n: usize) -> Value {
+        self.stack.iter().rev().nth(n).copied().unwrap_or(Value::Unknown)
+    }
+
+    /// Returns the `n`th value from the top of the stack if it is constant.
+    fn const_at(&self, n: usize) -> Option<U256> {
+        match self.peek(n) {
+            Value::Const(value) => Some(value),
+            Value::Unknown => None,
+        }
+    }
+
+    /// Swaps the top of the stack with the `n`th value from the top.
+    fn swap(&mut self, n: usize) {
+        if self.stack.len() <= n {
+            let missing = n + 1 - self.stack.len();
+            self.stack.splice(0..0, core::iter::repeat(Value::Unknown).take(missing));
+        }
+        let top = self.stack.len() - 1;
+        self.stack.swap(top, top - n);
+    }
+}
+
+impl Bytecode<'_> {
+    /// Replaces the `keccak256(key ++ slot)` computation of mapping storage slots with a builtin
+    /// call that does not go through memory.
+    ///
+    /// This matches a `KECCAK256` of `0x00..0x40` whose words are both stored by `MSTORE`s in the
+    /// straight-line run of stack-only instructions before it, such as the ones emitted by
+    /// Solidity:
+    ///
+    /// ```text
+    /// PUSH0 MSTORE PUSH1 slot PUSH1 0x20 MSTORE PUSH1 0x40 PUSH0 KECCAK256
+    /// PUSH0 SWAP1 DUP2 MSTORE PUSH1 0x20 DUP2 SWAP1 MSTORE PUSH1 0x40 SWAP1 KECCAK256
+    /// ```
+    ///
+    /// The `KECCAK256` and the two stores are marked as `MAP_SLOT`, and the stores are then not
+    /// written to memory. Since they are skipped, this is only done if the scratch space is
+    /// overwritten on all paths before it is read again, as per the Solidity memory layout.
+    /// Memory is still expanded and gas is still paid as if the stores were executed.
+    #[cfg_attr(feature = "tracing", instrument(name = "map_slot", level = "debug", skip_all))]
+    pub(super) fn map_slots(&mut self) {
+        debug_assert!(!self.is_eof());
+
+        for keccak in 0..self.insts.len() {
+            let data = &self.insts[keccak];
+            if data.opcode != op::KECCAK256 || data.flags.intersects(EXCLUDED) {
+                continue;
+            }
+            let Some(stores) = self.map_slot_stores(keccak) else { continue };
+            if !self.is_scratch_dead(keccak + 1) {
+                trace!(keccak, "scratch space may be read");
+                continue;
+            }
+
+            trace!(keccak, ?stores, "found map slot");
+            for (word, store) in stores.into_iter().enumerate() {
+                self.insts[store].flags |= InstFlags::MAP_SLOT;
+                self.insts[store].data = word as u32;
+            }
+            self.insts[keccak].flags |= InstFlags::MAP_SLOT;
+        }
+    }
+
+    /// Returns the `MSTORE`s of the first and second words of the scratch space if the
+    /// `KECCAK256` at `keccak` hashes them, and nothing else writes to the scratch space in
+    /// between.
+    fn map_slot_stores(&self, keccak: Inst) -> Option<[Inst; 2]> {
+        let run = self.run_before(keccak);
+        if run.const_at(0) != Some(U256::ZERO) || run.const_at(1) != Some(U256::from(0x40)) {
+            return None;
+        }
+
+        let mut stores = [None; 2];
+        for &(inst, offset) in run.stores.iter().rev() {
+            let word = match offset {
+                Value::Const(offset) if offset == U256::ZERO => 0,
+                Value::Const(offset) if offset == U256::from(0x20) => 1,
+                Value::Const(offset) if offset >= U256::from(0x40) => continue,
+                _ => return None,
+            };
+            stores[word].get_or_insert(inst);
+            if let [Some(key), Some(slot)] = stores {
+                return Some([key, slot]);
+            }
+        }
+        None
+    }
+
+    /// Simulates the stack of the run of stack-only instructions and `MSTORE`s that falls
+    /// through to `inst`.
+    ///
+    /// The run starts at a `JUMPDEST`, after any other instruction, or [`MAX_RUN_LEN`]
+    /// instructions before `inst`.
+    fn run_before(&self, inst: Inst) -> Run {
+        let mut start = inst;
+        if !self.insts[inst].is_jumpdest() {
+            while start > 0 && inst - start < MAX_RUN_LEN {
+                let data = &self.insts[start - 1];
+                if data.is_jumpdest() {
+                    start -= 1;
+                    break;
+                }
+                if !data.can_simulate() {
+                    break;
+                }
+                start -= 1;
+            }
+        }
+
+        let mut run = Run::default();
+        for i in start..inst {
+            let data = &self.insts[i];
+            match data.opcode {
+                op::JUMPDEST => {}
+                op::PUSH0..=op::PUSH32 => {
+                    run.push(self.push_value(i).map_or(Value::Unknown, Value::Const));
+                }
+                op::DUP1..=op::DUP16 => run.push(run.peek((data.opcode - op::DUP1) as usize)),
+                op::SWAP1..=op::SWAP16 => run.swap((data.opcode - op::SWAP1 + 1) as usize),
+                // Memory offsets are often computed as `offset + 0x20`.
+                op::ADD => {
+                    let value = match (run.pop(), run.pop()) {
+                        (Value::Const(a), Value::Const(b)) => Value::Const(a.wrapping_add(b)),
+                        _ => Value::Unknown,
+                    };
+                    run.push(value);
+                }
+                op::MSTORE => {
+                    let offset = run.pop();
+                    run.pop();
+                    run.stores.push((i, offset));
+                }
+                _ => {
+                    let (inputs, outputs) = data.stack_io();
+                    for _ in 0..inputs {
+                        run.pop();
+                    }
+                    for _ in 0..outputs {
+                        run.push(Value::Unknown);
+                    }
+                }
+            }
+        }
+        run
+    }
+
+    /// Returns `true` if both words of the scratch space are overwritten on all paths starting at
+    /// `inst` before being read.
+    ///
+    /// Memory is discarded when execution stops, so a path ends there, and only the range returned
+    /// by `RETURN` or `REVERT` is read.
+    fn is_scratch_dead(&self, inst: Inst) -> bool {
+        let mut visited = FxHashSet::default();
+        let mut worklist = vec![(inst, SCRATCH)];
+        while let Some((mut inst, mut live)) = worklist.pop() {
+            while live != 0 && visited.insert((inst, live)) {
+                if visited.len() > MAX_VISITED {
+                    return false;
+                }
+
+                let data = &self.insts[inst];
+                // User-defined opcodes may access memory.
+                if data.flags.contains(InstFlags::CUSTOM) {
+                    return false;
+                }
+                match data.opcode {
+                    op::MSTORE => {
+                        if let Some(offset) = self.run_before(inst).const_at(0) {
+                            live &= !written_words(offset);
+                        }
+                    }
+                    op::MLOAD | op::KECCAK256 | op::LOG0..=op::LOG4 | op::RETURN | op::REVERT => {
+                        let run = self.run_before(inst);
+                        let len = if data.opcode == op::MLOAD {
+                            Some(U256::from(32))
+                        } else {
+                            run.const_at(1)
+                        };
+                        match (run.const_at(0), len) {
+                            (Some(offset), Some(len)) if read_words(offset, len) & live == 0 => {}
+                            _ => return false,
+                        }
+                        if matches!(data.opcode, op::RETURN | op::REVERT) {
+                            break;
+                        }
+                    }
+                    op::JUMP | op::JUMPI => {
+                        if !data.is_legacy_static_jump()
+                            || data.flags.contains(InstFlags::INVALID_JUMP)
+                        {
+                            return false;
+                        }
+                        let target = data.data as Inst;
+                        if data.opcode == op::JUMP {
+                            inst = target;
+                            continue;
+                        }
+                        worklist.push((target, live));
+                    }
+                    _ if data.is_diverging(false) => break,
+                    _ if data.may_suspend(false) || reads_memory(data.opcode) => return false,
+                    _ => {}
+                }
+                inst += 1;
+            }
+        }
+        true
+    }
+
+    /// Returns the value pushed by the given instruction, if it is a `PUSH`.
+    pub(crate) fn push_value(&self, inst: Inst) -> Option<U256> {
+        let data = &self.insts[inst];
+        if data.flags.contains(InstFlags::CUSTOM) {
+            return None;
+        }
+        match data.opcode {
+            op::PUSH0 => Some(U256::ZERO),
+            op::PUSH1..=op::PUSH32 => self.get_imm(data).map(U256::from_be_slice),
+            _ => None,
+        }
+    }
+}
+
+impl InstData {
+    /// Returns `true` if the instruction can be part of a run, see [`Bytecode::run_before`].
+    fn can_simulate(&self) -> bool {
+        !self.flags.intersects(EXCLUDED)
+            && (self.is_pure()
+                || matches!(self.opcode, op::SWAP1..=op::SWAP16 | op::POP | op::MSTORE))
+    }
+}
+
+/// Returns the scratch space words that are fully overwritten by an `MSTORE` at `offset`.
+fn written_words(offset: U256) -> u8 {
+    if offset == U256::ZERO {
+        0b01
+    } else if offset == U256::from(0x20) {
+        0b10
+    } else {
+        0
+    }
+}
+
+/// Returns the scratch space words that are read by `len` bytes at `offset`.
+fn read_words(offset: U256, len: U256) -> u8 {
+    if len == U256::ZERO {
+        return 0;
+    }
+    let end = offset.saturating_add(len);
+    let first = (offset < U256::from(0x20)) as u8;
+    let second = (offset < U256::from(0x40) && end > U256::from(0x20)) as u8;
+    first | (second << 1)
+}
+
+/// Returns `true` if the given opcode may read any memory, other than `MLOAD`, `KECCAK256`,
+/// `LOG*`, `RETURN`, and `REVERT`.
+fn reads_memory(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        op::MCOPY
+            | op::CREATE
+            | op::CREATE2
+            | op::CALL
+            | op::CALLCODE
+            | op::DELEGATECALL
+            | op::STATICCALL
+    )
+}
@@ -1,13 +1,32 @@
 //! Internal EVM bytecode and opcode representation.
 
+use crate::{compiler::ensure_usage, CustomOpcode};
 use bitvec::vec::BitVec;
 use either::Either;
 use revm_interpreter::opcode as op;
 use revm_primitives::{hex, Eof, SpecId};
-use revmc_backend::{eyre::ensure, Result};
+use revmc_backend::{
+    eyre::{ensure, eyre},
+    Result,
+};
 use rustc_hash::FxHashMap;
 use std::{borrow::Cow, fmt};
 
+pub mod analysis;
+
+mod dead_values;
+
+#[cfg(feature = "debug-utils")]
+mod debug;
+#[cfg(feature = "debug-utils")]
+pub use debug::{InstInfo, SectionInfo};
+
+mod inline;
+
+mod map_slot;
+
+mod selector;
+
 mod sections;
 use sections::{Section, SectionAnalysis};
 
@@ -21,6 +40,34 @@ pub use opcode::*;
 #[cfg(any(feature = "__fuzzing", test))]
 pub(crate) const TEST_SUSPEND: u8 = 0x25;
 
+/// Configuration for [`Bytecode::analyze`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AnalysisConfig<'a> {
+    /// Overrides the base gas cost of each opcode.
+    pub(crate) base_gas: Option<&'a [u16; 256]>,
+    /// The maximum number of instructions of an internal function to inline. `0` disables
+    /// inlining.
+    pub(crate) inline_threshold: usize,
+    /// Whether to skip the logic of pure instructions whose results are never used.
+    pub(crate) dead_code: bool,
+    /// Whether the stack is observable after the function returns.
+    pub(crate) stack_observable: bool,
+    /// Whether to compute mapping storage slots without going through memory.
+    pub(crate) map_slot: bool,
+    /// User-defined opcodes.
+    pub(crate) custom_opcodes: Option<&'a FxHashMap<u8, CustomOpcode>>,
+    /// The calldata selector to resolve the function dispatch with.
+    pub(crate) selector: Option<u32>,
+}
+
+/// Validates the given EOF container.
+pub(crate) fn validate_eof(eof: &Eof) -> Result<()> {
+    revm_interpreter::analysis::validate_eof_inner(eof, None).map_err(|e| match e {
+        revm_interpreter::analysis::EofError::Decode(e) => e.into(),
+        revm_interpreter::analysis::EofError::Validation(e) => eyre!("validation error: {e:?}"),
+    })
+}
+
 // TODO: Use `indexvec`.
 /// An EVM instruction is a high level internal representation of an EVM opcode.
 ///
@@ -42,7 +89,7 @@ pub struct Bytecode<'a> {
     jumpdests: BitVec,
     /// The [`SpecId`].
     pub(crate) spec_id: SpecId,
-    /// Whether the bytecode contains dynamic jumps. Always false in EOF.
+    /// Whether the bytecode contains reachable dynamic jumps. Always false in EOF.
     has_dynamic_jumps: bool,
     /// Whether the bytecode may suspend execution.
     may_suspend: bool,
@@ -53,7 +100,7 @@ pub struct Bytecode<'a> {
 }
 
 impl<'a> Bytecode<'a> {
-    #[instrument(name = "new_bytecode", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "new_bytecode", level = "debug", skip_all))]
     pub(crate) fn new(mut code: &'a [u8], eof: Option<Cow<'a, Eof>>, spec_id: SpecId) -> Self {
         if let Some(eof) = &eof {
             code = unsafe {
@@ -184,13 +231,35 @@ impl<'a> Bytecode<'a> {
     }
 
     /// Runs a list of analysis passes on the instructions.
-    #[instrument(level = "debug", skip_all)]
-    pub(crate) fn analyze(&mut self) -> Result<()> {
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
+    pub(crate) fn analyze(&mut self, config: AnalysisConfig<'_>) -> Result<()> {
+        if let Some(base_gas) = config.base_gas {
+            self.set_base_gas(base_gas);
+        }
+        if let Some(custom_opcodes) = config.custom_opcodes {
+            self.set_custom_opcodes(custom_opcodes)?;
+        }
+
         if !self.is_eof() {
             self.static_jump_analysis();
+            // NOTE: `inline_internal_functions` must run after `static_jump_analysis` as it relies
+            // on resolved static jumps.
+            self.inline_internal_functions(config.inline_threshold);
+            // NOTE: `fold_selector` must run before `mark_dead_code` as it removes edges from the
+            // control flow graph.
+            if let Some(selector) = config.selector {
+                self.fold_selector(selector);
+            }
             // NOTE: `mark_dead_code` must run after `static_jump_analysis` as it can mark
             // unreachable `JUMPDEST`s as dead code.
             self.mark_dead_code();
+            if config.dead_code {
+                self.eliminate_dead_values(config.stack_observable);
+            }
+            // NOTE: `map_slots` must run after `static_jump_analysis` as it follows static jumps.
+            if config.map_slot {
+                self.map_slots();
+            }
         }
 
         self.calc_may_suspend();
@@ -205,8 +274,49 @@ impl<'a> Bytecode<'a> {
         Ok(())
     }
 
+    /// Overrides the base gas cost of all instructions.
+    fn set_base_gas(&mut self, base_gas: &[u16; 256]) {
+        for inst in &mut self.insts {
+            inst.base_gas = base_gas[inst.opcode as usize];
+        }
+    }
+
+    /// Marks the instructions of user-defined opcodes as `CUSTOM`.
+    ///
+    /// Returns an error if any of the opcodes is defined in the current spec.
+    fn set_custom_opcodes(&mut self, custom_opcodes: &FxHashMap<u8, CustomOpcode>) -> Result<()> {
+        let op_infos = op_info_map(self.spec_id);
+        for &opcode in custom_opcodes.keys() {
+            let info = op_infos[opcode as usize];
+            ensure_usage!(
+                info.is_unknown() || info.is_disabled(),
+                "custom opcode {opcode:#04x} collides with {} in {:?}",
+                Opcode { opcode, immediate: None },
+                self.spec_id,
+            );
+        }
+        if self.is_eof() {
+            return Ok(());
+        }
+
+        for inst in &mut self.insts {
+            if let Some(custom) = custom_opcodes.get(&inst.opcode) {
+                inst.flags.remove(InstFlags::UNKNOWN | InstFlags::DISABLED | InstFlags::EOF_ONLY);
+                inst.flags |= InstFlags::CUSTOM;
+                inst.base_gas = custom.gas;
+                inst.data = custom.inputs as u32 | (custom.outputs as u32) << 8;
+            }
+        }
+
+        // The code may now end with a custom opcode instead of an unknown one, see `new`.
+        if !self.insts.last().unwrap().is_diverging(false) {
+            self.insts.push(InstData::new(op::STOP));
+        }
+        Ok(())
+    }
+
     /// Mark `PUSH<N>` followed by `JUMP[I]` as `STATIC_JUMP` and resolve the target.
-    #[instrument(name = "sj", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "sj", level = "debug", skip_all))]
     fn static_jump_analysis(&mut self) {
         debug_assert!(!self.is_eof());
 
@@ -271,7 +381,7 @@ impl<'a> Bytecode<'a> {
     }
 
     /// Mark `RJUMP*` targets with `EOF_JUMPDEST` flag.
-    #[instrument(name = "eof_sj", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "eof_sj", level = "debug", skip_all))]
     fn eof_mark_jumpdests(&mut self) {
         debug_assert!(self.is_eof());
 
@@ -290,37 +400,102 @@ impl<'a> Bytecode<'a> {
     ///
     /// This pass is technically unnecessary as the backend will very likely optimize any
     /// unreachable code that we generate, but this is trivial for us to do and significantly speeds
-    /// up code generation.
+    /// up code generation, especially for the metadata that Solidity appends to the code.
     ///
-    /// We can simply mark all instructions that are between diverging instructions and
-    /// `JUMPDEST`s.
-    #[instrument(name = "dce", level = "debug", skip_all)]
+    /// Instructions are reachable from the entry point by falling through or by static jumps.
+    /// If any reachable jump is dynamic, all the `JUMPDEST`s are reachable too, otherwise
+    /// [`has_dynamic_jumps`](Self::has_dynamic_jumps) is reset. Unreachable `JUMPDEST`s are not
+    /// valid jump targets, as they are not part of the dynamic jump table.
+    #[cfg_attr(feature = "tracing", instrument(name = "dce", level = "debug", skip_all))]
     fn mark_dead_code(&mut self) {
         debug_assert!(!self.is_eof());
 
-        let mut iter = self.insts.iter_mut().enumerate();
-        while let Some((i, data)) = iter.next() {
-            if data.is_diverging(false) {
-                let mut end = i;
-                for (j, data) in &mut iter {
-                    end = j;
-                    if data.is_reachable_jumpdest(false, self.has_dynamic_jumps) {
-                        break;
-                    }
-                    data.flags |= InstFlags::DEAD_CODE;
+        // Static jump targets are marked again below, only if the jump is reachable.
+        for data in &mut self.insts {
+            if data.is_jumpdest() {
+                data.data = 0;
+            }
+        }
+
+        let mut reachable = BitVec::<usize>::repeat(false, self.insts.len());
+        let mut has_dynamic_jumps = false;
+        let mut worklist = vec![0];
+        while let Some(inst) = worklist.pop() {
+            if reachable.replace(inst, true) {
+                continue;
+            }
+
+            let data = &self.insts[inst];
+            let (opcode, target) = (data.opcode, data.data as Inst);
+            if data.flags.contains(InstFlags::JUMPI_NOT_TAKEN) {
+                worklist.push(inst + 1);
+                continue;
+            }
+            if data.flags.contains(InstFlags::JUMPI_TAKEN) {
+                self.insts[target].data = 1;
+                worklist.push(target);
+                continue;
+            }
+            if data.is_legacy_static_jump() {
+                if !data.flags.contains(InstFlags::INVALID_JUMP) {
+                    self.insts[target].data = 1;
+                    worklist.push(target);
                 }
-                let start = i + 1;
-                if end > start {
-                    debug!("found dead code: {start}..{end}");
+            } else if data.is_legacy_jump() {
+                if !has_dynamic_jumps {
+                    has_dynamic_jumps = true;
+                    worklist.extend(self.insts.iter().enumerate().filter_map(|(inst, data)| {
+                        (data.is_jumpdest() && !data.flags.contains(InstFlags::INLINED))
+                            .then_some(inst)
+                    }));
                 }
+            } else if !data.is_diverging(false) {
+                worklist.push(inst + 1);
+                continue;
             }
+            if opcode == op::JUMPI {
+                worklist.push(inst + 1);
+            }
+        }
+        if has_dynamic_jumps {
+            debug!("found reachable dynamic jumps");
+        }
+        self.has_dynamic_jumps = has_dynamic_jumps;
+
+        let mut start = None;
+        for (inst, data) in self.insts.iter_mut().enumerate() {
+            if reachable[inst] {
+                if let Some(start) = start.take() {
+                    debug!("found dead code: {start}..{inst}");
+                }
+                continue;
+            }
+            data.flags |= InstFlags::DEAD_CODE;
+            start.get_or_insert(inst);
+        }
+        if let Some(start) = start {
+            debug!("found dead code: {start}..{}", self.insts.len());
         }
     }
 
+    /// Returns the number of bytes of the original bytecode that are dead code.
+    pub(crate) fn dead_code_bytes(&self) -> usize {
+        let original = &self.insts[..self.pc_to_inst.len()];
+        original
+            .iter()
+            .enumerate()
+            .filter(|(_, data)| data.is_dead_code())
+            .map(|(inst, data)| {
+                let end = original.get(inst + 1).map_or(self.code.len(), |next| next.pc as usize);
+                end - data.pc as usize
+            })
+            .sum()
+    }
+
     /// Calculates whether the bytecode suspend suspend execution.
     ///
     /// This can only happen if the bytecode contains `*CALL*` or `*CREATE*` instructions.
-    #[instrument(name = "suspend", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "suspend", level = "debug", skip_all))]
     fn calc_may_suspend(&mut self) {
         let is_eof = self.is_eof();
         let may_suspend = self.iter_insts().any(|(_, data)| data.may_suspend(is_eof));
@@ -328,7 +503,7 @@ impl<'a> Bytecode<'a> {
     }
 
     /// Constructs the sections in the bytecode.
-    #[instrument(name = "sections", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "sections", level = "debug", skip_all))]
     fn construct_sections(&mut self) {
         let mut analysis = SectionAnalysis::default();
         for inst in 0..self.insts.len() {
@@ -340,7 +515,7 @@ impl<'a> Bytecode<'a> {
     }
 
     /// Constructs the sections in the bytecode.
-    #[instrument(name = "sections", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "sections", level = "debug", skip_all))]
     #[cfg(any())]
     fn construct_sections_default(&mut self) {
         for inst in &mut self.insts {
@@ -354,7 +529,7 @@ impl<'a> Bytecode<'a> {
     /// Calculates the list of instructions that call each EOF section.
     ///
     /// This is done to compute the `indirectbr` destinations of `RETF` instructions.
-    #[instrument(name = "eof_called_by", level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(name = "eof_called_by", level = "debug", skip_all))]
     fn calc_eof_called_by(&mut self) -> Result<()> {
         let code_sections_len = self.expect_eof().body.code_section.len();
         if code_sections_len <= 1 {
@@ -433,7 +608,7 @@ impl<'a> Bytecode<'a> {
         self.jumpdests.get(pc).as_deref().copied() == Some(true)
     }
 
-    /// Returns `true` if the bytecode has dynamic jumps.
+    /// Returns `true` if the bytecode has reachable dynamic jumps.
     pub(crate) fn has_dynamic_jumps(&self) -> bool {
         self.has_dynamic_jumps
     }
@@ -622,6 +797,7 @@ pub(crate) struct InstData {
     /// - if the instruction has immediate data, this is a packed offset+length into the bytecode;
     /// - `JUMP{,I} && STATIC_JUMP in kind`: the jump target, `Instr`;
     /// - `JUMPDEST`: `1` if the jump destination is reachable, `0` otherwise;
+    /// - `MSTORE && MAP_SLOT in flags`: the index of the scratch space word that it stores;
     /// - otherwise: no meaning.
     pub(crate) data: u32,
     /// The program counter, meaning `code[pc]` is this instruction's opcode.
@@ -633,7 +809,8 @@ pub(crate) struct InstData {
 impl PartialEq<u8> for InstData {
     #[inline]
     fn eq(&self, other: &u8) -> bool {
-        self.opcode == *other
+        // A user-defined opcode replaces the original one.
+        self.opcode == *other && !self.flags.contains(InstFlags::CUSTOM)
     }
 }
 
@@ -667,15 +844,22 @@ impl InstData {
     /// Returns the length of the immediate data of this instruction.
     #[inline]
     pub(crate) const fn imm_len(&self) -> u8 {
+        if self.flags.contains(InstFlags::CUSTOM) {
+            return 0;
+        }
         min_imm_len(self.opcode)
     }
 
     /// Returns the number of input and output stack elements of this instruction.
     #[inline]
     pub(crate) fn stack_io(&self) -> (u8, u8) {
+        if self.flags.contains(InstFlags::CUSTOM) {
+            return (self.data as u8, (self.data >> 8) as u8);
+        }
         let (mut inp, out) = stack_io(self.opcode);
         if self.is_legacy_static_jump()
             && !(self.opcode == op::JUMPI && self.flags.contains(InstFlags::INVALID_JUMP))
+            && !self.flags.contains(InstFlags::RETURN_JUMP)
         {
             inp -= 1;
         }
@@ -698,7 +882,7 @@ impl InstData {
     /// Returns `true` if this instruction is a push instruction.
     #[inline]
     pub(crate) fn is_push(&self) -> bool {
-        matches!(self.opcode, op::PUSH0..=op::PUSH32)
+        matches!(self.opcode, op::PUSH0..=op::PUSH32) && !self.flags.contains(InstFlags::CUSTOM)
     }
 
     /// Returns `true` if this instruction is a jump instruction.
@@ -777,6 +961,9 @@ impl InstData {
         if self.opcode == TEST_SUSPEND {
             return false;
         }
+        if self.flags.contains(InstFlags::CUSTOM) {
+            return false;
+        }
 
         (self.opcode == op::JUMP && self.flags.contains(InstFlags::INVALID_JUMP))
             || self.flags.contains(InstFlags::DISABLED)
@@ -793,6 +980,9 @@ impl InstData {
         if self.opcode == TEST_SUSPEND {
             return true;
         }
+        if self.flags.contains(InstFlags::CUSTOM) {
+            return false;
+        }
 
         if is_eof {
             matches!(
@@ -816,7 +1006,7 @@ impl InstData {
 bitflags::bitflags! {
     /// [`InstrData`] flags.
     #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-    pub(crate) struct InstFlags: u8 {
+    pub(crate) struct InstFlags: u16 {
         /// The `JUMP`/`JUMPI` target is known at compile time.
         /// This is implied for other jump instructions which are always static.
         const STATIC_JUMP = 1 << 0;
@@ -840,6 +1030,23 @@ bitflags::bitflags! {
         const SKIP_LOGIC = 1 << 6;
         /// Don't generate any code.
         const DEAD_CODE = 1 << 7;
+
+        /// The instruction is part of an inlined copy of an internal function.
+        /// Not a target of dynamic jumps.
+        const INLINED = 1 << 8;
+        /// The static `JUMP` returns from an inlined internal function.
+        /// The return address is still on the stack and must be popped.
+        const RETURN_JUMP = 1 << 9;
+        /// The instruction computes a mapping slot: either a `KECCAK256` of the scratch space,
+        /// or one of the two `MSTORE`s of its words, which are not written to memory.
+        const MAP_SLOT = 1 << 10;
+        /// The instruction is a user-defined opcode.
+        /// `data` holds its number of stack inputs and outputs.
+        const CUSTOM = 1 << 11;
+        /// The static `JUMPI` is always taken under the assumed calldata selector.
+        const JUMPI_TAKEN = 1 << 12;
+        /// The static `JUMPI` is never taken under the assumed calldata selector.
+        const JUMPI_NOT_TAKEN = 1 << 13;
     }
 }
 
@@ -867,4 +1074,247 @@ mod tests {
     fn test_suspend_is_free() {
         assert_eq!(op::OPCODE_INFO_JUMPTABLE[TEST_SUSPEND as usize], None);
     }
+
+    #[test]
+    fn dead_code_metadata() {
+        for code in [
+            include_str!("../../../../data/counter.rt.hex"),
+            include_str!("../../../../data/erc20_transfer.rt.hex"),
+        ] {
+            let code = hex::decode(code.trim()).unwrap();
+            let metadata_len = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]);
+            let metadata_start = code.len() - metadata_len as usize - 2;
+            let mut bytecode = Bytecode::new(&code, None, SpecId::CANCUN);
+            bytecode.analyze(AnalysisConfig::default()).unwrap();
+            assert!(bytecode
+                .iter_all_insts()
+                .filter(|(_, data)| data.pc as usize >= metadata_start)
+                .all(|(_, data)| data.is_dead_code()));
+            assert!(bytecode.dead_code_bytes() >= code.len() - metadata_start);
+        }
+    }
+
+    #[test]
+    fn dead_code_dynamic_jumps() {
+        let is_dead_jumpdest = |code: &[u8]| {
+            let mut bytecode = Bytecode::new(code, None, SpecId::CANCUN);
+            bytecode.analyze(AnalysisConfig::default()).unwrap();
+            let (_, data) = bytecode.iter_all_insts().find(|(_, data)| data.is_jumpdest()).unwrap();
+            data.is_dead_code()
+        };
+        // Not referenced by any jump, but may be the target of the dynamic jump.
+        assert!(!is_dead_jumpdest(&[
+            op::PUSH0,
+            op::CALLDATALOAD,
+            op::JUMP,
+            op::JUMPDEST,
+            op::STOP
+        ]));
+        // The dynamic jump is itself unreachable.
+        assert!(is_dead_jumpdest(&[op::STOP, op::PUSH0, op::JUMP, op::JUMPDEST, op::STOP]));
+        // Only referenced by an unreachable static jump.
+        assert!(is_dead_jumpdest(&[op::STOP, op::PUSH1, 4, op::JUMP, op::JUMPDEST, op::STOP]));
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        unsafe extern "C" fn noop(
+            _: &mut crate::EvmContext<'_>,
+            _: *const crate::EvmWord,
+            _: *mut crate::EvmWord,
+        ) -> revm_interpreter::InstructionResult {
+            revm_interpreter::InstructionResult::Continue
+        }
+
+        let custom = |opcode: u8| {
+            let builtin = crate::CustomBuiltin::new("noop", noop);
+            let custom = CustomOpcode { inputs: 2, outputs: 3, gas: 42, builtin };
+            FxHashMap::from_iter([(opcode, custom)])
+        };
+        let analyze = |code: &[u8], custom_opcodes| {
+            let mut bytecode = Bytecode::new(code, None, SpecId::CANCUN);
+            let config =
+                AnalysisConfig { custom_opcodes: Some(&custom_opcodes), ..Default::default() };
+            bytecode.analyze(config).map(|()| bytecode.insts)
+        };
+
+        let insts = analyze(&[op::PUSH0, op::PUSH0, 0xC0], custom(0xC0)).unwrap();
+        assert!(insts[2].flags.contains(InstFlags::CUSTOM));
+        assert!(!insts[2].flags.contains(InstFlags::UNKNOWN));
+        assert_eq!(insts[2].stack_io(), (2, 3));
+        assert_eq!(insts[2].base_gas, 42);
+        // The code no longer ends with a diverging instruction.
+        assert_eq!(insts.last().unwrap().opcode, op::STOP);
+
+        let err = analyze(&[op::STOP], custom(op::MUL)).unwrap_err();
+        assert_eq!(err.to_string(), "custom opcode 0x02 collides with MUL in CANCUN");
+
+        // Opcodes that are disabled in the spec are replaced entirely.
+        let analyze_in = |code: &[u8], opcode, spec_id| {
+            let custom_opcodes = custom(opcode);
+            let mut bytecode = Bytecode::new(code, None, spec_id);
+            let config =
+                AnalysisConfig { custom_opcodes: Some(&custom_opcodes), ..Default::default() };
+            bytecode.analyze(config).unwrap();
+            (bytecode.insts, bytecode.has_dynamic_jumps)
+        };
+        let (insts, has_dynamic_jumps) =
+            analyze_in(&[op::PUSH0, op::JUMP, op::JUMPDEST], op::PUSH0, SpecId::MERGE);
+        assert!(insts[0] != op::PUSH0 && !insts[0].is_push() && !insts[0].is_pure());
+        assert!(has_dynamic_jumps);
+        let (insts, _) = analyze_in(&[op::REVERT, op::STOP], op::REVERT, SpecId::HOMESTEAD);
+        assert!(!insts[0].is_diverging(false));
+        assert!(!insts[1].is_dead_code());
+        let (insts, _) = analyze_in(&[op::CREATE2, op::STOP], op::CREATE2, SpecId::HOMESTEAD);
+        assert!(!insts[0].may_suspend(false));
+        let (insts, _) = analyze_in(&[op::RJUMP, op::STOP], op::RJUMP, SpecId::CANCUN);
+        assert!(!insts[0].flags.contains(InstFlags::EOF_ONLY));
+        assert_eq!(insts[0].imm_len(), 0);
+    }
+
+    #[test]
+    fn dead_values() {
+        let code = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::POP, op::STOP];
+        let config = AnalysisConfig { dead_code: true, ..Default::default() };
+        let mut bytecode = Bytecode::new(&code, None, SpecId::CANCUN);
+        bytecode.analyze(config).unwrap();
+        let skipped = bytecode
+            .iter_insts()
+            .map(|(_, data)| data.flags.contains(InstFlags::SKIP_LOGIC))
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, [true, true, true, true, false]);
+        assert_eq!(bytecode.inst(0).section.gas_cost, 3 + 3 + 3 + 2);
+
+        let skipped = |code: &[u8]| {
+            let mut bytecode = Bytecode::new(code, None, SpecId::CANCUN);
+            bytecode.analyze(config).unwrap();
+            bytecode
+                .iter_insts()
+                .map(|(_, data)| data.flags.contains(InstFlags::SKIP_LOGIC))
+                .collect::<Vec<_>>()
+        };
+        // Below the operands of `RETURN`.
+        let ret = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::PUSH0, op::PUSH0, op::RETURN];
+        assert_eq!(skipped(&ret), [true, true, true, false, false, false]);
+        // The size is a copy of the value below, which must stay alive.
+        let code = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::DUP1, op::PUSH0, op::RETURN];
+        assert_eq!(skipped(&code), [false; 6]);
+
+        // The stack is observable.
+        let config = AnalysisConfig { stack_observable: true, ..config };
+        let mut bytecode = Bytecode::new(&ret, None, SpecId::CANCUN);
+        bytecode.analyze(config).unwrap();
+        assert!(bytecode.iter_insts().all(|(_, data)| !data.flags.contains(InstFlags::SKIP_LOGIC)));
+    }
+
+    #[test]
+    fn map_slot() {
+        #[rustfmt::skip]
+        fn map_slot(slot: u8) -> [u8; 11] {
+            [
+                op::PUSH0, op::MSTORE, op::PUSH1, slot, op::PUSH1, 0x20, op::MSTORE,
+                op::PUSH1, 0x40, op::PUSH0, op::KECCAK256,
+            ]
+        }
+        fn map_slots(code: &[&[u8]]) -> Vec<Inst> {
+            let code = code.concat();
+            let config = AnalysisConfig { map_slot: true, ..Default::default() };
+            let mut bytecode = Bytecode::new(&code, None, SpecId::CANCUN);
+            bytecode.analyze(config).unwrap();
+            bytecode
+                .iter_insts()
+                .filter(|(_, data)| {
+                    data.opcode == op::KECCAK256 && data.flags.contains(InstFlags::MAP_SLOT)
+                })
+                .map(|(inst, _)| inst)
+                .collect()
+        }
+
+        // Overwritten by the second one, and memory is discarded after `STOP`.
+        let insts = map_slots(&[&[op::CALLER], &map_slot(0), &[op::CALLER], &map_slot(1)]);
+        assert_eq!(insts, [8, 17]);
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &map_slot(0),
+            &[op::PUSH1, 0x40, op::MLOAD, op::POP, op::CALLER],
+            &map_slot(1),
+        ]);
+        assert_eq!(insts, [8, 20]);
+
+        // Computed on the stack.
+        #[rustfmt::skip]
+        let legacy = [
+            op::PUSH0, op::SWAP1, op::DUP2, op::MSTORE, op::PUSH1, 0x20, op::DUP2, op::SWAP1,
+            op::MSTORE, op::PUSH1, 0x40, op::SWAP1, op::KECCAK256,
+        ];
+        let insts = map_slots(&[&[op::CALLER], &legacy, &[op::CALLER], &legacy]);
+        assert_eq!(insts, [11, 23]);
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &legacy,
+            &[op::PUSH1, 0x20, op::PUSH0, op::ADD, op::MLOAD, op::POP, op::CALLER],
+            &legacy,
+        ]);
+        assert_eq!(insts, [28]);
+
+        // Overwritten in between.
+        let mut overwritten = map_slot(0).to_vec();
+        overwritten.splice(7..7, [op::CALLVALUE, op::CALLVALUE, op::MSTORE]);
+        let insts = map_slots(&[&[op::CALLER], &overwritten, &[op::CALLER], &map_slot(1)]);
+        assert_eq!(insts, [20]);
+
+        // Read.
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &map_slot(0),
+            &[op::PUSH1, 0x10, op::MLOAD, op::POP, op::CALLER],
+            &map_slot(1),
+        ]);
+        assert_eq!(insts, [20]);
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &map_slot(0),
+            &[op::CALLVALUE, op::PUSH1, 29, op::JUMPI, op::CALLER],
+            &map_slot(1),
+            &[op::STOP, op::JUMPDEST, op::PUSH0, op::MLOAD],
+        ]);
+        assert_eq!(insts, [20]);
+
+        // Returned.
+        let insts =
+            map_slots(&[&[op::CALLER], &map_slot(0), &[op::PUSH1, 0x40, op::PUSH0, op::RETURN]]);
+        assert!(insts.is_empty());
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &map_slot(0),
+            &[op::PUSH1, 0x20, op::PUSH1, 0x20, op::REVERT],
+        ]);
+        assert!(insts.is_empty());
+        let insts = map_slots(&[
+            &[op::CALLER],
+            &map_slot(0),
+            &[op::PUSH1, 0x20, op::PUSH1, 0x40, op::RETURN],
+        ]);
+        assert_eq!(insts, [8]);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-utils")]
+    fn inst_infos() {
+        let code = [op::PUSH1, 4, op::JUMP, op::INVALID, op::JUMPDEST];
+        let mut bytecode = Bytecode::new(&code, None, SpecId::CANCUN);
+        bytecode.analyze(AnalysisConfig::default()).unwrap();
+
+        let infos = bytecode.inst_infos().collect::<Vec<_>>();
+        assert_eq!(infos.len(), 5);
+        assert_eq!(infos[0].opcode().immediate, Some(&[4][..]));
+        assert_eq!(infos[1].jump_target(), Some(3));
+        assert_eq!(infos[1].stack_io(), (0, 0));
+        assert_eq!(infos[3].pc(), 4);
+        assert_eq!(infos[3].section().map(|s| s.gas_cost), Some(1));
+        assert!(infos[2].flag_names().any(|name| name == "DEAD_CODE"));
+        assert!(infos[4].is_synthetic());
+        assert_eq!(bytecode.inst_info_at_pc(4).map(|i| i.index()), Some(3));
+        assert!(bytecode.inst_info_at_pc(1).is_none());
+    }
 }
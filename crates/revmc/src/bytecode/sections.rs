@@ -88,6 +88,7 @@ impl SectionAnalysis {
     /// Finishes the analysis.
     pub(crate) fn finish(self, bytecode: &mut Bytecode<'_>) {
         self.save_to(bytecode, bytecode.insts.len() - 1);
+        #[cfg(feature = "tracing")]
         if enabled!(tracing::Level::DEBUG) {
             let mut max_len = 0;
             let mut current = 0;
@@ -0,0 +1,118 @@
+use super::{Bytecode, Inst, InstFlags};
+use revm_interpreter::opcode as op;
+use revm_primitives::U256;
+
+/// The number of instructions that load the selector, including the final `SHR`.
+const LOAD_LEN: usize = 4;
+
+/// The number of instructions in a single dispatch comparison, including the final `JUMPI`.
+const CASE_LEN: usize = 5;
+
+impl Bytecode<'_> {
+    /// Resolves the function dispatch as if calldata started with `selector`.
+    ///
+    /// This matches the selector load emitted by Solidity, followed by a chain of comparisons,
+    /// where `PUSH1 0` may be used instead of `PUSH0`, and `EQ` may also be `GT` or `LT` when the
+    /// dispatch is split into a binary search:
+    ///
+    /// ```text
+    /// PUSH0 CALLDATALOAD PUSH1 0xe0 SHR
+    /// DUP1 PUSH4 selector EQ PUSH<N> dest JUMPI
+    /// ...
+    /// ```
+    ///
+    /// Each comparison's `JUMPI` is marked as `JUMPI_TAKEN` or `JUMPI_NOT_TAKEN`. A taken jump is
+    /// only followed if its target cannot be reached in any other way, otherwise the chain stops
+    /// there, as it does at the first instruction that does not match. The conditions are still
+    /// evaluated and popped, so gas and the stack are unaffected.
+    ///
+    /// The translated function must check the actual selector before executing the bytecode.
+    #[cfg_attr(feature = "tracing", instrument(name = "selector", level = "debug", skip_all))]
+    pub(super) fn fold_selector(&mut self, selector: u32) {
+        debug_assert!(!self.is_eof());
+
+        let Some(start) = (0..self.insts.len()).find(|&inst| self.is_selector_load(inst)) else {
+            debug!("selector load not found");
+            return;
+        };
+
+        let selector = U256::from(selector);
+        let predecessors = self.static_jump_predecessors();
+        let mut folded = 0;
+        let mut case = start + LOAD_LEN;
+        while let Some((cmp, value)) = self.dispatch_case(case) {
+            let jumpi = case + CASE_LEN - 1;
+            let taken = match cmp {
+                op::EQ => value == selector,
+                op::GT => value > selector,
+                op::LT => value < selector,
+                _ => unreachable!(),
+            };
+            folded += 1;
+            if !taken {
+                self.insts[jumpi].flags |= InstFlags::JUMPI_NOT_TAKEN;
+                case += CASE_LEN;
+                continue;
+            }
+
+            self.insts[jumpi].flags |= InstFlags::JUMPI_TAKEN;
+            let target = self.insts[jumpi].data as Inst;
+            let falls_through = target.checked_sub(1).map_or(true, |prev| {
+                let prev = &self.insts[prev];
+                prev.opcode != op::JUMP && !prev.is_diverging(false)
+            });
+            if self.has_dynamic_jumps || predecessors[target] != 1 || falls_through {
+                trace!(jumpi, target, "dispatch target may be reached otherwise");
+                break;
+            }
+            case = target + 1;
+        }
+        debug!(folded, "folded selector dispatch");
+    }
+
+    /// Returns `true` if the instructions starting at `start` load the calldata selector.
+    fn is_selector_load(&self, start: Inst) -> bool {
+        let Some(insts) = self.insts.get(start..start + LOAD_LEN) else { return false };
+        if insts.iter().any(|data| !data.flags.is_empty()) {
+            return false;
+        }
+        self.push_value(start) == Some(U256::ZERO)
+            && insts[1] == op::CALLDATALOAD
+            && self.push_value(start + 2) == Some(U256::from(0xe0))
+            && insts[3] == op::SHR
+    }
+
+    /// Returns the comparison opcode and the compared selector if the instructions starting at
+    /// `start` are a dispatch comparison with a valid static jump, which has not been folded yet.
+    ///
+    /// Since the first instruction is not a `JUMPDEST`, it can only be reached by falling through
+    /// the previous instruction, so the selector is guaranteed to be on top of the stack.
+    fn dispatch_case(&self, start: Inst) -> Option<(u8, U256)> {
+        let insts = self.insts.get(start..start + CASE_LEN)?;
+        if insts[..3].iter().any(|data| !data.flags.is_empty())
+            || insts[3].flags != InstFlags::SKIP_LOGIC
+            || insts[4].flags != InstFlags::STATIC_JUMP
+        {
+            return None;
+        }
+        if insts[0] != op::DUP1
+            || insts[1] != op::PUSH4
+            || !matches!(insts[2].opcode, op::EQ | op::GT | op::LT)
+            || insts[4] != op::JUMPI
+        {
+            return None;
+        }
+        Some((insts[2].opcode, self.push_value(start + 1)?))
+    }
+
+    /// Returns the number of valid static jumps to each instruction.
+    fn static_jump_predecessors(&self) -> Vec<u32> {
+        let mut predecessors = vec![0; self.insts.len()];
+        for data in &self.insts {
+            if data.is_legacy_static_jump() && !data.flags.contains(InstFlags::INVALID_JUMP) {
+                predecessors[data.data as usize] += 1;
+            }
+        }
+        predecessors
+    }
+}
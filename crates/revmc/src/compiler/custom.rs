@@ -0,0 +1,64 @@
+//! User-defined opcodes.
+
+use crate::{EvmContext, EvmWord};
+use revm_interpreter::InstructionResult;
+use std::fmt;
+
+/// The signature of a [`CustomBuiltin`] function.
+///
+/// `args` points to the opcode's inputs as they are laid out on the stack, with the top of the
+/// stack last. `out` points to where the outputs are written, with the top of the stack last.
+///
+/// `out` is the same pointer as `args`, as the outputs replace the inputs on the stack, so all the
+/// inputs must be read before writing any output.
+///
+/// Returning anything other than [`InstructionResult::Continue`] stops execution with that
+/// result.
+pub type CustomBuiltinFn = unsafe extern "C" fn(
+    ecx: &mut EvmContext<'_>,
+    args: *const EvmWord,
+    out: *mut EvmWord,
+) -> InstructionResult;
+
+/// The function that implements a [`CustomOpcode`].
+#[derive(Clone, Copy)]
+pub struct CustomBuiltin {
+    /// The symbol name of the function.
+    ///
+    /// In AOT mode, this is imported and must be defined when linking. It must be unique, and
+    /// should not start with `__revmc_`.
+    pub name: &'static str,
+    /// The function, which is called directly in JIT mode.
+    pub f: CustomBuiltinFn,
+}
+
+impl fmt::Debug for CustomBuiltin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomBuiltin").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+impl CustomBuiltin {
+    /// Creates a new custom builtin.
+    pub const fn new(name: &'static str, f: CustomBuiltinFn) -> Self {
+        Self { name, f }
+    }
+}
+
+/// A user-defined opcode, registered with [`EvmCompiler::register_custom_opcode`].
+///
+/// The opcode pops `inputs` words, pays `gas` as static gas, calls `builtin`, and pushes
+/// `outputs` words.
+///
+/// [`EvmCompiler::register_custom_opcode`]: crate::EvmCompiler::register_custom_opcode
+#[derive(Clone, Copy, Debug)]
+pub struct CustomOpcode {
+    /// The number of stack inputs.
+    pub inputs: u8,
+    /// The number of stack outputs.
+    pub outputs: u8,
+    /// The static gas cost.
+    pub gas: u16,
+    /// The function that implements the opcode.
+    pub builtin: CustomBuiltin,
+}
@@ -0,0 +1,156 @@
+use crate::Opcode;
+use revm_primitives::SpecId;
+use std::{fmt, io};
+
+/// An error that occurred while compiling EVM bytecode with [`EvmCompiler`](crate::EvmCompiler).
+///
+/// Errors returned by the backend are wrapped in [`Backend`](Self::Backend), with the original
+/// error available as the [source](std::error::Error::source).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CompileError {
+    /// The bytecode contains an opcode that cannot be compiled in its spec.
+    ///
+    /// This only happens for EOF bytecode, since unknown and disabled opcodes in legacy bytecode
+    /// halt at runtime instead.
+    UnsupportedOpcode {
+        /// The program counter of the opcode.
+        pc: usize,
+        /// The opcode.
+        opcode: u8,
+        /// The spec that the bytecode was compiled for.
+        spec: SpecId,
+    },
+    /// The bytecode could not be decoded or did not pass validation.
+    InvalidBytecode(String),
+    /// The compiler was used incorrectly, for example by translating functions after finalizing
+    /// the module, or by JIT-compiling a function in AOT mode.
+    InvalidUsage(String),
+    /// A compiler limit was exceeded.
+    LimitExceeded(Limit),
+    /// The backend failed to translate, optimize or emit the code.
+    Backend(revmc_backend::Error),
+    /// An I/O error occurred while writing the output.
+    Io(io::Error),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedOpcode { pc, opcode, spec } => {
+                let opcode = Opcode { opcode: *opcode, immediate: None };
+                write!(f, "unsupported opcode {opcode} at pc {pc} in {spec:?}")
+            }
+            Self::InvalidBytecode(reason) => write!(f, "invalid bytecode: {reason}"),
+            Self::InvalidUsage(msg) => f.write_str(msg),
+            Self::LimitExceeded(limit) => write!(f, "limit exceeded: {limit}"),
+            Self::Backend(_) => f.write_str("backend error"),
+            Self::Io(_) => f.write_str("I/O error"),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Backend(err) => Some(err.as_ref()),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<revmc_backend::Error> for CompileError {
+    /// Recovers the `CompileError` or I/O error that `err` was created from, and otherwise wraps
+    /// it in [`Backend`](Self::Backend).
+    fn from(err: revmc_backend::Error) -> Self {
+        let err = match err.downcast::<Self>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        match err.downcast::<io::Error>() {
+            Ok(err) => Self::Io(err),
+            Err(err) => Self::Backend(err),
+        }
+    }
+}
+
+impl From<io::Error> for CompileError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A compiler limit, see [`CompileError::LimitExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// The bytecode is larger than the maximum set with
+    /// [`EvmCompiler::max_code_size`](crate::EvmCompiler::max_code_size).
+    CodeSize {
+        /// The size of the bytecode, in bytes.
+        size: usize,
+        /// The maximum size, in bytes.
+        max: usize,
+    },
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CodeSize { size, max } => {
+                write!(f, "code size of {size} bytes is larger than the maximum of {max} bytes")
+            }
+        }
+    }
+}
+
+/// Returns early with a [`CompileError::InvalidUsage`] if the condition is not satisfied.
+macro_rules! ensure_usage {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err($crate::CompileError::InvalidUsage(format!($($arg)+)).into());
+        }
+    };
+}
+pub(crate) use ensure_usage;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revmc_backend::eyre::{eyre, WrapErr};
+    use std::error::Error as _;
+
+    #[test]
+    fn from_report() {
+        let err = CompileError::from(eyre!("verification failed"));
+        assert!(matches!(err, CompileError::Backend(_)));
+        assert_eq!(err.to_string(), "backend error");
+        assert_eq!(err.source().unwrap().to_string(), "verification failed");
+
+        let err = CompileError::InvalidBytecode("bad".into());
+        let err = CompileError::from(revmc_backend::Error::new(err));
+        assert!(matches!(err, CompileError::InvalidBytecode(ref reason) if reason == "bad"));
+
+        let err: revmc_backend::Result<()> =
+            Err(CompileError::LimitExceeded(Limit::CodeSize { size: 2, max: 1 }).into());
+        let err = CompileError::from(err.wrap_err("context").unwrap_err());
+        assert!(matches!(err, CompileError::LimitExceeded(Limit::CodeSize { size: 2, max: 1 })));
+
+        let io = io::Error::new(io::ErrorKind::NotFound, "missing");
+        let err = CompileError::from(revmc_backend::Error::new(io));
+        assert!(matches!(err, CompileError::Io(_)));
+        assert_eq!(err.source().unwrap().to_string(), "missing");
+    }
+
+    #[test]
+    fn display() {
+        let err = CompileError::UnsupportedOpcode { pc: 3, opcode: 0x56, spec: SpecId::OSAKA };
+        assert_eq!(err.to_string(), "unsupported opcode JUMP at pc 3 in OSAKA");
+        let err = CompileError::LimitExceeded(Limit::CodeSize { size: 2, max: 1 });
+        assert_eq!(
+            err.to_string(),
+            "limit exceeded: code size of 2 bytes is larger than the maximum of 1 bytes"
+        );
+    }
+}
@@ -1,14 +1,16 @@
 //! EVM bytecode compiler implementation.
 
-use crate::{Backend, Builder, Bytecode, EvmCompilerFn, EvmContext, EvmStack, Result};
-use revm_interpreter::{Contract, Gas};
-use revm_primitives::{Bytes, Env, Eof, SpecId, EOF_MAGIC_BYTES};
-use revmc_backend::{
-    eyre::{ensure, eyre},
-    Attribute, FunctionAttributeLocation, Linkage, OptimizationLevel,
+use crate::{
+    validate_eof, wasm32, Assertions, Backend, Builder, Bytecode, ContractView, EvmCompilerFn,
+    EvmCompilerFnNoGas, EvmContext, EvmStack, ExecutionProfile, FnInfo, FnSignature, GasMode,
+    GasTable, Result,
 };
+use revm_interpreter::Gas;
+use revm_primitives::{Bytes, Env, Eof, SpecId, EOF_MAGIC_BYTES};
+use revmc_backend::{Attribute, FunctionAttributeLocation, Linkage, OptimizationLevel, Target};
 use revmc_builtins::Builtins;
-use revmc_context::RawEvmCompilerFn;
+use revmc_context::{RawEvmCompilerFn, RawEvmCompilerFnNoGas};
+use rustc_hash::FxHashMap;
 use std::{
     borrow::Cow,
     fs,
@@ -23,10 +25,17 @@ use std::{
 // emitted.
 // Use this when `stack` is passed in arguments.
 
-// TODO: Get rid of `cfg!(target_endian)` calls.
+// Byte order: stack words are 256-bit integers in the target's byte order, the same as `EvmWord`,
+// so they can be passed to builtins by pointer. Values are byte-swapped only where the EVM defines a byte order:
+// memory, calldata, and big-endian fields such as addresses. `U256` fields of the environment are
+// converted with `u256_to_native`, since `U256` limbs are always in little-endian order.
 
-// TODO: Test on big-endian hardware.
-// It probably doesn't work when loading Rust U256 into native endianness.
+mod custom;
+pub use custom::{CustomBuiltin, CustomBuiltinFn, CustomOpcode};
+
+mod error;
+pub(crate) use error::ensure_usage;
+pub use error::{CompileError, Limit};
 
 mod translate;
 use translate::{FcxConfig, FunctionCx};
@@ -53,13 +62,30 @@ pub struct EvmCompiler<B: Backend> {
     out_dir: Option<PathBuf>,
     config: FcxConfig,
     builtins: Builtins<B>,
+    fn_infos: FxHashMap<String, FnInfo>,
+    fn_names: FxHashMap<B::FuncId, String>,
+    stats: FxHashMap<B::FuncId, CompilationStats>,
+    custom_opcodes: FxHashMap<u8, CustomOpcode>,
 
     dump_assembly: bool,
     dump_unopt_assembly: bool,
 
+    deterministic: bool,
+    deterministic_fns: Vec<DeterministicFn>,
+
     finalized: bool,
 }
 
+/// A function recorded in deterministic mode, to translate when finalizing the module.
+struct DeterministicFn {
+    name: String,
+    code: Bytes,
+    spec_id: SpecId,
+    selector_guard: Option<(u32, String)>,
+    profile: Option<ExecutionProfile>,
+    config: FcxConfig,
+}
+
 impl<B: Backend> EvmCompiler<B> {
     /// Creates a new instance of the compiler with the given backend.
     pub fn new(backend: B) -> Self {
@@ -69,8 +95,14 @@ impl<B: Backend> EvmCompiler<B> {
             out_dir: None,
             config: FcxConfig::default(),
             builtins: Builtins::new(),
+            fn_infos: FxHashMap::default(),
+            fn_names: FxHashMap::default(),
+            stats: FxHashMap::default(),
+            custom_opcodes: FxHashMap::default(),
             dump_assembly: true,
             dump_unopt_assembly: false,
+            deterministic: false,
+            deterministic_fns: Vec::new(),
             finalized: false,
         }
     }
@@ -82,6 +114,23 @@ impl<B: Backend> EvmCompiler<B> {
         self.name = Some(name);
     }
 
+    /// (AOT) Makes the written object only depend on the translated functions and the
+    /// configuration, so that it is bit-identical across machines and runs.
+    ///
+    /// Functions are only recorded when they are translated, and are all translated when the
+    /// object is written, sorted by name, so that the emitted symbols, constants, and builtin
+    /// declarations do not depend on the order in which the functions were translated. The IDs
+    /// returned by [`translate`](Self::translate) only refer to declarations, and translation
+    /// errors are returned by [`write_object`](Self::write_object).
+    ///
+    /// Writing the object fails if the target CPU and features of the backend are not explicitly
+    /// set, since they would otherwise be detected from the host machine.
+    ///
+    /// Must be set before any functions are translated. Defaults to `false`.
+    pub fn deterministic(&mut self, yes: bool) {
+        self.deterministic = yes;
+    }
+
     fn is_aot(&self) -> bool {
         self.backend.is_aot()
     }
@@ -141,10 +190,25 @@ impl<B: Backend> EvmCompiler<B> {
     /// These are useful for debugging, but they do a moderate performance penalty due to the
     /// insertion of extra checks and removal of certain assumptions.
     ///
+    /// Also sets [`assertions`](Self::assertions) to [`Assertions::Check`] or
+    /// [`Assertions::None`].
+    ///
     /// Defaults to `cfg!(debug_assertions)`.
     pub fn debug_assertions(&mut self, yes: bool) {
         self.backend.set_debug_assertions(yes);
-        self.config.debug_assertions = yes;
+        self.config.assertions = if yes { Assertions::Check } else { Assertions::None };
+    }
+
+    /// Sets how the preconditions on the function arguments, such as pointers that must not be
+    /// null, are handled.
+    ///
+    /// [`Assertions::Assume`] lets the optimizer rely on them without checking them at runtime.
+    /// The mode is recorded in the function's [`FnInfo`].
+    ///
+    /// Defaults to [`Assertions::Check`] if `cfg!(debug_assertions)`, [`Assertions::None`]
+    /// otherwise.
+    pub fn assertions(&mut self, assertions: Assertions) {
+        self.config.assertions = assertions;
     }
 
     /// Sets whether to enable frame pointers.
@@ -165,6 +229,15 @@ impl<B: Backend> EvmCompiler<B> {
         self.config.validate_eof = yes;
     }
 
+    /// Sets the maximum size of the bytecode to compile, in bytes.
+    ///
+    /// Larger bytecode fails to compile with [`CompileError::LimitExceeded`].
+    ///
+    /// Defaults to `None`, meaning no limit.
+    pub fn max_code_size(&mut self, max: Option<usize>) {
+        self.config.max_code_size = max;
+    }
+
     /// Sets whether to allocate the stack locally.
     ///
     /// If this is set to `true`, the stack pointer argument will be ignored and the stack will be
@@ -216,18 +289,220 @@ impl<B: Backend> EvmCompiler<B> {
     /// Disabling this will greatly improves compilation speed and performance, at the cost of not
     /// being able to check for gas exhaustion.
     ///
-    /// Note that this does not disable gas usage in certain instructions, mainly the ones that
-    /// are implemented as builtins.
+    /// Gas costs paid by builtins are only disabled if [`EvmContext::gas_mode`] is also set to
+    /// [`GasMode::Disabled`], for example with [`EvmContext::disable_gas`].
     ///
     /// Use with care, as executing a function with gas disabled may result in an infinite loop.
     ///
     /// Defaults to `true`.
     pub fn gas_metering(&mut self, yes: bool) {
-        self.config.gas_metering = yes;
+        self.config.gas_mode = if yes { GasMode::Metered } else { GasMode::Disabled };
+    }
+
+    /// Sets how gas costs are metered.
+    ///
+    /// In [`GasMode::ObserveOnly`] mode, gas costs are still paid, but the gas limit check is
+    /// compiled out, and the limit is raised instead when exceeded, so execution never halts with
+    /// an out-of-gas error and [`Gas::spent`] is the total cost of the execution.
+    /// [`EvmContext::gas_mode`] must be set to the same mode for the costs paid by builtins.
+    ///
+    /// The mode is recorded in the function's [`FnInfo`].
+    ///
+    /// Defaults to [`GasMode::Metered`].
+    pub fn gas_metering_mode(&mut self, mode: GasMode) {
+        self.config.gas_mode = mode;
+    }
+
+    /// Sets whether to compile functions without the gas parameter, with the given value for the
+    /// `GAS` opcode to push.
+    ///
+    /// This removes the remaining gas pointer dereferences from the compiled code, to measure raw
+    /// throughput. It requires gas metering to be [disabled](Self::gas_metering), otherwise
+    /// translation fails. The functions must be JIT-compiled with
+    /// [`jit_function_no_gas`](Self::jit_function_no_gas), and are called through
+    /// [`EvmCompilerFnNoGas`]. The signature is recorded in the function's [`FnInfo`].
+    ///
+    /// Defaults to `None`.
+    pub fn no_gas_signature(&mut self, gas: Option<u64>) {
+        self.config.gas_constant = gas;
+    }
+
+    /// Sets whether to validate the `resume_at` value when resuming execution.
+    ///
+    /// If set, an invalid [`EvmContext::resume_at`] value makes the function return
+    /// [`InstructionResult::FatalExternalError`] instead of being undefined behavior. This also
+    /// makes `resume_at` values always be indexes, see [`resume_points`](Self::resume_points).
+    ///
+    /// [`InstructionResult::FatalExternalError`]: revm_interpreter::InstructionResult::FatalExternalError
+    ///
+    /// Defaults to `false`.
+    pub fn validate_resume_at(&mut self, yes: bool) {
+        self.config.validate_resume_at = yes;
+    }
+
+    /// Sets whether to count the executed opcodes in [`EvmContext::opcode_counts`].
+    ///
+    /// This is instrumentation for profiling: it adds a check and an increment to every
+    /// instruction, so it should not be enabled in production. Functions compiled without it
+    /// never touch `opcode_counts`.
+    ///
+    /// Defaults to `false`.
+    pub fn opcode_counts(&mut self, yes: bool) {
+        self.config.opcode_counts = yes;
+    }
+
+    /// Sets whether to record the executed basic blocks in [`EvmContext::block_profile`].
+    ///
+    /// This is instrumentation for profiling: it adds a builtin call to the start of every block,
+    /// so it should not be enabled in production. The recorded [`ExecutionProfile`] can then be
+    /// passed to [`translate_with_profile`](Self::translate_with_profile). Functions compiled
+    /// without it never touch `block_profile`.
+    ///
+    /// Defaults to `false`.
+    pub fn block_profile(&mut self, yes: bool) {
+        self.config.block_profile = yes;
+    }
+
+    /// Sets whether to decrement [`EvmContext::step_budget`] at every instruction, failing with
+    /// [`FatalExternalError`] once it is exhausted.
+    ///
+    /// This guarantees termination independently of gas metering, at the cost of a check on every
+    /// instruction. Functions compiled without it never touch `step_budget`.
+    ///
+    /// [`FatalExternalError`]: revm_interpreter::InstructionResult::FatalExternalError
+    ///
+    /// Defaults to `false`.
+    pub fn step_budget(&mut self, yes: bool) {
+        self.config.step_budget = yes;
+    }
+
+    /// Sets whether to report the result of the function to [`EvmContext::result_sink`], with the
+    /// pc of the instruction that produced it, whenever the function returns or suspends.
+    ///
+    /// This is instrumentation for debugging, such as locating reverts: it adds a store to every
+    /// instruction and a builtin call to the return block. Functions compiled without it never
+    /// touch `result_sink`.
+    ///
+    /// Defaults to `false`.
+    pub fn result_sink(&mut self, yes: bool) {
+        self.config.result_sink = yes;
+    }
+
+    /// Specializes the generated code for the given [`EvmContext::is_static`] value.
+    ///
+    /// If `Some(true)`, state-changing instructions, such as `SSTORE`, `LOG` and `CREATE`, compile
+    /// directly to a [`StateChangeDuringStaticCall`] failure. If `Some(false)`, the checks are
+    /// omitted, and the function must only be called in a non-static context. If `None`, the
+    /// context is checked at runtime.
+    ///
+    /// The specialization is recorded in the function's [`FnInfo`], which is checked by
+    /// [`EvmCompilerFn::call_checked`].
+    ///
+    /// [`StateChangeDuringStaticCall`]: revm_interpreter::InstructionResult::StateChangeDuringStaticCall
+    ///
+    /// Defaults to `None`.
+    pub fn static_context(&mut self, is_static: Option<bool>) {
+        self.config.static_context = is_static;
+    }
+
+    /// Sets the gas costs to compile with, overriding the costs of the `SpecId` that the bytecode
+    /// is compiled for.
+    ///
+    /// The base costs are compiled into the function. The dynamic cost parameters are read at
+    /// runtime from [`EvmContext::gas_params`], which must be set to the same parameters. They are
+    /// recorded in the function's [`FnInfo`], which is checked by
+    /// [`EvmCompilerFn::call_checked`].
+    ///
+    /// Defaults to `None`.
+    pub fn gas_table(&mut self, table: Option<GasTable>) {
+        self.config.gas_table = table;
+    }
+
+    /// Sets the maximum number of instructions of an internal function to inline at its call sites.
+    ///
+    /// Internal functions are recognized by the `PUSH ret ... PUSH fn JUMP` call idiom, where the
+    /// callee returns with a single `JUMP` to the pushed return address. Inlining removes the
+    /// dynamic jump on return at the cost of code size. Gas usage is not affected.
+    ///
+    /// Set to `0` to disable inlining.
+    ///
+    /// Defaults to `32`.
+    pub fn inline_threshold(&mut self, threshold: usize) {
+        self.config.inline_threshold = threshold;
+    }
+
+    /// Sets whether to optimize the generated code for speed or for size.
+    ///
+    /// In [`CodegenTier::Auto`] mode, functions with more than
+    /// [`size_tier_threshold`](Self::size_tier_threshold) instructions are optimized for size, and
+    /// the rest for speed. The chosen tier is reported in [`CompilationStats::codegen_tier`].
+    ///
+    /// Only the LLVM backend optimizes for size.
+    ///
+    /// Defaults to [`CodegenTier::Auto`].
+    pub fn codegen_tier(&mut self, tier: CodegenTier) {
+        self.config.codegen_tier = tier;
+    }
+
+    /// Sets the number of instructions above which functions are optimized for size in
+    /// [`CodegenTier::Auto`] mode.
+    ///
+    /// Dead code is not counted.
+    ///
+    /// Defaults to `8192`.
+    pub fn size_tier_threshold(&mut self, threshold: usize) {
+        self.config.size_tier_threshold = threshold;
+    }
+
+    /// Sets whether to skip computing values that are never used.
+    ///
+    /// This removes pure instructions whose results are immediately popped, and, if the stack is
+    /// not observable after the function returns (see [`local_stack`](Self::local_stack) and
+    /// [`inspect_stack_length`](Self::inspect_stack_length)), the ones whose results are left on
+    /// the stack at `STOP` or below the operands of `RETURN`. Gas usage and stack bound checks are
+    /// not affected.
+    ///
+    /// Defaults to `true`.
+    pub fn opt_dead_code(&mut self, yes: bool) {
+        self.config.opt_dead_code = yes;
+    }
+
+    /// Sets whether to compute mapping storage slots, `keccak256(key ++ slot)`, without storing
+    /// `key` and `slot` in the scratch space first.
+    ///
+    /// This is only done if the scratch space is overwritten before being read again, so the
+    /// result and the gas usage are not affected.
+    ///
+    /// Defaults to `true`.
+    pub fn opt_map_slot(&mut self, yes: bool) {
+        self.config.opt_map_slot = yes;
+    }
+
+    /// Sets whether to reuse the value stored by an `MSTORE` at a constant offset for an `MLOAD`
+    /// at the same offset that follows it in the same basic block, instead of loading it again.
+    ///
+    /// The value is still stored, and only forwarded if nothing that may write to memory or
+    /// suspend execution comes in between, so the result and the gas usage are not affected.
+    ///
+    /// Defaults to `true`.
+    pub fn opt_memory_forwarding(&mut self, yes: bool) {
+        self.config.opt_memory_forwarding = yes;
+    }
+
+    /// Registers a user-defined opcode, replacing any previously registered one.
+    ///
+    /// The opcode must not be defined in the spec of the bytecode that is translated, otherwise
+    /// translation fails. Custom opcodes are only supported in legacy bytecode.
+    ///
+    /// See [`CustomOpcode`] for more information.
+    pub fn register_custom_opcode(&mut self, opcode: u8, custom: CustomOpcode) {
+        self.custom_opcodes.insert(opcode, custom);
     }
 
     /// Translates the given EVM bytecode into an internal function.
     ///
+    /// See [`CompileError`] for the ways in which this can fail.
+    ///
     /// NOTE: `name` must be unique for each function, as it is used as the name of the final
     /// symbol.
     pub fn translate<'a>(
@@ -235,11 +510,83 @@ impl<B: Backend> EvmCompiler<B> {
         name: &str,
         input: impl Into<EvmCompilerInput<'a>>,
         spec_id: SpecId,
-    ) -> Result<B::FuncId> {
-        ensure!(cfg!(target_endian = "little"), "only little-endian is supported");
-        ensure!(!self.finalized, "cannot compile more functions after finalizing the module");
-        let bytecode = self.parse(input.into(), spec_id)?;
-        self.translate_inner(name, &bytecode)
+    ) -> Result<B::FuncId, CompileError> {
+        ensure_usage!(!self.finalized, "cannot compile more functions after finalizing the module");
+        let input = input.into();
+        if self.deterministic {
+            return Ok(self.defer(name, input, spec_id, None, None)?);
+        }
+        let bytecode = self.parse(input, spec_id)?;
+        Ok(self.translate_inner(name, &bytecode, None, None)?)
+    }
+
+    /// Translates the given EVM bytecode into an internal function, laying out the code according
+    /// to the block execution counts in `profile`.
+    ///
+    /// Blocks that were never executed are marked as cold, and conditional jumps are weighted by
+    /// how often they were taken. The profile is usually recorded by a function compiled with
+    /// [`block_profile`](Self::block_profile) enabled.
+    ///
+    /// Fails with [`CompileError::InvalidUsage`] if the profile was not recorded for `input`.
+    ///
+    /// See [`translate`](Self::translate) for more information.
+    pub fn translate_with_profile<'a>(
+        &mut self,
+        name: &str,
+        input: impl Into<EvmCompilerInput<'a>>,
+        spec_id: SpecId,
+        profile: &ExecutionProfile,
+    ) -> Result<B::FuncId, CompileError> {
+        ensure_usage!(!self.finalized, "cannot compile more functions after finalizing the module");
+        let input = input.into();
+        ensure_usage!(
+            profile.is_for(input.code()),
+            "profile code hash {} does not match the bytecode",
+            profile.code_hash
+        );
+        if self.deterministic {
+            return Ok(self.defer(name, input, spec_id, None, Some(profile))?);
+        }
+        let bytecode = self.parse(input, spec_id)?;
+        Ok(self.translate_inner(name, &bytecode, None, Some(profile))?)
+    }
+
+    /// Translates a copy of `base_fn_name` that is specialized for calls to `selector`.
+    ///
+    /// The function dispatch is resolved at compile time as if calldata started with `selector`,
+    /// and the comparisons with other selectors are removed. At runtime, the function checks the
+    /// actual selector on entry and tail-calls `base_fn_name` if it differs. Gas accounting is the
+    /// same on both paths.
+    ///
+    /// `base_fn_name` must already be translated in this module from the same `input` and
+    /// `spec_id`, with the same configuration. Only legacy bytecode with a Solidity-style dispatch
+    /// is specialized; otherwise this behaves like [`translate`](Self::translate), with the
+    /// additional guard.
+    ///
+    /// NOTE: `name` must be unique for each function, as it is used as the name of the final
+    /// symbol.
+    pub fn specialize_for_selector<'a>(
+        &mut self,
+        name: &str,
+        base_fn_name: &str,
+        input: impl Into<EvmCompilerInput<'a>>,
+        spec_id: SpecId,
+        selector: [u8; 4],
+    ) -> Result<B::FuncId, CompileError> {
+        ensure_usage!(!self.finalized, "cannot compile more functions after finalizing the module");
+        ensure_usage!(
+            !self.backend.function_name_is_unique(base_fn_name),
+            "base function `{base_fn_name}` not found"
+        );
+        let selector = u32::from_be_bytes(selector);
+        let input = input.into();
+        let selector_guard = Some((selector, base_fn_name));
+        if self.deterministic {
+            return Ok(self.defer(name, input, spec_id, selector_guard, None)?);
+        }
+        let bytecode = self.parse_inner(input, spec_id, Some(selector))?;
+        ensure_usage!(!bytecode.is_eof(), "selector specialization is not supported in EOF");
+        Ok(self.translate_inner(name, &bytecode, selector_guard, None)?)
     }
 
     /// (JIT) Compiles the given EVM bytecode into a JIT function.
@@ -255,27 +602,85 @@ impl<B: Backend> EvmCompiler<B> {
         name: &str,
         bytecode: impl Into<EvmCompilerInput<'a>>,
         spec_id: SpecId,
-    ) -> Result<EvmCompilerFn> {
+    ) -> Result<EvmCompilerFn, CompileError> {
         let id = self.translate(name, bytecode.into(), spec_id)?;
         unsafe { self.jit_function(id) }
     }
 
+    /// (JIT) Compiles the given EVM bytecode into a JIT function, laying out the code according to
+    /// `profile`.
+    ///
+    /// See [`translate_with_profile`](Self::translate_with_profile) for more information.
+    ///
+    /// # Safety
+    ///
+    /// The returned function pointer is owned by the module, and must not be called after the
+    /// module is cleared or the function is freed.
+    pub unsafe fn jit_with_profile<'a>(
+        &mut self,
+        name: &str,
+        bytecode: impl Into<EvmCompilerInput<'a>>,
+        spec_id: SpecId,
+        profile: &ExecutionProfile,
+    ) -> Result<EvmCompilerFn, CompileError> {
+        let id = self.translate_with_profile(name, bytecode, spec_id, profile)?;
+        unsafe { self.jit_function(id) }
+    }
+
     /// (JIT) Finalizes the module and JITs the given function.
     ///
     /// # Safety
     ///
     /// The returned function pointer is owned by the module, and must not be called after the
     /// module is cleared or the function is freed.
-    pub unsafe fn jit_function(&mut self, id: B::FuncId) -> Result<EvmCompilerFn> {
-        ensure!(self.is_jit(), "cannot JIT functions during AOT compilation");
+    pub unsafe fn jit_function(&mut self, id: B::FuncId) -> Result<EvmCompilerFn, CompileError> {
+        ensure_usage!(self.is_jit(), "cannot JIT functions during AOT compilation");
+        self.check_signature(id, FnSignature::Default)?;
         self.finalize()?;
         let addr = self.backend.jit_function(id)?;
         debug_assert!(addr != 0);
-        Ok(EvmCompilerFn::new(unsafe { std::mem::transmute::<usize, RawEvmCompilerFn>(addr) }))
+        let f = unsafe { std::mem::transmute::<usize, RawEvmCompilerFn>(addr) };
+        Ok(match self.get_fn_info(id) {
+            Some(info) => EvmCompilerFn::new_with_meta(f, info.meta),
+            None => EvmCompilerFn::new(f),
+        })
+    }
+
+    /// (JIT) Finalizes the module and JITs the given function, which must have been translated
+    /// without the gas parameter.
+    ///
+    /// See [`no_gas_signature`](Self::no_gas_signature) for more information.
+    ///
+    /// # Safety
+    ///
+    /// See [`jit_function`](Self::jit_function).
+    pub unsafe fn jit_function_no_gas(
+        &mut self,
+        id: B::FuncId,
+    ) -> Result<EvmCompilerFnNoGas, CompileError> {
+        ensure_usage!(self.is_jit(), "cannot JIT functions during AOT compilation");
+        self.check_signature(id, FnSignature::NoGas)?;
+        self.finalize()?;
+        let addr = self.backend.jit_function(id)?;
+        debug_assert!(addr != 0);
+        Ok(EvmCompilerFnNoGas::new(unsafe {
+            std::mem::transmute::<usize, RawEvmCompilerFnNoGas>(addr)
+        }))
+    }
+
+    fn check_signature(&self, id: B::FuncId, signature: FnSignature) -> Result<()> {
+        if let Some(info) = self.get_fn_info(id) {
+            ensure_usage!(
+                info.signature == signature,
+                "function was compiled with signature `{:?}`",
+                info.signature
+            );
+        }
+        Ok(())
     }
 
     /// (AOT) Writes the compiled object to the given file.
-    pub fn write_object_to_file(&mut self, path: &Path) -> Result<()> {
+    pub fn write_object_to_file(&mut self, path: &Path) -> Result<(), CompileError> {
         let file = fs::File::create(path)?;
         let mut writer = io::BufWriter::new(file);
         self.write_object(&mut writer)?;
@@ -284,10 +689,84 @@ impl<B: Backend> EvmCompiler<B> {
     }
 
     /// (AOT) Finalizes the module and writes the compiled object to the given writer.
-    pub fn write_object<W: io::Write>(&mut self, w: W) -> Result<()> {
-        ensure!(self.is_aot(), "cannot write AOT object during JIT compilation");
+    pub fn write_object<W: io::Write>(&mut self, w: W) -> Result<(), CompileError> {
+        ensure_usage!(self.is_aot(), "cannot write AOT object during JIT compilation");
+        if self.deterministic && !self.finalized {
+            self.translate_deterministic()?;
+        }
         self.finalize()?;
-        self.backend.write_object(w)
+        Ok(self.backend.write_object(w)?)
+    }
+
+    /// (AOT) Finalizes the module and links it into a standalone WebAssembly module.
+    ///
+    /// The backend must target `wasm32`. The module exports the translated functions with the
+    /// signature of [`RawEvmCompilerFn`], where pointers are `i32` addresses in its linear memory,
+    /// which is exported as `memory`. The caller lays out the arguments in linear memory after the
+    /// exported `__heap_base`, with the fixed layouts of [`wasm32`](crate::wasm32).
+    ///
+    /// Builtins are imported from the `env` module with their symbol names and the same
+    /// parameters, flattened to `i32`s, except that the context is a
+    /// [`wasm32::EvmContext`](crate::wasm32::EvmContext). Runtime library functions that LLVM
+    /// calls, such as `__multi3`, are imported the same way.
+    ///
+    /// Linking requires `wasm-ld`, see [`Linker::link_wasm`](crate::Linker::link_wasm).
+    pub fn emit_wasm(&mut self) -> Result<Vec<u8>, CompileError> {
+        let target = self.backend.target();
+        ensure_usage!(
+            is_wasm32(target),
+            "WebAssembly modules require a wasm32 target, got {target:?}"
+        );
+        let dir = tempfile::tempdir()?;
+        let object = dir.path().join("module.o");
+        let module = dir.path().join("module.wasm");
+        self.write_object_to_file(&object)?;
+        crate::Linker::new().link_wasm(&module, [&object])?;
+        Ok(fs::read(module)?)
+    }
+
+    /// Returns the number of resume points of the function with the given name, or `None` if the
+    /// function was not translated by this compiler.
+    ///
+    /// This is keyed by name rather than by [`FuncId`](Backend::FuncId), since function IDs may be
+    /// reused by the backend after the function is freed.
+    ///
+    /// When resume points are indexes, the valid [`EvmContext::resume_at`] values are `0`, to start
+    /// execution, and `1..=n` to resume after the `n`th suspension point. Resume points are always
+    /// indexes if [`validate_resume_at`](Self::validate_resume_at) is set, otherwise the backend
+    /// may use code addresses instead. Which one is used is recorded in
+    /// [`FnMeta::resume_indexes`](revmc_context::FnMeta::resume_indexes).
+    pub fn resume_points(&self, name: &str) -> Option<u32> {
+        self.fn_info_by_name(name).map(|info| info.resume_points)
+    }
+
+    /// Returns information about how the given function was compiled, or `None` if the function
+    /// was not translated by this compiler.
+    ///
+    /// Pass this to [`EvmCompilerFn::call_checked`] to check the arguments when calling the
+    /// function.
+    pub fn fn_info(&self, id: B::FuncId) -> Option<FnInfo> {
+        self.get_fn_info(id).copied()
+    }
+
+    /// Returns information about how the function with the given name was compiled, or `None` if
+    /// the function was not translated by this compiler.
+    ///
+    /// See [`fn_info`](Self::fn_info).
+    pub fn fn_info_by_name(&self, name: &str) -> Option<FnInfo> {
+        self.fn_infos.get(name).copied()
+    }
+
+    fn get_fn_info(&self, id: B::FuncId) -> Option<&FnInfo> {
+        self.fn_infos.get(self.fn_names.get(&id)?)
+    }
+
+    /// Returns statistics about the compilation of the given function, or `None` if the function
+    /// was not translated by this compiler.
+    pub fn stats(&self, id: B::FuncId) -> Option<CompilationStats> {
+        let mut stats = self.stats.get(&id).copied()?;
+        stats.machine_code_bytes = self.backend.function_size(id);
+        Some(stats)
     }
 
     /// (JIT) Frees the memory associated with a single function.
@@ -303,6 +782,10 @@ impl<B: Backend> EvmCompiler<B> {
     /// should only be used when none of the functions from that module are currently executing and
     /// none of the `fn` pointers are called afterwards.
     pub unsafe fn free_function(&mut self, id: B::FuncId) -> Result<()> {
+        if let Some(name) = self.fn_names.remove(&id) {
+            self.fn_infos.remove(&name);
+        }
+        self.stats.remove(&id);
         self.backend.free_function(id)
     }
 
@@ -315,7 +798,11 @@ impl<B: Backend> EvmCompiler<B> {
     /// should only be used when none of the functions from that module are currently executing and
     /// none of the `fn` pointers are called afterwards.
     pub unsafe fn clear(&mut self) -> Result<()> {
+        self.deterministic_fns.clear();
         self.builtins.clear();
+        self.fn_infos.clear();
+        self.fn_names.clear();
+        self.stats.clear();
         self.finalized = false;
         self.backend.free_all_functions()
     }
@@ -326,6 +813,15 @@ impl<B: Backend> EvmCompiler<B> {
         &mut self,
         input: EvmCompilerInput<'a>,
         spec_id: SpecId,
+    ) -> Result<Bytecode<'a>> {
+        self.parse_inner(input, spec_id, None)
+    }
+
+    fn parse_inner<'a>(
+        &mut self,
+        input: EvmCompilerInput<'a>,
+        spec_id: SpecId,
+        selector: Option<u32>,
     ) -> Result<Bytecode<'a>> {
         let bytecode;
         let eof;
@@ -333,7 +829,9 @@ impl<B: Backend> EvmCompiler<B> {
             EvmCompilerInput::Code(code) => {
                 bytecode = code;
                 if spec_id.is_enabled_in(SpecId::OSAKA) && code.starts_with(&EOF_MAGIC_BYTES) {
-                    eof = Some(Cow::Owned(Eof::decode(Bytes::copy_from_slice(code))?));
+                    let decoded = Eof::decode(Bytes::copy_from_slice(code))
+                        .map_err(|e| CompileError::InvalidBytecode(e.to_string()))?;
+                    eof = Some(Cow::Owned(decoded));
                 } else {
                     eof = None;
                 }
@@ -343,12 +841,23 @@ impl<B: Backend> EvmCompiler<B> {
                 eof = Some(Cow::Borrowed(e));
             }
         }
+        if let Some(max) = self.config.max_code_size {
+            let size = bytecode.len();
+            if size > max {
+                return Err(CompileError::LimitExceeded(Limit::CodeSize { size, max }).into());
+            }
+        }
         if let Some(eof) = &eof {
             self.do_validate_eof(eof)?;
         }
 
         let mut bytecode = Bytecode::new(bytecode, eof, spec_id);
-        bytecode.analyze()?;
+        let mut config = self.config.analysis_config();
+        if !self.custom_opcodes.is_empty() {
+            config.custom_opcodes = Some(&self.custom_opcodes);
+        }
+        config.selector = selector;
+        bytecode.analyze(config)?;
         if let Some(dump_dir) = &self.dump_dir() {
             Self::dump_bytecode(dump_dir, &bytecode)?;
         }
@@ -359,24 +868,133 @@ impl<B: Backend> EvmCompiler<B> {
         if !self.config.validate_eof {
             return Ok(());
         }
-        revm_interpreter::analysis::validate_eof_inner(eof, None).map_err(|e| match e {
-            revm_interpreter::analysis::EofError::Decode(e) => e.into(),
-            revm_interpreter::analysis::EofError::Validation(e) => {
-                eyre!("validation error: {e:?}")
-            }
-        })
+        validate_eof(eof).map_err(|e| CompileError::InvalidBytecode(e.to_string()).into())
     }
 
-    #[instrument(name = "translate", level = "debug", skip_all)]
-    fn translate_inner(&mut self, name: &str, bytecode: &Bytecode<'_>) -> Result<B::FuncId> {
-        ensure!(self.backend.function_name_is_unique(name), "function name `{name}` is not unique");
+    #[cfg_attr(feature = "tracing", instrument(name = "translate", level = "debug", skip_all))]
+    fn translate_inner(
+        &mut self,
+        name: &str,
+        bytecode: &Bytecode<'_>,
+        selector_guard: Option<(u32, &str)>,
+        profile: Option<&ExecutionProfile>,
+    ) -> Result<B::FuncId> {
+        ensure_usage!(
+            self.backend.function_name_is_unique(name),
+            "function name `{name}` is not unique"
+        );
+        ensure_usage!(
+            self.config.gas_constant.is_none() || self.config.gas_mode == GasMode::Disabled,
+            "functions without the gas parameter require gas metering to be disabled"
+        );
+        let mut config = self.config;
+        config.little_endian = self.backend.is_little_endian();
+        config.wasm32 = is_wasm32(self.backend.target());
         let linkage = Linkage::Public;
-        let (bcx, id) = Self::make_builder(&mut self.backend, &self.config, name, linkage)?;
-        FunctionCx::translate(bcx, self.config, &mut self.builtins, bytecode)?;
+        let (mut bcx, id) = Self::make_builder(&mut self.backend, &config, name, linkage)?;
+        let codegen_tier = self.config.codegen_tier(bytecode);
+        if codegen_tier == CodegenTier::Size {
+            for attr in [Attribute::OptimizeForSize, Attribute::MinSize] {
+                bcx.add_function_attribute(None, attr, FunctionAttributeLocation::Function);
+            }
+        }
+        let (resume_points, resume_indexes) = FunctionCx::translate(
+            bcx,
+            config,
+            &mut self.builtins,
+            &self.custom_opcodes,
+            bytecode,
+            selector_guard,
+            profile,
+        )?;
+        let mut info = FnInfo::default();
+        info.resume_points = resume_points as u32;
+        info.static_context = self.config.static_context;
+        info.gas_params = self.config.gas_table.map(|table| table.params).unwrap_or_default();
+        info.assertions = self.config.assertions;
+        info.gas_mode = self.config.gas_mode;
+        info.signature = self.config.signature();
+        info.meta = self.config.meta(bytecode);
+        info.meta.resume_indexes = resume_indexes;
+        self.fn_infos.insert(name.to_string(), info);
+        self.fn_names.insert(id, name.to_string());
+        let stats = CompilationStats {
+            code_bytes: bytecode.code.len(),
+            dead_code_bytes: bytecode.dead_code_bytes(),
+            machine_code_bytes: None,
+            codegen_tier,
+        };
+        self.stats.insert(id, stats);
         Ok(id)
     }
 
-    #[instrument(level = "debug", skip_all)]
+    /// Records a function to translate when the object is written, and declares it.
+    ///
+    /// See [`deterministic`](Self::deterministic).
+    fn defer(
+        &mut self,
+        name: &str,
+        input: EvmCompilerInput<'_>,
+        spec_id: SpecId,
+        selector_guard: Option<(u32, &str)>,
+        profile: Option<&ExecutionProfile>,
+    ) -> Result<B::FuncId> {
+        ensure_usage!(
+            self.backend.function_name_is_unique(name),
+            "function name `{name}` is not unique"
+        );
+        // The declaration is discarded with the rest of the module before translating.
+        let id = self.backend.declare_function(name, None, &[], Linkage::Import)?;
+        self.deterministic_fns.push(DeterministicFn {
+            name: name.to_string(),
+            code: Bytes::copy_from_slice(input.code()),
+            spec_id,
+            selector_guard: selector_guard.map(|(selector, base)| (selector, base.to_string())),
+            profile: profile.cloned(),
+            config: self.config,
+        });
+        Ok(id)
+    }
+
+    /// Translates the recorded functions into a new module, sorted by name.
+    ///
+    /// Specialized functions are translated after all the others, since their guard refers to
+    /// their base function.
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
+    fn translate_deterministic(&mut self) -> Result<()> {
+        let target = self.backend.target();
+        ensure_usage!(
+            matches!(target, Target::Triple { cpu: Some(_), features: Some(_), .. }),
+            "deterministic compilation requires an explicit target CPU and features, got {target:?}"
+        );
+
+        let mut fns = mem::take(&mut self.deterministic_fns);
+        fns.sort_by(|a, b| {
+            (a.selector_guard.is_some(), &a.name).cmp(&(b.selector_guard.is_some(), &b.name))
+        });
+        unsafe { self.clear() }?;
+        if let Some(name) = &self.name {
+            self.backend.set_module_name(name);
+        }
+        let config = self.config;
+        let result = fns.iter().try_for_each(|f| {
+            self.config = f.config;
+            let selector = f.selector_guard.as_ref().map(|(selector, _)| *selector);
+            let bytecode =
+                self.parse_inner(EvmCompilerInput::Code(&f.code), f.spec_id, selector)?;
+            ensure_usage!(
+                selector.is_none() || !bytecode.is_eof(),
+                "selector specialization is not supported in EOF"
+            );
+            let selector_guard = f.selector_guard.as_ref().map(|(s, base)| (*s, base.as_str()));
+            self.translate_inner(&f.name, &bytecode, selector_guard, f.profile.as_ref()).map(drop)
+        });
+        self.config = config;
+        self.deterministic_fns = fns;
+        result
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn finalize(&mut self) -> Result<()> {
         if self.finalized {
             return Ok(());
@@ -413,7 +1031,7 @@ impl<B: Backend> EvmCompiler<B> {
         Ok(())
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn make_builder<'a>(
         backend: &'a mut B,
         config: &FcxConfig,
@@ -437,35 +1055,52 @@ impl<B: Backend> EvmCompiler<B> {
                 "arg.contract.addr",
                 "arg.ecx.addr",
             ],
-            &[
-                size_align::<Gas>(0),
-                size_align::<EvmStack>(1),
-                size_align::<usize>(2),
-                size_align::<Env>(3),
-                size_align::<Contract>(4),
-                size_align::<EvmContext<'_>>(5),
-            ],
+            &if config.wasm32 {
+                [
+                    size_align::<wasm32::Gas>(0),
+                    size_align::<EvmStack>(1),
+                    size_align::<u32>(2),
+                    size_align::<wasm32::Env>(3),
+                    size_align::<wasm32::ContractView>(4),
+                    size_align::<wasm32::EvmContext>(5),
+                ]
+            } else {
+                [
+                    size_align::<Gas>(0),
+                    size_align::<EvmStack>(1),
+                    size_align::<usize>(2),
+                    size_align::<Env>(3),
+                    size_align::<ContractView>(4),
+                    size_align::<EvmContext<'_>>(5),
+                ]
+            },
         );
         debug_assert_eq!(params.len(), param_names.len());
+        // Drop the gas parameter, see `FnSignature::NoGas`.
+        let skip = params.len() - config.num_params();
+        let (params, param_names, ptr_attrs) =
+            (&params[skip..], &param_names[skip..], &ptr_attrs[skip..]);
         let (mut bcx, id) = backend.build_function(name, ret, params, param_names, linkage)?;
 
         // Function attributes.
         let function_attributes = default_attrs::for_fn()
             .chain(config.frame_pointers.then_some(Attribute::AllFramePointers))
             // We can unwind in panics, which are present only in debug assertions.
-            .chain((!config.debug_assertions).then_some(Attribute::NoUnwind));
+            .chain((config.assertions != Assertions::Check).then_some(Attribute::NoUnwind));
         for attr in function_attributes {
             bcx.add_function_attribute(None, attr, FunctionAttributeLocation::Function);
         }
 
         // Pointer argument attributes.
-        if !config.debug_assertions {
+        if config.assertions != Assertions::Check {
             for &(i, size, align) in ptr_attrs {
                 let attrs = default_attrs::for_sized_ptr((size, align))
                     // `Gas` is aliased in `EvmContext`.
-                    .chain((i != 0).then_some(Attribute::NoAlias));
+                    .chain((i != 0).then_some(Attribute::NoAlias))
+                    // The environment and the contract are never written to during execution.
+                    .chain(matches!(i, 3 | 4).then_some(Attribute::ReadOnly));
                 for attr in attrs {
-                    let loc = FunctionAttributeLocation::Param(i as _);
+                    let loc = FunctionAttributeLocation::Param((i - skip) as _);
                     bcx.add_function_attribute(None, attr, loc);
                 }
             }
@@ -474,27 +1109,27 @@ impl<B: Backend> EvmCompiler<B> {
         Ok((bcx, id))
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn dump_ir(&mut self, path: &Path) -> Result<()> {
         self.backend.dump_ir(path)
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn dump_disasm(&mut self, path: &Path) -> Result<()> {
         self.backend.dump_disasm(path)
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn verify_module(&mut self) -> Result<()> {
         self.backend.verify_module()
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn optimize_module(&mut self) -> Result<()> {
         self.backend.optimize_module()
     }
 
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     fn dump_bytecode(dump_dir: &Path, bytecode: &Bytecode<'_>) -> Result<()> {
         {
             let file = fs::File::create(dump_dir.join("bytecode.txt"))?;
@@ -525,7 +1160,44 @@ impl<B: Backend> EvmCompiler<B> {
     }
 }
 
+/// Statistics about the compilation of a single function.
+///
+/// Returned by [`EvmCompiler::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CompilationStats {
+    /// The size of the bytecode, in bytes.
+    pub code_bytes: usize,
+    /// The number of bytes of unreachable bytecode, for which no code was generated.
+    pub dead_code_bytes: usize,
+    /// The size of the machine code, in bytes, once the function has been JIT-compiled.
+    ///
+    /// `None` before then, or if the backend does not report it.
+    pub machine_code_bytes: Option<usize>,
+    /// The code generation tier that the function was compiled with, which is never
+    /// [`CodegenTier::Auto`] once the function has been translated.
+    pub codegen_tier: CodegenTier,
+}
+
+/// Whether to optimize the generated code for speed or for size.
+///
+/// See [`EvmCompiler::codegen_tier`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CodegenTier {
+    /// Optimize small functions for speed and large functions for size.
+    #[default]
+    Auto,
+    /// Optimize for speed.
+    Speed,
+    /// Optimize for size, like `-Oz`: no loop unrolling, less inlining, and outlining of repeated
+    /// machine code where supported.
+    ///
+    /// This reduces instruction cache pressure when many large contracts are compiled.
+    Size,
+}
+
 /// [`EvmCompiler`] input.
+#[derive(Clone, Copy)]
 #[allow(missing_debug_implementations)]
 pub enum EvmCompilerInput<'a> {
     /// EVM bytecode. Can also be raw EOF code, which will be parsed.
@@ -534,6 +1206,16 @@ pub enum EvmCompilerInput<'a> {
     Eof(&'a Eof),
 }
 
+impl<'a> EvmCompilerInput<'a> {
+    /// Returns the raw code.
+    fn code(&self) -> &'a [u8] {
+        match *self {
+            EvmCompilerInput::Code(code) => code,
+            EvmCompilerInput::Eof(eof) => &eof.raw[..],
+        }
+    }
+}
+
 impl<'a> From<&'a [u8]> for EvmCompilerInput<'a> {
     fn from(code: &'a [u8]) -> Self {
         EvmCompilerInput::Code(code)
@@ -558,6 +1240,11 @@ impl<'a> From<&'a Eof> for EvmCompilerInput<'a> {
     }
 }
 
+/// Returns `true` if the target is `wasm32`, which uses the layouts of [`wasm32`](crate::wasm32).
+fn is_wasm32(target: &Target) -> bool {
+    matches!(target, Target::Triple { triple, .. } if triple.starts_with("wasm32"))
+}
+
 #[allow(dead_code)]
 mod default_attrs {
     use revmc_backend::Attribute;
@@ -1,47 +1,165 @@
 //! EVM to IR translation.
 
-use super::default_attrs;
+use super::{default_attrs, CodegenTier, CompileError, CustomOpcode};
 use crate::{
-    Backend, Builder, Bytecode, EvmContext, Inst, InstData, InstFlags, IntCC, Result, I256_MIN,
+    wasm32, AnalysisConfig, Assertions, Backend, Builder, Bytecode, ContractView, EvmContext,
+    ExecutionProfile, FnMeta, FnSignature, GasMode, GasTable, Inst, InstData, InstFlags, IntCC,
+    Result, I256_MIN,
 };
 use revm_interpreter::{
-    opcode as op, Contract, FunctionReturnFrame, FunctionStack, InstructionResult,
-    OPCODE_INFO_JUMPTABLE,
+    opcode as op, FunctionReturnFrame, FunctionStack, InstructionResult, OPCODE_INFO_JUMPTABLE,
 };
 use revm_primitives::{BlockEnv, CfgEnv, Env, Eof, SpecId, TxEnv, U256};
 use revmc_backend::{
-    eyre::ensure, Attribute, BackendTypes, FunctionAttributeLocation, Pointer, TypeMethods,
+    Attribute, BackendTypes, FunctionAttributeLocation, Pointer, TailCallKind, TypeMethods,
 };
 use revmc_builtins::{Builtin, Builtins, CallKind, CreateKind, ExtCallKind, EXTCALL_LIGHT_FAILURE};
-use std::{fmt::Write, mem, sync::atomic::AtomicPtr};
+use rustc_hash::FxHashMap;
+use std::{fmt::Write, mem};
 
 const STACK_CAP: usize = 1024;
+
+/// Returns the offset of a field of a host type, or of its counterpart in [`wasm32`] when
+/// compiling for `wasm32`.
+macro_rules! offset_of {
+    ($config:expr, $host:ty => $wasm32:ident, $field:ident) => {
+        if $config.wasm32 {
+            mem::offset_of!(wasm32::$wasm32, $field)
+        } else {
+            mem::offset_of!($host, $field)
+        }
+    };
+}
 // const WORD_SIZE: usize = 32;
 
 #[derive(Clone, Copy, Debug)]
 pub(super) struct FcxConfig {
     pub(super) comments: bool,
-    pub(super) debug_assertions: bool,
+    pub(super) assertions: Assertions,
     pub(super) frame_pointers: bool,
+    /// Whether the target is little-endian. Set from [`Backend::is_little_endian`].
+    pub(super) little_endian: bool,
+    /// Whether the target is `wasm32`, which uses the layouts of [`wasm32`]. Set from
+    /// [`Backend::target`].
+    pub(super) wasm32: bool,
     pub(super) validate_eof: bool,
+    pub(super) max_code_size: Option<usize>,
 
     pub(super) local_stack: bool,
     pub(super) inspect_stack_length: bool,
     pub(super) stack_bound_checks: bool,
-    pub(super) gas_metering: bool,
+    pub(super) gas_mode: GasMode,
+    /// The value of `GAS` if the function does not take the gas parameter.
+    pub(super) gas_constant: Option<u64>,
+    pub(super) validate_resume_at: bool,
+    pub(super) opcode_counts: bool,
+    pub(super) block_profile: bool,
+    pub(super) step_budget: bool,
+    pub(super) result_sink: bool,
+    pub(super) static_context: Option<bool>,
+    pub(super) gas_table: Option<GasTable>,
+
+    pub(super) inline_threshold: usize,
+    pub(super) codegen_tier: CodegenTier,
+    pub(super) size_tier_threshold: usize,
+    pub(super) opt_dead_code: bool,
+    pub(super) opt_map_slot: bool,
+    pub(super) opt_memory_forwarding: bool,
 }
 
 impl Default for FcxConfig {
     fn default() -> Self {
         Self {
-            debug_assertions: cfg!(debug_assertions),
+            assertions: if cfg!(debug_assertions) { Assertions::Check } else { Assertions::None },
             comments: false,
             frame_pointers: cfg!(debug_assertions),
+            little_endian: cfg!(target_endian = "little"),
+            wasm32: false,
             validate_eof: true,
+            max_code_size: None,
             local_stack: false,
             inspect_stack_length: false,
             stack_bound_checks: true,
-            gas_metering: true,
+            gas_mode: GasMode::Metered,
+            gas_constant: None,
+            validate_resume_at: false,
+            opcode_counts: false,
+            block_profile: false,
+            step_budget: false,
+            result_sink: false,
+            static_context: None,
+            gas_table: None,
+            inline_threshold: 32,
+            codegen_tier: CodegenTier::Auto,
+            size_tier_threshold: 8192,
+            opt_dead_code: true,
+            opt_map_slot: true,
+            opt_memory_forwarding: true,
+        }
+    }
+}
+
+impl FcxConfig {
+    /// Returns the signature of the translated functions.
+    pub(super) fn signature(&self) -> FnSignature {
+        if self.gas_constant.is_some() {
+            FnSignature::NoGas
+        } else {
+            FnSignature::Default
+        }
+    }
+
+    /// Returns the calling convention of the function translated from `bytecode`.
+    pub(super) fn meta(&self, bytecode: &Bytecode<'_>) -> FnMeta {
+        let mut meta = FnMeta::default();
+        meta.local_stack = self.local_stack;
+        meta.inspect_stack_length = self.inspect_stack_length;
+        meta.writes_stack_length = self.inspect_stack_length || bytecode.may_suspend();
+        meta.gas_metered = self.gas_mode != GasMode::Disabled;
+        meta.stack_bound_checks = self.stack_bound_checks;
+        // EOF code is validated to never underflow the stack.
+        if !bytecode.is_eof() {
+            let entry = bytecode.iter_insts().next();
+            meta.min_stack_len = entry.map_or(0, |(_, data)| data.section.inputs);
+        }
+        meta
+    }
+
+    /// Returns the code generation tier of the function translated from `bytecode`, which is never
+    /// [`CodegenTier::Auto`].
+    pub(super) fn codegen_tier(&self, bytecode: &Bytecode<'_>) -> CodegenTier {
+        match self.codegen_tier {
+            CodegenTier::Auto if bytecode.iter_insts().count() > self.size_tier_threshold => {
+                CodegenTier::Size
+            }
+            CodegenTier::Auto => CodegenTier::Speed,
+            tier => tier,
+        }
+    }
+
+    /// Returns the number of parameters of the translated functions.
+    pub(super) fn num_params(&self) -> usize {
+        match self.signature() {
+            FnSignature::Default => 6,
+            FnSignature::NoGas => 5,
+        }
+    }
+
+    /// Maps the index of a parameter in [`FnSignature::Default`] to its index in the signature of
+    /// the translated functions.
+    fn param_index(&self, index: usize) -> usize {
+        index - (6 - self.num_params())
+    }
+
+    pub(super) fn analysis_config(&self) -> AnalysisConfig<'_> {
+        AnalysisConfig {
+            base_gas: self.gas_table.as_ref().map(|table| &table.base),
+            inline_threshold: self.inline_threshold,
+            dead_code: self.opt_dead_code,
+            stack_observable: !self.local_stack || self.inspect_stack_length,
+            map_slot: self.opt_map_slot,
+            custom_opcodes: None,
+            selector: None,
         }
     }
 }
@@ -83,6 +201,8 @@ pub(super) struct FunctionCx<'a, B: Backend> {
     stack: Pointer<B::Builder<'a>>,
     /// The amount of gas remaining. `i64`. See `Gas`.
     gas_remaining: Pointer<B::Builder<'a>>,
+    /// The pc of the current instruction. `i32`. Only allocated if `config.result_sink` is set.
+    pc: Option<Pointer<B::Builder<'a>>>,
     /// The environment. Constant throughout the function.
     env: B::Value,
     /// The contract. Constant throughout the function.
@@ -93,6 +213,21 @@ pub(super) struct FunctionCx<'a, B: Backend> {
     len_before: B::Value,
     /// Stack length offset for the current instruction, used for push/pop.
     len_offset: i8,
+    /// Stack length after the current instruction, and its difference from `len_before`.
+    len_after: (B::Value, i64),
+    /// Stack length to reuse in the given instruction instead of reloading `stack_len`.
+    /// Only set if that instruction can only be reached by falling through from the previous one.
+    next_len: Option<(Inst, B::Value)>,
+    /// Position of `len_before` relative to the start of the current fall-through run.
+    stack_base: i64,
+    /// Stack values known in the current fall-through run, keyed by their position relative to
+    /// the start of the run. Used to avoid reloading values that were just pushed or loaded.
+    stack_values: FxHashMap<i64, B::Value>,
+    /// Words known to be in memory at constant offsets in the current fall-through run. Used to
+    /// forward the values stored by `MSTORE` to the `MLOAD`s that follow.
+    memory_values: FxHashMap<u64, B::Value>,
+    /// The words stored by the `MAP_SLOT` stores of the current mapping slot computation.
+    map_slot_words: [Option<B::Value>; 2],
 
     /// The bytecode being translated.
     bytecode: &'a Bytecode<'a>,
@@ -100,6 +235,10 @@ pub(super) struct FunctionCx<'a, B: Backend> {
     inst_entries: Vec<B::BasicBlock>,
     /// The current instruction being translated.
     current_inst: Inst,
+    /// The profile to optimize the code layout for, if any.
+    profile: Option<&'a ExecutionProfile>,
+    /// The profiled execution count of the block containing the current instruction.
+    block_count: u64,
 
     // Basic blocks are `None` when outside of a main function.
     /// `dynamic_jump_table` incoming values.
@@ -127,6 +266,8 @@ pub(super) struct FunctionCx<'a, B: Backend> {
 
     /// Builtins.
     builtins: &'a mut Builtins<B>,
+    /// User-defined opcodes.
+    custom_opcodes: &'a FxHashMap<u8, CustomOpcode>,
 }
 
 impl<'a, B: Backend> FunctionCx<'a, B> {
@@ -180,17 +321,25 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
     ///     return(ir: InstructionResult): {
     ///         #[cfg(inspect_stack_length)]
     ///         *args.stack_len = stack_len;
+    ///         #[cfg(result_sink)]
+    ///         if let Some(sink) = ecx.result_sink { sink(pc, ir) };
     ///         return ir;
     ///     }
     /// }
     /// ```
     #[allow(rustdoc::invalid_rust_codeblocks)] // Syntax highlighting.
+    ///
+    /// Returns the number of resume points, and whether they are indexes rather than code
+    /// addresses.
     pub(super) fn translate(
         mut bcx: B::Builder<'a>,
         config: FcxConfig,
         builtins: &'a mut Builtins<B>,
+        custom_opcodes: &'a FxHashMap<u8, CustomOpcode>,
         bytecode: &'a Bytecode<'a>,
-    ) -> Result<()> {
+        selector_guard: Option<(u32, &str)>,
+        profile: Option<&'a ExecutionProfile>,
+    ) -> Result<(usize, bool)> {
         let entry_block = bcx.current_block().unwrap();
 
         // Get common types.
@@ -202,27 +351,39 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         let word_type = bcx.type_int(256);
 
         // Set up entry block.
-        let gas_ptr = bcx.fn_param(0);
-        let gas_remaining = {
-            let offset = bcx.iconst(i64_type, mem::offset_of!(pf::Gas, remaining) as i64);
+        let gas_ptr = (config.signature() == FnSignature::Default).then(|| bcx.fn_param(0));
+        let gas_remaining = if let Some(gas_ptr) = gas_ptr {
+            let offset = bcx.iconst(i64_type, offset_of!(config, pf::Gas => Gas, remaining) as i64);
             let name = "gas.remaining.addr";
             Pointer::new_address(i64_type, bcx.gep(i8_type, gas_ptr, &[offset], name))
+        } else {
+            // Never accessed, since gas metering is disabled and `GAS` is a constant.
+            bcx.new_stack_slot(i64_type, "gas.remaining.addr")
         };
 
-        let sp_arg = bcx.fn_param(1);
+        let sp_arg = bcx.fn_param(config.param_index(1));
         let stack = if config.local_stack {
             bcx.new_stack_slot(word_type, "stack.addr")
         } else {
             Pointer::new_address(word_type, sp_arg)
         };
 
-        let stack_len_arg = bcx.fn_param(2);
+        let stack_len_arg = bcx.fn_param(config.param_index(2));
         // This is initialized later in `post_entry_block`.
         let stack_len = bcx.new_stack_slot(isize_type, "len.addr");
 
-        let env = bcx.fn_param(3);
-        let contract = bcx.fn_param(4);
-        let ecx = bcx.fn_param(5);
+        // Stored at every instruction, and passed to the result sink in the return block.
+        let pc = config.result_sink.then(|| {
+            let i32_type = bcx.type_int(32);
+            let pc = bcx.new_stack_slot(i32_type, "pc.addr");
+            let no_pc = bcx.uconst(i32_type, u32::MAX as u64);
+            pc.store(&mut bcx, no_pc);
+            pc
+        });
+
+        let env = bcx.fn_param(config.param_index(3));
+        let contract = bcx.fn_param(config.param_index(4));
+        let ecx = bcx.fn_param(config.param_index(5));
 
         // Create all instruction entry blocks.
         let unreachable_block = bcx.create_block("unreachable");
@@ -254,16 +415,26 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             stack_len,
             stack,
             gas_remaining,
+            pc,
             env,
             contract,
             ecx,
             len_before: bcx.iconst(isize_type, 0),
             len_offset: 0,
+            len_after: (bcx.iconst(isize_type, 0), 0),
+            next_len: None,
+            stack_base: 0,
+            stack_values: FxHashMap::default(),
+            memory_values: FxHashMap::default(),
+            map_slot_words: [None; 2],
             bcx,
 
             bytecode,
             inst_entries,
             current_inst: usize::MAX,
+            // An empty profile has no information, rather than every block being cold.
+            profile: profile.filter(|profile| !profile.is_empty()),
+            block_count: 0,
 
             incoming_dynamic_jumps: Vec::new(),
             dynamic_jump_table,
@@ -279,19 +450,22 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             suspend_block,
 
             builtins,
+            custom_opcodes,
         };
 
         // We store the stack length if requested or necessary due to the bytecode.
-        let stack_length_observable = config.inspect_stack_length || bytecode.may_suspend();
+        let stack_length_observable = config.meta(bytecode).writes_stack_length;
 
         // Add debug assertions for the parameters.
-        if config.debug_assertions {
-            fx.pointer_panic_with_bool(
-                config.gas_metering,
-                gas_ptr,
-                "gas pointer",
-                "gas metering is enabled",
-            );
+        if config.assertions != Assertions::None {
+            if let Some(gas_ptr) = gas_ptr {
+                fx.pointer_panic_with_bool(
+                    config.gas_mode != GasMode::Disabled,
+                    gas_ptr,
+                    "gas pointer",
+                    "gas metering is enabled",
+                );
+            }
             fx.pointer_panic_with_bool(
                 !config.local_stack,
                 sp_arg,
@@ -317,10 +491,22 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         let first_inst_block = fx.inst_entries[0];
         let post_entry_block = fx.bcx.create_block_after(entry_block, "entry.post");
         let resume_block = fx.bcx.create_block_after(post_entry_block, "resume");
-        fx.bcx.br(post_entry_block);
+        if let Some((selector, fallback)) = selector_guard {
+            fx.build_selector_guard(selector, fallback, post_entry_block);
+        } else {
+            fx.bcx.br(post_entry_block);
+        }
 
         // Translate individual instructions into their respective blocks.
+        #[cfg(feature = "tracing")]
+        let mut block_span = None;
         for (inst, _) in bytecode.iter_insts() {
+            #[cfg(feature = "tracing")]
+            if fx.is_block_start(inst) {
+                drop(block_span.take());
+                let pc = bytecode.inst(inst).pc;
+                block_span = Some(debug_span!("translate_block", pc).entered());
+            }
             fx.translate_inst(inst)?;
         }
 
@@ -330,7 +516,9 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         if bytecode.has_dynamic_jumps() {
             fx.bcx.switch_to_block(fx.dynamic_jump_table);
             // TODO: Manually reduce to i32?
-            let jumpdests = bytecode.iter_insts().filter(|(_, data)| data.opcode == op::JUMPDEST);
+            let jumpdests = bytecode.iter_insts().filter(|(_, data)| {
+                data.opcode == op::JUMPDEST && !data.flags.contains(InstFlags::INLINED)
+            });
             // let max_pc =
             //     jumpdests.clone().map(|(_, data)| data.pc).next_back().expect("no jumpdests");
             let targets = jumpdests
@@ -369,7 +557,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             let get_ecx_resume_at_ptr = |fx: &mut Self| {
                 fx.get_field(
                     fx.ecx,
-                    mem::offset_of!(EvmContext<'_>, resume_at),
+                    offset_of!(fx.config, EvmContext<'_> => EvmContext, resume_at),
                     "ecx.resume_at.addr",
                 )
             };
@@ -381,7 +569,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             };
 
             // Resume block: load the `resume_at` value and switch to the corresponding block.
-            // Invalid values are treated as unreachable.
+            // Invalid values are treated as unreachable, unless `validate_resume_at` is set.
             {
                 // Special-case the no resume case to load 0 into the length if possible.
                 let no_resume_block = fx.bcx.create_block_after(resume_block, "no_resume");
@@ -410,7 +598,12 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                     ResumeKind::Indexes => {
                         let default = fx.bcx.create_block_after(resume_block, "resume_invalid");
                         fx.bcx.switch_to_block(default);
-                        fx.call_panic("invalid `resume_at` value");
+                        if fx.config.validate_resume_at {
+                            fx.bcx.set_current_block_cold();
+                            fx.build_return_imm(InstructionResult::FatalExternalError);
+                        } else {
+                            fx.call_panic("invalid `resume_at` value");
+                        }
 
                         fx.bcx.switch_to_block(resume_block);
                         let targets = fx
@@ -464,6 +657,10 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             if stack_length_observable {
                 fx.save_stack_len();
             }
+            if let Some(pc) = &fx.pc {
+                let pc = pc.load(&mut fx.bcx, "pc");
+                let _ = fx.call_builtin(Builtin::ResultSink, &[fx.ecx, pc, return_value]);
+            }
             fx.bcx.ret(&[return_value]);
         } else {
             fx.bcx.unreachable();
@@ -471,10 +668,13 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
         fx.bcx.seal_all_blocks();
 
-        Ok(())
+        Ok((fx.resume_blocks.len(), fx.resume_kind == ResumeKind::Indexes))
     }
 
-    #[instrument(level = "debug", skip_all, fields(inst = %self.bytecode.inst(inst).to_op()))]
+    #[cfg_attr(
+        feature = "tracing",
+        instrument(level = "debug", skip_all, fields(inst = %self.bytecode.inst(inst).to_op()))
+    )]
     fn translate_inst(&mut self, inst: Inst) -> Result<()> {
         self.current_inst = inst;
         let data = self.bytecode.inst(inst);
@@ -484,8 +684,9 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
         let is_eof = self.bytecode.is_eof();
         let is_eof_enabled = self.bytecode.spec_id.is_enabled_in(SpecId::OSAKA);
-        if is_eof {
-            ensure!(is_eof_enabled, "EOF bytecode in non-EOF spec");
+        if is_eof && !is_eof_enabled {
+            let reason = format!("EOF bytecode in non-EOF spec {:?}", self.bytecode.spec_id);
+            return Err(CompileError::InvalidBytecode(reason).into());
         }
 
         // self.call_printf(format_printf!("{}\n", self.op_block_name("")), &[]);
@@ -497,6 +698,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             );
             if let Some(next) = this.inst_entries.get(inst + 1) {
                 this.bcx.br(*next);
+                this.fall_through(inst);
             }
         };
         // Currently a noop.
@@ -534,20 +736,42 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         // Assert that we already skipped the block.
         debug_assert!(!data.flags.contains(InstFlags::DEAD_CODE));
 
+        if let Some(pc) = &self.pc {
+            pc.store_imm(&mut self.bcx, data.pc as i64);
+        }
+
         #[cfg(test)]
         if opcode == crate::TEST_SUSPEND {
             self.suspend();
             goto_return!(no_branch);
         }
 
+        if self.is_block_start(inst) {
+            if self.config.block_profile {
+                let i32_type = self.bcx.type_int(32);
+                let pc = self.bcx.iconst(i32_type, data.pc as i64);
+                let _ = self.call_builtin(Builtin::ProfileBlock, &[self.ecx, pc]);
+            }
+            if let Some(profile) = self.profile {
+                self.block_count = profile.count(data.pc);
+                if self.block_count == 0 {
+                    self.bcx.set_current_block_cold();
+                }
+            }
+        }
+        if self.config.opcode_counts {
+            self.build_count_opcode(opcode);
+        }
+        if self.config.step_budget {
+            self.build_step();
+        }
+
         // This is a compile error because it should've been validated as per EOF.
         if is_eof_enabled && is_eof {
             if let Some(info) = OPCODE_INFO_JUMPTABLE[opcode as usize] {
-                ensure!(
-                    !info.is_disabled_in_eof(),
-                    "disabled opcode in EOF bytecode: {}",
-                    data.to_op_in(self.bytecode),
-                );
+                if info.is_disabled_in_eof() {
+                    return Err(self.unsupported_opcode(inst).into());
+                }
             }
         }
 
@@ -568,26 +792,74 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             goto_return!(fail InstructionResult::NotActivated);
         }
         if data.flags.contains(InstFlags::UNKNOWN) {
-            ensure!(!is_eof, "Unknown opcode in EOF bytecode: {data:?}");
+            if is_eof {
+                return Err(self.unsupported_opcode(inst).into());
+            }
             goto_return!(fail InstructionResult::OpcodeNotFound);
         }
 
         if is_eof {
             if let Some(info) = OPCODE_INFO_JUMPTABLE[opcode as usize] {
-                ensure!(!info.is_disabled_in_eof(), "Disabled opcode in EOF bytecode: {data:?}");
+                if info.is_disabled_in_eof() {
+                    return Err(self.unsupported_opcode(inst).into());
+                }
+            }
+        }
+
+        // State changes in a static context fail before doing anything else, including the stack
+        // checks, like in the interpreter.
+        // The check is omitted if the function is specialized for a non-static context.
+        if !data.flags.contains(InstFlags::CUSTOM) && is_state_changing(opcode) {
+            match self.config.static_context {
+                Some(true) => goto_return!(fail InstructionResult::StateChangeDuringStaticCall),
+                None => {
+                    let is_static = self.load_is_static();
+                    self.build_check(is_static, InstructionResult::StateChangeDuringStaticCall);
+                }
+                Some(false) => {}
             }
         }
 
         // Pay static gas for the current section.
         self.gas_cost_imm(data.section.gas_cost as u64);
 
-        if data.flags.contains(InstFlags::SKIP_LOGIC) {
-            goto_return!("skipped");
-        }
-
         // Reset the stack length offset for this instruction.
         self.len_offset = 0;
-        self.len_before = self.stack_len.load(&mut self.bcx, "stack_len");
+        let fell_through = match self.next_len.take() {
+            Some((next, len)) if next == inst => {
+                self.len_before = len;
+                true
+            }
+            _ => {
+                self.len_before = self.stack_len.load(&mut self.bcx, "stack_len");
+                self.stack_base = 0;
+                self.stack_values.clear();
+                self.memory_values.clear();
+                false
+            }
+        };
+        self.len_after = (self.len_before, 0);
+        let is_custom = data.flags.contains(InstFlags::CUSTOM);
+        if is_custom || !is_stack_only(opcode) {
+            self.stack_values.clear();
+        }
+        // Anything but a word memory access may write memory or suspend execution. Skipped stores
+        // don't write the value that they would forward.
+        let is_memory_word_op = matches!(opcode, op::MLOAD | op::MSTORE | op::MSTORE8);
+        if is_custom
+            || !(is_stack_only(opcode)
+                || (is_memory_word_op && !data.flags.contains(InstFlags::SKIP_LOGIC)))
+        {
+            self.memory_values.clear();
+        }
+        // The memory offset of `MLOAD`, `MSTORE`, and `MSTORE8`, if it was pushed by the previous
+        // instruction.
+        let const_offset = if self.config.opt_memory_forwarding && fell_through && is_memory_word_op
+        {
+            self.const_operand(inst)
+        } else {
+            None
+        };
 
         // Check stack length for the current section.
         // Skip doing this for EOF bytecode, as it is done at deploy time.
@@ -636,6 +908,30 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             }
         }
 
+        // Skipped instructions may still start a section, so this must come after the checks.
+        if data.flags.contains(InstFlags::SKIP_LOGIC) {
+            goto_return!("skipped");
+        }
+
+        // `CALL` with value in a static context fails after popping its operands.
+        // The check is omitted if the function is specialized for a non-static context.
+        if !is_custom {
+            match self.config.static_context {
+                Some(true) | None if opcode == op::CALL => {
+                    let len = self.len_before();
+                    let sp = self.sp_from_top(len, 3);
+                    let value = self.load_word(sp, "call.value");
+                    let mut cond = self.bcx.icmp_imm(IntCC::NotEqual, value, 0);
+                    if self.config.static_context.is_none() {
+                        let is_static = self.load_is_static();
+                        cond = self.bcx.bitand(is_static, cond);
+                    }
+                    self.build_check(cond, InstructionResult::CallNotAllowedInsideStatic);
+                }
+                _ => {}
+            }
+        }
+
         // Update the stack length for this instruction.
         {
             let (inp, out) = data.stack_io();
@@ -650,9 +946,15 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 }
                 let len_changed = self.bcx.iadd_imm(self.len_before, diff);
                 self.stack_len.store(&mut self.bcx, len_changed);
+                self.len_after = (len_changed, diff);
             }
         }
 
+        if is_custom {
+            self.custom_opcode(opcode);
+            goto_return!("custom");
+        }
+
         // Macro utils.
         macro_rules! unop {
             ($op:ident) => {{
@@ -676,31 +978,59 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 let r = self.bcx.select(overflow, default, r);
                 self.push(r);
             }};
-            (@if_not_zero $op:ident) => {{
-                // TODO: `select` might not have the same semantics in all backends.
+            // Division and remainder return 0 for a zero divisor. The divisor is replaced with 1
+            // beforehand so that the operation itself is always well defined and branch-free.
+            (@div $op:ident, $signed:literal) => {{
                 let [a, b] = self.popn();
                 let b_is_zero = self.bcx.icmp_imm(IntCC::Equal, b, 0);
+                let mut replace_b = b_is_zero;
+                if $signed {
+                    // `MIN / -1` overflows. `MIN / 1` gives the wrapping result `MIN`, and
+                    // `MIN % 1` gives 0, which matches the EVM semantics.
+                    let min = self.bcx.iconst_256(I256_MIN);
+                    let a_is_min = self.bcx.icmp(IntCC::Equal, a, min);
+                    let b_is_neg1 = self.bcx.icmp_imm(IntCC::Equal, b, -1);
+                    let overflow = self.bcx.bitand(a_is_min, b_is_neg1);
+                    replace_b = self.bcx.bitor(replace_b, overflow);
+                }
+                let one = self.bcx.iconst_256(U256::from(1));
+                let divisor = self.bcx.select(replace_b, one, b);
+                let r = self.bcx.$op(a, divisor);
                 let zero = self.bcx.iconst_256(U256::ZERO);
-                let op_result = self.bcx.$op(a, b);
-                let r = self.bcx.select(b_is_zero, zero, op_result);
+                let r = self.bcx.select(b_is_zero, zero, r);
                 self.push(r);
             }};
         }
 
         macro_rules! field {
             // Gets the pointer to a field.
-            ($field:ident; @get $($paths:path),*; $($spec:tt).*) => {
-                self.get_field(self.$field, 0 $(+ mem::offset_of!($paths, $spec))*, stringify!($field.$($spec).*.addr))
-            };
+            ($field:ident; @get $($paths:ident),*; $($spec:tt).*) => {{
+                let offset = if self.config.wasm32 {
+                    0 $(+ mem::offset_of!(wasm32::$paths, $spec))*
+                } else {
+                    0 $(+ mem::offset_of!($paths, $spec))*
+                };
+                self.get_field(self.$field, offset, stringify!($field.$($spec).*.addr))
+            }};
+            // Gets and loads a `U256` field, converting it to a native-endian 256-bit integer.
+            ($field:ident; @load @[u256] $ty:expr, $($rest:tt)*) => {{
+                let value = field!($field; @load $ty, $($rest)*);
+                self.u256_to_native(value)
+            }};
+            // Gets, loads, and pushes the value of a `U256` field to the stack.
+            ($field:ident; @push @[u256] $ty:expr, $($rest:tt)*) => {{
+                let value = field!($field; @load @[u256] $ty, $($rest)*);
+                self.push(value);
+            }};
             // Gets and loads the pointer to a field.
             // The value is loaded as a native-endian 256-bit integer.
             // `@[endian]` is the endianness of the value. If native, omit it.
-            ($field:ident; @load $(@[endian = $endian:tt])? $ty:expr, $($paths:path),*; $($spec:tt).*) => {{
+            ($field:ident; @load $(@[endian = $endian:tt])? $ty:expr, $($paths:ident),*; $($spec:tt).*) => {{
                 let ptr = field!($field; @get $($paths),*; $($spec).*);
                 #[allow(unused_mut)]
                 let mut value = self.bcx.load($ty, ptr, stringify!($field.$($spec).*));
                 $(
-                    if !cfg!(target_endian = $endian) {
+                    if self.config.little_endian != ($endian == "little") {
                         value = self.bcx.bswap(value);
                     }
                 )?
@@ -729,29 +1059,10 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             op::ADD => binop!(iadd),
             op::MUL => binop!(imul),
             op::SUB => binop!(isub),
-            op::DIV => binop!(@if_not_zero udiv),
-            op::SDIV => {
-                let [a, b] = self.popn();
-                let b_is_zero = self.bcx.icmp_imm(IntCC::Equal, b, 0);
-                let r = self.bcx.lazy_select(
-                    b_is_zero,
-                    self.word_type,
-                    |bcx| bcx.iconst_256(U256::ZERO),
-                    |bcx| {
-                        let min = bcx.iconst_256(I256_MIN);
-                        let is_weird_sdiv_edge_case = {
-                            let a_is_min = bcx.icmp(IntCC::Equal, a, min);
-                            let b_is_neg1 = bcx.icmp_imm(IntCC::Equal, b, -1);
-                            bcx.bitand(a_is_min, b_is_neg1)
-                        };
-                        let sdiv_result = bcx.sdiv(a, b);
-                        bcx.select(is_weird_sdiv_edge_case, min, sdiv_result)
-                    },
-                );
-                self.push(r);
-            }
-            op::MOD => binop!(@if_not_zero urem),
-            op::SMOD => binop!(@if_not_zero srem),
+            op::DIV => binop!(@div udiv, false),
+            op::SDIV => binop!(@div sdiv, true),
+            op::MOD => binop!(@div urem, false),
+            op::SMOD => binop!(@div srem, true),
             op::ADDMOD => {
                 let sp = self.sp_after_inputs();
                 let _ = self.call_builtin(Builtin::AddMod, &[sp]);
@@ -810,13 +1121,14 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 self.bcx.select(is_negative, max, zero)
             }),
 
+            op::KECCAK256 if data.flags.contains(InstFlags::MAP_SLOT) => self.map_slot(),
             op::KECCAK256 => {
                 let sp = self.sp_after_inputs();
                 self.call_fallible_builtin(Builtin::Keccak256, &[self.ecx, sp]);
             }
 
             op::ADDRESS => {
-                contract_field!(@push @[endian = "big"] self.address_type, Contract; target_address)
+                contract_field!(@push @[endian = "big"] self.address_type, ContractView; target_address)
             }
             op::BALANCE => {
                 let sp = self.sp_after_inputs();
@@ -827,10 +1139,10 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 env_field!(@push @[endian = "big"] self.address_type, Env, TxEnv; tx.caller)
             }
             op::CALLER => {
-                contract_field!(@push @[endian = "big"] self.address_type, Contract; caller)
+                contract_field!(@push @[endian = "big"] self.address_type, ContractView; caller)
             }
             op::CALLVALUE => {
-                contract_field!(@push @[endian = "little"] self.word_type, Contract; call_value)
+                contract_field!(@push @[u256] self.word_type, ContractView; call_value)
             }
             op::CALLDATALOAD => {
                 let index = self.pop();
@@ -838,7 +1150,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 self.push(r);
             }
             op::CALLDATASIZE => {
-                contract_field!(@push self.isize_type, Contract, pf::Bytes; input.len)
+                contract_field!(@push self.isize_type, ContractView; input_len)
             }
             op::CALLDATACOPY => {
                 let sp = self.sp_after_inputs();
@@ -856,7 +1168,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
             op::GASPRICE => {
                 let sp = self.sp_after_inputs();
-                let _ = self.call_builtin(Builtin::GasPrice, &[self.ecx, sp]);
+                self.call_fallible_builtin(Builtin::GasPrice, &[self.ecx, sp]);
             }
             op::EXTCODESIZE => {
                 let sp = self.sp_after_inputs();
@@ -869,7 +1181,12 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 self.call_fallible_builtin(Builtin::ExtCodeCopy, &[self.ecx, sp, spec_id]);
             }
             op::RETURNDATASIZE => {
-                field!(ecx; @push self.isize_type, EvmContext<'_>, pf::Slice; return_data.len);
+                let offset = offset_of!(self.config, EvmContext<'_> => EvmContext, return_data)
+                    + offset_of!(self.config, pf::Slice => Slice, len);
+                let ptr = self.get_field(self.ecx, offset, "ecx.return_data.len.addr");
+                let len = self.bcx.load(self.isize_type, ptr, "ecx.return_data.len");
+                let len = self.bcx.zext(self.word_type, len);
+                self.push(len);
             }
             op::RETURNDATACOPY => {
                 let sp = self.sp_after_inputs();
@@ -888,18 +1205,18 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 env_field!(@push @[endian = "big"] self.address_type, Env, BlockEnv; block.coinbase)
             }
             op::TIMESTAMP => {
-                env_field!(@push @[endian = "little"] self.word_type, Env, BlockEnv; block.timestamp)
+                env_field!(@push @[u256] self.word_type, Env, BlockEnv; block.timestamp)
             }
             op::NUMBER => {
-                env_field!(@push @[endian = "little"] self.word_type, Env, BlockEnv; block.number)
+                env_field!(@push @[u256] self.word_type, Env, BlockEnv; block.number)
             }
             op::DIFFICULTY => {
                 let slot = self.sp_at_top();
                 let spec_id = self.const_spec_id();
-                let _ = self.call_builtin(Builtin::Difficulty, &[self.ecx, slot, spec_id]);
+                self.call_fallible_builtin(Builtin::Difficulty, &[self.ecx, slot, spec_id]);
             }
             op::GASLIMIT => {
-                env_field!(@push @[endian = "little"] self.word_type, Env, BlockEnv; block.gas_limit)
+                env_field!(@push @[u256] self.word_type, Env, BlockEnv; block.gas_limit)
             }
             op::CHAINID => env_field!(@push self.bcx.type_int(64), Env, CfgEnv; cfg.chain_id),
             op::SELFBALANCE => {
@@ -907,32 +1224,54 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 self.call_fallible_builtin(Builtin::SelfBalance, &[self.ecx, slot]);
             }
             op::BASEFEE => {
-                env_field!(@push @[endian = "little"] self.word_type, Env, BlockEnv; block.basefee)
+                env_field!(@push @[u256] self.word_type, Env, BlockEnv; block.basefee)
             }
             op::BLOBHASH => {
                 let sp = self.sp_after_inputs();
-                let _ = self.call_builtin(Builtin::BlobHash, &[self.ecx, sp]);
+                self.call_fallible_builtin(Builtin::BlobHash, &[self.ecx, sp]);
             }
             op::BLOBBASEFEE => {
                 let len = self.len_before();
                 let slot = self.sp_at(len);
-                let _ = self.call_builtin(Builtin::BlobBaseFee, &[self.ecx, slot]);
+                self.call_fallible_builtin(Builtin::BlobBaseFee, &[self.ecx, slot]);
             }
 
             op::POP => { /* Already handled in stack_io */ }
             op::MLOAD => {
                 let offset = self.pop();
-                let value = self.call_mload(offset);
+                // Memory was already expanded by the store, so skipping the load doesn't change
+                // the gas usage.
+                let value = match const_offset.and_then(|o| self.memory_values.get(&o)) {
+                    Some(&value) => value,
+                    None => self.call_mload(offset),
+                };
                 self.push(value);
             }
+            op::MSTORE if data.flags.contains(InstFlags::MAP_SLOT) => {
+                let [_, value] = self.popn();
+                self.map_slot_words[data.data as usize] = Some(value);
+            }
             op::MSTORE => {
                 let [offset, value] = self.popn();
                 self.call_mstore(offset, value);
+                match const_offset {
+                    Some(offset) => {
+                        self.memory_values.retain(|&o, _| o + 32 <= offset || offset + 32 <= o);
+                        self.memory_values.insert(offset, value);
+                    }
+                    None => self.memory_values.clear(),
+                }
             }
             op::MSTORE8 => {
                 let [offset, value] = self.popn();
                 let value = self.bcx.ireduce(self.i8_type, value);
                 self.call_mstore8(offset, value);
+                match const_offset {
+                    Some(offset) => {
+                        self.memory_values.retain(|&o, _| o + 32 <= offset || offset < o)
+                    }
+                    None => self.memory_values.clear(),
+                }
             }
             op::SLOAD => {
                 let sp = self.sp_after_inputs();
@@ -963,6 +1302,11 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                             op::JUMPDEST,
                             "jumping to non-JUMPDEST; target_inst={target_inst}",
                         );
+                        if data.flags.contains(InstFlags::RETURN_JUMP) {
+                            // The return address is known, but we still need to account for the
+                            // stack.
+                            self.len_offset -= 1;
+                        }
                         self.inst_entries[target_inst]
                     } else {
                         // Dynamic jump.
@@ -975,12 +1319,29 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
                     if opcode == op::JUMPI {
                         let cond_word = self.pop();
-                        let cond = self.bcx.icmp_imm(IntCC::NotEqual, cond_word, 0);
                         let next = self.inst_entries[inst + 1];
-                        if target == self.return_block.unwrap() {
-                            self.add_invalid_jump();
+                        if data.flags.contains(InstFlags::JUMPI_TAKEN) {
+                            self.bcx.br(target);
+                        } else if data.flags.contains(InstFlags::JUMPI_NOT_TAKEN) {
+                            self.bcx.br(next);
+                        } else {
+                            let cond = self.bcx.icmp_imm(IntCC::NotEqual, cond_word, 0);
+                            if target == self.return_block.unwrap() {
+                                self.add_invalid_jump();
+                            }
+                            if let Some(profile) = self.profile {
+                                let not_taken = profile.count(self.bytecode.inst(inst + 1).pc);
+                                let taken = self.block_count.saturating_sub(not_taken);
+                                self.bcx.brif_weighted(
+                                    cond,
+                                    target,
+                                    next,
+                                    branch_weights(taken, not_taken),
+                                );
+                            } else {
+                                self.bcx.brif(cond, target, next);
+                            }
                         }
-                        self.bcx.brif(cond, target, next);
                     } else {
                         self.bcx.br(target);
                     }
@@ -999,8 +1360,12 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
                 self.push(msize);
             }
             op::GAS => {
-                let remaining = self.load_gas_remaining();
-                let remaining = self.bcx.zext(self.word_type, remaining);
+                let remaining = if let Some(gas) = self.config.gas_constant {
+                    self.bcx.iconst_256(U256::from(gas))
+                } else {
+                    let remaining = self.load_gas_remaining();
+                    self.bcx.zext(self.word_type, remaining)
+                };
                 self.push(remaining);
             }
             op::JUMPDEST => {
@@ -1008,7 +1373,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             }
             op::TLOAD => {
                 let sp = self.sp_after_inputs();
-                let _ = self.call_builtin(Builtin::Tload, &[self.ecx, sp]);
+                self.call_fallible_builtin(Builtin::Tload, &[self.ecx, sp]);
             }
             op::TSTORE => {
                 let sp = self.sp_after_inputs();
@@ -1218,9 +1583,11 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             } else {
                 len_start
             };
+            let pos = self.stack_base + self.len_offset as i64;
             self.len_offset += 1;
             let sp = self.sp_at(len);
             self.bcx.store(value, sp);
+            self.stack_values.insert(pos, value);
         }
     }
 
@@ -1230,14 +1597,16 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
     }
 
     /// Removes the topmost `N` elements from the stack and returns them.
-    ///
-    /// If `load` is `false`, returns just the pointers.
     fn popn<const N: usize>(&mut self) -> [B::Value; N] {
         debug_assert_ne!(N, 0);
 
         let len_start = self.len_before();
         std::array::from_fn(|i| {
             self.len_offset -= 1;
+            let pos = self.stack_base + self.len_offset as i64;
+            if let Some(&value) = self.stack_values.get(&pos) {
+                return value;
+            }
             let len = if self.len_offset != 0 {
                 self.bcx.iadd_imm(len_start, self.len_offset as i64)
             } else {
@@ -1245,7 +1614,9 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
             };
             let sp = self.sp_at(len);
             let name = b'a' + i as u8;
-            self.load_word(sp, std::str::from_utf8(&[name]).unwrap())
+            let value = self.load_word(sp, std::str::from_utf8(&[name]).unwrap());
+            self.stack_values.insert(pos, value);
+            value
         })
     }
 
@@ -1253,9 +1624,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
     /// `n` cannot be `0`.
     fn dup(&mut self, n: usize) {
         debug_assert_ne!(n, 0);
-        let len = self.len_before();
-        let sp = self.sp_from_top(len, n);
-        let value = self.load_word(sp, &format!("dup{n}"));
+        let value = self.load_from_top(n, &format!("dup{n}")).1;
         self.push(value);
     }
 
@@ -1270,16 +1639,146 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
     /// `m` cannot be `0`.
     fn exchange(&mut self, n: usize, m: usize) {
         debug_assert_ne!(m, 0);
+        let (a_pos, a) = self.load_from_top(n + 1, "swap.a");
+        let (b_pos, b) = self.load_from_top(n + m + 1, "swap.b");
+        // Store.
         let len = self.len_before();
-        // Load a.
-        let a_sp = self.sp_from_top(len, n + 1);
-        let a = self.load_word(a_sp, "swap.a");
-        // Load b.
         let b_sp = self.sp_from_top(len, n + m + 1);
-        let b = self.load_word(b_sp, "swap.b");
-        // Store.
         self.bcx.store(a, b_sp);
+        let a_sp = self.sp_from_top(len, n + 1);
         self.bcx.store(b, a_sp);
+        self.stack_values.insert(b_pos, a);
+        self.stack_values.insert(a_pos, b);
+    }
+
+    /// Returns the `n`th value from the top of the stack before the current instruction, and its
+    /// position in the current fall-through run.
+    ///
+    /// The value is only loaded if it is not already known.
+    fn load_from_top(&mut self, n: usize, name: &str) -> (i64, B::Value) {
+        let pos = self.stack_base - n as i64;
+        if let Some(&value) = self.stack_values.get(&pos) {
+            return (pos, value);
+        }
+        let len = self.len_before();
+        let sp = self.sp_from_top(len, n);
+        let value = self.load_word(sp, name);
+        self.stack_values.insert(pos, value);
+        (pos, value)
+    }
+
+    /// Returns the error for an opcode that cannot be compiled.
+    fn unsupported_opcode(&self, inst: Inst) -> CompileError {
+        let data = self.bytecode.inst(inst);
+        CompileError::UnsupportedOpcode {
+            pc: data.pc as usize,
+            opcode: data.opcode,
+            spec: self.bytecode.spec_id,
+        }
+    }
+
+    /// Returns the value on top of the stack before `inst`, if it was pushed by the previous
+    /// instruction and is a small enough memory offset to be tracked.
+    ///
+    /// `inst` must have been reached by falling through from the previous instruction.
+    fn const_operand(&self, inst: Inst) -> Option<u64> {
+        let prev = inst.checked_sub(1)?;
+        if self.bytecode.inst(prev).flags.contains(InstFlags::SKIP_LOGIC) {
+            return None;
+        }
+        let value = self.bytecode.push_value(prev)?;
+        value.try_into().ok().filter(|&offset: &u64| offset <= u32::MAX as u64)
+    }
+
+    /// Records the stack state for the next instruction if it can only be reached by falling
+    /// through from `inst`.
+    fn fall_through(&mut self, inst: Inst) {
+        let next = inst + 1;
+        let data = self.bytecode.inst(next);
+        // Jump and resume targets have other predecessors. Resume targets always follow a
+        // suspending instruction, which never falls through.
+        if self.bytecode.is_eof() || data.is_dead_code() || data.is_jumpdest() {
+            self.next_len = None;
+            return;
+        }
+        let (len, diff) = self.len_after;
+        self.next_len = Some((next, len));
+        self.stack_base += diff;
+        let current = self.current_inst();
+        if current.flags.contains(InstFlags::CUSTOM) || !is_stack_only(current.opcode) {
+            self.stack_values.clear();
+        }
+    }
+
+    /// `KECCAK256` instruction marked as `MAP_SLOT`.
+    ///
+    /// Hashes the words of the previous `MAP_SLOT` stores, paying for the stores.
+    fn map_slot(&mut self) {
+        // The stores expand memory to 2 words, which are then hashed.
+        self.ensure_memory_imm(64);
+        let params = self.config.gas_table.map(|table| table.params).unwrap_or_default();
+        self.gas_cost_imm(params.keccak256_cost(64).unwrap_or(u64::MAX));
+
+        let words = mem::take(&mut self.map_slot_words);
+        let [key, slot] = words.map(|word| {
+            let word = word.expect("MAP_SLOT store was not translated");
+            let ptr = self.bcx.new_stack_slot(self.word_type, "map_slot.word.addr");
+            ptr.store(&mut self.bcx, word);
+            ptr.addr(&mut self.bcx)
+        });
+        let sp = self.sp_after_inputs();
+        let _ = self.call_builtin(Builtin::MapSlot, &[key, slot, sp]);
+    }
+
+    /// Resizes memory to at least `size` bytes.
+    fn ensure_memory_imm(&mut self, size: u64) {
+        let (_, _, len) = self.load_memory_len(self.ecx);
+        let cond = self.bcx.icmp_imm(IntCC::UnsignedLessThan, len, size as i64);
+
+        let resize = self.create_block_after_current("resize");
+        let cont = self.create_block_after(resize, "contd");
+        self.bcx.brif_cold(cond, resize, cont, true);
+
+        self.bcx.switch_to_block(resize);
+        let size = self.bcx.iconst(self.isize_type, size as i64);
+        self.call_fallible_builtin(Builtin::ResizeMemory, &[self.ecx, size]);
+        self.bcx.br(cont);
+
+        self.bcx.switch_to_block(cont);
+    }
+
+    /// Loads the pointer to the shared memory, its last checkpoint, and the length of the current
+    /// context's memory.
+    fn load_memory_len(&mut self, ecx: B::Value) -> (B::Value, B::Value, B::Value) {
+        let memory_ptr = {
+            let memory_ptr_ptr = self.get_field(
+                ecx,
+                offset_of!(self.config, EvmContext<'_> => EvmContext, memory),
+                "ecx.memory.addr",
+            );
+            self.bcx.load(self.ptr_type, memory_ptr_ptr, "ecx.memory")
+        };
+
+        let memory_buffer_offset =
+            offset_of!(self.config, pf::SharedMemory => SharedMemory, buffer);
+        let len_ptr = self.get_field(
+            memory_ptr,
+            memory_buffer_offset + offset_of!(self.config, pf::Vec<u8> => Vec, len),
+            "ecx.memory.len.addr",
+        );
+        let sm_len = self.bcx.load(self.isize_type, len_ptr, "ecx.memory.len");
+
+        // `memory.len() = memory.buffer.len() - memory.last_checkpoint`
+        let last_checkpoint = {
+            let ptr = self.get_field(
+                memory_ptr,
+                offset_of!(self.config, pf::SharedMemory => SharedMemory, last_checkpoint),
+                "ecx.memory.last_checkpoint.addr",
+            );
+            self.bcx.load(self.isize_type, ptr, "ecx.memory.last_checkpoint")
+        };
+        let len = self.bcx.isub(sm_len, last_checkpoint);
+        (memory_ptr, last_checkpoint, len)
     }
 
     /// `RETURN` or `REVERT` instruction.
@@ -1371,7 +1870,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         let func_stack = self.func_stack(self.ecx);
         let idx_ptr = self.get_field(
             func_stack,
-            mem::offset_of!(FunctionStack, current_code_idx),
+            offset_of!(self.config, FunctionStack => FunctionStack, current_code_idx),
             "ecx.func_stack.current_code_idx",
         );
         let value = self.bcx.iconst(self.isize_type, idx as i64);
@@ -1382,7 +1881,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
     fn func_stack(&mut self, ecx: B::Value) -> B::Value {
         let ptr = self.get_field(
             ecx,
-            mem::offset_of!(EvmContext<'_>, func_stack),
+            offset_of!(self.config, EvmContext<'_> => EvmContext, func_stack),
             "ecx.func_stack.addr.addr",
         );
         self.bcx.load(self.ptr_type, ptr, "ecx.func_stack.addr")
@@ -1407,7 +1906,8 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
     /// Adds a resume point and returns its index.
     fn add_resume_at(&mut self, block: B::BasicBlock) -> Option<B::Value> {
-        let value = self.bcx.block_addr(block);
+        // Block addresses cannot be validated.
+        let value = if self.config.validate_resume_at { None } else { self.bcx.block_addr(block) };
         if self.resume_blocks.is_empty() {
             self.resume_kind =
                 if value.is_some() { ResumeKind::Blocks } else { ResumeKind::Indexes };
@@ -1437,6 +1937,17 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         self.bcx.iconst(self.i8_type, self.bytecode.spec_id as i64)
     }
 
+    /// Loads `ecx.is_static` as a boolean.
+    fn load_is_static(&mut self) -> B::Value {
+        let ptr = self.get_field(
+            self.ecx,
+            offset_of!(self.config, EvmContext<'_> => EvmContext, is_static),
+            "ecx.is_static.addr",
+        );
+        let is_static = self.bcx.load(self.i8_type, ptr, "ecx.is_static");
+        self.bcx.icmp_imm(IntCC::NotEqual, is_static, 0)
+    }
+
     /// Gets a field at the given offset.
     fn get_field(&mut self, ptr: B::Value, offset: usize, name: &str) -> B::Value {
         get_field(&mut self.bcx, ptr, offset, name)
@@ -1452,6 +1963,13 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         self.gas_remaining.store(&mut self.bcx, value);
     }
 
+    /// Returns the pointer to the gas limit.
+    fn gas_limit_ptr(&mut self) -> B::Value {
+        debug_assert_eq!(self.config.signature(), FnSignature::Default);
+        let gas_ptr = self.bcx.fn_param(0);
+        self.get_field(gas_ptr, offset_of!(self.config, pf::Gas => Gas, limit), "gas.limit.addr")
+    }
+
     /// Saves the local `stack_len` to `stack_len_arg`.
     fn save_stack_len(&mut self) {
         let len = self.stack_len.load(&mut self.bcx, "stack_len");
@@ -1461,7 +1979,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
     /// Returns the stack length argument.
     fn stack_len_arg(&mut self) -> B::Value {
-        self.bcx.fn_param(2)
+        self.bcx.fn_param(self.config.param_index(2))
     }
 
     /// Returns the stack pointer at the top (`&stack[stack.len]`).
@@ -1496,7 +2014,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
     /// Builds a gas cost deduction for an immediate value.
     fn gas_cost_imm(&mut self, cost: u64) {
-        if !self.config.gas_metering || cost == 0 {
+        if self.config.gas_mode == GasMode::Disabled || cost == 0 {
             return;
         }
         let value = self.bcx.iconst(self.isize_type, cost as i64);
@@ -1505,7 +2023,7 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
     /// Builds a gas cost deduction for a value.
     fn gas_cost(&mut self, cost: B::Value) {
-        if !self.config.gas_metering {
+        if self.config.gas_mode == GasMode::Disabled {
             return;
         }
 
@@ -1513,6 +2031,26 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         // This can overflow the gas counters, which has to be adjusted for after the call.
         let gas_remaining = self.load_gas_remaining();
         let (res, overflow) = self.bcx.usub_overflow(gas_remaining, cost);
+        if self.config.gas_mode == GasMode::ObserveOnly {
+            // Same as `GasMode::record_cost`: raise the limit by the cost instead of failing, and
+            // keep the remaining gas.
+            let exceeded = self.create_block_after_current("gas.exceeded");
+            let cont = self.create_block_after(exceeded, "gas.contd");
+            self.store_gas_remaining(res);
+            self.bcx.brif_cold(overflow, exceeded, cont, true);
+
+            self.bcx.switch_to_block(exceeded);
+            let limit = self.gas_limit_ptr();
+            let i64_type = self.bcx.type_int(64);
+            let old_limit = self.bcx.load(i64_type, limit, "gas.limit");
+            let new_limit = self.bcx.uadd_sat(old_limit, cost);
+            self.bcx.store(new_limit, limit);
+            self.store_gas_remaining(gas_remaining);
+            self.bcx.br(cont);
+
+            self.bcx.switch_to_block(cont);
+            return;
+        }
         if self.bytecode.is_small() {
             // Storing the result before the check significantly increases time spent in
             // `llvm::MemoryDependenceResults::getNonLocalPointerDependency`, but it might produce
@@ -1585,6 +2123,87 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         target
     }
 
+    /// Returns `true` if the instruction starts a basic block in [`ExecutionProfile`]s: the first
+    /// instruction, `JUMPDEST`s, and the instructions after `JUMPI`s.
+    fn is_block_start(&self, inst: Inst) -> bool {
+        if self.bytecode.is_eof() {
+            return false;
+        }
+        inst == 0
+            || self.bytecode.inst(inst).is_jumpdest()
+            || self.bytecode.inst(inst - 1).opcode == op::JUMPI
+    }
+
+    /// Increments `ecx.opcode_counts[opcode]`, if `ecx.opcode_counts` is `Some`.
+    fn build_count_opcode(&mut self, opcode: u8) {
+        let counts_ptr = self.get_field(
+            self.ecx,
+            offset_of!(self.config, EvmContext<'_> => EvmContext, opcode_counts),
+            "ecx.opcode_counts.addr",
+        );
+        let counts = self.bcx.load(self.ptr_type, counts_ptr, "ecx.opcode_counts");
+        let is_null = self.bcx.is_null(counts);
+        let count_block = self.create_block_after_current("count");
+        let contd = self.create_block_after(count_block, "count.contd");
+        self.bcx.brif(is_null, contd, count_block);
+
+        self.bcx.switch_to_block(count_block);
+        let count_ptr = self.get_field(counts, opcode as usize * 8, "count.addr");
+        let i64_type = self.bcx.type_int(64);
+        let count = self.bcx.load(i64_type, count_ptr, "count");
+        let count = self.bcx.iadd_imm(count, 1);
+        self.bcx.store(count, count_ptr);
+        self.bcx.br(contd);
+
+        self.bcx.switch_to_block(contd);
+    }
+
+    /// Decrements `*ecx.step_budget`, failing if it is already zero, if `ecx.step_budget` is
+    /// `Some`.
+    fn build_step(&mut self) {
+        let budget_ptr = self.get_field(
+            self.ecx,
+            offset_of!(self.config, EvmContext<'_> => EvmContext, step_budget),
+            "ecx.step_budget.addr",
+        );
+        let budget_ptr = self.bcx.load(self.ptr_type, budget_ptr, "ecx.step_budget");
+        let is_null = self.bcx.is_null(budget_ptr);
+        let step_block = self.create_block_after_current("step");
+        let contd = self.create_block_after(step_block, "step.contd");
+        self.bcx.brif(is_null, contd, step_block);
+
+        self.bcx.switch_to_block(step_block);
+        let i64_type = self.bcx.type_int(64);
+        let budget = self.bcx.load(i64_type, budget_ptr, "step_budget");
+        let is_exhausted = self.bcx.icmp_imm(IntCC::Equal, budget, 0);
+        self.build_check(is_exhausted, InstructionResult::FatalExternalError);
+        let budget = self.bcx.iadd_imm(budget, -1);
+        self.bcx.store(budget, budget_ptr);
+        self.bcx.br(contd);
+
+        self.bcx.switch_to_block(contd);
+    }
+
+    /// Calls the builtin of a custom opcode with its stack inputs.
+    fn custom_opcode(&mut self, opcode: u8) {
+        let builtin = self.custom_opcodes[&opcode].builtin;
+        let function = self.bcx.get_function(builtin.name).unwrap_or_else(|| {
+            let ptr = self.ptr_type;
+            let address = builtin.f as usize;
+            let linkage = revmc_backend::Linkage::Import;
+            self.bcx.add_function(
+                builtin.name,
+                &[ptr; 3],
+                Some(self.i8_type),
+                Some(address),
+                linkage,
+            )
+        });
+        let sp = self.sp_after_inputs();
+        let ret = self.bcx.call(function, &[self.ecx, sp, sp]).unwrap();
+        self.build_check_instruction_result(ret);
+    }
+
     /// Builds a branch to the failure block.
     fn build_fail_imm(&mut self, ret: InstructionResult) {
         let ret_value = self.bcx.iconst(self.i8_type, ret as i64);
@@ -1634,6 +2253,25 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         ));
     }
 
+    /// Branches to `then_block` if calldata starts with `selector`, otherwise tail-calls the
+    /// `fallback` function with the same arguments.
+    fn build_selector_guard(&mut self, selector: u32, fallback: &str, then_block: B::BasicBlock) {
+        let zero = self.bcx.iconst_256(U256::ZERO);
+        let word = self.call_calldataload(zero);
+        let shift = self.bcx.iconst_256(U256::from(224));
+        let actual = self.bcx.ushr(word, shift);
+        let cond = self.bcx.icmp_imm(IntCC::Equal, actual, selector as i64);
+        let current = self.bcx.current_block().unwrap();
+        let fallback_block = self.bcx.create_block_after(current, "selector.fallback");
+        self.bcx.brif_cold(cond, then_block, fallback_block, false);
+
+        self.bcx.switch_to_block(fallback_block);
+        let function = self.bcx.get_function(fallback).expect("fallback function not found");
+        let args = (0..self.config.num_params()).map(|i| self.bcx.fn_param(i)).collect::<Vec<_>>();
+        let ret = self.bcx.tail_call(function, &args, TailCallKind::Tail).unwrap();
+        self.bcx.ret(&[ret]);
+    }
+
     // Pointer must not be null if `must_be_set` is true.
     fn pointer_panic_with_bool(
         &mut self,
@@ -1653,7 +2291,18 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
         self.build_assertion(panic_cond, &msg);
     }
 
+    /// Builds an assertion that `cond` is `false`, according to `config.assertions`.
     fn build_assertion(&mut self, cond: B::Value, msg: &str) {
+        match self.config.assertions {
+            Assertions::Check => {}
+            Assertions::Assume => {
+                let holds = self.bcx.bitnot(cond);
+                self.bcx.assume(holds);
+                return;
+            }
+            Assertions::None => return,
+        }
+
         let failure = self.create_block_after_current("panic");
         let target = self.create_block_after(failure, "contd");
         self.bcx.brif(cond, failure, target);
@@ -1753,6 +2402,27 @@ impl<'a, B: Backend> FunctionCx<'a, B> {
 
 /// IR builtins.
 impl<B: Backend> FunctionCx<'_, B> {
+    /// Converts a [`U256`] that was loaded as a native-endian 256-bit integer to its value.
+    ///
+    /// `U256` stores its native-endian 64-bit limbs in little-endian order, so this is a no-op on
+    /// little-endian targets, and reverses the order of the limbs on big-endian targets.
+    fn u256_to_native(&mut self, value: B::Value) -> B::Value {
+        if self.config.little_endian {
+            return value;
+        }
+        let mask = self.bcx.iconst_256(U256::from(u64::MAX));
+        let mut result = self.bcx.iconst_256(U256::ZERO);
+        for i in 0..4 {
+            let shift = self.bcx.iconst_256(U256::from(64 * i));
+            let limb = self.bcx.ushr(value, shift);
+            let limb = self.bcx.bitand(limb, mask);
+            let shift = self.bcx.iconst_256(U256::from(64 * (3 - i)));
+            let limb = self.bcx.ishl(limb, shift);
+            result = self.bcx.bitor(result, limb);
+        }
+        result
+    }
+
     fn call_byte(&mut self, index: B::Value, value: B::Value) -> B::Value {
         self.call_ir_binop_builtin("byte", index, value, Self::build_byte)
     }
@@ -1851,21 +2521,21 @@ impl<B: Backend> FunctionCx<'_, B> {
         let isize_type = self.isize_type;
         let i8_type = self.i8_type;
         let word_type = self.word_type;
+        let little_endian = self.config.little_endian;
 
-        let input_offset = mem::offset_of!(Contract, input);
         let ptr_ptr = self.get_field(
             contract,
-            input_offset + mem::offset_of!(pf::Bytes, ptr),
-            "contract.input.ptr.addr",
+            offset_of!(self.config, ContractView => ContractView, input_ptr),
+            "contract.input_ptr.addr",
         );
-        let ptr = self.bcx.load(self.ptr_type, ptr_ptr, "contract.input.ptr");
+        let ptr = self.bcx.load(self.ptr_type, ptr_ptr, "contract.input_ptr");
 
         let len_ptr = self.get_field(
             contract,
-            input_offset + mem::offset_of!(pf::Bytes, len),
-            "contract.input.len.addr",
+            offset_of!(self.config, ContractView => ContractView, input_len),
+            "contract.input_len.addr",
         );
-        let len = self.bcx.load(isize_type, len_ptr, "contract.input.len");
+        let len = self.bcx.load(isize_type, len_ptr, "contract.input_len");
 
         let len_256 = self.bcx.zext(word_type, len);
 
@@ -1891,7 +2561,7 @@ impl<B: Backend> FunctionCx<'_, B> {
                 let tmp_addr = tmp.addr(bcx);
                 bcx.memcpy(tmp_addr, calldata, slice_len);
                 let mut value = tmp.load(bcx, "calldata.i256");
-                if cfg!(target_endian = "little") {
+                if little_endian {
                     value = bcx.bswap(value);
                 }
                 value
@@ -1967,32 +2637,9 @@ impl<B: Backend> FunctionCx<'_, B> {
         let value = self.bcx.fn_param(1);
         let ecx = self.bcx.fn_param(2);
 
-        let memory_ptr = {
-            let memory_ptr_ptr =
-                self.get_field(ecx, mem::offset_of!(EvmContext<'_>, memory), "ecx.memory.addr");
-            self.bcx.load(self.ptr_type, memory_ptr_ptr, "ecx.memory")
-        };
-
-        let memory_buffer_offset = mem::offset_of!(pf::SharedMemory, buffer);
-        let len_ptr = self.get_field(
-            memory_ptr,
-            memory_buffer_offset + mem::offset_of!(pf::Vec<u8>, len),
-            "ecx.memory.len.addr",
-        );
-        let sm_len = self.bcx.load(self.isize_type, len_ptr, "ecx.memory.len");
-
-        // `memory.len() = memory.buffer.len() - memory.last_checkpoint`
         // `new_size = offset + len`
         // `if new_size > memory.len() { resize_memory(new_size) }`
-        let last_checkpoint = {
-            let ptr = self.get_field(
-                memory_ptr,
-                mem::offset_of!(pf::SharedMemory, last_checkpoint),
-                "ecx.memory.last_checkpoint.addr",
-            );
-            self.bcx.load(self.isize_type, ptr, "ecx.memory.last_checkpoint")
-        };
-        let buffer_len = self.bcx.isub(sm_len, last_checkpoint);
+        let (memory_ptr, last_checkpoint, buffer_len) = self.load_memory_len(ecx);
         let max_isize = ((1u128 << self.bcx.type_bit_width(self.isize_type)) - 1u128) as u64;
         let max_isize_u256 = self.bcx.iconst_256(U256::from(max_isize));
         let max_isize = self.bcx.uconst(self.isize_type, max_isize);
@@ -2024,7 +2671,8 @@ impl<B: Backend> FunctionCx<'_, B> {
         let shared_buffer_ptr = {
             let ptr = self.get_field(
                 memory_ptr,
-                memory_buffer_offset + mem::offset_of!(pf::Vec<u8>, ptr),
+                offset_of!(self.config, pf::SharedMemory => SharedMemory, buffer)
+                    + offset_of!(self.config, pf::Vec<u8> => Vec, ptr),
                 "ecx.memory.buffer.ptr.shared.addr",
             );
             self.bcx.load(self.ptr_type, ptr, "ecx.memory.buffer.ptr.shared")
@@ -2040,11 +2688,11 @@ impl<B: Backend> FunctionCx<'_, B> {
             MemOpKind::Load => {
                 let loaded = self.bcx.load_unaligned(self.word_type, slot, "slot.value");
                 let loaded =
-                    if cfg!(target_endian = "little") { self.bcx.bswap(loaded) } else { loaded };
+                    if self.config.little_endian { self.bcx.bswap(loaded) } else { loaded };
                 self.bcx.store(loaded, value);
             }
             MemOpKind::Store | MemOpKind::Store8 => {
-                let value = if matches!(kind, MemOpKind::Store) && cfg!(target_endian = "little") {
+                let value = if matches!(kind, MemOpKind::Store) && self.config.little_endian {
                     self.bcx.bswap(value)
                 } else {
                     value
@@ -2081,12 +2729,13 @@ impl<B: Backend> FunctionCx<'_, B> {
         let new_idx = self.bcx.fn_param(2);
 
         let func_stack = self.func_stack(ecx);
-        let return_stack_offset = mem::offset_of!(FunctionStack, return_stack);
+        let return_stack_offset =
+            offset_of!(self.config, FunctionStack => FunctionStack, return_stack);
 
         // Increment the length.
         let len_ptr = self.get_field(
             func_stack,
-            return_stack_offset + mem::offset_of!(pf::Vec<FunctionReturnFrame>, len),
+            return_stack_offset + offset_of!(self.config, pf::Vec<FunctionReturnFrame> => Vec, len),
             "ecx.func_stack.return_stack.len.addr",
         );
         let old_len = self.bcx.load(self.isize_type, len_ptr, "ecx.func_stack.return_stack.len");
@@ -2098,7 +2747,8 @@ impl<B: Backend> FunctionCx<'_, B> {
         let cap = {
             let cap_ptr = self.get_field(
                 func_stack,
-                return_stack_offset + mem::offset_of!(pf::Vec<FunctionReturnFrame>, cap),
+                return_stack_offset
+                    + offset_of!(self.config, pf::Vec<FunctionReturnFrame> => Vec, cap),
                 "ecx.func_stack.return_stack.cap.addr",
             );
             self.bcx.load(self.isize_type, cap_ptr, "ecx.func_stack.return_stack.capacity")
@@ -2121,7 +2771,8 @@ impl<B: Backend> FunctionCx<'_, B> {
         let ptr = {
             let ptr_ptr = self.get_field(
                 func_stack,
-                return_stack_offset + mem::offset_of!(pf::Vec<FunctionReturnFrame>, ptr),
+                return_stack_offset
+                    + offset_of!(self.config, pf::Vec<FunctionReturnFrame> => Vec, ptr),
                 "ecx.func_stack.return_stack.ptr.addr",
             );
             self.bcx.load(self.ptr_type, ptr_ptr, "ecx.func_stack.return_stack.ptr")
@@ -2139,7 +2790,7 @@ impl<B: Backend> FunctionCx<'_, B> {
         // Store the current index into the frame.
         let current_idx_ptr = self.get_field(
             func_stack,
-            mem::offset_of!(FunctionStack, current_code_idx),
+            offset_of!(self.config, FunctionStack => FunctionStack, current_code_idx),
             "ecx.func_stack.current_code_idx",
         );
         let current_idx =
@@ -2176,17 +2827,18 @@ impl<B: Backend> FunctionCx<'_, B> {
         let ecx = self.bcx.fn_param(0);
 
         let func_stack = self.func_stack(ecx);
-        let return_stack_offset = mem::offset_of!(FunctionStack, return_stack);
+        let return_stack_offset =
+            offset_of!(self.config, FunctionStack => FunctionStack, return_stack);
 
         // Decrement the length.
         // This is a debug assertion because EOF validation should have caught this.
         let len_ptr = self.get_field(
             func_stack,
-            return_stack_offset + mem::offset_of!(pf::Vec<FunctionReturnFrame>, len),
+            return_stack_offset + offset_of!(self.config, pf::Vec<FunctionReturnFrame> => Vec, len),
             "ecx.func_stack.return_stack.len",
         );
         let len = self.bcx.load(self.isize_type, len_ptr, "ecx.func_stack.return_stack.len");
-        if self.config.debug_assertions {
+        if self.config.assertions != Assertions::None {
             let cond = self.bcx.icmp_imm(IntCC::Equal, len, 0);
             self.build_assertion(cond, "RETF with empty function stack");
         }
@@ -2197,7 +2849,8 @@ impl<B: Backend> FunctionCx<'_, B> {
         let ptr = {
             let ptr_ptr = self.get_field(
                 func_stack,
-                return_stack_offset + mem::offset_of!(pf::Vec<FunctionReturnFrame>, ptr),
+                return_stack_offset
+                    + offset_of!(self.config, pf::Vec<FunctionReturnFrame> => Vec, ptr),
                 "ecx.func_stack.return_stack.ptr.addr",
             );
             self.bcx.load(self.ptr_type, ptr_ptr, "ecx.func_stack.return_stack.ptr")
@@ -2273,24 +2926,6 @@ enum MemOpKind {
 mod pf {
     use super::*;
 
-    pub(super) struct Bytes {
-        pub(super) ptr: *const u8,
-        pub(super) len: usize,
-        data: AtomicPtr<()>,
-        vtable: &'static Vtable,
-    }
-    const _: [(); mem::size_of::<revm_primitives::Bytes>()] = [(); mem::size_of::<Bytes>()];
-    struct Vtable {
-        /// fn(data, ptr, len)
-        clone: unsafe fn(&AtomicPtr<()>, *const u8, usize) -> Bytes,
-        /// fn(data, ptr, len)
-        ///
-        /// takes `Bytes` to value
-        to_vec: unsafe fn(&AtomicPtr<()>, *const u8, usize) -> Vec<u8>,
-        /// fn(data, ptr, len)
-        drop: unsafe fn(&mut AtomicPtr<()>, *const u8, usize),
-    }
-
     #[repr(C)] // See core::ptr::metadata::PtrComponents
     pub(super) struct Slice {
         pub(super) ptr: *const u8,
@@ -2357,6 +2992,54 @@ mod pf {
     }
 }
 
+/// Returns `true` if the opcode always fails in a static context.
+fn is_state_changing(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        op::SSTORE | op::TSTORE | op::LOG0
+            ..=op::LOG4 | op::CREATE | op::CREATE2 | op::EOFCREATE | op::SELFDESTRUCT
+    )
+}
+
+/// Returns `true` if the opcode only accesses the stack through `push`, `pop`, `dup` and `swap`.
+///
+/// Known stack values are kept across these instructions, as nothing else can modify the stack.
+fn is_stack_only(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        op::ADD
+            | op::MUL
+            | op::SUB
+            | op::DIV
+            | op::SDIV
+            | op::MOD
+            | op::SMOD
+            | op::LT
+            | op::GT
+            | op::SLT
+            | op::SGT
+            | op::EQ
+            | op::ISZERO
+            | op::AND
+            | op::OR
+            | op::XOR
+            | op::NOT
+            | op::SHL
+            | op::SHR
+            | op::SAR
+            | op::POP
+            | op::PUSH0..=op::PUSH32
+            | op::DUP1..=op::DUP16
+            | op::SWAP1..=op::SWAP16
+    )
+}
+
+/// Scales the profiled execution counts of the two successors of a branch down to branch weights.
+fn branch_weights(a: u64, b: u64) -> [u32; 2] {
+    let shift = (u64::BITS - a.max(b).leading_zeros()).saturating_sub(u32::BITS);
+    [a, b].map(|n| ((n >> shift) as u32).max(1))
+}
+
 fn get_field<B: Builder>(bcx: &mut B, ptr: B::Value, offset: usize, name: &str) -> B::Value {
     let offset = bcx.iconst(bcx.type_ptr_sized_int(), offset as i64);
     bcx.gep(bcx.type_int(8), ptr, &[offset], name)
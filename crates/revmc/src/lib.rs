@@ -2,10 +2,32 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(not(test), warn(unused_extern_crates))]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+// Some values are only computed to be logged.
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables, unused_assignments))]
 
+#[cfg(feature = "tracing")]
 #[macro_use]
 extern crate tracing;
 
+/// No-op stand-ins for the `tracing` macros, used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[macro_use]
+#[allow(unused_macros)]
+mod no_tracing {
+    macro_rules! trace {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! debug {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! warn {
+        ($($t:tt)*) => {};
+    }
+    macro_rules! error {
+        ($($t:tt)*) => {};
+    }
+}
+
 // For features.
 use alloy_primitives as _;
 
@@ -13,11 +35,20 @@ mod bytecode;
 pub use bytecode::*;
 
 mod compiler;
-pub use compiler::{EvmCompiler, EvmCompilerInput};
+pub use compiler::{
+    CodegenTier, CompilationStats, CompileError, CustomBuiltin, CustomBuiltinFn, CustomOpcode,
+    EvmCompiler, EvmCompilerInput, Limit,
+};
 
 mod linker;
 pub use linker::Linker;
 
+mod tiered;
+pub use tiered::{TieredConfig, TieredExecutor, TieredFunction, TieredMetrics, TieredWorker};
+
+#[cfg(feature = "statetests")]
+pub mod statetest;
+
 /// Internal tests and testing utilities. Not public API.
 #[cfg(any(test, feature = "__fuzzing"))]
 pub mod tests;
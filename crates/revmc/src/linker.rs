@@ -20,12 +20,18 @@ impl Linker {
         Self { cc: None, linker: None, cflags: vec![] }
     }
 
-    /// Sets the C compiler to use for linking. Default: "cc".
+    /// Sets the C compiler to use for linking.
+    ///
+    /// Default: the `CC` environment variable, or the first of `cc`, `clang` and `gcc` found in
+    /// `PATH`.
     pub fn cc(&mut self, cc: Option<PathBuf>) {
         self.cc = cc;
     }
 
-    /// Sets the linker to use for linking. Default: `lld`.
+    /// Sets the linker to use for linking.
+    ///
+    /// Default: `lld` if it is found in `PATH`, otherwise the default linker of the C compiler.
+    /// When linking WebAssembly modules, this is the `wasm-ld` executable instead.
     pub fn linker(&mut self, linker: Option<PathBuf>) {
         self.linker = linker;
     }
@@ -36,25 +42,20 @@ impl Linker {
     }
 
     /// Links the given object files into a shared library at the given path.
-    #[instrument(level = "debug", skip_all)]
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
     pub fn link(
         &self,
         out: &Path,
         objects: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
     ) -> std::io::Result<()> {
-        let storage;
         let cc = match &self.cc {
-            Some(cc) => cc,
-            None => {
-                let str = match std::env::var_os("CC") {
-                    Some(cc) => {
-                        storage = cc;
-                        storage.as_os_str()
-                    }
-                    None => "cc".as_ref(),
-                };
-                Path::new(str)
-            }
+            Some(cc) => cc.clone(),
+            None => Self::find_cc().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "no C compiler found in PATH; set `CC` or configure one with `Linker::cc`",
+                )
+            })?,
         };
 
         let mut cmd = std::process::Command::new(cc);
@@ -63,7 +64,7 @@ impl Linker {
         cmd.arg("-O3");
         if let Some(linker) = &self.linker {
             cmd.arg(format!("-fuse-ld={}", linker.display()));
-        } else {
+        } else if Self::has_lld() {
             cmd.arg("-fuse-ld=lld");
         }
         if cfg!(target_vendor = "apple") {
@@ -84,6 +85,93 @@ impl Linker {
         }
         Ok(())
     }
+
+    /// Links the given WebAssembly object files into a standalone module at the given path.
+    ///
+    /// The module has no entry point, and exports its public functions, its linear memory as
+    /// `memory`, and the start of its heap as `__heap_base`. Undefined functions, such as builtins,
+    /// are imported from the `env` module by name.
+    #[cfg_attr(feature = "tracing", instrument(level = "debug", skip_all))]
+    pub fn link_wasm(
+        &self,
+        out: &Path,
+        objects: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+    ) -> std::io::Result<()> {
+        let wasm_ld = match &self.linker {
+            Some(linker) => linker.clone(),
+            None => find_program("wasm-ld").ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "`wasm-ld` not found in PATH; configure one with `Linker::linker`",
+                )
+            })?,
+        };
+
+        let mut cmd = std::process::Command::new(wasm_ld);
+        cmd.arg("-o").arg(out);
+        cmd.arg("--no-entry");
+        cmd.arg("--export-dynamic");
+        cmd.arg("--export=__heap_base");
+        cmd.arg("--allow-undefined");
+        cmd.arg("--gc-sections");
+        cmd.arg("--strip-debug");
+        cmd.args(objects);
+        debug!(cmd=?cmd.get_program(), "linking");
+        trace!(?cmd, "full linking command");
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!("wasm-ld failed with {output:#?}")));
+        }
+        Ok(())
+    }
+
+    /// Returns the C compiler that is used if none is configured.
+    ///
+    /// This is the `CC` environment variable if set, or the first of `cc`, `clang` and `gcc` that
+    /// is found in `PATH`.
+    pub fn find_cc() -> Option<PathBuf> {
+        if let Some(cc) = std::env::var_os("CC") {
+            return Some(cc.into());
+        }
+        ["cc", "clang", "gcc"].into_iter().find_map(find_program)
+    }
+
+    /// Returns `true` if `lld` is found in `PATH`.
+    fn has_lld() -> bool {
+        let lld = if cfg!(target_vendor = "apple") {
+            "ld64.lld"
+        } else if cfg!(windows) {
+            "lld-link"
+        } else {
+            "ld.lld"
+        };
+        find_program(lld).is_some()
+    }
+}
+
+/// Finds the executable `name` in `PATH`.
+fn find_program(name: &str) -> Option<PathBuf> {
+    find_program_in(name, &std::env::var_os("PATH")?)
+}
+
+/// Finds the executable `name` in the given `PATH`-like list of directories.
+fn find_program_in(name: &str, path: &std::ffi::OsStr) -> Option<PathBuf> {
+    let name = program_file_name(name);
+    std::env::split_paths(path).map(|dir| dir.join(&name)).find(|path| path.is_file())
+}
+
+/// Returns the file name of the executable `name`.
+///
+/// The platform's executable extension is appended, if any, rather than replacing the extension
+/// that is already in `name`, like the one in `ld.lld`.
+fn program_file_name(name: &str) -> std::ffi::OsString {
+    let mut file_name = std::ffi::OsString::from(name);
+    let ext = std::env::consts::EXE_EXTENSION;
+    if !ext.is_empty() {
+        file_name.push(".");
+        file_name.push(ext);
+    }
+    file_name
 }
 
 #[cfg(test)]
@@ -130,6 +218,33 @@ mod tests {
         assert!(n > 0, "no C compiler found");
     }
 
+    #[test]
+    fn find() {
+        assert!(find_program("definitely-not-a-program").is_none());
+        if cfg!(unix) {
+            assert!(find_program("sh").is_some());
+        }
+    }
+
+    #[test]
+    fn find_dotted() {
+        if cfg!(windows) {
+            assert_eq!(program_file_name("ld.lld"), "ld.lld.exe");
+            return;
+        }
+        assert_eq!(program_file_name("ld.lld"), "ld.lld");
+
+        // `ld.lld` must not be found as `ld`.
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("ld"), "").unwrap();
+        assert_eq!(find_program_in("ld.lld", tmp.path().as_os_str()), None);
+        std::fs::write(tmp.path().join("ld.lld"), "").unwrap();
+        assert_eq!(
+            find_program_in("ld.lld", tmp.path().as_os_str()),
+            Some(tmp.path().join("ld.lld"))
+        );
+    }
+
     fn command_v(cmd: &str) -> bool {
         let Ok(output) = std::process::Command::new(cmd).arg("--version").output() else {
             return false;
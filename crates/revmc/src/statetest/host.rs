@@ -0,0 +1,253 @@
+use super::types::TestAccount;
+use revm_interpreter::{
+    AccountLoad, Eip7702CodeLoad, Host, SStoreResult, SelfDestructResult, StateLoad,
+};
+use revm_primitives::{keccak256, Address, Bytes, Env, Log, SpecId, B256, KECCAK_EMPTY, U256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A [`Host`] backed by the pre-state of a state test.
+///
+/// Both the interpreter and the compiled function are run against clones of the same host, and
+/// their [`State`]s are compared afterwards.
+#[derive(Clone, Debug)]
+pub(super) struct StateHost {
+    env: Env,
+    spec_id: SpecId,
+    pub(super) state: State,
+}
+
+/// The state observable by, and modified by, the executed code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct State {
+    pub(super) accounts: BTreeMap<Address, Account>,
+    pub(super) transient_storage: BTreeMap<(Address, U256), U256>,
+    pub(super) warm_accounts: BTreeSet<Address>,
+    pub(super) warm_slots: BTreeSet<(Address, U256)>,
+    pub(super) logs: Vec<Log>,
+    pub(super) selfdestructs: Vec<(Address, Address)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Account {
+    pub(super) balance: U256,
+    pub(super) nonce: u64,
+    pub(super) code: Bytes,
+    pub(super) code_hash: B256,
+    pub(super) storage: BTreeMap<U256, U256>,
+    pub(super) original_storage: BTreeMap<U256, U256>,
+}
+
+impl Account {
+    fn is_empty(&self) -> bool {
+        self.balance.is_zero() && self.nonce == 0 && self.code.is_empty()
+    }
+}
+
+impl StateHost {
+    /// Creates a new host from the pre-state, applying the transaction's effects that happen
+    /// before the first frame is executed.
+    pub(super) fn new(
+        env: Env,
+        spec_id: SpecId,
+        pre: &BTreeMap<Address, TestAccount>,
+        target: Address,
+    ) -> Self {
+        let accounts = pre
+            .iter()
+            .map(|(&address, account)| {
+                let account = Account {
+                    balance: account.balance,
+                    nonce: account.nonce.saturating_to(),
+                    code: account.code.clone(),
+                    code_hash: if account.code.is_empty() {
+                        KECCAK_EMPTY
+                    } else {
+                        keccak256(&account.code)
+                    },
+                    storage: account.storage.clone(),
+                    original_storage: account.storage.clone(),
+                };
+                (address, account)
+            })
+            .collect();
+        let mut this = Self { env, spec_id, state: State { accounts, ..Default::default() } };
+
+        let caller = this.env.tx.caller;
+        let value = this.env.tx.value;
+        let fee = this.env.effective_gas_price().saturating_mul(U256::from(this.env.tx.gas_limit));
+        let sender = this.state.accounts.entry(caller).or_default();
+        sender.balance = sender.balance.saturating_sub(fee).saturating_sub(value);
+        sender.nonce = sender.nonce.saturating_add(1);
+        let target_account = this.state.accounts.entry(target).or_default();
+        target_account.balance = target_account.balance.saturating_add(value);
+        if this.env.tx.transact_to.is_create() && spec_id.is_enabled_in(SpecId::SPURIOUS_DRAGON) {
+            target_account.nonce = 1;
+        }
+
+        if spec_id.is_enabled_in(SpecId::BERLIN) {
+            let warm = &mut this.state.warm_accounts;
+            warm.extend([caller, target]);
+            warm.extend((1..=last_precompile(spec_id)).map(Address::with_last_byte));
+            if spec_id.is_enabled_in(SpecId::SHANGHAI) {
+                warm.insert(this.env.block.coinbase);
+            }
+            for item in this.env.tx.access_list.iter() {
+                warm.insert(item.address);
+                let slots = item.storage_keys.iter().map(|&key| (item.address, key.into()));
+                this.state.warm_slots.extend(slots);
+            }
+        }
+
+        this
+    }
+
+    /// Marks the account as warm, returning whether it was cold.
+    fn warm_account(&mut self, address: Address) -> bool {
+        self.state.warm_accounts.insert(address)
+    }
+
+    /// Marks the storage slot as warm, returning whether it was cold.
+    fn warm_slot(&mut self, address: Address, index: U256) -> bool {
+        self.state.warm_slots.insert((address, index))
+    }
+
+    /// Returns `true` if the account is considered empty by `EXTCODEHASH`, `CALL` and friends.
+    fn is_empty(&self, address: Address) -> bool {
+        match self.state.accounts.get(&address) {
+            Some(account) => {
+                self.spec_id.is_enabled_in(SpecId::SPURIOUS_DRAGON) && account.is_empty()
+            }
+            None => true,
+        }
+    }
+
+    fn account(&self, address: Address) -> Option<&Account> {
+        self.state.accounts.get(&address)
+    }
+}
+
+impl Host for StateHost {
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn load_account_delegated(&mut self, address: Address) -> Option<AccountLoad> {
+        let is_cold = self.warm_account(address);
+        Some(AccountLoad {
+            load: Eip7702CodeLoad {
+                state_load: StateLoad::new((), is_cold),
+                is_delegate_account_cold: None,
+            },
+            is_empty: self.is_empty(address),
+        })
+    }
+
+    fn block_hash(&mut self, number: u64) -> Option<B256> {
+        // Same as `revm::db::EmptyDB`, which the reference implementation uses.
+        Some(keccak256(number.to_string()))
+    }
+
+    fn balance(&mut self, address: Address) -> Option<StateLoad<U256>> {
+        let is_cold = self.warm_account(address);
+        let balance = self.account(address).map(|a| a.balance).unwrap_or_default();
+        Some(StateLoad::new(balance, is_cold))
+    }
+
+    fn code(&mut self, address: Address) -> Option<StateLoad<Bytes>> {
+        let is_cold = self.warm_account(address);
+        let code = self.account(address).map(|a| a.code.clone()).unwrap_or_default();
+        Some(StateLoad::new(code, is_cold))
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<StateLoad<B256>> {
+        let is_cold = self.warm_account(address);
+        let hash = match self.account(address) {
+            Some(account) if !self.is_empty(address) => account.code_hash,
+            _ => B256::ZERO,
+        };
+        Some(StateLoad::new(hash, is_cold))
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<StateLoad<U256>> {
+        let is_cold = self.warm_slot(address, index);
+        let value =
+            self.account(address).and_then(|a| a.storage.get(&index).copied()).unwrap_or_default();
+        Some(StateLoad::new(value, is_cold))
+    }
+
+    fn sstore(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Option<StateLoad<SStoreResult>> {
+        let is_cold = self.warm_slot(address, index);
+        let account = self.state.accounts.entry(address).or_default();
+        let original_value = account.original_storage.get(&index).copied().unwrap_or_default();
+        let present_value = account.storage.insert(index, value).unwrap_or_default();
+        Some(StateLoad::new(
+            SStoreResult { original_value, present_value, new_value: value },
+            is_cold,
+        ))
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        self.state.transient_storage.get(&(address, index)).copied().unwrap_or_default()
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        self.state.transient_storage.insert((address, index), value);
+    }
+
+    fn log(&mut self, log: Log) {
+        self.state.logs.push(log);
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<StateLoad<SelfDestructResult>> {
+        let is_cold = self.warm_account(target);
+        let target_exists = !self.is_empty(target);
+        let previously_destroyed = self.state.selfdestructs.iter().any(|&(a, _)| a == address);
+        self.state.selfdestructs.push((address, target));
+
+        let balance = self.account(address).map(|a| a.balance).unwrap_or_default();
+        if address != target {
+            if let Some(account) = self.state.accounts.get_mut(&address) {
+                account.balance = U256::ZERO;
+            }
+            let target = self.state.accounts.entry(target).or_default();
+            target.balance = target.balance.saturating_add(balance);
+        }
+
+        Some(StateLoad::new(
+            SelfDestructResult {
+                had_value: !balance.is_zero(),
+                target_exists,
+                previously_destroyed,
+            },
+            is_cold,
+        ))
+    }
+}
+
+/// Returns the address of the last precompile enabled in the given spec.
+fn last_precompile(spec_id: SpecId) -> u8 {
+    if spec_id.is_enabled_in(SpecId::PRAGUE) {
+        0x11
+    } else if spec_id.is_enabled_in(SpecId::CANCUN) {
+        0x0a
+    } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+        0x09
+    } else if spec_id.is_enabled_in(SpecId::BYZANTIUM) {
+        0x08
+    } else {
+        0x04
+    }
+}
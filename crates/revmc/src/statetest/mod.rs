@@ -0,0 +1,461 @@
+//! Interpreter-equivalence tests driven by Ethereum state test fixtures.
+//!
+//! Runs the `GeneralStateTests` JSON fixtures from [`ethereum/tests`] and
+//! [`execution-spec-tests`]. Each test compiles the code of the transaction's target account (or
+//! the init code of a contract creation), and executes the first call frame of the transaction
+//! with both the compiled function and the [`revm_interpreter`] [`Interpreter`], against the same
+//! pre-state. The instruction result, next action (including output and gas), storage,
+//! transient storage, logs and touched accounts must be the same. If the frame runs to completion
+//! without calling into another frame, the logs hash is also compared with the fixture's.
+//!
+//! The whole transaction is then executed with [`revm`], once interpreted and once calling the
+//! compiled code of every frame whose code is known before execution. The gas used must be the
+//! same, and the post-state root and logs hash must match the fixture's. Tests whose fixture
+//! values are not reproduced by the interpreter alone, for example because they call a precompile
+//! that is not built in, are skipped.
+//!
+//! Fixtures in [`SKIPPED_TESTS`], and individual tests that cannot be run this way, are counted
+//! as skipped in the [`Report`].
+//!
+//! [`ethereum/tests`]: https://github.com/ethereum/tests
+//! [`execution-spec-tests`]: https://github.com/ethereum/execution-spec-tests
+
+use crate::{Backend, EvmCompiler, EvmCompilerFn};
+use revm::{
+    db::{CacheState, EmptyDB, State},
+    handler::register::EvmHandler,
+    Evm,
+};
+use revm_interpreter::{
+    gas::calculate_initial_tx_gas, opcode::make_instruction_table, Contract, Interpreter,
+    InterpreterAction, SharedMemory,
+};
+use revm_primitives::{
+    keccak256, spec_to_generic, AccountInfo, Address, Bytecode, Bytes, Env, Log, SpecId, TxKind,
+    B256,
+};
+use revmc_backend::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use rustc_hash::FxHashMap;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+mod host;
+use host::StateHost;
+
+mod trie;
+
+mod types;
+use types::{PostState, TestSuite, TestUnit};
+
+/// The compiled functions, by the hash of their code.
+type Functions = Arc<FxHashMap<B256, EvmCompilerFn>>;
+
+/// Fixture file names that are not run.
+///
+/// Entries are removed once the compiler supports what they exercise, so this list shrinking is a
+/// measure of feature-completeness.
+pub const SKIPPED_TESTS: &[&str] = &[
+    // Too slow to execute in debug builds.
+    "loopExp.json",
+    "loopMul.json",
+    "Call50000_sha256.json",
+    "static_Call50000_sha256.json",
+    "CALLBlake2f_MaxRounds.json",
+];
+
+/// The results of running state tests.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The number of tests that passed.
+    pub passed: usize,
+    /// The number of tests that were skipped.
+    pub skipped: usize,
+    /// The tests that failed.
+    pub failures: Vec<Failure>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for failure in &self.failures {
+            writeln!(f, "{failure}")?;
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed,
+            self.failures.len(),
+            self.skipped
+        )
+    }
+}
+
+impl Report {
+    /// Returns `true` if no tests failed.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A failed state test.
+#[derive(Debug)]
+pub struct Failure {
+    /// The fixture file.
+    pub path: PathBuf,
+    /// The test name.
+    pub name: String,
+    /// The spec the test was run with.
+    pub spec_id: SpecId,
+    /// The index of the test in the fixture's expected results for `spec_id`.
+    pub index: usize,
+    /// The error message.
+    pub error: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { path, name, spec_id, index, error } = self;
+        write!(f, "{}: {name} ({spec_id:?} #{index}): {error:#}", path.display())
+    }
+}
+
+enum Outcome {
+    Passed,
+    Skipped,
+}
+
+/// Runs all the JSON fixtures in the given file or directory.
+///
+/// The compiler's functions are freed after each fixture file.
+pub fn run<B: Backend>(compiler: &mut EvmCompiler<B>, path: &Path) -> Result<Report> {
+    let mut report = Report::default();
+    for path in find_all_json_tests(path) {
+        run_file(compiler, &path, &mut report)?;
+    }
+    Ok(report)
+}
+
+/// Returns all the JSON files in the given directory, or the path itself if it is a file.
+pub fn find_all_json_tests(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path.to_path_buf()];
+    }
+    walkdir::WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension() == Some("json".as_ref()))
+        .map(walkdir::DirEntry::into_path)
+        .collect()
+}
+
+/// Runs a single fixture file, adding the results to `report`.
+///
+/// Returns an error if the file could not be read or parsed.
+pub fn run_file<B: Backend>(
+    compiler: &mut EvmCompiler<B>,
+    path: &Path,
+    report: &mut Report,
+) -> Result<()> {
+    let s = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    let suite: TestSuite =
+        serde_json::from_str(&s).wrap_err_with(|| format!("failed to parse {}", path.display()))?;
+
+    let is_skipped =
+        path.file_name().and_then(|s| s.to_str()).is_some_and(|name| SKIPPED_TESTS.contains(&name));
+    let mut fn_counter = 0;
+    for (name, unit) in &suite {
+        for (spec_name, tests) in &unit.post {
+            let spec_id = spec_name.to_spec_id().filter(|_| !is_skipped);
+            let Some(spec_id) = spec_id else {
+                report.skipped += tests.len();
+                continue;
+            };
+            for (index, test) in tests.iter().enumerate() {
+                let fn_name = format!("statetest_{fn_counter}");
+                fn_counter += 1;
+                match run_test(compiler, &fn_name, unit, spec_id, test) {
+                    Ok(Outcome::Passed) => report.passed += 1,
+                    Ok(Outcome::Skipped) => report.skipped += 1,
+                    Err(error) => report.failures.push(Failure {
+                        path: path.to_path_buf(),
+                        name: name.clone(),
+                        spec_id,
+                        index,
+                        error: format!("{error:#}"),
+                    }),
+                }
+            }
+        }
+    }
+    unsafe { compiler.clear() }
+}
+
+fn run_test<B: Backend>(
+    compiler: &mut EvmCompiler<B>,
+    fn_name: &str,
+    unit: &TestUnit,
+    spec_id: SpecId,
+    test: &PostState,
+) -> Result<Outcome> {
+    let tx = &unit.transaction;
+    // Invalid transactions never execute any code.
+    if test.expect_exception.is_some() {
+        return Ok(Outcome::Skipped);
+    }
+    // EIP-7702 delegations are not applied by the host.
+    if tx.authorization_list.is_some() {
+        return Ok(Outcome::Skipped);
+    }
+    // The sender can't be recovered without a secp256k1 implementation.
+    let Some(caller) = tx.sender else { return Ok(Outcome::Skipped) };
+
+    let env = build_env(unit, spec_id, test, caller)?;
+    let (code, input, target) = match env.tx.transact_to {
+        TxKind::Call(to) => {
+            let code = unit.pre.get(&to).map(|a| a.code.clone()).unwrap_or_default();
+            (code, env.tx.data.clone(), to)
+        }
+        TxKind::Create => {
+            (env.tx.data.clone(), Bytes::new(), caller.create(tx.nonce.saturating_to()))
+        }
+    };
+    // Nothing to compile, or a precompile.
+    if code.is_empty() {
+        return Ok(Outcome::Skipped);
+    }
+    let bytecode = if spec_id.is_enabled_in(SpecId::OSAKA) {
+        match Bytecode::new_raw_checked(code) {
+            Ok(bytecode) => bytecode,
+            // Malformed EOF code can't be deployed.
+            Err(_) => return Ok(Outcome::Skipped),
+        }
+    } else {
+        Bytecode::new_legacy(code)
+    };
+    if bytecode.is_eip7702() {
+        return Ok(Outcome::Skipped);
+    }
+
+    let is_create = env.tx.transact_to.is_create();
+    let initial_gas =
+        calculate_initial_tx_gas(spec_id, &env.tx.data, is_create, &env.tx.access_list, 0)
+            .initial_gas;
+    let Some(gas_limit) = env.tx.gas_limit.checked_sub(initial_gas) else {
+        return Ok(Outcome::Skipped);
+    };
+
+    let value = env.tx.value;
+    let host = StateHost::new(env.clone(), spec_id, &unit.pre, target);
+    let contract = Contract::new(input, bytecode, None, target, None, caller, value);
+
+    // Reference.
+    let table = spec_to_generic!(spec_id, make_instruction_table::<StateHost, SPEC>());
+    let mut interpreter = Interpreter::new(contract.clone(), gas_limit, false);
+    let mut interpreter_host = host.clone();
+    let interpreter_action = interpreter.run(SharedMemory::new(), &table, &mut interpreter_host);
+    let interpreter_result = interpreter.instruction_result;
+
+    // Compiled.
+    let bytecode = contract.bytecode.original_byte_slice();
+    let f = unsafe { compiler.jit(fn_name, bytecode, spec_id) }.wrap_err("compilation failed")?;
+    let mut interpreter = Interpreter::new(contract, gas_limit, false);
+    let mut compiled_host = host;
+    let compiled_action = unsafe { f.call_with_interpreter(&mut interpreter, &mut compiled_host) };
+    let compiled_result = interpreter.instruction_result;
+
+    check_eq("instruction result", &interpreter_result, &compiled_result)?;
+    check_eq("next action", &interpreter_action, &compiled_action)?;
+    let (expected, actual) = (&interpreter_host.state, &compiled_host.state);
+    check_eq("accounts", &expected.accounts, &actual.accounts)?;
+    check_eq("transient storage", &expected.transient_storage, &actual.transient_storage)?;
+    check_eq("warm accounts", &expected.warm_accounts, &actual.warm_accounts)?;
+    check_eq("warm storage slots", &expected.warm_slots, &actual.warm_slots)?;
+    check_eq("logs", &expected.logs, &actual.logs)?;
+    check_eq("selfdestructs", &expected.selfdestructs, &actual.selfdestructs)?;
+
+    // The whole transaction was executed, so the logs must match the fixture's.
+    if let InterpreterAction::Return { result } = &interpreter_action {
+        if !is_create {
+            let logs = if result.result.is_ok() { &expected.logs[..] } else { &[] };
+            check_eq("logs hash", &test.logs, &logs_hash(logs))?;
+        }
+    }
+
+    // The whole transaction.
+    let reference = transact(unit, &env, spec_id, None);
+    if (reference.state_root, reference.logs_hash) != (test.hash, test.logs) {
+        return Ok(Outcome::Skipped);
+    }
+    let functions = compile_all(compiler, fn_name, unit, spec_id)?;
+    let compiled = transact(unit, &env, spec_id, Some(functions));
+    check_eq("gas used", &reference.gas_used, &compiled.gas_used)?;
+    check_eq("state root", &test.hash, &compiled.state_root)?;
+    check_eq("logs hash", &test.logs, &compiled.logs_hash)?;
+
+    Ok(Outcome::Passed)
+}
+
+/// Compiles the code of all the accounts in the pre-state, and the init code of a contract
+/// creation.
+fn compile_all<B: Backend>(
+    compiler: &mut EvmCompiler<B>,
+    fn_name: &str,
+    unit: &TestUnit,
+    spec_id: SpecId,
+) -> Result<Functions> {
+    let init_code = unit.transaction.to.is_none().then(|| &unit.transaction.data[..]);
+    let codes =
+        unit.pre.values().map(|account| &account.code).chain(init_code.into_iter().flatten());
+    let mut functions = FxHashMap::default();
+    for code in codes {
+        let bytecode = to_bytecode(code.clone());
+        if code.is_empty() || bytecode.is_eip7702() {
+            continue;
+        }
+        let hash = bytecode.hash_slow();
+        if functions.contains_key(&hash) {
+            continue;
+        }
+        let name = format!("{fn_name}_{}", functions.len());
+        let f = unsafe { compiler.jit(&name, bytecode.original_byte_slice(), spec_id) }
+            .wrap_err("compilation failed")?;
+        functions.insert(hash, f);
+    }
+    Ok(Arc::new(functions))
+}
+
+/// The result of executing a whole transaction.
+struct TxResult {
+    gas_used: u64,
+    state_root: B256,
+    logs_hash: B256,
+}
+
+/// Executes the transaction with revm, calling the given compiled functions instead of
+/// interpreting their code.
+fn transact(unit: &TestUnit, env: &Env, spec_id: SpecId, functions: Option<Functions>) -> TxResult {
+    let mut cache = CacheState::new(spec_id.is_enabled_in(SpecId::SPURIOUS_DRAGON));
+    for (&address, account) in &unit.pre {
+        let code = to_bytecode(account.code.clone());
+        let info = AccountInfo::new(
+            account.balance,
+            account.nonce.saturating_to(),
+            code.hash_slow(),
+            code,
+        );
+        cache.insert_account_with_storage(
+            address,
+            info,
+            account.storage.clone().into_iter().collect(),
+        );
+    }
+    let state = State::builder().with_cached_prestate(cache).with_bundle_update().build();
+
+    let builder =
+        Evm::builder().with_db(state).with_env(Box::new(env.clone())).with_spec_id(spec_id);
+    let mut evm = match functions {
+        Some(functions) => builder
+            .append_handler_register_box(Box::new(move |handler| {
+                register_functions(handler, functions.clone())
+            }))
+            .build(),
+        None => builder.build(),
+    };
+    let result = evm.transact_commit();
+    let (gas_used, logs) = match &result {
+        Ok(result) => (result.gas_used(), result.logs()),
+        Err(_) => (0, &[][..]),
+    };
+    let logs_hash = logs_hash(logs);
+    let state = evm.into_context().evm.inner.db;
+    TxResult { gas_used, state_root: trie::state_root(state.cache.trie_account()), logs_hash }
+}
+
+/// Calls the compiled functions for the frames that execute their code.
+#[allow(clippy::arc_with_non_send_sync)] // The handle type is an `Arc`.
+fn register_functions(handler: &mut EvmHandler<'_, (), State<EmptyDB>>, functions: Functions) {
+    let prev = handler.execution.execute_frame.clone();
+    handler.execution.execute_frame = Arc::new(move |frame, memory, tables, context| {
+        let interpreter = frame.interpreter_mut();
+        let bytecode = &interpreter.contract.bytecode;
+        let hash = interpreter.contract.hash.unwrap_or_else(|| bytecode.hash_slow());
+        match functions.get(&hash) {
+            Some(f) => {
+                Ok(unsafe { f.call_with_interpreter_and_memory(interpreter, memory, context) })
+            }
+            None => prev(frame, memory, tables, context),
+        }
+    });
+}
+
+/// Creates the bytecode of an account, which is legacy bytecode if it is not valid EOF.
+fn to_bytecode(code: Bytes) -> Bytecode {
+    Bytecode::new_raw_checked(code.clone()).unwrap_or_else(|_| Bytecode::new_legacy(code))
+}
+
+fn build_env(unit: &TestUnit, spec_id: SpecId, test: &PostState, caller: Address) -> Result<Env> {
+    let tx = &unit.transaction;
+    let indexes = test.indexes;
+    let mut env = Env::default();
+    env.cfg.chain_id = 1;
+
+    env.block.number = unit.env.current_number;
+    env.block.coinbase = unit.env.current_coinbase;
+    env.block.timestamp = unit.env.current_timestamp;
+    env.block.gas_limit = unit.env.current_gas_limit;
+    env.block.basefee = unit.env.current_base_fee.unwrap_or_default();
+    env.block.difficulty = unit.env.current_difficulty;
+    env.block.prevrandao = unit.env.current_random.map(B256::from);
+    if let Some(excess_blob_gas) = unit.env.current_excess_blob_gas {
+        env.block.set_blob_excess_gas_and_price(
+            excess_blob_gas.saturating_to(),
+            spec_id.is_enabled_in(SpecId::PRAGUE),
+        );
+    }
+
+    env.tx.caller = caller;
+    env.tx.gas_price = tx.gas_price.or(tx.max_fee_per_gas).unwrap_or_default();
+    env.tx.gas_priority_fee = tx.max_priority_fee_per_gas;
+    env.tx.blob_hashes.clone_from(&tx.blob_versioned_hashes);
+    env.tx.max_fee_per_blob_gas = tx.max_fee_per_blob_gas;
+    env.tx.nonce = Some(tx.nonce.saturating_to());
+    env.tx.transact_to = match tx.to {
+        Some(to) => TxKind::Call(to),
+        None => TxKind::Create,
+    };
+    let index_err = |field: &str, index: usize| eyre!("{field} index {index} is out of bounds");
+    env.tx.data = tx.data.get(indexes.data).ok_or_else(|| index_err("data", indexes.data))?.clone();
+    env.tx.gas_limit =
+        tx.gas_limit.get(indexes.gas).ok_or_else(|| index_err("gas", indexes.gas))?.saturating_to();
+    env.tx.value = *tx.value.get(indexes.value).ok_or_else(|| index_err("value", indexes.value))?;
+    if let Some(access_list) = tx.access_lists.get(indexes.data).cloned().flatten() {
+        env.tx.access_list = access_list.0;
+    }
+
+    Ok(env)
+}
+
+/// Returns the hash of the RLP encoding of the logs, as stored in the fixtures.
+fn logs_hash(logs: &[Log]) -> B256 {
+    let mut out = Vec::new();
+    alloy_rlp::encode_list::<_, Log>(logs, &mut out);
+    keccak256(out)
+}
+
+fn check_eq<T: PartialEq + fmt::Debug + ?Sized>(
+    what: &str,
+    expected: &T,
+    actual: &T,
+) -> Result<()> {
+    if expected != actual {
+        bail!("{what} mismatch:\n  expected: {expected:?}\n    actual: {actual:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Merkle Patricia trie roots, used to compare the post-state with the fixture's state root.
+
+use alloy_rlp::{Encodable, Header, EMPTY_STRING_CODE};
+use revm::db::PlainAccount;
+use revm_primitives::{keccak256, Address, B256};
+
+/// Returns the state root of the given accounts.
+pub(super) fn state_root<'a>(
+    accounts: impl IntoIterator<Item = (Address, &'a PlainAccount)>,
+) -> B256 {
+    secure_trie_root(accounts.into_iter().map(|(address, account)| {
+        let storage_root = secure_trie_root(
+            account
+                .storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(slot, value)| (slot.to_be_bytes::<32>(), alloy_rlp::encode(value))),
+        );
+        let info = &account.info;
+        let payload_length = info.nonce.length()
+            + info.balance.length()
+            + storage_root.length()
+            + info.code_hash.length();
+        let mut out = Vec::with_capacity(payload_length + 3);
+        Header { list: true, payload_length }.encode(&mut out);
+        info.nonce.encode(&mut out);
+        info.balance.encode(&mut out);
+        storage_root.encode(&mut out);
+        info.code_hash.encode(&mut out);
+        (address, out)
+    }))
+}
+
+/// Returns the root of the trie with the hashed keys.
+fn secure_trie_root<K: AsRef<[u8]>>(items: impl IntoIterator<Item = (K, Vec<u8>)>) -> B256 {
+    trie_root(items.into_iter().map(|(key, value)| (keccak256(key), value)))
+}
+
+/// Returns the root of the trie with the given keys and values.
+fn trie_root<K: AsRef<[u8]>>(items: impl IntoIterator<Item = (K, Vec<u8>)>) -> B256 {
+    let mut items = items
+        .into_iter()
+        .map(|(key, value)| {
+            let nibbles = key.as_ref().iter().flat_map(|&b| [b >> 4, b & 0xf]).collect::<Vec<_>>();
+            (nibbles, value)
+        })
+        .collect::<Vec<_>>();
+    items.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    items.dedup_by(|(a, _), (b, _)| a == b);
+    if items.is_empty() {
+        return keccak256([EMPTY_STRING_CODE]);
+    }
+    keccak256(encode_node(&items, 0))
+}
+
+/// Encodes the node of the given sorted items, whose keys share the first `depth` nibbles.
+fn encode_node(items: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    if let [(key, value)] = items {
+        return encode_list(&[encode_bytes(&hex_prefix(&key[depth..], true)), encode_bytes(value)]);
+    }
+
+    // The keys are sorted, so the common prefix of the first and last keys is shared by all.
+    let (first, last) = (&items[0].0, &items[items.len() - 1].0);
+    let prefix = first[depth..].iter().zip(&last[depth..]).take_while(|(a, b)| a == b).count();
+    if prefix > 0 {
+        let child = encode_node(items, depth + prefix);
+        let path = hex_prefix(&first[depth..depth + prefix], false);
+        return encode_list(&[encode_bytes(&path), node_ref(child)]);
+    }
+
+    // A key that ends here is the shortest, so it is sorted first.
+    let (value, items) = match items.split_first() {
+        Some(((key, value), rest)) if key.len() == depth => (encode_bytes(value), rest),
+        _ => (vec![EMPTY_STRING_CODE], items),
+    };
+    let mut children = Vec::with_capacity(17);
+    let mut rest = items;
+    for nibble in 0..16 {
+        let len = rest.partition_point(|(key, _)| key[depth] == nibble);
+        let (child, next) = rest.split_at(len);
+        children.push(if child.is_empty() {
+            vec![EMPTY_STRING_CODE]
+        } else {
+            node_ref(encode_node(child, depth + 1))
+        });
+        rest = next;
+    }
+    children.push(value);
+    encode_list(&children)
+}
+
+/// Returns the node itself if it is shorter than a hash, or its hash otherwise.
+fn node_ref(node: Vec<u8>) -> Vec<u8> {
+    if node.len() < 32 {
+        node
+    } else {
+        encode_bytes(keccak256(&node).as_slice())
+    }
+}
+
+/// Encodes the nibbles of a path with the hex-prefix encoding.
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(((flag | 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    out.extend(rest.chunks_exact(2).map(|pair| (pair[0] << 4) | pair[1]));
+    out
+}
+
+fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + 3);
+    bytes.encode(&mut out);
+    out
+}
+
+fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_length = items.iter().map(Vec::len).sum();
+    let mut out = Vec::with_capacity(payload_length + 3);
+    Header { list: true, payload_length }.encode(&mut out);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm_primitives::b256;
+
+    #[test]
+    fn empty() {
+        let root = trie_root(std::iter::empty::<(&[u8], _)>());
+        assert_eq!(root, b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"));
+    }
+
+    #[test]
+    fn puppy() {
+        let items = [("do", "verb"), ("dog", "puppy"), ("doge", "coin"), ("horse", "stallion")];
+        let root = trie_root(items.map(|(k, v)| (k, v.as_bytes().to_vec())));
+        assert_eq!(root, b256!("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"));
+    }
+}
@@ -0,0 +1,146 @@
+//! `GeneralStateTests` fixture format.
+//!
+//! Only the fields used by the runner are deserialized.
+
+use revm_primitives::{AccessList, Address, Bytes, SpecId, B256, U256};
+use serde::{de, Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+/// A fixture file, mapping test names to their contents.
+pub(super) type TestSuite = BTreeMap<String, TestUnit>;
+
+/// A single state test.
+#[derive(Debug, Deserialize)]
+pub(super) struct TestUnit {
+    pub(super) env: TestEnv,
+    pub(super) pre: BTreeMap<Address, TestAccount>,
+    pub(super) transaction: TestTransaction,
+    pub(super) post: BTreeMap<SpecName, Vec<PostState>>,
+}
+
+/// The block environment.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TestEnv {
+    pub(super) current_coinbase: Address,
+    #[serde(default)]
+    pub(super) current_difficulty: U256,
+    pub(super) current_gas_limit: U256,
+    pub(super) current_number: U256,
+    pub(super) current_timestamp: U256,
+    pub(super) current_base_fee: Option<U256>,
+    pub(super) current_random: Option<U256>,
+    pub(super) current_excess_blob_gas: Option<U256>,
+}
+
+/// An account in the pre-state.
+#[derive(Debug, Deserialize)]
+pub(super) struct TestAccount {
+    pub(super) balance: U256,
+    pub(super) code: Bytes,
+    pub(super) nonce: U256,
+    pub(super) storage: BTreeMap<U256, U256>,
+}
+
+/// The transaction template. `data`, `gas_limit` and `value` are indexed by
+/// [`PostState::indexes`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TestTransaction {
+    pub(super) data: Vec<Bytes>,
+    pub(super) gas_limit: Vec<U256>,
+    pub(super) gas_price: Option<U256>,
+    pub(super) nonce: U256,
+    pub(super) sender: Option<Address>,
+    #[serde(deserialize_with = "deserialize_maybe_empty")]
+    pub(super) to: Option<Address>,
+    pub(super) value: Vec<U256>,
+    pub(super) max_fee_per_gas: Option<U256>,
+    pub(super) max_priority_fee_per_gas: Option<U256>,
+    #[serde(default)]
+    pub(super) access_lists: Vec<Option<AccessList>>,
+    pub(super) authorization_list: Option<serde_json::Value>,
+    #[serde(default)]
+    pub(super) blob_versioned_hashes: Vec<B256>,
+    pub(super) max_fee_per_blob_gas: Option<U256>,
+}
+
+/// The expected result of running the transaction with a given set of indexes.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PostState {
+    pub(super) hash: B256,
+    pub(super) logs: B256,
+    pub(super) indexes: TxIndexes,
+    pub(super) expect_exception: Option<String>,
+}
+
+/// Indexes into the [`TestTransaction`] fields.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(super) struct TxIndexes {
+    pub(super) data: usize,
+    pub(super) gas: usize,
+    pub(super) value: usize,
+}
+
+/// The fork names used in fixtures.
+///
+/// Transition forks are not supported, and are deserialized as [`SpecName::Unknown`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub(super) enum SpecName {
+    Frontier,
+    Homestead,
+    #[serde(rename = "EIP150")]
+    Tangerine,
+    #[serde(rename = "EIP158")]
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    ConstantinopleFix,
+    Istanbul,
+    Berlin,
+    London,
+    #[serde(alias = "Merge")]
+    Paris,
+    Shanghai,
+    Cancun,
+    Prague,
+    Osaka,
+    #[serde(other)]
+    Unknown,
+}
+
+impl SpecName {
+    pub(super) fn to_spec_id(self) -> Option<SpecId> {
+        Some(match self {
+            Self::Frontier => SpecId::FRONTIER,
+            Self::Homestead => SpecId::HOMESTEAD,
+            Self::Tangerine => SpecId::TANGERINE,
+            Self::SpuriousDragon => SpecId::SPURIOUS_DRAGON,
+            Self::Byzantium => SpecId::BYZANTIUM,
+            Self::Constantinople => SpecId::CONSTANTINOPLE,
+            Self::ConstantinopleFix => SpecId::PETERSBURG,
+            Self::Istanbul => SpecId::ISTANBUL,
+            Self::Berlin => SpecId::BERLIN,
+            Self::London => SpecId::LONDON,
+            Self::Paris => SpecId::MERGE,
+            Self::Shanghai => SpecId::SHANGHAI,
+            Self::Cancun => SpecId::CANCUN,
+            Self::Prague => SpecId::PRAGUE,
+            Self::Osaka => SpecId::OSAKA,
+            Self::Unknown => return None,
+        })
+    }
+}
+
+/// Deserializes an address that is an empty string for contract creation transactions.
+fn deserialize_maybe_empty<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Address>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    if s.is_empty() {
+        Ok(None)
+    } else {
+        s.parse().map(Some).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,64 @@
+//! Checks the IR emitted for each [`Assertions`] mode.
+
+use super::DEF_SPEC;
+use crate::{llvm::with_llvm_context, Assertions, EvmCompiler, EvmLlvmBackend, OptimizationLevel};
+use revm_interpreter::opcode as op;
+
+#[rustfmt::skip]
+const LOOP: &[u8] = &[
+    op::PUSH1, 0,
+    op::JUMPDEST,
+    op::PUSH1, 1,
+    op::ADD,
+    op::DUP1,
+    op::PUSH1, 100,
+    op::GT,
+    op::PUSH1, 2,
+    op::JUMPI,
+    op::STOP,
+];
+
+/// Compiles [`LOOP`] and returns the unoptimized and optimized IR.
+fn compile(assertions: Assertions) -> (String, String) {
+    let tmp = tempfile::tempdir().expect("could not create temp dir");
+    with_llvm_context(|cx| {
+        let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::Aggressive).unwrap();
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.inspect_stack_length(true);
+        compiler.assertions(assertions);
+        compiler.set_dump_to(Some(tmp.path().to_path_buf()));
+        let id = compiler.translate("assertions", LOOP, DEF_SPEC).unwrap();
+        let _ = unsafe { compiler.jit_function(id) }.unwrap();
+        assert_eq!(compiler.fn_info(id).unwrap().assertions, assertions);
+    });
+    let read = |name: &str| std::fs::read_to_string(tmp.path().join(name)).unwrap();
+    (read("unopt.ll"), read("opt.ll"))
+}
+
+fn count_null_compares(ir: &str) -> usize {
+    ir.lines().filter(|line| line.contains("icmp eq ptr") && line.contains("null")).count()
+}
+
+#[test]
+fn check() {
+    let (unopt, opt) = compile(Assertions::Check);
+    assert!(!unopt.contains("@llvm.assume"));
+    assert!(opt.contains("@__revmc_builtin_panic"));
+    assert!(count_null_compares(&opt) > 0);
+}
+
+#[test]
+fn assume() {
+    let (unopt, opt) = compile(Assertions::Assume);
+    assert!(unopt.contains("call void @llvm.assume"));
+    assert!(!opt.contains("@__revmc_builtin_panic"));
+    let (_, checked) = compile(Assertions::Check);
+    assert!(count_null_compares(&opt) < count_null_compares(&checked));
+}
+
+#[test]
+fn none() {
+    let (unopt, opt) = compile(Assertions::None);
+    assert!(!unopt.contains("@llvm.assume"));
+    assert!(!opt.contains("@__revmc_builtin_panic"));
+}
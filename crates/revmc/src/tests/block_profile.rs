@@ -0,0 +1,56 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, CompileError, EvmCompiler, ExecutionProfile};
+use revm_interpreter::{opcode as op, InstructionResult};
+use std::collections::BTreeMap;
+
+matrix_tests!(enabled = |compiler| run(compiler, true));
+matrix_tests!(disabled = |compiler| run(compiler, false));
+matrix_tests!(stale = |compiler| stale(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 3,
+    // Loop 3 times.
+    op::JUMPDEST,
+    op::PUSH0, op::PUSH0, op::KECCAK256, op::POP,
+    op::PUSH1, 1, op::SWAP1, op::SUB,
+    op::DUP1, op::PUSH1, 2, op::JUMPI,
+    op::STOP,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, block_profile: bool) {
+    compiler.block_profile(block_profile);
+    let f = unsafe { compiler.jit("block_profile", TEST, DEF_SPEC) }.unwrap();
+
+    let profile = with_evm_context(TEST, |ecx, stack, stack_len| {
+        ecx.block_profile = Some(Box::leak(Box::new(ExecutionProfile::new(TEST))));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        ecx.block_profile.as_deref().unwrap().clone()
+    });
+    if !block_profile {
+        assert!(profile.is_empty());
+        return;
+    }
+    assert_eq!(profile.blocks, BTreeMap::from([(0, 1), (2, 3), (15, 1)]));
+
+    // Recompile with the profile.
+    compiler.block_profile(false);
+    let f = unsafe { compiler.jit_with_profile("block_profile.opt", TEST, DEF_SPEC, &profile) }
+        .unwrap();
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+    });
+}
+
+fn stale<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let mut profile = ExecutionProfile::new(&TEST[..TEST.len() - 1]);
+    profile.record(0);
+    let err = compiler.translate_with_profile("stale", TEST, DEF_SPEC, &profile).unwrap_err();
+    assert!(matches!(err, CompileError::InvalidUsage(_)), "{err:?}");
+    assert_eq!(
+        err.to_string(),
+        format!("profile code hash {} does not match the bytecode", profile.code_hash)
+    );
+}
@@ -0,0 +1,51 @@
+use super::{with_evm_context, TestHost, DEF_BN, DEF_SPEC};
+use crate::{Backend, EvmCompiler, RecordingHost};
+use revm_interpreter::{opcode as op, InstructionResult};
+use revm_primitives::U256;
+
+matrix_tests!(bounds = |compiler| run(compiler));
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let numbers = [
+        DEF_BN - U256::from(1),
+        DEF_BN - U256::from(256),
+        DEF_BN - U256::from(257),
+        DEF_BN,
+        U256::from(1) << 70,
+    ];
+    let mut code = Vec::new();
+    for number in numbers {
+        code.push(op::PUSH32);
+        code.extend_from_slice(&number.to_be_bytes::<32>());
+        code.push(op::BLOCKHASH);
+    }
+    code.push(op::STOP);
+
+    let f = unsafe { compiler.jit("blockhash", &code, DEF_SPEC) }.unwrap();
+    with_evm_context(&code, |ecx, stack, stack_len| {
+        ecx.host = Box::leak(Box::new(RecordingHost::new(TestHost::new())));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        assert_eq!(ecx.gas.spent(), numbers.len() as u64 * (3 + 20));
+
+        let hashes = stack.as_slice()[..*stack_len].iter().map(|w| w.to_u256()).collect::<Vec<_>>();
+        assert_eq!(hashes, [numbers[0], numbers[1], U256::ZERO, U256::ZERO, U256::ZERO]);
+
+        #[cfg(not(feature = "__fuzzing"))]
+        {
+            use crate::HostCall;
+
+            let host = ecx.host.downcast_ref::<RecordingHost<TestHost>>().unwrap();
+            let queried = host
+                .tape()
+                .calls
+                .iter()
+                .map(|call| match call {
+                    HostCall::BlockHash { number, .. } => U256::from(*number),
+                    call => panic!("unexpected host call: {call:?}"),
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(queried, numbers[..2]);
+        }
+    });
+}
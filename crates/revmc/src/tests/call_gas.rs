@@ -0,0 +1,107 @@
+use super::{TestHost, DEF_SPEC};
+use crate::{test_contract, Backend, EvmCompiler, EvmCompilerFn, FrameOutcome};
+use revm_interpreter::{
+    gas, opcode as op, CallOutcome, Gas, InstructionResult, Interpreter, InterpreterAction,
+    InterpreterResult, SharedMemory,
+};
+use revm_primitives::{spec_to_generic, Bytes, SpecId};
+
+matrix_tests!(no_value = |compiler| run(compiler, false));
+matrix_tests!(value = |compiler| run(compiler, true));
+
+const GAS_LIMIT: u64 = 100_000;
+const FORWARDED: u64 = 10_000;
+
+/// `call(FORWARDED, 0x69, value, 0, 0, 0, 32)`, then returns the success flag and the child's
+/// output.
+fn caller(value: bool) -> Bytes {
+    #[rustfmt::skip]
+    let code = [
+        op::PUSH1, 32, op::PUSH0, op::PUSH0, op::PUSH0,
+        op::PUSH1, value as u8,
+        op::PUSH1, 0x69,
+        op::PUSH2, (FORWARDED >> 8) as u8, FORWARDED as u8,
+        op::CALL,
+        op::PUSH1, 32, op::MSTORE,
+        op::PUSH1, 64, op::PUSH0, op::RETURN,
+    ];
+    Bytes::copy_from_slice(&code)
+}
+
+/// The outcomes of a child given `gas_limit`: it returns, reverts, runs out of gas, or
+/// selfdestructs. Every child accumulates a refund, which must only be merged on success.
+fn outcomes(gas_limit: u64) -> [CallOutcome; 4] {
+    let outcome = |result, spent, refund| {
+        let mut gas = Gas::new(gas_limit);
+        if result == InstructionResult::OutOfGas {
+            gas.spend_all();
+        } else {
+            assert!(gas.record_cost(spent));
+        }
+        gas.record_refund(refund);
+        let output = Bytes::from_static(&[0xff; 32]);
+        CallOutcome::new(InterpreterResult { result, output, gas }, 0..32)
+    };
+    [
+        outcome(InstructionResult::Return, 1000, 4800),
+        outcome(InstructionResult::Revert, 1000, 4800),
+        outcome(InstructionResult::OutOfGas, 0, 4800),
+        outcome(InstructionResult::SelfDestruct, 5000, gas::SELFDESTRUCT),
+    ]
+}
+
+fn new_interpreter(code: &Bytes) -> Interpreter {
+    let contract = test_contract(code.clone());
+    let mut interpreter = Interpreter::new(contract, GAS_LIMIT, false);
+    interpreter.shared_memory = SharedMemory::new();
+    interpreter
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, value: bool) {
+    let code = caller(value);
+    let f = unsafe { compiler.jit("call_gas", &code, DEF_SPEC) }.unwrap();
+    let stipend = if value { gas::CALL_STIPEND } else { 0 };
+    for i in 0..4 {
+        let expected = run_interpreter(&code, i, stipend);
+        let actual = run_compiled(f, &code, i, stipend);
+        let (
+            InterpreterAction::Return { result: expected },
+            InterpreterAction::Return { result: actual },
+        ) = (expected, actual)
+        else {
+            panic!("expected return actions");
+        };
+        assert_eq!(actual.result, InstructionResult::Return, "outcome {i}");
+        assert_eq!(actual.output, expected.output, "outcome {i}");
+        assert_eq!(actual.gas.remaining(), expected.gas.remaining(), "outcome {i}");
+        assert_eq!(actual.gas.refunded(), expected.gas.refunded(), "outcome {i}");
+    }
+}
+
+/// Returns the outcome `i` for the call requested by `action`, checking the stipend.
+fn outcome(action: &InterpreterAction, i: usize, stipend: u64) -> CallOutcome {
+    let InterpreterAction::Call { inputs } = action else { panic!("expected a call: {action:?}") };
+    assert_eq!(inputs.gas_limit, FORWARDED + stipend);
+    outcomes(inputs.gas_limit)[i].clone()
+}
+
+fn run_interpreter(code: &Bytes, i: usize, stipend: u64) -> InterpreterAction {
+    let table = spec_to_generic!(DEF_SPEC, op::make_instruction_table::<_, SPEC>());
+    let mut interpreter = new_interpreter(code);
+    let mut host = TestHost::new();
+    let memory = interpreter.take_memory();
+    let action = interpreter.run(memory, &table, &mut host);
+    let outcome = outcome(&action, i, stipend);
+    let mut memory = interpreter.take_memory();
+    interpreter.insert_call_outcome(&mut memory, outcome);
+    interpreter.run(memory, &table, &mut host)
+}
+
+fn run_compiled(f: EvmCompilerFn, code: &Bytes, i: usize, stipend: u64) -> InterpreterAction {
+    let mut interpreter = new_interpreter(code);
+    let mut host = TestHost::new();
+    let action = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+    let outcome = outcome(&action, i, stipend);
+    FrameOutcome::Call(outcome).insert_into(&mut interpreter);
+    unsafe { f.call_with_interpreter(&mut interpreter, &mut host) }
+}
@@ -0,0 +1,45 @@
+use super::DEF_SPEC;
+use crate::{Backend, CodegenTier, EvmCompiler};
+use revm_interpreter::opcode as op;
+
+matrix_tests!(auto = |compiler| auto(compiler));
+matrix_tests!(manual = |compiler| manual(compiler));
+
+const THRESHOLD: usize = 100;
+
+/// Returns bytecode with `2 * n + 1` instructions.
+fn code(n: usize) -> Vec<u8> {
+    let mut code = [op::PUSH1, 1, op::POP].repeat(n);
+    code.push(op::STOP);
+    code
+}
+
+fn tier<B: Backend>(compiler: &mut EvmCompiler<B>, name: &str, code: &[u8]) -> CodegenTier {
+    let id = compiler.translate(name, code, DEF_SPEC).unwrap();
+    compiler.stats(id).unwrap().codegen_tier
+}
+
+fn auto<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.size_tier_threshold(THRESHOLD);
+    assert_eq!(tier(compiler, "small", &code(10)), CodegenTier::Speed);
+    assert_eq!(tier(compiler, "below", &code(THRESHOLD / 2 - 1)), CodegenTier::Speed);
+    assert_eq!(tier(compiler, "above", &code(THRESHOLD / 2)), CodegenTier::Size);
+    assert_eq!(tier(compiler, "large", &code(THRESHOLD)), CodegenTier::Size);
+
+    // Dead code is not counted.
+    let mut dead = code(10);
+    dead.extend(code(THRESHOLD));
+    assert_eq!(tier(compiler, "dead", &dead), CodegenTier::Speed);
+
+    // Both tiers can be compiled in the same module.
+    let _ = unsafe { compiler.jit("jit", &code(THRESHOLD), DEF_SPEC) }.unwrap();
+}
+
+fn manual<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.size_tier_threshold(THRESHOLD);
+    compiler.codegen_tier(CodegenTier::Speed);
+    assert_eq!(tier(compiler, "large", &code(THRESHOLD)), CodegenTier::Speed);
+    compiler.codegen_tier(CodegenTier::Size);
+    assert_eq!(tier(compiler, "small", &code(10)), CodegenTier::Size);
+    let _ = unsafe { compiler.jit("jit", &code(10), DEF_SPEC) }.unwrap();
+}
@@ -0,0 +1,63 @@
+use super::{eof_sections_unchecked, DEF_SPEC};
+use crate::{Backend, CompileError, EvmCompiler, Limit};
+use revm_interpreter::opcode as op;
+use revm_primitives::SpecId;
+
+matrix_tests!(unsupported_opcode = |compiler| unsupported_opcode(compiler));
+matrix_tests!(invalid_bytecode = |compiler| invalid_bytecode(compiler));
+matrix_tests!(invalid_usage = |compiler| invalid_usage(compiler));
+matrix_tests!(limit_exceeded = |compiler| limit_exceeded(compiler));
+matrix_tests!(io = |compiler| io(compiler));
+
+fn unsupported_opcode<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    // `0x0C` is not defined in any spec.
+    let eof = eof_sections_unchecked(&[&[0x0C, op::STOP]]);
+    compiler.validate_eof(false);
+    let err = compiler.translate("unsupported_opcode", &eof.raw[..], SpecId::OSAKA).unwrap_err();
+    match err {
+        CompileError::UnsupportedOpcode { opcode, spec, .. } => {
+            assert_eq!(opcode, 0x0C);
+            assert_eq!(spec, SpecId::OSAKA);
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+fn invalid_bytecode<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let code = [0xEF, 0x00, 0x01];
+    let err = compiler.translate("invalid_bytecode", &code[..], SpecId::OSAKA).unwrap_err();
+    assert!(matches!(err, CompileError::InvalidBytecode(_)), "{err:?}");
+
+    // Invalid jump destinations are not an error.
+    let code = [op::PUSH1, 0x10, op::JUMP];
+    compiler.translate("invalid_jump", &code[..], DEF_SPEC).unwrap();
+}
+
+fn invalid_usage<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let code = [op::STOP];
+    compiler.translate("invalid_usage", &code[..], DEF_SPEC).unwrap();
+    let err = compiler.translate("invalid_usage", &code[..], DEF_SPEC).unwrap_err();
+    assert!(matches!(err, CompileError::InvalidUsage(_)), "{err:?}");
+    assert_eq!(err.to_string(), "function name `invalid_usage` is not unique");
+}
+
+fn limit_exceeded<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let code = [op::PUSH0, op::STOP];
+    compiler.max_code_size(Some(1));
+    let err = compiler.translate("limit_exceeded", &code[..], DEF_SPEC).unwrap_err();
+    match err {
+        CompileError::LimitExceeded(limit) => {
+            assert_eq!(limit, Limit::CodeSize { size: 2, max: 1 })
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+
+    compiler.max_code_size(Some(2));
+    compiler.translate("limit_exceeded", &code[..], DEF_SPEC).unwrap();
+}
+
+fn io<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let path = std::env::temp_dir().join("revmc-compile-error-missing").join("a.o");
+    let err = compiler.write_object_to_file(&path).unwrap_err();
+    assert!(matches!(err, CompileError::Io(_)), "{err:?}");
+}
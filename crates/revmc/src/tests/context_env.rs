@@ -0,0 +1,18 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(timestamp = |compiler| run(compiler));
+
+const TEST: &[u8] = &[op::TIMESTAMP, op::STOP];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.inspect_stack_length(true);
+    let f = unsafe { compiler.jit("context_env", TEST, DEF_SPEC) }.unwrap();
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        assert_eq!(*stack_len, 1);
+        assert_eq!(stack.as_slice()[0].to_u256(), ecx.env().block.timestamp);
+    });
+}
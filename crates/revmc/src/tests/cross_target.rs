@@ -0,0 +1,64 @@
+//! Checks the objects emitted for targets other than the host.
+
+use super::DEF_SPEC;
+use crate::{llvm::with_llvm_context, Backend, EvmCompiler, EvmLlvmBackend, OptimizationLevel};
+use revm_interpreter::opcode as op;
+
+const CODE: &[u8] = &[op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::PUSH0, op::SSTORE, op::STOP];
+
+/// Compiles [`CODE`] for the given target triple and returns the object.
+fn compile(triple: &str) -> Vec<u8> {
+    with_llvm_context(|cx| {
+        let mut backend = EvmLlvmBackend::new(cx, true, OptimizationLevel::Default).unwrap();
+        backend.set_target(triple, None, None).unwrap();
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.translate("cross_target", CODE, DEF_SPEC).unwrap();
+        let mut object = Vec::new();
+        compiler.write_object(&mut object).unwrap();
+        object
+    })
+}
+
+/// Returns the `e_machine` field of a little-endian ELF header.
+fn elf_machine(object: &[u8]) -> u16 {
+    assert_eq!(object[..4], *b"\x7fELF");
+    // `EI_DATA` is `ELFDATA2LSB`.
+    assert_eq!(object[5], 1);
+    u16::from_le_bytes([object[18], object[19]])
+}
+
+#[test]
+fn elf_machine_matches_target() {
+    const EM_X86_64: u16 = 62;
+    const EM_AARCH64: u16 = 183;
+    const EM_RISCV: u16 = 243;
+
+    for (triple, machine) in [
+        ("x86_64-unknown-linux-gnu", EM_X86_64),
+        ("aarch64-unknown-linux-gnu", EM_AARCH64),
+        ("riscv64gc-unknown-linux-gnu", EM_RISCV),
+    ] {
+        assert_eq!(elf_machine(&compile(triple)), machine, "{triple}");
+    }
+}
+
+#[test]
+fn byte_order_matches_target() {
+    with_llvm_context(|cx| {
+        for (triple, little_endian) in
+            [("x86_64-unknown-linux-gnu", true), ("s390x-unknown-linux-gnu", false)]
+        {
+            let mut backend = EvmLlvmBackend::new(cx, true, OptimizationLevel::Default).unwrap();
+            backend.set_target(triple, None, None).unwrap();
+            assert_eq!(backend.is_little_endian(), little_endian, "{triple}");
+        }
+    });
+}
+
+#[test]
+fn jit_target_is_fixed() {
+    with_llvm_context(|cx| {
+        let mut backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::Default).unwrap();
+        assert!(backend.set_target("aarch64-unknown-linux-gnu", None, None).is_err());
+    });
+}
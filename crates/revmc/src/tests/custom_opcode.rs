@@ -0,0 +1,49 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, CustomBuiltin, CustomOpcode, EvmCompiler, EvmContext, EvmWord};
+use revm_interpreter::{opcode as op, InstructionResult};
+use revm_primitives::U256;
+
+matrix_tests!(mul = |compiler| run(compiler));
+
+const CUSTOM_MUL: u8 = 0xC0;
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 6, op::PUSH1, 7, CUSTOM_MUL,
+    op::PUSH0, op::MSTORE,
+    op::PUSH1, 32, op::PUSH0, op::RETURN,
+];
+
+unsafe extern "C" fn custom_mul(
+    _ecx: &mut EvmContext<'_>,
+    args: *const EvmWord,
+    out: *mut EvmWord,
+) -> InstructionResult {
+    let [a, b] = unsafe { args.cast::<[EvmWord; 2]>().read() };
+    unsafe { out.write((a.to_u256() * b.to_u256()).into()) };
+    InstructionResult::Continue
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let builtin = CustomBuiltin::new("custom_mul", custom_mul);
+    compiler.register_custom_opcode(
+        CUSTOM_MUL,
+        CustomOpcode { inputs: 2, outputs: 1, gas: 7, builtin },
+    );
+    let f = unsafe { compiler.jit("custom_opcode", TEST, DEF_SPEC) }.unwrap();
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Return);
+        assert_eq!(ecx.memory_word(0).to_u256(), U256::from(42));
+        // PUSH1, PUSH1, custom, PUSH0, MSTORE + 1 word of memory, PUSH1, PUSH0, RETURN.
+        assert_eq!(ecx.gas.spent(), 3 + 3 + 7 + 2 + 3 + 3 + 3 + 2);
+    });
+
+    // Collides with `MUL`.
+    unsafe { compiler.clear() }.unwrap();
+    compiler
+        .register_custom_opcode(op::MUL, CustomOpcode { inputs: 2, outputs: 1, gas: 7, builtin });
+    let err = compiler.translate("custom_opcode_mul", TEST, DEF_SPEC).unwrap_err();
+    assert!(err.to_string().contains("collides with MUL"), "{err}");
+}
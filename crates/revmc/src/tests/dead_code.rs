@@ -0,0 +1,15 @@
+use super::DEF_SPEC;
+use crate::{Backend, EvmCompiler};
+use revm_primitives::hex;
+
+matrix_tests!(metadata = |compiler| run(compiler));
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let code = hex::decode(include_str!("../../../../data/counter.rt.hex").trim()).unwrap();
+    let metadata_len = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) + 2;
+    let id = compiler.translate("dead_code", &code, DEF_SPEC).unwrap();
+    let stats = compiler.stats(id).unwrap();
+    assert_eq!(stats.code_bytes, code.len());
+    assert!(stats.dead_code_bytes >= metadata_len as usize, "{stats:?}");
+    let _ = unsafe { compiler.jit_function(id) }.unwrap();
+}
@@ -0,0 +1,45 @@
+//! Checks the IR emitted for dead values.
+
+use super::DEF_SPEC;
+use crate::{llvm::with_llvm_context, EvmCompiler, EvmLlvmBackend, OptimizationLevel};
+use revm_interpreter::opcode as op;
+
+/// Compiles `code` and returns the unoptimized IR, which still has the instructions that the
+/// analysis did not skip.
+fn compile(code: &[u8], dead_code: bool) -> String {
+    let tmp = tempfile::tempdir().expect("could not create temp dir");
+    with_llvm_context(|cx| {
+        let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::None).unwrap();
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.opt_dead_code(dead_code);
+        compiler.set_dump_to(Some(tmp.path().to_path_buf()));
+        let id = compiler.translate("dead_values", code, DEF_SPEC).unwrap();
+        let _ = unsafe { compiler.jit_function(id) }.unwrap();
+    });
+    std::fs::read_to_string(tmp.path().join("unopt.ll")).unwrap()
+}
+
+fn count_adds(ir: &str) -> usize {
+    ir.lines().filter(|line| line.contains("= add i256")).count()
+}
+
+#[test]
+fn pop() {
+    let code = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::POP, op::STOP];
+    assert_eq!(count_adds(&compile(&code, false)), 1);
+    assert_eq!(count_adds(&compile(&code, true)), 0);
+}
+
+#[test]
+fn stop() {
+    let code = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::STOP];
+    assert_eq!(count_adds(&compile(&code, false)), 1);
+    assert_eq!(count_adds(&compile(&code, true)), 0);
+}
+
+#[test]
+fn ret() {
+    let code = [op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::PUSH0, op::PUSH0, op::RETURN];
+    assert_eq!(count_adds(&compile(&code, false)), 1);
+    assert_eq!(count_adds(&compile(&code, true)), 0);
+}
@@ -0,0 +1,128 @@
+//! Checks that deterministic compilation emits bit-identical objects.
+
+use super::DEF_SPEC;
+use crate::{Backend, EvmCompiler, OptimizationLevel, Result, Target};
+use revm_primitives::hex;
+
+fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    let read = |s: &str| hex::decode(s.trim()).unwrap();
+    vec![
+        ("erc20", read(include_str!("../../../../data/erc20_transfer.rt.hex"))),
+        ("fiat_token", read(include_str!("../../../../data/fiat_token.rt.hex"))),
+        ("univ2_router", read(include_str!("../../../../data/univ2_router.rt.hex"))),
+        ("weth", read(include_str!("../../../../data/weth.rt.hex"))),
+    ]
+}
+
+fn target() -> Target {
+    Target::new("x86_64-unknown-linux-gnu", Some("x86-64-v2".into()), Some("+sse4.2".into()))
+}
+
+/// Compiles the fixtures in the given order, with a selector-specialized copy of the router.
+fn compile<B: Backend>(backend: B, order: &[usize]) -> Result<Vec<u8>> {
+    let fixtures = fixtures();
+    let mut compiler = EvmCompiler::new(backend);
+    compiler.set_module_name("deterministic");
+    compiler.deterministic(true);
+    for &i in order {
+        let (name, code) = &fixtures[i];
+        compiler.translate(name, &code[..], DEF_SPEC)?;
+        if *name == "univ2_router" {
+            let selector = hex!("38ed1739");
+            let name = "univ2_router_swap";
+            compiler.specialize_for_selector(
+                name,
+                "univ2_router",
+                &code[..],
+                DEF_SPEC,
+                selector,
+            )?;
+        }
+    }
+    let mut object = Vec::new();
+    compiler.write_object(&mut object)?;
+    Ok(object)
+}
+
+fn explicit_target_errors(compile: impl Fn(&[usize], &Target) -> Result<Vec<u8>>) {
+    for target in [
+        Target::Native,
+        Target::new("x86_64-unknown-linux-gnu", None, None),
+        Target::new("x86_64-unknown-linux-gnu", Some("x86-64-v2".into()), None),
+    ] {
+        let err = compile(&[0], &target).unwrap_err().to_string();
+        assert!(err.contains("requires an explicit target CPU and features"), "{target:?}: {err}");
+    }
+}
+
+#[cfg(feature = "llvm")]
+mod llvm {
+    use super::*;
+    use crate::{llvm::with_llvm_context, EvmLlvmBackend};
+
+    fn compile_llvm(order: &[usize], target: &Target) -> Result<Vec<u8>> {
+        with_llvm_context(|cx| {
+            let opt_level = OptimizationLevel::Aggressive;
+            compile(EvmLlvmBackend::new_for_target(cx, true, opt_level, target)?, order)
+        })
+    }
+
+    #[test]
+    fn same_object() {
+        let target = target();
+        let object = compile_llvm(&[0, 1, 2, 3], &target).unwrap();
+        assert!(!object.is_empty());
+        assert_eq!(compile_llvm(&[3, 1, 0, 2], &target).unwrap(), object);
+        assert_eq!(compile_llvm(&[2, 3, 1, 0], &target).unwrap(), object);
+    }
+
+    #[test]
+    fn requires_explicit_target() {
+        explicit_target_errors(compile_llvm);
+    }
+}
+
+#[cfg(feature = "cranelift")]
+mod cranelift {
+    use super::*;
+    use crate::EvmCraneliftBackend;
+
+    fn compile_cranelift(order: &[usize], target: &Target) -> Result<Vec<u8>> {
+        let opt_level = OptimizationLevel::Aggressive;
+        compile(EvmCraneliftBackend::new_for_target(true, opt_level, target)?, order)
+    }
+
+    #[test]
+    fn explicit_target() {
+        let opt_level = OptimizationLevel::Aggressive;
+        let backend = EvmCraneliftBackend::new_for_target(true, opt_level, &target()).unwrap();
+        assert_eq!(*backend.target(), target());
+    }
+
+    #[test]
+    fn requires_explicit_target() {
+        explicit_target_errors(compile_cranelift);
+    }
+
+    #[test]
+    fn unsupported_target() {
+        let opt_level = OptimizationLevel::Aggressive;
+        for (target, expected) in [
+            (Target::new("not-a-triple", None, None), "invalid target triple"),
+            (Target::new("x86_64-unknown-linux-gnu", Some("z80".into()), None), "target CPU"),
+            (
+                Target::new("x86_64-unknown-linux-gnu", None, Some("+sse4.2,+z80".into())),
+                "target feature `+z80`",
+            ),
+        ] {
+            let err = EvmCraneliftBackend::new_for_target(true, opt_level, &target)
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(err.contains(expected), "{target:?}: {err}");
+        }
+
+        let err = EvmCraneliftBackend::new_for_target(false, opt_level, &target()).err().unwrap();
+        assert!(err.to_string().contains("only supported for the host machine"), "{err}");
+    }
+}
@@ -0,0 +1,33 @@
+use super::{TestHost, DEF_SPEC};
+use crate::{test_contract, Backend, EvmCompiler, EvmExecute, InterpreterExec};
+use revm_interpreter::{opcode as op, Interpreter};
+use revm_primitives::{Bytes, U256};
+use std::sync::Arc;
+
+matrix_tests!(same_outcome = |compiler| run(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    // sstore(0, keccak256(0, 32) + 1)
+    op::PUSH1, 1, op::PUSH1, 32, op::PUSH0, op::KECCAK256, op::ADD, op::PUSH0, op::SSTORE,
+    // mstore(0, sload(0)); return(0, 32)
+    op::PUSH0, op::SLOAD, op::PUSH0, op::MSTORE,
+    op::PUSH1, 32, op::PUSH0, op::RETURN,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let f = unsafe { compiler.jit("execute", TEST, DEF_SPEC) }.unwrap();
+    let executors: [Arc<dyn EvmExecute + Send + Sync>; 2] =
+        [Arc::new(f), Arc::new(InterpreterExec::new(DEF_SPEC))];
+
+    let outcomes = executors.map(|exec| {
+        let contract = test_contract(Bytes::from_static(TEST));
+        let mut interpreter = Interpreter::new(contract, 100_000, false);
+        let mut host = TestHost::new();
+        let action = unsafe { exec.execute(&mut interpreter, &mut host) };
+        let storage = host.host.storage.get(&U256::ZERO).copied();
+        (action, interpreter.instruction_result, storage)
+    });
+    assert!(outcomes[0].2.is_some());
+    assert_eq!(outcomes[0], outcomes[1]);
+}
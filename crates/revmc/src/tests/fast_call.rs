@@ -0,0 +1,63 @@
+use super::{TestHost, DEF_SPEC};
+use crate::{Backend, ContextConfig, EvmCompiler, StackPool};
+use revm_interpreter::{
+    analysis::to_analysed, opcode as op, InstructionResult, Interpreter, InterpreterAction,
+    SharedMemory,
+};
+use revm_primitives::{Address, Bytecode, Bytes, U256};
+
+matrix_tests!(run);
+
+const CALLEE: Address = Address::with_last_byte(0x69);
+
+#[rustfmt::skip]
+const CALLEE_CODE: &[u8] = &[
+    op::PUSH1, 0x2a, op::PUSH0, op::MSTORE,
+    op::PUSH1, 0x20, op::PUSH0, op::RETURN,
+];
+
+#[rustfmt::skip]
+const CALLER_CODE: &[u8] = &[
+    // Call `CALLEE`, with its output in `0..0x20`.
+    op::PUSH1, 0x20, op::PUSH0, op::PUSH0, op::PUSH0, op::PUSH0,
+    op::PUSH1, 0x69, op::GAS, op::CALL,
+    op::POP,
+    op::PUSH1, 0x20, op::PUSH0, op::RETURN,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.opcode_counts(true);
+    let caller_id = compiler.translate("caller", CALLER_CODE, DEF_SPEC).unwrap();
+    let callee_id = compiler.translate("callee", CALLEE_CODE, DEF_SPEC).unwrap();
+    let caller = unsafe { compiler.jit_function(caller_id) }.unwrap();
+    let callee = unsafe { compiler.jit_function(callee_id) }.unwrap();
+
+    let mut host = TestHost::new();
+    let bytecode = to_analysed(Bytecode::new_raw(Bytes::from_static(CALLEE_CODE)));
+    host.fast_calls.insert(CALLEE, (callee, bytecode));
+
+    let contract = crate::test_contract(Bytes::from_static(CALLER_CODE));
+    let mut interpreter = Interpreter::new(contract, 100_000, false);
+    let mut memory = SharedMemory::new();
+    let mut stacks = StackPool::new();
+    let mut counts = [0; 256];
+    let mut config = ContextConfig { opcode_counts: Some(&mut counts), ..Default::default() };
+    let action = unsafe {
+        caller.call_with_fast_calls(
+            &mut interpreter,
+            &mut memory,
+            &mut host,
+            &mut stacks,
+            &mut config,
+        )
+    };
+
+    let InterpreterAction::Return { result } = action else { panic!("{action:?}") };
+    assert_eq!(result.result, InstructionResult::Return);
+    assert_eq!(result.output[..], U256::from(0x2a).to_be_bytes::<32>());
+    // The callee was executed directly, with the opcode counts of the caller.
+    assert_eq!(stacks.len(), 1);
+    assert_eq!(counts[op::CALL as usize], 1);
+    assert_eq!(counts[op::MSTORE as usize], 1);
+    assert_eq!(counts[op::RETURN as usize], 2);
+}
@@ -0,0 +1,80 @@
+use super::{with_evm_context, TestHost, DEF_SPEC};
+use crate::{test_contract, Backend, EvmCompiler, FnMeta};
+use revm_interpreter::{opcode as op, InstructionResult, Interpreter};
+use revm_primitives::{Bytes, U256};
+
+matrix_tests!(default = |compiler| run(compiler, false, false));
+matrix_tests!(local_stack = |compiler| run(compiler, true, false));
+matrix_tests!(inspect_stack_length = |compiler| run(compiler, false, true));
+matrix_tests!(interpreter_stack = |compiler| interpreter_stack(compiler, false));
+matrix_tests!(interpreter_stack_inspected = |compiler| interpreter_stack(compiler, true));
+matrix_tests!(min_stack_len = |compiler| min_stack_len(compiler));
+
+const TEST: &[u8] = &[op::PUSH1, 0x69, op::POP, op::STOP];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, local_stack: bool, inspect_stack_length: bool) {
+    compiler.local_stack(local_stack);
+    compiler.inspect_stack_length(inspect_stack_length);
+    compiler.gas_metering(false);
+    let id = compiler.translate("fn_meta", TEST, DEF_SPEC).unwrap();
+    let f = unsafe { compiler.jit_function(id) }.unwrap();
+
+    let mut expected = FnMeta::default();
+    expected.local_stack = local_stack;
+    expected.inspect_stack_length = inspect_stack_length;
+    expected.writes_stack_length = inspect_stack_length;
+    expected.gas_metered = false;
+    assert_eq!(f.meta(), Some(expected));
+    assert_eq!(compiler.fn_info(id).unwrap().meta, expected);
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let stack = if local_stack { None } else { Some(stack) };
+        let stack_len = if inspect_stack_length { Some(stack_len) } else { None };
+        let r = unsafe { f.call(stack, stack_len, ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+    });
+}
+
+/// Reads the interpreter's stack after a call, like an inspector would.
+fn interpreter_stack<B: Backend>(compiler: &mut EvmCompiler<B>, inspect_stack_length: bool) {
+    const CODE: &[u8] = &[op::PUSH1, 1, op::PUSH1, 2, op::STOP];
+    compiler.inspect_stack_length(inspect_stack_length);
+    let f = unsafe { compiler.jit("interpreter_stack", CODE, DEF_SPEC) }.unwrap();
+    assert_eq!(f.meta().unwrap().writes_stack_length, inspect_stack_length);
+
+    let contract = test_contract(Bytes::from_static(CODE));
+    let mut interpreter = Interpreter::new(contract, 100_000, false);
+    // Left over from a previous call.
+    interpreter.stack.push(U256::from(0x69)).unwrap();
+    let mut host = TestHost::new();
+    let _ = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+    assert_eq!(interpreter.instruction_result, InstructionResult::Stop);
+
+    // The function starts with the interpreter's stack only if it inspects the stack length.
+    let expected: &[U256] =
+        if inspect_stack_length { &[U256::from(0x69), U256::from(1), U256::from(2)] } else { &[] };
+    assert_eq!(interpreter.stack.data(), expected);
+}
+
+/// Starting with too short a stack is rejected before calling the function, even without stack
+/// bound checks.
+fn min_stack_len<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    const CODE: &[u8] = &[op::ADD, op::PUSH0, op::STOP];
+    compiler.inspect_stack_length(true);
+    unsafe { compiler.stack_bound_checks(false) };
+    let f = unsafe { compiler.jit("min_stack_len", CODE, DEF_SPEC) }.unwrap();
+    assert_eq!(f.meta().unwrap().min_stack_len, 2);
+
+    with_evm_context(CODE, |ecx, stack, stack_len| {
+        *stack_len = 1;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::StackUnderflow);
+        assert_eq!(*stack_len, 1);
+
+        stack.as_mut_slice()[..2].copy_from_slice(&[U256::from(1).into(), U256::from(2).into()]);
+        *stack_len = 2;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        assert_eq!(stack.as_slice()[..*stack_len], [U256::from(3).into(), U256::ZERO.into()]);
+    });
+}
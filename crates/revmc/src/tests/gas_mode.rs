@@ -0,0 +1,130 @@
+use super::{with_evm_context, DEF_GAS_LIMIT, DEF_SPEC};
+use crate::{Backend, CallCheckError, EvmCompiler, FnSignature, GasMode};
+use revm_interpreter::{opcode as op, Gas, InstructionResult};
+use revm_primitives::U256;
+
+matrix_tests!(observe_only = |compiler| run(compiler));
+matrix_tests!(no_gas = |compiler| run_no_gas(compiler));
+matrix_tests!(disabled = |compiler| run_disabled(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    // Expand memory to 4097 words, which costs more than 21000 gas in the builtin.
+    op::PUSH1, 1, op::PUSH3, 0x02, 0x00, 0x00, op::MSTORE,
+    // Base costs paid by the compiled code after the limit has been exceeded.
+    op::PUSH1, 2, op::PUSH1, 3, op::ADD, op::POP,
+    // The remaining gas is not exhausted by the overrun.
+    op::GAS, op::PUSH0, op::SSTORE,
+    op::GAS, op::PUSH0, op::MSTORE,
+    op::STOP,
+];
+
+const GAS_LIMIT: u64 = 21_000;
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let metered_id = compiler.translate("metered", TEST, DEF_SPEC).unwrap();
+    compiler.gas_metering_mode(GasMode::ObserveOnly);
+    let observe_id = compiler.translate("observe_only", TEST, DEF_SPEC).unwrap();
+    let info = compiler.fn_info(observe_id).unwrap();
+    assert_eq!(info.gas_mode, GasMode::ObserveOnly);
+
+    let metered = unsafe { compiler.jit_function(metered_id) }.unwrap();
+    let observe = unsafe { compiler.jit_function(observe_id) }.unwrap();
+
+    let expected = with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { metered.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        ecx.gas.spent()
+    });
+    assert!(expected > GAS_LIMIT, "{expected}");
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        *ecx.gas = Gas::new(GAS_LIMIT);
+        let r = unsafe { metered.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::MemoryOOG);
+    });
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        *ecx.gas = Gas::new(GAS_LIMIT);
+        let r = unsafe { observe.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Err(CallCheckError::GasMode { expected: GasMode::ObserveOnly }));
+
+        ecx.gas_mode = GasMode::ObserveOnly;
+        let r = unsafe { observe.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Ok(InstructionResult::Stop));
+        assert_eq!(ecx.gas.spent(), expected);
+        let remaining = ecx.gas.remaining();
+        assert!(remaining > 2300, "{remaining}");
+        assert_eq!(ecx.gas.limit(), expected + remaining);
+        // `GAS` saw the gas remaining before `PUSH0` and `MSTORE`.
+        assert_eq!(ecx.memory_word(0).to_u256(), U256::from(remaining + 5));
+    });
+}
+
+/// Sums `1..=10` in a loop, and returns the sum and `GAS`.
+#[rustfmt::skip]
+const NO_GAS: &[u8] = &[
+    op::PUSH0, op::PUSH1, 10,
+    // 3
+    op::JUMPDEST, op::DUP1, op::SWAP2, op::ADD, op::SWAP1, op::PUSH1, 1, op::SWAP1, op::SUB,
+    op::DUP1, op::PUSH1, 3, op::JUMPI,
+    op::POP, op::GAS, op::PUSH0, op::MSTORE, op::PUSH1, 0x20, op::MSTORE,
+    op::PUSH1, 0x40, op::PUSH0, op::RETURN,
+];
+
+fn run_no_gas<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.inspect_stack_length(true);
+    compiler.gas_metering(false);
+    let default_id = compiler.translate("default", NO_GAS, DEF_SPEC).unwrap();
+    compiler.no_gas_signature(Some(DEF_GAS_LIMIT));
+    let no_gas_id = compiler.translate("no_gas", NO_GAS, DEF_SPEC).unwrap();
+    let info = compiler.fn_info(no_gas_id).unwrap();
+    assert_eq!(info.signature, FnSignature::NoGas);
+    assert!(unsafe { compiler.jit_function(no_gas_id) }.is_err());
+
+    let default = unsafe { compiler.jit_function(default_id) }.unwrap();
+    let no_gas = unsafe { compiler.jit_function_no_gas(no_gas_id) }.unwrap();
+
+    let expected = with_evm_context(NO_GAS, |ecx, stack, stack_len| {
+        let r = unsafe { default.call(Some(stack), Some(stack_len), ecx) };
+        (r, *stack_len, ecx.memory.context_memory().to_vec(), *ecx.gas)
+    });
+    assert_eq!(expected.0, InstructionResult::Return);
+    assert_eq!(expected.2[0x3f], 55);
+
+    with_evm_context(NO_GAS, |ecx, stack, stack_len| {
+        let default_info = compiler.fn_info(default_id).unwrap();
+        let r = unsafe { no_gas.call_checked(&default_info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Err(CallCheckError::Signature { expected: FnSignature::Default }));
+
+        let r = unsafe { no_gas.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        let actual = (r.unwrap(), *stack_len, ecx.memory.context_memory().to_vec(), *ecx.gas);
+        assert_eq!(actual, expected);
+    });
+}
+
+fn run_disabled<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.gas_metering(false);
+    let id = compiler.translate("disabled", TEST, DEF_SPEC).unwrap();
+    let info = compiler.fn_info(id).unwrap();
+    assert_eq!(info.gas_mode, GasMode::Disabled);
+    assert!(!info.meta.gas_metered);
+    let f = unsafe { compiler.jit_function(id) }.unwrap();
+
+    // Builtins still pay for memory expansion if the context is metered.
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        *ecx.gas = Gas::new(GAS_LIMIT);
+        let r = unsafe { f.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Ok(InstructionResult::MemoryOOG));
+    });
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        *ecx.gas = Gas::new(0);
+        ecx.disable_gas();
+        let r = unsafe { f.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Ok(InstructionResult::Stop));
+        assert_eq!(ecx.memory.len(), 0x20020);
+        assert_eq!(ecx.gas.spent(), 0);
+        assert_eq!(ecx.gas.remaining(), u64::MAX);
+    });
+}
@@ -0,0 +1,65 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, CallCheckError, EvmCompiler, GasParams, GasTable};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(default = |compiler| run_default(compiler));
+matrix_tests!(custom = |compiler| run_custom(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    // 1 memory word.
+    op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::PUSH0, op::MSTORE,
+    // 2 hashed words, 3 memory words.
+    op::PUSH1, 64, op::PUSH1, 32, op::KECCAK256,
+    op::STOP,
+];
+
+fn run_default<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let implicit = compiler.translate("implicit", TEST, DEF_SPEC).unwrap();
+    compiler.gas_table(Some(GasTable::for_spec(DEF_SPEC)));
+    let explicit = compiler.translate("explicit", TEST, DEF_SPEC).unwrap();
+    assert_eq!(compiler.fn_info(implicit), compiler.fn_info(explicit));
+
+    let implicit = unsafe { compiler.jit_function(implicit) }.unwrap();
+    let explicit = unsafe { compiler.jit_function(explicit) }.unwrap();
+    let [implicit_gas, explicit_gas] = [implicit, explicit].map(|f| {
+        with_evm_context(TEST, |ecx, stack, stack_len| {
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            assert_eq!(r, InstructionResult::Stop);
+            ecx.gas.spent()
+        })
+    });
+    assert_eq!(implicit_gas, explicit_gas);
+}
+
+fn run_custom<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let default_id = compiler.translate("default", TEST, DEF_SPEC).unwrap();
+    let mut table = GasTable::for_spec(DEF_SPEC);
+    table.base[op::ADD as usize] = 7;
+    table.params.memory_word = 5;
+    table.params.keccak256_word = 10;
+    compiler.gas_table(Some(table));
+    let custom_id = compiler.translate("custom", TEST, DEF_SPEC).unwrap();
+    let info = compiler.fn_info(custom_id).unwrap();
+    assert_eq!(info.gas_params, table.params);
+
+    let default = unsafe { compiler.jit_function(default_id) }.unwrap();
+    let custom = unsafe { compiler.jit_function(custom_id) }.unwrap();
+    let default_gas = with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { default.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+        ecx.gas.spent()
+    });
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { custom.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Err(CallCheckError::GasParams));
+
+        ecx.gas_params = Some(Box::leak(Box::new(table.params)));
+        let r = unsafe { custom.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, Ok(InstructionResult::Stop));
+        let extra_add = 7 - 3;
+        let extra_memory = 3 * (5 - GasParams::DEFAULT.memory_word);
+        let extra_keccak256 = 2 * (10 - GasParams::DEFAULT.keccak256_word);
+        assert_eq!(ecx.gas.spent(), default_gas + extra_add + extra_memory + extra_keccak256);
+    });
+}
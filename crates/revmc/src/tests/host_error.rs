@@ -0,0 +1,121 @@
+use super::{test_interpreter, TestHost, DEF_SPEC};
+use crate::{Backend, EvmCompiler, EvmContext, EvmWord, HostRequest, HostResponse, OverrideHost};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(third_sload = |compiler| run(compiler, false));
+matrix_tests!(panicking_sload = |compiler| run(compiler, true));
+
+#[test]
+fn panicking_sload_builtin() {
+    let mut interpreter = test_interpreter(TEST);
+    let mut host = failing_host(2, true);
+    let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+    let mut index = EvmWord::ZERO;
+    let r = unsafe { revmc_builtins::__revmc_builtin_sload(&mut ecx, &mut index, DEF_SPEC) };
+    assert_eq!(r, InstructionResult::FatalExternalError);
+    assert_host_panic(&mut ecx, "sload");
+}
+
+#[test]
+fn panicking_builtin() {
+    use crate::{HostCallLog, HostCallRecord};
+
+    struct PanickingLog;
+    impl HostCallLog for PanickingLog {
+        fn record(&mut self, _record: HostCallRecord) {
+            panic!("poisoned");
+        }
+    }
+
+    let mut log = PanickingLog;
+    let mut interpreter = test_interpreter(TEST);
+    let mut host = TestHost::new();
+    let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+    ecx.host_call_log = Some(&mut log);
+    let mut index = EvmWord::ZERO;
+    let r = unsafe { revmc_builtins::__revmc_builtin_sload(&mut ecx, &mut index, DEF_SPEC) };
+    assert_eq!(r, InstructionResult::FatalExternalError);
+    assert_host_panic(&mut ecx, "sload");
+}
+
+#[test]
+fn failed_calls_are_logged() {
+    use crate::{HostCallKind, HostCallRing};
+    use revm_interpreter::Gas;
+
+    let mut ring = HostCallRing::<4>::new();
+    let mut interpreter = test_interpreter(TEST);
+    let mut host = failing_host(1, false);
+    let mut ecx = EvmContext::from_interpreter(&mut interpreter, &mut host);
+    ecx.host_call_log = Some(&mut ring);
+    let mut index = EvmWord::ZERO;
+
+    *ecx.gas = Gas::new(0);
+    let r = unsafe { revmc_builtins::__revmc_builtin_sload(&mut ecx, &mut index, DEF_SPEC) };
+    assert_eq!(r, InstructionResult::OutOfGas);
+
+    *ecx.gas = Gas::new(100_000);
+    let r = unsafe { revmc_builtins::__revmc_builtin_sload(&mut ecx, &mut index, DEF_SPEC) };
+    assert_eq!(r, InstructionResult::FatalExternalError);
+
+    let records = ring.iter().map(|r| (r.kind, r.gas, r.result)).collect::<Vec<_>>();
+    assert_eq!(
+        records,
+        [
+            (HostCallKind::Sload, 100, InstructionResult::OutOfGas),
+            (HostCallKind::Sload, 0, InstructionResult::FatalExternalError),
+        ]
+    );
+}
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH0, op::SLOAD, op::POP,
+    op::PUSH0, op::SLOAD, op::POP,
+    op::PUSH0, op::SLOAD, op::POP,
+    op::STOP,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, panics: bool) {
+    let f = unsafe { compiler.jit("host_error", TEST, DEF_SPEC) }.unwrap();
+
+    let mut interpreter = test_interpreter(TEST);
+    let mut host = failing_host(0, panics);
+    let (mut ecx, stack, stack_len) =
+        EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+    let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+    assert_eq!(r, InstructionResult::FatalExternalError);
+    if panics {
+        assert_host_panic(&mut ecx, "sload");
+    } else {
+        let error = ecx.take_host_error().expect("no host error");
+        assert_eq!(error.to_string(), "missing trie node");
+    }
+}
+
+fn assert_host_panic(ecx: &mut EvmContext<'_>, call: &str) {
+    let error = ecx.take_host_error().expect("no host error");
+    let panic = error.downcast::<crate::HostPanic>().expect("not a host panic");
+    assert_eq!(panic.call(), call);
+    assert_eq!(panic.message(), "poisoned");
+    assert_eq!(panic.into_payload().downcast_ref::<&str>(), Some(&"poisoned"));
+}
+
+/// Returns a host that fails the third `SLOAD`, counting from `sloads` already done, either by
+/// reporting an error or by panicking.
+fn failing_host(mut sloads: usize, panics: bool) -> OverrideHost<TestHost> {
+    OverrideHost::new(TestHost::new(), move |request| {
+        if !matches!(request, HostRequest::Sload { .. }) {
+            return None;
+        }
+        sloads += 1;
+        if sloads != 3 {
+            return None;
+        }
+        if panics {
+            panic!("poisoned");
+        }
+        crate::report_host_error("missing trie node");
+        Some(HostResponse::Sload(None))
+    })
+}
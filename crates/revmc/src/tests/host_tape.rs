@@ -0,0 +1,72 @@
+use super::{test_interpreter, with_evm_context, TestHost, DEF_ADDR, DEF_SPEC};
+use crate::{
+    Backend, EvmCompiler, EvmCompilerFn, EvmContext, EvmWord, HostCallKind, HostCallRing, HostExt,
+    HostTape, RecordingHost, ReplayHost,
+};
+use revm_interpreter::{opcode as op, InstructionResult};
+use revm_primitives::U256;
+
+matrix_tests!(record_replay = |compiler| run(compiler));
+matrix_tests!(ring = |compiler| run_ring(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 0x69, op::PUSH1, 0x01, op::SSTORE,
+    op::PUSH1, 0x01, op::SLOAD,
+    op::PUSH1, 0x02, op::SLOAD,
+    op::PUSH1, 0x42, op::PUSH1, 0x03, op::TSTORE,
+    op::PUSH1, 0x03, op::TLOAD,
+    op::ADDRESS, op::BALANCE,
+    op::PUSH0, op::PUSH0, op::LOG0,
+    op::STOP,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let f = unsafe { compiler.jit("host_tape", TEST, DEF_SPEC) }.unwrap();
+
+    let mut tape = HostTape::default();
+    let recorded = execute(f, RecordingHost::new(TestHost::new()), |host| {
+        assert_eq!(host.inner().storage.get(&U256::from(1)), Some(&U256::from(0x69)));
+        assert_eq!(host.inner().log.len(), 1);
+        assert_eq!(host.inner().log[0].address, DEF_ADDR);
+        tape = host.tape().clone();
+    });
+    assert_eq!(recorded.0, InstructionResult::Stop);
+    assert_eq!(tape.calls.len(), 7);
+
+    let replayed = execute(f, ReplayHost::new(tape), |host| assert!(host.is_finished()));
+    assert_eq!(replayed, recorded);
+}
+
+fn run_ring<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let f = unsafe { compiler.jit("host_call_ring", TEST, DEF_SPEC) }.unwrap();
+
+    let mut ring = HostCallRing::<4>::new();
+    let mut interpreter = test_interpreter(TEST);
+    let mut host = TestHost::new();
+    let (mut ecx, stack, stack_len) =
+        EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+    ecx.host_call_log = Some(&mut ring);
+    let r = unsafe { f.call(Some(stack), Some(stack_len), &mut ecx) };
+    assert_eq!(r, InstructionResult::Stop);
+
+    assert_eq!(ring.total(), 7);
+    let kinds = ring.iter().map(|r| r.kind).collect::<Vec<_>>();
+    use HostCallKind::*;
+    assert_eq!(kinds, [Tstore, Tload, Balance, Log]);
+    assert_eq!(ring.iter().map(|r| r.gas).take(2).collect::<Vec<_>>(), [0, 0]);
+}
+
+fn execute<H: HostExt + 'static>(
+    f: EvmCompilerFn,
+    host: H,
+    _check_host: impl FnOnce(&H),
+) -> (InstructionResult, Vec<EvmWord>, u64) {
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        ecx.host = Box::leak(Box::new(host));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        #[cfg(not(feature = "__fuzzing"))]
+        _check_host(ecx.host.downcast_ref().unwrap());
+        (r, stack.as_slice()[..*stack_len].to_vec(), ecx.gas.remaining())
+    })
+}
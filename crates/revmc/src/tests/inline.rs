@@ -0,0 +1,93 @@
+use super::{run_test_case, TestCase, DEF_SPEC};
+use crate::{Backend, EvmCompiler, EvmContext};
+use revm_primitives::{hex, Bytes};
+
+matrix_tests!(weth = |compiler| run(compiler, WETH, &WETH_CALLS));
+matrix_tests!(fiat_token = |compiler| run(compiler, FIAT_TOKEN, &FIAT_TOKEN_CALLS));
+matrix_tests!(erc20 = |compiler| run(compiler, ERC20, &ERC20_CALLS));
+
+const WETH: &str = include_str!("../../../../data/weth.rt.hex");
+const FIAT_TOKEN: &str = include_str!("../../../../data/fiat_token.rt.hex");
+const ERC20: &str = include_str!("../../../../data/erc20_transfer.rt.hex");
+
+/// The default inlining threshold of the compiler.
+const THRESHOLD: usize = 32;
+
+const WETH_CALLS: [fn(&mut EvmContext<'_>); 4] = [
+    // `deposit()`
+    |ecx| set_calldata(ecx, &hex!("d0e30db0")),
+    // `balanceOf(0xcaca..)`
+    |ecx| {
+        set_calldata(
+            ecx,
+            &hex!("70a08231000000000000000000000000cacacacacacacacacacacacacacacacacacacaca"),
+        )
+    },
+    // `transfer(0x6969.., 0)`
+    |ecx| {
+        set_calldata(
+            ecx,
+            &hex!(
+                "a9059cbb0000000000000000000000006969696969696969696969696969696969696969"
+                "0000000000000000000000000000000000000000000000000000000000000000"
+            ),
+        )
+    },
+    // `name()`
+    |ecx| set_calldata(ecx, &hex!("06fdde03")),
+];
+
+const FIAT_TOKEN_CALLS: [fn(&mut EvmContext<'_>); 3] = [
+    // `name()`
+    |ecx| set_calldata(ecx, &hex!("06fdde03")),
+    // `totalSupply()`
+    |ecx| set_calldata(ecx, &hex!("18160ddd")),
+    // `balanceOf(0xcaca..)`
+    |ecx| {
+        set_calldata(
+            ecx,
+            &hex!("70a08231000000000000000000000000cacacacacacacacacacacacacacacacacacacaca"),
+        )
+    },
+];
+
+const ERC20_CALLS: [fn(&mut EvmContext<'_>); 1] = [
+    // `Benchmark()`, a loop of transfers.
+    |ecx| set_calldata(ecx, &hex!("30627b7c")),
+];
+
+fn set_calldata(ecx: &mut EvmContext<'_>, data: &'static [u8]) {
+    ecx.contract.input = Bytes::from_static(data);
+}
+
+#[test]
+fn inlines() {
+    let inlined = |code: &str, threshold| {
+        use crate::bytecode::{AnalysisConfig, Bytecode};
+
+        let code = hex::decode(code.trim()).unwrap();
+        let count = |inline_threshold| {
+            let mut bytecode = Bytecode::new(&code, None, DEF_SPEC);
+            bytecode.analyze(AnalysisConfig { inline_threshold, ..Default::default() }).unwrap();
+            bytecode.iter_all_insts().count()
+        };
+        count(threshold) - count(0)
+    };
+    assert!(inlined(WETH, THRESHOLD) > 0);
+    assert!(inlined(FIAT_TOKEN, THRESHOLD) > 0);
+    assert!(inlined(ERC20, 2 * THRESHOLD) > 0);
+}
+
+/// Runs the calls with and without inlining, checking that both match the interpreter.
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, code: &str, calls: &[fn(&mut EvmContext<'_>)]) {
+    let code = hex::decode(code.trim()).unwrap();
+    for threshold in [0, THRESHOLD, 2 * THRESHOLD] {
+        compiler.inline_threshold(threshold);
+        for &call in calls {
+            let mut test_case = TestCase::what_interpreter_says(&code, DEF_SPEC);
+            test_case.modify_ecx = Some(call);
+            run_test_case(&test_case, compiler);
+            unsafe { compiler.clear() }.unwrap();
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::{run_test_case, TestCase, TestHost, DEF_ADDR, DEF_CALLER, DEF_SPEC};
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::opcode as op;
+use revm_primitives::{keccak256, Address, U256};
+
+matrix_tests!(transfer = |compiler| run(compiler, &transfer(&VIA_IR, false)));
+matrix_tests!(transfer_scratch_read = |compiler| run(compiler, &transfer(&VIA_IR, true)));
+matrix_tests!(transfer_legacy = |compiler| run(compiler, &transfer(&LEGACY, false)));
+matrix_tests!(transfer_legacy_scratch_read = |compiler| run(compiler, &transfer(&LEGACY, true)));
+
+/// `keccak256(key ++ 0)`, as emitted by the Solidity IR pipeline.
+#[rustfmt::skip]
+const VIA_IR: [u8; 11] = [
+    op::PUSH0, op::MSTORE, op::PUSH1, 0, op::PUSH1, 0x20, op::MSTORE,
+    op::PUSH1, 0x40, op::PUSH0, op::KECCAK256,
+];
+
+/// `keccak256(key ++ 0)`, as emitted by the optimized Solidity legacy pipeline.
+#[rustfmt::skip]
+const LEGACY: [u8; 13] = [
+    op::PUSH0, op::SWAP1, op::DUP2, op::MSTORE, op::PUSH1, 0x20, op::DUP2, op::SWAP1, op::MSTORE,
+    op::PUSH1, 0x40, op::SWAP1, op::KECCAK256,
+];
+
+#[test]
+fn rewrites() {
+    use crate::bytecode::{AnalysisConfig, Bytecode, InstFlags};
+
+    let map_slots = |code: &[u8]| {
+        let mut bytecode = Bytecode::new(code, None, DEF_SPEC);
+        bytecode.analyze(AnalysisConfig { map_slot: true, ..Default::default() }).unwrap();
+        let flagged = |opcode| {
+            bytecode
+                .iter_insts()
+                .filter(|(_, data)| {
+                    data.opcode == opcode && data.flags.contains(InstFlags::MAP_SLOT)
+                })
+                .count()
+        };
+        assert_eq!(flagged(op::MSTORE), 2 * flagged(op::KECCAK256));
+        flagged(op::KECCAK256)
+    };
+    for map_slot in [&VIA_IR[..], &LEGACY[..]] {
+        assert_eq!(map_slots(&transfer(map_slot, false)), 2);
+        assert_eq!(map_slots(&transfer(map_slot, true)), 1);
+    }
+}
+
+/// `balances[caller] -= 1; balances[address] += 1; emit Transfer(caller, address, 1)`, where
+/// `balances` is a mapping at slot 0.
+///
+/// Both slot computations are rewritten, as the `LOG` only reads the event data past the scratch
+/// space, unless `read_scratch` reads the scratch space before the second one overwrites it.
+#[rustfmt::skip]
+fn transfer(map_slot: &[u8], read_scratch: bool) -> Vec<u8> {
+    let mut code = vec![op::CALLER];
+    code.extend(map_slot);
+    code.extend([op::DUP1, op::SLOAD, op::PUSH1, 1, op::SWAP1, op::SUB, op::SWAP1, op::SSTORE]);
+    if read_scratch {
+        code.extend([op::PUSH0, op::MLOAD, op::PUSH1, 0x80, op::MSTORE]);
+    }
+    code.push(op::ADDRESS);
+    code.extend(map_slot);
+    code.extend([op::DUP1, op::SLOAD, op::PUSH1, 1, op::ADD, op::SWAP1, op::SSTORE]);
+    code.extend([op::PUSH1, 1, op::PUSH1, 0xa0, op::MSTORE]);
+    code.extend([op::ADDRESS, op::CALLER, op::PUSH1, 0xdd, op::PUSH1, 0x20, op::PUSH1, 0xa0, op::LOG3]);
+    code.push(op::STOP);
+    code
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, code: &[u8]) {
+    let mut test_case = TestCase::what_interpreter_says(code, DEF_SPEC);
+    test_case.assert_host = Some(assert_balances);
+    run_test_case(&test_case, compiler);
+}
+
+fn assert_balances(host: &TestHost) {
+    let balance = |account: Address| {
+        let mut key = [0; 64];
+        key[12..32].copy_from_slice(account.as_slice());
+        let slot = U256::from_be_bytes(keccak256(key).0);
+        host.host.storage.get(&slot).copied().unwrap_or_default()
+    };
+    assert_eq!(balance(DEF_CALLER), U256::MAX);
+    assert_eq!(balance(DEF_ADDR), U256::from(1));
+    assert_eq!(host.host.log.len(), 1);
+}
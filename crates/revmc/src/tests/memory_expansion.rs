@@ -0,0 +1,83 @@
+use super::{run_test_case, TestCase, DEF_GAS_LIMIT, DEF_SPEC, OTHER_ADDR};
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::{gas, opcode as op};
+use revm_primitives::U256;
+
+matrix_tests!(differential = |compiler| run(compiler));
+
+/// The number of memory words that `MSTORE` can expand to with [`DEF_GAS_LIMIT`].
+fn max_mstore_words() -> u64 {
+    // 2 pushes and `MSTORE`.
+    let budget = DEF_GAS_LIMIT - 3 * 3;
+    let mut words = 0;
+    while gas::memory_gas(words + 1) <= budget {
+        words += 1;
+    }
+    words
+}
+
+/// Offsets and lengths around the overflow and out-of-gas boundaries.
+fn values() -> Vec<U256> {
+    let boundary = max_mstore_words() * 32;
+    [
+        0,
+        1,
+        boundary - 32,
+        boundary - 31,
+        boundary,
+        boundary + 1,
+        u32::MAX as u64 - 31,
+        u32::MAX as u64,
+        u32::MAX as u64 + 1,
+        u64::MAX - 32,
+        u64::MAX - 31,
+        u64::MAX,
+    ]
+    .into_iter()
+    .map(U256::from)
+    .chain([U256::from(u64::MAX) + U256::from(1), U256::MAX])
+    .collect()
+}
+
+/// Returns the bytecode that pushes `args`, with the first one on top of the stack, and then
+/// executes `opcode`.
+fn code(opcode: u8, args: &[U256]) -> Vec<u8> {
+    let mut code = Vec::with_capacity(args.len() * 33 + 1);
+    for arg in args.iter().rev() {
+        code.push(op::PUSH32);
+        code.extend_from_slice(&arg.to_be_bytes::<32>());
+    }
+    code.push(opcode);
+    code
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let zero = U256::ZERO;
+    let addr = OTHER_ADDR.into_word().into();
+    let mut codes = Vec::new();
+    for &x in &values() {
+        codes.push(code(op::MLOAD, &[x]));
+        codes.push(code(op::MSTORE, &[x, zero]));
+        codes.push(code(op::MSTORE8, &[x, zero]));
+        for (offset, len) in [(x, U256::from(32)), (zero, x), (x, x), (x, zero)] {
+            codes.push(code(op::CALLDATACOPY, &[offset, zero, len]));
+            codes.push(code(op::CODECOPY, &[offset, zero, len]));
+            codes.push(code(op::EXTCODECOPY, &[addr, offset, zero, len]));
+            codes.push(code(op::RETURNDATACOPY, &[offset, zero, len]));
+            codes.push(code(op::MCOPY, &[offset, zero, len]));
+            codes.push(code(op::MCOPY, &[zero, offset, len]));
+            codes.push(code(op::KECCAK256, &[offset, len]));
+            codes.push(code(op::LOG0, &[offset, len]));
+            codes.push(code(op::RETURN, &[offset, len]));
+            codes.push(code(op::REVERT, &[offset, len]));
+            codes.push(code(op::CREATE, &[zero, offset, len]));
+            codes.push(code(op::CALL, &[zero, addr, zero, offset, len, zero, zero]));
+            codes.push(code(op::CALL, &[zero, addr, zero, zero, zero, offset, len]));
+        }
+    }
+
+    for code in &codes {
+        unsafe { compiler.clear() }.unwrap();
+        run_test_case(&TestCase::what_interpreter_says(code, DEF_SPEC), compiler);
+    }
+}
@@ -0,0 +1,75 @@
+//! Checks that `MSTORE`d values are forwarded to the `MLOAD`s that follow them.
+
+use super::{run_test_case, TestCase, DEF_SPEC};
+use crate::{llvm::with_llvm_context, Backend, EvmCompiler, EvmLlvmBackend, OptimizationLevel};
+use revm_interpreter::opcode as op;
+
+matrix_tests!(enabled = |compiler| run(compiler, true));
+matrix_tests!(disabled = |compiler| run(compiler, false));
+
+/// Returns the number of calls to the `MLOAD` builtin in the unoptimized IR of `code`.
+fn count_mloads(code: &[u8], forwarding: bool) -> usize {
+    let tmp = tempfile::tempdir().expect("could not create temp dir");
+    let ir = with_llvm_context(|cx| {
+        let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::None).unwrap();
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.opt_memory_forwarding(forwarding);
+        compiler.set_dump_to(Some(tmp.path().to_path_buf()));
+        let _ = unsafe { compiler.jit("memory_forwarding", code, DEF_SPEC) }.unwrap();
+        std::fs::read_to_string(tmp.path().join("unopt.ll")).unwrap()
+    });
+    ir.lines().filter(|line| line.contains(" call ") && line.contains("mload")).count()
+}
+
+/// Encodes `caller` after the free memory pointer like the ABI encoder does, then hashes it.
+#[rustfmt::skip]
+const ENCODE_AND_HASH: &[u8] = &[
+    // Initialize the free memory pointer.
+    op::PUSH1, 0x80, op::PUSH1, 0x40, op::MSTORE,
+    // `mstore(0x80, caller())`.
+    op::CALLER, op::PUSH1, 0x80, op::MSTORE,
+    // Bump the free memory pointer.
+    op::PUSH1, 0x40, op::MLOAD, op::PUSH1, 0x20, op::ADD, op::PUSH1, 0x40, op::MSTORE,
+    // Read the encoded value back.
+    op::PUSH1, 0x80, op::MLOAD,
+    // A partial overwrite of the encoded value.
+    op::PUSH1, 0xff, op::PUSH1, 0x9f, op::MSTORE8,
+    op::PUSH1, 0x80, op::MLOAD,
+    // `keccak256(0x80, 0x20)`.
+    op::PUSH1, 0x20, op::PUSH1, 0x80, op::KECCAK256,
+    // Hashing clears the known values.
+    op::PUSH1, 0x40, op::MLOAD,
+    op::STOP,
+];
+
+/// Stores with unknown offsets and stores across jump destinations.
+#[rustfmt::skip]
+const UNKNOWN_OFFSETS: &[u8] = &[
+    op::PUSH1, 0x11, op::PUSH0, op::MSTORE,
+    // `mstore(calldataload(0) & 0x3f, 0x22)`.
+    op::PUSH1, 0x22, op::PUSH0, op::CALLDATALOAD, op::PUSH1, 0x3f, op::AND, op::MSTORE,
+    op::PUSH0, op::MLOAD,
+    op::PUSH1, 0x33, op::PUSH1, 0x20, op::MSTORE,
+    op::PUSH1, 22, op::JUMP,
+    op::JUMPDEST,
+    op::PUSH1, 0x20, op::MLOAD,
+    op::STOP,
+];
+
+#[test]
+fn forwarded_loads() {
+    assert_eq!(count_mloads(ENCODE_AND_HASH, false), 4);
+    // Only the loads after `MSTORE8` and `KECCAK256` remain.
+    assert_eq!(count_mloads(ENCODE_AND_HASH, true), 2);
+
+    assert_eq!(count_mloads(UNKNOWN_OFFSETS, false), 2);
+    assert_eq!(count_mloads(UNKNOWN_OFFSETS, true), 2);
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, forwarding: bool) {
+    compiler.opt_memory_forwarding(forwarding);
+    for code in [ENCODE_AND_HASH, UNKNOWN_OFFSETS] {
+        unsafe { compiler.clear() }.unwrap();
+        run_test_case(&TestCase::what_interpreter_says(code, DEF_SPEC), compiler);
+    }
+}
@@ -20,8 +20,55 @@ mod macros;
 
 mod meta;
 
+#[cfg(feature = "llvm")]
+mod assertions;
+mod block_profile;
+mod blockhash;
+mod call_gas;
+mod codegen_tier;
+mod compile_error;
+mod context_env;
+#[cfg(feature = "llvm")]
+mod cross_target;
+mod custom_opcode;
+mod dead_code;
+#[cfg(feature = "llvm")]
+mod dead_values;
+mod deterministic;
+mod execute;
+mod fast_call;
 mod fibonacci;
+mod fn_meta;
+mod gas_mode;
+mod gas_table;
+mod host_error;
+mod host_tape;
+mod inline;
+mod map_slot;
+mod memory_expansion;
+#[cfg(feature = "llvm")]
+mod memory_forwarding;
+mod opcode_counts;
+mod result_sink;
 mod resume;
+mod selector;
+mod signed_ops;
+// Needs `tracing-subscriber`, which is only a dev-dependency.
+#[cfg(all(test, feature = "tracing"))]
+mod spans;
+#[cfg(feature = "llvm")]
+mod stack_shuffle;
+#[cfg(feature = "statetests")]
+mod statetest;
+mod static_context;
+mod step_budget;
+// Needs `serde_json` and the `serde` feature of `revmc-context`, which are only dev-dependencies.
+#[cfg(test)]
+mod suspended_frame;
+#[cfg(feature = "llvm")]
+mod tiered;
+#[cfg(feature = "llvm")]
+mod wasm;
 
 mod runner;
 pub use runner::*;
@@ -151,6 +198,31 @@ tests! {
             bytecode: &[op::PUSH1, 1, op::POP],
             expected_gas: 3 + 2,
         }),
+        pop_dead_value(@raw {
+            bytecode: &[op::PUSH1, 1, op::PUSH1, 2, op::ADD, op::POP, op::STOP],
+            expected_gas: 3 + 3 + 3 + 2,
+        }),
+        pop_dead_dup(@raw {
+            bytecode: &[op::PUSH1, 1, op::DUP1, op::POP],
+            expected_stack: &[1_U256],
+            expected_gas: 3 + 3 + 2,
+        }),
+        pop_live_input(@raw {
+            bytecode: &[op::PUSH1, 1, op::JUMPDEST, op::PUSH1, 2, op::ADD, op::POP],
+            expected_gas: 3 + 1 + 3 + 3 + 2,
+        }),
+        pop_dead_value_underflow(@raw {
+            bytecode: &[op::PUSH1, 1, op::ADD, op::POP],
+            expected_return: InstructionResult::StackUnderflow,
+            expected_stack: &[1_U256],
+            expected_gas: 3 + 3,
+        }),
+        pop_dead_value_overflow(@raw {
+            bytecode: &[[op::PUSH0; 1023].as_slice(), &[op::PUSH0, op::PUSH0, op::ADD, op::POP]].concat(),
+            expected_return: InstructionResult::StackOverflow,
+            expected_stack: &[0_U256; 1024],
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+        }),
         dup(@raw {
             bytecode: &[op::PUSH1, 1, op::DUP1],
             expected_stack: &[1_U256, 1_U256],
@@ -177,6 +249,21 @@ tests! {
             expected_stack: &[4_U256, 2_U256, 3_U256, 1_U256],
             expected_gas: 3 + 3 + 3 + 3 + 3,
         }),
+        dup_swap_dup(@raw {
+            bytecode: &[op::PUSH1, 1, op::PUSH1, 2, op::PUSH1, 3, op::DUP1, op::SWAP2, op::DUP3],
+            expected_stack: &[1_U256, 3_U256, 3_U256, 2_U256, 3_U256],
+            expected_gas: 3 + 3 + 3 + 3 + 3 + 3,
+        }),
+        swap_dup_across_jumpdest(@raw {
+            bytecode: &[op::PUSH1, 1, op::PUSH1, 2, op::JUMPDEST, op::SWAP1, op::DUP2],
+            expected_stack: &[2_U256, 1_U256, 2_U256],
+            expected_gas: 3 + 3 + 1 + 3 + 3,
+        }),
+        dup_after_builtin(@raw {
+            bytecode: &[op::PUSH1, 2, op::PUSH1, 3, op::EXP, op::DUP1, op::SWAP1],
+            expected_stack: &[9_U256, 9_U256],
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+        }),
         swapn(@raw {
             bytecode: &eof(&[op::PUSH1, 1, op::PUSH1, 2, op::SWAPN, 0, op::STOP]),
             spec_id: SpecId::OSAKA,
@@ -363,6 +450,32 @@ tests! {
             expected_stack: &[1_U256, 2_U256],
             expected_gas: GAS_WHAT_INTERPRETER_SAYS,
         }),
+        internal_functions(@raw {
+            bytecode: &[
+                // add(2, 3)
+                op::PUSH1, 9, op::PUSH1, 2, op::PUSH1, 3, op::PUSH1, 20, op::JUMP,
+                // double_add(r, 5)
+                op::JUMPDEST, op::PUSH1, 18, op::DUP2, op::PUSH1, 5, op::PUSH1, 24, op::JUMP,
+                op::JUMPDEST, op::STOP,
+                // add(a, b)
+                op::JUMPDEST, op::ADD, op::SWAP1, op::JUMP,
+                // double_add(a, b) = add(a, b) * 2
+                op::JUMPDEST, op::PUSH1, 31, op::SWAP2, op::PUSH1, 20, op::JUMP,
+                op::JUMPDEST, op::PUSH1, 2, op::MUL, op::SWAP1, op::JUMP,
+            ],
+            expected_stack: &[5_U256, 20_U256],
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+        }),
+        internal_function_dup_return(@raw {
+            bytecode: &[
+                op::PUSH1, 7, op::PUSH1, 1, op::PUSH1, 10, op::JUMP,
+                op::JUMPDEST, op::STOP, op::INVALID,
+                // Returns with a duplicated return address, not inlined.
+                op::JUMPDEST, op::POP, op::DUP1, op::SWAP1, op::JUMP,
+            ],
+            expected_stack: &[7_U256],
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+        }),
     }
 
     arith {
@@ -618,6 +731,30 @@ tests! {
             expected_memory: &hex!("60055f5f39000000000000000000000000000000000000000000000000000000"),
             expected_gas: 3 + 2 + 2 + (gas::verylowcopy_cost(32).unwrap() + gas::memory_gas(1)),
         }),
+        // The analyzed bytecode is padded to complete the last immediate and to end in `STOP`,
+        // which must not be visible.
+        codesize_padded(@raw {
+            bytecode: &[op::CODESIZE, op::PUSH2, 0x01],
+            expected_stack: &[3_U256, 0x0100_U256],
+            expected_gas: 2 + 3,
+        }),
+        codecopy_padded(@raw {
+            bytecode: &[op::PUSH1, 64, op::PUSH0, op::PUSH0, op::CODECOPY, op::PUSH2, 0x01],
+            expected_stack: &[0x0100_U256],
+            expected_memory: &hex!("60405f5f396101000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+            expected_gas: 3 + 2 + 2 + (gas::verylowcopy_cost(64).unwrap() + gas::memory_gas(2)) + 3,
+        }),
+        codecopy_self_hash(@raw {
+            bytecode: &[
+                op::CODESIZE, op::PUSH0, op::PUSH0, op::CODECOPY,
+                op::CODESIZE, op::PUSH0, op::KECCAK256,
+                op::PUSH0, op::MSTORE, op::PUSH1, 32, op::PUSH0, op::RETURN,
+            ],
+            expected_return: InstructionResult::Return,
+            expected_memory: MEMORY_WHAT_INTERPRETER_SAYS,
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+            expected_next_action: ACTION_WHAT_INTERPRETER_SAYS,
+        }),
     }
 
     returndata {
@@ -718,12 +855,14 @@ tests! {
             expected_stack: &[def_env().effective_gas_price()],
             expected_gas: 2,
         }),
-        // Host determines blockhash
-        blockhash0(op::BLOCKHASH, DEF_BN - 0_U256 => DEF_BN - 0_U256),
+        // Only the last 256 blocks are available, excluding the current one.
+        blockhash0(op::BLOCKHASH, DEF_BN - 0_U256 => 0_U256),
         blockhash1(op::BLOCKHASH, DEF_BN - 1_U256 => DEF_BN - 1_U256),
         blockhash2(op::BLOCKHASH, DEF_BN - 255_U256 => DEF_BN - 255_U256),
         blockhash3(op::BLOCKHASH, DEF_BN - 256_U256 => DEF_BN - 256_U256),
-        blockhash4(op::BLOCKHASH, DEF_BN - 257_U256 => DEF_BN - 257_U256),
+        blockhash4(op::BLOCKHASH, DEF_BN - 257_U256 => 0_U256),
+        blockhash5(op::BLOCKHASH, DEF_BN + 1_U256 => 0_U256),
+        blockhash6(op::BLOCKHASH, 1_U256 << 70 => 0_U256),
         coinbase(@raw {
             bytecode: &[op::COINBASE, op::COINBASE],
             expected_stack: &[def_env().block.coinbase.into_word().into(), def_env().block.coinbase.into_word().into()],
@@ -1062,6 +1201,7 @@ tests! {
                     gas_limit: 66917,
                 })
             },
+            assert_ecx: Some(|ecx| assert_eq!(ecx.create2_address(), None)),
         }),
         create2(@raw {
             bytecode: &[op::PUSH1, 0x69, op::PUSH0, op::MSTORE, op::PUSH1, 100, op::PUSH1, 32, op::PUSH0, op::PUSH1, 0x42, op::CREATE2],
@@ -1079,6 +1219,23 @@ tests! {
                     gas_limit: 66908,
                 })
             },
+            assert_ecx: Some(|ecx| {
+                let init_code_hash = keccak256(0x69_U256.to_be_bytes::<32>());
+                let address = DEF_ADDR.create2(100_U256.to_be_bytes::<32>(), init_code_hash);
+                assert_eq!(ecx.create2_address(), Some(address));
+            }),
+        }),
+        create2_empty(@raw {
+            bytecode: &[op::PUSH1, 100, op::PUSH0, op::PUSH0, op::PUSH1, 0x42, op::CREATE2],
+            expected_return: InstructionResult::CallOrCreate,
+            // NOTE: The address is pushed by the caller.
+            expected_stack: &[],
+            expected_gas: GAS_WHAT_INTERPRETER_SAYS,
+            expected_next_action: ACTION_WHAT_INTERPRETER_SAYS,
+            assert_ecx: Some(|ecx| {
+                let address = DEF_ADDR.create2(100_U256.to_be_bytes::<32>(), KECCAK_EMPTY);
+                assert_eq!(ecx.create2_address(), Some(address));
+            }),
         }),
         call(@raw {
             bytecode: &[
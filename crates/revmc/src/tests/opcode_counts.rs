@@ -0,0 +1,51 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(enabled = |compiler| run(compiler, true));
+matrix_tests!(disabled = |compiler| run(compiler, false));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 3,
+    // Loop 3 times.
+    op::JUMPDEST,
+    op::PUSH0, op::PUSH0, op::KECCAK256, op::POP,
+    op::PUSH1, 1, op::SWAP1, op::SUB,
+    op::DUP1, op::PUSH1, 2, op::JUMPI,
+    op::STOP,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, opcode_counts: bool) {
+    compiler.opcode_counts(opcode_counts);
+    let f = unsafe { compiler.jit("opcode_counts", TEST, DEF_SPEC) }.unwrap();
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+    });
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        ecx.opcode_counts = Some(Box::leak(Box::new([0; 256])));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+
+        let counts = ecx.opcode_counts.as_deref().unwrap();
+        if !opcode_counts {
+            assert_eq!(*counts, [0; 256]);
+            return;
+        }
+        let mut expected = [0; 256];
+        expected[op::PUSH1 as usize] = 1 + 3 + 3;
+        expected[op::JUMPDEST as usize] = 3;
+        expected[op::PUSH0 as usize] = 2 * 3;
+        expected[op::KECCAK256 as usize] = 3;
+        expected[op::POP as usize] = 3;
+        expected[op::SWAP1 as usize] = 3;
+        expected[op::SUB as usize] = 3;
+        expected[op::DUP1 as usize] = 3;
+        expected[op::JUMPI as usize] = 3;
+        expected[op::STOP as usize] = 1;
+        assert_eq!(*counts, expected);
+    });
+}
@@ -0,0 +1,52 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, EvmCompiler, TEST_SUSPEND};
+use revm_interpreter::{opcode as op, InstructionResult};
+use std::{cell::RefCell, rc::Rc};
+
+matrix_tests!(enabled = |compiler| run(compiler, true));
+matrix_tests!(disabled = |compiler| run(compiler, false));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    // 0
+    op::PUSH1, 0x42,
+    // 2
+    TEST_SUSPEND,
+    // 3
+    op::PUSH0, op::PUSH0,
+    // 5
+    op::REVERT,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, result_sink: bool) {
+    compiler.result_sink(result_sink);
+    let f = unsafe { compiler.jit("result_sink", TEST, DEF_SPEC) }.unwrap();
+
+    // No sink.
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::CallOrCreate);
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Revert);
+    });
+
+    let results = Rc::new(RefCell::new(Vec::new()));
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let sink_results = results.clone();
+        let sink = move |pc, result| sink_results.borrow_mut().push((pc, result));
+        ecx.result_sink = Some(Box::leak(Box::new(sink)));
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::CallOrCreate);
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Revert);
+    });
+    let results = results.borrow();
+    if result_sink {
+        assert_eq!(
+            *results,
+            [(2, InstructionResult::CallOrCreate), (5, InstructionResult::Revert)]
+        );
+    } else {
+        assert!(results.is_empty());
+    }
+}
@@ -12,6 +12,7 @@ matrix_tests!(
         SpecId::OSAKA
     )
 );
+matrix_tests!(validate_resume_at = |compiler| run_validate(compiler));
 
 #[rustfmt::skip]
 const TEST: &[u8] = &[
@@ -131,3 +132,28 @@ fn run<B: Backend>(compiler: &mut EvmCompiler<B>, code: &[u8], spec_id: SpecId)
         assert_eq!(ecx.resume_at, resume_3);
     });
 }
+
+fn run_validate<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.validate_resume_at(true);
+    let id = compiler.translate("resume", TEST, DEF_SPEC).unwrap();
+    assert_eq!(compiler.resume_points("resume"), Some(3));
+    assert_eq!(compiler.fn_info_by_name("resume"), compiler.fn_info(id));
+    assert_eq!(compiler.resume_points("missing"), None);
+    let f = unsafe { compiler.jit_function(id) }.unwrap();
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::CallOrCreate);
+        assert_eq!(ecx.resume_at, 1);
+
+        ecx.resume_at = 3;
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+
+        for resume_at in [4, 100, usize::MAX] {
+            ecx.resume_at = resume_at;
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            assert_eq!(r, InstructionResult::FatalExternalError, "{resume_at}");
+        }
+    });
+}
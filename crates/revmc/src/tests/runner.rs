@@ -2,7 +2,8 @@ use super::*;
 use interpreter::{AccountLoad, SStoreResult, StateLoad};
 use revm_interpreter::{opcode as op, Contract, DummyHost, Host, SelfDestructResult};
 use revm_primitives::{
-    spec_to_generic, BlobExcessGasAndPrice, BlockEnv, CfgEnv, Env, HashMap, TxEnv, EOF_MAGIC_BYTES,
+    spec_to_generic, BlobExcessGasAndPrice, BlockEnv, CfgEnv, Env, HashMap, TxEnv,
+    BLOCK_HASH_HISTORY, EOF_MAGIC_BYTES,
 };
 use similar_asserts::assert_eq;
 use std::{fmt, path::Path, sync::OnceLock};
@@ -204,6 +205,8 @@ pub struct TestHost {
     pub host: DummyHost,
     pub code_map: &'static HashMap<Address, primitives::Bytecode>,
     pub selfdestructs: Vec<(Address, Address)>,
+    /// The compiled functions that calls are executed with directly, see [`FastCallHost`].
+    pub fast_calls: HashMap<Address, (EvmCompilerFn, primitives::Bytecode)>,
 }
 
 impl Default for TestHost {
@@ -223,6 +226,7 @@ impl TestHost {
             },
             code_map: def_codemap(),
             selfdestructs: Vec::new(),
+            fast_calls: HashMap::new(),
         }
     }
 }
@@ -255,6 +259,11 @@ impl Host for TestHost {
     }
 
     fn block_hash(&mut self, number: u64) -> Option<B256> {
+        // Same bounds as `revm::Context`, which are also checked by the builtin.
+        let age = self.env().block.number.checked_sub(U256::from(number));
+        if age.map_or(true, |age| age == U256::ZERO || age > U256::from(BLOCK_HASH_HISTORY)) {
+            return Some(B256::ZERO);
+        }
         Some(U256::from(number).into())
     }
 
@@ -315,30 +324,46 @@ impl Host for TestHost {
     }
 }
 
+impl FastCallHost for TestHost {
+    type Checkpoint = ();
+
+    fn enter_call(&mut self, inputs: &CallInputs) -> Option<FastCall<()>> {
+        let (function, bytecode) = self.fast_calls.get(&inputs.bytecode_address)?.clone();
+        let contract = Contract::new_with_context(inputs.input.clone(), bytecode, None, inputs);
+        Some(FastCall { function, contract, checkpoint: () })
+    }
+
+    fn exit_call(&mut self, (): (), _result: InstructionResult) {}
+}
+
 pub fn with_evm_context<F: FnOnce(&mut EvmContext<'_>, &mut EvmStack, &mut usize) -> R, R>(
     bytecode: &[u8],
     f: F,
 ) -> R {
+    let mut interpreter = test_interpreter(bytecode);
+    let mut host = TestHost::new();
+
+    let (mut ecx, stack, stack_len) =
+        EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+    f(&mut ecx, stack, stack_len)
+}
+
+/// Returns the interpreter that [`with_evm_context`] creates the context from.
+///
+/// This can be used instead of [`with_evm_context`] when the context has to borrow values that are
+/// created by the test.
+pub fn test_interpreter(bytecode: &[u8]) -> revm_interpreter::Interpreter {
     let contract = Contract {
         input: Bytes::from_static(DEF_CD),
-        bytecode: revm_interpreter::analysis::to_analysed(revm_primitives::Bytecode::new_raw(
-            Bytes::copy_from_slice(bytecode),
-        )),
-        hash: None,
-        bytecode_address: None,
         target_address: DEF_ADDR,
         caller: DEF_CALLER,
         call_value: DEF_VALUE,
+        ..test_contract(Bytes::copy_from_slice(bytecode))
     };
 
     let mut interpreter = revm_interpreter::Interpreter::new(contract, DEF_GAS_LIMIT, false);
     interpreter.return_data_buffer = Bytes::from_static(DEF_RD);
-
-    let mut host = TestHost::new();
-
-    let (mut ecx, stack, stack_len) =
-        EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
-    f(&mut ecx, stack, stack_len)
+    interpreter
 }
 
 #[cfg(feature = "llvm")]
@@ -375,7 +400,7 @@ pub fn run_test_case<B: Backend>(test_case: &TestCase<'_>, compiler: &mut EvmCom
     run_compiled_test_case(test_case, f);
 }
 
-fn run_compiled_test_case(test_case: &TestCase<'_>, f: EvmCompilerFn) {
+pub fn run_compiled_test_case(test_case: &TestCase<'_>, f: EvmCompilerFn) {
     let TestCase {
         bytecode,
         spec_id,
@@ -0,0 +1,105 @@
+use super::{run_compiled_test_case, TestCase, DEF_SPEC};
+use crate::{Backend, EvmCompiler, EvmContext};
+use revm_interpreter::opcode as op;
+use revm_primitives::{hex, Bytes};
+
+matrix_tests!(guard = |compiler| run(compiler));
+
+const A: [u8; 4] = hex!("11111111");
+const B: [u8; 4] = hex!("22222222");
+const C: [u8; 4] = hex!("33333333");
+
+#[test]
+fn folds() {
+    use crate::bytecode::{AnalysisConfig, Bytecode, InstFlags};
+
+    let folded = |code: &[u8], selector: [u8; 4]| {
+        let mut bytecode = Bytecode::new(code, None, DEF_SPEC);
+        let selector = Some(u32::from_be_bytes(selector));
+        bytecode.analyze(AnalysisConfig { selector, ..Default::default() }).unwrap();
+        bytecode
+            .iter_insts()
+            .filter_map(|(_, data)| {
+                let taken = data.flags.contains(InstFlags::JUMPI_TAKEN);
+                let not_taken = data.flags.contains(InstFlags::JUMPI_NOT_TAKEN);
+                (taken || not_taken).then_some((data.pc, taken))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let code = dispatcher();
+    assert_eq!(folded(&code, A), [(14, true), (48, true)]);
+    assert_eq!(folded(&code, B), [(14, false), (24, true)]);
+    assert_eq!(folded(&code, C), [(14, false), (24, false), (34, true)]);
+    assert_eq!(folded(&code, hex!("44444444")), [(14, false), (24, false), (34, false)]);
+    assert_eq!(folded(&code, hex!("00000001")), [(14, true), (48, false)]);
+
+    let code = hex::decode(include_str!("../../../../data/univ2_router.rt.hex").trim()).unwrap();
+    let swap_exact_tokens_for_tokens = hex!("38ed1739");
+    // The router has dynamic jumps, so the dispatch is not followed past the first split.
+    assert_eq!(folded(&code, swap_exact_tokens_for_tokens), [(29, true)]);
+}
+
+/// A dispatcher that returns `1`, `2` or `3` for the selectors `A`, `B` and `C`, and reverts
+/// otherwise. The first comparison splits it in two, as Solidity does for large contracts.
+#[rustfmt::skip]
+fn dispatcher() -> Vec<u8> {
+    fn case(cmp: u8, selector: [u8; 4], dest: u8) -> [u8; 10] {
+        let [a, b, c, d] = selector;
+        [op::DUP1, op::PUSH4, a, b, c, d, cmp, op::PUSH1, dest, op::JUMPI]
+    }
+    fn ret(value: u8) -> [u8; 9] {
+        [op::JUMPDEST, op::PUSH1, value, op::PUSH0, op::MSTORE, op::PUSH1, 0x20, op::PUSH0, op::RETURN]
+    }
+
+    [
+        &[op::PUSH0, op::CALLDATALOAD, op::PUSH1, 0xe0, op::SHR][..],
+        &case(op::GT, B, 38),
+        &case(op::EQ, B, 62),
+        &case(op::EQ, C, 71),
+        &[op::PUSH1, 49, op::JUMP],
+        // 38
+        &[op::JUMPDEST],
+        &case(op::EQ, A, 53),
+        // 49
+        &[op::JUMPDEST, op::PUSH0, op::DUP1, op::REVERT],
+        // 53
+        &ret(1),
+        &ret(2),
+        &ret(3),
+    ]
+    .concat()
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let code = dispatcher();
+    compiler.inspect_stack_length(true);
+    compiler.translate("generic", &code[..], DEF_SPEC).unwrap();
+    let ids = [A, B, hex!("44444444")].map(|selector| {
+        let name = format!("specialized_{}", hex::encode(selector));
+        compiler.specialize_for_selector(&name, "generic", &code[..], DEF_SPEC, selector).unwrap()
+    });
+
+    let calldatas: [fn(&mut EvmContext<'_>); 7] = [
+        |ecx| set_calldata(ecx, &A),
+        |ecx| set_calldata(ecx, &B),
+        |ecx| set_calldata(ecx, &C),
+        |ecx| set_calldata(ecx, &[0x44, 0x44, 0x44, 0x44, 0xff]),
+        |ecx| set_calldata(ecx, &[0x11, 0x11, 0x11]),
+        |ecx| set_calldata(ecx, &[]),
+        // `DEF_CD`.
+        |_| {},
+    ];
+    for id in ids {
+        let f = unsafe { compiler.jit_function(id) }.unwrap();
+        for modify_ecx in calldatas {
+            let mut test_case = TestCase::what_interpreter_says(&code, DEF_SPEC);
+            test_case.modify_ecx = Some(modify_ecx);
+            run_compiled_test_case(&test_case, f);
+        }
+    }
+}
+
+fn set_calldata(ecx: &mut EvmContext<'_>, data: &'static [u8]) {
+    ecx.contract.input = Bytes::from_static(data);
+}
@@ -0,0 +1,35 @@
+use super::{bytecode_binop, run_test_case, TestCase, DEF_SPEC, I256_MAX};
+use crate::{Backend, EvmCompiler, I256_MIN};
+use revm_interpreter::opcode as op;
+use revm_primitives::U256;
+
+matrix_tests!(differential = |compiler| run(compiler));
+
+/// Operands around zero and the signed overflow boundaries.
+fn values() -> [U256; 9] {
+    let one = U256::from(1);
+    let two = U256::from(2);
+    [
+        U256::ZERO,
+        one,
+        one.wrapping_neg(),
+        two,
+        two.wrapping_neg(),
+        I256_MIN,
+        I256_MIN + one,
+        I256_MAX,
+        I256_MAX - one,
+    ]
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    for opcode in [op::SLT, op::SGT, op::SDIV, op::SMOD, op::DIV, op::MOD] {
+        for a in values() {
+            for b in values() {
+                let code = bytecode_binop(opcode, a, b);
+                unsafe { compiler.clear() }.unwrap();
+                run_test_case(&TestCase::what_interpreter_says(&code, DEF_SPEC), compiler);
+            }
+        }
+    }
+}
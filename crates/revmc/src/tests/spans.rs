@@ -0,0 +1,42 @@
+use super::DEF_SPEC;
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::opcode as op;
+use std::sync::{Arc, Mutex};
+use tracing::{span, subscriber::Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+matrix_tests!(compile = |compiler| run(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 0x69, op::PUSH0, op::MSTORE,
+    op::PUSH1, 0x20, op::PUSH0, op::RETURN,
+];
+
+/// Records the names of the spans that are created.
+#[derive(Clone, Default)]
+struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+impl<S: Subscriber> Layer<S> for SpanNames {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+        if metadata.name() == "translate_block" {
+            assert!(metadata.fields().field("pc").is_some(), "missing `pc` field");
+        }
+        self.0.lock().unwrap().push(metadata.name());
+    }
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let names = SpanNames::default();
+    let subscriber = tracing_subscriber::registry().with(names.clone());
+    tracing::subscriber::with_default(subscriber, || {
+        let id = compiler.translate("spans", TEST, DEF_SPEC).unwrap();
+        unsafe { compiler.jit_function(id) }.unwrap();
+    });
+
+    let names = names.0.lock().unwrap();
+    for expected in ["analyze", "translate_block", "llvm_opt", "codegen"] {
+        assert!(names.contains(&expected), "missing span `{expected}` in {names:?}");
+    }
+}
@@ -0,0 +1,41 @@
+//! Checks that stack shuffles within a fall-through run don't reload known values.
+
+use super::DEF_SPEC;
+use crate::{llvm::with_llvm_context, EvmCompiler, EvmLlvmBackend, OptimizationLevel};
+use revm_interpreter::opcode as op;
+
+/// Returns the number of `(word, stack length)` loads in the unoptimized IR of `code`.
+fn count_loads(code: &[u8]) -> (usize, usize) {
+    let tmp = tempfile::tempdir().expect("could not create temp dir");
+    let ir = with_llvm_context(|cx| {
+        let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::None).unwrap();
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.inspect_stack_length(true);
+        compiler.set_dump_to(Some(tmp.path().to_path_buf()));
+        let _ = unsafe { compiler.jit("stack_shuffle", code, DEF_SPEC) }.unwrap();
+        std::fs::read_to_string(tmp.path().join("unopt.ll")).unwrap()
+    });
+    let loads = ir.lines().filter(|line| line.contains(" = load "));
+    let words = loads.clone().filter(|line| line.contains("load i256")).count();
+    let lens = loads.filter(|line| line.contains("%len.addr")).count();
+    (words, lens)
+}
+
+#[test]
+fn dup_swap_dup() {
+    // DUP1 loads the top, SWAP2 loads the third value, DUP3 reuses the swapped value.
+    // The stack length is loaded once at the start and once when returning.
+    assert_eq!(count_loads(&[op::DUP1, op::SWAP2, op::DUP3, op::STOP]), (2, 2));
+}
+
+#[test]
+fn pushed_values() {
+    let code = [op::PUSH1, 1, op::PUSH1, 2, op::PUSH1, 3, op::DUP1, op::SWAP2, op::DUP3, op::STOP];
+    assert_eq!(count_loads(&code), (0, 2));
+}
+
+#[test]
+fn reload_after_jumpdest() {
+    let code = [op::PUSH1, 1, op::JUMPDEST, op::DUP1, op::STOP];
+    assert_eq!(count_loads(&code), (1, 3));
+}
@@ -0,0 +1,19 @@
+use super::*;
+use std::path::PathBuf;
+
+#[cfg(feature = "llvm")]
+#[test]
+#[ignore = "requires the ethereum/tests fixtures; set `ETHEREUM_TESTS` to the repository root"]
+fn statetest() {
+    crate::tests::with_llvm_backend_jit(OptimizationLevel::Aggressive, |compiler| {
+        run_statetests(compiler)
+    });
+}
+
+fn run_statetests<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    let root = std::env::var_os("ETHEREUM_TESTS").expect("`ETHEREUM_TESTS` is not set");
+    let path = PathBuf::from(root).join("GeneralStateTests");
+    let report = crate::statetest::run(compiler, &path).unwrap();
+    eprintln!("{report}");
+    assert!(report.is_success(), "{} state tests failed", report.failures.len());
+}
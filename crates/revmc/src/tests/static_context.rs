@@ -0,0 +1,119 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, CallCheckError, EvmCompiler};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(runtime = |compiler| run(compiler, None));
+matrix_tests!(is_static = |compiler| run(compiler, Some(true)));
+matrix_tests!(not_static = |compiler| run(compiler, Some(false)));
+matrix_tests!(runtime_underflow = |compiler| underflow(compiler, None));
+matrix_tests!(is_static_underflow = |compiler| underflow(compiler, Some(true)));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 0x69, op::PUSH0, op::SSTORE,
+    op::PUSH0, op::PUSH0, op::PUSH0, op::LOG1,
+    op::STOP,
+];
+
+#[rustfmt::skip]
+const CALL_WITH_VALUE: &[u8] = &[
+    op::PUSH0, op::PUSH0, op::PUSH0, op::PUSH0,
+    op::PUSH1, 1, op::PUSH0, op::GAS,
+    op::CALL,
+    op::STOP,
+];
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, static_context: Option<bool>) {
+    compiler.static_context(static_context);
+    let id = compiler.translate("static_context", TEST, DEF_SPEC).unwrap();
+    let info = compiler.fn_info(id).unwrap();
+    assert_eq!(info.static_context, static_context);
+    let f = unsafe { compiler.jit_function(id) }.unwrap();
+
+    for is_static in [false, true] {
+        with_evm_context(TEST, |ecx, stack, stack_len| {
+            ecx.is_static = is_static;
+            let r = unsafe { f.call_checked(&info, Some(stack), Some(stack_len), ecx) };
+            match static_context {
+                Some(expected) if expected != is_static => {
+                    assert_eq!(r, Err(CallCheckError::StaticContext { expected }));
+                    assert_eq!(ecx.gas.remaining(), ecx.gas.limit());
+                }
+                _ if is_static => {
+                    assert_eq!(r, Ok(InstructionResult::StateChangeDuringStaticCall));
+                }
+                _ => assert_eq!(r, Ok(InstructionResult::Stop)),
+            }
+        });
+    }
+
+    // The checks are not compiled in when specialized for a non-static context.
+    if static_context == Some(false) {
+        with_evm_context(TEST, |ecx, stack, stack_len| {
+            ecx.is_static = true;
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            assert_eq!(r, InstructionResult::Stop);
+        });
+    }
+
+    let id = compiler.translate("static_context_call", CALL_WITH_VALUE, DEF_SPEC).unwrap();
+    let f = unsafe { compiler.jit_function(id) }.unwrap();
+    for is_static in [false, true] {
+        with_evm_context(CALL_WITH_VALUE, |ecx, stack, stack_len| {
+            ecx.is_static = is_static;
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            if is_static && static_context != Some(false) {
+                assert_eq!(r, InstructionResult::CallNotAllowedInsideStatic);
+            } else {
+                assert_eq!(r, InstructionResult::CallOrCreate);
+            }
+        });
+    }
+}
+
+/// An underflowing `SSTORE` in a static context fails like in the interpreter, which checks the
+/// static context before popping the operands.
+fn underflow<B: Backend>(compiler: &mut EvmCompiler<B>, static_context: Option<bool>) {
+    const CODE: &[u8] = &[op::SSTORE];
+
+    compiler.static_context(static_context);
+    let f = unsafe { compiler.jit("static_context_underflow", CODE, DEF_SPEC) }.unwrap();
+    for is_static in [false, true] {
+        if static_context == Some(true) && !is_static {
+            continue;
+        }
+        with_evm_context(CODE, |ecx, stack, stack_len| {
+            ecx.is_static = is_static;
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            if is_static {
+                assert_eq!(r, InstructionResult::StateChangeDuringStaticCall);
+            } else {
+                assert_eq!(r, InstructionResult::StackUnderflow);
+            }
+        });
+    }
+}
+
+/// Counts the loads of `ecx.is_static` in the unoptimized IR.
+#[test]
+#[cfg(feature = "llvm")]
+fn is_static_loads() {
+    use crate::{llvm::with_llvm_context, EvmLlvmBackend, OptimizationLevel};
+
+    let count = |static_context| {
+        let tmp = tempfile::tempdir().expect("could not create temp dir");
+        with_llvm_context(|cx| {
+            let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::None).unwrap();
+            let mut compiler = EvmCompiler::new(backend);
+            compiler.static_context(static_context);
+            compiler.set_dump_to(Some(tmp.path().to_path_buf()));
+            let id = compiler.translate("static_context", TEST, DEF_SPEC).unwrap();
+            let _ = unsafe { compiler.jit_function(id) }.unwrap();
+        });
+        let ir = std::fs::read_to_string(tmp.path().join("unopt.ll")).unwrap();
+        ir.lines().filter(|line| line.contains("%ecx.is_static") && line.contains("= load")).count()
+    };
+    assert_eq!(count(None), 2);
+    assert_eq!(count(Some(false)), 0);
+    assert_eq!(count(Some(true)), 0);
+}
@@ -0,0 +1,50 @@
+use super::{with_evm_context, DEF_SPEC};
+use crate::{Backend, EvmCompiler};
+use revm_interpreter::{opcode as op, InstructionResult};
+
+matrix_tests!(enabled = |compiler| run(compiler, true));
+matrix_tests!(disabled = |compiler| run(compiler, false));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    op::PUSH1, 3,
+    // Loop 3 times.
+    op::JUMPDEST,
+    op::PUSH0, op::PUSH0, op::KECCAK256, op::POP,
+    op::PUSH1, 1, op::SWAP1, op::SUB,
+    op::DUP1, op::PUSH1, 2, op::JUMPI,
+    op::STOP,
+];
+
+/// The number of instructions executed by [`TEST`].
+const STEPS: u64 = 1 + 3 * 11 + 1;
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>, step_budget: bool) {
+    compiler.step_budget(step_budget);
+    let f = unsafe { compiler.jit("step_budget", TEST, DEF_SPEC) }.unwrap();
+
+    with_evm_context(TEST, |ecx, stack, stack_len| {
+        let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+        assert_eq!(r, InstructionResult::Stop);
+    });
+
+    for (budget, expected) in [
+        (STEPS + 1, InstructionResult::Stop),
+        (STEPS, InstructionResult::Stop),
+        (STEPS - 1, InstructionResult::FatalExternalError),
+        (0, InstructionResult::FatalExternalError),
+    ] {
+        with_evm_context(TEST, |ecx, stack, stack_len| {
+            ecx.step_budget = Some(Box::leak(Box::new(budget)));
+            let r = unsafe { f.call(Some(stack), Some(stack_len), ecx) };
+            let remaining = **ecx.step_budget.as_ref().unwrap();
+            if !step_budget {
+                assert_eq!(r, InstructionResult::Stop);
+                assert_eq!(remaining, budget);
+                return;
+            }
+            assert_eq!(r, expected, "budget: {budget}");
+            assert_eq!(remaining, budget.saturating_sub(STEPS));
+        });
+    }
+}
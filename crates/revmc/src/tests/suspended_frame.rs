@@ -0,0 +1,100 @@
+use super::{TestHost, DEF_SPEC};
+use crate::{test_contract, Backend, EvmCompiler, EvmContext, FrameOutcome, SuspendedFrame};
+use revm_interpreter::{
+    opcode as op, CallOutcome, Gas, InstructionResult, Interpreter, InterpreterAction,
+    InterpreterResult, SharedMemory,
+};
+use revm_primitives::{spec_to_generic, Bytes, SpecId, B256};
+
+matrix_tests!(migrate = |compiler| run(compiler));
+
+#[rustfmt::skip]
+const TEST: &[u8] = &[
+    // mstore(0, 0x42)
+    op::PUSH1, 0x42, op::PUSH0, op::MSTORE,
+    // call(gas, 0x69, 0, 0, 32, 32, 32)
+    op::PUSH1, 32, op::PUSH1, 32, op::PUSH1, 32, op::PUSH0, op::PUSH0, op::PUSH1, 0x69, op::GAS,
+    op::CALL,
+    // mstore(0, mload(0) + mload(32) + success)
+    op::PUSH0, op::MLOAD, op::ADD, op::PUSH1, 32, op::MLOAD, op::ADD, op::PUSH0, op::MSTORE,
+    // return(0, 64)
+    op::PUSH1, 64, op::PUSH0, op::RETURN,
+];
+
+const GAS_LIMIT: u64 = 100_000;
+
+fn new_interpreter() -> Interpreter {
+    let contract = test_contract(Bytes::from_static(TEST));
+    let mut interpreter = Interpreter::new(contract, GAS_LIMIT, false);
+    interpreter.shared_memory = SharedMemory::new();
+    interpreter
+}
+
+fn outcome() -> CallOutcome {
+    let result = InterpreterResult {
+        result: InstructionResult::Return,
+        output: Bytes::from_static(&[0xff; 32]),
+        gas: Gas::new(1000),
+    };
+    CallOutcome::new(result, 32..64)
+}
+
+fn run<B: Backend>(compiler: &mut EvmCompiler<B>) {
+    compiler.validate_resume_at(true);
+    let f = unsafe { compiler.jit("suspended_frame", TEST, DEF_SPEC) }.unwrap();
+    let artifact = B256::repeat_byte(0x11);
+
+    // Interpreter.
+    let table = spec_to_generic!(DEF_SPEC, op::make_instruction_table::<_, SPEC>());
+    let mut interpreter = new_interpreter();
+    let mut host = TestHost::new();
+    let memory = interpreter.take_memory();
+    let action = interpreter.run(memory, &table, &mut host);
+    assert!(matches!(action, InterpreterAction::Call { .. }), "{action:?}");
+    let mut memory = interpreter.take_memory();
+    interpreter.insert_call_outcome(&mut memory, outcome());
+    let expected = interpreter.run(memory, &table, &mut host);
+
+    // Suspend, serialize, and resume in a fresh interpreter.
+    let mut interpreter = new_interpreter();
+    let mut host = TestHost::new();
+    let action = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+    assert!(matches!(action, InterpreterAction::Call { .. }), "{action:?}");
+    // Put the action back, as the context reads it to check that the function is suspended.
+    interpreter.next_action = action;
+    let (ecx, stack, stack_len) =
+        EvmContext::from_interpreter_with_stack(&mut interpreter, &mut host);
+    let frame = SuspendedFrame::capture(&f, &ecx, stack, *stack_len, artifact).unwrap();
+    assert_eq!(frame.resume_index, 1);
+    let bytes = serde_json::to_vec(&frame).unwrap();
+    drop(interpreter);
+
+    let frame: SuspendedFrame = serde_json::from_slice(&bytes).unwrap();
+    let mut interpreter = new_interpreter();
+    let mut host = TestHost::new();
+    let r = unsafe {
+        f.resume_from(
+            frame.clone(),
+            B256::ZERO,
+            FrameOutcome::Call(outcome()),
+            &mut interpreter,
+            &mut host,
+        )
+    };
+    assert!(r.is_err());
+    let actual = unsafe {
+        f.resume_from(frame, artifact, FrameOutcome::Call(outcome()), &mut interpreter, &mut host)
+    }
+    .unwrap();
+
+    let (
+        InterpreterAction::Return { result: expected },
+        InterpreterAction::Return { result: actual },
+    ) = (expected, actual)
+    else {
+        panic!("expected return actions");
+    };
+    assert_eq!(actual.result, InstructionResult::Return);
+    assert_eq!(actual.output, expected.output);
+    assert_eq!(actual.gas.remaining(), expected.gas.remaining());
+}
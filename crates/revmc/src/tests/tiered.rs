@@ -0,0 +1,395 @@
+use super::with_evm_context;
+use crate::{
+    llvm::{inkwell::context::Context, with_llvm_context},
+    EvmCompiler, EvmLlvmBackend, OptimizationLevel, Result, TieredConfig, TieredExecutor,
+    TieredFunction,
+};
+use revm_interpreter::{
+    analysis::to_analysed, opcode as op, Contract, DummyHost, InstructionResult,
+};
+use revm_primitives::{keccak256, Bytecode, Bytes, SpecId, B256};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+fn new_compiler(cx: &Context) -> Result<EvmCompiler<EvmLlvmBackend<'_>>> {
+    let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::None)?;
+    Ok(EvmCompiler::new(backend))
+}
+
+fn executor(config: TieredConfig) -> TieredExecutor {
+    TieredExecutor::new(config, |worker| with_llvm_context(|cx| worker.run(|| new_compiler(cx))))
+}
+
+fn contract(i: u8) -> (B256, Vec<u8>) {
+    let code = vec![op::PUSH1, i, op::STOP];
+    (keccak256(&code), code)
+}
+
+/// Returns the compiled function of the contract, waiting for it to be compiled if it was queued.
+fn get_compiled(
+    executor: &TieredExecutor,
+    (hash, code): &(B256, Vec<u8>),
+) -> Option<TieredFunction> {
+    unsafe { executor.get_function(*hash, code, SpecId::CANCUN) }.or_else(|| {
+        executor.wait_idle();
+        unsafe { executor.get_function(*hash, code, SpecId::CANCUN) }
+    })
+}
+
+#[test]
+fn only_hot_compiled() {
+    let config = TieredConfig { hot_threshold: 100, ..Default::default() };
+    let mut executor = executor(config);
+    let tier_ups = Arc::new(AtomicUsize::new(0));
+    let tier_ups2 = tier_ups.clone();
+    executor.on_tier_up(move |_| {
+        tier_ups2.fetch_add(1, Ordering::Relaxed);
+    });
+
+    // 5 hot contracts are called 191 times each, and 45 cold ones once: 1000 calls.
+    let contracts = (0..50).map(contract).collect::<Vec<_>>();
+    let mut calls = 0;
+    for round in 0..191 {
+        for (i, (hash, code)) in contracts.iter().enumerate() {
+            if i >= 5 && round > 0 {
+                continue;
+            }
+            let f = unsafe { executor.get_function(*hash, code, SpecId::CANCUN) };
+            // The 100th call queues the code, and the next one runs it.
+            assert_eq!(f.is_some(), i < 5 && round >= 100, "{i} {round}");
+            calls += 1;
+        }
+        executor.wait_idle();
+    }
+    assert_eq!(calls, 1000);
+
+    for (i, (hash, _)) in contracts.iter().enumerate() {
+        assert_eq!(executor.is_compiled(*hash, SpecId::CANCUN), i < 5, "{i}");
+    }
+    assert_eq!(executor.compiled_count(), 5);
+    assert_eq!(tier_ups.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn background_compilation() {
+    let (tx, rx) = mpsc::channel::<()>();
+    let rx = Mutex::new(rx);
+    let config = TieredConfig { hot_threshold: 1, ..Default::default() };
+    let executor = TieredExecutor::new(config, move |worker| {
+        with_llvm_context(|cx| {
+            worker.run(|| {
+                rx.lock().unwrap().recv().unwrap();
+                new_compiler(cx)
+            })
+        })
+    });
+
+    // Invocations are interpreted while the code is being compiled.
+    let (hash, code) = contract(0);
+    for _ in 0..10 {
+        assert!(unsafe { executor.get_function(hash, &code, SpecId::CANCUN) }.is_none());
+    }
+    assert!(!executor.is_compiled(hash, SpecId::CANCUN));
+    assert_eq!(executor.metrics().queued_compilations, 1);
+
+    tx.send(()).unwrap();
+    executor.wait_idle();
+    assert!(executor.is_compiled(hash, SpecId::CANCUN));
+    let metrics = executor.metrics();
+    assert_eq!((metrics.compilations, metrics.queued_compilations), (1, 0));
+    assert_eq!((metrics.hits, metrics.misses), (0, 10));
+    assert!(unsafe { executor.get_function(hash, &code, SpecId::CANCUN) }.is_some());
+}
+
+#[test]
+fn shared_executor() {
+    let config = TieredConfig { hot_threshold: 10, compile_threads: 4, ..Default::default() };
+    let executor = executor(config);
+    let contracts = (0..8).map(contract).collect::<Vec<_>>();
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    for (hash, code) in &contracts {
+                        let f = unsafe { executor.get_function(*hash, code, SpecId::CANCUN) };
+                        if let Some(f) = f {
+                            assert_eq!(call(&f, code), InstructionResult::Stop);
+                        }
+                    }
+                }
+            });
+        }
+    });
+    executor.wait_idle();
+
+    let metrics = executor.metrics();
+    assert_eq!(metrics.hits + metrics.misses, 8 * 100 * 8);
+    assert_eq!(metrics.compilations, 8);
+    assert_eq!(executor.compiled_count(), 8);
+}
+
+#[test]
+fn lru_eviction() {
+    let config = TieredConfig { hot_threshold: 1, max_cached_functions: 2, ..Default::default() };
+    let executor = executor(config);
+    let [a, b, c] = [0, 1, 2].map(contract);
+    for contract in [&a, &b, &a, &c] {
+        assert!(get_compiled(&executor, contract).is_some());
+    }
+    assert_eq!(executor.compiled_count(), 2);
+    assert!(executor.is_compiled(a.0, SpecId::CANCUN));
+    assert!(!executor.is_compiled(b.0, SpecId::CANCUN));
+    assert!(executor.is_compiled(c.0, SpecId::CANCUN));
+
+    // Evicted code that is still hot is compiled again.
+    assert!(get_compiled(&executor, &b).is_some());
+    assert!(!executor.is_compiled(a.0, SpecId::CANCUN));
+    assert!(executor.is_compiled(b.0, SpecId::CANCUN));
+
+    let metrics = executor.metrics();
+    assert_eq!(metrics.hits, 5);
+    assert_eq!(metrics.misses, 4);
+    assert_eq!(metrics.evictions, 2);
+    assert_eq!(metrics.compilations, 4);
+    assert_eq!(metrics.compile_failures, 0);
+    assert!(metrics.compile_time > Duration::ZERO);
+    assert_eq!(metrics.hit_rate(), 5.0 / 9.0);
+    // LLVM does not report machine code sizes, so the size of the bytecode is used instead.
+    assert_eq!(metrics.resident_bytes, 2 * b.1.len());
+}
+
+#[test]
+fn byte_budget() {
+    let [a, b, c] = [0, 1, 2].map(contract);
+    let size = a.1.len();
+    let config =
+        TieredConfig { hot_threshold: 1, max_cached_bytes: 2 * size, ..Default::default() };
+    let executor = executor(config);
+    let fa = get_compiled(&executor, &a).unwrap();
+    // LLVM does not report machine code sizes, so the budget is in bytes of bytecode.
+    assert_eq!(fa.code_size(), None);
+    for contract in [&b, &c] {
+        assert!(get_compiled(&executor, contract).is_some());
+    }
+    assert_eq!(executor.compiled_count(), 2);
+    assert!(!executor.is_compiled(a.0, SpecId::CANCUN));
+
+    // `a` is evicted, but not freed while it is in use.
+    let metrics = executor.metrics();
+    assert_eq!(metrics.evictions, 1);
+    assert_eq!(metrics.resident_bytes, 3 * size);
+    assert_eq!(call(&fa, &a.1), InstructionResult::Stop);
+    assert_eq!(executor.free_evicted(), 0);
+    drop(fa);
+    assert_eq!(executor.free_evicted(), 1);
+    assert_eq!(executor.metrics().resident_bytes, 2 * size);
+}
+
+#[test]
+fn too_large() {
+    let contract = contract(0);
+    let config = TieredConfig {
+        hot_threshold: 1,
+        max_cached_bytes: contract.1.len() - 1,
+        ..Default::default()
+    };
+    let executor = executor(config);
+    assert!(get_compiled(&executor, &contract).is_none());
+    assert!(get_compiled(&executor, &contract).is_none());
+    assert_eq!(executor.compiled_count(), 0);
+    let metrics = executor.metrics();
+    assert_eq!(metrics.compilations, 1);
+    assert_eq!(metrics.resident_bytes, 0);
+}
+
+/// Executes functions on 16 threads while this thread keeps evicting them.
+#[test]
+fn concurrent_eviction() {
+    let config = TieredConfig { hot_threshold: 1, max_cached_functions: 2, ..Default::default() };
+    let executor = executor(config);
+    let contracts = (0..8).map(contract).collect::<Vec<_>>();
+    thread::scope(|s| {
+        let workers = (0..16)
+            .map(|_| {
+                let (tx, rx) = mpsc::channel::<(TieredFunction, Vec<u8>)>();
+                s.spawn(move || {
+                    for (f, code) in rx {
+                        for _ in 0..10 {
+                            assert_eq!(call(&f, &code), InstructionResult::Stop);
+                        }
+                    }
+                });
+                tx
+            })
+            .collect::<Vec<_>>();
+        for round in 0..50 {
+            for (i, contract) in contracts.iter().enumerate() {
+                let f = get_compiled(&executor, contract).unwrap();
+                let worker = &workers[(round * contracts.len() + i) % workers.len()];
+                worker.send((f, contract.1.clone())).unwrap();
+            }
+        }
+    });
+    executor.free_evicted();
+
+    let metrics = executor.metrics();
+    assert_eq!(metrics.hits, 50 * 8);
+    assert!(metrics.evictions > 0);
+    assert_eq!(metrics.resident_bytes, 2 * contracts[0].1.len());
+}
+
+fn call(f: &TieredFunction, code: &[u8]) -> InstructionResult {
+    with_evm_context(code, |ecx, stack, stack_len| unsafe {
+        f.call(Some(stack), Some(stack_len), ecx)
+    })
+}
+
+#[test]
+fn outlives_executor() {
+    let config = TieredConfig { hot_threshold: 1, ..Default::default() };
+    let executor = executor(config);
+    let contract = contract(0);
+    let f = get_compiled(&executor, &contract).unwrap();
+    drop(executor);
+    assert_eq!(call(&f, &contract.1), InstructionResult::Stop);
+}
+
+#[test]
+fn compile_specs() {
+    let config = TieredConfig {
+        hot_threshold: 1,
+        compile_specs: vec![SpecId::CANCUN],
+        ..Default::default()
+    };
+    let executor = executor(config);
+    let (hash, code) = contract(0);
+    for _ in 0..2 {
+        assert!(unsafe { executor.get_function(hash, &code, SpecId::SHANGHAI) }.is_none());
+        executor.wait_idle();
+    }
+    assert!(get_compiled(&executor, &(hash, code)).is_some());
+    assert!(!executor.is_compiled(hash, SpecId::SHANGHAI));
+}
+
+#[test]
+fn max_tracked_contracts() {
+    let config = TieredConfig { hot_threshold: 2, max_tracked_contracts: 2, ..Default::default() };
+    let executor = executor(config);
+    let [a, b, c] = [0, 1, 2].map(contract);
+    for (hash, code) in [&a, &a, &b, &c, &b] {
+        let _ = unsafe { executor.get_function(*hash, code, SpecId::CANCUN) };
+        executor.wait_idle();
+        assert!(executor.tracked_count() <= 2);
+    }
+    // The count of `b` was forgotten when `c` was first called, but compiled code is kept.
+    assert!(executor.is_compiled(a.0, SpecId::CANCUN));
+    assert!(!executor.is_compiled(b.0, SpecId::CANCUN));
+    assert_eq!(executor.compiled_count(), 1);
+}
+
+#[test]
+fn compile_failure() {
+    let config = TieredConfig { hot_threshold: 1, ..Default::default() };
+    let executor = TieredExecutor::new(config, |worker| {
+        worker.run::<EvmLlvmBackend<'static>>(|| Err(crate::eyre::eyre!("no compiler")))
+    });
+    let contract = contract(0);
+    for _ in 0..2 {
+        assert!(get_compiled(&executor, &contract).is_none());
+    }
+    let metrics = executor.metrics();
+    assert_eq!((metrics.compilations, metrics.compile_failures), (0, 1));
+    assert_eq!((metrics.hits, metrics.misses), (0, 4));
+    assert_eq!(metrics.hit_rate(), 0.0);
+}
+
+#[test]
+fn warm_up() {
+    let config = TieredConfig { hot_threshold: 1, ..Default::default() };
+    let executor = executor(config);
+    let code = [op::PUSH1, 1, op::PUSH0, op::SSTORE, op::STOP];
+    let hash = keccak256(code);
+    let contract = Contract {
+        bytecode: to_analysed(Bytecode::new_raw(Bytes::copy_from_slice(&code))),
+        ..Default::default()
+    };
+    let mut host = DummyHost::default();
+    host.env.tx.gas_limit = 100_000;
+
+    // Not compiled yet.
+    assert_eq!(unsafe { executor.warm_up(hash, SpecId::CANCUN, contract.clone(), &host) }, None);
+
+    let f = get_compiled(&executor, &(hash, code.to_vec())).unwrap();
+    // MCJIT does not report the size of the code, so there is nothing to prefault.
+    assert_eq!(f.code_size(), None);
+    assert!(!f.prefault());
+    drop(f);
+
+    let time = unsafe { executor.warm_up(hash, SpecId::CANCUN, contract.clone(), &host) };
+    let time = time.unwrap();
+    let metrics = executor.metrics();
+    assert_eq!(metrics.warm_ups, 1);
+    assert_eq!(metrics.warm_up_time, time);
+    // The warm-up is not an invocation.
+    assert_eq!((metrics.hits, metrics.misses), (1, 1));
+    // The store went to a clone of the host.
+    assert!(host.storage.is_empty());
+
+    assert!(unsafe { executor.warm_up(hash, SpecId::SHANGHAI, contract, &host) }.is_none());
+    assert_eq!(executor.metrics().warm_ups, 1);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_recorder() {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        let config = TieredConfig { hot_threshold: 1, ..Default::default() };
+        let executor = executor(config);
+        let contract = contract(0);
+        let f = get_compiled(&executor, &contract).unwrap();
+        assert_eq!(call(&f, &contract.1), InstructionResult::Stop);
+        executor.record_execution_time(true, Duration::from_micros(1));
+    });
+
+    let snapshot = snapshotter.snapshot().into_vec();
+    let value = |name: &str, labels: &[(&str, &str)]| {
+        let (.., value) = snapshot
+            .iter()
+            .find(|(key, ..)| {
+                let key = key.key();
+                key.name() == name && key.labels().map(|l| (l.key(), l.value())).eq(labels.to_vec())
+            })
+            .unwrap_or_else(|| panic!("{name} {labels:?} was not recorded"));
+        value
+    };
+    let path = |path| [("path", path)];
+    assert_eq!(value("revm_jit_executions_total", &path("interpreter")), &DebugValue::Counter(1));
+    assert_eq!(value("revm_jit_executions_total", &path("compiled")), &DebugValue::Counter(1));
+    assert_eq!(value("revm_jit_compilations_total", &[]), &DebugValue::Counter(1));
+    let DebugValue::Histogram(compile_times) = value("revm_jit_compile_seconds", &[]) else {
+        panic!("not a histogram");
+    };
+    assert_eq!(compile_times.len(), 1);
+    let DebugValue::Histogram(times) = value("revm_jit_execution_seconds", &path("compiled"))
+    else {
+        panic!("not a histogram");
+    };
+    assert_eq!(times.iter().map(|t| t.0).collect::<Vec<_>>(), [1e-6]);
+    assert_eq!(value("revm_jit_compile_queue_depth", &[]), &DebugValue::Gauge(0.0.into()));
+    assert_eq!(value("revm_jit_cache_functions", &[]), &DebugValue::Gauge(1.0.into()));
+    let resident_bytes = contract(0).1.len() as f64;
+    assert_eq!(
+        value("revm_jit_cache_resident_bytes", &[]),
+        &DebugValue::Gauge(resident_bytes.into())
+    );
+}
@@ -0,0 +1,68 @@
+//! Checks the WebAssembly modules emitted with [`EvmCompiler::emit_wasm`].
+
+use super::DEF_SPEC;
+use crate::{
+    llvm::with_llvm_context, CompileError, EvmCompiler, EvmLlvmBackend, OptimizationLevel,
+};
+use revm_interpreter::opcode as op;
+
+#[rustfmt::skip]
+const CODE: &[u8] = &[
+    op::PUSH1, 1, op::PUSH1, 2, op::ADD,
+    op::DUP1, op::PUSH0, op::SSTORE,
+    op::PUSH0, op::MSTORE,
+    op::PUSH1, 32, op::PUSH0, op::RETURN,
+];
+
+/// Translates [`CODE`] for the given target triple, and returns the result of `f`.
+fn with_compiler<R>(
+    triple: Option<&str>,
+    f: impl FnOnce(&mut EvmCompiler<EvmLlvmBackend<'_>>) -> R,
+) -> R {
+    with_llvm_context(|cx| {
+        let mut backend = EvmLlvmBackend::new(cx, true, OptimizationLevel::Default).unwrap();
+        if let Some(triple) = triple {
+            backend.set_target(triple, None, None).unwrap();
+        }
+        let mut compiler = EvmCompiler::new(backend);
+        compiler.translate("wasm", CODE, DEF_SPEC).unwrap();
+        f(&mut compiler)
+    })
+}
+
+/// Returns `true` if `haystack` contains `needle`.
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn requires_wasm32() {
+    for triple in [None, Some("wasm64-unknown-unknown")] {
+        let err = with_compiler(triple, |compiler| compiler.emit_wasm()).unwrap_err();
+        assert!(matches!(err, CompileError::InvalidUsage(_)), "{triple:?}: {err:?}");
+    }
+}
+
+#[test]
+fn object() {
+    let mut object = Vec::new();
+    with_compiler(Some("wasm32-unknown-unknown"), |compiler| compiler.write_object(&mut object))
+        .unwrap();
+    assert_eq!(object[..8], *b"\0asm\x01\0\0\0");
+    // The builtins are undefined, and become imports of the module when linking.
+    for builtin in ["sstore", "resize_memory", "do_return"] {
+        assert!(contains(&object, format!("__revmc_builtin_{builtin}").as_bytes()), "{builtin}");
+    }
+}
+
+#[test]
+#[ignore = "requires wasm-ld"]
+fn module() {
+    let module =
+        with_compiler(Some("wasm32-unknown-unknown"), |compiler| compiler.emit_wasm()).unwrap();
+    assert_eq!(module[..8], *b"\0asm\x01\0\0\0");
+    assert!(contains(&module, b"wasm"));
+    assert!(contains(&module, b"memory"));
+    assert!(contains(&module, b"__heap_base"));
+    assert!(contains(&module, b"__revmc_builtin_sstore"));
+}
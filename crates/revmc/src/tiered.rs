@@ -0,0 +1,887 @@
+//! Tiered execution: interpret cold code, compile hot code in the background.
+
+use crate::{eyre, Backend, EvmCompiler, EvmCompilerFn, HostExt};
+use revm_interpreter::{Interpreter, SharedMemory};
+use revm_primitives::{SpecId, B256};
+use revmc_backend::Result;
+use rustc_hash::FxHashMap;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    ops::Deref,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// [`TieredExecutor`] configuration.
+#[derive(Clone, Debug)]
+pub struct TieredConfig {
+    /// The number of invocations within [`hot_window`](Self::hot_window) after which code is
+    /// compiled.
+    ///
+    /// Defaults to `100`.
+    pub hot_threshold: u32,
+    /// The window in which invocations are counted. The count is reset when the first counted
+    /// invocation is older than this, so that rarely executed code is never compiled.
+    ///
+    /// Defaults to 60 seconds.
+    pub hot_window: Duration,
+    /// The maximum number of compiled functions to keep. When exceeded, the least recently used
+    /// function is freed, and its code goes back to being interpreted.
+    ///
+    /// Defaults to `1024`.
+    pub max_cached_functions: usize,
+    /// The maximum size of the machine code of the compiled functions to keep, in bytes. When
+    /// exceeded, the least recently used functions are freed until the new one fits, and a
+    /// function that is larger than this on its own is never kept.
+    ///
+    /// Sizes are taken from [`CompilationStats::machine_code_bytes`], falling back to the size of
+    /// the bytecode if the backend does not report it. The LLVM backend never reports it, so with
+    /// LLVM this is a budget in bytes of bytecode, and [`TieredFunction::code_size`] is `None`.
+    ///
+    /// Defaults to `usize::MAX`.
+    ///
+    /// [`CompilationStats::machine_code_bytes`]: crate::CompilationStats::machine_code_bytes
+    pub max_cached_bytes: usize,
+    /// The specs to compile code for. Code executed under any other spec is always interpreted.
+    /// If empty, code is compiled for any spec.
+    ///
+    /// Defaults to empty.
+    pub compile_specs: Vec<SpecId>,
+    /// The maximum number of code hashes whose invocations are counted. When exceeded, the
+    /// counts of all the code that is not compiled are forgotten, and start again from zero.
+    ///
+    /// Defaults to `65536`.
+    pub max_tracked_contracts: usize,
+    /// The number of threads that compile hot code in the background. If zero, code is never
+    /// compiled.
+    ///
+    /// Defaults to `1`.
+    pub compile_threads: usize,
+    /// The maximum number of functions waiting to be compiled. Code that becomes hot while the
+    /// queue is full is interpreted, and is queued by its first invocation that finds room.
+    ///
+    /// Defaults to `256`.
+    pub max_queued_compilations: usize,
+}
+
+impl Default for TieredConfig {
+    fn default() -> Self {
+        Self {
+            hot_threshold: 100,
+            hot_window: Duration::from_secs(60),
+            max_cached_functions: 1024,
+            max_cached_bytes: usize::MAX,
+            compile_specs: Vec::new(),
+            max_tracked_contracts: 65536,
+            compile_threads: 1,
+            max_queued_compilations: 256,
+        }
+    }
+}
+
+/// Cache and compilation metrics of a [`TieredExecutor`], returned by
+/// [`TieredExecutor::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TieredMetrics {
+    /// The number of invocations that found a compiled function.
+    pub hits: u64,
+    /// The number of invocations that did not find a compiled function, including the ones that
+    /// compiled it.
+    pub misses: u64,
+    /// The total time spent running compiled code, as reported to
+    /// [`TieredExecutor::record_execution_time`].
+    pub compiled_time: Duration,
+    /// The total time spent interpreting code, as reported to
+    /// [`TieredExecutor::record_execution_time`].
+    pub interpreted_time: Duration,
+    /// The number of compiled functions that were evicted.
+    pub evictions: u64,
+    /// The number of functions that were compiled successfully.
+    pub compilations: u64,
+    /// The number of functions that failed to compile, and are always interpreted since.
+    pub compile_failures: u64,
+    /// The total time spent compiling functions, including the ones that failed to compile.
+    pub compile_time: Duration,
+    /// The number of functions that are waiting to be compiled or being compiled.
+    pub queued_compilations: usize,
+    /// The size of the machine code that has not been freed yet, in bytes, including evicted
+    /// functions that are still in use.
+    pub resident_bytes: usize,
+    /// The number of calls to [`TieredExecutor::warm_up`] that ran a compiled function.
+    pub warm_ups: u64,
+    /// The total time spent in [`TieredExecutor::warm_up`], including prefaulting the code.
+    pub warm_up_time: Duration,
+}
+
+impl TieredMetrics {
+    /// Returns the fraction of invocations that found a compiled function, or `0.0` if there were
+    /// no invocations.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// A compiled function returned by [`TieredExecutor::get_function`].
+///
+/// The machine code of the function is not freed while this, or any of its clones, is alive, even
+/// if the function is evicted or the executor is dropped in the meantime. It can be sent to other
+/// threads to execute the function there, and the executor frees it once it has been dropped
+/// everywhere. Code that is still in use when the executor is dropped is leaked instead.
+#[derive(Clone, Debug)]
+pub struct TieredFunction {
+    f: EvmCompilerFn,
+    code_size: Option<usize>,
+    _guard: Arc<()>,
+}
+
+impl TieredFunction {
+    /// Returns the size of the function's machine code, if reported by the backend.
+    #[inline]
+    pub fn code_size(&self) -> Option<usize> {
+        self.code_size
+    }
+
+    /// Touches every page of the function's machine code, so that the first call does not page
+    /// fault on it, returning `true` if it did.
+    ///
+    /// Does nothing and returns `false` if the size of the code is not known, which is always the
+    /// case with the LLVM backend, see [`code_size`](Self::code_size).
+    ///
+    /// See [`EvmCompilerFn::prefault`].
+    pub fn prefault(&self) -> bool {
+        let Some(code_size) = self.code_size else { return false };
+        // SAFETY: The size is the one reported by the backend, and the code is kept alive.
+        unsafe { self.f.prefault(code_size) };
+        true
+    }
+}
+
+impl Deref for TieredFunction {
+    type Target = EvmCompilerFn;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.f
+    }
+}
+
+/// A tiering controller that decides, per code hash and spec, whether to interpret or to run
+/// compiled code.
+///
+/// Code is interpreted until it has been invoked [`hot_threshold`](TieredConfig::hot_threshold)
+/// times, at which point it is queued for compilation, and the compiled function is used from the
+/// first invocation after it is ready.
+///
+/// Compilation happens in the background, on [`compile_threads`](TieredConfig::compile_threads)
+/// threads that each run a [`TieredWorker`]. Each function is compiled in its own [`EvmCompiler`],
+/// so that it can be freed independently of the others. Finished functions are installed by the
+/// next call to [`get_function`](Self::get_function) or [`wait_idle`](Self::wait_idle), so
+/// invocations never wait for the compiler.
+///
+/// The executor is `Send` and `Sync`, and can be shared between threads, e.g. in an [`Arc`]. The
+/// invocation counts and the cache are kept behind a lock that is never held while compiling or
+/// running code. The [`TieredFunction`]s that it returns can be sent to other threads as well.
+///
+/// Compiled functions are kept in a cache bounded by
+/// [`max_cached_functions`](TieredConfig::max_cached_functions) and
+/// [`max_cached_bytes`](TieredConfig::max_cached_bytes). Evicted code goes back to being
+/// interpreted, and its invocations are counted again from zero, so that it is compiled again if it
+/// is still hot. Evicted functions are only freed once all of the [`TieredFunction`]s returned for
+/// them have been dropped, see [`free_evicted`](TieredExecutor::free_evicted).
+///
+/// # Metrics
+///
+/// Besides [`metrics`](Self::metrics), with the `metrics` feature the executor records the
+/// following with the [`metrics`](https://docs.rs/metrics) crate, on the threads that call it:
+///
+/// - `revm_jit_executions_total{path}`: counter of invocations, with `path` being `compiled` or
+///   `interpreter`;
+/// - `revm_jit_execution_seconds{path}`: histogram of the times reported to
+///   [`record_execution_time`](Self::record_execution_time);
+/// - `revm_jit_compilations_total`: counter of successful compilations;
+/// - `revm_jit_compile_failures_total{reason}`: counter of failed compilations, with `reason` being
+///   `compiler`, `translate`, `jit` or `panic`;
+/// - `revm_jit_compile_seconds`: histogram of compilation times;
+/// - `revm_jit_compile_queue_depth`: gauge of [`TieredMetrics::queued_compilations`];
+/// - `revm_jit_cache_evictions_total`: counter of evicted functions;
+/// - `revm_jit_cache_functions`: gauge of the number of compiled functions;
+/// - `revm_jit_cache_resident_bytes`: gauge of [`TieredMetrics::resident_bytes`].
+///
+/// See [`describe_metrics`](Self::describe_metrics) to register their descriptions.
+///
+/// # Examples
+///
+/// Compiling with LLVM, and calling compiled functions from a revm handler, falling back to the
+/// interpreter:
+///
+/// ```ignore
+/// let tiered = Arc::new(TieredExecutor::new(TieredConfig::default(), |worker| {
+///     with_llvm_context(|cx| {
+///         worker.run(|| {
+///             let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::Aggressive)?;
+///             Ok(EvmCompiler::new(backend))
+///         })
+///     })
+/// }));
+///
+/// handler.execution.execute_frame = Arc::new(move |frame, memory, tables, context| {
+///     let interpreter = frame.interpreter_mut();
+///     let hash = interpreter.contract.hash.unwrap_or_default();
+///     let code = interpreter.contract.bytecode.original_byte_slice();
+///     match unsafe { tiered.get_function(hash, code, spec_id) } {
+///         Some(f) => Ok(unsafe { f.call_with_interpreter_and_memory(interpreter, memory, context) }),
+///         None => prev(frame, memory, tables, context),
+///     }
+/// });
+/// ```
+pub struct TieredExecutor {
+    config: TieredConfig,
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+    on_tier_up: Option<Box<dyn Fn(B256) + Send + Sync>>,
+}
+
+/// The names of the metrics recorded with the `metrics` feature.
+#[cfg(feature = "metrics")]
+mod names {
+    pub(super) const EXECUTIONS: &str = "revm_jit_executions_total";
+    pub(super) const EXECUTION_SECONDS: &str = "revm_jit_execution_seconds";
+    pub(super) const COMPILATIONS: &str = "revm_jit_compilations_total";
+    pub(super) const COMPILE_FAILURES: &str = "revm_jit_compile_failures_total";
+    pub(super) const COMPILE_SECONDS: &str = "revm_jit_compile_seconds";
+    pub(super) const COMPILE_QUEUE_DEPTH: &str = "revm_jit_compile_queue_depth";
+    pub(super) const CACHE_EVICTIONS: &str = "revm_jit_cache_evictions_total";
+    pub(super) const CACHE_FUNCTIONS: &str = "revm_jit_cache_functions";
+    pub(super) const CACHE_RESIDENT_BYTES: &str = "revm_jit_cache_resident_bytes";
+}
+
+/// The state shared between a [`TieredExecutor`] and its workers.
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when a function is queued or is to be freed, and on shutdown.
+    work: Condvar,
+    /// Notified when a compilation finishes.
+    finished: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct State {
+    contracts: FxHashMap<(B256, SpecId), Contract>,
+    /// The compiled contracts, by the time they were last used.
+    lru: BTreeMap<u64, (B256, SpecId)>,
+    cached_bytes: usize,
+    evicted: Vec<Compiled>,
+    metrics: TieredMetrics,
+    clock: u64,
+    /// The functions waiting to be compiled.
+    queue: VecDeque<Job>,
+    /// The compilations that have finished, but have not been installed yet.
+    finished: Vec<Finished>,
+    /// The IDs of the functions to free, by worker.
+    to_free: Vec<Vec<u64>>,
+    next_id: u64,
+    shutdown: bool,
+}
+
+struct Contract {
+    count: u32,
+    window_start: Instant,
+    last_used: u64,
+    tier: Tier,
+}
+
+enum Tier {
+    Interpreted,
+    Queued,
+    Compiled(Compiled),
+    Failed,
+}
+
+struct Compiled {
+    f: TieredFunction,
+    size: usize,
+    /// The worker that owns the compiler of the function.
+    worker: usize,
+    /// The ID of the function in its worker.
+    id: u64,
+}
+
+impl Compiled {
+    /// Returns `true` if no [`TieredFunction`] returned for this function is alive.
+    ///
+    /// New ones are only cloned from this one, under the lock, so this cannot change concurrently.
+    fn is_unused(&self) -> bool {
+        Arc::strong_count(&self.f._guard) == 1
+    }
+}
+
+struct Job {
+    id: u64,
+    key: (B256, SpecId),
+    bytecode: Vec<u8>,
+}
+
+struct Finished {
+    key: (B256, SpecId),
+    compile_time: Duration,
+    /// The compiled function, or the reason why compilation failed.
+    compiled: Result<Compiled, &'static str>,
+}
+
+impl fmt::Debug for TieredExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("TieredExecutor")
+            .field("config", &self.config)
+            .field("n_contracts", &state.contracts.len())
+            .field("n_compiled", &state.lru.len())
+            .field("n_evicted", &state.evicted.len())
+            .field("metrics", &state.metrics)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for TieredExecutor {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        // Drop the references to the functions, so that the workers free the ones that are not in
+        // use anymore, and leak the others.
+        state.contracts.clear();
+        state.lru.clear();
+        state.evicted.clear();
+        state.queue.clear();
+        state.finished.clear();
+        state.shutdown = true;
+        drop(state);
+        self.shared.work.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl TieredExecutor {
+    /// Creates a new tiered executor, and spawns its compilation threads.
+    ///
+    /// `run_worker` is called on each of the threads, and must call [`TieredWorker::run`] with the
+    /// worker that it is given. This allows creating the compilers on the thread that uses them,
+    /// e.g. with a thread-local LLVM context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a thread cannot be spawned.
+    pub fn new(
+        config: TieredConfig,
+        run_worker: impl Fn(TieredWorker) + Send + Sync + 'static,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                contracts: FxHashMap::default(),
+                lru: BTreeMap::new(),
+                cached_bytes: 0,
+                evicted: Vec::new(),
+                metrics: TieredMetrics::default(),
+                clock: 0,
+                queue: VecDeque::new(),
+                finished: Vec::new(),
+                to_free: vec![Vec::new(); config.compile_threads],
+                next_id: 0,
+                shutdown: false,
+            }),
+            work: Condvar::new(),
+            finished: Condvar::new(),
+        });
+        let run_worker = Arc::new(run_worker);
+        let workers = (0..config.compile_threads)
+            .map(|index| {
+                let worker = TieredWorker { shared: shared.clone(), index };
+                let run_worker = run_worker.clone();
+                thread::Builder::new()
+                    .name(format!("revmc-tiered-{index}"))
+                    .spawn(move || run_worker(worker))
+                    .expect("failed to spawn a compilation thread")
+            })
+            .collect();
+        Self { config, shared, workers, on_tier_up: None }
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &TieredConfig {
+        &self.config
+    }
+
+    /// Sets a hook that is called with the code hash whenever compiled code is installed.
+    ///
+    /// The hook is called on the thread that installs the code, without holding any lock.
+    pub fn on_tier_up(&mut self, f: impl Fn(B256) + Send + Sync + 'static) {
+        self.on_tier_up = Some(Box::new(f));
+    }
+
+    /// Returns `true` if the code is currently compiled for the given spec.
+    pub fn is_compiled(&self, code_hash: B256, spec_id: SpecId) -> bool {
+        self.shared
+            .lock()
+            .contracts
+            .get(&(code_hash, spec_id))
+            .is_some_and(|c| matches!(c.tier, Tier::Compiled(_)))
+    }
+
+    /// Returns the number of compiled functions.
+    pub fn compiled_count(&self) -> usize {
+        self.shared.lock().lru.len()
+    }
+
+    /// Returns the number of code hashes whose invocations are counted, including the compiled
+    /// ones.
+    pub fn tracked_count(&self) -> usize {
+        self.shared.lock().contracts.len()
+    }
+
+    /// Returns the cache and compilation metrics.
+    pub fn metrics(&self) -> TieredMetrics {
+        self.shared.lock().metrics
+    }
+
+    /// Registers the descriptions and units of the metrics recorded with the `metrics` feature
+    /// with the installed recorder.
+    ///
+    /// See the [type-level documentation](Self#metrics) for the list of metrics.
+    #[cfg(feature = "metrics")]
+    pub fn describe_metrics() {
+        use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+
+        describe_counter!(names::EXECUTIONS, Unit::Count, "Invocations, by execution path.");
+        describe_histogram!(
+            names::EXECUTION_SECONDS,
+            Unit::Seconds,
+            "Execution times, by execution path."
+        );
+        describe_counter!(names::COMPILATIONS, Unit::Count, "Successful compilations.");
+        describe_counter!(names::COMPILE_FAILURES, Unit::Count, "Failed compilations, by reason.");
+        describe_histogram!(names::COMPILE_SECONDS, Unit::Seconds, "Compilation times.");
+        describe_gauge!(
+            names::COMPILE_QUEUE_DEPTH,
+            Unit::Count,
+            "Functions waiting to be compiled or being compiled."
+        );
+        describe_counter!(names::CACHE_EVICTIONS, Unit::Count, "Evicted compiled functions.");
+        describe_gauge!(names::CACHE_FUNCTIONS, Unit::Count, "Cached compiled functions.");
+        describe_gauge!(
+            names::CACHE_RESIDENT_BYTES,
+            Unit::Bytes,
+            "Size of the compiled code that has not been freed."
+        );
+    }
+
+    /// Records the time it took to execute code, that was either run with a function returned by
+    /// [`get_function`](Self::get_function) if `compiled` is `true`, or interpreted.
+    ///
+    /// The time is added to [`TieredMetrics::compiled_time`] or
+    /// [`TieredMetrics::interpreted_time`].
+    pub fn record_execution_time(&self, compiled: bool, time: Duration) {
+        let mut state = self.shared.lock();
+        if compiled {
+            state.metrics.compiled_time += time;
+        } else {
+            state.metrics.interpreted_time += time;
+        }
+        drop(state);
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(names::EXECUTION_SECONDS, "path" => path(compiled)).record(time);
+    }
+
+    /// Frees the evicted functions that are no longer in use, returning how many were freed.
+    ///
+    /// The functions are freed by the workers that compiled them. This is also done by
+    /// [`get_function`](Self::get_function).
+    pub fn free_evicted(&self) -> usize {
+        let mut state = self.shared.lock();
+        let freed = state.free_evicted();
+        #[cfg(feature = "metrics")]
+        state.record_gauges();
+        drop(state);
+        if freed > 0 {
+            self.shared.work.notify_all();
+        }
+        freed
+    }
+
+    /// Blocks until all of the queued code has been compiled, and installs it.
+    pub fn wait_idle(&self) {
+        let mut state = self.shared.lock();
+        while state.metrics.queued_compilations > 0 && !self.workers.is_empty() {
+            state = self.shared.finished.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        let tier_ups = state.install(&self.config);
+        #[cfg(feature = "metrics")]
+        state.record_gauges();
+        drop(state);
+        self.tier_up(tier_ups);
+    }
+
+    /// Records an invocation of the given code, and returns the compiled function to run it with.
+    ///
+    /// Returns `None` if the code should be interpreted, including while it is being compiled.
+    /// Code that fails to compile is always interpreted, until its count is forgotten (see
+    /// [`max_tracked_contracts`](TieredConfig::max_tracked_contracts)). Failures are logged, and
+    /// counted in [`TieredMetrics::compile_failures`].
+    ///
+    /// # Safety
+    ///
+    /// The returned function keeps its machine code alive, but copies of the [`EvmCompilerFn`]
+    /// that it dereferences to must not be called after it and all of its clones are dropped.
+    pub unsafe fn get_function(
+        &self,
+        code_hash: B256,
+        bytecode: &[u8],
+        spec_id: SpecId,
+    ) -> Option<TieredFunction> {
+        let mut state = self.shared.lock();
+        let tier_ups = state.install(&self.config);
+        if state.free_evicted() > 0 {
+            self.shared.work.notify_all();
+        }
+        let f = self.invoke(&mut state, code_hash, bytecode, spec_id);
+        #[cfg(feature = "metrics")]
+        state.record_gauges();
+        drop(state);
+        self.tier_up(tier_ups);
+        #[cfg(feature = "metrics")]
+        metrics::counter!(names::EXECUTIONS, "path" => path(f.is_some())).increment(1);
+        f
+    }
+
+    fn invoke(
+        &self,
+        state: &mut State,
+        code_hash: B256,
+        bytecode: &[u8],
+        spec_id: SpecId,
+    ) -> Option<TieredFunction> {
+        state.clock += 1;
+        let now = Instant::now();
+        let key = (code_hash, spec_id);
+        if state.contracts.len() >= self.config.max_tracked_contracts
+            && !state.contracts.contains_key(&key)
+        {
+            state.contracts.retain(|_, c| matches!(c.tier, Tier::Queued | Tier::Compiled(_)));
+        }
+        let contract = state.contracts.entry(key).or_insert_with(|| Contract {
+            count: 0,
+            window_start: now,
+            last_used: 0,
+            tier: Tier::Interpreted,
+        });
+        let last_used = std::mem::replace(&mut contract.last_used, state.clock);
+        if let Tier::Compiled(compiled) = &contract.tier {
+            let f = compiled.f.clone();
+            state.lru.remove(&last_used);
+            state.lru.insert(state.clock, key);
+            state.metrics.hits += 1;
+            return Some(f);
+        }
+        state.metrics.misses += 1;
+        if let Tier::Queued | Tier::Failed = contract.tier {
+            return None;
+        }
+
+        if now.duration_since(contract.window_start) > self.config.hot_window {
+            contract.count = 0;
+            contract.window_start = now;
+        }
+        contract.count = contract.count.saturating_add(1);
+        let is_hot = contract.count >= self.config.hot_threshold;
+        let compile_spec =
+            self.config.compile_specs.is_empty() || self.config.compile_specs.contains(&spec_id);
+        if !is_hot
+            || !compile_spec
+            || self.config.max_cached_functions == 0
+            || self.workers.is_empty()
+            || state.queue.len() >= self.config.max_queued_compilations
+        {
+            return None;
+        }
+
+        contract.tier = Tier::Queued;
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queue.push_back(Job { id, key, bytecode: bytecode.to_vec() });
+        state.metrics.queued_compilations += 1;
+        self.shared.work.notify_all();
+        None
+    }
+
+    fn tier_up(&self, code_hashes: Vec<B256>) {
+        if let Some(on_tier_up) = &self.on_tier_up {
+            code_hashes.into_iter().for_each(on_tier_up);
+        }
+    }
+
+    /// Prepares the compiled function of the given code for latency-critical execution, returning
+    /// the time it took, or `None` if the code is not compiled for the given spec.
+    ///
+    /// The machine code is [prefaulted](TieredFunction::prefault) if its size is known, and the
+    /// function is then run
+    /// once on `contract` against a clone of `host`, to warm up the caches and the branch
+    /// predictors. The result of the run is discarded, and calls and creates are not executed.
+    ///
+    /// This does not count as an invocation of the code. The time is also added to
+    /// [`TieredMetrics::warm_up_time`].
+    ///
+    /// # Safety
+    ///
+    /// `contract` must contain the bytecode that the function was compiled from.
+    pub unsafe fn warm_up<H: HostExt + Clone>(
+        &self,
+        code_hash: B256,
+        spec_id: SpecId,
+        contract: revm_interpreter::Contract,
+        host: &H,
+    ) -> Option<Duration> {
+        let f = match self.shared.lock().contracts.get(&(code_hash, spec_id)) {
+            Some(Contract { tier: Tier::Compiled(compiled), .. }) => compiled.f.clone(),
+            _ => return None,
+        };
+        let start = Instant::now();
+        f.prefault();
+        let mut host = host.clone();
+        let gas_limit = host.env().tx.gas_limit;
+        let mut interpreter = Interpreter::new(contract, gas_limit, false);
+        interpreter.shared_memory = SharedMemory::new();
+        let _ = unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+        let elapsed = start.elapsed();
+        let mut state = self.shared.lock();
+        state.metrics.warm_ups += 1;
+        state.metrics.warm_up_time += elapsed;
+        Some(elapsed)
+    }
+}
+
+impl State {
+    /// Installs the finished compilations, returning the code hashes of the installed functions.
+    fn install(&mut self, config: &TieredConfig) -> Vec<B256> {
+        let mut tier_ups = Vec::new();
+        for Finished { key, compile_time, compiled } in std::mem::take(&mut self.finished) {
+            self.metrics.compile_time += compile_time;
+            #[cfg(feature = "metrics")]
+            metrics::histogram!(names::COMPILE_SECONDS).record(compile_time);
+            let contract = self.contracts.get_mut(&key).unwrap();
+            let compiled = match compiled {
+                Ok(compiled) => compiled,
+                Err(reason) => {
+                    self.metrics.compile_failures += 1;
+                    #[cfg(feature = "metrics")]
+                    metrics::counter!(names::COMPILE_FAILURES, "reason" => reason).increment(1);
+                    #[cfg(not(feature = "metrics"))]
+                    let _ = reason;
+                    contract.tier = Tier::Failed;
+                    continue;
+                }
+            };
+            self.metrics.compilations += 1;
+            #[cfg(feature = "metrics")]
+            metrics::counter!(names::COMPILATIONS).increment(1);
+            self.metrics.resident_bytes += compiled.size;
+            if compiled.size > config.max_cached_bytes {
+                debug!(code_hash = %key.0, spec_id = ?key.1, size = compiled.size, "compiled code exceeds the cache size");
+                contract.tier = Tier::Failed;
+                self.evicted.push(compiled);
+                continue;
+            }
+
+            while self.lru.len() >= config.max_cached_functions
+                || self.cached_bytes + compiled.size > config.max_cached_bytes
+            {
+                self.evict_lru();
+            }
+            self.clock += 1;
+            self.lru.insert(self.clock, key);
+            self.cached_bytes += compiled.size;
+            let contract = self.contracts.get_mut(&key).unwrap();
+            contract.last_used = self.clock;
+            contract.tier = Tier::Compiled(compiled);
+            tier_ups.push(key.0);
+        }
+        self.free_evicted();
+        tier_ups
+    }
+
+    /// Hands the evicted functions that are no longer in use to their workers to be freed,
+    /// returning how many there were.
+    fn free_evicted(&mut self) -> usize {
+        let mut freed = 0;
+        let mut i = 0;
+        while i < self.evicted.len() {
+            if !self.evicted[i].is_unused() {
+                i += 1;
+                continue;
+            }
+            let compiled = self.evicted.swap_remove(i);
+            self.metrics.resident_bytes -= compiled.size;
+            self.to_free[compiled.worker].push(compiled.id);
+            freed += 1;
+        }
+        freed
+    }
+
+    /// Evicts the least recently used compiled function, which is freed once it is no longer in
+    /// use.
+    fn evict_lru(&mut self) {
+        let Some((_, key)) = self.lru.pop_first() else { return };
+        let contract = self.contracts.get_mut(&key).unwrap();
+        let Tier::Compiled(compiled) = std::mem::replace(&mut contract.tier, Tier::Interpreted)
+        else {
+            unreachable!()
+        };
+        contract.count = 0;
+        contract.window_start = Instant::now();
+        self.cached_bytes -= compiled.size;
+        self.metrics.evictions += 1;
+        #[cfg(feature = "metrics")]
+        metrics::counter!(names::CACHE_EVICTIONS).increment(1);
+        self.evicted.push(compiled);
+    }
+
+    /// Records the current size of the cache and of the queue.
+    #[cfg(feature = "metrics")]
+    fn record_gauges(&self) {
+        metrics::gauge!(names::COMPILE_QUEUE_DEPTH).set(self.metrics.queued_compilations as f64);
+        metrics::gauge!(names::CACHE_FUNCTIONS).set(self.lru.len() as f64);
+        metrics::gauge!(names::CACHE_RESIDENT_BYTES).set(self.metrics.resident_bytes as f64);
+    }
+}
+
+/// Returns the `path` label of an execution.
+#[cfg(feature = "metrics")]
+fn path(compiled: bool) -> &'static str {
+    if compiled {
+        "compiled"
+    } else {
+        "interpreter"
+    }
+}
+
+/// A compilation thread of a [`TieredExecutor`], passed to the closure given to
+/// [`TieredExecutor::new`].
+pub struct TieredWorker {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl fmt::Debug for TieredWorker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TieredWorker").field("index", &self.index).finish_non_exhaustive()
+    }
+}
+
+/// A function compiled by a [`TieredWorker`], together with the compiler that owns its code.
+struct Owned<B: Backend> {
+    compiler: Box<EvmCompiler<B>>,
+    id: B::FuncId,
+    guard: Weak<()>,
+}
+
+impl<B: Backend> Owned<B> {
+    fn free(mut self) {
+        // SAFETY: No `TieredFunction` referencing the function is alive.
+        if let Err(err) = unsafe { self.compiler.free_function(self.id) } {
+            warn!(%err, "failed to free a compiled function");
+        }
+    }
+}
+
+impl TieredWorker {
+    /// Compiles the code queued by the executor until the executor is dropped, with a new
+    /// compiler from `new_compiler` for each function.
+    ///
+    /// Functions are freed on this thread once they are evicted and no longer in use. Functions
+    /// that are still in use when the executor is dropped are leaked, together with their
+    /// compilers.
+    pub fn run<B: Backend>(self, mut new_compiler: impl FnMut() -> Result<EvmCompiler<B>>) {
+        let mut owned = FxHashMap::<u64, Owned<B>>::default();
+        let mut state = self.shared.lock();
+        loop {
+            let to_free = std::mem::take(&mut state.to_free[self.index]);
+            if !to_free.is_empty() {
+                drop(state);
+                for id in to_free {
+                    owned.remove(&id).unwrap().free();
+                }
+            } else if let Some(job) = state.queue.pop_front() {
+                drop(state);
+                let start = Instant::now();
+                let compiled = match self.compile(&job, &mut new_compiler) {
+                    Ok((compiled, owned_fn)) => {
+                        owned.insert(job.id, owned_fn);
+                        Ok(compiled)
+                    }
+                    Err((reason, err)) => {
+                        let (code_hash, spec_id) = job.key;
+                        warn!(%code_hash, ?spec_id, reason, %err, "compilation failed, falling back to the interpreter");
+                        Err(reason)
+                    }
+                };
+                let finished = Finished { key: job.key, compile_time: start.elapsed(), compiled };
+                let mut state = self.shared.lock();
+                state.metrics.queued_compilations -= 1;
+                if !state.shutdown {
+                    state.finished.push(finished);
+                }
+                drop(state);
+                self.shared.finished.notify_all();
+            } else if state.shutdown {
+                break;
+            } else {
+                state = self.shared.work.wait(state).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+            state = self.shared.lock();
+        }
+        drop(state);
+
+        for (_, owned) in owned {
+            if owned.guard.strong_count() > 0 {
+                std::mem::forget(owned.compiler);
+            } else {
+                owned.free();
+            }
+        }
+    }
+
+    /// Compiles the job's code, returning the reason and the error on failure.
+    fn compile<B: Backend>(
+        &self,
+        job: &Job,
+        new_compiler: &mut impl FnMut() -> Result<EvmCompiler<B>>,
+    ) -> Result<(Compiled, Owned<B>), (&'static str, eyre::Report)> {
+        let (code_hash, spec_id) = job.key;
+        catch_unwind(AssertUnwindSafe(|| {
+            let mut compiler = new_compiler().map_err(|e| ("compiler", e))?;
+            let name = format!("tiered_{code_hash}");
+            let id = compiler
+                .translate(&name, &job.bytecode, spec_id)
+                .map_err(|e| ("translate", e.into()))?;
+            let f = unsafe { compiler.jit_function(id) }.map_err(|e| ("jit", e.into()))?;
+            let stats = compiler.stats(id).unwrap_or_default();
+            let size = stats.machine_code_bytes.unwrap_or(stats.code_bytes);
+            let f = TieredFunction { f, code_size: stats.machine_code_bytes, _guard: Arc::new(()) };
+            let guard = Arc::downgrade(&f._guard);
+            let compiled = Compiled { f, size, worker: self.index, id: job.id };
+            Ok((compiled, Owned { compiler: Box::new(compiler), id, guard }))
+        }))
+        .unwrap_or_else(|_| Err(("panic", eyre::eyre!("the compiler panicked"))))
+    }
+}
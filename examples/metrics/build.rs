@@ -0,0 +1,4 @@
+fn main() {
+    // Emit the configuration to run compiled bytecodes.
+    revmc_build::emit();
+}
@@ -0,0 +1,62 @@
+//! Exports the metrics of a tiered executor in the Prometheus text format.
+//!
+//! Contracts are run through a [`TieredExecutor`], which interprets them until they are hot, and
+//! the metrics that it recorded are then printed. A server would serve them instead, e.g. with the
+//! HTTP listener of `metrics-exporter-prometheus`.
+
+use metrics_exporter_prometheus::PrometheusBuilder;
+use revmc::{
+    interpreter::{
+        analysis::to_analysed,
+        opcode::{self as op, make_instruction_table},
+        Contract, DummyHost, Interpreter, SharedMemory,
+    },
+    llvm::with_llvm_context,
+    primitives::{keccak256, Bytecode, CancunSpec, SpecId},
+    EvmCompiler, EvmLlvmBackend, OptimizationLevel, TieredConfig, TieredExecutor,
+};
+use std::time::Instant;
+
+fn main() -> eyre::Result<()> {
+    let prometheus = PrometheusBuilder::new().install_recorder()?;
+    TieredExecutor::describe_metrics();
+
+    let config = TieredConfig { hot_threshold: 10, ..Default::default() };
+    let executor = TieredExecutor::new(config, |worker| {
+        with_llvm_context(|cx| {
+            worker.run(|| {
+                let backend = EvmLlvmBackend::new(cx, false, OptimizationLevel::Aggressive)?;
+                Ok(EvmCompiler::new(backend))
+            })
+        })
+    });
+    let table = make_instruction_table::<DummyHost, CancunSpec>();
+
+    // Contract `i` is called `5 * i` times, so only some of them become hot.
+    for i in 1..=4u8 {
+        let code = [op::PUSH1, i, op::PUSH0, op::MSTORE, op::STOP];
+        let hash = keccak256(code);
+        let contract = Contract {
+            bytecode: to_analysed(Bytecode::new_raw(code.to_vec().into())),
+            hash: Some(hash),
+            ..Default::default()
+        };
+        for _ in 0..5 * i {
+            let mut interpreter = Interpreter::new(contract.clone(), 1_000_000, false);
+            let mut host = DummyHost::default();
+            let start = Instant::now();
+            let f = unsafe { executor.get_function(hash, &code, SpecId::CANCUN) };
+            if let Some(f) = &f {
+                unsafe { f.call_with_interpreter(&mut interpreter, &mut host) };
+            } else {
+                interpreter.run(SharedMemory::new(), &table, &mut host);
+            }
+            executor.record_execution_time(f.is_some(), start.elapsed());
+        }
+        // Install the functions that were compiled in the meantime.
+        executor.wait_idle();
+    }
+
+    print!("{}", prometheus.render());
+    Ok(())
+}
@@ -14,7 +14,7 @@ use revm::{
     primitives::{hex, B256},
     Database,
 };
-use revmc_context::EvmCompilerFn;
+use revmc_context::{EvmCompilerFn, EvmExecute};
 
 include!("./common.rs");
 
@@ -32,17 +32,19 @@ pub fn build_evm<'a, DB: Database + 'static>(db: DB) -> revm::Evm<'a, ExternalCo
         .build()
 }
 
-pub struct ExternalContext;
+pub struct ExternalContext {
+    fibonacci: Arc<dyn EvmExecute + Send + Sync>,
+}
 
 impl ExternalContext {
     fn new() -> Self {
-        Self
+        Self { fibonacci: Arc::new(EvmCompilerFn::new(fibonacci)) }
     }
 
-    fn get_function(&self, bytecode_hash: B256) -> Option<EvmCompilerFn> {
+    fn get_function(&self, bytecode_hash: B256) -> Option<Arc<dyn EvmExecute + Send + Sync>> {
         // Can use any mapping between bytecode hash and function.
         if bytecode_hash == FIBONACCI_HASH {
-            return Some(EvmCompilerFn::new(fibonacci));
+            return Some(self.fibonacci.clone());
         }
 
         None
@@ -56,7 +58,7 @@ fn register_handler<DB: Database + 'static>(handler: &mut EvmHandler<'_, Externa
         let interpreter = frame.interpreter_mut();
         let bytecode_hash = interpreter.contract.hash.unwrap_or_default();
         if let Some(f) = context.external.get_function(bytecode_hash) {
-            Ok(unsafe { f.call_with_interpreter_and_memory(interpreter, memory, context) })
+            Ok(unsafe { f.execute_with_memory(interpreter, memory, context) })
         } else {
             prev(frame, memory, tables, context)
         }
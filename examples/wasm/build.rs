@@ -0,0 +1,24 @@
+use revmc::{
+    primitives::{hex, SpecId},
+    EvmCompiler, EvmLlvmBackend, OptimizationLevel, Result,
+};
+use std::path::PathBuf;
+
+include!("./src/common.rs");
+
+fn main() -> Result<()> {
+    // Compile the bytecodes into a WebAssembly module.
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    let context = revmc::llvm::inkwell::context::Context::create();
+    let mut backend = EvmLlvmBackend::new(&context, true, OptimizationLevel::Aggressive)?;
+    backend.set_target("wasm32-unknown-unknown", None, None)?;
+    let mut compiler = EvmCompiler::new(backend);
+    // The input of `fibonacci` is passed on the stack, and the result is read from it.
+    compiler.inspect_stack_length(true);
+    compiler.translate("fibonacci", FIBONACCI_CODE, SpecId::CANCUN)?;
+    compiler.translate("counter", COUNTER_CODE, SpecId::CANCUN)?;
+    let module = compiler.emit_wasm()?;
+    std::fs::write(out_dir.join("module.wasm"), module)?;
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+/// Computes the Fibonacci number of the input on the stack, leaving the result as the only element
+/// on the stack.
+///
+/// Apparently the code does `fibonacci(input + 1)`.
+#[allow(dead_code)]
+const FIBONACCI_CODE: &[u8] =
+    &hex!("5b5b5b600060015b8215601c578181019150909160019003916007565b91505000");
+
+/// Increments the counter in storage slot 0, and returns its new value.
+///
+/// `PUSH0 SLOAD PUSH1 1 ADD DUP1 PUSH0 SSTORE PUSH0 MSTORE PUSH1 32 PUSH0 RETURN`
+#[allow(dead_code)]
+const COUNTER_CODE: &[u8] = &hex!("5f54600101805f555f5260205ff3");
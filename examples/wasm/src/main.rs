@@ -0,0 +1,371 @@
+//! Runs bytecode compiled to WebAssembly with `wasmtime`.
+//!
+//! The build script compiles the bytecode for `wasm32`, so the arguments are laid out in the linear
+//! memory of the module with the fixed layouts of [`revmc_context::wasm32`]. The builtins that the
+//! bytecode calls are imported from the `env` module, and are implemented here over an in-memory
+//! storage. Any other builtin traps.
+
+use revm_interpreter::{gas, InstructionResult, SStoreResult};
+use revm_primitives::{hex, SpecId, U256};
+use revmc_context::{wasm32, EvmStack};
+use std::{
+    collections::{HashMap, HashSet},
+    mem::{align_of, offset_of, size_of},
+};
+use wasmtime::{Caller, Engine, Error, Extern, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+include!("./common.rs");
+
+const MODULE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/module.wasm"));
+
+/// Capacity of the EVM memory. Expanding the memory past it fails.
+const MEMORY_CAPACITY: u32 = 64 * 1024;
+
+/// A compiled function, with the arguments of `RawEvmCompilerFn` as linear memory addresses.
+type EvmFn = TypedFunc<(u32, u32, u32, u32, u32, u32), u32>;
+
+/// The state of the host.
+#[derive(Default)]
+struct Host {
+    /// The storage of the contract.
+    storage: HashMap<U256, U256>,
+    /// The storage at the start of the current call.
+    original: HashMap<U256, U256>,
+    /// The storage slots accessed in the current call.
+    warm: HashSet<U256>,
+    /// The output of the last call.
+    output: Vec<u8>,
+    /// The arguments in linear memory.
+    args: Args,
+}
+
+/// The addresses of the arguments, and of the EVM memory they point to, in linear memory.
+#[derive(Clone, Copy, Debug, Default)]
+struct Args {
+    gas: u32,
+    stack: u32,
+    stack_len: u32,
+    env: u32,
+    contract: u32,
+    ecx: u32,
+    memory: u32,
+    buffer: u32,
+}
+
+fn main() -> wasmtime::Result<()> {
+    let input = std::env::args().nth(1).map(|s| s.parse()).transpose()?.unwrap_or(100u16);
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, MODULE)?;
+    let mut store = Store::new(&engine, Host::default());
+    let mut linker = Linker::new(&engine);
+    define_builtins(&mut linker)?;
+    linker.define_unknown_imports_as_traps(&module)?;
+    let instance = linker.instantiate(&mut store, &module)?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| Error::msg("missing `memory` export"))?;
+    store.data_mut().args = alloc_args(&mut store, &instance, memory)?;
+
+    let f = instance.get_typed_func(&mut store, "fibonacci")?;
+    let stack = call(&mut store, memory, &f, &[U256::from(input)], InstructionResult::Stop)?;
+    println!("fib({}) = {}", input + 1, stack[0]);
+    assert_eq!(stack, [fibonacci(input + 1)]);
+
+    let f = instance.get_typed_func(&mut store, "counter")?;
+    for expected in 1..=3u64 {
+        call(&mut store, memory, &f, &[], InstructionResult::Return)?;
+        let output = U256::from_be_slice(&store.data().output);
+        println!("counter = {output}");
+        assert_eq!(output, U256::from(expected));
+        assert_eq!(store.data().storage[&U256::ZERO], output);
+    }
+    Ok(())
+}
+
+/// Lays out the arguments in linear memory after the heap base.
+fn alloc_args(
+    store: &mut Store<Host>,
+    instance: &Instance,
+    memory: Memory,
+) -> wasmtime::Result<Args> {
+    let heap_base = instance
+        .get_global(&mut *store, "__heap_base")
+        .and_then(|global| global.get(&mut *store).i32())
+        .ok_or_else(|| Error::msg("missing `__heap_base` export"))? as u32;
+
+    let mut end = heap_base;
+    let mut alloc = |size: usize, align: usize| {
+        let offset = end.next_multiple_of(align as u32);
+        end = offset + size as u32;
+        offset
+    };
+    let args = Args {
+        gas: alloc(size_of::<wasm32::Gas>(), align_of::<wasm32::Gas>()),
+        stack: alloc(size_of::<EvmStack>(), align_of::<EvmStack>()),
+        stack_len: alloc(size_of::<u32>(), align_of::<u32>()),
+        env: alloc(size_of::<wasm32::Env>(), align_of::<wasm32::Env>()),
+        contract: alloc(size_of::<wasm32::ContractView>(), align_of::<wasm32::ContractView>()),
+        ecx: alloc(size_of::<wasm32::EvmContext>(), align_of::<wasm32::EvmContext>()),
+        memory: alloc(size_of::<wasm32::SharedMemory>(), align_of::<wasm32::SharedMemory>()),
+        buffer: alloc(MEMORY_CAPACITY as usize, 32),
+    };
+    let page_size = memory.page_size(&*store);
+    if let Some(additional) = u64::from(end).div_ceil(page_size).checked_sub(memory.size(&*store)) {
+        memory.grow(&mut *store, additional)?;
+    }
+
+    // The environment and the contract are constant.
+    let env = wasm32::Env { cfg: wasm32::CfgEnv { chain_id: 1 }, ..Default::default() };
+    memory.write(&mut *store, args.env as usize, as_bytes(&env))?;
+    let contract = wasm32::ContractView::default();
+    memory.write(&mut *store, args.contract as usize, as_bytes(&contract))?;
+    Ok(args)
+}
+
+/// Calls `f` with `input` on the stack, checks that it returns `expected`, and returns the stack.
+fn call(
+    store: &mut Store<Host>,
+    memory: Memory,
+    f: &EvmFn,
+    input: &[U256],
+    expected: InstructionResult,
+) -> wasmtime::Result<Vec<U256>> {
+    let host = store.data_mut();
+    host.original = host.storage.clone();
+    host.warm.clear();
+    host.output.clear();
+    let args = host.args;
+
+    // Reset the gas, the stack, the EVM memory and the context.
+    let gas = wasm32::Gas { limit: 100_000, remaining: 100_000, refunded: 0 };
+    memory.write(&mut *store, args.gas as usize, as_bytes(&gas))?;
+    for (i, word) in input.iter().enumerate() {
+        memory.write(&mut *store, args.stack as usize + i * 32, &word.to_le_bytes::<32>())?;
+    }
+    memory.write(&mut *store, args.stack_len as usize, &(input.len() as u32).to_le_bytes())?;
+    let buffer = wasm32::Vec { cap: MEMORY_CAPACITY, ptr: args.buffer, len: 0 };
+    let shared_memory = wasm32::SharedMemory { buffer, last_checkpoint: 0 };
+    memory.write(&mut *store, args.memory as usize, as_bytes(&shared_memory))?;
+    let ecx = wasm32::EvmContext { memory: args.memory, ..Default::default() };
+    memory.write(&mut *store, args.ecx as usize, as_bytes(&ecx))?;
+
+    let result = f.call(
+        &mut *store,
+        (args.gas, args.stack, args.stack_len, args.env, args.contract, args.ecx),
+    )?;
+    if result != expected as u32 {
+        return Err(Error::msg(format!("expected {expected:?}, got {result:#x}")));
+    }
+
+    let mem = memory.data(&*store);
+    let len = read_u32(mem, args.stack_len)?;
+    (0..len).map(|i| read_word(mem, args.stack + i * 32)).collect()
+}
+
+/// Defines the builtins that the bytecodes call in the `env` module.
+fn define_builtins(linker: &mut Linker<Host>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        "env",
+        "__revmc_builtin_sload",
+        |mut caller: Caller<'_, Host>, _ecx: u32, sp: u32, spec_id: u32| {
+            let (mem, host) = data(&mut caller)?;
+            let key = read_word(mem, sp)?;
+            let is_cold = host.warm.insert(key);
+            if !charge(mem, host.args.gas, gas::sload_cost(spec(spec_id)?, is_cold))? {
+                return Ok(InstructionResult::OutOfGas as u32);
+            }
+            let value = host.storage.get(&key).copied().unwrap_or_default();
+            write_word(mem, sp, value)?;
+            Ok(InstructionResult::Continue as u32)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "__revmc_builtin_sstore",
+        |mut caller: Caller<'_, Host>, _ecx: u32, sp: u32, spec_id: u32| {
+            let (mem, host) = data(&mut caller)?;
+            let spec_id = spec(spec_id)?;
+            // The key is on top of the stack, above the value.
+            let new_value = read_word(mem, sp)?;
+            let key = read_word(mem, sp + 32)?;
+            let vals = SStoreResult {
+                original_value: host.original.get(&key).copied().unwrap_or_default(),
+                present_value: host.storage.get(&key).copied().unwrap_or_default(),
+                new_value,
+            };
+            let is_cold = host.warm.insert(key);
+            let remaining =
+                read_u64(mem, host.args.gas + offset_of!(wasm32::Gas, remaining) as u32)?;
+            let Some(cost) = gas::sstore_cost(spec_id, &vals, remaining, is_cold) else {
+                return Ok(InstructionResult::OutOfGas as u32);
+            };
+            if !charge(mem, host.args.gas, cost)? {
+                return Ok(InstructionResult::OutOfGas as u32);
+            }
+            let refunded = host.args.gas + offset_of!(wasm32::Gas, refunded) as u32;
+            let refund = read_u64(mem, refunded)? as i64 + gas::sstore_refund(spec_id, &vals);
+            slice(mem, refunded, 8)?.copy_from_slice(&refund.to_le_bytes());
+            host.storage.insert(key, new_value);
+            Ok(InstructionResult::Continue as u32)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "__revmc_builtin_resize_memory",
+        |mut caller: Caller<'_, Host>, ecx: u32, new_size: u32| {
+            let (mem, host) = data(&mut caller)?;
+            Ok(resize_memory(mem, host.args.gas, ecx, new_size)? as u32)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "__revmc_builtin_msize",
+        |mut caller: Caller<'_, Host>, ecx: u32| {
+            let (mem, _) = data(&mut caller)?;
+            let (_, len) = memory_len(mem, ecx)?;
+            Ok(len)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "__revmc_builtin_do_return",
+        |mut caller: Caller<'_, Host>, ecx: u32, sp: u32, _result: u32| {
+            let (mem, host) = data(&mut caller)?;
+            // The offset is on top of the stack, above the length.
+            let (len, offset) = (read_word(mem, sp)?, read_word(mem, sp + 32)?);
+            host.output.clear();
+            if len.is_zero() {
+                return Ok(InstructionResult::Continue as u32);
+            }
+            let (Ok(offset), Ok(len)) = (u32::try_from(offset), u32::try_from(len)) else {
+                return Ok(InstructionResult::InvalidOperandOOG as u32);
+            };
+            let Some(end) = offset.checked_add(len) else {
+                return Ok(InstructionResult::InvalidOperandOOG as u32);
+            };
+            let result = resize_memory(mem, host.args.gas, ecx, end)?;
+            if result != InstructionResult::Continue {
+                return Ok(result as u32);
+            }
+            let (buffer, _) = memory_len(mem, ecx)?;
+            host.output.extend_from_slice(slice(mem, buffer + offset, len as usize)?);
+            Ok(InstructionResult::Continue as u32)
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Expands the EVM memory of `ecx` to hold at least `new_size` bytes, charging for it.
+fn resize_memory(
+    mem: &mut [u8],
+    gas: u32,
+    ecx: u32,
+    new_size: u32,
+) -> wasmtime::Result<InstructionResult> {
+    let (buffer, len) = memory_len(mem, ecx)?;
+    if new_size <= len {
+        return Ok(InstructionResult::Continue);
+    }
+    let new_len = new_size.next_multiple_of(32);
+    if new_len > MEMORY_CAPACITY {
+        return Ok(InstructionResult::MemoryLimitOOG);
+    }
+    let cost = gas::memory_gas_for_len(new_len as usize) - gas::memory_gas_for_len(len as usize);
+    if !charge(mem, gas, cost)? {
+        return Ok(InstructionResult::MemoryOOG);
+    }
+    slice(mem, buffer + len, (new_len - len) as usize)?.fill(0);
+    let shared_memory = read_u32(mem, ecx + offset_of!(wasm32::EvmContext, memory) as u32)?;
+    let len_addr = shared_memory
+        + (offset_of!(wasm32::SharedMemory, buffer) + offset_of!(wasm32::Vec, len)) as u32;
+    slice(mem, len_addr, 4)?.copy_from_slice(&new_len.to_le_bytes());
+    Ok(InstructionResult::Continue)
+}
+
+/// Returns the address and length of the EVM memory of `ecx`.
+///
+/// This host never creates nested calls, so the last checkpoint is always 0.
+fn memory_len(mem: &[u8], ecx: u32) -> wasmtime::Result<(u32, u32)> {
+    let shared_memory = read_u32(mem, ecx + offset_of!(wasm32::EvmContext, memory) as u32)?;
+    let buffer = shared_memory + offset_of!(wasm32::SharedMemory, buffer) as u32;
+    let ptr = read_u32(mem, buffer + offset_of!(wasm32::Vec, ptr) as u32)?;
+    let len = read_u32(mem, buffer + offset_of!(wasm32::Vec, len) as u32)?;
+    Ok((ptr, len))
+}
+
+/// Subtracts `cost` from the remaining gas, returning `false` if there is not enough.
+fn charge(mem: &mut [u8], gas: u32, cost: u64) -> wasmtime::Result<bool> {
+    let remaining = gas + offset_of!(wasm32::Gas, remaining) as u32;
+    let Some(new) = read_u64(mem, remaining)?.checked_sub(cost) else {
+        return Ok(false);
+    };
+    slice(mem, remaining, 8)?.copy_from_slice(&new.to_le_bytes());
+    Ok(true)
+}
+
+/// Returns the linear memory and the host state of the caller.
+fn data<'a>(caller: &'a mut Caller<'_, Host>) -> wasmtime::Result<(&'a mut [u8], &'a mut Host)> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Error::msg("missing `memory` export"))?;
+    Ok(memory.data_and_store_mut(caller))
+}
+
+fn spec(spec_id: u32) -> wasmtime::Result<SpecId> {
+    u8::try_from(spec_id)
+        .ok()
+        .and_then(SpecId::try_from_u8)
+        .ok_or_else(|| Error::msg(format!("invalid spec ID: {spec_id}")))
+}
+
+fn slice(mem: &mut [u8], addr: u32, len: usize) -> wasmtime::Result<&mut [u8]> {
+    mem.get_mut(addr as usize..)
+        .and_then(|mem| mem.get_mut(..len))
+        .ok_or_else(|| Error::msg(format!("out of bounds access at {addr:#x}")))
+}
+
+fn read<const N: usize>(mem: &[u8], addr: u32) -> wasmtime::Result<[u8; N]> {
+    mem.get(addr as usize..)
+        .and_then(|mem| mem.get(..N))
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or_else(|| Error::msg(format!("out of bounds access at {addr:#x}")))
+}
+
+fn read_u32(mem: &[u8], addr: u32) -> wasmtime::Result<u32> {
+    read(mem, addr).map(u32::from_le_bytes)
+}
+
+fn read_u64(mem: &[u8], addr: u32) -> wasmtime::Result<u64> {
+    read(mem, addr).map(u64::from_le_bytes)
+}
+
+/// Reads a stack word, which is a little-endian 256-bit integer.
+fn read_word(mem: &[u8], addr: u32) -> wasmtime::Result<U256> {
+    read(mem, addr).map(U256::from_le_bytes::<32>)
+}
+
+fn write_word(mem: &mut [u8], addr: u32, value: U256) -> wasmtime::Result<()> {
+    slice(mem, addr, 32)?.copy_from_slice(&value.to_le_bytes::<32>());
+    Ok(())
+}
+
+/// Returns the bytes of a `wasm32` layout. Like linear memory, the host is little-endian.
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    const { assert!(cfg!(target_endian = "little"), "the host must be little-endian") };
+    unsafe { std::slice::from_raw_parts((value as *const T).cast(), size_of::<T>()) }
+}
+
+fn fibonacci(n: u16) -> U256 {
+    let (mut a, mut b) = (U256::ZERO, U256::from(1));
+    for _ in 0..n {
+        (a, b) = (b, b.wrapping_add(a));
+    }
+    a
+}